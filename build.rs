@@ -0,0 +1,14 @@
+fn main() {
+    // The `wavpack` feature links the system libwavpack; without it nothing is probed
+    if std::env::var_os("CARGO_FEATURE_WAVPACK").is_none() {
+        return;
+    }
+    if let Err(e) = pkg_config::Config::new().atleast_version("5.0").probe("wavpack") {
+        panic!(
+            "The `wavpack` feature needs libwavpack 5.0 or later, but pkg-config could not find it.\n\
+             Install libwavpack-dev (Debian/Ubuntu), wavpack-devel (Fedora) or wavpack (Homebrew),\n\
+             or build without `--features wavpack`.\n\n{}",
+            e
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use crate::models::{PlaybackState, PlayerStatus, TrackInfo};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Position changes smaller than this are not pushed while playing
+const POSITION_STEP: Duration = Duration::from_secs(1);
+
+/// Change pushed to WebSocket clients, tagged by `type`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum PlayerEvent {
+    TrackChanged { track: Option<Box<TrackInfo>> },
+    StateChanged { state: PlaybackState },
+    VolumeChanged { volume: f32 },
+    PositionUpdate { position: Duration },
+}
+
+/// Turns successive status snapshots into the events between them
+pub struct StatusDiff {
+    last: PlayerStatus,
+}
+
+impl StatusDiff {
+    /// Start from the snapshot the client was sent on connection
+    pub fn new(initial: PlayerStatus) -> Self {
+        Self { last: initial }
+    }
+
+    /// Events for what changed since the previous snapshot
+    pub fn update(&mut self, status: PlayerStatus) -> Vec<PlayerEvent> {
+        let mut events = Vec::new();
+
+        let track_changed = track_key(&status.current_track) != track_key(&self.last.current_track);
+        if track_changed {
+            events.push(PlayerEvent::TrackChanged { track: status.current_track.clone().map(Box::new) });
+        }
+        if status.state != self.last.state {
+            events.push(PlayerEvent::StateChanged { state: status.state });
+        }
+        if (status.volume - self.last.volume).abs() > f32::EPSILON {
+            events.push(PlayerEvent::VolumeChanged { volume: status.volume });
+        }
+
+        // Report whole steps of progress and any seek backwards, but not the restart of a new track
+        let moved_on = status.position >= self.last.position + POSITION_STEP;
+        let moved_back = status.position < self.last.position;
+        if !track_changed && (moved_on || moved_back) {
+            events.push(PlayerEvent::PositionUpdate { position: status.position });
+        }
+
+        // Keep the last reported position so slow progress still adds up to a step
+        let reported_position = track_changed || moved_on || moved_back;
+        let position = if reported_position { status.position } else { self.last.position };
+        self.last = PlayerStatus { position, ..status };
+        events
+    }
+}
+
+/// Identity of a track: a file, and where in it a CUE sheet track starts
+fn track_key(track: &Option<TrackInfo>) -> Option<(&std::path::Path, Duration)> {
+    track.as_ref().map(|track| (track.path.as_path(), track.start_offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AudioMetadata;
+    use std::path::PathBuf;
+
+    fn track(path: &str) -> TrackInfo {
+        TrackInfo::new(PathBuf::from(path), AudioMetadata::new(), Duration::from_secs(200), 0)
+    }
+
+    fn playing(path: &str, position_ms: u64) -> PlayerStatus {
+        let mut status = PlayerStatus::new();
+        status.state = PlaybackState::Playing;
+        status.current_track = Some(track(path));
+        status.position = Duration::from_millis(position_ms);
+        status
+    }
+
+    #[test]
+    fn test_track_state_and_volume_changes() {
+        let mut diff = StatusDiff::new(PlayerStatus::new());
+
+        let events = diff.update(playing("/music/a.flac", 0));
+        assert_eq!(events, vec![
+            PlayerEvent::TrackChanged { track: Some(Box::new(track("/music/a.flac"))) },
+            PlayerEvent::StateChanged { state: PlaybackState::Playing },
+        ]);
+
+        let mut status = playing("/music/a.flac", 100);
+        status.volume = 0.5;
+        assert_eq!(diff.update(status), vec![PlayerEvent::VolumeChanged { volume: 0.5 }]);
+
+        let mut status = playing("/music/a.flac", 200);
+        status.volume = 0.5;
+        assert!(diff.update(status).is_empty());
+    }
+
+    #[test]
+    fn test_position_updates_once_per_second_and_on_seek() {
+        let mut diff = StatusDiff::new(playing("/music/a.flac", 0));
+
+        // Ten polls of 100ms add up to one update
+        let updates: Vec<_> = (1..=10)
+            .flat_map(|tick| diff.update(playing("/music/a.flac", tick * 100)))
+            .collect();
+        assert_eq!(updates, vec![PlayerEvent::PositionUpdate { position: Duration::from_secs(1) }]);
+
+        // Seeking back is reported at once
+        assert_eq!(
+            diff.update(playing("/music/a.flac", 400)),
+            vec![PlayerEvent::PositionUpdate { position: Duration::from_millis(400) }]
+        );
+
+        // A new track restarts the position without a separate update
+        assert_eq!(
+            diff.update(playing("/music/b.flac", 0)),
+            vec![PlayerEvent::TrackChanged { track: Some(Box::new(track("/music/b.flac"))) }]
+        );
+    }
+
+    #[test]
+    fn test_events_are_tagged_by_type() {
+        let json = serde_json::to_value(PlayerEvent::VolumeChanged { volume: 0.25 }).unwrap();
+        assert_eq!(json, serde_json::json!({ "type": "VolumeChanged", "volume": 0.25 }));
+    }
+}
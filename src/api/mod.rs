@@ -0,0 +1,448 @@
+use crate::cli::{CliApp, Commands, QueueAction};
+use crate::error::{PlayerError, QueueError};
+use crate::models::{PlayerStatus, TrackInfo};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{FromRef, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+pub mod events;
+use events::StatusDiff;
+
+/// What a REST request asks of the player
+#[derive(Debug)]
+pub enum ApiCommand {
+    /// Run a player command
+    Run(Commands),
+    /// Report the player status
+    Status,
+    /// Report the queue
+    Queue,
+    /// Restart playback where it stopped after a stream error; sent by a retry error recovery scheduled
+    RetryPlayback,
+}
+
+/// A request for the main loop, which owns the player, and where to send the answer
+#[derive(Debug)]
+pub struct ApiRequest {
+    pub command: ApiCommand,
+    pub reply: oneshot::Sender<Result<Value, PlayerError>>,
+}
+
+pub type ApiSender = mpsc::UnboundedSender<ApiRequest>;
+pub type ApiReceiver = mpsc::UnboundedReceiver<ApiRequest>;
+
+/// Channel between the HTTP server and the main loop
+pub fn channel() -> (ApiSender, ApiReceiver) {
+    mpsc::unbounded_channel()
+}
+
+/// Status snapshots the main loop publishes for WebSocket clients
+pub fn status_channel() -> broadcast::Sender<PlayerStatus> {
+    broadcast::channel(16).0
+}
+
+#[derive(Clone)]
+struct ApiState {
+    requests: ApiSender,
+    statuses: broadcast::Sender<PlayerStatus>,
+}
+
+impl FromRef<ApiState> for ApiSender {
+    fn from_ref(state: &ApiState) -> Self {
+        state.requests.clone()
+    }
+}
+
+/// Queue contents as returned by `GET /queue`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueSnapshot {
+    /// Index of the current track, `None` when the queue is empty
+    pub current_index: Option<usize>,
+    pub tracks: Vec<TrackInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlayRequest {
+    path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VolumeRequest {
+    level: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueueAddRequest {
+    path: String,
+}
+
+type ApiResponse = (StatusCode, Json<Value>);
+
+/// HTTP server for remote control; requests are answered by the player's main loop
+pub struct RestApiServer {
+    local_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl RestApiServer {
+    /// Listen on `address:port` (port 0 picks a free one)
+    pub async fn bind(
+        address: &str,
+        port: u16,
+        requests: ApiSender,
+        statuses: broadcast::Sender<PlayerStatus>,
+    ) -> std::io::Result<Self> {
+        let listener = tokio::net::TcpListener::bind((address, port)).await?;
+        let local_addr = listener.local_addr()?;
+        let router = Self::router(ApiState { requests, statuses });
+        let task = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, router).await {
+                log::error!("REST API server stopped: {}", e);
+            }
+        });
+        Ok(Self { local_addr, task })
+    }
+
+    /// Address the server is listening on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    fn router(state: ApiState) -> Router {
+        Router::new()
+            .route("/play", post(play))
+            .route("/pause", post(|State(requests): State<ApiSender>| run(requests, Commands::Pause)))
+            .route("/resume", post(|State(requests): State<ApiSender>| run(requests, Commands::Resume)))
+            .route("/stop", post(|State(requests): State<ApiSender>| run(requests, Commands::Stop)))
+            .route("/next", post(|State(requests): State<ApiSender>| run(requests, Commands::Next)))
+            .route("/prev", post(|State(requests): State<ApiSender>| run(requests, Commands::Prev)))
+            .route("/status", get(|State(requests): State<ApiSender>| forward(requests, ApiCommand::Status)))
+            .route("/volume", post(volume))
+            .route("/queue", get(|State(requests): State<ApiSender>| forward(requests, ApiCommand::Queue)).delete(clear_queue))
+            .route("/queue/add", post(add_to_queue))
+            .route("/ws", get(websocket))
+            .with_state(state)
+    }
+}
+
+impl Drop for RestApiServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn play(State(requests): State<ApiSender>, body: Option<Json<PlayRequest>>) -> ApiResponse {
+    let path = body.and_then(|Json(body)| body.path).map(|path| CliApp::expand_path(&path));
+    run(requests, Commands::Play { path }).await
+}
+
+async fn volume(State(requests): State<ApiSender>, Json(body): Json<VolumeRequest>) -> ApiResponse {
+    if body.level > 100 {
+        return error_response(StatusCode::BAD_REQUEST, format!("Volume must be between 0 and 100, got {}", body.level));
+    }
+    run(requests, Commands::Volume { level: body.level }).await
+}
+
+async fn add_to_queue(State(requests): State<ApiSender>, Json(body): Json<QueueAddRequest>) -> ApiResponse {
+    let path = CliApp::expand_path(&body.path);
+    run_then(requests, Commands::Queue { action: QueueAction::Add { path, filters: Default::default() } }, ApiCommand::Queue).await
+}
+
+async fn clear_queue(State(requests): State<ApiSender>) -> ApiResponse {
+    run_then(requests, Commands::Queue { action: QueueAction::Clear }, ApiCommand::Queue).await
+}
+
+async fn websocket(upgrade: WebSocketUpgrade, State(state): State<ApiState>) -> Response {
+    upgrade.on_upgrade(move |socket| push_events(socket, state))
+}
+
+/// Send the current status, then an event for each change until the client leaves
+async fn push_events(mut socket: WebSocket, state: ApiState) {
+    // Subscribe first so nothing between the snapshot and the first update is missed
+    let mut statuses = state.statuses.subscribe();
+    let (reply, answer) = oneshot::channel();
+    if state.requests.send(ApiRequest { command: ApiCommand::Status, reply }).is_err() {
+        return;
+    }
+    let Ok(Ok(initial)) = answer.await else {
+        return;
+    };
+    let Ok(status) = serde_json::from_value::<PlayerStatus>(initial.clone()) else {
+        return;
+    };
+    if socket.send(Message::Text(initial.to_string())).await.is_err() {
+        return;
+    }
+
+    let mut diff = StatusDiff::new(status);
+    loop {
+        tokio::select! {
+            update = statuses.recv() => match update {
+                Ok(status) => {
+                    for event in diff.update(status) {
+                        let Ok(text) = serde_json::to_string(&event) else { continue };
+                        if socket.send(Message::Text(text)).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                // Only the latest snapshot matters to the diff
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+/// Run a command and answer with the resulting status
+async fn run(requests: ApiSender, command: Commands) -> ApiResponse {
+    run_then(requests, command, ApiCommand::Status).await
+}
+
+async fn run_then(requests: ApiSender, command: Commands, report: ApiCommand) -> ApiResponse {
+    let (status, body) = forward(requests.clone(), ApiCommand::Run(command)).await;
+    if !status.is_success() {
+        return (status, body);
+    }
+    forward(requests, report).await
+}
+
+async fn forward(requests: ApiSender, command: ApiCommand) -> ApiResponse {
+    let (reply, answer) = oneshot::channel();
+    if requests.send(ApiRequest { command, reply }).is_err() {
+        return unavailable();
+    }
+    match answer.await {
+        Ok(Ok(body)) => (StatusCode::OK, Json(body)),
+        Ok(Err(e)) => error_response(status_for(&e), e.user_message()),
+        Err(_) => unavailable(),
+    }
+}
+
+fn unavailable() -> ApiResponse {
+    error_response(StatusCode::SERVICE_UNAVAILABLE, "Player is shutting down".to_string())
+}
+
+fn error_response(status: StatusCode, message: String) -> ApiResponse {
+    (status, Json(json!({ "error": message })))
+}
+
+/// HTTP status for a failed command
+fn status_for(error: &PlayerError) -> StatusCode {
+    match error {
+        PlayerError::Queue(QueueError::FileNotFound { .. }) => StatusCode::NOT_FOUND,
+        PlayerError::File(e) if e.kind() == std::io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        PlayerError::Queue(QueueError::EmptyQueue) => StatusCode::CONFLICT,
+        PlayerError::Queue(_) | PlayerError::Parse(_) | PlayerError::Config(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PlayerStatus;
+    use reqwest::Client;
+
+    /// Stands in for the player's main loop: a queue of paths and a volume
+    fn spawn_fake_player(mut requests: ApiReceiver, statuses: broadcast::Sender<PlayerStatus>) {
+        tokio::spawn(async move {
+            let mut status = PlayerStatus::new();
+            let mut queue: Vec<TrackInfo> = Vec::new();
+            while let Some(request) = requests.recv().await {
+                let result = match request.command {
+                    ApiCommand::Status => Ok(serde_json::to_value(&status).unwrap()),
+                    ApiCommand::Queue => Ok(serde_json::to_value(QueueSnapshot {
+                        current_index: if queue.is_empty() { None } else { Some(0) },
+                        tracks: queue.clone(),
+                    }).unwrap()),
+                    ApiCommand::RetryPlayback => Ok(Value::Null),
+                    ApiCommand::Run(command) => {
+                        let result = match command {
+                            Commands::Play { .. } if queue.is_empty() => Err(PlayerError::Queue(QueueError::EmptyQueue)),
+                            Commands::Play { .. } | Commands::Resume | Commands::Next | Commands::Prev => {
+                                status.state = crate::models::PlaybackState::Playing;
+                                Ok(())
+                            }
+                            Commands::Pause => {
+                                status.state = crate::models::PlaybackState::Paused;
+                                Ok(())
+                            }
+                            Commands::Stop => {
+                                status.state = crate::models::PlaybackState::Stopped;
+                                Ok(())
+                            }
+                            Commands::Volume { level } => {
+                                status.volume = level as f32 / 100.0;
+                                Ok(())
+                            }
+                            Commands::Queue { action: QueueAction::Add { path, .. } } if path.exists() => {
+                                queue.push(TrackInfo::new(path, crate::models::AudioMetadata::new(), std::time::Duration::ZERO, 0));
+                                Ok(())
+                            }
+                            Commands::Queue { action: QueueAction::Add { path, .. } } => Err(PlayerError::Queue(QueueError::FileNotFound { path })),
+                            Commands::Queue { action: QueueAction::Clear } => {
+                                queue.clear();
+                                Ok(())
+                            }
+                            other => panic!("unexpected command {:?}", other),
+                        };
+                        let _ = statuses.send(status.clone());
+                        result.map(|()| Value::Null)
+                    }
+                };
+                let _ = request.reply.send(result);
+            }
+        });
+    }
+
+    async fn start() -> (RestApiServer, String) {
+        let (sender, receiver) = channel();
+        let statuses = status_channel();
+        spawn_fake_player(receiver, statuses.clone());
+        let server = RestApiServer::bind("127.0.0.1", 0, sender, statuses).await.unwrap();
+        let base = format!("http://{}", server.local_addr());
+        (server, base)
+    }
+
+    #[tokio::test]
+    async fn test_playback_endpoints_return_status() {
+        let (_server, base) = start().await;
+        let client = Client::new();
+
+        let response = client.get(format!("{}/status", base)).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        let status: PlayerStatus = response.json().await.unwrap();
+        assert_eq!(status.state, crate::models::PlaybackState::Stopped);
+
+        let response = client.post(format!("{}/play", base)).send().await.unwrap();
+        assert_eq!(response.status(), 409);
+        let body: Value = response.json().await.unwrap();
+        assert!(body["error"].as_str().unwrap().contains("No tracks in queue"));
+
+        let track = tempfile::NamedTempFile::new().unwrap();
+        client.post(format!("{}/queue/add", base))
+            .json(&json!({ "path": track.path() }))
+            .send().await.unwrap();
+
+        for (endpoint, state) in [
+            ("play", "Playing"),
+            ("pause", "Paused"),
+            ("resume", "Playing"),
+            ("next", "Playing"),
+            ("prev", "Playing"),
+            ("stop", "Stopped"),
+        ] {
+            let response = client.post(format!("{}/{}", base, endpoint)).send().await.unwrap();
+            assert_eq!(response.status(), 200, "POST /{}", endpoint);
+            let body: Value = response.json().await.unwrap();
+            assert_eq!(body["state"], state, "POST /{}", endpoint);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_volume_endpoint_validates_level() {
+        let (_server, base) = start().await;
+        let client = Client::new();
+
+        let response = client.post(format!("{}/volume", base)).json(&json!({ "level": 75 })).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        let status: PlayerStatus = response.json().await.unwrap();
+        assert!((status.volume - 0.75).abs() < f32::EPSILON);
+
+        let response = client.post(format!("{}/volume", base)).json(&json!({ "level": 150 })).send().await.unwrap();
+        assert_eq!(response.status(), 400);
+        let body: Value = response.json().await.unwrap();
+        assert!(body["error"].is_string());
+
+        let response = client.post(format!("{}/volume", base)).json(&json!({ "volume": 75 })).send().await.unwrap();
+        assert!(response.status().is_client_error());
+    }
+
+    #[tokio::test]
+    async fn test_queue_endpoints() {
+        let (_server, base) = start().await;
+        let client = Client::new();
+
+        let response = client.get(format!("{}/queue", base)).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        let queue: QueueSnapshot = response.json().await.unwrap();
+        assert_eq!(queue.current_index, None);
+        assert!(queue.tracks.is_empty());
+
+        let track = tempfile::NamedTempFile::new().unwrap();
+        let response = client.post(format!("{}/queue/add", base))
+            .json(&json!({ "path": track.path() }))
+            .send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        let queue: QueueSnapshot = response.json().await.unwrap();
+        assert_eq!(queue.current_index, Some(0));
+        assert_eq!(queue.tracks[0].path, track.path());
+
+        let response = client.post(format!("{}/queue/add", base))
+            .json(&json!({ "path": "/no/such/file.flac" }))
+            .send().await.unwrap();
+        assert_eq!(response.status(), 404);
+
+        let response = client.delete(format!("{}/queue", base)).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        let queue: QueueSnapshot = response.json().await.unwrap();
+        assert!(queue.tracks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_requests_fail_when_player_is_gone() {
+        let (sender, receiver) = channel();
+        drop(receiver);
+        let server = RestApiServer::bind("127.0.0.1", 0, sender, status_channel()).await.unwrap();
+
+        let response = reqwest::get(format!("http://{}/status", server.local_addr())).await.unwrap();
+        assert_eq!(response.status(), 503);
+    }
+
+    #[tokio::test]
+    async fn test_websocket_pushes_volume_change() {
+        use futures_util::StreamExt;
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let (server, base) = start().await;
+        let url = format!("ws://{}/ws", server.local_addr());
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+
+        // The current status comes first
+        let Some(Ok(WsMessage::Text(initial))) = socket.next().await else {
+            panic!("expected the current status");
+        };
+        let status: PlayerStatus = serde_json::from_str(&initial).unwrap();
+        assert_eq!(status.state, crate::models::PlaybackState::Stopped);
+
+        let response = Client::new().post(format!("{}/volume", base)).json(&json!({ "level": 40 })).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        let event = tokio::time::timeout(std::time::Duration::from_millis(200), async {
+            while let Some(Ok(message)) = socket.next().await {
+                if let WsMessage::Text(text) = message {
+                    return serde_json::from_str::<Value>(&text).unwrap();
+                }
+            }
+            panic!("socket closed before the event arrived");
+        })
+        .await
+        .expect("volume event within 200ms");
+        assert_eq!(event["type"], "VolumeChanged");
+        assert!((event["volume"].as_f64().unwrap() - 0.4).abs() < 1e-6);
+    }
+}
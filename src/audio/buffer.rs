@@ -1,6 +1,8 @@
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use crate::audio::level::LevelMeter;
 use crate::models::AudioBuffer;
 use crate::error::AudioError;
 
@@ -13,6 +15,7 @@ pub struct RingBuffer {
     write_pos: AtomicUsize,
     channels: u16,
     sample_rate: u32,
+    level_meter: LevelMeter,
 }
 
 impl RingBuffer {
@@ -26,6 +29,7 @@ impl RingBuffer {
             write_pos: AtomicUsize::new(0),
             channels,
             sample_rate,
+            level_meter: LevelMeter::new(channels, sample_rate),
         }
     }
 
@@ -47,6 +51,11 @@ impl RingBuffer {
         self.sample_rate
     }
 
+    /// Levels of the audio most recently written with `write_audio_buffer`
+    pub fn level_meter(&self) -> &LevelMeter {
+        &self.level_meter
+    }
+
     /// Get the number of samples available for reading
     pub fn available_read(&self) -> usize {
         let write_pos = self.write_pos.load(Ordering::Acquire);
@@ -203,7 +212,11 @@ impl RingBuffer {
             return 0; // Channel mismatch
         }
 
-        let samples_written = self.write(&audio_buffer.samples);
+        // Whole frames only, so the reader never sees one channel's sample in another's slot
+        let whole_frames = self.available_write_frames() * self.channels as usize;
+        let samples = &audio_buffer.samples[..audio_buffer.samples.len().min(whole_frames)];
+        let samples_written = self.write(samples);
+        self.level_meter.process(&audio_buffer.samples[..samples_written]);
         samples_written / self.channels as usize
     }
 
@@ -225,10 +238,32 @@ impl RingBuffer {
         }
     }
 
+    /// Copy of the most recently written `frames` frames, played or not; nothing is consumed
+    ///
+    /// Asking for more than the buffer holds returns what fits. Frames that were never
+    /// written come back as silence.
+    pub fn latest(&self, frames: usize) -> AudioBuffer {
+        let channels = self.channels.max(1) as usize;
+        let samples_wanted = (frames * channels).min(self.capacity - self.capacity % channels);
+        let write_pos = self.write_pos.load(Ordering::Acquire);
+        let start = (write_pos + self.capacity - samples_wanted) % self.capacity.max(1);
+        let samples: Vec<f32> = (0..samples_wanted)
+            .map(|i| self.buffer[(start + i) % self.capacity])
+            .collect();
+
+        AudioBuffer {
+            frames: samples.len() / channels,
+            samples,
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+        }
+    }
+
     /// Clear the buffer
     pub fn clear(&self) {
         self.read_pos.store(0, Ordering::Release);
         self.write_pos.store(0, Ordering::Release);
+        self.level_meter.reset();
     }
 
     /// Get the current fill level as a percentage (0.0 to 1.0)
@@ -251,12 +286,17 @@ impl RingBuffer {
 #[derive(Debug)]
 pub struct BufferManager {
     ring_buffer: Arc<RingBuffer>,
-    target_buffer_duration: Duration,
-    min_buffer_duration: Duration,
+    target_buffer_ms: AtomicU64,
+    min_buffer_ms: AtomicU64,
     underrun_count: AtomicUsize,
-    last_underrun: std::sync::Mutex<Option<Instant>>,
+    epoch: Instant,
+    /// Microseconds after `epoch` of the last underrun, or `NO_UNDERRUN`
+    last_underrun_us: AtomicU64,
 }
 
+/// `last_underrun_us` before any underrun, so recording never takes a lock
+const NO_UNDERRUN: u64 = u64::MAX;
+
 impl BufferManager {
     /// Create a new buffer manager
     pub fn new(
@@ -270,10 +310,11 @@ impl BufferManager {
 
         Self {
             ring_buffer,
-            target_buffer_duration: Duration::from_millis(target_buffer_ms),
-            min_buffer_duration: Duration::from_millis(min_buffer_ms),
+            target_buffer_ms: AtomicU64::new(target_buffer_ms),
+            min_buffer_ms: AtomicU64::new(min_buffer_ms),
             underrun_count: AtomicUsize::new(0),
-            last_underrun: std::sync::Mutex::new(None),
+            epoch: Instant::now(),
+            last_underrun_us: AtomicU64::new(NO_UNDERRUN),
         }
     }
 
@@ -282,15 +323,41 @@ impl BufferManager {
         Arc::clone(&self.ring_buffer)
     }
 
+    /// Buffer level the decoder refills to, in milliseconds
+    pub fn target_buffer_ms(&self) -> u64 {
+        self.target_buffer_ms.load(Ordering::Relaxed)
+    }
+
+    /// Change the refill target and underrun threshold while playing
+    pub fn set_thresholds(&self, target_buffer_ms: u64, min_buffer_ms: u64) {
+        self.target_buffer_ms.store(target_buffer_ms, Ordering::Relaxed);
+        self.min_buffer_ms.store(min_buffer_ms, Ordering::Relaxed);
+    }
+
+    /// Refill to `target_frames` from now on, with the underrun threshold at half of it
+    ///
+    /// Returns false, leaving the thresholds unchanged, when the ring buffer is too small to
+    /// hold that target with headroom for the decoder; the caller then needs a larger buffer.
+    pub fn resize(&self, target_frames: usize) -> bool {
+        if target_frames > self.ring_buffer.capacity_frames() * 4 / 5 {
+            return false;
+        }
+        let sample_rate = self.ring_buffer.sample_rate().max(1) as u64;
+        let target_ms = target_frames as u64 * 1000 / sample_rate;
+        self.set_thresholds(target_ms, target_ms / 2);
+        true
+    }
+
     /// Check if buffer needs more data
     pub fn needs_data(&self) -> bool {
-        self.ring_buffer.buffered_duration() < self.target_buffer_duration
+        self.ring_buffer.buffered_duration() < Duration::from_millis(self.target_buffer_ms())
     }
 
     /// Check for buffer underrun
     pub fn check_underrun(&self) -> bool {
         let buffered = self.ring_buffer.buffered_duration();
-        if buffered < self.min_buffer_duration && !self.ring_buffer.is_empty() {
+        let min_buffer = Duration::from_millis(self.min_buffer_ms.load(Ordering::Relaxed));
+        if buffered < min_buffer && !self.ring_buffer.is_empty() {
             self.record_underrun();
             true
         } else {
@@ -301,9 +368,8 @@ impl BufferManager {
     /// Record a buffer underrun
     fn record_underrun(&self) {
         self.underrun_count.fetch_add(1, Ordering::Relaxed);
-        if let Ok(mut last_underrun) = self.last_underrun.lock() {
-            *last_underrun = Some(Instant::now());
-        }
+        let elapsed_us = self.epoch.elapsed().as_micros().min(NO_UNDERRUN as u128 - 1) as u64;
+        self.last_underrun_us.store(elapsed_us, Ordering::Relaxed);
     }
 
     /// Get the total number of underruns
@@ -313,15 +379,16 @@ impl BufferManager {
 
     /// Get the time of the last underrun
     pub fn last_underrun(&self) -> Option<Instant> {
-        self.last_underrun.lock().ok().and_then(|guard| *guard)
+        match self.last_underrun_us.load(Ordering::Relaxed) {
+            NO_UNDERRUN => None,
+            elapsed_us => Some(self.epoch + Duration::from_micros(elapsed_us)),
+        }
     }
 
     /// Reset underrun statistics
     pub fn reset_underrun_stats(&self) {
         self.underrun_count.store(0, Ordering::Relaxed);
-        if let Ok(mut last_underrun) = self.last_underrun.lock() {
-            *last_underrun = None;
-        }
+        self.last_underrun_us.store(NO_UNDERRUN, Ordering::Relaxed);
     }
 
     /// Get buffer status information
@@ -349,6 +416,280 @@ impl BufferManager {
     }
 }
 
+/// Lock-free underrun accounting shared with the output callback
+///
+/// The callback only touches atomics here: it counts short reads and, while an output hold is
+/// active, plays silence so the decoder can refill the ring buffer.
+#[derive(Debug, Default)]
+pub struct UnderrunMonitor {
+    underruns: AtomicU64,
+    source_active: AtomicBool,
+    hold_frames: AtomicU64,
+}
+
+impl UnderrunMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a callback that got fewer frames than it needed (ignored while no source is feeding)
+    pub fn record_short_read(&self) {
+        if self.source_active.load(Ordering::Relaxed) {
+            self.underruns.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Total underruns since creation
+    pub fn underrun_count(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+
+    /// Mark whether the decoder is feeding audio; short reads before the first buffer of a track
+    /// or after the end of the queue are expected and not counted
+    pub fn set_source_active(&self, active: bool) {
+        self.source_active.store(active, Ordering::Relaxed);
+    }
+
+    /// Output silence for the next `frames` frames instead of reading the ring buffer
+    pub fn hold_output(&self, frames: u64) {
+        self.hold_frames.store(frames, Ordering::Relaxed);
+    }
+
+    /// Consume `frames` of an active hold; returns true if this block should be silent
+    pub fn consume_hold(&self, frames: u64) -> bool {
+        let remaining = self.hold_frames.load(Ordering::Relaxed);
+        if remaining == 0 {
+            return false;
+        }
+        self.hold_frames.store(remaining.saturating_sub(frames), Ordering::Relaxed);
+        true
+    }
+}
+
+/// Holds back the start of playback until the ring buffer has filled far enough
+///
+/// Armed when a track starts or a stopped track is played again. The output callback plays
+/// silence, without moving the position, until `target` frames are buffered or the decoder
+/// has reached the end of what it can give.
+#[derive(Debug, Default)]
+pub struct PrebufferGate {
+    waiting: AtomicBool,
+    target_frames: AtomicU64,
+    source_finished: AtomicBool,
+}
+
+impl PrebufferGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait for `target_frames` buffered frames before playing (0 plays at once)
+    pub fn arm(&self, target_frames: u64) {
+        self.source_finished.store(false, Ordering::Relaxed);
+        self.target_frames.store(target_frames, Ordering::Relaxed);
+        self.waiting.store(target_frames > 0, Ordering::Relaxed);
+    }
+
+    /// Stop waiting and play whatever is buffered
+    pub fn release(&self) {
+        self.waiting.store(false, Ordering::Relaxed);
+    }
+
+    /// The decoder has no more audio for the track; a track shorter than the target plays as is
+    pub fn finish_source(&self) {
+        self.source_finished.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_waiting(&self) -> bool {
+        self.waiting.load(Ordering::Relaxed)
+    }
+
+    pub fn target_frames(&self) -> u64 {
+        self.target_frames.load(Ordering::Relaxed)
+    }
+
+    /// Called by the output callback with the frames ready to read; true while it should stay silent
+    pub fn hold(&self, available_frames: u64) -> bool {
+        if !self.waiting.load(Ordering::Relaxed) {
+            return false;
+        }
+        if available_frames >= self.target_frames.load(Ordering::Relaxed) || self.source_finished.load(Ordering::Relaxed) {
+            self.waiting.store(false, Ordering::Relaxed);
+            return false;
+        }
+        true
+    }
+}
+
+/// When and how far to grow the buffer after repeated underruns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveBufferPolicy {
+    /// Underruns within `window` that trigger an adjustment
+    pub threshold: usize,
+    pub window: Duration,
+    /// Factor applied to the target buffer level on each adjustment
+    pub growth: f32,
+    /// Upper bound for the target buffer level
+    pub max_target_ms: u64,
+    /// Silence played after an adjustment so the decoder can catch up (0 = none)
+    pub pause_ms: u64,
+    /// Underruns within one second that double the target at once
+    pub burst_threshold: usize,
+    /// Upper bound for doubling; the ring buffer is grown to fit
+    pub burst_max_target_ms: u64,
+}
+
+/// Ceiling for the target buffer level when underruns come in bursts
+pub const MAX_ADAPTIVE_TARGET_MS: u64 = 2000;
+
+impl AdaptiveBufferPolicy {
+    /// Default policy for a ring buffer of the given length
+    pub fn for_ring_buffer(ring_buffer_ms: u64, pause_ms: u64) -> Self {
+        Self {
+            threshold: 3,
+            window: Duration::from_secs(10),
+            growth: 1.5,
+            // Keep headroom so the decoder can always write ahead of the target
+            max_target_ms: ring_buffer_ms * 4 / 5,
+            pause_ms,
+            // More than 3 per second
+            burst_threshold: 4,
+            burst_max_target_ms: MAX_ADAPTIVE_TARGET_MS,
+        }
+    }
+}
+
+/// A buffer adjustment made in response to underruns
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferAdjustment {
+    pub underruns: usize,
+    pub window: Duration,
+    pub old_target_ms: u64,
+    pub new_target_ms: u64,
+    pub pause_ms: u64,
+}
+
+impl BufferAdjustment {
+    /// Human-readable description of the action taken
+    pub fn description(&self) -> String {
+        let mut description = format!(
+            "{} underruns in {}s: raised target buffer from {} ms to {} ms",
+            self.underruns, self.window.as_secs(), self.old_target_ms, self.new_target_ms
+        );
+        if self.pause_ms > 0 {
+            description.push_str(&format!(", paused output {} ms to refill", self.pause_ms));
+        }
+        description
+    }
+}
+
+/// Tracks underrun bursts and decides when to grow the buffer
+///
+/// Underruns that recur within the policy window raise the target by `growth`, up to
+/// `max_target_ms`. More than 3 within a second double it, up to `burst_max_target_ms`.
+#[derive(Debug)]
+pub struct AdaptiveBufferController {
+    policy: AdaptiveBufferPolicy,
+    recent: VecDeque<Instant>,
+    burst: VecDeque<Instant>,
+    last_count: u64,
+    target_ms: u64,
+    sample_rate: u32,
+}
+
+impl AdaptiveBufferController {
+    pub fn new(policy: AdaptiveBufferPolicy) -> Self {
+        Self {
+            policy,
+            recent: VecDeque::new(),
+            burst: VecDeque::new(),
+            last_count: 0,
+            target_ms: 0,
+            sample_rate: 0,
+        }
+    }
+
+    /// Start from `target_ms` at `sample_rate`
+    pub fn with_target(mut self, target_ms: u64, sample_rate: u32) -> Self {
+        self.target_ms = target_ms;
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    /// Record one underrun at `now`; returns the new target in frames when a burst doubles it
+    pub fn on_underrun(&mut self, now: Instant) -> Option<usize> {
+        self.burst.push_back(now);
+        while let Some(&oldest) = self.burst.front() {
+            if now.duration_since(oldest) >= Duration::from_secs(1) {
+                self.burst.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let ceiling = self.policy.burst_max_target_ms;
+        if self.burst.len() < self.policy.burst_threshold || self.target_ms >= ceiling {
+            return None;
+        }
+        self.burst.clear();
+        self.target_ms = (self.target_ms * 2).clamp(1, ceiling);
+        Some((self.sample_rate as u64 * self.target_ms / 1000) as usize)
+    }
+
+    /// Feed the latest underrun total; returns an adjustment when the policy threshold is reached
+    pub fn observe(&mut self, underrun_total: u64, current_target_ms: u64, now: Instant) -> Option<BufferAdjustment> {
+        let new_underruns = underrun_total.saturating_sub(self.last_count);
+        self.last_count = underrun_total;
+        self.target_ms = current_target_ms;
+
+        let mut burst = 0;
+        let mut doubled = false;
+        for _ in 0..new_underruns.min(self.policy.burst_threshold.max(self.policy.threshold) as u64) {
+            burst += 1;
+            doubled |= self.on_underrun(now).is_some();
+        }
+        if doubled {
+            self.recent.clear();
+            return Some(BufferAdjustment {
+                underruns: burst.max(self.policy.burst_threshold),
+                window: Duration::from_secs(1),
+                old_target_ms: current_target_ms,
+                new_target_ms: self.target_ms,
+                pause_ms: self.policy.pause_ms,
+            });
+        }
+
+        for _ in 0..new_underruns.min(self.policy.threshold as u64) {
+            self.recent.push_back(now);
+        }
+
+        while let Some(&oldest) = self.recent.front() {
+            if now.duration_since(oldest) > self.policy.window {
+                self.recent.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if self.recent.len() < self.policy.threshold || current_target_ms >= self.policy.max_target_ms {
+            return None;
+        }
+
+        let underruns = self.recent.len();
+        self.recent.clear();
+        self.burst.clear();
+        let grown = (current_target_ms as f32 * self.policy.growth).ceil() as u64;
+        self.target_ms = grown.clamp(current_target_ms + 1, self.policy.max_target_ms);
+        Some(BufferAdjustment {
+            underruns,
+            window: self.policy.window,
+            old_target_ms: current_target_ms,
+            new_target_ms: self.target_ms,
+            pause_ms: self.policy.pause_ms,
+        })
+    }
+}
+
 /// Buffer status information
 #[derive(Debug, Clone)]
 pub struct BufferStatus {
@@ -445,6 +786,22 @@ mod tests {
         assert_eq!(all_data, vec![3.0, 4.0, 5.0]);
     }
 
+    #[test]
+    fn test_ring_buffer_latest_copies_newest_frames_without_reading() {
+        let buffer = RingBuffer::new(4, 2, 48000);
+        buffer.write(&[1.0, 1.5, 2.0, 2.5, 3.0, 3.5]);
+        let mut played = vec![0.0; 4];
+        buffer.read(&mut played);
+        // Wraps around the end of the storage
+        buffer.write(&[4.0, 4.5, 5.0, 5.5]);
+
+        let latest = buffer.latest(3);
+        assert_eq!(latest.samples, vec![3.0, 3.5, 4.0, 4.5, 5.0, 5.5]);
+        assert_eq!((latest.frames, latest.channels, latest.sample_rate), (3, 2, 48000));
+        assert_eq!(buffer.available_read(), 6, "nothing is consumed");
+        assert_eq!(buffer.latest(10).frames, 4);
+    }
+
     #[test]
     fn test_ring_buffer_audio_buffer_integration() {
         let buffer = RingBuffer::new(100, 2, 44100);
@@ -466,6 +823,59 @@ mod tests {
         assert_eq!(read_buffer.samples, audio_buffer.samples);
     }
 
+    #[test]
+    fn test_ring_buffer_keeps_surround_channels_apart() {
+        let buffer = RingBuffer::new(16, 6, 48000);
+        // Each 5.1 channel carries its own ramp: channel * 100 + frame
+        let surround = |frames: std::ops::Range<usize>| AudioBuffer {
+            samples: frames.clone().flat_map(|frame| (0..6).map(move |channel| (channel * 100 + frame) as f32)).collect(),
+            channels: 6,
+            sample_rate: 48000,
+            frames: frames.len(),
+        };
+        let check = |read: &AudioBuffer, first_frame: usize| {
+            for (offset, frame) in read.samples.chunks(6).enumerate() {
+                for (channel, &sample) in frame.iter().enumerate() {
+                    assert_eq!(sample, (channel * 100 + first_frame + offset) as f32, "channel {} of frame {}", channel, offset);
+                }
+            }
+        };
+
+        // One sample short of the capacity: the frame that doesn't fit isn't split
+        assert_eq!(buffer.write_audio_buffer(&surround(0..20)), 15);
+        assert_eq!(buffer.available_read(), 15 * 6);
+        let read = buffer.read_audio_buffer(10);
+        assert_eq!((read.frames, read.channels), (10, 6));
+        check(&read, 0);
+
+        // The next write wraps around the end of the storage
+        assert_eq!(buffer.write_audio_buffer(&surround(15..25)), 10);
+        let read = buffer.read_audio_buffer(15);
+        assert_eq!(read.frames, 15);
+        check(&read, 10);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_ring_buffer_meters_written_audio() {
+        let buffer = RingBuffer::new(100, 2, 44100);
+        let audio_buffer = AudioBuffer {
+            samples: [0.25, -0.8].repeat(10),
+            channels: 2,
+            sample_rate: 44100,
+            frames: 10,
+        };
+
+        buffer.write_audio_buffer(&audio_buffer);
+        assert_eq!(buffer.level_meter().peak_left(), 0.25);
+        assert_eq!(buffer.level_meter().peak_right(), 0.8);
+        // Reading doesn't change the meter; clearing the buffer does
+        buffer.read_audio_buffer(10);
+        assert_eq!(buffer.level_meter().peak_right(), 0.8);
+        buffer.clear();
+        assert_eq!(buffer.level_meter().peak_right(), 0.0);
+    }
+
     #[test]
     fn test_ring_buffer_thread_safety() {
         let buffer = Arc::new(RingBuffer::new(1000, 2, 44100));
@@ -535,9 +945,16 @@ mod tests {
         ring_buffer.write(&small_data);
 
         // Check for underrun
+        assert!(manager.last_underrun().is_none());
+        let before = Instant::now();
         let is_underrun = manager.check_underrun();
         assert!(is_underrun);
         assert_eq!(manager.underrun_count(), 1);
+        let last_underrun = manager.last_underrun().unwrap();
+        assert!(last_underrun + Duration::from_millis(1) >= before && last_underrun <= Instant::now());
+
+        manager.reset_underrun_stats();
+        assert!(manager.last_underrun().is_none());
     }
 
     #[test]
@@ -608,4 +1025,155 @@ mod tests {
         let duration = buffer.buffered_duration();
         assert!((duration.as_secs_f64() - 0.5).abs() < 0.01);
     }
+
+    #[test]
+    fn test_underrun_monitor_counts_and_holds() {
+        let monitor = UnderrunMonitor::new();
+
+        // Nothing is counted until the decoder starts feeding
+        monitor.record_short_read();
+        assert_eq!(monitor.underrun_count(), 0);
+
+        monitor.set_source_active(true);
+        monitor.record_short_read();
+        monitor.record_short_read();
+        assert_eq!(monitor.underrun_count(), 2);
+
+        // Short reads after the last track ended are not underruns
+        monitor.set_source_active(false);
+        monitor.record_short_read();
+        assert_eq!(monitor.underrun_count(), 2);
+
+        monitor.hold_output(1000);
+        assert!(monitor.consume_hold(512));
+        assert!(monitor.consume_hold(512));
+        assert!(!monitor.consume_hold(512));
+    }
+
+    #[test]
+    fn test_prebuffer_gate_waits_for_target_fill() {
+        let gate = PrebufferGate::new();
+        assert!(!gate.hold(0));
+
+        gate.arm(1000);
+        assert!(gate.is_waiting());
+        assert!(gate.hold(0));
+        assert!(gate.hold(999));
+        assert!(!gate.hold(1000));
+        // Once open it stays open while the buffer drains
+        assert!(!gate.hold(0));
+        assert!(!gate.is_waiting());
+
+        // A pre-buffer of zero starts at once
+        gate.arm(0);
+        assert!(!gate.hold(0));
+    }
+
+    #[test]
+    fn test_prebuffer_gate_opens_when_the_source_ends_early() {
+        let gate = PrebufferGate::new();
+        gate.arm(1000);
+        gate.finish_source();
+        assert!(!gate.hold(200));
+
+        // Arming again forgets the previous track's end
+        gate.arm(1000);
+        assert!(gate.hold(200));
+        gate.release();
+        assert!(!gate.hold(200));
+    }
+
+    #[test]
+    fn test_adaptive_controller_grows_target_after_burst() {
+        let policy = AdaptiveBufferPolicy::for_ring_buffer(1000, 200);
+        let mut controller = AdaptiveBufferController::new(policy);
+        let start = Instant::now();
+
+        assert_eq!(controller.observe(1, 300, start), None);
+        assert_eq!(controller.observe(2, 300, start + Duration::from_secs(1)), None);
+
+        let adjustment = controller.observe(3, 300, start + Duration::from_secs(2)).expect("third underrun adjusts");
+        assert_eq!(adjustment.old_target_ms, 300);
+        assert_eq!(adjustment.new_target_ms, 450);
+        assert_eq!(adjustment.pause_ms, 200);
+        assert!(adjustment.description().contains("300 ms to 450 ms"));
+
+        // Capped below the ring buffer size
+        let adjustment = controller.observe(6, 700, start + Duration::from_secs(3)).unwrap();
+        assert_eq!(adjustment.new_target_ms, 800);
+        assert_eq!(controller.observe(9, 800, start + Duration::from_secs(4)), None);
+    }
+
+    #[test]
+    fn test_adaptive_controller_ignores_spread_out_underruns() {
+        let mut controller = AdaptiveBufferController::new(AdaptiveBufferPolicy::for_ring_buffer(1000, 0));
+        let start = Instant::now();
+
+        for i in 1..=5u64 {
+            let now = start + Duration::from_secs(i * 11);
+            assert_eq!(controller.observe(i, 300, now), None);
+        }
+    }
+
+    #[test]
+    fn test_adaptive_controller_doubles_on_rapid_underruns() {
+        let policy = AdaptiveBufferPolicy::for_ring_buffer(1000, 200);
+        let mut controller = AdaptiveBufferController::new(policy).with_target(300, 48000);
+        let start = Instant::now();
+
+        // Five bursts of rapid underruns, each more than 3 within a second
+        let mut targets = Vec::new();
+        for burst in 0..5u64 {
+            let mut new_frames = None;
+            for i in 0..4u64 {
+                let now = start + Duration::from_millis(burst * 2000 + i * 100);
+                new_frames = new_frames.or(controller.on_underrun(now));
+            }
+            targets.push(new_frames);
+        }
+
+        // 600, 1200, then capped at 2000 ms
+        assert_eq!(targets, vec![Some(28800), Some(57600), Some(96000), None, None]);
+        assert_eq!(96000 * 1000 / 48000, MAX_ADAPTIVE_TARGET_MS);
+
+        // 3 per second is not a burst
+        let mut controller = AdaptiveBufferController::new(policy).with_target(300, 48000);
+        for i in 0..9u64 {
+            assert_eq!(controller.on_underrun(start + Duration::from_millis(i * 400)), None);
+        }
+    }
+
+    #[test]
+    fn test_adaptive_controller_observe_reports_burst() {
+        let mut controller = AdaptiveBufferController::new(AdaptiveBufferPolicy::for_ring_buffer(1000, 0));
+        let adjustment = controller.observe(5, 300, Instant::now()).unwrap();
+        assert_eq!(adjustment.old_target_ms, 300);
+        // Doubling may go past the ring buffer limit of the slower policy
+        assert_eq!(adjustment.new_target_ms, 600);
+        let adjustment = controller.observe(10, 600, Instant::now()).unwrap();
+        assert_eq!(adjustment.new_target_ms, 1200);
+        assert!(adjustment.description().contains("in 1s"));
+    }
+
+    #[test]
+    fn test_buffer_manager_resize() {
+        let manager = BufferManager::new(44100, 2, 44100, 300, 150);
+        assert!(manager.resize(22050));
+        assert_eq!(manager.target_buffer_ms(), 500);
+
+        // Beyond 80% of the ring buffer it has to be rebuilt larger
+        assert!(!manager.resize(44100));
+        assert_eq!(manager.target_buffer_ms(), 500);
+    }
+
+    #[test]
+    fn test_buffer_manager_thresholds_adjustable() {
+        let manager = BufferManager::new(44100, 1, 44100, 100, 50);
+        manager.ring_buffer().write(&vec![0.0; 8820]); // 200ms
+        assert!(!manager.needs_data());
+
+        manager.set_thresholds(300, 150);
+        assert_eq!(manager.target_buffer_ms(), 300);
+        assert!(manager.needs_data());
+    }
 }
@@ -0,0 +1,130 @@
+/*!
+The processing between a decoder and the output.
+
+Decoded buffers are first brought to the output's format with `to_output_format`: remixed
+to its channels (through the configured speaker layout) and resampled to its rate. The
+decoder thread does this before writing to the ring buffer.
+
+`OutputChain` is what happens to each block after that: mono/swap/balance, crossfeed, EQ,
+fades, volume with clipping detection or the soft limiter, then dither for narrow outputs.
+The output callback runs it on what it reads from the ring buffer; `export` runs it on
+decoded audio directly, so an exported file matches what plays.
+*/
+
+use std::sync::Arc;
+use cpal::SampleFormat;
+
+use crate::audio::channels::{self, ChannelControl};
+use crate::audio::crossfeed::{CrossfeedControl, CrossfeedProcessor};
+use crate::audio::device::ChannelMap;
+use crate::audio::dither::{DitherControl, DitherProcessor};
+use crate::audio::effects::{FadeControl, FadeProcessor};
+use crate::audio::eq::{EqProcessor, ParametricEq};
+use crate::audio::limiter::ClipMonitor;
+use crate::audio::RateAdapter;
+use crate::models::AudioBuffer;
+
+/// Settings of the output chain, shared between the engine and the streams that run it
+#[derive(Debug, Clone, Default)]
+pub struct ChainControls {
+    pub clipping: Arc<ClipMonitor>,
+    pub eq: Arc<ParametricEq>,
+    pub fades: Arc<FadeControl>,
+    pub channels: Arc<ChannelControl>,
+    pub crossfeed: Arc<CrossfeedControl>,
+    pub dither: Arc<DitherControl>,
+}
+
+impl ChainControls {
+    /// Controls with the same settings as these but none of their state, for rendering
+    /// off-line: clipping counts and crossfeed's source channels stay with playback, and
+    /// no fade is in progress
+    pub fn detached(&self) -> Self {
+        let clipping = ClipMonitor::new();
+        clipping.set_limiter_enabled(self.clipping.is_limiter_enabled());
+        let eq = ParametricEq::new();
+        eq.set_bands(self.eq.bands());
+        Self {
+            clipping: Arc::new(clipping),
+            eq: Arc::new(eq),
+            fades: Arc::new(FadeControl::default()),
+            channels: Arc::new(ChannelControl::new(self.channels.get())),
+            crossfeed: Arc::new(CrossfeedControl::new(self.crossfeed.get())),
+            dither: Arc::new(DitherControl::new(self.dither.is_enabled())),
+        }
+    }
+
+    /// Filter and dither state for a stream of `channels` at `sample_rate`, converted to `format`
+    pub fn chain(&self, sample_rate: u32, channels: usize, format: SampleFormat) -> OutputChain {
+        OutputChain {
+            controls: self.clone(),
+            channels,
+            eq: self.eq.processor(sample_rate, channels),
+            fades: self.fades.processor(sample_rate, channels),
+            crossfeed: self.crossfeed.processor(sample_rate, channels),
+            dither: self.dither.processor(format),
+        }
+    }
+}
+
+/// The output chain of one stream
+#[derive(Debug)]
+pub struct OutputChain {
+    controls: ChainControls,
+    channels: usize,
+    eq: EqProcessor,
+    fades: FadeProcessor,
+    crossfeed: CrossfeedProcessor,
+    dither: DitherProcessor,
+}
+
+impl OutputChain {
+    /// Run interleaved samples through the chain in place, at `volume`
+    pub fn process(&mut self, samples: &mut [f32], volume: f32) {
+        let controls = &self.controls;
+        // Mono/swap/balance, crossfeed, then equalize so a boost is caught by the clipping detection
+        controls.channels.process(samples, self.channels);
+        self.crossfeed.process(&controls.crossfeed, samples);
+        self.eq.process(&controls.eq, samples);
+        self.fades.process(&controls.fades, samples);
+
+        // Apply volume, detect clipping and optionally soft-limit
+        controls.clipping.process(samples, volume);
+        // Round to a narrower output ourselves rather than let the conversion truncate
+        self.dither.process(&controls.dither, samples);
+    }
+
+    /// Forget a fade in progress, while the stream outputs silence
+    pub fn reset_fades(&mut self) {
+        self.fades.reset();
+    }
+}
+
+/// Bring a decoded buffer to an output of `channels` at `sample_rate`
+///
+/// `layout` routes the source's speakers when it has as many channels as the output;
+/// otherwise the output's default layout is used. The rate adapter keeps filter state
+/// between buffers of one stream.
+pub fn to_output_format(
+    buffer: AudioBuffer,
+    channels: u16,
+    layout: Option<ChannelMap>,
+    rate_adapter: &mut RateAdapter,
+    sample_rate: u32,
+) -> AudioBuffer {
+    let layout = layout
+        .filter(|layout| layout.channels() == channels)
+        .or_else(|| ChannelMap::for_output(channels));
+
+    // Fold surround down to stereo, or route each speaker to its output channel
+    let passthrough = match &layout {
+        Some(layout) => channels::is_passthrough(buffer.channels, layout),
+        None => buffer.channels == channels,
+    };
+    let converted = if passthrough {
+        buffer
+    } else {
+        channels::remix(&buffer, channels, layout.as_ref())
+    };
+    rate_adapter.process(converted, sample_rate)
+}
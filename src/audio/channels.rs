@@ -0,0 +1,333 @@
+/*!
+Channel processing for the output path.
+
+- `remix` fits decoded audio to the output's channels before it goes into the ring
+  buffer. Surround sources fold down to stereo with the ITU-R BS.775 coefficients: centre
+  and surrounds at -3 dB, LFE left out. On surround outputs each channel is routed to its
+  speaker by `ChannelMap`, since hosts order the channels differently.
+- `ChannelControl` is shared between the engine and the output callback, which runs every
+  block through `ChannelControl::process`: mono downmix or left/right swap, then balance.
+  The settings are atomics, so `output` changes are heard on the next block without
+  rebuilding the stream.
+*/
+
+use std::fmt;
+use std::sync::atomic::{AtomicI8, AtomicU8, Ordering};
+use serde::{Deserialize, Serialize};
+use crate::audio::device::ChannelMap;
+use crate::models::AudioBuffer;
+
+/// Furthest the balance goes to either side
+pub const MAX_BALANCE: i8 = 100;
+
+/// How the left and right channels reach the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelMode {
+    /// Left and right as they are
+    #[default]
+    Stereo,
+    /// Both channels mixed down and played on each side
+    Mono,
+    /// Left and right exchanged
+    Swap,
+}
+
+impl ChannelMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChannelMode::Stereo => "stereo",
+            ChannelMode::Mono => "mono",
+            ChannelMode::Swap => "swap",
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => ChannelMode::Mono,
+            2 => ChannelMode::Swap,
+            _ => ChannelMode::Stereo,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            ChannelMode::Stereo => 0,
+            ChannelMode::Mono => 1,
+            ChannelMode::Swap => 2,
+        }
+    }
+}
+
+impl std::str::FromStr for ChannelMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "stereo" | "normal" => Ok(ChannelMode::Stereo),
+            "mono" => Ok(ChannelMode::Mono),
+            "swap" => Ok(ChannelMode::Swap),
+            _ => Err(format!("unknown output mode '{}' (expected stereo, mono or swap)", s)),
+        }
+    }
+}
+
+/// Channel mode and balance applied to the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct OutputChannels {
+    pub mode: ChannelMode,
+    /// -100 (left only) to 100 (right only)
+    pub balance: i8,
+}
+
+impl OutputChannels {
+    /// Whether the output is left exactly as decoded
+    pub fn is_neutral(&self) -> bool {
+        self.mode == ChannelMode::Stereo && self.balance == 0
+    }
+
+    /// Left and right gains for the balance: the side it moves away from is turned down
+    pub fn gains(&self) -> (f32, f32) {
+        let balance = self.balance.clamp(-MAX_BALANCE, MAX_BALANCE) as f32 / MAX_BALANCE as f32;
+        ((1.0 - balance).min(1.0), (1.0 + balance).min(1.0))
+    }
+
+    /// Balance as shown to the user: "center", "L20" or "R35"
+    pub fn balance_label(&self) -> String {
+        match self.balance {
+            0 => "center".to_string(),
+            balance if balance < 0 => format!("L{}", balance.unsigned_abs()),
+            balance => format!("R{}", balance),
+        }
+    }
+}
+
+impl fmt::Display for OutputChannels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.mode.as_str())?;
+        if self.balance != 0 {
+            write!(f, ", balance {}", self.balance_label())?;
+        }
+        Ok(())
+    }
+}
+
+/// Output channel settings shared with the output callback
+#[derive(Debug, Default)]
+pub struct ChannelControl {
+    mode: AtomicU8,
+    balance: AtomicI8,
+}
+
+impl ChannelControl {
+    pub fn new(settings: OutputChannels) -> Self {
+        let control = Self::default();
+        control.set(settings);
+        control
+    }
+
+    pub fn set(&self, settings: OutputChannels) {
+        self.mode.store(settings.mode.to_u8(), Ordering::Relaxed);
+        self.balance.store(settings.balance.clamp(-MAX_BALANCE, MAX_BALANCE), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> OutputChannels {
+        OutputChannels {
+            mode: ChannelMode::from_u8(self.mode.load(Ordering::Relaxed)),
+            balance: self.balance.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Apply the mode and balance to interleaved samples in place; only the first two channels are touched
+    pub fn process(&self, samples: &mut [f32], channels: usize) {
+        let settings = self.get();
+        if channels < 2 || settings.is_neutral() {
+            return;
+        }
+        let (left_gain, right_gain) = settings.gains();
+        for frame in samples.chunks_exact_mut(channels) {
+            let (left, right) = match settings.mode {
+                ChannelMode::Stereo => (frame[0], frame[1]),
+                ChannelMode::Mono => {
+                    let mono = (frame[0] + frame[1]) * 0.5;
+                    (mono, mono)
+                }
+                ChannelMode::Swap => (frame[1], frame[0]),
+            };
+            frame[0] = left * left_gain;
+            frame[1] = right * right_gain;
+        }
+    }
+}
+
+/// Left and right of one frame: mono on both sides, surround folded down
+fn stereo_frame(frame: &[f32], routing: Option<&[Vec<f32>]>) -> (f32, f32) {
+    match (frame, routing) {
+        ([mono], _) => (*mono, *mono),
+        ([left, right], _) => (*left, *right),
+        (_, Some([left, right])) => (mix(left, frame), mix(right, frame)),
+        _ => (frame[0], frame[1]),
+    }
+}
+
+fn mix(gains: &[f32], frame: &[f32]) -> f32 {
+    gains.iter().zip(frame).map(|(gain, sample)| gain * sample).sum()
+}
+
+/// Whether audio with `source` channels already plays on the right speakers of `layout`
+pub fn is_passthrough(source: u16, layout: &ChannelMap) -> bool {
+    source == layout.channels() && (source <= 2 || ChannelMap::for_source(source).as_ref() == Some(layout))
+}
+
+/// Convert interleaved audio to `channels` channels laid out as `layout`
+///
+/// Mono and stereo outputs get a proper downmix; for wider outputs mono goes to the front
+/// pair and each surround channel to its speaker, or the nearest one the output has. Without
+/// a layout, or for sources wider than 7.1, channels are copied by position, padding with silence.
+pub fn remix(buffer: &AudioBuffer, channels: u16, layout: Option<&ChannelMap>) -> AudioBuffer {
+    let source = buffer.channels.max(1) as usize;
+    let target = channels.max(1) as usize;
+    let source_map = ChannelMap::for_source(source as u16);
+    let stereo_routing = source_map.as_ref()
+        .zip(ChannelMap::wave(2))
+        .map(|(source_map, stereo)| source_map.routing(&stereo));
+    let routing = source_map.as_ref()
+        .zip(layout.filter(|layout| layout.channels() as usize == target))
+        .map(|(source_map, layout)| source_map.routing(layout));
+
+    let mut samples = Vec::with_capacity(buffer.frames * target);
+    for frame in buffer.samples.chunks_exact(source) {
+        match target {
+            1 => {
+                let (left, right) = stereo_frame(frame, stereo_routing.as_deref());
+                samples.push((left + right) * 0.5);
+            }
+            2 => {
+                let (left, right) = stereo_frame(frame, stereo_routing.as_deref());
+                samples.extend([left, right]);
+            }
+            _ if source == 1 => {
+                samples.extend([frame[0], frame[0]]);
+                samples.extend(std::iter::repeat_n(0.0, target - 2));
+            }
+            _ => match &routing {
+                Some(routing) => samples.extend(routing.iter().map(|gains| mix(gains, frame))),
+                None => samples.extend((0..target).map(|channel| frame.get(channel).copied().unwrap_or(0.0))),
+            },
+        }
+    }
+    AudioBuffer {
+        samples,
+        channels,
+        sample_rate: buffer.sample_rate,
+        frames: buffer.frames,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    fn assert_samples(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len(), "{:?} vs {:?}", actual, expected);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-6, "{:?} vs {:?}", actual, expected);
+        }
+    }
+
+    fn processed(settings: OutputChannels, samples: &[f32], channels: usize) -> Vec<f32> {
+        let mut samples = samples.to_vec();
+        ChannelControl::new(settings).process(&mut samples, channels);
+        samples
+    }
+
+    #[test]
+    fn test_mono_swap_and_stereo() {
+        let stereo = [0.8, 0.2, -0.4, 0.0];
+        let mode = |mode| OutputChannels { mode, balance: 0 };
+        assert_samples(&processed(mode(ChannelMode::Stereo), &stereo, 2), &stereo);
+        assert_samples(&processed(mode(ChannelMode::Mono), &stereo, 2), &[0.5, 0.5, -0.2, -0.2]);
+        assert_samples(&processed(mode(ChannelMode::Swap), &stereo, 2), &[0.2, 0.8, 0.0, -0.4]);
+
+        // Channels past the front pair and mono outputs are left alone
+        assert_samples(&processed(mode(ChannelMode::Swap), &[0.1, 0.2, 0.3], 3), &[0.2, 0.1, 0.3]);
+        assert_samples(&processed(mode(ChannelMode::Mono), &[0.1, 0.2], 1), &[0.1, 0.2]);
+    }
+
+    #[test]
+    fn test_balance_turns_down_the_other_side() {
+        let balance = |balance| OutputChannels { mode: ChannelMode::Stereo, balance };
+        assert_eq!(balance(0).gains(), (1.0, 1.0));
+        assert_eq!(balance(100).gains(), (0.0, 1.0));
+        assert_eq!(balance(-100).gains(), (1.0, 0.0));
+        assert_samples(&processed(balance(-25), &[0.8, 0.8], 2), &[0.8, 0.6]);
+
+        // Balance applies after the mode
+        let swapped_right = OutputChannels { mode: ChannelMode::Swap, balance: 50 };
+        assert_samples(&processed(swapped_right, &[0.8, 0.2], 2), &[0.1, 0.8]);
+
+        assert_eq!(balance(-20).to_string(), "stereo, balance L20");
+        assert_eq!(OutputChannels { mode: ChannelMode::Mono, balance: 0 }.to_string(), "mono");
+    }
+
+    #[test]
+    fn test_settings_round_trip_through_the_atomics() {
+        let control = ChannelControl::new(OutputChannels::default());
+        let settings = OutputChannels { mode: ChannelMode::Swap, balance: -40 };
+        control.set(settings);
+        assert_eq!(control.get(), settings);
+
+        control.set(OutputChannels { mode: ChannelMode::Mono, balance: i8::MIN });
+        assert_eq!(control.get().balance, -MAX_BALANCE);
+        assert_eq!("Normal".parse::<ChannelMode>(), Ok(ChannelMode::Stereo));
+        assert!("quad".parse::<ChannelMode>().is_err());
+    }
+
+    #[test]
+    fn test_surround_folds_down_with_standard_coefficients() {
+        // 5.1: L R C LFE Ls Rs
+        let frame = [0.1, 0.2, 0.3, 0.9, 0.4, 0.5];
+        let buffer = AudioBuffer { samples: frame.to_vec(), channels: 6, sample_rate: 48000, frames: 1 };
+        let k = FRAC_1_SQRT_2;
+        let left = 0.1 + k * 0.3 + k * 0.4;
+        let right = 0.2 + k * 0.3 + k * 0.5;
+
+        let stereo = remix(&buffer, 2, ChannelMap::wave(2).as_ref());
+        assert_eq!((stereo.channels, stereo.frames), (2, 1));
+        assert_samples(&stereo.samples, &[left, right]);
+        assert_samples(&remix(&buffer, 1, ChannelMap::wave(1).as_ref()).samples, &[(left + right) / 2.0]);
+    }
+
+    #[test]
+    fn test_mono_and_stereo_sources() {
+        let mono = AudioBuffer { samples: vec![0.5, -0.25], channels: 1, sample_rate: 44100, frames: 2 };
+        assert_samples(&remix(&mono, 2, ChannelMap::wave(2).as_ref()).samples, &[0.5, 0.5, -0.25, -0.25]);
+        assert_samples(&remix(&mono, 4, ChannelMap::wave(4).as_ref()).samples, &[0.5, 0.5, 0.0, 0.0, -0.25, -0.25, 0.0, 0.0]);
+
+        let stereo = AudioBuffer { samples: vec![0.5, 0.25], channels: 2, sample_rate: 44100, frames: 1 };
+        assert_samples(&remix(&stereo, 1, ChannelMap::wave(1).as_ref()).samples, &[0.375]);
+        assert_samples(&remix(&stereo, 3, ChannelMap::wave(3).as_ref()).samples, &[0.5, 0.25, 0.0]);
+    }
+
+    #[test]
+    fn test_surround_is_routed_to_the_output_layout() {
+        // 5.1 in WAVE order onto ALSA's FL FR BL BR FC LFE
+        let surround = AudioBuffer { samples: vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6], channels: 6, sample_rate: 48000, frames: 1 };
+        let alsa = ChannelMap::alsa(6).unwrap();
+        assert!(!is_passthrough(6, &alsa));
+        assert!(is_passthrough(6, &ChannelMap::wave(6).unwrap()));
+        assert_samples(&remix(&surround, 6, Some(&alsa)).samples, &[0.1, 0.2, 0.5, 0.6, 0.3, 0.4]);
+
+        // Quad keeps its surrounds on the back pair rather than the centre and LFE
+        let quad = AudioBuffer { samples: vec![0.1, 0.2, 0.3, 0.4], channels: 4, sample_rate: 48000, frames: 1 };
+        assert_samples(&remix(&quad, 6, ChannelMap::wave(6).as_ref()).samples, &[0.1, 0.2, 0.0, 0.0, 0.3, 0.4]);
+
+        // 7.1 on a 5.1 output: the sides join the back pair
+        let wide = AudioBuffer { samples: vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.25, 0.125], channels: 8, sample_rate: 48000, frames: 1 };
+        assert_samples(&remix(&wide, 6, ChannelMap::wave(6).as_ref()).samples, &[0.1, 0.2, 0.3, 0.4, 0.75, 0.725]);
+
+        // Without a layout, channels are copied by position
+        assert_samples(&remix(&quad, 6, None).samples, &[0.1, 0.2, 0.3, 0.4, 0.0, 0.0]);
+    }
+}
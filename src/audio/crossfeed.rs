@@ -0,0 +1,259 @@
+/*!
+Headphone crossfeed for the output path.
+
+On headphones each ear hears only its own channel, which makes hard-panned stereo tiring
+to listen to. Crossfeed mixes a low-passed, slightly delayed copy of each channel into the
+other side, the way sound from a speaker also reaches the far ear (Bauer's
+stereophonic-to-binaural filter, as in bs2b). The same low-passed signal is taken off the
+direct side, so bass panned to the centre keeps its level and only the difference between
+the channels is narrowed.
+
+- `CrossfeedControl` is shared between the engine and the output callback. It holds atomics:
+  whether crossfeed is on, the feed level and the channel count of the source being decoded.
+- Each output stream owns a `CrossfeedProcessor` whose delay and filter are computed for the
+  stream's sample rate, so a stream rebuilt at another rate gets new coefficients. It leaves
+  the audio untouched while crossfeed is off, the output is not stereo, or the source is mono
+  or multichannel.
+*/
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering};
+use serde::{Deserialize, Serialize};
+
+/// Accepted feed levels, in dB below the direct signal
+pub const LEVEL_RANGE: std::ops::RangeInclusive<f32> = 3.0..=15.0;
+
+/// Corner of the low-pass on the crossfed signal
+const CUTOFF_HZ: f64 = 700.0;
+
+/// Extra path length to the far ear
+const DELAY_SECONDS: f64 = 0.0003;
+
+/// Whether crossfeed is on and how much of the other channel is mixed in
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CrossfeedSettings {
+    pub enabled: bool,
+    /// Level of the crossfed signal in dB below the direct one (`LEVEL_RANGE`)
+    pub level_db: f32,
+}
+
+impl CrossfeedSettings {
+    pub const DEFAULT_LEVEL_DB: f32 = 7.5;
+
+    /// Linear gain of the crossfed signal
+    pub fn feed_gain(&self) -> f32 {
+        10f32.powf(-self.level_db / 20.0)
+    }
+}
+
+impl Default for CrossfeedSettings {
+    fn default() -> Self {
+        Self { enabled: false, level_db: Self::DEFAULT_LEVEL_DB }
+    }
+}
+
+impl fmt::Display for CrossfeedSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.enabled {
+            write!(f, "on (-{:.1} dB)", self.level_db)
+        } else {
+            write!(f, "off")
+        }
+    }
+}
+
+/// Crossfeed settings shared with the output callback
+#[derive(Debug)]
+pub struct CrossfeedControl {
+    enabled: AtomicBool,
+    /// `level_db` as f32 bits
+    level: AtomicU32,
+    /// Channels of the source being decoded; 0 until the first buffer
+    source_channels: AtomicU16,
+}
+
+impl Default for CrossfeedControl {
+    fn default() -> Self {
+        Self::new(CrossfeedSettings::default())
+    }
+}
+
+impl CrossfeedControl {
+    pub fn new(settings: CrossfeedSettings) -> Self {
+        Self {
+            enabled: AtomicBool::new(settings.enabled),
+            level: AtomicU32::new(settings.level_db.to_bits()),
+            source_channels: AtomicU16::new(0),
+        }
+    }
+
+    pub fn set(&self, settings: CrossfeedSettings) {
+        let level = settings.level_db.clamp(*LEVEL_RANGE.start(), *LEVEL_RANGE.end());
+        self.level.store(level.to_bits(), Ordering::Relaxed);
+        self.enabled.store(settings.enabled, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> CrossfeedSettings {
+        CrossfeedSettings {
+            enabled: self.enabled.load(Ordering::Relaxed),
+            level_db: f32::from_bits(self.level.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Note the channel count of the audio going into the ring buffer
+    pub fn set_source_channels(&self, channels: u16) {
+        self.source_channels.store(channels, Ordering::Relaxed);
+    }
+
+    /// Delay line and filter state for one output stream
+    pub fn processor(&self, sample_rate: u32, channels: usize) -> CrossfeedProcessor {
+        let sample_rate = sample_rate.max(1) as f64;
+        let delay = delay_frames(sample_rate as u32);
+        CrossfeedProcessor {
+            channels,
+            alpha: (1.0 - (-2.0 * std::f64::consts::PI * CUTOFF_HZ / sample_rate).exp()) as f32,
+            delay_line: vec![[0.0; 2]; delay],
+            cursor: 0,
+            low_passed: [0.0; 2],
+            level_bits: u32::MAX,
+            feed: 0.0,
+            engaged: false,
+        }
+    }
+}
+
+/// Frames the crossfed signal lags behind at `sample_rate`
+pub fn delay_frames(sample_rate: u32) -> usize {
+    ((DELAY_SECONDS * sample_rate as f64).round() as usize).max(1)
+}
+
+/// Filter state owned by one output stream
+#[derive(Debug)]
+pub struct CrossfeedProcessor {
+    channels: usize,
+    /// One-pole low-pass coefficient for `CUTOFF_HZ`
+    alpha: f32,
+    /// Low-passed left/right frames, `delay_frames` long
+    delay_line: Vec<[f32; 2]>,
+    cursor: usize,
+    low_passed: [f32; 2],
+    /// Level the feed gain was computed for
+    level_bits: u32,
+    feed: f32,
+    /// Whether the last block was filtered; state is cleared when it starts again
+    engaged: bool,
+}
+
+impl CrossfeedProcessor {
+    /// Crossfeed interleaved stereo samples in place, or leave them as they are when bypassed
+    pub fn process(&mut self, control: &CrossfeedControl, samples: &mut [f32]) {
+        let settings = control.get();
+        let source_channels = control.source_channels.load(Ordering::Relaxed);
+        if !settings.enabled || self.channels != 2 || !matches!(source_channels, 0 | 2) {
+            self.engaged = false;
+            return;
+        }
+        if !self.engaged {
+            self.delay_line.iter_mut().for_each(|frame| *frame = [0.0; 2]);
+            self.low_passed = [0.0; 2];
+            self.engaged = true;
+        }
+        if settings.level_db.to_bits() != self.level_bits {
+            self.level_bits = settings.level_db.to_bits();
+            self.feed = settings.feed_gain();
+        }
+
+        for frame in samples.chunks_exact_mut(2) {
+            for (low_passed, sample) in self.low_passed.iter_mut().zip(frame.iter()) {
+                *low_passed += self.alpha * (*sample - *low_passed);
+            }
+            let delayed = std::mem::replace(&mut self.delay_line[self.cursor], self.low_passed);
+            self.cursor = (self.cursor + 1) % self.delay_line.len();
+            frame[0] += self.feed * (delayed[1] - self.low_passed[0]);
+            frame[1] += self.feed * (delayed[0] - self.low_passed[1]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled(level_db: f32) -> CrossfeedControl {
+        CrossfeedControl::new(CrossfeedSettings { enabled: true, level_db })
+    }
+
+    /// Left and right response to a one-sample impulse on the left
+    fn impulse_response(control: &CrossfeedControl, sample_rate: u32, frames: usize) -> (Vec<f32>, Vec<f32>) {
+        let mut samples = vec![0.0; frames * 2];
+        samples[0] = 1.0;
+        control.processor(sample_rate, 2).process(control, &mut samples);
+        samples.chunks(2).map(|frame| (frame[0], frame[1])).unzip()
+    }
+
+    #[test]
+    fn test_delay_follows_the_sample_rate() {
+        assert_eq!(delay_frames(44_100), 13);
+        assert_eq!(delay_frames(48_000), 14);
+        assert_eq!(delay_frames(96_000), 29);
+        assert_eq!(delay_frames(192_000), 58);
+
+        let control = enabled(6.0);
+        for rate in [44_100, 96_000] {
+            let (_, right) = impulse_response(&control, rate, 64);
+            let first = right.iter().position(|sample| *sample != 0.0);
+            assert_eq!(first, Some(delay_frames(rate)), "at {} Hz", rate);
+        }
+    }
+
+    #[test]
+    fn test_crossfed_level_and_shape() {
+        for level_db in [6.0, 9.0] {
+            let control = enabled(level_db);
+            let (left, right) = impulse_response(&control, 48_000, 4800);
+            let delay = delay_frames(48_000);
+
+            // The far side gets the impulse low-passed, at the feed level overall
+            let total: f32 = right.iter().sum();
+            assert!((20.0 * total.log10() + level_db).abs() < 0.01, "{} dB feed summed to {}", level_db, total);
+            assert!(right[delay] > right[delay + 1] && right[delay + 1] > right[delay + 100]);
+            assert!(right[delay + 100] > 0.0);
+
+            // The near side keeps the impulse, minus the same low-passed signal
+            assert!(left[0] < 1.0 && left[0] > 0.9);
+            assert!(left[1..100].iter().all(|sample| *sample < 0.0));
+            assert!((left.iter().sum::<f32>() - 1.0 + total).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_centred_bass_keeps_its_level() {
+        let control = enabled(6.0);
+        let mut processor = control.processor(44_100, 2);
+        let mut samples = vec![0.5; 44_100 * 2];
+        processor.process(&control, &mut samples);
+        assert!(samples[samples.len() - 2..].iter().all(|sample| (sample - 0.5).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_bypass_is_bit_transparent() {
+        let original: Vec<f32> = (0..4096).map(|index| ((index * 7919) % 2001) as f32 / 1000.0 - 1.0).collect();
+        let unchanged = |control: &CrossfeedControl, channels: usize| {
+            let mut samples = original.clone();
+            control.processor(44_100, channels).process(control, &mut samples);
+            samples == original
+        };
+
+        assert!(unchanged(&CrossfeedControl::default(), 2));
+        let control = enabled(6.0);
+        assert!(!unchanged(&control, 2));
+        assert!(unchanged(&control, 1));
+        assert!(unchanged(&control, 6));
+        control.set_source_channels(1);
+        assert!(unchanged(&control, 2));
+        control.set_source_channels(6);
+        assert!(unchanged(&control, 2));
+        control.set_source_channels(2);
+        assert!(!unchanged(&control, 2));
+    }
+}
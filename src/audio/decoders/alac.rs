@@ -9,22 +9,25 @@ use symphonia::core::formats::{FormatOptions, FormatReader};
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, Value};
 use symphonia::core::probe::Hint;
-use symphonia::core::units::{Time, TimeBase};
+use symphonia::core::units::TimeBase;
 
-use crate::audio::{AudioBuffer, AudioDecoder, AudioMetadata};
+use crate::audio::{AudioBuffer, AudioCodec, AudioDecoder, AudioMetadata, MetadataExtractor};
 use crate::error::DecodeError;
 
+use super::seek::SeekState;
+
 /// ALAC (Apple Lossless) audio decoder implementation using symphonia
 pub struct AlacDecoder {
     format_reader: Box<dyn FormatReader>,
     decoder: Box<dyn Decoder>,
     track_id: u32,
     metadata: AudioMetadata,
-    duration: Duration,
+    duration: Option<Duration>,
     sample_rate: u32,
     bit_depth: u16,
     channels: u16,
     time_base: TimeBase,
+    seek_state: SeekState,
 }
 
 impl AlacDecoder {
@@ -86,11 +89,9 @@ impl AlacDecoder {
         };
 
         // Calculate duration
-        let duration = if let (Some(n_frames), Some(sample_rate)) =
-            (track.codec_params.n_frames, track.codec_params.sample_rate) {
-            Duration::from_secs_f64(n_frames as f64 / sample_rate as f64)
-        } else {
-            Duration::from_secs(0) // Unknown duration
+        let duration = match (track.codec_params.n_frames, track.codec_params.sample_rate) {
+            (Some(n_frames), Some(sample_rate)) => Some(Duration::from_secs_f64(n_frames as f64 / sample_rate as f64)),
+            _ => None, // Unknown duration
         };
 
         // Extract metadata during initialization
@@ -109,6 +110,7 @@ impl AlacDecoder {
             bit_depth,
             channels,
             time_base,
+            seek_state: SeekState::default(),
         })
     }
 
@@ -116,14 +118,7 @@ impl AlacDecoder {
     fn extract_metadata_from_probed(
         mut probed_metadata: symphonia::core::probe::ProbedMetadata,
     ) -> AudioMetadata {
-        let mut metadata = AudioMetadata {
-            title: None,
-            artist: None,
-            album: None,
-            track_number: None,
-            year: None,
-            genre: None,
-        };
+        let mut metadata = AudioMetadata::new();
 
         // Try to get metadata from the probed metadata
         if let Some(probed_meta) = probed_metadata.get() {
@@ -155,18 +150,15 @@ impl AlacDecoder {
                             metadata.album = Some(album.clone());
                         }
                     }
-                    StandardTagKey::TrackNumber => {
-                        match &tag.value {
-                            Value::UnsignedInt(track_num) => {
-                                metadata.track_number = Some(*track_num as u32);
-                            }
-                            Value::String(track_str) => {
-                                if let Ok(track_num) = track_str.parse::<u32>() {
-                                    metadata.track_number = Some(track_num);
-                                }
-                            }
-                            _ => {}
-                        }
+                    StandardTagKey::TrackNumber
+                    | StandardTagKey::TrackTotal
+                    | StandardTagKey::DiscNumber
+                    | StandardTagKey::DiscTotal
+                    | StandardTagKey::Composer
+                    | StandardTagKey::Bpm
+                    | StandardTagKey::AlbumArtist
+                    | StandardTagKey::Lyrics => {
+                        MetadataExtractor::apply_common_tag(std_key, &tag.value, metadata);
                     }
                     StandardTagKey::Date => {
                         match &tag.value {
@@ -341,23 +333,18 @@ impl AudioDecoder for AlacDecoder {
             Ok(audio_buf) => {
                 // Convert to our AudioBuffer format
                 let buffer = Self::convert_audio_buffer(audio_buf)?;
-                Ok(Some(buffer))
+                match self.seek_state.trim(packet.ts(), self.time_base, buffer) {
+                    Some(buffer) => Ok(Some(buffer)),
+                    // Still short of the seek target
+                    None => self.decode_next(),
+                }
             }
             Err(e) => Err(DecodeError::DecodeFailed(format!("Failed to decode packet: {}", e)))
         }
     }
 
     fn seek(&mut self, position: Duration) -> Result<(), DecodeError> {
-        // Convert duration to time units
-        let seek_time = Time::new(
-            (position.as_secs_f64() * self.time_base.denom as f64) as u64,
-            self.time_base.denom as f64,
-        );
-
-        // Perform the seek
-        self.format_reader
-            .seek(symphonia::core::formats::SeekMode::Accurate, symphonia::core::formats::SeekTo::Time { time: seek_time, track_id: Some(self.track_id) })
-            .map_err(|e| DecodeError::SeekError(format!("Seek failed: {}", e)))?;
+        self.seek_state.seek(self.format_reader.as_mut(), self.track_id, self.time_base, position)?;
 
         // Reset the decoder state after seeking
         self.decoder.reset();
@@ -365,11 +352,15 @@ impl AudioDecoder for AlacDecoder {
         Ok(())
     }
 
+    fn supports_seek(&self) -> bool {
+        self.seek_state.supports_seek()
+    }
+
     fn metadata(&self) -> &AudioMetadata {
         &self.metadata
     }
 
-    fn duration(&self) -> Duration {
+    fn duration(&self) -> Option<Duration> {
         self.duration
     }
 
@@ -384,6 +375,10 @@ impl AudioDecoder for AlacDecoder {
     fn channels(&self) -> u16 {
         self.channels
     }
+
+    fn codec(&self) -> AudioCodec {
+        AudioCodec::Alac
+    }
 }
 
 impl AlacDecoder {
@@ -394,10 +389,128 @@ impl AlacDecoder {
 }
 
 #[cfg(test)]
-mod tests {
+pub(super) mod tests {
     use super::*;
     use tempfile::NamedTempFile;
 
+    /// Samples per ALAC frame in the test files
+    pub(crate) const TEST_FRAME_LENGTH: usize = 1024;
+
+    fn atom(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut atom = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        atom.extend(kind);
+        atom.extend(body);
+        atom
+    }
+
+    /// An atom with a zero version and flags before `body`
+    fn full_atom(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        atom(kind, &[&[0; 4], body].concat())
+    }
+
+    /// An uncompressed ALAC frame holding one mono element
+    fn alac_frame(samples: impl Iterator<Item = i16>) -> Vec<u8> {
+        let mut bits = String::new();
+        // Single channel element, instance 0, no partial frame or shift, uncompressed
+        bits.push_str("000");
+        bits.push_str(&"0".repeat(4 + 12 + 1 + 2));
+        bits.push('1');
+        for sample in samples {
+            bits.push_str(&format!("{:016b}", sample as u16));
+        }
+        // End element
+        bits.push_str("111");
+        while !bits.len().is_multiple_of(8) {
+            bits.push('0');
+        }
+        bits.as_bytes()
+            .chunks(8)
+            .map(|byte| u8::from_str_radix(std::str::from_utf8(byte).unwrap(), 2).unwrap())
+            .collect()
+    }
+
+    /// An M4A file of 48 kHz mono 16-bit ALAC, each sample holding its frame index
+    pub(crate) fn alac_m4a_bytes(frames: usize) -> Vec<u8> {
+        let total = (frames * TEST_FRAME_LENGTH) as u32;
+        let packets: Vec<Vec<u8>> = (0..frames)
+            .map(|frame| alac_frame((frame * TEST_FRAME_LENGTH..(frame + 1) * TEST_FRAME_LENGTH).map(|sample| sample as i16)))
+            .collect();
+
+        let ftyp = atom(b"ftyp", b"M4A \0\0\0\0M4A isom");
+        let moov = |mdat_offset: u32| {
+            let mut cookie = (TEST_FRAME_LENGTH as u32).to_be_bytes().to_vec();
+            // Version, bit depth, pb, mb, kb, channels, max run, max frame bytes, bit rate, rate
+            cookie.extend([0, 16, 40, 10, 14, 1]);
+            cookie.extend(255u16.to_be_bytes());
+            cookie.extend([0; 8]);
+            cookie.extend(48_000u32.to_be_bytes());
+
+            let mut entry = vec![0; 6];
+            entry.extend(1u16.to_be_bytes());
+            entry.extend([0; 8]);
+            entry.extend(1u16.to_be_bytes());
+            entry.extend(16u16.to_be_bytes());
+            entry.extend([0; 4]);
+            entry.extend((48_000u32 << 16).to_be_bytes());
+            entry.extend(full_atom(b"alac", &cookie));
+
+            let stsd = full_atom(b"stsd", &[&1u32.to_be_bytes()[..], &atom(b"alac", &entry)].concat());
+            let stts = full_atom(b"stts", &[1, frames as u32, TEST_FRAME_LENGTH as u32].map(u32::to_be_bytes).concat());
+            let stsc = full_atom(b"stsc", &[1, 1, frames as u32, 1].map(u32::to_be_bytes).concat());
+            let mut sizes = [0, frames as u32].map(u32::to_be_bytes).concat();
+            sizes.extend(packets.iter().flat_map(|packet| (packet.len() as u32).to_be_bytes()));
+            let stsz = full_atom(b"stsz", &sizes);
+            let stco = full_atom(b"stco", &[1, mdat_offset].map(u32::to_be_bytes).concat());
+            let stbl = atom(b"stbl", &[stsd, stts, stsc, stsz, stco].concat());
+
+            let mut mdhd = vec![0; 8];
+            mdhd.extend(48_000u32.to_be_bytes());
+            mdhd.extend(total.to_be_bytes());
+            mdhd.extend([0; 4]);
+            let mut hdlr = vec![0; 4];
+            hdlr.extend(b"soun");
+            hdlr.extend([0; 12]);
+            let mdia = atom(b"mdia", &[full_atom(b"mdhd", &mdhd), full_atom(b"hdlr", &hdlr), atom(b"minf", &stbl)].concat());
+
+            let mut tkhd = vec![0; 8];
+            tkhd.extend(1u32.to_be_bytes());
+            tkhd.extend([0; 4]);
+            tkhd.extend(total.to_be_bytes());
+            tkhd.extend([0; 60]);
+            let trak = atom(b"trak", &[full_atom(b"tkhd", &tkhd), mdia].concat());
+
+            let mut mvhd = vec![0; 8];
+            mvhd.extend(48_000u32.to_be_bytes());
+            mvhd.extend(total.to_be_bytes());
+            mvhd.extend(0x0001_0000u32.to_be_bytes());
+            mvhd.extend(0x0100u16.to_be_bytes());
+            mvhd.extend([0; 70]);
+            mvhd.extend(2u32.to_be_bytes());
+            atom(b"moov", &[full_atom(b"mvhd", &mvhd), trak].concat())
+        };
+
+        // The chunk offset doesn't change the size of the moov atom
+        let mdat_offset = (ftyp.len() + moov(0).len() + 8) as u32;
+        [ftyp, moov(mdat_offset), atom(b"mdat", &packets.concat())].concat()
+    }
+
+    #[test]
+    fn test_seek_is_sample_accurate() {
+        let mut file = tempfile::Builder::new().suffix(".m4a").tempfile().unwrap();
+        std::io::Write::write_all(&mut file, &alac_m4a_bytes(10)).unwrap();
+        let mut decoder = AlacDecoder::new(file.path()).unwrap();
+        assert!(decoder.supports_seek());
+
+        // 150 ms is sample 7200, partway into the eighth frame
+        decoder.seek(Duration::from_millis(150)).unwrap();
+        let mut samples = Vec::new();
+        while let Some(buffer) = decoder.decode_next().unwrap() {
+            samples.extend(buffer.samples);
+        }
+        assert_eq!(samples[0], 7200.0 / 32768.0);
+        assert_eq!(samples.len(), 10 * TEST_FRAME_LENGTH - 7200);
+    }
+
     #[test]
     fn test_alac_decoder_new_with_nonexistent_file() {
         let result = AlacDecoder::new("/nonexistent/file.m4a");
@@ -6,27 +6,30 @@ use symphonia::core::audio::AudioBufferRef;
 use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_FLAC};
 use symphonia::core::errors::Error as SymphoniaError;
 use symphonia::core::formats::{FormatOptions, FormatReader};
-use symphonia::core::io::MediaSourceStream;
+use symphonia::core::io::{MediaSource, MediaSourceStream};
 use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, Value};
 use symphonia::core::probe::Hint;
-use symphonia::core::units::{Time, TimeBase};
+use symphonia::core::units::TimeBase;
 
-use crate::audio::{AudioBuffer, AudioDecoder, AudioMetadata, MetadataExtractor};
+use crate::audio::{AudioBuffer, AudioCodec, AudioDecoder, AudioMetadata, MetadataExtractor};
 use crate::audio::performance::AudioPerformanceProfiler;
 use crate::audio::memory::{HighResBufferAllocator, ManagedAudioBuffer};
 use crate::error::DecodeError;
 
+use super::seek::SeekState;
+
 /// FLAC audio decoder implementation using symphonia with performance optimizations
 pub struct FlacDecoder {
     format_reader: Box<dyn FormatReader>,
     decoder: Box<dyn Decoder>,
     track_id: u32,
     metadata: AudioMetadata,
-    duration: Duration,
+    duration: Option<Duration>,
     sample_rate: u32,
     bit_depth: u16,
     channels: u16,
     time_base: TimeBase,
+    seek_state: SeekState,
 
     // Performance optimizations
     performance_profiler: Option<std::sync::Arc<AudioPerformanceProfiler>>,
@@ -53,18 +56,28 @@ impl FlacDecoder {
         let file = File::open(&path).map_err(|e| {
             DecodeError::DecodeFailed(format!("Failed to open file: {}", e))
         })?;
+        let extension = path.as_ref().extension().and_then(|extension| extension.to_str());
+        Self::with_source(Box::new(file), extension, profiler, allocator)
+    }
+
+    /// Create a FLAC decoder reading from `source`, e.g. a network stream; `extension` hints the container
+    pub fn from_source(source: Box<dyn MediaSource>, extension: Option<&str>) -> Result<Self, DecodeError> {
+        Self::with_source(source, extension, None, None)
+    }
 
-        let media_source = MediaSourceStream::new(
-            Box::new(file),
-            Default::default(),
-        );
+    fn with_source(
+        source: Box<dyn MediaSource>,
+        extension: Option<&str>,
+        profiler: Option<std::sync::Arc<AudioPerformanceProfiler>>,
+        allocator: Option<std::sync::Arc<HighResBufferAllocator>>,
+    ) -> Result<Self, DecodeError> {
+        let seekable = source.is_seekable();
+        let media_source = MediaSourceStream::new(source, Default::default());
 
         // Create a hint to help the format registry guess the format
         let mut hint = Hint::new();
-        if let Some(extension) = path.as_ref().extension() {
-            if let Some(ext_str) = extension.to_str() {
-                hint.with_extension(ext_str);
-            }
+        if let Some(extension) = extension {
+            hint.with_extension(extension);
         }
 
         // Probe the media source for a format
@@ -106,11 +119,9 @@ impl FlacDecoder {
         };
 
         // Calculate duration
-        let duration = if let (Some(n_frames), Some(sample_rate)) =
-            (track.codec_params.n_frames, track.codec_params.sample_rate) {
-            Duration::from_secs_f64(n_frames as f64 / sample_rate as f64)
-        } else {
-            Duration::from_secs(0) // Unknown duration
+        let duration = match (track.codec_params.n_frames, track.codec_params.sample_rate) {
+            (Some(n_frames), Some(sample_rate)) => Some(Duration::from_secs_f64(n_frames as f64 / sample_rate as f64)),
+            _ => None, // Unknown duration
         };
 
         // Extract metadata during initialization when we have mutable access
@@ -144,6 +155,7 @@ impl FlacDecoder {
             bit_depth,
             channels,
             time_base,
+            seek_state: SeekState::default().with_seekable(seekable),
             performance_profiler: profiler,
             buffer_allocator: allocator,
             is_high_resolution,
@@ -156,14 +168,7 @@ impl FlacDecoder {
     fn extract_metadata_from_probed(
         mut probed_metadata: symphonia::core::probe::ProbedMetadata,
     ) -> AudioMetadata {
-        let mut metadata = AudioMetadata {
-            title: None,
-            artist: None,
-            album: None,
-            track_number: None,
-            year: None,
-            genre: None,
-        };
+        let mut metadata = AudioMetadata::new();
 
         // Try to get metadata from the probed metadata
         if let Some(probed_meta) = probed_metadata.get() {
@@ -195,18 +200,15 @@ impl FlacDecoder {
                             metadata.album = Some(album.clone());
                         }
                     }
-                    StandardTagKey::TrackNumber => {
-                        match &tag.value {
-                            Value::UnsignedInt(track_num) => {
-                                metadata.track_number = Some(*track_num as u32);
-                            }
-                            Value::String(track_str) => {
-                                if let Ok(track_num) = track_str.parse::<u32>() {
-                                    metadata.track_number = Some(track_num);
-                                }
-                            }
-                            _ => {}
-                        }
+                    StandardTagKey::TrackNumber
+                    | StandardTagKey::TrackTotal
+                    | StandardTagKey::DiscNumber
+                    | StandardTagKey::DiscTotal
+                    | StandardTagKey::Composer
+                    | StandardTagKey::Bpm
+                    | StandardTagKey::AlbumArtist
+                    | StandardTagKey::Lyrics => {
+                        MetadataExtractor::apply_common_tag(std_key, &tag.value, metadata);
                     }
                     StandardTagKey::Date => {
                         match &tag.value {
@@ -471,7 +473,11 @@ impl AudioDecoder for FlacDecoder {
                 } else {
                     Self::convert_audio_buffer(audio_buf)?
                 };
-                Ok(Some(buffer))
+                match self.seek_state.trim(packet.ts(), self.time_base, buffer) {
+                    Some(buffer) => Ok(Some(buffer)),
+                    // Still short of the seek target
+                    None => return self.decode_next(),
+                }
             }
             Err(e) => Err(DecodeError::DecodeFailed(format!("Failed to decode packet: {}", e)))
         };
@@ -501,16 +507,7 @@ impl AudioDecoder for FlacDecoder {
     }
 
     fn seek(&mut self, position: Duration) -> Result<(), DecodeError> {
-        // Convert duration to time units
-        let seek_time = Time::new(
-            (position.as_secs_f64() * self.time_base.denom as f64) as u64,
-            self.time_base.denom as f64,
-        );
-
-        // Perform the seek
-        self.format_reader
-            .seek(symphonia::core::formats::SeekMode::Accurate, symphonia::core::formats::SeekTo::Time { time: seek_time, track_id: Some(self.track_id) })
-            .map_err(|e| DecodeError::SeekError(format!("Seek failed: {}", e)))?;
+        self.seek_state.seek(self.format_reader.as_mut(), self.track_id, self.time_base, position)?;
 
         // Reset the decoder state after seeking
         self.decoder.reset();
@@ -518,11 +515,15 @@ impl AudioDecoder for FlacDecoder {
         Ok(())
     }
 
+    fn supports_seek(&self) -> bool {
+        self.seek_state.supports_seek()
+    }
+
     fn metadata(&self) -> &AudioMetadata {
         &self.metadata
     }
 
-    fn duration(&self) -> Duration {
+    fn duration(&self) -> Option<Duration> {
         self.duration
     }
 
@@ -537,6 +538,10 @@ impl AudioDecoder for FlacDecoder {
     fn channels(&self) -> u16 {
         self.channels
     }
+
+    fn codec(&self) -> AudioCodec {
+        AudioCodec::Flac
+    }
 }
 
 impl FlacDecoder {
@@ -549,14 +554,106 @@ impl FlacDecoder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audio::test_files;
     use tempfile::NamedTempFile;
 
-    // Helper function to create a minimal FLAC file for testing
-    // Note: In a real implementation, you'd want to use actual FLAC test files
+    const TEST_BLOCK_SIZE: usize = 1024;
+    const TEST_BLOCKS: usize = 10;
+
+    /// A 48 kHz mono 16-bit FLAC file of verbatim frames, each sample holding its frame index
     fn create_test_flac_file() -> Result<NamedTempFile, Box<dyn std::error::Error>> {
-        // For now, we'll skip this test since creating a valid FLAC file programmatically
-        // is complex. In a real implementation, you'd have test FLAC files in a test directory.
-        Err("Test FLAC file creation not implemented".into())
+        create_tagged_flac_file(&[])
+    }
+
+    /// The test FLAC file with a VORBIS_COMMENT block holding `KEY=value` comments
+    fn create_tagged_flac_file(comments: &[&str]) -> Result<NamedTempFile, Box<dyn std::error::Error>> {
+        let total = (TEST_BLOCK_SIZE * TEST_BLOCKS) as u64;
+        let streaminfo = test_files::flac_streaminfo(TEST_BLOCK_SIZE as u16, 48_000, 1, 16, total, [0; 16]);
+        let mut bytes = b"fLaC".to_vec();
+        // STREAMINFO is the last metadata block unless there are comments
+        bytes.extend(test_files::flac_block(0, comments.is_empty(), &streaminfo));
+
+        if !comments.is_empty() {
+            let mut block = 0u32.to_le_bytes().to_vec();
+            block.extend((comments.len() as u32).to_le_bytes());
+            for comment in comments {
+                block.extend((comment.len() as u32).to_le_bytes());
+                block.extend(comment.as_bytes());
+            }
+            bytes.extend(test_files::flac_block(4, true, &block));
+        }
+
+        for block in 0..TEST_BLOCKS {
+            // Fixed block size read from the header end, rate from STREAMINFO, mono 16-bit
+            let mut header = vec![0xFF, 0xF8, 0x70, 0x08, block as u8];
+            header.extend(((TEST_BLOCK_SIZE - 1) as u16).to_be_bytes());
+            // Verbatim subframe
+            let mut subframe = vec![0x02];
+            for sample in block * TEST_BLOCK_SIZE..(block + 1) * TEST_BLOCK_SIZE {
+                subframe.extend((sample as i16).to_be_bytes());
+            }
+            bytes.extend(test_files::flac_frame(&header, &subframe));
+        }
+
+        let mut file = tempfile::Builder::new().suffix(".flac").tempfile()?;
+        std::io::Write::write_all(&mut file, &bytes)?;
+        Ok(file)
+    }
+
+    #[test]
+    fn test_lyrics_comment_is_read_into_the_track() {
+        let file = create_tagged_flac_file(&["TITLE=Sung", "LYRICS=[00:01.00]First line\n[00:02.00]Second line"]).unwrap();
+        let track = crate::queue::QueueManagerImpl::create_track_info(file.path()).unwrap();
+        assert_eq!(track.metadata.title.as_deref(), Some("Sung"));
+        assert_eq!(track.metadata.lyrics.as_deref(), Some("[00:01.00]First line\n[00:02.00]Second line"));
+    }
+
+    #[test]
+    fn test_full_report() {
+        let file = create_tagged_flac_file(&["TITLE=Detailed", "REPLAYGAIN_TRACK_GAIN=-6.20 dB"]).unwrap();
+        let mut bytes = std::fs::read(file.path()).unwrap();
+        // STREAMINFO's MD5, then a vendor string at the start of the comment block
+        bytes[26..42].copy_from_slice(&(1..=16).collect::<Vec<u8>>());
+        let vendor = b"reference libFLAC 1.4.3";
+        let size = u32::from_be_bytes([0, bytes[43], bytes[44], bytes[45]]) + vendor.len() as u32;
+        bytes[43..46].copy_from_slice(&size.to_be_bytes()[1..]);
+        bytes[46..50].copy_from_slice(&(vendor.len() as u32).to_le_bytes());
+        bytes.splice(50..50, vendor.iter().copied());
+        std::fs::write(file.path(), &bytes).unwrap();
+
+        let report = MetadataExtractor::full_report(file.path()).unwrap();
+        assert_eq!(report.container, "FLAC");
+        assert_eq!(report.codec, "Free Lossless Audio Codec");
+        assert_eq!((report.sample_rate, report.bit_depth, report.channels), (Some(48_000), Some(16), Some(1)));
+        assert_eq!(report.duration.as_millis(), 213);
+        assert_eq!(report.file_size, bytes.len() as u64);
+        assert_eq!(report.encoder.as_deref(), Some("reference libFLAC 1.4.3"));
+        assert_eq!(report.md5.as_deref(), Some("0102030405060708090a0b0c0d0e0f10"));
+        assert_eq!(report.metadata.title.as_deref(), Some("Detailed"));
+        assert_eq!(report.metadata.extra_tags, vec![("REPLAYGAIN_TRACK_GAIN".to_string(), "-6.20 dB".to_string())]);
+    }
+
+    #[test]
+    fn test_seek_is_sample_accurate() {
+        let file = create_test_flac_file().unwrap();
+        let mut decoder = FlacDecoder::new(file.path()).unwrap();
+        assert!(decoder.supports_seek());
+        assert_eq!((decoder.duration().unwrap().as_secs_f64() * 48_000.0).round() as usize, TEST_BLOCK_SIZE * TEST_BLOCKS);
+
+        // 150 ms is sample 7200, partway into the eighth frame
+        decoder.seek(Duration::from_millis(150)).unwrap();
+        let mut samples = Vec::new();
+        while let Some(buffer) = decoder.decode_next().unwrap() {
+            samples.extend(buffer.samples);
+        }
+        assert_eq!(samples[0], 7200.0 / 32768.0);
+        assert_eq!(samples.len(), TEST_BLOCK_SIZE * TEST_BLOCKS - 7200);
+
+        // Back to a frame boundary: 64 ms is the start of the fourth frame
+        decoder.seek(Duration::from_millis(64)).unwrap();
+        let buffer = decoder.decode_next().unwrap().unwrap();
+        assert_eq!(buffer.samples[0], 3072.0 / 32768.0);
+        assert_eq!(buffer.frames, TEST_BLOCK_SIZE);
     }
 
     #[test]
@@ -580,6 +677,7 @@ mod tests {
             track_number: None,
             year: None,
             genre: None,
+            ..Default::default()
         };
 
         assert!(metadata.title.is_none());
@@ -599,6 +697,7 @@ mod tests {
             track_number: Some(1),
             year: Some(2023),
             genre: Some("Test Genre".to_string()),
+            ..Default::default()
         };
 
         assert_eq!(metadata.title, Some("Test Title".to_string()));
@@ -6,25 +6,29 @@ use symphonia::core::audio::AudioBufferRef;
 use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_AAC, CODEC_TYPE_ALAC};
 use symphonia::core::errors::Error as SymphoniaError;
 use symphonia::core::formats::{FormatOptions, FormatReader};
-use symphonia::core::io::MediaSourceStream;
+use symphonia::core::io::{MediaSource, MediaSourceStream};
 use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, Value};
 use symphonia::core::probe::Hint;
-use symphonia::core::units::{Time, TimeBase};
+use symphonia::core::units::TimeBase;
 
-use crate::audio::{AudioBuffer, AudioDecoder, AudioMetadata};
+use crate::audio::{AudioBuffer, AudioCodec, AudioDecoder, AudioMetadata, MetadataExtractor};
 use crate::error::DecodeError;
 
+use super::seek::SeekState;
+
 /// M4A/MP4 audio decoder implementation (supports AAC and ALAC via Symphonia)
 pub struct M4aDecoder {
     format_reader: Box<dyn FormatReader>,
     decoder: Box<dyn Decoder>,
     track_id: u32,
     metadata: AudioMetadata,
-    duration: Duration,
+    duration: Option<Duration>,
     sample_rate: u32,
     bit_depth: u16,
     channels: u16,
     time_base: TimeBase,
+    seek_state: SeekState,
+    codec: AudioCodec,
 }
 
 impl M4aDecoder {
@@ -33,18 +37,19 @@ impl M4aDecoder {
         let file = File::open(&path).map_err(|e| {
             DecodeError::DecodeFailed(format!("Failed to open file: {}", e))
         })?;
+        let extension = path.as_ref().extension().and_then(|extension| extension.to_str());
+        Self::from_source(Box::new(file), extension)
+    }
 
-        let media_source = MediaSourceStream::new(
-            Box::new(file),
-            Default::default(),
-        );
+    /// Create an M4A/MP4 or ADTS AAC decoder reading from `source`, e.g. a network stream; `extension` hints the container
+    pub fn from_source(source: Box<dyn MediaSource>, extension: Option<&str>) -> Result<Self, DecodeError> {
+        let seekable = source.is_seekable();
+        let media_source = MediaSourceStream::new(source, Default::default());
 
         // Create a hint to help the format registry guess the format
         let mut hint = Hint::new();
-        if let Some(extension) = path.as_ref().extension() {
-            if let Some(ext_str) = extension.to_str() {
-                hint.with_extension(ext_str);
-            }
+        if let Some(extension) = extension {
+            hint.with_extension(extension);
         }
 
         // Probe the media source for a format (isomp4/m4a)
@@ -66,6 +71,11 @@ impl M4aDecoder {
             })?;
 
         let track_id = track.id;
+        let codec = if track.codec_params.codec == CODEC_TYPE_ALAC {
+            AudioCodec::Alac
+        } else {
+            AudioCodec::Aac
+        };
 
         // Create a decoder for the track
         let decoder = symphonia::default::get_codecs()
@@ -85,11 +95,9 @@ impl M4aDecoder {
         };
 
         // Calculate duration
-        let duration = if let (Some(n_frames), Some(sample_rate)) =
-            (track.codec_params.n_frames, track.codec_params.sample_rate) {
-            Duration::from_secs_f64(n_frames as f64 / sample_rate as f64)
-        } else {
-            Duration::from_secs(0) // Unknown duration
+        let duration = match (track.codec_params.n_frames, track.codec_params.sample_rate) {
+            (Some(n_frames), Some(sample_rate)) => Some(Duration::from_secs_f64(n_frames as f64 / sample_rate as f64)),
+            _ => None, // Unknown duration
         };
 
         // Extract metadata during initialization (iTunes/MP4-style tags)
@@ -108,6 +116,12 @@ impl M4aDecoder {
             bit_depth,
             channels,
             time_base,
+            // AAC needs the previous frame to overlap with; ALAC frames stand alone
+            seek_state: match codec {
+                AudioCodec::Aac => SeekState::with_preroll(Duration::from_secs_f64(2048.0 / sample_rate as f64)),
+                _ => SeekState::default(),
+            }.with_seekable(seekable),
+            codec,
         })
     }
 
@@ -115,14 +129,7 @@ impl M4aDecoder {
     fn extract_metadata_from_probed(
         mut probed_metadata: symphonia::core::probe::ProbedMetadata,
     ) -> AudioMetadata {
-        let mut metadata = AudioMetadata {
-            title: None,
-            artist: None,
-            album: None,
-            track_number: None,
-            year: None,
-            genre: None,
-        };
+        let mut metadata = AudioMetadata::new();
 
         if let Some(probed_meta) = probed_metadata.get() {
             if let Some(metadata_rev) = probed_meta.current() {
@@ -153,18 +160,15 @@ impl M4aDecoder {
                             metadata.album = Some(album.clone());
                         }
                     }
-                    StandardTagKey::TrackNumber => {
-                        match &tag.value {
-                            Value::UnsignedInt(track_num) => {
-                                metadata.track_number = Some(*track_num as u32);
-                            }
-                            Value::String(track_str) => {
-                                if let Ok(track_num) = track_str.parse::<u32>() {
-                                    metadata.track_number = Some(track_num);
-                                }
-                            }
-                            _ => {}
-                        }
+                    StandardTagKey::TrackNumber
+                    | StandardTagKey::TrackTotal
+                    | StandardTagKey::DiscNumber
+                    | StandardTagKey::DiscTotal
+                    | StandardTagKey::Composer
+                    | StandardTagKey::Bpm
+                    | StandardTagKey::AlbumArtist
+                    | StandardTagKey::Lyrics => {
+                        MetadataExtractor::apply_common_tag(std_key, &tag.value, metadata);
                     }
                     StandardTagKey::Date => {
                         match &tag.value {
@@ -340,29 +344,18 @@ impl AudioDecoder for M4aDecoder {
             Ok(audio_buf) => {
                 // Convert to our AudioBuffer format
                 let buffer = Self::convert_audio_buffer(audio_buf)?;
-                Ok(Some(buffer))
+                match self.seek_state.trim(packet.ts(), self.time_base, buffer) {
+                    Some(buffer) => Ok(Some(buffer)),
+                    // Still short of the seek target
+                    None => self.decode_next(),
+                }
             }
             Err(e) => Err(DecodeError::DecodeFailed(format!("Failed to decode packet: {}", e))),
         }
     }
 
     fn seek(&mut self, position: Duration) -> Result<(), DecodeError> {
-        // Convert duration to time units
-        let seek_time = Time::new(
-            (position.as_secs_f64() * self.time_base.denom as f64) as u64,
-            self.time_base.denom as f64,
-        );
-
-        // Perform the seek
-        self.format_reader
-            .seek(
-                symphonia::core::formats::SeekMode::Accurate,
-                symphonia::core::formats::SeekTo::Time {
-                    time: seek_time,
-                    track_id: Some(self.track_id),
-                },
-            )
-            .map_err(|e| DecodeError::SeekError(format!("Seek failed: {}", e)))?;
+        self.seek_state.seek(self.format_reader.as_mut(), self.track_id, self.time_base, position)?;
 
         // Reset the decoder state after seeking
         self.decoder.reset();
@@ -370,11 +363,15 @@ impl AudioDecoder for M4aDecoder {
         Ok(())
     }
 
+    fn supports_seek(&self) -> bool {
+        self.seek_state.supports_seek()
+    }
+
     fn metadata(&self) -> &AudioMetadata {
         &self.metadata
     }
 
-    fn duration(&self) -> Duration {
+    fn duration(&self) -> Option<Duration> {
         self.duration
     }
 
@@ -389,6 +386,10 @@ impl AudioDecoder for M4aDecoder {
     fn channels(&self) -> u16 {
         self.channels
     }
+
+    fn codec(&self) -> AudioCodec {
+        self.codec
+    }
 }
 
 impl M4aDecoder {
@@ -397,3 +398,39 @@ impl M4aDecoder {
         self.channels
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::decoders::alac::tests::{alac_m4a_bytes, TEST_FRAME_LENGTH};
+
+    #[test]
+    fn test_full_report() {
+        let mut file = tempfile::Builder::new().suffix(".m4a").tempfile().unwrap();
+        std::io::Write::write_all(&mut file, &alac_m4a_bytes(10)).unwrap();
+
+        let report = MetadataExtractor::full_report(file.path()).unwrap();
+        assert_eq!(report.container, "MPEG-4");
+        assert_eq!(report.codec, "Apple Lossless Audio Codec");
+        assert_eq!(report.sample_rate, Some(48_000));
+        assert_eq!((report.duration.as_secs_f64() * 48_000.0).round() as usize, 10 * TEST_FRAME_LENGTH);
+        assert_eq!(report.md5, None);
+    }
+
+    #[test]
+    fn test_seek_is_sample_accurate() {
+        let mut file = tempfile::Builder::new().suffix(".m4a").tempfile().unwrap();
+        std::io::Write::write_all(&mut file, &alac_m4a_bytes(10)).unwrap();
+        let mut decoder = M4aDecoder::new(file.path()).unwrap();
+        assert_eq!(decoder.codec(), AudioCodec::Alac);
+        assert!(decoder.supports_seek());
+
+        // Seeking back and forth always lands on the requested sample
+        for (position, sample) in [(Duration::from_millis(150), 7200), (Duration::from_millis(10), 480)] {
+            decoder.seek(position).unwrap();
+            let buffer = decoder.decode_next().unwrap().unwrap();
+            assert_eq!(buffer.samples[0], sample as f32 / 32768.0);
+            assert_eq!(buffer.frames, TEST_FRAME_LENGTH - sample % TEST_FRAME_LENGTH);
+        }
+    }
+}
@@ -4,6 +4,8 @@ pub mod alac;
 pub mod mp3;
 pub mod ogg;
 pub mod m4a;
+pub mod wavpack;
+mod seek;
 
 pub use flac::FlacDecoder;
 pub use wav::WavDecoder;
@@ -11,3 +13,4 @@ pub use alac::AlacDecoder;
 pub use mp3::Mp3Decoder;
 pub use ogg::OggDecoder;
 pub use m4a::M4aDecoder;
+pub use wavpack::WavPackDecoder;
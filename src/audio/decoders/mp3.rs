@@ -1,4 +1,5 @@
 use std::fs::File;
+use std::io::{BufReader, Read};
 use std::path::Path;
 use std::time::Duration;
 
@@ -6,25 +7,30 @@ use symphonia::core::audio::AudioBufferRef;
 use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_MP3};
 use symphonia::core::errors::Error as SymphoniaError;
 use symphonia::core::formats::{FormatOptions, FormatReader};
-use symphonia::core::io::MediaSourceStream;
+use symphonia::core::io::{MediaSource, MediaSourceStream};
 use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, Value};
 use symphonia::core::probe::Hint;
-use symphonia::core::units::{Time, TimeBase};
+use symphonia::core::units::TimeBase;
 
-use crate::audio::{AudioBuffer, AudioDecoder, AudioMetadata};
+use crate::audio::{AudioBuffer, AudioCodec, AudioDecoder, AudioMetadata, MetadataExtractor};
 use crate::error::DecodeError;
 
+use super::seek::SeekState;
+
 /// MP3 audio decoder implementation using symphonia
 pub struct Mp3Decoder {
     format_reader: Box<dyn FormatReader>,
     decoder: Box<dyn Decoder>,
     track_id: u32,
     metadata: AudioMetadata,
-    duration: Duration,
+    duration: Option<Duration>,
     sample_rate: u32,
     bit_depth: u16,
     channels: u16,
     time_base: TimeBase,
+    seek_state: SeekState,
+    encoder_delay: u32,
+    encoder_padding: u32,
 }
 
 impl Mp3Decoder {
@@ -33,18 +39,36 @@ impl Mp3Decoder {
         let file = File::open(&path).map_err(|e| {
             DecodeError::DecodeFailed(format!("Failed to open file: {}", e))
         })?;
+        let extension = path.as_ref().extension().and_then(|extension| extension.to_str());
+        let mut decoder = Self::from_source(Box::new(file), extension)?;
+
+        // Frames Symphonia skips (TCMP) or strips the description from (COMM iTunSMPB)
+        let raw_frames = File::open(&path)
+            .and_then(|file| RawId3Frames::read(&mut BufReader::new(file)))
+            .unwrap_or_default();
+        decoder.metadata.compilation = raw_frames.compilation;
+        if raw_frames.tagged {
+            // Symphonia drops COMM descriptions, so iTunes data comments look like real ones
+            decoder.metadata.comment = raw_frames.comment;
+        }
+        let gapless = raw_frames.itunsmpb.unwrap_or_default();
+        decoder.encoder_delay = gapless.encoder_delay;
+        decoder.encoder_padding = gapless.encoder_padding;
+        Ok(decoder)
+    }
 
-        let media_source = MediaSourceStream::new(
-            Box::new(file),
-            Default::default(),
-        );
+    /// Create an MP3 decoder reading from `source`, e.g. a network stream; `extension` hints the container
+    ///
+    /// The raw ID3 frames `new` reads need a second pass over the file, so a source goes without
+    /// the compilation flag and iTunes gapless data.
+    pub fn from_source(source: Box<dyn MediaSource>, extension: Option<&str>) -> Result<Self, DecodeError> {
+        let seekable = source.is_seekable();
+        let media_source = MediaSourceStream::new(source, Default::default());
 
         // Create a hint to help the format registry guess the format
         let mut hint = Hint::new();
-        if let Some(extension) = path.as_ref().extension() {
-            if let Some(ext_str) = extension.to_str() {
-                hint.with_extension(ext_str);
-            }
+        if let Some(extension) = extension {
+            hint.with_extension(extension);
         }
 
         // Probe the media source for a format
@@ -86,12 +110,9 @@ impl Mp3Decoder {
         };
 
         // Calculate duration - MP3 duration can be estimated from bitrate and file size
-        let duration = if let (Some(n_frames), Some(sample_rate)) =
-            (track.codec_params.n_frames, track.codec_params.sample_rate) {
-            Duration::from_secs_f64(n_frames as f64 / sample_rate as f64)
-        } else {
-            // For MP3, we might need to estimate duration differently
-            Duration::from_secs(0) // Unknown duration
+        let duration = match (track.codec_params.n_frames, track.codec_params.sample_rate) {
+            (Some(n_frames), Some(sample_rate)) => Some(Duration::from_secs_f64(n_frames as f64 / sample_rate as f64)),
+            _ => None, // Unknown duration
         };
 
         // Extract metadata during initialization (MP3 often has ID3 tags)
@@ -110,21 +131,27 @@ impl Mp3Decoder {
             bit_depth,
             channels,
             time_base,
+            seek_state: SeekState::default().with_seekable(seekable),
+            encoder_delay: 0,
+            encoder_padding: 0,
         })
     }
 
+    /// Encoder delay (silent lead-in frames) from the iTunSMPB tag
+    pub fn encoder_delay_frames(&self) -> u32 {
+        self.encoder_delay
+    }
+
+    /// Encoder padding (silent trailing frames) from the iTunSMPB tag
+    pub fn encoder_padding_frames(&self) -> u32 {
+        self.encoder_padding
+    }
+
     /// Extract metadata from probed metadata during initialization
     fn extract_metadata_from_probed(
         mut probed_metadata: symphonia::core::probe::ProbedMetadata,
     ) -> AudioMetadata {
-        let mut metadata = AudioMetadata {
-            title: None,
-            artist: None,
-            album: None,
-            track_number: None,
-            year: None,
-            genre: None,
-        };
+        let mut metadata = AudioMetadata::new();
 
         // Try to get metadata from the probed metadata (ID3 tags for MP3)
         if let Some(probed_meta) = probed_metadata.get() {
@@ -156,17 +183,26 @@ impl Mp3Decoder {
                             metadata.album = Some(album.clone());
                         }
                     }
-                    StandardTagKey::TrackNumber => {
-                        match &tag.value {
-                            Value::UnsignedInt(track_num) => {
-                                metadata.track_number = Some(*track_num as u32);
-                            }
-                            Value::String(track_str) => {
-                                if let Ok(track_num) = track_str.parse::<u32>() {
-                                    metadata.track_number = Some(track_num);
-                                }
+                    StandardTagKey::TrackNumber
+                    | StandardTagKey::TrackTotal
+                    | StandardTagKey::DiscNumber
+                    | StandardTagKey::DiscTotal
+                    | StandardTagKey::Composer
+                    | StandardTagKey::Bpm
+                    | StandardTagKey::AlbumArtist => {
+                        MetadataExtractor::apply_common_tag(std_key, &tag.value, metadata);
+                    }
+                    StandardTagKey::Comment => {
+                        // Keep the first comment (replaced by the raw ID3v2 pass when tagged)
+                        if let Value::String(comment) = &tag.value {
+                            if metadata.comment.is_none() && !comment.is_empty() {
+                                metadata.comment = Some(comment.clone());
                             }
-                            _ => {}
+                        }
+                    }
+                    StandardTagKey::Lyrics => {
+                        if let Value::String(lyrics) = &tag.value {
+                            metadata.lyrics = Some(lyrics.clone());
                         }
                     }
                     StandardTagKey::Date => {
@@ -342,23 +378,18 @@ impl AudioDecoder for Mp3Decoder {
             Ok(audio_buf) => {
                 // Convert to our AudioBuffer format
                 let buffer = Self::convert_audio_buffer(audio_buf)?;
-                Ok(Some(buffer))
+                match self.seek_state.trim(packet.ts(), self.time_base, buffer) {
+                    Some(buffer) => Ok(Some(buffer)),
+                    // Still short of the seek target
+                    None => self.decode_next(),
+                }
             }
             Err(e) => Err(DecodeError::DecodeFailed(format!("Failed to decode packet: {}", e)))
         }
     }
 
     fn seek(&mut self, position: Duration) -> Result<(), DecodeError> {
-        // Convert duration to time units
-        let seek_time = Time::new(
-            (position.as_secs_f64() * self.time_base.denom as f64) as u64,
-            self.time_base.denom as f64,
-        );
-
-        // Perform the seek
-        self.format_reader
-            .seek(symphonia::core::formats::SeekMode::Accurate, symphonia::core::formats::SeekTo::Time { time: seek_time, track_id: Some(self.track_id) })
-            .map_err(|e| DecodeError::SeekError(format!("Seek failed: {}", e)))?;
+        self.seek_state.seek(self.format_reader.as_mut(), self.track_id, self.time_base, position)?;
 
         // Reset the decoder state after seeking
         self.decoder.reset();
@@ -366,11 +397,15 @@ impl AudioDecoder for Mp3Decoder {
         Ok(())
     }
 
+    fn supports_seek(&self) -> bool {
+        self.seek_state.supports_seek()
+    }
+
     fn metadata(&self) -> &AudioMetadata {
         &self.metadata
     }
 
-    fn duration(&self) -> Duration {
+    fn duration(&self) -> Option<Duration> {
         self.duration
     }
 
@@ -385,6 +420,18 @@ impl AudioDecoder for Mp3Decoder {
     fn channels(&self) -> u16 {
         self.channels
     }
+
+    fn codec(&self) -> AudioCodec {
+        AudioCodec::Mp3
+    }
+
+    fn encoder_delay_frames(&self) -> u32 {
+        self.encoder_delay
+    }
+
+    fn encoder_padding_frames(&self) -> u32 {
+        self.encoder_padding
+    }
 }
 
 impl Mp3Decoder {
@@ -394,6 +441,161 @@ impl Mp3Decoder {
     }
 }
 
+/// Gapless playback info from the iTunes `iTunSMPB` comment written by LAME and iTunes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ItunSmpb {
+    /// Silent frames the encoder added at the start
+    pub encoder_delay: u32,
+    /// Silent frames the encoder added at the end
+    pub encoder_padding: u32,
+    /// Frames of real audio in the file
+    pub total_samples: u64,
+}
+
+impl ItunSmpb {
+    /// Parse a value like `" 00000000 00000210 000003C0 0000000000A2E5B0 ..."`.
+    ///
+    /// The first word is reserved; delay, padding and total length follow as hex.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut words = value.split_whitespace().skip(1);
+        let encoder_delay = u32::from_str_radix(words.next()?, 16).ok()?;
+        let encoder_padding = u32::from_str_radix(words.next()?, 16).ok()?;
+        let total_samples = u64::from_str_radix(words.next()?, 16).ok()?;
+        Some(Self { encoder_delay, encoder_padding, total_samples })
+    }
+}
+
+/// ID3v2 frames read straight from the leading tag
+#[derive(Debug, Default)]
+struct RawId3Frames {
+    /// Whether the stream starts with an ID3v2 tag
+    tagged: bool,
+    /// iTunes `TCMP` compilation flag
+    compilation: bool,
+    /// First comment that is not iTunes data (`iTunSMPB`, `iTunNORM`, ...)
+    comment: Option<String>,
+    itunsmpb: Option<ItunSmpb>,
+}
+
+impl RawId3Frames {
+    /// Walk the ID3v2 tag at the start of the stream. Returns the defaults when there is no tag.
+    fn read<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        let mut frames = Self::default();
+        let mut header = [0u8; 10];
+        reader.read_exact(&mut header)?;
+        if &header[0..3] != b"ID3" {
+            return Ok(frames);
+        }
+
+        frames.tagged = true;
+        let major = header[3];
+        let flags = header[5];
+        let mut tag = Vec::new();
+        reader.take(syncsafe(&header[6..10]) as u64).read_to_end(&mut tag)?;
+
+        let mut pos = 0;
+        if flags & 0x40 != 0 && major >= 3 && tag.len() >= 4 {
+            // Skip the extended header; only v2.4 counts the size field in its size
+            pos = match major {
+                3 => 4 + u32::from_be_bytes([tag[0], tag[1], tag[2], tag[3]]) as usize,
+                _ => syncsafe(&tag[0..4]) as usize,
+            };
+        }
+
+        let (id_len, header_len) = if major == 2 { (3, 6) } else { (4, 10) };
+        while pos + header_len <= tag.len() {
+            let frame = &tag[pos..pos + header_len];
+            if frame[0] == 0 {
+                break; // Padding
+            }
+
+            let size = match major {
+                2 => u32::from_be_bytes([0, frame[3], frame[4], frame[5]]),
+                3 => u32::from_be_bytes([frame[4], frame[5], frame[6], frame[7]]),
+                _ => syncsafe(&frame[4..8]),
+            } as usize;
+            let body_start = pos + header_len;
+            let body_end = match body_start.checked_add(size) {
+                Some(end) if end <= tag.len() => end,
+                _ => break,
+            };
+            let body = &tag[body_start..body_end];
+
+            match &frame[..id_len] {
+                b"TCMP" | b"TCP" => {
+                    // Text frame: encoding byte, then "1" in Latin-1, UTF-16 or UTF-8
+                    let text: String = body
+                        .iter()
+                        .skip(1)
+                        .filter(|&&b| b.is_ascii_graphic())
+                        .map(|&b| b as char)
+                        .collect();
+                    frames.compilation = text == "1";
+                }
+                b"COMM" | b"COM" if body.len() > 4 => {
+                    // Encoding, language, terminated description, then the text
+                    let encoding = body[0];
+                    let (description, text) = split_terminated(encoding, &body[4..]);
+                    let description = decode_text(encoding, description);
+                    let text = decode_text(encoding, text);
+                    if description == "iTunSMPB" {
+                        frames.itunsmpb = ItunSmpb::parse(&text);
+                    } else if !description.starts_with("iTun") && !text.is_empty() && frames.comment.is_none() {
+                        frames.comment = Some(text);
+                    }
+                }
+                _ => {}
+            }
+
+            pos = body_end;
+        }
+
+        Ok(frames)
+    }
+}
+
+/// Split an ID3v2 string at its terminator (one NUL, or two for UTF-16)
+fn split_terminated(encoding: u8, bytes: &[u8]) -> (&[u8], &[u8]) {
+    let end = match encoding {
+        1 | 2 => bytes.chunks_exact(2).position(|unit| unit == [0, 0]).map(|i| (i * 2, 2)),
+        _ => bytes.iter().position(|&b| b == 0).map(|i| (i, 1)),
+    };
+    match end {
+        Some((end, terminator)) => (&bytes[..end], &bytes[end + terminator..]),
+        None => (bytes, &[]),
+    }
+}
+
+/// Decode ID3v2 text in the frame's encoding (Latin-1, UTF-16 with BOM, UTF-16BE or UTF-8)
+fn decode_text(encoding: u8, bytes: &[u8]) -> String {
+    let text = match encoding {
+        1 | 2 => {
+            let (big_endian, bytes) = match bytes {
+                [0xFE, 0xFF, rest @ ..] => (true, rest),
+                [0xFF, 0xFE, rest @ ..] => (false, rest),
+                _ => (encoding == 2, bytes),
+            };
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|unit| if big_endian {
+                    u16::from_be_bytes([unit[0], unit[1]])
+                } else {
+                    u16::from_le_bytes([unit[0], unit[1]])
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        3 => String::from_utf8_lossy(bytes).into_owned(),
+        _ => bytes.iter().map(|&b| b as char).collect(),
+    };
+    text.trim_end_matches('\0').to_string()
+}
+
+/// Decode a 28-bit ID3v2 syncsafe integer
+fn syncsafe(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |acc, &b| (acc << 7) | u32::from(b & 0x7f))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -632,4 +834,167 @@ mod tests {
         assert!(avg_bitrate > vbr_range.0);
         assert!(avg_bitrate < vbr_range.1);
     }
+
+    /// Encode an ID3v2.4 frame with a syncsafe size
+    fn id3v24_frame(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let size = body.len() as u32;
+        let mut frame = id.to_vec();
+        frame.extend([(size >> 21) as u8 & 0x7f, (size >> 14) as u8 & 0x7f, (size >> 7) as u8 & 0x7f, size as u8 & 0x7f]);
+        frame.extend([0, 0]);
+        frame.extend(body);
+        frame
+    }
+
+    fn text_frame(id: &[u8; 4], text: &str) -> Vec<u8> {
+        let mut body = vec![3]; // UTF-8
+        body.extend(text.as_bytes());
+        id3v24_frame(id, &body)
+    }
+
+    fn lang_frame(id: &[u8; 4], description: &str, text: &str) -> Vec<u8> {
+        let mut body = vec![3];
+        body.extend(b"eng");
+        body.extend(description.as_bytes());
+        body.push(0);
+        body.extend(text.as_bytes());
+        id3v24_frame(id, &body)
+    }
+
+    const ITUNSMPB: &str = " 00000000 00000840 000001C0 0000000000000C80 00000000 00000000 00000000";
+
+    /// An ID3v2.4 tag followed by silent MPEG-1 Layer III frames
+    fn tagged_mp3_bytes() -> Vec<u8> {
+        let mut frames = Vec::new();
+        frames.extend(text_frame(b"TIT2", "Tagged"));
+        frames.extend(lang_frame(b"COMM", "iTunSMPB", ITUNSMPB));
+        frames.extend(lang_frame(b"COMM", "", "Recorded live"));
+        frames.extend(lang_frame(b"USLT", "", "First line\nSecond line"));
+        frames.extend(text_frame(b"TCOM", "J. S. Bach"));
+        frames.extend(text_frame(b"TPOS", "1/2"));
+        frames.extend(text_frame(b"TCMP", "1"));
+        frames.extend(text_frame(b"TRCK", "3/12"));
+        frames.extend(text_frame(b"TSSE", "LAME 3.100"));
+
+        let size = frames.len() as u32;
+        let mut bytes = b"ID3\x04\x00\x00".to_vec();
+        bytes.extend([(size >> 21) as u8 & 0x7f, (size >> 14) as u8 & 0x7f, (size >> 7) as u8 & 0x7f, size as u8 & 0x7f]);
+        bytes.extend(frames);
+
+        bytes.extend(silent_frames(10));
+        bytes
+    }
+
+    /// Silent MPEG-1 Layer III frames of 1152 samples each
+    fn silent_frames(count: usize) -> Vec<u8> {
+        // 128 kbps, 44.1 kHz, stereo: 417 bytes per frame
+        let mut frame = vec![0u8; 417];
+        frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x64]);
+        frame.repeat(count)
+    }
+
+    #[test]
+    fn test_seek_is_sample_accurate() {
+        use std::io::Write;
+
+        let mut file = tempfile::Builder::new().suffix(".mp3").tempfile().unwrap();
+        file.write_all(&silent_frames(40)).unwrap();
+        let mut decoder = Mp3Decoder::new(file.path()).unwrap();
+        assert!(decoder.supports_seek());
+
+        // 500 ms is 22050 samples, partway into the 20th frame
+        decoder.seek(Duration::from_millis(500)).unwrap();
+        let mut frames = 0;
+        while let Some(buffer) = decoder.decode_next().unwrap() {
+            frames += buffer.frames;
+        }
+        assert_eq!(frames, 40 * 1152 - 22_050);
+    }
+
+    #[test]
+    fn test_id3v24_extended_tags() {
+        use std::io::Write;
+
+        let mut file = tempfile::Builder::new().suffix(".mp3").tempfile().unwrap();
+        file.write_all(&tagged_mp3_bytes()).unwrap();
+
+        let decoder = Mp3Decoder::new(file.path()).unwrap();
+        let metadata = decoder.metadata();
+        assert_eq!(metadata.title.as_deref(), Some("Tagged"));
+        assert_eq!(metadata.comment.as_deref(), Some("Recorded live"));
+        assert_eq!(metadata.lyrics.as_deref(), Some("First line\nSecond line"));
+        assert_eq!(metadata.composer.as_deref(), Some("J. S. Bach"));
+        assert_eq!(metadata.disc_number, Some(1));
+        assert!(metadata.compilation);
+        assert_eq!(metadata.track_number, Some(3));
+        assert_eq!(metadata.total_tracks, Some(12));
+
+        assert_eq!(decoder.encoder_delay_frames(), 0x840);
+        assert_eq!(decoder.encoder_padding_frames(), 0x1C0);
+    }
+
+    #[test]
+    fn test_full_report() {
+        use std::io::Write;
+
+        let mut file = tempfile::Builder::new().suffix(".mp3").tempfile().unwrap();
+        file.write_all(&tagged_mp3_bytes()).unwrap();
+
+        let report = MetadataExtractor::full_report(file.path()).unwrap();
+        assert_eq!(report.container, "MPEG audio");
+        assert_eq!(report.codec, "MPEG Audio Layer 3");
+        assert_eq!((report.sample_rate, report.channels), (Some(44_100), Some(2)));
+        assert_eq!(report.encoder.as_deref(), Some("LAME 3.100"));
+        assert_eq!(report.md5, None);
+        // Tags without a field of their own are kept under their frame IDs
+        assert!(report.metadata.extra_tags.contains(&("TSSE".to_string(), "LAME 3.100".to_string())));
+        assert_eq!(report.metadata.title.as_deref(), Some("Tagged"));
+    }
+
+    #[test]
+    fn test_itunsmpb_parse() {
+        let info = ItunSmpb::parse(ITUNSMPB).unwrap();
+        assert_eq!(info.encoder_delay, 2112);
+        assert_eq!(info.encoder_padding, 448);
+        assert_eq!(info.total_samples, 3200);
+
+        // Delay + audio + padding fills whole 1152-frame MP3 frames
+        assert_eq!((info.encoder_delay as u64 + info.total_samples + info.encoder_padding as u64) % 1152, 0);
+
+        assert!(ItunSmpb::parse("").is_none());
+        assert!(ItunSmpb::parse(" 00000000 zz").is_none());
+    }
+
+    #[test]
+    fn test_itunsmpb_in_utf16_comment() {
+        let utf16 = |text: &str| -> Vec<u8> {
+            let mut bytes = vec![0xFF, 0xFE];
+            bytes.extend(text.encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+            bytes
+        };
+        let mut body = vec![1];
+        body.extend(b"eng");
+        body.extend(utf16("iTunSMPB"));
+        body.extend([0, 0]);
+        body.extend(utf16(ITUNSMPB));
+
+        let frame = id3v24_frame(b"COMM", &body);
+        let size = frame.len() as u32;
+        let mut bytes = b"ID3\x04\x00\x00\x00\x00".to_vec();
+        bytes.extend([(size >> 7) as u8 & 0x7f, size as u8 & 0x7f]);
+        bytes.extend(frame);
+
+        let frames = RawId3Frames::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(frames.itunsmpb.unwrap().encoder_delay, 2112);
+        assert!(frames.comment.is_none());
+    }
+
+    #[test]
+    fn test_compilation_flag_absent() {
+        let mut untagged: &[u8] = &[0xFF, 0xFB, 0x90, 0x64, 0, 0, 0, 0, 0, 0];
+        assert!(!RawId3Frames::read(&mut untagged).unwrap().compilation);
+
+        let bytes = tagged_mp3_bytes();
+        let mut tagged: &[u8] = &bytes;
+        assert!(RawId3Frames::read(&mut tagged).unwrap().compilation);
+    }
 }
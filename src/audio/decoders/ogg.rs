@@ -6,25 +6,28 @@ use symphonia::core::audio::AudioBufferRef;
 use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_VORBIS};
 use symphonia::core::errors::Error as SymphoniaError;
 use symphonia::core::formats::{FormatOptions, FormatReader};
-use symphonia::core::io::MediaSourceStream;
+use symphonia::core::io::{MediaSource, MediaSourceStream};
 use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, Value};
 use symphonia::core::probe::Hint;
-use symphonia::core::units::{Time, TimeBase};
+use symphonia::core::units::TimeBase;
 
-use crate::audio::{AudioBuffer, AudioDecoder, AudioMetadata};
+use crate::audio::{AudioBuffer, AudioCodec, AudioDecoder, AudioMetadata, MetadataExtractor};
 use crate::error::DecodeError;
 
+use super::seek::SeekState;
+
 /// OGG Vorbis audio decoder implementation using symphonia
 pub struct OggDecoder {
     format_reader: Box<dyn FormatReader>,
     decoder: Box<dyn Decoder>,
     track_id: u32,
     metadata: AudioMetadata,
-    duration: Duration,
+    duration: Option<Duration>,
     sample_rate: u32,
     bit_depth: u16,
     channels: u16,
     time_base: TimeBase,
+    seek_state: SeekState,
 }
 
 impl OggDecoder {
@@ -33,18 +36,19 @@ impl OggDecoder {
         let file = File::open(&path).map_err(|e| {
             DecodeError::DecodeFailed(format!("Failed to open file: {}", e))
         })?;
+        let extension = path.as_ref().extension().and_then(|extension| extension.to_str());
+        Self::from_source(Box::new(file), extension)
+    }
 
-        let media_source = MediaSourceStream::new(
-            Box::new(file),
-            Default::default(),
-        );
+    /// Create an OGG Vorbis decoder reading from `source`, e.g. a network stream; `extension` hints the container
+    pub fn from_source(source: Box<dyn MediaSource>, extension: Option<&str>) -> Result<Self, DecodeError> {
+        let seekable = source.is_seekable();
+        let media_source = MediaSourceStream::new(source, Default::default());
 
         // Create a hint to help the format registry guess the format
         let mut hint = Hint::new();
-        if let Some(extension) = path.as_ref().extension() {
-            if let Some(ext_str) = extension.to_str() {
-                hint.with_extension(ext_str);
-            }
+        if let Some(extension) = extension {
+            hint.with_extension(extension);
         }
 
         // Probe the media source for a format
@@ -86,11 +90,9 @@ impl OggDecoder {
         };
 
         // Calculate duration
-        let duration = if let (Some(n_frames), Some(sample_rate)) =
-            (track.codec_params.n_frames, track.codec_params.sample_rate) {
-            Duration::from_secs_f64(n_frames as f64 / sample_rate as f64)
-        } else {
-            Duration::from_secs(0) // Unknown duration
+        let duration = match (track.codec_params.n_frames, track.codec_params.sample_rate) {
+            (Some(n_frames), Some(sample_rate)) => Some(Duration::from_secs_f64(n_frames as f64 / sample_rate as f64)),
+            _ => None, // Unknown duration
         };
 
         // Extract metadata during initialization (OGG often has Vorbis comments)
@@ -109,6 +111,8 @@ impl OggDecoder {
             bit_depth,
             channels,
             time_base,
+            // Covers the longest Vorbis packet, half of the largest block size
+            seek_state: SeekState::with_preroll(Duration::from_secs_f64(4096.0 / sample_rate as f64)).with_seekable(seekable),
         })
     }
 
@@ -116,14 +120,7 @@ impl OggDecoder {
     fn extract_metadata_from_probed(
         mut probed_metadata: symphonia::core::probe::ProbedMetadata,
     ) -> AudioMetadata {
-        let mut metadata = AudioMetadata {
-            title: None,
-            artist: None,
-            album: None,
-            track_number: None,
-            year: None,
-            genre: None,
-        };
+        let mut metadata = AudioMetadata::new();
 
         // Try to get metadata from the probed metadata (Vorbis comments for OGG)
         if let Some(probed_meta) = probed_metadata.get() {
@@ -155,18 +152,15 @@ impl OggDecoder {
                             metadata.album = Some(album.clone());
                         }
                     }
-                    StandardTagKey::TrackNumber => {
-                        match &tag.value {
-                            Value::UnsignedInt(track_num) => {
-                                metadata.track_number = Some(*track_num as u32);
-                            }
-                            Value::String(track_str) => {
-                                if let Ok(track_num) = track_str.parse::<u32>() {
-                                    metadata.track_number = Some(track_num);
-                                }
-                            }
-                            _ => {}
-                        }
+                    StandardTagKey::TrackNumber
+                    | StandardTagKey::TrackTotal
+                    | StandardTagKey::DiscNumber
+                    | StandardTagKey::DiscTotal
+                    | StandardTagKey::Composer
+                    | StandardTagKey::Bpm
+                    | StandardTagKey::AlbumArtist
+                    | StandardTagKey::Lyrics => {
+                        MetadataExtractor::apply_common_tag(std_key, &tag.value, metadata);
                     }
                     StandardTagKey::Date => {
                         match &tag.value {
@@ -340,23 +334,18 @@ impl AudioDecoder for OggDecoder {
             Ok(audio_buf) => {
                 // Convert to our AudioBuffer format
                 let buffer = Self::convert_audio_buffer(audio_buf)?;
-                Ok(Some(buffer))
+                match self.seek_state.trim(packet.ts(), self.time_base, buffer) {
+                    Some(buffer) => Ok(Some(buffer)),
+                    // Still short of the seek target
+                    None => self.decode_next(),
+                }
             }
             Err(e) => Err(DecodeError::DecodeFailed(format!("Failed to decode packet: {}", e)))
         }
     }
 
     fn seek(&mut self, position: Duration) -> Result<(), DecodeError> {
-        // Convert duration to time units
-        let seek_time = Time::new(
-            (position.as_secs_f64() * self.time_base.denom as f64) as u64,
-            self.time_base.denom as f64,
-        );
-
-        // Perform the seek
-        self.format_reader
-            .seek(symphonia::core::formats::SeekMode::Accurate, symphonia::core::formats::SeekTo::Time { time: seek_time, track_id: Some(self.track_id) })
-            .map_err(|e| DecodeError::SeekError(format!("Seek failed: {}", e)))?;
+        self.seek_state.seek(self.format_reader.as_mut(), self.track_id, self.time_base, position)?;
 
         // Reset the decoder state after seeking
         self.decoder.reset();
@@ -364,11 +353,15 @@ impl AudioDecoder for OggDecoder {
         Ok(())
     }
 
+    fn supports_seek(&self) -> bool {
+        self.seek_state.supports_seek()
+    }
+
     fn metadata(&self) -> &AudioMetadata {
         &self.metadata
     }
 
-    fn duration(&self) -> Duration {
+    fn duration(&self) -> Option<Duration> {
         self.duration
     }
 
@@ -383,6 +376,10 @@ impl AudioDecoder for OggDecoder {
     fn channels(&self) -> u16 {
         self.channels
     }
+
+    fn codec(&self) -> AudioCodec {
+        AudioCodec::OggVorbis
+    }
 }
 
 impl OggDecoder {
@@ -397,6 +394,19 @@ mod tests {
     use super::*;
     use tempfile::NamedTempFile;
 
+    #[test]
+    fn test_full_report() {
+        let mut file = tempfile::Builder::new().suffix(".ogg").tempfile().unwrap();
+        std::io::Write::write_all(&mut file, &silent_vorbis_bytes(375)).unwrap();
+
+        let report = MetadataExtractor::full_report(file.path()).unwrap();
+        assert_eq!(report.container, "Ogg");
+        assert_eq!(report.codec, "Vorbis");
+        assert_eq!((report.sample_rate, report.channels), (Some(48_000), Some(1)));
+        assert_eq!(report.md5, None);
+        assert!(report.metadata.extra_tags.is_empty());
+    }
+
     #[test]
     fn test_ogg_decoder_new_with_nonexistent_file() {
         let result = OggDecoder::new("/nonexistent/file.ogg");
@@ -658,4 +668,131 @@ mod tests {
             }
         }
     }
+
+    /// Packs values least significant bit first, as Vorbis headers are
+    #[derive(Default)]
+    struct BitWriter {
+        bytes: Vec<u8>,
+        bits: usize,
+    }
+
+    impl BitWriter {
+        /// Append the low `width` bits of `value`
+        fn put(&mut self, value: u32, width: usize) {
+            for bit in 0..width {
+                if self.bits.is_multiple_of(8) {
+                    self.bytes.push(0);
+                }
+                if value.checked_shr(bit as u32).unwrap_or(0) & 1 == 1 {
+                    *self.bytes.last_mut().unwrap() |= 1 << (self.bits % 8);
+                }
+                self.bits += 1;
+            }
+        }
+    }
+
+    fn ogg_crc(bytes: &[u8]) -> u32 {
+        bytes.iter().fold(0u32, |crc, &byte| {
+            (0..8).fold(crc ^ ((byte as u32) << 24), |crc, _| {
+                if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04c1_1db7 } else { crc << 1 }
+            })
+        })
+    }
+
+    fn ogg_page(flags: u8, granule: u64, sequence: u32, packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut page = b"OggS\0".to_vec();
+        page.push(flags);
+        page.extend(granule.to_le_bytes());
+        page.extend(1u32.to_le_bytes());
+        page.extend(sequence.to_le_bytes());
+        page.extend([0; 4]);
+        page.push(packets.len() as u8);
+        // Every packet is shorter than 255 bytes, so takes one lacing value
+        page.extend(packets.iter().map(|packet| packet.len() as u8));
+        page.extend(packets.concat());
+        let crc = ogg_crc(&page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        page
+    }
+
+    /// Samples each short-block packet adds once the first one has been decoded
+    const PACKET_FRAMES: u64 = 128;
+
+    /// A 48 kHz mono Vorbis stream of `packets` silent short-block packets
+    fn silent_vorbis_bytes(packets: usize) -> Vec<u8> {
+        let mut ident = vec![1];
+        ident.extend(b"vorbis");
+        ident.extend(0u32.to_le_bytes());
+        ident.push(1);
+        ident.extend(48_000u32.to_le_bytes());
+        ident.extend([0; 12]);
+        // 256 and 2048 sample blocks, framing bit
+        ident.extend([0xB8, 1]);
+
+        let mut comment = vec![3];
+        comment.extend(b"vorbis");
+        comment.extend([0; 8]);
+        comment.push(1);
+
+        let mut setup = BitWriter::default();
+        setup.bytes.extend([5, b'v', b'o', b'r', b'b', b'i', b's']);
+        setup.bits = setup.bytes.len() * 8;
+        // One codebook: a single dimension, two entries of one bit, no lookup table
+        setup.put(0, 8);
+        setup.put(0x56_4342, 24);
+        setup.put(1, 16);
+        setup.put(2, 24);
+        setup.put(0, 2);
+        setup.put(0, 10);
+        setup.put(0, 4);
+        // One placeholder time-domain transform
+        setup.put(0, 6 + 16);
+        // One type-1 floor without partitions, multiplier 1, 8 range bits
+        setup.put(0, 6);
+        setup.put(1, 16);
+        setup.put(0, 5 + 2);
+        setup.put(8, 4);
+        // One empty type-0 residue with a single classification
+        setup.put(0, 6 + 16);
+        setup.put(0, 24 * 3);
+        setup.put(0, 6 + 8 + 3 + 1);
+        // One mapping: one submap using floor 0 and residue 0, no coupling
+        setup.put(0, 6 + 16 + 1 + 1 + 2);
+        setup.put(0, 8 * 3);
+        // One short-block mode using mapping 0
+        setup.put(0, 6 + 1 + 16);
+        setup.put(0, 16 + 8);
+        // Framing bit
+        setup.put(1, 1);
+
+        let mut bytes = ogg_page(0x02, 0, 0, &[ident]);
+        bytes.extend(ogg_page(0, 0, 1, &[comment, setup.bytes]));
+        // Audio packets with an unused floor decode to silence
+        for (page, chunk) in (0..packets).collect::<Vec<_>>().chunks(100).enumerate() {
+            let last = *chunk.last().unwrap() as u64;
+            let flags = if last as usize == packets - 1 { 0x04 } else { 0 };
+            let audio = vec![vec![0u8]; chunk.len()];
+            bytes.extend(ogg_page(flags, last * PACKET_FRAMES, page as u32 + 2, &audio));
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_seek_is_sample_accurate() {
+        let mut file = tempfile::Builder::new().suffix(".ogg").tempfile().unwrap();
+        std::io::Write::write_all(&mut file, &silent_vorbis_bytes(401)).unwrap();
+        let mut decoder = OggDecoder::new(file.path()).unwrap();
+        assert!(decoder.supports_seek());
+        let total = 400 * PACKET_FRAMES as usize;
+
+        // 500 ms is sample 24000, partway into a packet that needs the one before it to decode
+        for position in [Duration::from_millis(500), Duration::from_millis(100)] {
+            decoder.seek(position).unwrap();
+            let mut frames = 0;
+            while let Some(buffer) = decoder.decode_next().unwrap() {
+                frames += buffer.frames;
+            }
+            assert_eq!(frames, total - position.as_millis() as usize * 48);
+        }
+    }
 }
@@ -0,0 +1,151 @@
+/*!
+Seeking shared by the symphonia-based decoders.
+
+- Format readers land on a packet boundary at or before the requested time: an OGG page, an
+  MP3 frame, an MP4 sample. `SeekState` keeps the requested timestamp and `trim` drops the
+  decoded frames ahead of it, so playback resumes exactly where it was asked to.
+- Lapped-transform codecs (Vorbis, AAC) only produce correct output from the second packet
+  after a reset. Their decoders seek `preroll` earlier and let `trim` drop the extra audio.
+- A reader that reports the stream as unseekable, or seekable forward only, isn't asked again:
+  `supports_seek` turns false and later seeks fail straight away.
+*/
+
+use std::time::Duration;
+
+use symphonia::core::errors::{Error as SymphoniaError, SeekErrorKind};
+use symphonia::core::formats::{FormatReader, SeekMode, SeekTo};
+use symphonia::core::units::{Time, TimeBase};
+
+use crate::audio::AudioBuffer;
+use crate::error::DecodeError;
+
+#[derive(Debug)]
+pub struct SeekState {
+    /// Timestamp asked for, while decoded packets still start before it
+    target_ts: Option<u64>,
+    seekable: bool,
+    preroll: Duration,
+}
+
+impl Default for SeekState {
+    fn default() -> Self {
+        Self { target_ts: None, seekable: true, preroll: Duration::ZERO }
+    }
+}
+
+impl SeekState {
+    /// Seek `preroll` ahead of the requested time so the decoder has warmed up by then
+    pub fn with_preroll(preroll: Duration) -> Self {
+        Self { preroll, ..Self::default() }
+    }
+
+    /// Refuse seeks from the start when the source can't seek, such as a live HTTP stream
+    pub fn with_seekable(mut self, seekable: bool) -> Self {
+        self.seekable &= seekable;
+        self
+    }
+
+    /// Whether the stream has not yet refused a seek
+    pub fn supports_seek(&self) -> bool {
+        self.seekable
+    }
+
+    /// Seek `reader` to `position`; the decoder must be reset afterwards
+    pub fn seek(
+        &mut self,
+        reader: &mut dyn FormatReader,
+        track_id: u32,
+        time_base: TimeBase,
+        position: Duration,
+    ) -> Result<(), DecodeError> {
+        if !self.seekable {
+            return Err(DecodeError::SeekError("This file does not support seeking".to_string()));
+        }
+        let start = position.saturating_sub(self.preroll);
+        let to = SeekTo::Time { time: Time::from(start), track_id: Some(track_id) };
+        match reader.seek(SeekMode::Accurate, to) {
+            Ok(seeked) => {
+                let target_ts = time_base.calc_timestamp(Time::from(position));
+                self.target_ts = (seeked.actual_ts < target_ts).then_some(target_ts);
+                Ok(())
+            }
+            Err(err) => {
+                if matches!(
+                    err,
+                    SymphoniaError::SeekError(SeekErrorKind::Unseekable | SeekErrorKind::ForwardOnly)
+                        | SymphoniaError::Unsupported(_)
+                ) {
+                    self.seekable = false;
+                }
+                self.target_ts = None;
+                Err(DecodeError::SeekError(format!("Seek failed: {}", err)))
+            }
+        }
+    }
+
+    /// Drop the part of a decoded packet that lies before the seek target
+    ///
+    /// Returns `None` when the whole packet comes before it and the next one should be decoded.
+    pub fn trim(&mut self, packet_ts: u64, time_base: TimeBase, mut buffer: AudioBuffer) -> Option<AudioBuffer> {
+        let Some(target_ts) = self.target_ts else {
+            return Some(buffer);
+        };
+        if packet_ts >= target_ts {
+            self.target_ts = None;
+            return Some(buffer);
+        }
+
+        let skip = Duration::from(time_base.calc_time(target_ts - packet_ts));
+        let skip_frames = (skip.as_secs_f64() * buffer.sample_rate as f64).round() as usize;
+        if skip_frames >= buffer.frames {
+            return None;
+        }
+        self.target_ts = None;
+        buffer.samples.drain(..skip_frames * buffer.channels as usize);
+        buffer.frames -= skip_frames;
+        Some(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(frames: usize, channels: u16) -> AudioBuffer {
+        AudioBuffer {
+            samples: (0..frames * channels as usize).map(|i| (i / channels as usize) as f32).collect(),
+            channels,
+            sample_rate: 1_000,
+            frames,
+        }
+    }
+
+    #[test]
+    fn test_trim_discards_up_to_the_target() {
+        let time_base = TimeBase::new(1, 1_000);
+        let mut state = SeekState { target_ts: Some(250), ..SeekState::default() };
+
+        // Packets wholly before the target are dropped
+        assert!(state.trim(0, time_base, ramp(100, 2)).is_none());
+        assert!(state.trim(100, time_base, ramp(100, 2)).is_none());
+
+        // The packet holding the target starts at it
+        let buffer = state.trim(200, time_base, ramp(100, 2)).unwrap();
+        assert_eq!(buffer.frames, 50);
+        assert_eq!(&buffer.samples[..4], &[50.0, 50.0, 51.0, 51.0]);
+
+        // Later packets pass untouched
+        assert_eq!(state.trim(300, time_base, ramp(100, 2)).unwrap().frames, 100);
+    }
+
+    #[test]
+    fn test_trim_converts_from_the_stream_time_base() {
+        // An MP4 track with a 1/90000 timescale carrying 1 kHz audio
+        let time_base = TimeBase::new(1, 90_000);
+        let mut state = SeekState { target_ts: Some(9_000), ..SeekState::default() };
+
+        let buffer = state.trim(4_500, time_base, ramp(100, 1)).unwrap();
+        assert_eq!(buffer.frames, 50);
+        assert_eq!(buffer.samples[0], 50.0);
+    }
+}
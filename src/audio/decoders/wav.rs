@@ -1,50 +1,68 @@
 use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
 use std::time::Duration;
 
-use symphonia::core::audio::AudioBufferRef;
-use symphonia::core::codecs::{Decoder, DecoderOptions};
-use symphonia::core::errors::Error as SymphoniaError;
-use symphonia::core::formats::{FormatOptions, FormatReader};
-use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, Value};
+use symphonia::core::audio::{AudioBufferRef, Channels};
+use symphonia::core::codecs::{
+    CodecParameters, CodecType, Decoder, DecoderOptions, CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_F64LE,
+    CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_S24LE, CODEC_TYPE_PCM_S32LE, CODEC_TYPE_PCM_U8,
+};
+use symphonia::core::errors::{Error as SymphoniaError, Result as SymphoniaResult};
+use symphonia::core::formats::{Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track};
+use symphonia::core::io::{MediaSource, MediaSourceStream, ReadBytes};
+use symphonia::core::meta::{Metadata, MetadataLog, MetadataOptions, MetadataRevision, StandardTagKey, Value};
 use symphonia::core::probe::Hint;
-use symphonia::core::units::{Time, TimeBase};
+use symphonia::core::units::TimeBase;
 
-use crate::audio::{AudioBuffer, AudioDecoder, AudioMetadata, MetadataExtractor};
+use crate::audio::{AudioBuffer, AudioCodec, AudioDecoder, AudioMetadata, MetadataExtractor};
 use crate::error::DecodeError;
 
+use super::seek::SeekState;
+
 /// WAV audio decoder implementation using symphonia
 pub struct WavDecoder {
     format_reader: Box<dyn FormatReader>,
     decoder: Box<dyn Decoder>,
     track_id: u32,
     metadata: AudioMetadata,
-    duration: Duration,
+    duration: Option<Duration>,
     sample_rate: u32,
     bit_depth: u16,
     channels: u16,
     time_base: TimeBase,
+    seek_state: SeekState,
 }
 
 impl WavDecoder {
     /// Create a new WAV decoder for the given file path
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, DecodeError> {
-        let file = File::open(&path).map_err(|e| {
+        let mut file = File::open(&path).map_err(|e| {
             DecodeError::DecodeFailed(format!("Failed to open file: {}", e))
         })?;
 
-        let media_source = MediaSourceStream::new(
-            Box::new(file),
-            Default::default(),
-        );
+        // RF64 files (> 4 GB) keep their real sizes in a ds64 chunk that symphonia does not read
+        if !is_rf64(&mut file)? {
+            let extension = path.as_ref().extension().and_then(|extension| extension.to_str());
+            return Self::from_source(Box::new(file), extension);
+        }
+        let media_source = MediaSourceStream::new(Box::new(file), Default::default());
+        let reader = Rf64Reader::try_new(media_source, &FormatOptions::default())
+            .map_err(|e| DecodeError::UnsupportedFormat {
+                format: format!("RF64 parse failed: {}", e),
+            })?;
+        Self::with_reader(Box::new(reader), AudioMetadata::new(), true)
+    }
+
+    /// Create a WAV decoder reading from `source`, e.g. a network stream; `extension` hints the container
+    pub fn from_source(source: Box<dyn MediaSource>, extension: Option<&str>) -> Result<Self, DecodeError> {
+        let seekable = source.is_seekable();
+        let media_source = MediaSourceStream::new(source, Default::default());
 
         // Create a hint to help the format registry guess the format
         let mut hint = Hint::new();
-        if let Some(extension) = path.as_ref().extension() {
-            if let Some(ext_str) = extension.to_str() {
-                hint.with_extension(ext_str);
-            }
+        if let Some(extension) = extension {
+            hint.with_extension(extension);
         }
 
         // Probe the media source for a format
@@ -53,9 +71,11 @@ impl WavDecoder {
             .map_err(|e| DecodeError::UnsupportedFormat {
                 format: format!("WAV probe failed: {}", e),
             })?;
+        let metadata = MetadataExtractor::extract_from_format_reader(probed.format.as_ref(), probed.metadata);
+        Self::with_reader(probed.format, metadata, seekable)
+    }
 
-        let format_reader = probed.format;
-
+    fn with_reader(format_reader: Box<dyn FormatReader>, metadata: AudioMetadata, seekable: bool) -> Result<Self, DecodeError> {
         // Find the first audio track (WAV files typically have PCM audio)
         let track = format_reader
             .tracks()
@@ -86,16 +106,11 @@ impl WavDecoder {
         };
 
         // Calculate duration
-        let duration = if let (Some(n_frames), Some(sample_rate)) =
-            (track.codec_params.n_frames, track.codec_params.sample_rate) {
-            Duration::from_secs_f64(n_frames as f64 / sample_rate as f64)
-        } else {
-            Duration::from_secs(0) // Unknown duration
+        let duration = match (track.codec_params.n_frames, track.codec_params.sample_rate) {
+            (Some(n_frames), Some(sample_rate)) => Some(Duration::from_secs_f64(n_frames as f64 / sample_rate as f64)),
+            _ => None, // Unknown duration
         };
 
-        // Extract metadata during initialization
-        let metadata = MetadataExtractor::extract_from_format_reader(format_reader.as_ref(), probed.metadata);
-
         // Get time base for seeking
         let time_base = track.codec_params.time_base.unwrap_or(TimeBase::new(1, sample_rate));
 
@@ -109,6 +124,7 @@ impl WavDecoder {
             bit_depth,
             channels,
             time_base,
+            seek_state: SeekState::default().with_seekable(seekable),
         })
     }
 
@@ -116,14 +132,7 @@ impl WavDecoder {
     fn extract_metadata_from_probed(
         mut probed_metadata: symphonia::core::probe::ProbedMetadata,
     ) -> AudioMetadata {
-        let mut metadata = AudioMetadata {
-            title: None,
-            artist: None,
-            album: None,
-            track_number: None,
-            year: None,
-            genre: None,
-        };
+        let mut metadata = AudioMetadata::new();
 
         // Try to get metadata from the probed metadata
         if let Some(probed_meta) = probed_metadata.get() {
@@ -155,18 +164,15 @@ impl WavDecoder {
                             metadata.album = Some(album.clone());
                         }
                     }
-                    StandardTagKey::TrackNumber => {
-                        match &tag.value {
-                            Value::UnsignedInt(track_num) => {
-                                metadata.track_number = Some(*track_num as u32);
-                            }
-                            Value::String(track_str) => {
-                                if let Ok(track_num) = track_str.parse::<u32>() {
-                                    metadata.track_number = Some(track_num);
-                                }
-                            }
-                            _ => {}
-                        }
+                    StandardTagKey::TrackNumber
+                    | StandardTagKey::TrackTotal
+                    | StandardTagKey::DiscNumber
+                    | StandardTagKey::DiscTotal
+                    | StandardTagKey::Composer
+                    | StandardTagKey::Bpm
+                    | StandardTagKey::AlbumArtist
+                    | StandardTagKey::Lyrics => {
+                        MetadataExtractor::apply_common_tag(std_key, &tag.value, metadata);
                     }
                     StandardTagKey::Date => {
                         match &tag.value {
@@ -341,23 +347,18 @@ impl AudioDecoder for WavDecoder {
             Ok(audio_buf) => {
                 // Convert to our AudioBuffer format
                 let buffer = Self::convert_audio_buffer(audio_buf)?;
-                Ok(Some(buffer))
+                match self.seek_state.trim(packet.ts(), self.time_base, buffer) {
+                    Some(buffer) => Ok(Some(buffer)),
+                    // Still short of the seek target
+                    None => self.decode_next(),
+                }
             }
             Err(e) => Err(DecodeError::DecodeFailed(format!("Failed to decode packet: {}", e)))
         }
     }
 
     fn seek(&mut self, position: Duration) -> Result<(), DecodeError> {
-        // Convert duration to time units
-        let seek_time = Time::new(
-            (position.as_secs_f64() * self.time_base.denom as f64) as u64,
-            self.time_base.denom as f64,
-        );
-
-        // Perform the seek
-        self.format_reader
-            .seek(symphonia::core::formats::SeekMode::Accurate, symphonia::core::formats::SeekTo::Time { time: seek_time, track_id: Some(self.track_id) })
-            .map_err(|e| DecodeError::SeekError(format!("Seek failed: {}", e)))?;
+        self.seek_state.seek(self.format_reader.as_mut(), self.track_id, self.time_base, position)?;
 
         // Reset the decoder state after seeking
         self.decoder.reset();
@@ -365,11 +366,15 @@ impl AudioDecoder for WavDecoder {
         Ok(())
     }
 
+    fn supports_seek(&self) -> bool {
+        self.seek_state.supports_seek()
+    }
+
     fn metadata(&self) -> &AudioMetadata {
         &self.metadata
     }
 
-    fn duration(&self) -> Duration {
+    fn duration(&self) -> Option<Duration> {
         self.duration
     }
 
@@ -384,6 +389,241 @@ impl AudioDecoder for WavDecoder {
     fn channels(&self) -> u16 {
         self.channels
     }
+
+    fn codec(&self) -> AudioCodec {
+        AudioCodec::Wav
+    }
+}
+
+/// Frames read per packet from an RF64 data chunk (same as symphonia's WAV reader)
+const RF64_FRAMES_PER_PACKET: u64 = 1152;
+
+/// Size fields from an RF64 `ds64` chunk, which replace the 32-bit RIFF sizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rf64Sizes {
+    pub riff_size: u64,
+    pub data_size: u64,
+    pub sample_count: u64,
+}
+
+/// Check for the RF64 marker, leaving the file positioned at the start
+fn is_rf64(file: &mut File) -> Result<bool, DecodeError> {
+    let mut marker = [0u8; 4];
+    let read = file.read(&mut marker)
+        .and_then(|read| file.seek(SeekFrom::Start(0)).map(|_| read))
+        .map_err(|e| DecodeError::DecodeFailed(format!("Failed to read file header: {}", e)))?;
+    Ok(read == marker.len() && &marker == b"RF64")
+}
+
+/// Read the RF64 header and `ds64` chunk, returning `None` for classic RIFF WAV files
+pub fn read_rf64_sizes<R: ReadBytes>(reader: &mut R) -> SymphoniaResult<Option<Rf64Sizes>> {
+    if &reader.read_quad_bytes()? != b"RF64" {
+        return Ok(None);
+    }
+    let _riff_size_32 = reader.read_u32()?;
+    if &reader.read_quad_bytes()? != b"WAVE" {
+        return Err(SymphoniaError::Unsupported("rf64: riff form is not wave"));
+    }
+
+    // The ds64 chunk is required to be the first chunk
+    let tag = reader.read_quad_bytes()?;
+    let len = reader.read_u32()? as u64;
+    if &tag != b"ds64" || len < 28 {
+        return Err(SymphoniaError::DecodeError("rf64: missing ds64 chunk"));
+    }
+
+    let sizes = Rf64Sizes {
+        riff_size: reader.read_u64()?,
+        data_size: reader.read_u64()?,
+        sample_count: reader.read_u64()?,
+    };
+
+    // Skip the chunk size table (only used for chunks other than data) and padding
+    reader.ignore_bytes(len - 24 + (len & 1))?;
+
+    Ok(Some(sizes))
+}
+
+/// PCM layout parsed from a `fmt ` chunk
+struct WaveFormat {
+    codec: CodecType,
+    channels: Channels,
+    sample_rate: u32,
+    block_align: u16,
+    bits_per_sample: u16,
+    bits_per_coded_sample: u16,
+}
+
+impl WaveFormat {
+    fn read<R: ReadBytes>(reader: &mut R, len: u64) -> SymphoniaResult<Self> {
+        if len < 16 {
+            return Err(SymphoniaError::DecodeError("rf64: fmt chunk too short"));
+        }
+
+        let mut format_tag = reader.read_u16()?;
+        let channel_count = reader.read_u16()? as u32;
+        let sample_rate = reader.read_u32()?;
+        let _byte_rate = reader.read_u32()?;
+        let block_align = reader.read_u16()?;
+        let bits_per_coded_sample = reader.read_u16()?;
+        let mut bits_per_sample = bits_per_coded_sample;
+        let mut channel_mask = 0u32;
+        let mut consumed = 16;
+
+        // WAVE_FORMAT_EXTENSIBLE keeps the real format tag at the start of the sub-format GUID
+        if format_tag == 0xFFFE && len >= 40 {
+            let _extension_size = reader.read_u16()?;
+            bits_per_sample = reader.read_u16()?;
+            channel_mask = reader.read_u32()?;
+            format_tag = reader.read_u16()?;
+            reader.ignore_bytes(14)?;
+            consumed = 40;
+        }
+        reader.ignore_bytes(len - consumed + (len & 1))?;
+
+        let codec = match (format_tag, bits_per_coded_sample) {
+            (1, 8) => CODEC_TYPE_PCM_U8,
+            (1, 16) => CODEC_TYPE_PCM_S16LE,
+            (1, 24) => CODEC_TYPE_PCM_S24LE,
+            (1, 32) => CODEC_TYPE_PCM_S32LE,
+            (3, 32) => CODEC_TYPE_PCM_F32LE,
+            (3, 64) => CODEC_TYPE_PCM_F64LE,
+            _ => return Err(SymphoniaError::Unsupported("rf64: only PCM and IEEE float data is supported")),
+        };
+
+        if channel_count == 0 || channel_count > 32 || block_align == 0 || sample_rate == 0 {
+            return Err(SymphoniaError::DecodeError("rf64: invalid fmt chunk"));
+        }
+
+        // Use the speaker mask when it matches the channel count, otherwise assign in order
+        let channels = Channels::from_bits(channel_mask)
+            .filter(|channels| channels.count() as u32 == channel_count)
+            .unwrap_or_else(|| Channels::from_bits_truncate(((1u64 << channel_count) - 1) as u32));
+
+        Ok(Self {
+            codec,
+            channels,
+            sample_rate,
+            block_align,
+            bits_per_sample: bits_per_sample.min(bits_per_coded_sample),
+            bits_per_coded_sample,
+        })
+    }
+}
+
+/// Format reader for RF64 WAV files, whose sizes do not fit the 32-bit RIFF fields
+pub struct Rf64Reader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    metadata: MetadataLog,
+    block_align: u64,
+    data_start_pos: u64,
+    data_end_pos: u64,
+}
+
+impl FormatReader for Rf64Reader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> SymphoniaResult<Self> {
+        let sizes = read_rf64_sizes(&mut source)?
+            .ok_or(SymphoniaError::Unsupported("rf64: missing RF64 marker"))?;
+
+        let mut format = None;
+        loop {
+            let tag = source.read_quad_bytes()?;
+            let len = source.read_u32()?;
+
+            match &tag {
+                b"fmt " => format = Some(WaveFormat::read(&mut source, len as u64)?),
+                b"data" => {
+                    let format = format.ok_or(SymphoniaError::DecodeError("rf64: data chunk before fmt chunk"))?;
+
+                    // A data size of 0xFFFFFFFF means the real size is in ds64
+                    let data_len = if len == u32::MAX { sizes.data_size } else { len as u64 };
+                    let block_align = format.block_align as u64;
+                    let n_frames = data_len / block_align;
+
+                    let mut codec_params = CodecParameters::new();
+                    codec_params
+                        .for_codec(format.codec)
+                        .with_sample_rate(format.sample_rate)
+                        .with_time_base(TimeBase::new(1, format.sample_rate))
+                        .with_channels(format.channels)
+                        .with_bits_per_sample(format.bits_per_sample as u32)
+                        .with_bits_per_coded_sample(format.bits_per_coded_sample as u32)
+                        .with_n_frames(n_frames)
+                        .with_max_frames_per_packet(RF64_FRAMES_PER_PACKET)
+                        .with_frames_per_block(1);
+
+                    let data_start_pos = source.pos();
+                    return Ok(Rf64Reader {
+                        reader: source,
+                        tracks: vec![Track::new(0, codec_params)],
+                        metadata: MetadataLog::default(),
+                        block_align,
+                        data_start_pos,
+                        data_end_pos: data_start_pos + n_frames * block_align,
+                    });
+                }
+                _ => source.ignore_bytes(len as u64 + (len as u64 & 1))?,
+            }
+        }
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &[]
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> SymphoniaResult<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+        let n_frames = params.n_frames.unwrap_or(0);
+        let required_ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => TimeBase::new(1, params.sample_rate.unwrap_or(1)).calc_timestamp(time),
+        };
+        if required_ts > n_frames {
+            return Err(SymphoniaError::SeekError(symphonia::core::errors::SeekErrorKind::OutOfRange));
+        }
+
+        // Land on a packet boundary so packet timestamps match an unseeked stream
+        let actual_ts = required_ts / RF64_FRAMES_PER_PACKET * RF64_FRAMES_PER_PACKET;
+        self.reader.seek(SeekFrom::Start(self.data_start_pos + actual_ts * self.block_align))?;
+        Ok(SeekedTo { track_id: 0, required_ts, actual_ts })
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn next_packet(&mut self) -> SymphoniaResult<Packet> {
+        let pos = self.reader.pos();
+        let frames_left = self.data_end_pos.saturating_sub(pos) / self.block_align;
+        let frames = frames_left.min(RF64_FRAMES_PER_PACKET);
+
+        // Read what is available; files cut short end at the last complete frame
+        let mut data = vec![0u8; (frames * self.block_align) as usize];
+        let mut filled = 0;
+        while filled < data.len() {
+            match self.reader.read(&mut data[filled..])? {
+                0 => break,
+                read => filled += read,
+            }
+        }
+        let frames = filled as u64 / self.block_align;
+        if frames == 0 {
+            return Err(SymphoniaError::IoError(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+        data.truncate((frames * self.block_align) as usize);
+
+        let ts = (pos - self.data_start_pos) / self.block_align;
+        Ok(Packet::new_from_boxed_slice(0, ts, frames, data.into_boxed_slice()))
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
 }
 
 impl WavDecoder {
@@ -396,8 +636,133 @@ impl WavDecoder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audio::test_files;
     use tempfile::NamedTempFile;
 
+    /// Build a 16-bit stereo 48 kHz WAV file, optionally as RF64 claiming `rf64_data_size` bytes
+    fn wav_bytes(samples: &[i16], rf64_data_size: Option<u64>) -> Vec<u8> {
+        let data = test_files::pcm16(samples);
+        let Some(data_size) = rf64_data_size else {
+            return test_files::wav_bytes(48_000, 2, 16, &data);
+        };
+        let fmt = test_files::wav_fmt(48_000, 2, 16);
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RF64");
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend_from_slice(b"ds64");
+        bytes.extend_from_slice(&28u32.to_le_bytes());
+        bytes.extend_from_slice(&(data_size + 72).to_le_bytes());
+        bytes.extend_from_slice(&data_size.to_le_bytes());
+        bytes.extend_from_slice(&(data_size / 4).to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(b"fmt ");
+        bytes.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&fmt);
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&data);
+        bytes
+    }
+
+    fn write_wav(bytes: &[u8]) -> NamedTempFile {
+        let mut file = tempfile::Builder::new().suffix(".wav").tempfile().unwrap();
+        std::io::Write::write_all(&mut file, bytes).unwrap();
+        file
+    }
+
+    fn decode_all(decoder: &mut WavDecoder) -> Vec<f32> {
+        let mut samples = Vec::new();
+        while let Some(buffer) = decoder.decode_next().unwrap() {
+            samples.extend(buffer.samples);
+        }
+        samples
+    }
+
+    #[test]
+    fn test_read_rf64_sizes() {
+        let rf64 = wav_bytes(&[0; 8], Some(6 << 30));
+        let sizes = read_rf64_sizes(&mut symphonia::core::io::BufReader::new(&rf64)).unwrap().unwrap();
+        assert_eq!(sizes.data_size, 6 << 30);
+        assert_eq!(sizes.sample_count, (6 << 30) / 4);
+
+        let classic = wav_bytes(&[0; 8], None);
+        assert!(read_rf64_sizes(&mut symphonia::core::io::BufReader::new(&classic)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rf64_header_beyond_4gb_does_not_overflow() {
+        // A 6 GiB RF64 header stub followed by only 100 frames of audio
+        let samples: Vec<i16> = (0..200).map(|i| i * 100).collect();
+        let file = write_wav(&wav_bytes(&samples, Some(6 << 30)));
+
+        let mut decoder = WavDecoder::new(file.path()).expect("RF64 file should open");
+        assert_eq!(decoder.sample_rate(), 48_000);
+        assert_eq!(decoder.bit_depth(), 16);
+        assert_eq!(AudioDecoder::channels(&decoder), 2);
+        let expected = Duration::from_secs_f64((6u64 << 30) as f64 / 4.0 / 48_000.0);
+        assert!((decoder.duration().unwrap().as_secs_f64() - expected.as_secs_f64()).abs() < 0.001);
+
+        // The truncated stub decodes to its real length and then ends cleanly
+        let decoded = decode_all(&mut decoder);
+        assert_eq!(decoded.len(), samples.len());
+        assert!((decoded[1] - 100.0 / 32768.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_rf64_decodes_like_classic_wav() {
+        let samples: Vec<i16> = (0..9600).map(|i| (i % 1000) as i16 * 30).collect();
+        let classic_file = write_wav(&wav_bytes(&samples, None));
+        let rf64_file = write_wav(&wav_bytes(&samples, Some(samples.len() as u64 * 2)));
+
+        let mut classic = WavDecoder::new(classic_file.path()).unwrap();
+        let mut rf64 = WavDecoder::new(rf64_file.path()).unwrap();
+        assert_eq!(classic.duration(), rf64.duration());
+        assert_eq!(classic.sample_rate(), rf64.sample_rate());
+        assert_eq!(classic.bit_depth(), rf64.bit_depth());
+        assert_eq!(decode_all(&mut classic), decode_all(&mut rf64));
+
+        // Seeking lands on the same frame in both formats
+        classic.seek(Duration::from_millis(50)).unwrap();
+        rf64.seek(Duration::from_millis(50)).unwrap();
+        let classic_next = classic.decode_next().unwrap().unwrap();
+        let rf64_next = rf64.decode_next().unwrap().unwrap();
+        assert_eq!(classic_next.samples[..2], rf64_next.samples[..2]);
+    }
+
+    #[test]
+    fn test_seek_is_sample_accurate() {
+        // The left channel counts frames, the right is silent
+        let samples: Vec<i16> = (0..9600).flat_map(|frame| [frame as i16, 0]).collect();
+        for rf64_data_size in [None, Some(samples.len() as u64 * 2)] {
+            let file = write_wav(&wav_bytes(&samples, rf64_data_size));
+            let mut decoder = WavDecoder::new(file.path()).unwrap();
+            assert!(decoder.supports_seek());
+
+            // 50 ms is frame 2400, partway into a packet
+            decoder.seek(Duration::from_millis(50)).unwrap();
+            let decoded = decode_all(&mut decoder);
+            assert_eq!(decoded[0], 2400.0 / 32768.0);
+            assert_eq!(decoded.len(), (9600 - 2400) * 2);
+        }
+    }
+
+    #[test]
+    fn test_full_report() {
+        // One second of 16-bit stereo at 48 kHz
+        let bytes = wav_bytes(&vec![0; 96_000], None);
+        let file = write_wav(&bytes);
+
+        let report = MetadataExtractor::full_report(file.path()).unwrap();
+        assert_eq!(report.container, "RIFF WAVE");
+        assert_eq!(report.codec, "PCM Signed 16-bit Little-Endian Interleaved");
+        assert_eq!((report.sample_rate, report.bit_depth, report.channels), (Some(48_000), Some(16), Some(2)));
+        assert_eq!(report.duration, Duration::from_secs(1));
+        assert_eq!(report.file_size, bytes.len() as u64);
+        assert_eq!(report.average_bitrate(), Some(1_536));
+        assert_eq!((report.encoder, report.md5), (None, None));
+    }
+
     #[test]
     fn test_wav_decoder_new_with_nonexistent_file() {
         let result = WavDecoder::new("/nonexistent/file.wav");
@@ -0,0 +1,778 @@
+/*!
+WavPack decoder backed by libwavpack, behind the `wavpack` cargo feature.
+
+symphonia 0.5 has no WavPack reader, so the reference C library does the decoding: integer
+and float samples at any rate and channel count, joint stereo and every decorrelation mode
+the `wavpack` encoder writes. Hybrid files play lossy, or lossless when their `.wvc`
+correction file sits next to them. DSD files are rejected as unsupported.
+
+Building with `--features wavpack` needs libwavpack 5.0 or later; `build.rs` finds it through
+pkg-config and stops the build if it is missing. Without the feature `.wv` files are still
+recognised, but opening one reports that this build cannot decode WavPack.
+
+Tags come from the APEv2 footer, read here so they share the common tag mapping.
+
+The tests encode their streams with libwavpack's own encoder and only run with the feature.
+*/
+
+#[cfg(feature = "wavpack")]
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+#[cfg(feature = "wavpack")]
+use std::os::raw::c_char;
+use std::path::Path;
+#[cfg(feature = "wavpack")]
+use std::ptr::NonNull;
+use std::time::Duration;
+
+use symphonia::core::meta::{StandardTagKey, Value};
+
+use crate::audio::{AudioBuffer, AudioCodec, AudioDecoder, AudioMetadata, MetadataExtractor};
+use crate::error::DecodeError;
+
+/// Frames unpacked per `decode_next` call
+const FRAMES_PER_CHUNK: usize = 4096;
+/// Largest APEv2 tag read from the footer
+const MAX_TAG_SIZE: u64 = 1 << 24;
+/// libwavpack writes at most 80 characters of error text
+#[cfg(feature = "wavpack")]
+const ERROR_BUFFER_SIZE: usize = 81;
+
+#[cfg(feature = "wavpack")]
+mod ffi {
+    use std::os::raw::{c_char, c_int};
+
+    #[repr(C)]
+    pub struct WavpackContext {
+        _private: [u8; 0],
+    }
+
+    /// Open the `.wvc` correction file next to a hybrid file
+    pub const OPEN_WVC: c_int = 0x1;
+    /// Scale float samples to +/-1.0 whatever exponent they were stored with
+    pub const OPEN_NORMALIZE: c_int = 0x10;
+    /// The path is UTF-8 rather than the ANSI code page (Windows only)
+    #[cfg(windows)]
+    pub const OPEN_FILE_UTF8: c_int = 0x80;
+
+    pub const MODE_FLOAT: c_int = 0x8;
+
+    #[link(name = "wavpack")]
+    extern "C" {
+        pub fn WavpackOpenFileInput(
+            infilename: *const c_char,
+            error: *mut c_char,
+            flags: c_int,
+            norm_offset: c_int,
+        ) -> *mut WavpackContext;
+        pub fn WavpackCloseFile(wpc: *mut WavpackContext) -> *mut WavpackContext;
+        pub fn WavpackGetMode(wpc: *mut WavpackContext) -> c_int;
+        pub fn WavpackGetNumSamples64(wpc: *mut WavpackContext) -> i64;
+        pub fn WavpackGetSampleRate(wpc: *mut WavpackContext) -> u32;
+        pub fn WavpackGetBitsPerSample(wpc: *mut WavpackContext) -> c_int;
+        pub fn WavpackGetBytesPerSample(wpc: *mut WavpackContext) -> c_int;
+        pub fn WavpackGetNumChannels(wpc: *mut WavpackContext) -> c_int;
+        pub fn WavpackGetNumErrors(wpc: *mut WavpackContext) -> c_int;
+        pub fn WavpackUnpackSamples(wpc: *mut WavpackContext, buffer: *mut i32, samples: u32) -> u32;
+        pub fn WavpackSeekSample64(wpc: *mut WavpackContext, sample: i64) -> c_int;
+    }
+
+    #[cfg(test)]
+    pub use encoder::*;
+
+    /// The encoder half of the library, used by the tests to write reference streams
+    #[cfg(test)]
+    mod encoder {
+        use super::WavpackContext;
+        use std::os::raw::{c_char, c_int, c_uchar, c_void};
+
+        pub const CONFIG_JOINT_STEREO: c_int = 0x10;
+        pub const CONFIG_HIGH_FLAG: c_int = 0x800;
+        pub const CONFIG_VERY_HIGH_FLAG: c_int = 0x1000;
+        pub const CONFIG_JOINT_OVERRIDE: c_int = 0x10000;
+        pub const CONFIG_EXTRA_MODE: c_int = 0x200_0000;
+
+        pub type WavpackBlockOutput = extern "C" fn(id: *mut c_void, data: *mut c_void, bcount: i32) -> c_int;
+
+        /// `WavpackConfig` from wavpack.h (5.x layout)
+        #[repr(C)]
+        pub struct WavpackConfig {
+            pub bitrate: f32,
+            pub shaping_weight: f32,
+            pub bits_per_sample: c_int,
+            pub bytes_per_sample: c_int,
+            pub qmode: c_int,
+            pub flags: c_int,
+            pub xmode: c_int,
+            pub num_channels: c_int,
+            pub float_norm_exp: c_int,
+            pub block_samples: i32,
+            pub worker_threads: i32,
+            pub sample_rate: i32,
+            pub channel_mask: i32,
+            pub md5_checksum: [c_uchar; 16],
+            pub md5_read: c_uchar,
+            pub num_tag_strings: c_int,
+            pub tag_strings: *mut *mut c_char,
+        }
+
+        #[link(name = "wavpack")]
+        extern "C" {
+            pub fn WavpackOpenFileOutput(
+                blockout: WavpackBlockOutput,
+                wv_id: *mut c_void,
+                wvc_id: *mut c_void,
+            ) -> *mut WavpackContext;
+            pub fn WavpackSetConfiguration64(
+                wpc: *mut WavpackContext,
+                config: *mut WavpackConfig,
+                total_samples: i64,
+                chan_ids: *const c_uchar,
+            ) -> c_int;
+            pub fn WavpackPackInit(wpc: *mut WavpackContext) -> c_int;
+            pub fn WavpackPackSamples(wpc: *mut WavpackContext, sample_buffer: *mut i32, sample_count: u32) -> c_int;
+            pub fn WavpackFlushSamples(wpc: *mut WavpackContext) -> c_int;
+            pub fn WavpackAppendTagItem(
+                wpc: *mut WavpackContext,
+                item: *const c_char,
+                value: *const c_char,
+                vsize: c_int,
+            ) -> c_int;
+            pub fn WavpackWriteTag(wpc: *mut WavpackContext) -> c_int;
+        }
+    }
+}
+
+/// An open libwavpack input context, closed on drop
+#[cfg(feature = "wavpack")]
+struct Context(NonNull<ffi::WavpackContext>);
+
+/// Without the `wavpack` feature no context can be opened, so no decoder is ever built
+#[cfg(not(feature = "wavpack"))]
+enum Context {}
+
+/// Stream format reported when a file is opened
+struct StreamInfo {
+    total_samples: Option<u64>,
+    float: bool,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    bytes_per_sample: u32,
+    channels: u16,
+}
+
+// The context is owned by one decoder and only touched through `&mut self`
+#[cfg(feature = "wavpack")]
+unsafe impl Send for Context {}
+
+#[cfg(feature = "wavpack")]
+impl Context {
+    fn open(path: &Path) -> Result<(Self, StreamInfo), DecodeError> {
+        let c_path = c_path(path)?;
+        let mut error = [0 as c_char; ERROR_BUFFER_SIZE];
+        let context = unsafe {
+            ffi::WavpackOpenFileInput(c_path.as_ptr(), error.as_mut_ptr(), open_flags(), 0)
+        };
+        let context = NonNull::new(context).map(Context).ok_or_else(|| {
+            let reason = unsafe { std::ffi::CStr::from_ptr(error.as_ptr()) }.to_string_lossy();
+            DecodeError::UnsupportedFormat {
+                format: format!("WavPack: {}", reason),
+            }
+        })?;
+
+        let wpc = context.0.as_ptr();
+        let (mode, total, sample_rate, bits, bytes, channels) = unsafe {
+            (
+                ffi::WavpackGetMode(wpc),
+                ffi::WavpackGetNumSamples64(wpc),
+                ffi::WavpackGetSampleRate(wpc),
+                ffi::WavpackGetBitsPerSample(wpc),
+                ffi::WavpackGetBytesPerSample(wpc),
+                ffi::WavpackGetNumChannels(wpc),
+            )
+        };
+        if sample_rate == 0 || channels <= 0 || !(1..=4).contains(&bytes) {
+            return Err(DecodeError::UnsupportedFormat {
+                format: format!("WavPack stream with {} Hz, {} channels, {} bytes per sample", sample_rate, channels, bytes),
+            });
+        }
+
+        let info = StreamInfo {
+            total_samples: u64::try_from(total).ok(),
+            float: mode & ffi::MODE_FLOAT != 0,
+            sample_rate,
+            bits_per_sample: bits as u16,
+            bytes_per_sample: bytes as u32,
+            channels: channels as u16,
+        };
+        Ok((context, info))
+    }
+
+    /// Unpack up to `frames` interleaved frames, returning how many came back and the
+    /// running count of blocks that failed their CRC check
+    fn unpack(&mut self, buffer: &mut [i32], frames: usize) -> (usize, i32) {
+        let wpc = self.0.as_ptr();
+        unsafe {
+            let unpacked = ffi::WavpackUnpackSamples(wpc, buffer.as_mut_ptr(), frames as u32) as usize;
+            (unpacked, ffi::WavpackGetNumErrors(wpc))
+        }
+    }
+
+    /// libwavpack finds the block holding the target and decodes up to it
+    fn seek(&mut self, sample: u64) -> bool {
+        unsafe { ffi::WavpackSeekSample64(self.0.as_ptr(), sample as i64) != 0 }
+    }
+}
+
+#[cfg(not(feature = "wavpack"))]
+impl Context {
+    fn open(_path: &Path) -> Result<(Self, StreamInfo), DecodeError> {
+        Err(DecodeError::UnsupportedFormat {
+            format: "WavPack (this build has no libwavpack; rebuild with `--features wavpack`)".to_string(),
+        })
+    }
+
+    fn unpack(&mut self, _buffer: &mut [i32], _frames: usize) -> (usize, i32) {
+        match *self {}
+    }
+
+    fn seek(&mut self, _sample: u64) -> bool {
+        match *self {}
+    }
+}
+
+#[cfg(feature = "wavpack")]
+impl Drop for Context {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::WavpackCloseFile(self.0.as_ptr());
+        }
+    }
+}
+
+/// WavPack audio decoder for integer and 32-bit float streams
+///
+/// DSD streams are rejected as unsupported.
+pub struct WavPackDecoder {
+    context: Context,
+    buffer: Vec<i32>,
+    /// Next sample to be unpacked
+    position: u64,
+    /// Samples in the stream, when the first block records them
+    total_samples: Option<u64>,
+    /// CRC and bitstream errors libwavpack had counted before the last unpack
+    errors: i32,
+    float: bool,
+    /// Integer samples are right-justified in the stored bytes per sample
+    scale: f32,
+    metadata: AudioMetadata,
+    sample_rate: u32,
+    bit_depth: u16,
+    channels: u16,
+}
+
+impl WavPackDecoder {
+    /// Create a new WavPack decoder for the given file path
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, DecodeError> {
+        let path = path.as_ref();
+        let mut file = File::open(path).map_err(|e| {
+            DecodeError::DecodeFailed(format!("Failed to open file: {}", e))
+        })?;
+        let metadata = read_ape_tag(&mut file).unwrap_or_default();
+        drop(file);
+
+        let (context, info) = Context::open(path)?;
+        Ok(WavPackDecoder {
+            context,
+            buffer: Vec::new(),
+            position: 0,
+            total_samples: info.total_samples,
+            errors: 0,
+            float: info.float,
+            scale: 1.0 / (1u64 << (info.bytes_per_sample * 8 - 1)) as f32,
+            metadata,
+            sample_rate: info.sample_rate,
+            bit_depth: info.bits_per_sample,
+            channels: info.channels,
+        })
+    }
+}
+
+impl AudioDecoder for WavPackDecoder {
+    fn decode_next(&mut self) -> Result<Option<AudioBuffer>, DecodeError> {
+        let wanted = match self.total_samples {
+            Some(total) => total.saturating_sub(self.position).min(FRAMES_PER_CHUNK as u64) as usize,
+            None => FRAMES_PER_CHUNK,
+        };
+        if wanted == 0 {
+            return Ok(None);
+        }
+
+        let channels = self.channels as usize;
+        self.buffer.resize(wanted * channels, 0);
+        let (frames, errors) = self.context.unpack(&mut self.buffer, wanted);
+        let first_sample = self.position;
+        // libwavpack's read position has moved past the bad block, so ours follows it
+        self.position += frames as u64;
+
+        if errors > self.errors {
+            self.errors = errors;
+            return Err(DecodeError::CorruptedFile(format!(
+                "WavPack block near sample {} failed its CRC check",
+                first_sample
+            )));
+        }
+        if frames == 0 {
+            return match self.total_samples {
+                Some(total) => Err(DecodeError::CorruptedFile(format!(
+                    "WavPack stream ends at sample {} of {}",
+                    self.position, total
+                ))),
+                None => Ok(None),
+            };
+        }
+
+        let unpacked = &self.buffer[..frames * channels];
+        let samples = if self.float {
+            unpacked.iter().map(|&sample| f32::from_bits(sample as u32)).collect()
+        } else {
+            unpacked.iter().map(|&sample| sample as f32 * self.scale).collect()
+        };
+
+        Ok(Some(AudioBuffer {
+            samples,
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            frames,
+        }))
+    }
+
+    fn seek(&mut self, position: Duration) -> Result<(), DecodeError> {
+        let target = (position.as_nanos() * self.sample_rate as u128 / 1_000_000_000) as u64;
+        if let Some(total) = self.total_samples {
+            if target >= total {
+                self.position = total;
+                return Ok(());
+            }
+        }
+
+        if !self.context.seek(target) {
+            return Err(DecodeError::SeekError(format!("WavPack seek to sample {} failed", target)));
+        }
+        self.position = target;
+        Ok(())
+    }
+
+    fn metadata(&self) -> &AudioMetadata {
+        &self.metadata
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.total_samples.map(|total| {
+            Duration::from_nanos((total as u128 * 1_000_000_000 / self.sample_rate as u128) as u64)
+        })
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn bit_depth(&self) -> u16 {
+        self.bit_depth
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn codec(&self) -> AudioCodec {
+        AudioCodec::WavPack
+    }
+}
+
+#[cfg(all(feature = "wavpack", windows))]
+fn open_flags() -> i32 {
+    ffi::OPEN_WVC | ffi::OPEN_NORMALIZE | ffi::OPEN_FILE_UTF8
+}
+
+#[cfg(all(feature = "wavpack", not(windows)))]
+fn open_flags() -> i32 {
+    ffi::OPEN_WVC | ffi::OPEN_NORMALIZE
+}
+
+/// The path as libwavpack's `fopen` expects it: raw bytes on Unix, UTF-8 elsewhere
+#[cfg(feature = "wavpack")]
+fn c_path(path: &Path) -> Result<CString, DecodeError> {
+    #[cfg(unix)]
+    let bytes = {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes().to_vec()
+    };
+    #[cfg(not(unix))]
+    let bytes = path
+        .to_str()
+        .ok_or_else(|| DecodeError::DecodeFailed(format!("Path is not valid Unicode: {}", path.display())))?
+        .as_bytes()
+        .to_vec();
+
+    CString::new(bytes).map_err(|_| DecodeError::DecodeFailed(format!("Path contains a NUL byte: {}", path.display())))
+}
+
+/// Read an APEv2 tag from the end of the file, before an optional ID3v1 tag
+fn read_ape_tag(file: &mut File) -> Option<AudioMetadata> {
+    let len = file.seek(SeekFrom::End(0)).ok()?;
+    let mut end = len;
+    if len >= 128 {
+        let mut id3 = [0u8; 3];
+        file.seek(SeekFrom::Start(len - 128)).ok()?;
+        file.read_exact(&mut id3).ok()?;
+        if &id3 == b"TAG" {
+            end -= 128;
+        }
+    }
+
+    let mut footer = [0u8; 32];
+    file.seek(SeekFrom::Start(end.checked_sub(32)?)).ok()?;
+    file.read_exact(&mut footer).ok()?;
+    if &footer[..8] != b"APETAGEX" {
+        return None;
+    }
+    let tag_size = u32::from_le_bytes([footer[12], footer[13], footer[14], footer[15]]) as u64;
+    let item_count = u32::from_le_bytes([footer[16], footer[17], footer[18], footer[19]]);
+    if tag_size < 32 || tag_size > end || tag_size > MAX_TAG_SIZE {
+        return None;
+    }
+
+    let mut items = vec![0u8; tag_size as usize - 32];
+    file.seek(SeekFrom::Start(end - tag_size)).ok()?;
+    file.read_exact(&mut items).ok()?;
+
+    let mut metadata = AudioMetadata::new();
+    let mut rest = items.as_slice();
+    for _ in 0..item_count {
+        if rest.len() < 8 {
+            break;
+        }
+        let value_len = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let item_flags = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]);
+        let key_len = rest[8..].iter().position(|&byte| byte == 0)?;
+        let key = String::from_utf8_lossy(&rest[8..8 + key_len]).into_owned();
+        let value_start = 8 + key_len + 1;
+        let value = rest.get(value_start..value_start + value_len)?;
+        rest = &rest[value_start + value_len..];
+
+        // Only UTF-8 text items; binary and external items are skipped
+        if (item_flags >> 1) & 0x3 != 0 {
+            continue;
+        }
+        apply_ape_item(&key, String::from_utf8_lossy(value).trim_end_matches('\0'), &mut metadata);
+    }
+    Some(metadata)
+}
+
+fn apply_ape_item(key: &str, value: &str, metadata: &mut AudioMetadata) {
+    // Item keys are case-insensitive; others keep the case they were written with
+    let name = key.to_ascii_lowercase();
+    let common_key = match name.as_str() {
+        "track" => Some(StandardTagKey::TrackNumber),
+        "disc" => Some(StandardTagKey::DiscNumber),
+        "composer" => Some(StandardTagKey::Composer),
+        "bpm" => Some(StandardTagKey::Bpm),
+        "album artist" | "albumartist" => Some(StandardTagKey::AlbumArtist),
+        _ => None,
+    };
+    if let Some(common_key) = common_key {
+        MetadataExtractor::apply_common_tag(common_key, &Value::String(value.to_string()), metadata);
+        return;
+    }
+
+    match name.as_str() {
+        "title" => metadata.title = Some(value.to_string()),
+        "artist" => metadata.artist = Some(value.to_string()),
+        "album" => metadata.album = Some(value.to_string()),
+        "genre" => metadata.genre = Some(value.to_string()),
+        "comment" => metadata.comment = Some(value.to_string()),
+        "lyrics" => metadata.lyrics = Some(value.to_string()),
+        "year" => metadata.year = value.get(..4).and_then(|year| year.parse().ok()),
+        "compilation" => metadata.compilation = value == "1",
+        _ => metadata.extra_tags.push((key.to_string(), value.to_string())),
+    }
+}
+
+#[cfg(all(test, feature = "wavpack"))]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::os::raw::{c_int, c_void};
+    use std::ptr;
+    use tempfile::NamedTempFile;
+
+    /// Block header flags checked in the encoded streams
+    const JOINT_STEREO: u32 = 0x10;
+
+    extern "C" fn collect_block(id: *mut c_void, data: *mut c_void, bcount: i32) -> c_int {
+        let out = unsafe { &mut *(id as *mut Vec<u8>) };
+        out.extend_from_slice(unsafe { std::slice::from_raw_parts(data as *const u8, bcount as usize) });
+        1
+    }
+
+    /// Encoder settings for integer PCM, as `wavpack` would pick them for a WAV input
+    fn config(sample_rate: i32, channels: i32, bits: i32) -> ffi::WavpackConfig {
+        // wavpack.h asks for a zeroed config before filling it in
+        let mut config: ffi::WavpackConfig = unsafe { std::mem::zeroed() };
+        config.sample_rate = sample_rate;
+        config.num_channels = channels;
+        config.channel_mask = if channels == 1 { 0x4 } else { 0x3 };
+        config.bits_per_sample = bits;
+        config.bytes_per_sample = bits / 8;
+        config
+    }
+
+    /// Encode interleaved samples with libwavpack, appending an APEv2 tag when given items
+    fn encode(samples: &[i32], mut config: ffi::WavpackConfig, tags: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        let frames = samples.len() / config.num_channels as usize;
+        let mut buffer = samples.to_vec();
+        unsafe {
+            let wpc = ffi::WavpackOpenFileOutput(collect_block, &mut bytes as *mut Vec<u8> as *mut c_void, ptr::null_mut());
+            assert!(!wpc.is_null());
+            assert_ne!(ffi::WavpackSetConfiguration64(wpc, &mut config, frames as i64, ptr::null()), 0);
+            assert_ne!(ffi::WavpackPackInit(wpc), 0);
+            assert_ne!(ffi::WavpackPackSamples(wpc, buffer.as_mut_ptr(), frames as u32), 0);
+            assert_ne!(ffi::WavpackFlushSamples(wpc), 0);
+            for (key, value) in tags {
+                let key = CString::new(*key).unwrap();
+                let added = ffi::WavpackAppendTagItem(wpc, key.as_ptr(), value.as_ptr() as *const c_char, value.len() as c_int);
+                assert_ne!(added, 0);
+            }
+            if !tags.is_empty() {
+                assert_ne!(ffi::WavpackWriteTag(wpc), 0);
+            }
+            ffi::WavpackCloseFile(wpc);
+        }
+        bytes
+    }
+
+    fn write_wv(bytes: &[u8]) -> NamedTempFile {
+        let mut file = tempfile::Builder::new().suffix(".wv").tempfile().unwrap();
+        file.write_all(bytes).unwrap();
+        file
+    }
+
+    fn decode_all(decoder: &mut WavPackDecoder) -> Vec<f32> {
+        let mut samples = Vec::new();
+        while let Some(buffer) = decoder.decode_next().unwrap() {
+            assert_eq!(buffer.samples.len(), buffer.frames * buffer.channels as usize);
+            samples.extend(buffer.samples);
+        }
+        samples
+    }
+
+    /// Flags of every block header in an encoded stream
+    fn block_flags(bytes: &[u8]) -> Vec<u32> {
+        let mut flags = Vec::new();
+        let mut offset = 0;
+        while offset + 32 <= bytes.len() && &bytes[offset..offset + 4] == b"wvpk" {
+            let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+            flags.push(u32::from_le_bytes(bytes[offset + 24..offset + 28].try_into().unwrap()));
+            offset += size + 8;
+        }
+        flags
+    }
+
+    /// One second of 16-bit stereo at 44.1 kHz with distinct channels
+    fn stereo_sine() -> Vec<i32> {
+        (0..44_100)
+            .flat_map(|n| {
+                let t = n as f64 / 44_100.0;
+                let left = (12_000.0 * (2.0 * std::f64::consts::PI * 440.0 * t).sin()) as i32;
+                let right = (9_000.0 * (2.0 * std::f64::consts::PI * 660.0 * t).sin()) as i32 + left / 3;
+                [left, right]
+            })
+            .collect()
+    }
+
+    fn scaled(source: &[i32], bits: u32) -> Vec<f32> {
+        let full_scale = (1u64 << (bits - 1)) as f32;
+        source.iter().map(|&sample| sample as f32 / full_scale).collect()
+    }
+
+    #[test]
+    fn test_decode_one_second_stereo() {
+        let source = stereo_sine();
+        let file = write_wv(&encode(&source, config(44_100, 2, 16), &[]));
+
+        let mut decoder = WavPackDecoder::new(file.path()).unwrap();
+        assert_eq!(decoder.sample_rate(), 44_100);
+        assert_eq!(decoder.channels(), 2);
+        assert_eq!(decoder.bit_depth(), 16);
+        assert_eq!(decoder.duration(), Some(Duration::from_secs(1)));
+        assert_eq!(decoder.codec(), AudioCodec::WavPack);
+
+        let decoded = decode_all(&mut decoder);
+        assert_eq!(decoded.len() / 2, 44_100);
+        assert_eq!(decoded, scaled(&source, 16));
+    }
+
+    #[test]
+    fn test_seek_is_sample_accurate() {
+        let source = stereo_sine();
+        let mut config = config(44_100, 2, 16);
+        config.block_samples = 11_025;
+        let file = write_wv(&encode(&source, config, &[]));
+        let mut decoder = WavPackDecoder::new(file.path()).unwrap();
+
+        // 0.6 s is sample 26460, inside the third block
+        decoder.seek(Duration::from_millis(600)).unwrap();
+        let buffer = decoder.decode_next().unwrap().unwrap();
+        assert_eq!(buffer.samples[0], source[26_460 * 2] as f32 / 32_768.0);
+        assert_eq!(buffer.samples[1], source[26_460 * 2 + 1] as f32 / 32_768.0);
+        assert_eq!(buffer.frames + decode_all(&mut decoder).len() / 2, 44_100 - 26_460);
+
+        decoder.seek(Duration::ZERO).unwrap();
+        assert_eq!(decode_all(&mut decoder), scaled(&source, 16));
+
+        decoder.seek(Duration::from_secs(2)).unwrap();
+        assert!(decoder.decode_next().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_decode_24_bit_mono_with_silence() {
+        // Leading silence exercises the zero-run coding
+        let source: Vec<i32> = (0..96_000).map(|n| if n < 40_000 { 0 } else { (n - 40_000) * 97 - 2_000_000 }).collect();
+        let file = write_wv(&encode(&source, config(96_000, 1, 24), &[]));
+
+        let mut decoder = WavPackDecoder::new(file.path()).unwrap();
+        assert_eq!(decoder.sample_rate(), 96_000);
+        assert_eq!(decoder.channels(), 1);
+        assert_eq!(decoder.bit_depth(), 24);
+        assert_eq!(decode_all(&mut decoder), scaled(&source, 24));
+    }
+
+    #[test]
+    fn test_decode_32_bit_integer() {
+        let source: Vec<i32> = (0..48_000).map(|n| (1_500_000_000.0 * (n as f64 / 30.0).sin()) as i32 | 1).collect();
+        let file = write_wv(&encode(&source, config(48_000, 1, 32), &[]));
+
+        let mut decoder = WavPackDecoder::new(file.path()).unwrap();
+        assert_eq!(decoder.bit_depth(), 32);
+        assert_eq!(decode_all(&mut decoder), scaled(&source, 32));
+    }
+
+    #[test]
+    fn test_decode_float_stream() {
+        let source: Vec<f32> = (0..48_000).map(|n| 0.5 * (n as f32 / 20.0).sin()).collect();
+        let mut config = config(48_000, 1, 32);
+        config.float_norm_exp = 127;
+        let bits: Vec<i32> = source.iter().map(|sample| sample.to_bits() as i32).collect();
+        let file = write_wv(&encode(&bits, config, &[]));
+
+        let mut decoder = WavPackDecoder::new(file.path()).unwrap();
+        assert_eq!(decoder.bit_depth(), 32);
+        assert_eq!(decoder.duration(), Some(Duration::from_secs(1)));
+        assert_eq!(decode_all(&mut decoder), source);
+    }
+
+    #[test]
+    fn test_decode_joint_stereo_multi_block() {
+        let source = stereo_sine();
+        let mut config = config(44_100, 2, 16);
+        config.flags = ffi::CONFIG_JOINT_OVERRIDE | ffi::CONFIG_JOINT_STEREO;
+        config.block_samples = 4_410;
+        let bytes = encode(&source, config, &[]);
+
+        let flags = block_flags(&bytes);
+        assert_eq!(flags.len(), 10);
+        assert!(flags.iter().all(|flags| flags & JOINT_STEREO != 0));
+
+        let mut decoder = WavPackDecoder::new(write_wv(&bytes).path()).unwrap();
+        assert_eq!(decode_all(&mut decoder), scaled(&source, 16));
+    }
+
+    #[test]
+    fn test_decode_high_and_extra_modes() {
+        // -hh and -x6 pick from every decorrelation term, including 17, 18 and the negative ones
+        let source = stereo_sine();
+        let modes = [
+            (ffi::CONFIG_HIGH_FLAG, 0),
+            (ffi::CONFIG_HIGH_FLAG | ffi::CONFIG_VERY_HIGH_FLAG, 0),
+            (ffi::CONFIG_HIGH_FLAG | ffi::CONFIG_VERY_HIGH_FLAG | ffi::CONFIG_EXTRA_MODE, 6),
+        ];
+        for (flags, xmode) in modes {
+            let mut config = config(44_100, 2, 16);
+            config.flags = flags;
+            config.xmode = xmode;
+            let file = write_wv(&encode(&source, config, &[]));
+
+            let mut decoder = WavPackDecoder::new(file.path()).unwrap();
+            assert_eq!(decode_all(&mut decoder), scaled(&source, 16), "flags {:#x}", flags);
+        }
+    }
+
+    #[test]
+    fn test_rejects_corrupt_and_unknown_files() {
+        let mut bytes = encode(&stereo_sine()[..2_000], config(44_100, 2, 16), &[]);
+        let middle = bytes.len() / 2 + 16;
+        bytes[middle] ^= 0xff;
+        let file = write_wv(&bytes);
+        let mut decoder = WavPackDecoder::new(file.path()).unwrap();
+        assert!(matches!(decoder.decode_next(), Err(DecodeError::CorruptedFile(_))));
+
+        let file = write_wv(b"RIFF not a wavpack stream");
+        assert!(matches!(WavPackDecoder::new(file.path()), Err(DecodeError::UnsupportedFormat { .. })));
+        assert!(WavPackDecoder::new("/nonexistent/file.wv").is_err());
+    }
+
+    #[test]
+    fn test_full_report() {
+        let file = write_wv(&encode(&stereo_sine()[..2_000], config(44_100, 2, 16), &[]));
+
+        let report = MetadataExtractor::full_report(file.path()).unwrap();
+        assert_eq!((report.container.as_str(), report.codec.as_str()), ("WavPack", "WavPack"));
+        assert_eq!((report.sample_rate, report.bit_depth, report.channels), (Some(44_100), Some(16), Some(2)));
+        assert_eq!((report.duration.as_secs_f64() * 44_100.0).round() as usize, 1_000);
+    }
+
+    #[test]
+    fn test_ape_tag_metadata() {
+        let tags = [
+            ("Title", "Test Song"),
+            ("Artist", "Test Artist"),
+            ("Album Artist", "Various Artists"),
+            ("Track", "3/12"),
+            ("Year", "2021-05-01"),
+            ("Catalog", "CAT-001"),
+        ];
+        let file = write_wv(&encode(&stereo_sine()[..2_000], config(44_100, 2, 16), &tags));
+
+        let mut decoder = WavPackDecoder::new(file.path()).unwrap();
+        let metadata = decoder.metadata();
+        assert_eq!(metadata.title.as_deref(), Some("Test Song"));
+        assert_eq!(metadata.artist.as_deref(), Some("Test Artist"));
+        assert_eq!(metadata.album_artist.as_deref(), Some("Various Artists"));
+        assert_eq!(metadata.track_number, Some(3));
+        assert_eq!(metadata.total_tracks, Some(12));
+        assert_eq!(metadata.year, Some(2021));
+        assert_eq!(metadata.extra_tags, vec![("Catalog".to_string(), "CAT-001".to_string())]);
+        // The tag ends the block stream
+        assert_eq!(decode_all(&mut decoder).len(), 2_000);
+    }
+}
+
+#[cfg(all(test, not(feature = "wavpack")))]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_reports_missing_feature() {
+        let mut file = tempfile::Builder::new().suffix(".wv").tempfile().unwrap();
+        file.write_all(b"wvpk").unwrap();
+        match WavPackDecoder::new(file.path()) {
+            Err(DecodeError::UnsupportedFormat { format }) => assert!(format.contains("--features wavpack")),
+            _ => panic!("WavPack decoded without the wavpack feature"),
+        }
+        assert!(matches!(WavPackDecoder::new("/nonexistent/file.wv"), Err(DecodeError::DecodeFailed(_))));
+    }
+}
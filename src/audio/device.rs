@@ -1,18 +1,266 @@
 use cpal::{Device, Host, SupportedStreamConfig, SampleFormat};
 use cpal::traits::{DeviceTrait, HostTrait};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 use crate::error::AudioError;
 
 /// Information about an audio device's capabilities
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DeviceCapabilities {
     pub name: String,
     pub supported_sample_rates: Vec<u32>,
     pub supported_bit_depths: Vec<u16>,
     pub max_channels: u16,
+    /// Whether the device accepted a direct stream at its native configuration
+    pub supports_exclusive_mode: bool,
+    #[serde(skip)]
     pub default_config: SupportedStreamConfig,
 }
 
+impl DeviceCapabilities {
+    /// Serialize the capabilities as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Highest sample rate the device accepts
+    pub fn max_sample_rate(&self) -> Option<u32> {
+        self.supported_sample_rates.iter().copied().max()
+    }
+
+    /// Widest sample format the device accepts, in bits
+    pub fn max_bit_depth(&self) -> Option<u16> {
+        self.supported_bit_depths.iter().copied().max()
+    }
+}
+
+/// A loudspeaker position in a multichannel layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Speaker {
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    Lfe,
+    BackLeft,
+    BackRight,
+    FrontLeftOfCenter,
+    FrontRightOfCenter,
+    BackCenter,
+    SideLeft,
+    SideRight,
+}
+
+impl Speaker {
+    const ALL: [Speaker; 11] = [
+        Speaker::FrontLeft,
+        Speaker::FrontRight,
+        Speaker::FrontCenter,
+        Speaker::Lfe,
+        Speaker::BackLeft,
+        Speaker::BackRight,
+        Speaker::FrontLeftOfCenter,
+        Speaker::FrontRightOfCenter,
+        Speaker::BackCenter,
+        Speaker::SideLeft,
+        Speaker::SideRight,
+    ];
+
+    /// Short name used in layouts, e.g. "FL"
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            Speaker::FrontLeft => "FL",
+            Speaker::FrontRight => "FR",
+            Speaker::FrontCenter => "FC",
+            Speaker::Lfe => "LFE",
+            Speaker::BackLeft => "BL",
+            Speaker::BackRight => "BR",
+            Speaker::FrontLeftOfCenter => "FLC",
+            Speaker::FrontRightOfCenter => "FRC",
+            Speaker::BackCenter => "BC",
+            Speaker::SideLeft => "SL",
+            Speaker::SideRight => "SR",
+        }
+    }
+
+    /// Where this channel goes on an output without its speaker, in order of preference
+    ///
+    /// Each entry is a set of speakers sharing the channel at a gain. Surrounds fold into the
+    /// front at -3 dB, as the ITU-R BS.775 downmix does; the LFE is left out.
+    fn substitutes(self) -> &'static [(&'static [Speaker], f32)] {
+        use Speaker::*;
+        const K: f32 = std::f32::consts::FRAC_1_SQRT_2;
+        match self {
+            FrontLeft => &[(&[FrontCenter], K)],
+            FrontRight => &[(&[FrontCenter], K)],
+            FrontCenter => &[(&[FrontLeft, FrontRight], K)],
+            Lfe => &[],
+            BackLeft => &[(&[SideLeft], 1.0), (&[FrontLeft], K)],
+            BackRight => &[(&[SideRight], 1.0), (&[FrontRight], K)],
+            FrontLeftOfCenter => &[(&[FrontLeft], 1.0)],
+            FrontRightOfCenter => &[(&[FrontRight], 1.0)],
+            BackCenter => &[(&[BackLeft, BackRight], K), (&[SideLeft, SideRight], K), (&[FrontLeft, FrontRight], 0.5)],
+            SideLeft => &[(&[BackLeft], 1.0), (&[FrontLeft], K)],
+            SideRight => &[(&[BackRight], 1.0), (&[FrontRight], K)],
+        }
+    }
+}
+
+impl std::str::FromStr for Speaker {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Speaker::ALL.into_iter()
+            .find(|speaker| speaker.abbreviation().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown speaker '{}' (expected FL, FR, FC, LFE, BL, BR, FLC, FRC, BC, SL or SR)", s))
+    }
+}
+
+/// The speaker each channel of an interleaved stream plays on
+///
+/// cpal reports only a channel count, so outputs are assumed to use their host's standard
+/// order unless the `channel_layout` setting names the speakers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ChannelMap {
+    speakers: Vec<Speaker>,
+}
+
+impl ChannelMap {
+    /// Layout of decoded audio: FLAC, WAVE and the other decoders order channels as WAVEFORMATEXTENSIBLE does
+    pub fn for_source(channels: u16) -> Option<Self> {
+        Self::wave(channels)
+    }
+
+    /// Layout the host expects on an output with `channels` channels
+    ///
+    /// WASAPI and Core Audio use the WAVE order. ALSA, and the PulseAudio and PipeWire
+    /// plugins behind its default device, put the back pair before the centre and LFE.
+    pub fn for_output(channels: u16) -> Option<Self> {
+        if cfg!(target_os = "linux") {
+            Self::alsa(channels)
+        } else {
+            Self::wave(channels)
+        }
+    }
+
+    /// WAVEFORMATEXTENSIBLE order: FL FR FC LFE BL BR, with the sides last in 6.1 and 7.1
+    pub fn wave(channels: u16) -> Option<Self> {
+        use Speaker::*;
+        let speakers: &[Speaker] = match channels {
+            1 => &[FrontCenter],
+            2 => &[FrontLeft, FrontRight],
+            3 => &[FrontLeft, FrontRight, FrontCenter],
+            4 => &[FrontLeft, FrontRight, BackLeft, BackRight],
+            5 => &[FrontLeft, FrontRight, FrontCenter, BackLeft, BackRight],
+            6 => &[FrontLeft, FrontRight, FrontCenter, Lfe, BackLeft, BackRight],
+            7 => &[FrontLeft, FrontRight, FrontCenter, Lfe, BackCenter, SideLeft, SideRight],
+            8 => &[FrontLeft, FrontRight, FrontCenter, Lfe, BackLeft, BackRight, SideLeft, SideRight],
+            _ => return None,
+        };
+        Some(Self { speakers: speakers.to_vec() })
+    }
+
+    /// ALSA's standard order: FL FR BL BR FC LFE, then the sides
+    pub fn alsa(channels: u16) -> Option<Self> {
+        use Speaker::*;
+        let speakers: &[Speaker] = match channels {
+            5 => &[FrontLeft, FrontRight, BackLeft, BackRight, FrontCenter],
+            6 => &[FrontLeft, FrontRight, BackLeft, BackRight, FrontCenter, Lfe],
+            7 => &[FrontLeft, FrontRight, BackLeft, BackRight, FrontCenter, Lfe, BackCenter],
+            8 => &[FrontLeft, FrontRight, BackLeft, BackRight, FrontCenter, Lfe, SideLeft, SideRight],
+            _ => return Self::wave(channels),
+        };
+        Some(Self { speakers: speakers.to_vec() })
+    }
+
+    pub fn speakers(&self) -> &[Speaker] {
+        &self.speakers
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.speakers.len() as u16
+    }
+
+    /// Gain of each channel of this layout in each channel of `output`, as `gains[output][source]`
+    ///
+    /// Channels go to the same speaker when `output` has it, otherwise to its nearest
+    /// substitutes; see `Speaker::substitutes`.
+    pub fn routing(&self, output: &ChannelMap) -> Vec<Vec<f32>> {
+        let mut gains = vec![vec![0.0; self.speakers.len()]; output.speakers.len()];
+        let position = |speaker: &Speaker| output.speakers.iter().position(|candidate| candidate == speaker);
+        for (source, speaker) in self.speakers.iter().enumerate() {
+            if let Some(target) = position(speaker) {
+                gains[target][source] = 1.0;
+                continue;
+            }
+            let substitute = speaker.substitutes().iter().find_map(|(speakers, gain)| {
+                let targets: Option<Vec<usize>> = speakers.iter().map(position).collect();
+                targets.map(|targets| (targets, *gain))
+            });
+            for (target, gain) in substitute.into_iter().flat_map(|(targets, gain)| targets.into_iter().map(move |target| (target, gain))) {
+                gains[target][source] += gain;
+            }
+        }
+        gains
+    }
+}
+
+impl std::fmt::Display for ChannelMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<&str> = self.speakers.iter().map(|speaker| speaker.abbreviation()).collect();
+        write!(f, "{}", names.join(" "))
+    }
+}
+
+impl std::str::FromStr for ChannelMap {
+    type Err = String;
+
+    /// Speaker names separated by spaces or commas, e.g. "FL FR FC LFE BL BR"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let speakers = s.split([' ', ',']).filter(|name| !name.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<Speaker>, _>>()?;
+        if speakers.is_empty() {
+            return Err("expected speaker names such as FL FR FC LFE BL BR".to_string());
+        }
+        if let Some(repeated) = speakers.iter().enumerate().find_map(|(index, speaker)| speakers[..index].contains(speaker).then_some(speaker)) {
+            return Err(format!("{} is listed twice", repeated.abbreviation()));
+        }
+        Ok(Self { speakers })
+    }
+}
+
+impl TryFrom<String> for ChannelMap {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ChannelMap> for String {
+    fn from(map: ChannelMap) -> Self {
+        map.to_string()
+    }
+}
+
+/// Whether two device names refer to the same device; WASAPI reports the same device with varying case
+pub fn device_names_match(a: &str, b: &str) -> bool {
+    a == b || a.to_lowercase() == b.to_lowercase()
+}
+
+/// Entry for a device name, preferring an exact match over a case-insensitive one
+fn lookup<'a, T>(entries: &'a HashMap<String, T>, name: &str) -> Option<&'a T> {
+    entries.get(name).or_else(|| {
+        entries.iter().find(|(known, _)| device_names_match(known, name)).map(|(_, entry)| entry)
+    })
+}
+
 /// Manages audio device enumeration and selection
 pub struct DeviceManager {
     host: Host,
@@ -66,14 +314,14 @@ impl DeviceManager {
 
     /// Get capabilities for a specific device
     pub fn get_capabilities(&self, device_name: &str) -> Option<&DeviceCapabilities> {
-        self.device_capabilities.get(device_name)
+        lookup(&self.device_capabilities, device_name)
     }
 
     /// Select a device by name, with fallback to default device
     pub fn select_device(&mut self, device_name: Option<&str>) -> Result<(), AudioError> {
         match device_name {
             Some(name) => {
-                if let Some(device) = self.devices.get(name).cloned() {
+                if let Some(device) = lookup(&self.devices, name).cloned() {
                     self.current_device = Some(device);
                     Ok(())
                 } else {
@@ -91,7 +339,7 @@ impl DeviceManager {
     pub fn select_device_with_fallback(&mut self, device_name: Option<&str>) -> Result<(), AudioError> {
         match device_name {
             Some(name) => {
-                if let Some(device) = self.devices.get(name).cloned() {
+                if let Some(device) = lookup(&self.devices, name).cloned() {
                     self.current_device = Some(device);
                     Ok(())
                 } else {
@@ -132,9 +380,15 @@ impl DeviceManager {
         }
     }
 
+    /// Get the name of the system default output device
+    pub fn default_device_name(&self) -> Option<String> {
+        self.host.default_output_device()
+            .and_then(|device| device.name().ok())
+    }
+
     /// Check if a device supports a specific sample rate and bit depth
     pub fn supports_format(&self, device_name: &str, sample_rate: u32, bit_depth: u16) -> bool {
-        if let Some(capabilities) = self.device_capabilities.get(device_name) {
+        if let Some(capabilities) = lookup(&self.device_capabilities, device_name) {
             capabilities.supported_sample_rates.contains(&sample_rate) &&
             capabilities.supported_bit_depths.contains(&bit_depth)
         } else {
@@ -144,7 +398,7 @@ impl DeviceManager {
 
     /// Get the best supported configuration for a device given preferred settings
     pub fn get_best_config(&self, device_name: &str, preferred_sample_rate: Option<u32>, _preferred_bit_depth: Option<u16>) -> Option<SupportedStreamConfig> {
-        let capabilities = self.device_capabilities.get(device_name)?;
+        let capabilities = lookup(&self.device_capabilities, device_name)?;
         
         // For now, return the default config
         // In a more advanced implementation, we would create a custom config
@@ -241,14 +495,32 @@ impl DeviceManager {
             max_channels = default_config.channels();
         }
 
+        let supports_exclusive_mode = Self::probe_exclusive_mode(device, &default_config);
+
         Ok(DeviceCapabilities {
             name: device_name,
             supported_sample_rates: sample_rates,
             supported_bit_depths: bit_depths,
             max_channels,
+            supports_exclusive_mode,
             default_config,
         })
     }
+
+    /// Check whether the device can be opened directly by building (and dropping) a test stream
+    ///
+    /// cpal has no portable exclusive-mode API, so a device that accepts a stream at its native
+    /// format and rate is treated as exclusive-capable. Busy or shared-only devices fail here.
+    fn probe_exclusive_mode(device: &Device, default_config: &SupportedStreamConfig) -> bool {
+        let stream = device.build_output_stream_raw(
+            &default_config.config(),
+            default_config.sample_format(),
+            |_: &mut cpal::Data, _: &cpal::OutputCallbackInfo| {},
+            |_| {},
+            None,
+        );
+        stream.is_ok()
+    }
 }
 
 impl Default for DeviceManager {
@@ -257,9 +529,167 @@ impl Default for DeviceManager {
     }
 }
 
+/// Hot-plug notification for output devices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceEvent {
+    Added(String),
+    Removed(String),
+}
+
+/// Source of output device names polled by `DeviceMonitor`
+pub trait DeviceLister: Send + 'static {
+    fn output_device_names(&self) -> Vec<String>;
+}
+
+/// Lists output devices from the default CPAL host
+struct CpalDeviceLister;
+
+impl DeviceLister for CpalDeviceLister {
+    fn output_device_names(&self) -> Vec<String> {
+        cpal::default_host()
+            .output_devices()
+            .map(|devices| devices.filter_map(|device| device.name().ok()).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Background watcher that reports output devices being connected or disconnected
+pub struct DeviceMonitor {
+    events: Receiver<DeviceEvent>,
+    is_running: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl DeviceMonitor {
+    /// Default interval between device list polls
+    pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+    /// Start monitoring the system's output devices
+    pub fn start() -> Self {
+        Self::with_lister(CpalDeviceLister, Self::POLL_INTERVAL)
+    }
+
+    /// Start monitoring with a custom device source and poll interval
+    pub fn with_lister<L: DeviceLister>(lister: L, interval: Duration) -> Self {
+        let (sender, events) = mpsc::channel();
+        let is_running = Arc::new(AtomicBool::new(true));
+        let running = Arc::clone(&is_running);
+        // Snapshot before returning so changes made right after start() are reported
+        let known = lister.output_device_names();
+
+        let handle = thread::Builder::new()
+            .name("device-monitor".to_string())
+            .spawn(move || Self::poll_loop(lister, known, interval, running, sender))
+            .ok();
+
+        Self { events, is_running, handle }
+    }
+
+    fn poll_loop<L: DeviceLister>(
+        lister: L,
+        mut known: Vec<String>,
+        interval: Duration,
+        is_running: Arc<AtomicBool>,
+        sender: Sender<DeviceEvent>,
+    ) {
+        let tick = Duration::from_millis(10).min(interval);
+
+        while is_running.load(Ordering::Relaxed) {
+            // Sleep in short steps so stop() doesn't wait a full interval
+            let mut slept = Duration::ZERO;
+            while slept < interval && is_running.load(Ordering::Relaxed) {
+                thread::sleep(tick);
+                slept += tick;
+            }
+
+            let current = lister.output_device_names();
+            for event in diff_devices(&known, &current) {
+                if sender.send(event).is_err() {
+                    return;
+                }
+            }
+            known = current;
+        }
+    }
+
+    /// Get the next pending device event without blocking
+    pub fn try_recv(&self) -> Option<DeviceEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// Stop the background polling thread
+    pub fn stop(&mut self) {
+        self.is_running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for DeviceMonitor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Compare two device lists and return the resulting hot-plug events
+pub fn diff_devices(previous: &[String], current: &[String]) -> Vec<DeviceEvent> {
+    let removed = previous.iter()
+        .filter(|name| !current.contains(name))
+        .map(|name| DeviceEvent::Removed(name.clone()));
+    let added = current.iter()
+        .filter(|name| !previous.contains(name))
+        .map(|name| DeviceEvent::Added(name.clone()));
+
+    removed.chain(added).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
+
+    /// Device source whose contents the test can change
+    struct ScriptedLister(Arc<Mutex<Vec<String>>>);
+
+    impl DeviceLister for ScriptedLister {
+        fn output_device_names(&self) -> Vec<String> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    #[test]
+    fn test_diff_devices() {
+        let before = vec!["Built-in Output".to_string(), "HDMI".to_string()];
+        let after = vec!["Built-in Output".to_string(), "USB DAC".to_string()];
+
+        let events = diff_devices(&before, &after);
+        assert_eq!(events, vec![
+            DeviceEvent::Removed("HDMI".to_string()),
+            DeviceEvent::Added("USB DAC".to_string()),
+        ]);
+        assert!(diff_devices(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn test_device_monitor_emits_hotplug_events() {
+        let devices = Arc::new(Mutex::new(vec!["Built-in Output".to_string()]));
+        let mut monitor = DeviceMonitor::with_lister(
+            ScriptedLister(Arc::clone(&devices)),
+            Duration::from_millis(20),
+        );
+
+        devices.lock().unwrap().push("USB DAC".to_string());
+        let added = monitor.events.recv_timeout(Duration::from_secs(2));
+        assert_eq!(added, Ok(DeviceEvent::Added("USB DAC".to_string())));
+
+        devices.lock().unwrap().retain(|name| name != "USB DAC");
+        let removed = monitor.events.recv_timeout(Duration::from_secs(2));
+        assert_eq!(removed, Ok(DeviceEvent::Removed("USB DAC".to_string())));
+
+        monitor.stop();
+        assert!(monitor.try_recv().is_none());
+    }
 
     #[test]
     fn test_device_manager_creation() {
@@ -324,6 +754,65 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_device_names_match_ignoring_case() {
+        let mut entries = HashMap::new();
+        entries.insert("Speakers (Realtek(R) Audio)".to_string(), 1);
+        entries.insert("SPDIF Out".to_string(), 2);
+        entries.insert("spdif out".to_string(), 3);
+
+        assert!(device_names_match("Speakers (Realtek(R) Audio)", "speakers (realtek(r) audio)"));
+        assert!(!device_names_match("Speakers", "Speakers 2"));
+        assert_eq!(lookup(&entries, "SPEAKERS (REALTEK(R) AUDIO)"), Some(&1));
+        // An exact match wins over other spellings
+        assert_eq!(lookup(&entries, "spdif out"), Some(&3));
+        assert_eq!(lookup(&entries, "Headphones"), None);
+    }
+
+    #[test]
+    fn test_channel_map_parses_and_displays_speaker_names() {
+        let layout: ChannelMap = "fl, fr fc LFE bl br".parse().unwrap();
+        assert_eq!(layout, ChannelMap::wave(6).unwrap());
+        assert_eq!(layout.to_string(), "FL FR FC LFE BL BR");
+        assert_eq!(layout.speakers()[3], Speaker::Lfe);
+
+        assert!("".parse::<ChannelMap>().is_err());
+        assert!("FL FR XX".parse::<ChannelMap>().unwrap_err().contains("unknown speaker 'XX'"));
+        assert_eq!("FL FR FR".parse::<ChannelMap>().unwrap_err(), "FR is listed twice");
+        assert_eq!(ChannelMap::wave(9), None);
+    }
+
+    #[test]
+    fn test_alsa_puts_the_back_pair_before_the_centre() {
+        assert_eq!(ChannelMap::alsa(6).unwrap().to_string(), "FL FR BL BR FC LFE");
+        assert_eq!(ChannelMap::alsa(8).unwrap().to_string(), "FL FR BL BR FC LFE SL SR");
+        // Stereo and quad are the same everywhere
+        assert_eq!(ChannelMap::alsa(2), ChannelMap::wave(2));
+        assert_eq!(ChannelMap::alsa(4), ChannelMap::wave(4));
+
+        // 5.1 from a file reaches the same speakers on an ALSA device
+        let source = ChannelMap::for_source(6).unwrap();
+        let gains = source.routing(&ChannelMap::alsa(6).unwrap());
+        let targets: Vec<usize> = (0..6).map(|source| gains.iter().position(|row| row[source] == 1.0).unwrap()).collect();
+        assert_eq!(targets, vec![0, 1, 4, 5, 2, 3]);
+    }
+
+    #[test]
+    fn test_routing_folds_missing_speakers_into_their_neighbours() {
+        use std::f32::consts::FRAC_1_SQRT_2 as K;
+        let stereo = ChannelMap::wave(2).unwrap();
+        let gains = ChannelMap::wave(6).unwrap().routing(&stereo);
+        // FL FR FC LFE BL BR: centre and surrounds at -3 dB, no LFE
+        assert_eq!(gains[0], vec![1.0, 0.0, K, 0.0, K, 0.0]);
+        assert_eq!(gains[1], vec![0.0, 1.0, K, 0.0, 0.0, K]);
+
+        // Back surrounds use the side speakers of a 7.1 layout missing them
+        let sides: ChannelMap = "FL FR FC LFE SL SR".parse().unwrap();
+        let gains = ChannelMap::wave(6).unwrap().routing(&sides);
+        assert_eq!(gains[4][4], 1.0);
+        assert_eq!(gains[5][5], 1.0);
+    }
+
     #[test]
     fn test_device_capabilities() {
         let manager = DeviceManager::new().expect("Failed to create DeviceManager");
@@ -415,6 +904,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_default_device_capabilities_populated() {
+        let manager = DeviceManager::new().expect("Failed to create DeviceManager");
+        let devices = manager.list_devices();
+
+        if let Some(default_name) = manager.default_device_name().filter(|name| devices.contains(name)) {
+            let capabilities = manager.get_capabilities(&default_name)
+                .expect("Default device should have capabilities");
+
+            assert_eq!(capabilities.name, default_name);
+            assert!(!capabilities.supported_sample_rates.is_empty());
+            assert!(!capabilities.supported_bit_depths.is_empty());
+            assert!(capabilities.max_channels > 0);
+
+            let json = capabilities.to_json().expect("Capabilities should serialize");
+            assert!(json.contains("\"supports_exclusive_mode\""));
+            assert!(json.contains("\"supported_sample_rates\""));
+        }
+    }
+
     #[test]
     fn test_no_current_device_initially() {
         let manager = DeviceManager::new().expect("Failed to create DeviceManager");
@@ -0,0 +1,219 @@
+/*!
+TPDF dither for output streams narrower than the audio.
+
+cpal turns the engine's f32 samples into a 16-bit stream by truncating them, so the lost
+bits become distortion that follows the signal, audible on quiet passages. With `dither`
+on, the output callback rounds every sample to the stream's step itself, after adding
+triangular (TPDF) noise of up to one step either way. The error becomes a steady hiss that
+no longer depends on the signal, and the rounded values convert exactly.
+
+- `DitherControl` is shared between the engine and the output callback and holds the switch.
+- Each output stream owns a `DitherProcessor` for its sample format. Streams of 24 bits or
+  more and float streams are never dithered.
+- Blocks already on the output's grid pass through untouched: 16-bit files played at full
+  volume with nothing changing them stay bit-perfect.
+*/
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use cpal::SampleFormat;
+
+/// Whether output streams narrower than the audio are dithered
+#[derive(Debug, Default)]
+pub struct DitherControl {
+    enabled: AtomicBool,
+}
+
+impl DitherControl {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled: AtomicBool::new(enabled) }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Dither state for an output stream of `format`
+    pub fn processor(&self, format: SampleFormat) -> DitherProcessor {
+        let bits = match format {
+            SampleFormat::I8 | SampleFormat::U8 => Some(8),
+            SampleFormat::I16 | SampleFormat::U16 => Some(16),
+            _ => None,
+        };
+        DitherProcessor {
+            steps: bits.map(|bits: i32| 2f32.powi(bits - 1)),
+            random: 0x2545_f491,
+        }
+    }
+}
+
+/// Noise source and output step of one stream
+#[derive(Debug)]
+pub struct DitherProcessor {
+    /// Output steps per unit of amplitude; None when the stream is wide enough
+    steps: Option<f32>,
+    /// xorshift32 state
+    random: u32,
+}
+
+impl DitherProcessor {
+    /// Dither and round samples to the output step in place, unless off or already on the grid
+    pub fn process(&mut self, control: &DitherControl, samples: &mut [f32]) {
+        let Some(steps) = self.steps.filter(|_| control.is_enabled()) else {
+            return;
+        };
+        if samples.iter().all(|sample| (sample * steps).fract() == 0.0) {
+            return;
+        }
+        for sample in samples.iter_mut() {
+            let noise = self.next_uniform() - self.next_uniform();
+            let rounded = (*sample * steps + noise).round().clamp(-steps, steps - 1.0);
+            *sample = rounded / steps;
+        }
+    }
+
+    /// Uniform value in [0, 1)
+    fn next_uniform(&mut self) -> f32 {
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 17;
+        self.random ^= self.random << 5;
+        (self.random >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = 48_000.0;
+
+    /// One second of a 1 kHz sine at -60 dBFS
+    fn quiet_sine() -> Vec<f32> {
+        (0..RATE as usize)
+            .map(|index| 0.001 * (2.0 * std::f32::consts::PI * 1000.0 * index as f32 / RATE).sin())
+            .collect()
+    }
+
+    /// What cpal's conversion to i16 keeps of each sample
+    fn truncated(samples: &[f32]) -> Vec<f32> {
+        samples.iter().map(|sample| (sample * 32_768.0) as i16 as f32 / 32_768.0).collect()
+    }
+
+    fn dithered(samples: &[f32]) -> Vec<f32> {
+        let mut samples = samples.to_vec();
+        let control = DitherControl::new(true);
+        control.processor(SampleFormat::I16).process(&control, &mut samples);
+        samples
+    }
+
+    /// Power at `frequency`, in 16-bit steps squared (Goertzel)
+    fn power_at(samples: &[f32], frequency: f32) -> f32 {
+        let coefficient = 2.0 * (2.0 * std::f32::consts::PI * frequency / RATE).cos();
+        let (mut previous, mut before) = (0.0f32, 0.0f32);
+        for sample in samples {
+            let current = sample * 32_768.0 + coefficient * previous - before;
+            before = previous;
+            previous = current;
+        }
+        (previous * previous + before * before - coefficient * previous * before) / samples.len() as f32
+    }
+
+    /// Mean and variance of the error in 16-bit steps, and its correlation with the signal
+    fn error_statistics(signal: &[f32], quantized: &[f32]) -> (f32, f32, f32) {
+        let errors: Vec<f32> = signal.iter().zip(quantized).map(|(signal, quantized)| (quantized - signal) * 32_768.0).collect();
+        let count = errors.len() as f32;
+        let mean = errors.iter().sum::<f32>() / count;
+        let variance = errors.iter().map(|error| (error - mean).powi(2)).sum::<f32>() / count;
+        let signal_power = signal.iter().map(|sample| (sample * 32_768.0).powi(2)).sum::<f32>() / count;
+        let covariance = signal.iter().zip(&errors).map(|(sample, error)| sample * 32_768.0 * error).sum::<f32>() / count;
+        (mean, variance, covariance / (signal_power * variance).sqrt())
+    }
+
+    #[test]
+    fn test_dither_decorrelates_the_error() {
+        let sine = quiet_sine();
+        let (truncated, dithered) = (truncated(&sine), dithered(&sine));
+        assert!(dithered.iter().all(|sample| (sample * 32_768.0).fract() == 0.0), "rounded to whole steps");
+
+        // Truncation leaves an error that follows the signal; TPDF leaves a quarter step² of noise
+        let (_, _, truncated_correlation) = error_statistics(&sine, &truncated);
+        let (mean, variance, correlation) = error_statistics(&sine, &dithered);
+        assert!(truncated_correlation.abs() > 0.2, "{}", truncated_correlation);
+        assert!(correlation.abs() < 0.02, "{}", correlation);
+        assert!(mean.abs() < 0.02, "{}", mean);
+        assert!((variance - 0.25).abs() < 0.02, "{}", variance);
+
+        // The tone itself survives, while truncation's harmonics sink into the noise floor
+        assert!((power_at(&dithered, 1000.0) / power_at(&sine, 1000.0) - 1.0).abs() < 0.05);
+        for harmonic in [3000.0, 5000.0] {
+            assert!(power_at(&truncated, harmonic) > 20.0 * power_at(&dithered, harmonic), "at {} Hz", harmonic);
+        }
+    }
+
+    /// Geometric over arithmetic mean of the power spectrum: near 0 for tones, about 0.56
+    /// (e^-γ) for white noise
+    fn spectral_flatness(samples: &[f32]) -> f64 {
+        let count = samples.len();
+        let powers: Vec<f64> = (1..count / 2).map(|bin| {
+            let (mut real, mut imaginary) = (0.0f64, 0.0f64);
+            for (index, &sample) in samples.iter().enumerate() {
+                let phase = 2.0 * std::f64::consts::PI * (bin * index % count) as f64 / count as f64;
+                real += sample as f64 * phase.cos();
+                imaginary -= sample as f64 * phase.sin();
+            }
+            real * real + imaginary * imaginary + 1e-12
+        }).collect();
+        let log_mean = powers.iter().map(|power| power.ln()).sum::<f64>() / powers.len() as f64;
+        log_mean.exp() / (powers.iter().sum::<f64>() / powers.len() as f64)
+    }
+
+    #[test]
+    fn test_dithered_error_of_near_silence_is_white() {
+        // A 24-bit file fading out: a 1 kHz tone only a few 16-bit steps high
+        let near_silence: Vec<f32> = quiet_sine().iter()
+            .take(4800)
+            .map(|sample| (sample * 0.1 * 8_388_608.0).round() / 8_388_608.0)
+            .collect();
+        let error = |quantized: Vec<f32>| -> Vec<f32> {
+            quantized.iter().zip(&near_silence).map(|(quantized, signal)| (quantized - signal) * 32_768.0).collect()
+        };
+
+        // Truncation error repeats with the tone and piles up in its harmonics
+        let truncated_flatness = spectral_flatness(&error(truncated(&near_silence)));
+        let dithered_flatness = spectral_flatness(&error(dithered(&near_silence)));
+        assert!(truncated_flatness < 0.05, "{}", truncated_flatness);
+        assert!(dithered_flatness > 0.4, "{}", dithered_flatness);
+    }
+
+    #[test]
+    fn test_bypass_leaves_samples_untouched() {
+        let sine = quiet_sine();
+        let unchanged = |control: &DitherControl, format: SampleFormat, samples: &[f32]| {
+            let mut processed = samples.to_vec();
+            control.processor(format).process(control, &mut processed);
+            processed == samples
+        };
+
+        let control = DitherControl::new(true);
+        assert!(!unchanged(&control, SampleFormat::I16, &sine));
+        assert!(unchanged(&DitherControl::default(), SampleFormat::I16, &sine));
+        assert!(unchanged(&control, SampleFormat::F32, &sine));
+        assert!(unchanged(&control, SampleFormat::I32, &sine));
+
+        // 16-bit audio at full volume is already on the grid
+        assert!(unchanged(&control, SampleFormat::I16, &truncated(&sine)));
+        assert!(!unchanged(&control, SampleFormat::I8, &truncated(&sine)));
+    }
+
+    #[test]
+    fn test_full_scale_stays_in_range() {
+        let mut samples = vec![1.0, -1.0, 0.99999, -0.99999];
+        let control = DitherControl::new(true);
+        control.processor(SampleFormat::I16).process(&control, &mut samples);
+        assert!(samples.iter().all(|sample| (-1.0..1.0).contains(sample)));
+        assert_eq!(samples[1], -1.0);
+    }
+}
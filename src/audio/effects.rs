@@ -0,0 +1,271 @@
+/*!
+Volume fades for the output path.
+
+- `FadeControl` is shared between the engine and the output callback. `stop` raises its
+  fade-out flag and waits for the callback to report silence before stopping the stream;
+  starting playback raises the fade-in flag.
+- Each output stream owns a `FadeProcessor` that checks the flags once per block and runs
+  a `FadeInEffect` or `FadeOutEffect`: a linear ramp of a gain multiplier, stepped per frame.
+- A fade-out starts from whatever multiplier is current, so stopping during a fade-in
+  doesn't jump back to full level first.
+*/
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Fade requests and durations shared with the output callback
+#[derive(Debug, Default)]
+pub struct FadeControl {
+    fade_out_ms: AtomicU64,
+    fade_in_ms: AtomicU64,
+    fade_out_requested: AtomicBool,
+    fade_out_finished: AtomicBool,
+    fade_in_requested: AtomicBool,
+}
+
+impl FadeControl {
+    pub fn new(fade_out: Duration, fade_in: Duration) -> Self {
+        let control = Self::default();
+        control.set_durations(fade_out, fade_in);
+        control
+    }
+
+    /// Change the fade lengths; a zero duration turns that fade off
+    pub fn set_durations(&self, fade_out: Duration, fade_in: Duration) {
+        self.fade_out_ms.store(fade_out.as_millis() as u64, Ordering::Relaxed);
+        self.fade_in_ms.store(fade_in.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub fn fade_out_duration(&self) -> Duration {
+        Duration::from_millis(self.fade_out_ms.load(Ordering::Relaxed))
+    }
+
+    pub fn fade_in_duration(&self) -> Duration {
+        Duration::from_millis(self.fade_in_ms.load(Ordering::Relaxed))
+    }
+
+    /// Ask the output callback to fade to silence
+    pub fn request_fade_out(&self) {
+        self.fade_out_finished.store(false, Ordering::Release);
+        self.fade_out_requested.store(true, Ordering::Release);
+    }
+
+    /// Whether the requested fade-out has reached silence
+    pub fn fade_out_finished(&self) -> bool {
+        self.fade_out_finished.load(Ordering::Acquire)
+    }
+
+    /// Ask the output callback to fade in the audio that follows
+    pub fn request_fade_in(&self) {
+        if !self.fade_in_duration().is_zero() {
+            self.fade_in_requested.store(true, Ordering::Release);
+        }
+    }
+
+    /// Fade state for one output stream
+    pub fn processor(&self, sample_rate: u32, channels: usize) -> FadeProcessor {
+        FadeProcessor {
+            fade_in: FadeInEffect::new(sample_rate),
+            fade_out: FadeOutEffect::new(sample_rate),
+            channels: channels.max(1),
+        }
+    }
+}
+
+/// Linear ramp of the gain multiplier from its current value down to 0.0
+#[derive(Debug, Clone)]
+pub struct FadeOutEffect {
+    sample_rate: u32,
+    gain: f32,
+    step: f32,
+    active: bool,
+}
+
+impl FadeOutEffect {
+    pub fn new(sample_rate: u32) -> Self {
+        Self { sample_rate, gain: 1.0, step: 0.0, active: false }
+    }
+
+    /// Start fading from `from` to silence over `duration`
+    pub fn start(&mut self, from: f32, duration: Duration) {
+        let frames = (duration.as_secs_f64() * self.sample_rate as f64).round().max(1.0);
+        self.gain = from.clamp(0.0, 1.0);
+        self.step = self.gain / frames as f32;
+        self.active = true;
+    }
+
+    /// Whether a fade has run all the way to silence
+    pub fn is_silent(&self) -> bool {
+        self.active && self.gain <= 0.0
+    }
+
+    /// Back to full level
+    pub fn reset(&mut self) {
+        self.gain = 1.0;
+        self.active = false;
+    }
+
+    /// Scale interleaved samples, lowering the multiplier once per frame
+    pub fn process(&mut self, samples: &mut [f32], channels: usize) {
+        if !self.active {
+            return;
+        }
+        for frame in samples.chunks_mut(channels) {
+            for sample in frame.iter_mut() {
+                *sample *= self.gain;
+            }
+            self.gain = (self.gain - self.step).max(0.0);
+        }
+    }
+}
+
+/// Linear ramp of the gain multiplier from 0.0 up to 1.0
+#[derive(Debug, Clone)]
+pub struct FadeInEffect {
+    sample_rate: u32,
+    gain: f32,
+    step: f32,
+}
+
+impl FadeInEffect {
+    pub fn new(sample_rate: u32) -> Self {
+        Self { sample_rate, gain: 1.0, step: 0.0 }
+    }
+
+    /// Start fading up from silence over `duration`
+    pub fn start(&mut self, duration: Duration) {
+        let frames = (duration.as_secs_f64() * self.sample_rate as f64).round().max(1.0);
+        self.gain = 0.0;
+        self.step = 1.0 / frames as f32;
+    }
+
+    /// Current multiplier (1.0 once the fade is complete)
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Skip the rest of the fade
+    pub fn reset(&mut self) {
+        self.gain = 1.0;
+    }
+
+    /// Scale interleaved samples, raising the multiplier once per frame
+    pub fn process(&mut self, samples: &mut [f32], channels: usize) {
+        if self.gain >= 1.0 {
+            return;
+        }
+        for frame in samples.chunks_mut(channels) {
+            for sample in frame.iter_mut() {
+                *sample *= self.gain;
+            }
+            self.gain = (self.gain + self.step).min(1.0);
+        }
+    }
+}
+
+/// Fades owned by one output stream
+#[derive(Debug, Clone)]
+pub struct FadeProcessor {
+    fade_in: FadeInEffect,
+    fade_out: FadeOutEffect,
+    channels: usize,
+}
+
+impl FadeProcessor {
+    /// Apply the current fade to interleaved samples, picking up new requests first
+    pub fn process(&mut self, control: &FadeControl, samples: &mut [f32]) {
+        if control.fade_in_requested.swap(false, Ordering::AcqRel) {
+            self.fade_out.reset();
+            self.fade_in.start(control.fade_in_duration());
+        }
+        if control.fade_out_requested.swap(false, Ordering::AcqRel) {
+            self.fade_out.start(self.fade_in.gain(), control.fade_out_duration());
+            self.fade_in.reset();
+        }
+
+        self.fade_in.process(samples, self.channels);
+        self.fade_out.process(samples, self.channels);
+        if self.fade_out.is_silent() {
+            control.fade_out_finished.store(true, Ordering::Release);
+        }
+    }
+
+    /// Forget a finished fade-out once playback has stopped, so the next start is at full level
+    pub fn reset(&mut self) {
+        self.fade_out.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    #[test]
+    fn test_fade_out_applies_decreasing_multiplier() {
+        let control = FadeControl::new(Duration::from_millis(50), Duration::ZERO);
+        let mut processor = control.processor(RATE, 2);
+        // 10 ms callback blocks of a constant full-scale signal
+        let block = || vec![1.0f32; RATE as usize / 100 * 2];
+
+        let mut first = block();
+        processor.process(&control, &mut first);
+        assert!(first.iter().all(|&sample| sample == 1.0));
+
+        control.request_fade_out();
+        let mut gains: Vec<f32> = Vec::new();
+        for _ in 0..6 {
+            let mut samples = block();
+            processor.process(&control, &mut samples);
+            // Both channels of a frame get the same multiplier
+            assert!(samples.chunks(2).all(|frame| frame[0] == frame[1]));
+            gains.extend(samples.iter().step_by(2).copied());
+        }
+
+        assert!(gains[0] > 0.99, "fade starts near the current level, not at silence");
+        assert!(gains.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!(gains[RATE as usize / 100] < gains[0] && gains[RATE as usize / 100] > 0.7);
+        let fade_frames = RATE as usize / 20;
+        assert!(gains[..fade_frames].iter().all(|&gain| gain > 0.0));
+        assert!(gains[fade_frames..].iter().all(|&gain| gain == 0.0));
+        assert!(control.fade_out_finished());
+
+        // Stopped: the next start is at full level again
+        processor.reset();
+        let mut samples = block();
+        processor.process(&control, &mut samples);
+        assert!(samples.iter().all(|&sample| sample == 1.0));
+    }
+
+    #[test]
+    fn test_fade_in_ramps_up_and_fade_out_continues_from_it() {
+        let control = FadeControl::new(Duration::from_millis(10), Duration::from_millis(20));
+        let mut processor = control.processor(RATE, 1);
+
+        control.request_fade_in();
+        let mut samples = vec![1.0f32; RATE as usize / 200];
+        processor.process(&control, &mut samples);
+        assert_eq!(samples[0], 0.0);
+        assert!(samples.windows(2).all(|pair| pair[1] > pair[0]));
+        let reached = *samples.last().unwrap();
+        assert!(reached > 0.2 && reached < 0.3);
+
+        control.request_fade_out();
+        let mut samples = vec![1.0f32; RATE as usize / 50];
+        processor.process(&control, &mut samples);
+        assert!((samples[0] - reached).abs() < 0.01);
+        assert!(samples.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert_eq!(*samples.last().unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_zero_fade_in_is_off() {
+        let control = FadeControl::new(Duration::ZERO, Duration::ZERO);
+        let mut processor = control.processor(RATE, 2);
+        control.request_fade_in();
+        let mut samples = vec![0.5f32; 64];
+        processor.process(&control, &mut samples);
+        assert!(samples.iter().all(|&sample| sample == 0.5));
+    }
+}
@@ -0,0 +1,491 @@
+/*!
+FLAC writer.
+
+A small encoder in the spirit of `flac -3`: fixed 4096-sample blocks, and for each channel
+the cheapest of a CONSTANT, VERBATIM or FIXED (order 0 to 4) subframe with partitioned Rice
+residuals. Stereo blocks also try left/side, right/side and mid/side. There is no LPC, so
+files come out a little larger than the reference encoder's, but they are lossless and
+carry the MD5 of the audio in STREAMINFO for `verify`.
+
+STREAMINFO is written first with the length unknown and rewritten by `finish`.
+*/
+
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use symphonia::core::checksum::{Crc16Ansi, Crc8Ccitt, Md5};
+use symphonia::core::io::Monitor;
+
+use super::{quantize, AudioEncoder, EncoderFormat};
+
+const BLOCK_SIZE: usize = 4096;
+const MAX_FIXED_ORDER: usize = 4;
+const MAX_PARTITION_ORDER: u32 = 8;
+/// Largest parameter of the 4-bit Rice coding; wider residuals need the 5-bit variant
+const MAX_RICE_PARAMETER: u32 = 14;
+const MAX_RICE2_PARAMETER: u32 = 30;
+/// Byte offset of the STREAMINFO body: after "fLaC" and the block header
+const STREAMINFO_AT: u64 = 8;
+
+/// A FLAC file being written
+pub struct FlacEncoder {
+    writer: BufWriter<File>,
+    format: EncoderFormat,
+    /// Interleaved samples not yet in a frame
+    pending: Vec<i32>,
+    frame_number: u64,
+    /// Samples per channel written so far
+    total_samples: u64,
+    md5: Md5,
+    md5_bytes: Vec<u8>,
+    frame_sizes: Option<(u32, u32)>,
+}
+
+impl FlacEncoder {
+    /// Create `path` and write the stream header
+    pub fn create(path: &Path, format: EncoderFormat) -> io::Result<Self> {
+        if !(1..=8).contains(&format.channels) || format.sample_rate == 0 || format.sample_rate >= 1 << 20 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "FLAC holds 1 to 8 channels at up to 1 MHz"));
+        }
+        let mut encoder = Self {
+            writer: BufWriter::new(File::create(path)?),
+            format,
+            pending: Vec::with_capacity(BLOCK_SIZE * format.channels as usize),
+            frame_number: 0,
+            total_samples: 0,
+            md5: Md5::default(),
+            md5_bytes: Vec::new(),
+            frame_sizes: None,
+        };
+        encoder.writer.write_all(b"fLaC")?;
+        // The last metadata block, STREAMINFO, 34 bytes
+        encoder.writer.write_all(&[0x80, 0, 0, 34])?;
+        let streaminfo = encoder.streaminfo();
+        encoder.writer.write_all(&streaminfo)?;
+        Ok(encoder)
+    }
+
+    fn streaminfo(&self) -> [u8; 34] {
+        let format = self.format;
+        let (min_frame, max_frame) = self.frame_sizes.unwrap_or_default();
+        let mut info = [0; 34];
+        info[0..2].copy_from_slice(&(BLOCK_SIZE as u16).to_be_bytes());
+        info[2..4].copy_from_slice(&(BLOCK_SIZE as u16).to_be_bytes());
+        info[4..7].copy_from_slice(&min_frame.to_be_bytes()[1..]);
+        info[7..10].copy_from_slice(&max_frame.to_be_bytes()[1..]);
+        let packed = (format.sample_rate as u64) << 44
+            | (format.channels as u64 - 1) << 41
+            | (format.bit_depth as u64 - 1) << 36
+            | self.total_samples;
+        info[10..18].copy_from_slice(&packed.to_be_bytes());
+        info[18..34].copy_from_slice(&self.md5.md5());
+        info
+    }
+
+    /// Encode one block of interleaved samples as a frame
+    fn write_frame(&mut self, samples: &[i32]) -> io::Result<()> {
+        let channels = self.format.channels as usize;
+        let bits = self.format.bit_depth as u32;
+        let block = samples.len() / channels;
+        let planes: Vec<Vec<i64>> = (0..channels)
+            .map(|channel| samples.iter().skip(channel).step_by(channels).map(|&sample| sample as i64).collect())
+            .collect();
+
+        // Independent channels, or for stereo the cheapest pairing with the side channel
+        let mut assignment = channels as u64 - 1;
+        let mut subframes: Vec<(Vec<i64>, u32)> = planes.iter().map(|plane| (plane.clone(), bits)).collect();
+        if channels == 2 {
+            let (left, right) = (&planes[0], &planes[1]);
+            let side: Vec<i64> = left.iter().zip(right).map(|(left, right)| left - right).collect();
+            let mid: Vec<i64> = left.iter().zip(right).map(|(left, right)| (left + right) >> 1).collect();
+            let cost = |plane: &[i64], bits| Subframe::plan(plane, bits).bits;
+            let (left_cost, right_cost) = (cost(left, bits), cost(right, bits));
+            let (side_cost, mid_cost) = (cost(&side, bits + 1), cost(&mid, bits));
+            let options = [
+                (left_cost + right_cost, 1),
+                (left_cost + side_cost, 8),
+                (side_cost + right_cost, 9),
+                (mid_cost + side_cost, 10),
+            ];
+            let (_, best) = options.iter().min_by_key(|(cost, _)| *cost).copied().unwrap_or((0, 1));
+            assignment = best;
+            subframes = match best {
+                8 => vec![(left.clone(), bits), (side, bits + 1)],
+                9 => vec![(side, bits + 1), (right.clone(), bits)],
+                10 => vec![(mid, bits), (side, bits + 1)],
+                _ => subframes,
+            };
+        }
+
+        let mut writer = BitWriter::default();
+        writer.write(0xFFF8, 16);
+        let block_code = if block == BLOCK_SIZE { 12 } else { 7 };
+        writer.write(block_code, 4);
+        writer.write(sample_rate_code(self.format.sample_rate), 4);
+        writer.write(assignment, 4);
+        writer.write(if bits == 16 { 0b100 } else { 0b110 }, 3);
+        writer.write(0, 1);
+        writer.write_utf8(self.frame_number);
+        if block_code == 7 {
+            writer.write(block as u64 - 1, 16);
+        }
+        if sample_rate_code(self.format.sample_rate) == 12 {
+            writer.write(self.format.sample_rate as u64 / 1000, 8);
+        }
+        let mut crc8 = Crc8Ccitt::new(0);
+        crc8.process_buf_bytes(&writer.bytes);
+        writer.write(crc8.crc() as u64, 8);
+
+        for (plane, bits) in &subframes {
+            Subframe::plan(plane, *bits).write(&mut writer, plane, *bits);
+        }
+        writer.align();
+        let mut crc16 = Crc16Ansi::new(0);
+        crc16.process_buf_bytes(&writer.bytes);
+        writer.write(crc16.crc() as u64, 16);
+
+        self.writer.write_all(&writer.bytes)?;
+        let size = writer.bytes.len() as u32;
+        self.frame_sizes = Some(match self.frame_sizes {
+            Some((min, max)) => (min.min(size), max.max(size)),
+            None => (size, size),
+        });
+        self.frame_number += 1;
+        self.total_samples += block as u64;
+        Ok(())
+    }
+}
+
+impl AudioEncoder for FlacEncoder {
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        let width = self.format.bit_depth as usize / 8;
+        self.md5_bytes.clear();
+        for &sample in samples {
+            let value = quantize(sample, self.format.bit_depth);
+            self.md5_bytes.extend_from_slice(&value.to_le_bytes()[..width]);
+            self.pending.push(value);
+        }
+        self.md5.process_buf_bytes(&self.md5_bytes);
+
+        let frame_samples = BLOCK_SIZE * self.format.channels as usize;
+        let mut start = 0;
+        while self.pending.len() - start >= frame_samples {
+            let block = self.pending[start..start + frame_samples].to_vec();
+            self.write_frame(&block)?;
+            start += frame_samples;
+        }
+        self.pending.drain(..start);
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        let channels = self.format.channels as usize;
+        let rest = std::mem::take(&mut self.pending);
+        let whole = rest.len() / channels * channels;
+        if whole > 0 {
+            self.write_frame(&rest[..whole])?;
+        }
+        let streaminfo = self.streaminfo();
+        let mut file = self.writer.into_inner().map_err(|e| e.into_error())?;
+        file.seek(SeekFrom::Start(STREAMINFO_AT))?;
+        file.write_all(&streaminfo)?;
+        file.sync_all()
+    }
+}
+
+/// Frame header code of a sample rate; 12 is kHz in a byte after the header, 0 is STREAMINFO's
+fn sample_rate_code(sample_rate: u32) -> u64 {
+    match sample_rate {
+        88_200 => 1,
+        176_400 => 2,
+        192_000 => 3,
+        8_000 => 4,
+        16_000 => 5,
+        22_050 => 6,
+        24_000 => 7,
+        32_000 => 8,
+        44_100 => 9,
+        48_000 => 10,
+        96_000 => 11,
+        rate if rate.is_multiple_of(1000) && rate / 1000 < 256 => 12,
+        _ => 0,
+    }
+}
+
+/// How one channel of a block is coded, and its size in bits
+#[derive(Debug, Clone, PartialEq)]
+struct Subframe {
+    kind: SubframeKind,
+    bits: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SubframeKind {
+    Constant,
+    Verbatim,
+    Fixed { order: usize, partition_order: u32, parameters: Vec<u32> },
+}
+
+impl Subframe {
+    /// The smallest coding of `samples` at `bits` per sample
+    fn plan(samples: &[i64], bits: u32) -> Self {
+        let header = 8;
+        if samples.iter().all(|&sample| sample == samples[0]) {
+            return Self { kind: SubframeKind::Constant, bits: header + bits as u64 };
+        }
+        let mut best = Self { kind: SubframeKind::Verbatim, bits: header + bits as u64 * samples.len() as u64 };
+        for order in 0..=MAX_FIXED_ORDER.min(samples.len() - 1) {
+            let residuals = fixed_residuals(samples, order);
+            let (partition_order, parameters, residual_bits) = rice_partitions(&residuals, samples.len(), order);
+            let size = header + order as u64 * bits as u64 + residual_bits;
+            if size < best.bits {
+                best = Self { kind: SubframeKind::Fixed { order, partition_order, parameters }, bits: size };
+            }
+        }
+        best
+    }
+
+    fn write(&self, writer: &mut BitWriter, samples: &[i64], bits: u32) {
+        match &self.kind {
+            SubframeKind::Constant => {
+                writer.write(0b0000_0000, 8);
+                writer.write_signed(samples[0], bits);
+            }
+            SubframeKind::Verbatim => {
+                writer.write(0b0000_0010, 8);
+                for &sample in samples {
+                    writer.write_signed(sample, bits);
+                }
+            }
+            SubframeKind::Fixed { order, partition_order, parameters } => {
+                writer.write((0b001000 | *order as u64) << 1, 8);
+                for &sample in &samples[..*order] {
+                    writer.write_signed(sample, bits);
+                }
+                let wide = parameters.iter().any(|&parameter| parameter > MAX_RICE_PARAMETER);
+                writer.write(wide as u64, 2);
+                writer.write(*partition_order as u64, 4);
+                let residuals = fixed_residuals(samples, *order);
+                let partition = samples.len() >> partition_order;
+                let mut start = 0;
+                for (index, &parameter) in parameters.iter().enumerate() {
+                    let end = (index + 1) * partition - order;
+                    writer.write(parameter as u64, if wide { 5 } else { 4 });
+                    for &residual in &residuals[start..end] {
+                        let folded = zigzag(residual);
+                        writer.write_unary(folded >> parameter);
+                        writer.write(folded, parameter);
+                    }
+                    start = end;
+                }
+            }
+        }
+    }
+}
+
+/// Prediction errors of the fixed polynomial predictor of `order`, after its warm-up samples
+fn fixed_residuals(samples: &[i64], order: usize) -> Vec<i64> {
+    (order..samples.len())
+        .map(|index| {
+            let x = |back: usize| samples[index - back];
+            match order {
+                0 => x(0),
+                1 => x(0) - x(1),
+                2 => x(0) - 2 * x(1) + x(2),
+                3 => x(0) - 3 * x(1) + 3 * x(2) - x(3),
+                _ => x(0) - 4 * x(1) + 6 * x(2) - 4 * x(3) + x(4),
+            }
+        })
+        .collect()
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Partition order, Rice parameter per partition and total bits (with the coding method
+/// and order fields) of the cheapest partitioning of `residuals`
+///
+/// Sizes are estimated from each partition's sum, which is close enough to choose by.
+fn rice_partitions(residuals: &[i64], block: usize, order: usize) -> (u32, Vec<u32>, u64) {
+    let folded: Vec<u64> = residuals.iter().map(|&residual| zigzag(residual)).collect();
+    let mut best: Option<(u32, Vec<u32>, u64)> = None;
+    for partition_order in 0..=MAX_PARTITION_ORDER {
+        let partitions = 1usize << partition_order;
+        if !block.is_multiple_of(partitions) || block / partitions <= order {
+            break;
+        }
+        let size = block / partitions;
+        let mut parameters = Vec::with_capacity(partitions);
+        let mut bits = 2 + 4;
+        let mut start = 0;
+        for index in 0..partitions {
+            let end = (index + 1) * size - order;
+            let part = &folded[start..end];
+            let sum: u64 = part.iter().sum();
+            let count = part.len() as u64;
+            let parameter = rice_parameter(sum, count);
+            parameters.push(parameter);
+            bits += 5 + count * (parameter as u64 + 1) + (sum >> parameter);
+            start = end;
+        }
+        if best.as_ref().is_none_or(|(_, _, best_bits)| bits < *best_bits) {
+            best = Some((partition_order, parameters, bits));
+        }
+    }
+    best.unwrap_or((0, vec![0], u64::MAX / 2))
+}
+
+/// Rice parameter close to the best for `count` values summing to `sum`
+fn rice_parameter(sum: u64, count: u64) -> u32 {
+    if count == 0 || sum <= count {
+        return 0;
+    }
+    (63 - (sum / count).leading_zeros()).min(MAX_RICE2_PARAMETER)
+}
+
+/// Bits packed most significant first
+#[derive(Debug, Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    accumulator: u64,
+    pending: u32,
+}
+
+impl BitWriter {
+    /// Append the low `bits` (at most 32) of `value`
+    fn write(&mut self, value: u64, bits: u32) {
+        if bits == 0 {
+            return;
+        }
+        self.accumulator = (self.accumulator << bits) | (value & ((1u64 << bits) - 1));
+        self.pending += bits;
+        while self.pending >= 8 {
+            self.pending -= 8;
+            self.bytes.push((self.accumulator >> self.pending) as u8);
+        }
+    }
+
+    /// Two's complement in `bits`
+    fn write_signed(&mut self, value: i64, bits: u32) {
+        self.write(value as u64, bits);
+    }
+
+    /// `zeros` zero bits and a one
+    fn write_unary(&mut self, mut zeros: u64) {
+        while zeros >= 32 {
+            self.write(0, 32);
+            zeros -= 32;
+        }
+        self.write(1, zeros as u32 + 1);
+    }
+
+    /// The frame number in FLAC's extended UTF-8 form
+    fn write_utf8(&mut self, value: u64) {
+        if value < 0x80 {
+            self.write(value, 8);
+            return;
+        }
+        // n bytes carry 5n + 1 bits
+        let length = (2..=7u32).find(|length| value < 1 << (5 * length + 1)).unwrap_or(7);
+        let continuation = 6 * (length - 1);
+        self.write((0xFF00 >> length) & 0xFF | (value >> continuation), 8);
+        for shift in (0..continuation).step_by(6).rev() {
+            self.write(0x80 | ((value >> shift) & 0x3F), 8);
+        }
+    }
+
+    fn align(&mut self) {
+        if self.pending > 0 {
+            self.write(0, 8 - self.pending);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::decoders::FlacDecoder;
+    use crate::audio::AudioDecoder;
+    use std::sync::atomic::AtomicBool;
+
+    fn encode(path: &Path, format: EncoderFormat, samples: &[f32]) {
+        let mut encoder: Box<dyn AudioEncoder> = Box::new(FlacEncoder::create(path, format).unwrap());
+        // Pieces that don't line up with blocks
+        for chunk in samples.chunks(1000 * format.channels as usize) {
+            encoder.write(chunk).unwrap();
+        }
+        encoder.finish().unwrap();
+    }
+
+    fn decode(path: &Path) -> (Vec<f32>, FlacDecoder) {
+        let mut decoder = FlacDecoder::new(path).unwrap();
+        let mut decoded = Vec::new();
+        while let Some(buffer) = decoder.decode_next().unwrap() {
+            decoded.extend(buffer.samples);
+        }
+        (decoded, decoder)
+    }
+
+    /// A tone with some noise on it, so every subframe type and the stereo modes get used
+    fn music(frames: usize, channels: usize) -> Vec<f32> {
+        let mut noise = 0x1234_5678u32;
+        (0..frames * channels)
+            .map(|index| {
+                noise ^= noise << 13;
+                noise ^= noise >> 17;
+                noise ^= noise << 5;
+                let frame = (index / channels) as f32;
+                let tone = 0.5 * (2.0 * std::f32::consts::PI * 440.0 * frame / 44_100.0).sin();
+                let fade = if frame < 4096.0 { 0.0 } else { 1.0 };
+                fade * (tone + (noise >> 8) as f32 / (1 << 24) as f32 * 0.01 - 0.005) * if index % channels == 1 { 0.8 } else { 1.0 }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lossless_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        for (channels, bit_depth, sample_rate) in [(2u16, 16u16, 44_100u32), (1, 24, 96_000), (3, 24, 44_000), (2, 24, 37_800)] {
+            let path = dir.path().join(format!("{}-{}-{}.flac", channels, bit_depth, sample_rate));
+            let format = EncoderFormat { sample_rate, channels, bit_depth };
+            let samples = music(3 * BLOCK_SIZE + 123, channels as usize);
+            encode(&path, format, &samples);
+
+            let (decoded, decoder) = decode(&path);
+            assert_eq!((decoder.sample_rate(), decoder.channels(), decoder.bit_depth()), (sample_rate, channels, bit_depth));
+            let expected: Vec<i32> = samples.iter().map(|&sample| quantize(sample, bit_depth)).collect();
+            let got: Vec<i32> = decoded.iter().map(|&sample| quantize(sample, bit_depth)).collect();
+            assert!(got == expected, "{} channels, {} bits, {} Hz", channels, bit_depth, sample_rate);
+
+            let check = crate::queue::verify::check_file(&path, &AtomicBool::new(false));
+            assert_eq!(check.problem, None, "the MD5 in STREAMINFO matches");
+        }
+    }
+
+    #[test]
+    fn test_compresses_smooth_audio() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tone.flac");
+        let samples = music(10 * BLOCK_SIZE, 2);
+        encode(&path, EncoderFormat { sample_rate: 44_100, channels: 2, bit_depth: 16 }, &samples);
+        // The noise alone takes about 9 of the 16 bits
+        let size = std::fs::metadata(&path).unwrap().len() as usize;
+        let raw = samples.len() * 2;
+        assert!(size < raw * 3 / 4, "{} bytes for {} bytes of PCM", size, raw);
+    }
+
+    #[test]
+    fn test_utf8_frame_numbers() {
+        let encoded = |value| {
+            let mut writer = BitWriter::default();
+            writer.write_utf8(value);
+            writer.bytes
+        };
+        assert_eq!(encoded(0x7F), vec![0x7F]);
+        assert_eq!(encoded(0x80), vec![0xC2, 0x80]);
+        assert_eq!(encoded(0x800), vec![0xE0, 0xA0, 0x80]);
+        assert_eq!(encoded(0xFFFF_FFFF).len(), 7);
+    }
+}
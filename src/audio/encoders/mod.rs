@@ -0,0 +1,66 @@
+/*!
+File writers for `export`.
+
+Both take interleaved f32 samples, as the output chain produces them, and quantize them
+to the file's integer bit depth by rounding; dithering, when on, has already put them on
+that grid. Headers that depend on the length are completed by `finish`.
+*/
+
+pub mod flac;
+pub mod wav;
+
+use std::io;
+use std::path::Path;
+
+pub use flac::FlacEncoder;
+pub use wav::WavEncoder;
+
+/// Layout of the audio written to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bit_depth: u16,
+}
+
+/// A file being written
+pub trait AudioEncoder: Send {
+    /// Append interleaved samples, nominally in [-1, 1)
+    fn write(&mut self, samples: &[f32]) -> io::Result<()>;
+
+    /// Complete the headers and flush the file
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// Bit depths the encoder for `path` can write; empty when its extension has no encoder
+pub fn supported_bit_depths(path: &Path) -> &'static [u16] {
+    match extension(path).as_deref() {
+        Some("wav") => &[16, 24, 32],
+        Some("flac") => &[16, 24],
+        _ => &[],
+    }
+}
+
+/// Create `path` with the encoder its extension names (`.wav` or `.flac`)
+pub fn create(path: &Path, format: EncoderFormat) -> io::Result<Box<dyn AudioEncoder>> {
+    if !supported_bit_depths(path).contains(&format.bit_depth) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("can't write {}-bit audio to {}", format.bit_depth, path.display()),
+        ));
+    }
+    match extension(path).as_deref() {
+        Some("flac") => Ok(Box::new(FlacEncoder::create(path, format)?)),
+        _ => Ok(Box::new(WavEncoder::create(path, format)?)),
+    }
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_ascii_lowercase())
+}
+
+/// `sample` as a signed integer of `bit_depth` bits
+pub fn quantize(sample: f32, bit_depth: u16) -> i32 {
+    let steps = (1i64 << (bit_depth - 1)) as f64;
+    (sample as f64 * steps).round().clamp(-steps, steps - 1.0) as i32
+}
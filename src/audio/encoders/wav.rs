@@ -0,0 +1,181 @@
+/*!
+WAV writer.
+
+Integer PCM at 16, 24 or 32 bits. Mono and stereo 16-bit files use the plain `fmt ` chunk,
+anything wider or with more channels `WAVE_FORMAT_EXTENSIBLE` with the usual speaker mask.
+
+A 28-byte `JUNK` chunk is reserved after the header. A file that outgrows the 4 GiB a RIFF
+header can describe becomes RF64 in `finish`: the `JUNK` chunk turns into the `ds64` chunk
+holding the 64-bit sizes, as EBU Tech 3306 intends.
+*/
+
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use super::{quantize, AudioEncoder, EncoderFormat};
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+/// KSDATAFORMAT_SUBTYPE_PCM
+const PCM_SUBTYPE: [u8; 16] = [1, 0, 0, 0, 0, 0, 0x10, 0, 0x80, 0, 0, 0xAA, 0, 0x38, 0x9B, 0x71];
+/// Bytes of the `ds64` chunk body: RIFF size, data size, sample count and an empty table
+const DS64_SIZE: u32 = 28;
+
+/// A WAV file being written
+pub struct WavEncoder {
+    writer: BufWriter<File>,
+    format: EncoderFormat,
+    /// Offset of the `data` chunk's size field
+    data_size_at: u64,
+    data_size: u64,
+    bytes: Vec<u8>,
+}
+
+impl WavEncoder {
+    /// Create `path` and write the header, with the sizes left to `finish`
+    pub fn create(path: &Path, format: EncoderFormat) -> io::Result<Self> {
+        let mut header = Vec::new();
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(b"WAVE");
+        header.extend_from_slice(b"JUNK");
+        header.extend_from_slice(&DS64_SIZE.to_le_bytes());
+        header.extend_from_slice(&[0; DS64_SIZE as usize]);
+
+        let fmt = fmt_chunk(format);
+        header.extend_from_slice(b"fmt ");
+        header.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+        header.extend_from_slice(&fmt);
+        header.extend_from_slice(b"data");
+        let data_size_at = header.len() as u64;
+        header.extend_from_slice(&0u32.to_le_bytes());
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&header)?;
+        Ok(Self { writer, format, data_size_at, data_size: 0, bytes: Vec::new() })
+    }
+}
+
+impl AudioEncoder for WavEncoder {
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        let width = self.format.bit_depth as usize / 8;
+        self.bytes.clear();
+        for &sample in samples {
+            let value = quantize(sample, self.format.bit_depth);
+            self.bytes.extend_from_slice(&value.to_le_bytes()[..width]);
+        }
+        self.writer.write_all(&self.bytes)?;
+        self.data_size += self.bytes.len() as u64;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        // Chunks are word-aligned; 24-bit mono can end on an odd byte
+        let padding = self.data_size % 2;
+        if padding == 1 {
+            self.writer.write_all(&[0])?;
+        }
+        let riff_size = self.data_size_at + 4 + self.data_size + padding - 8;
+        let mut file = self.writer.into_inner().map_err(|e| e.into_error())?;
+
+        match (u32::try_from(riff_size), u32::try_from(self.data_size)) {
+            (Ok(riff_size), Ok(data_size)) => {
+                file.seek(SeekFrom::Start(4))?;
+                file.write_all(&riff_size.to_le_bytes())?;
+                file.seek(SeekFrom::Start(self.data_size_at))?;
+                file.write_all(&data_size.to_le_bytes())?;
+            }
+            _ => {
+                let frame_size = self.format.channels as u64 * self.format.bit_depth as u64 / 8;
+                let mut header = Vec::new();
+                header.extend_from_slice(b"RF64");
+                header.extend_from_slice(&u32::MAX.to_le_bytes());
+                header.extend_from_slice(b"WAVE");
+                header.extend_from_slice(b"ds64");
+                header.extend_from_slice(&DS64_SIZE.to_le_bytes());
+                header.extend_from_slice(&riff_size.to_le_bytes());
+                header.extend_from_slice(&self.data_size.to_le_bytes());
+                header.extend_from_slice(&(self.data_size / frame_size.max(1)).to_le_bytes());
+                header.extend_from_slice(&0u32.to_le_bytes());
+                file.seek(SeekFrom::Start(0))?;
+                file.write_all(&header)?;
+                file.seek(SeekFrom::Start(self.data_size_at))?;
+                file.write_all(&u32::MAX.to_le_bytes())?;
+            }
+        }
+        file.sync_all()
+    }
+}
+
+/// Body of the `fmt ` chunk
+fn fmt_chunk(format: EncoderFormat) -> Vec<u8> {
+    let block_align = format.channels * format.bit_depth / 8;
+    let extensible = format.channels > 2 || format.bit_depth > 16;
+    let mut fmt = Vec::new();
+    fmt.extend_from_slice(&(if extensible { WAVE_FORMAT_EXTENSIBLE } else { WAVE_FORMAT_PCM }).to_le_bytes());
+    fmt.extend_from_slice(&format.channels.to_le_bytes());
+    fmt.extend_from_slice(&format.sample_rate.to_le_bytes());
+    fmt.extend_from_slice(&(format.sample_rate * block_align as u32).to_le_bytes());
+    fmt.extend_from_slice(&block_align.to_le_bytes());
+    fmt.extend_from_slice(&format.bit_depth.to_le_bytes());
+    if extensible {
+        fmt.extend_from_slice(&22u16.to_le_bytes());
+        fmt.extend_from_slice(&format.bit_depth.to_le_bytes());
+        fmt.extend_from_slice(&speaker_mask(format.channels).to_le_bytes());
+        fmt.extend_from_slice(&PCM_SUBTYPE);
+    }
+    fmt
+}
+
+/// `dwChannelMask` of the usual layout for `channels`, or 0 (unassigned) for others
+fn speaker_mask(channels: u16) -> u32 {
+    match channels {
+        1 => 0x4,
+        2 => 0x3,
+        4 => 0x33,
+        6 => 0x3F,
+        8 => 0x63F,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::decoders::WavDecoder;
+    use crate::audio::AudioDecoder;
+
+    fn round_trip(format: EncoderFormat, samples: &[f32]) -> (Vec<f32>, u32, u16) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.wav");
+        let mut encoder: Box<dyn AudioEncoder> = Box::new(WavEncoder::create(&path, format).unwrap());
+        for chunk in samples.chunks(7 * format.channels as usize) {
+            encoder.write(chunk).unwrap();
+        }
+        encoder.finish().unwrap();
+
+        let mut decoder = WavDecoder::new(&path).unwrap();
+        let mut decoded = Vec::new();
+        while let Some(buffer) = decoder.decode_next().unwrap() {
+            decoded.extend(buffer.samples);
+        }
+        (decoded, decoder.sample_rate(), decoder.bit_depth())
+    }
+
+    #[test]
+    fn test_writes_what_the_decoder_reads_back() {
+        let samples: Vec<f32> = (0..200).map(|index| (index as f32 / 100.0) - 1.0).collect();
+        for (channels, bit_depth) in [(2, 16), (1, 24), (6, 24), (2, 32)] {
+            let format = EncoderFormat { sample_rate: 96_000, channels, bit_depth };
+            let samples = &samples[..samples.len() / channels as usize * channels as usize];
+            let (decoded, sample_rate, decoded_depth) = round_trip(format, samples);
+            assert_eq!((sample_rate, decoded_depth), (96_000, bit_depth));
+            assert_eq!(decoded.len(), samples.len());
+            let step = 1.0 / (1u64 << (bit_depth - 1)) as f32;
+            for (decoded, sample) in decoded.iter().zip(samples) {
+                assert!((decoded - sample).abs() <= step, "{} channels, {} bits: {} vs {}", channels, bit_depth, decoded, sample);
+            }
+        }
+    }
+}
@@ -1,23 +1,39 @@
-use crate::audio::{AudioEngine, AudioDecoder, RingBuffer, BufferManager};
-use crate::audio::device::DeviceManager;
+use crate::audio::{AudioEngine, AudioEngineControl, AudioDecoder, RingBuffer, BufferManager, DeviceCapabilities, PerformanceReport};
+use crate::audio::buffer::{AdaptiveBufferController, AdaptiveBufferPolicy, BufferAdjustment, PrebufferGate, UnderrunMonitor};
+use crate::audio::device::{ChannelMap, DeviceManager};
 use crate::audio::performance::AudioPerformanceProfiler;
 use crate::audio::memory::HighResBufferAllocator;
 use crate::error::AudioError;
-use crate::models::AudioBuffer;
-use crate::audio::LinearResampler;
+use crate::models::{AudioFormat, LoopRegion};
+use crate::audio::RateAdapter;
+use crate::audio::chain::{self, ChainControls};
+use crate::audio::channels::{ChannelControl, OutputChannels};
+use crate::audio::crossfeed::{CrossfeedControl, CrossfeedSettings};
+use crate::audio::dither::DitherControl;
+use crate::audio::effects::FadeControl;
+use crate::audio::eq::{EqBand, ParametricEq};
+use crate::audio::limiter::ClipMonitor;
+use crate::audio::PositionTracker;
+use crate::audio::spectrum::SpectrumTap;
+use crate::audio::exclusive::{self, ExclusiveGuard};
+use crate::audio::gapless::GaplessTrimmer;
+use crate::audio::silence::{SilenceSettings, SilenceSkipper, SilenceStep};
+use crate::audio::priority;
+use crate::audio::watchdog::DecodeWatchdog;
+use symphonia::core::io::MediaSource;
+use crate::config::{DecoderThreadConfig, PlayerConfig};
 
 pub trait NextTrackProvider: Send + Sync {
     /// Return the absolute path of the next track to play, or None if at end of queue.
     fn request_next(&self) -> Option<std::path::PathBuf>;
 }
-use cpal::{Stream, SampleFormat, SampleRate, StreamConfig};
+use cpal::{Stream, SampleFormat, SampleRate, StreamConfig, SupportedBufferSize};
 use cpal::traits::{DeviceTrait, StreamTrait};
-use std::sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU32, Ordering}};
+use std::sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU8, AtomicU32, AtomicU64, Ordering}};
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::thread;
 use std::time::{Duration, Instant};
 use tokio::sync::{mpsc as tokio_mpsc, oneshot};
-use tokio::task::JoinHandle;
 
 /// Playback state for the audio engine
 #[derive(Debug, Clone, PartialEq)]
@@ -27,6 +43,39 @@ pub enum PlaybackState {
     Paused,
 }
 
+/// Playback state shared with the audio callback, readable without locking
+#[derive(Debug)]
+pub struct AtomicPlaybackState(AtomicU8);
+
+impl AtomicPlaybackState {
+    /// Create a shared state starting in `state`
+    pub fn new(state: PlaybackState) -> Self {
+        Self(AtomicU8::new(Self::encode(&state)))
+    }
+
+    /// Get the current state
+    pub fn load(&self) -> PlaybackState {
+        match self.0.load(Ordering::Acquire) {
+            1 => PlaybackState::Playing,
+            2 => PlaybackState::Paused,
+            _ => PlaybackState::Stopped,
+        }
+    }
+
+    /// Set the current state
+    pub fn store(&self, state: PlaybackState) {
+        self.0.store(Self::encode(&state), Ordering::Release);
+    }
+
+    fn encode(state: &PlaybackState) -> u8 {
+        match state {
+            PlaybackState::Stopped => 0,
+            PlaybackState::Playing => 1,
+            PlaybackState::Paused => 2,
+        }
+    }
+}
+
 /// Commands sent to the audio thread
 #[derive(Debug)]
 pub enum AudioCommand {
@@ -34,7 +83,6 @@ pub enum AudioCommand {
     Pause,
     Stop,
     SetVolume(f32),
-    Seek(Duration),
     Shutdown,
 }
 
@@ -43,82 +91,352 @@ pub enum AudioCommand {
 pub enum DecoderCommand {
     LoadFile(std::path::PathBuf),
     PreloadNext(std::path::PathBuf),
+    /// Drop the preloaded next track; the queue changed after it was loaded
+    DiscardPreload,
     Seek(Duration),
-    Stop,
+    /// Set or clear the A-B loop (file positions)
+    SetLoop(Option<LoopRegion>),
+    /// Rewind the current track to `start`, reopening `path` if the decoder cannot seek back,
+    /// and hold decoding until `Resume`
+    Stop { path: Option<std::path::PathBuf>, start: Duration },
+    /// Decode again after `Stop`
+    Resume,
+    /// Drop the current and preloaded tracks
+    Unload,
     NextTrack,
     Shutdown,
 }
 
+/// Thread a status update came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusOrigin {
+    Output,
+    Decoder,
+}
+
 /// Status updates from threads
 #[derive(Debug, Clone)]
 pub struct ThreadStatus {
+    pub origin: StatusOrigin,
     pub playback_state: PlaybackState,
     pub position: Duration,
     pub buffer_fill: f32,
     pub current_file: Option<std::path::PathBuf>,
+    /// Format of the track being decoded
+    pub source_format: Option<AudioFormat>,
+    /// Whether decoded audio is resampled to the output rate
+    pub resampling: bool,
+}
+
+impl Default for ThreadStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ThreadStatus {
+    fn new() -> Self {
+        Self {
+            origin: StatusOrigin::Output,
+            playback_state: PlaybackState::Stopped,
+            position: Duration::from_secs(0),
+            buffer_fill: 0.0,
+            current_file: None,
+            source_format: None,
+            resampling: false,
+        }
+    }
+
+    /// Fold an update into this snapshot, taking from each thread only what it knows
+    pub fn merge(&mut self, update: ThreadStatus) {
+        self.buffer_fill = update.buffer_fill;
+        match update.origin {
+            StatusOrigin::Output => {
+                self.playback_state = update.playback_state;
+                self.position = update.position;
+            }
+            StatusOrigin::Decoder => {
+                self.current_file = update.current_file;
+                self.source_format = update.source_format;
+                self.resampling = update.resampling;
+            }
+        }
+    }
 }
 
 /// Response from decoder thread
 #[derive(Debug)]
 pub enum DecoderResponse {
     FileLoaded {
-        duration: Duration,
+        path: std::path::PathBuf,
+        /// `None` for a stream with no end
+        duration: Option<Duration>,
         sample_rate: u32,
         bit_depth: u16,
         channels: u16,
     },
     NextTrackPreloaded {
-        duration: Duration,
+        duration: Option<Duration>,
         sample_rate: u32,
         bit_depth: u16,
         channels: u16,
     },
     Error(AudioError),
+    /// A file could not be opened, or stopped decoding part way; the decoder has let go of it
+    TrackFailed {
+        path: std::path::PathBuf,
+        error: AudioError,
+    },
+    /// The decoder could not seek; playback carries on from `position`
+    SeekFailed {
+        requested: Duration,
+        position: Duration,
+        reason: String,
+    },
+    /// A live stream sent a new title for what it is playing now
+    StreamMetadata {
+        path: std::path::PathBuf,
+        title: String,
+    },
     BufferFilled(usize), // frames filled
     EndOfFile,
     TrackTransitioned,
 }
 
+/// Buffer sizing for the output stream and the decoded-audio ring buffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BufferSettings {
+    /// Requested device buffer size in frames (0 = device default)
+    pub output_buffer_frames: u32,
+    /// Ring buffer capacity in milliseconds
+    pub ring_buffer_ms: u64,
+    /// Buffer level the decoder refills to, in milliseconds
+    pub target_buffer_ms: u64,
+    /// Silence played after an adaptive adjustment, in milliseconds (0 = none)
+    pub underrun_pause_ms: u64,
+}
+
+impl BufferSettings {
+    /// Read buffer settings from the player configuration
+    pub fn from_config(config: &PlayerConfig) -> Self {
+        let ring_buffer_ms = config.ring_buffer_ms.max(MIN_RING_BUFFER_MS);
+        Self {
+            output_buffer_frames: config.device_buffer_frames(),
+            ring_buffer_ms,
+            target_buffer_ms: config.target_buffer_ms.clamp(1, ring_buffer_ms),
+            underrun_pause_ms: config.underrun_pause_ms,
+        }
+    }
+
+    /// Adaptive underrun policy for this ring buffer size
+    pub fn adaptive_policy(&self) -> AdaptiveBufferPolicy {
+        AdaptiveBufferPolicy::for_ring_buffer(self.ring_buffer_ms, self.underrun_pause_ms)
+    }
+
+    /// Minimum buffer level before an underrun is reported
+    pub fn min_buffer_ms(&self) -> u64 {
+        self.target_buffer_ms / 2
+    }
+
+    /// Create a buffer manager sized for the given stream format
+    fn buffer_manager(&self, sample_rate: u32, channels: u16) -> BufferManager {
+        let buffer_frames = (sample_rate as u64 * self.ring_buffer_ms / 1000) as usize;
+        BufferManager::new(
+            buffer_frames,
+            channels,
+            sample_rate,
+            self.target_buffer_ms,
+            self.min_buffer_ms(),
+        )
+    }
+}
+
+impl Default for BufferSettings {
+    fn default() -> Self {
+        Self::from_config(&PlayerConfig::default())
+    }
+}
+
+/// Monitors the output callback reports into (atomics only)
+#[derive(Clone)]
+struct OutputMonitors {
+    underruns: Arc<UnderrunMonitor>,
+    prebuffer: Arc<PrebufferGate>,
+    chain: ChainControls,
+}
+
+/// Smallest ring buffer the engine will create
+const MIN_RING_BUFFER_MS: u64 = 100;
+
+/// Silence played when a suspended stream starts again, while the device wakes up
+const RESUME_PRE_ROLL: Duration = Duration::from_millis(20);
+
+/// How long a track may wait for its pre-buffer before playback gives up on it
+pub const PREBUFFER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Stream error sent when a decoder stops returning from `decode_next`
+pub const DECODE_TIMEOUT_MESSAGE: &str = "Decode timeout";
+
+/// Decode errors in a row, with no audio in between, after which a file is given up on
+const MAX_CONSECUTIVE_DECODE_ERRORS: u32 = 10;
+
+/// Stuck decoder threads the engine leaves behind before it stops starting new ones
+const MAX_ABANDONED_DECODER_THREADS: usize = 4;
+
+/// Clamp a requested device buffer size to the range the device supports
+pub fn clamp_output_buffer_frames(requested: u32, supported: &SupportedBufferSize) -> u32 {
+    match supported {
+        SupportedBufferSize::Range { min, max } if requested > 0 => requested.clamp(*min, *max),
+        _ => requested,
+    }
+}
+
+/// How long `shutdown_threads` waits for the decoder thread to exit
+const DECODER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Frames per callback assumed when the device picks its own buffer size
+const DEFAULT_CALLBACK_FRAMES: usize = 16384;
+
+/// Scratch space for an output callback, reserved up front so the callback never allocates
+fn callback_scratch(config: &StreamConfig) -> Vec<f32> {
+    let frames = match config.buffer_size {
+        cpal::BufferSize::Fixed(frames) => frames as usize,
+        cpal::BufferSize::Default => DEFAULT_CALLBACK_FRAMES,
+    };
+    Vec::with_capacity(frames * config.channels as usize)
+}
+
+/// Shared state the decoder loop needs, moved onto the decoder thread
+struct DecoderContext {
+    buffer_manager: Arc<BufferManager>,
+    current_decoder: Arc<Mutex<Option<Box<dyn AudioDecoder>>>>,
+    next_decoder: Arc<Mutex<Option<Box<dyn AudioDecoder>>>>,
+    gapless_enabled: Arc<AtomicBool>,
+    is_running: Arc<AtomicBool>,
+    decode_timeout: Duration,
+    next_track_provider: Option<Arc<dyn NextTrackProvider>>,
+    spectrum_tap: Arc<SpectrumTap>,
+    underrun_monitor: Arc<UnderrunMonitor>,
+    prebuffer: Arc<PrebufferGate>,
+    performance_profiler: Arc<AudioPerformanceProfiler>,
+    current_position: Arc<PositionTracker>,
+    silence_settings: Arc<Mutex<SilenceSettings>>,
+    channel_layout: Arc<Mutex<Option<ChannelMap>>>,
+    oversampling: Arc<Mutex<Option<u32>>>,
+    crossfeed: Arc<CrossfeedControl>,
+}
+
+/// Spawn the `audio-decoder` OS thread and drive `task` on a single-threaded runtime there
+///
+/// The thread is pinned and prioritised before `task` starts, and `task` decodes on the thread
+/// itself, so every `decode_next` call runs with those settings.
+fn spawn_decoder_thread<F>(config: &DecoderThreadConfig, task: F) -> std::io::Result<thread::JoinHandle<()>>
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let thread_priority = config.priority;
+    let cpu_affinity = config.cpu_affinity.clone();
+    thread::Builder::new()
+        .name("audio-decoder".to_string())
+        .stack_size(config.stack_size)
+        .spawn(move || {
+            if let Some(cores) = &cpu_affinity {
+                priority::pin_current_thread(cores, "decoder");
+            }
+            priority::set_decoder_thread_priority(thread_priority);
+            runtime.block_on(task);
+        })
+}
+
 /// Audio engine implementation with multi-threaded architecture
 pub struct AudioEngineImpl {
     device_manager: DeviceManager,
     stream: Option<Stream>,
-    playback_state: Arc<Mutex<PlaybackState>>,
+    playback_state: Arc<AtomicPlaybackState>,
     volume: Arc<AtomicU32>, // Store as f32 bits for atomic access
     sample_rate: u32,
     bit_depth: u16,
     channels: u16,
+    buffer_settings: BufferSettings,
+    /// Longest a single `decode_next` call may run before the file is abandoned (zero = no limit)
+    decode_timeout: Duration,
+    exclusive_requested: bool,
+    exclusive_guard: Option<ExclusiveGuard>,
+    /// Rebuild the stream at each gapless track's own rate instead of resampling into it
+    prefer_native_rate_switch: bool,
 
     // Thread communication
     audio_command_sender: Option<Sender<AudioCommand>>,
     decoder_command_sender: Option<tokio_mpsc::UnboundedSender<DecoderCommand>>,
     status_receiver: Option<tokio_mpsc::UnboundedReceiver<ThreadStatus>>,
+    thread_status: ThreadStatus,
     decoder_response_receiver: Option<tokio_mpsc::UnboundedReceiver<DecoderResponse>>,
+    /// Handed to a decoder thread that replaces one stuck in a decode
+    decoder_response_sender: Option<tokio_mpsc::UnboundedSender<DecoderResponse>>,
+    decoder_status_sender: Option<tokio_mpsc::UnboundedSender<ThreadStatus>>,
 
     // Thread handles
     audio_thread_handle: Option<thread::JoinHandle<()>>,
-    decoder_thread_handle: Option<JoinHandle<()>>,
+    decoder_thread_handle: Option<thread::JoinHandle<()>>,
+    /// Decoder threads detached while stuck in a decode; each one leaks until its call returns
+    abandoned_decoder_threads: usize,
+    decoder_thread_config: DecoderThreadConfig,
+    /// Cores the output thread is pinned to
+    output_cpu_affinity: Option<Vec<usize>>,
 
     // Shared state
     buffer_manager: Arc<BufferManager>,
     is_running: Arc<AtomicBool>,
-    current_position: Arc<Mutex<Duration>>,
+    current_position: Arc<PositionTracker>,
+    /// File position sought while stopped; the next `start_playback_at` starts there
+    pending_start: Option<Duration>,
+    /// File of the loaded track, which `stop` keeps loaded and only `unload` lets go of
+    current_track_path: Option<std::path::PathBuf>,
+    /// Where the loaded track starts in its file; `stop` rewinds to here
+    track_start: Duration,
+    /// File preloaded for a gapless transition
+    preloaded_track_path: Option<std::path::PathBuf>,
     current_decoder: Arc<Mutex<Option<Box<dyn AudioDecoder>>>>,
     next_decoder: Arc<Mutex<Option<Box<dyn AudioDecoder>>>>,
     gapless_enabled: Arc<AtomicBool>,
-
-    // Tokio runtime for async operations
-    runtime: Arc<tokio::runtime::Runtime>,
+    /// Silence skipping, read by the decoder thread for every buffer
+    silence_settings: Arc<Mutex<SilenceSettings>>,
+    /// Speaker order of the output when it isn't the usual one for its channel count
+    channel_layout: Arc<Mutex<Option<ChannelMap>>>,
+    /// Whole factor to upsample tracks by, when the device reaches the multiplied rate
+    oversampling: Arc<Mutex<Option<u32>>>,
 
     // Performance monitoring
     performance_profiler: Arc<AudioPerformanceProfiler>,
     buffer_allocator: Arc<HighResBufferAllocator>,
+    clip_monitor: Arc<ClipMonitor>,
+    eq: Arc<ParametricEq>,
+    fades: Arc<FadeControl>,
+    channel_control: Arc<ChannelControl>,
+    crossfeed: Arc<CrossfeedControl>,
+    dither: Arc<DitherControl>,
+    /// Milliseconds paused or stopped after which the output stream is suspended (0 = never)
+    suspend_output_after_ms: Arc<AtomicU64>,
+    spectrum_tap: Arc<SpectrumTap>,
+    underrun_monitor: Arc<UnderrunMonitor>,
+    prebuffer: Arc<PrebufferGate>,
+    /// Audio to buffer before a track starts, in milliseconds (0 = start at once)
+    prebuffer_ms: AtomicU64,
+    /// When the pre-buffer was last armed, to time it out
+    prebuffer_armed_at: Option<Instant>,
+    adaptive_buffer: AdaptiveBufferController,
     next_track_provider: Option<std::sync::Arc<dyn NextTrackProvider>>,
 }
 
 impl AudioEngineImpl {
-    /// Create a new AudioEngine instance
+    /// Create a new AudioEngine instance with default buffer settings
     pub fn new() -> Result<Self, AudioError> {
+        Self::new_with_config(&PlayerConfig::default())
+    }
+
+    /// Create a new AudioEngine instance using the buffer settings from the config
+    pub fn new_with_config(config: &PlayerConfig) -> Result<Self, AudioError> {
         let mut device_manager = DeviceManager::new()?;
 
         // Select default device
@@ -141,62 +459,84 @@ impl AudioEngineImpl {
             _ => 32, // Default to 32-bit for unknown formats
         };
 
-        // Create buffer manager with appropriate buffer sizes
-        let buffer_frames = (sample_rate as f64 * 1.0) as usize; // 1000ms buffer
-        let buffer_manager = Arc::new(BufferManager::new(
-            buffer_frames,
-            channels,
-            sample_rate,
-            300, // 300ms target buffer
-            150,  // 150ms minimum buffer
-        ));
-
-        // Create tokio runtime for async operations
-        let runtime = Arc::new(
-            tokio::runtime::Builder::new_multi_thread()
-                .worker_threads(2) // Dedicated threads for audio processing
-                .thread_name("audio-runtime")
-                .enable_all()
-                .build()
-                .map_err(|e| AudioError::InitializationFailed(format!("Failed to create runtime: {}", e)))?
-        );
+        // Create buffer manager sized from the configured ring buffer
+        let buffer_settings = BufferSettings::from_config(config);
+        let buffer_manager = Arc::new(buffer_settings.buffer_manager(sample_rate, channels));
 
         // Initialize performance monitoring
         let performance_profiler = Arc::new(AudioPerformanceProfiler::new());
         let buffer_allocator = Arc::new(HighResBufferAllocator::new());
 
-        Ok(AudioEngineImpl {
+        let engine = AudioEngineImpl {
             device_manager,
             stream: None,
-            playback_state: Arc::new(Mutex::new(PlaybackState::Stopped)),
+            playback_state: Arc::new(AtomicPlaybackState::new(PlaybackState::Stopped)),
             volume: Arc::new(AtomicU32::new(1.0f32.to_bits())), // Default volume 1.0
             sample_rate,
             bit_depth,
             channels,
+            buffer_settings,
+            decode_timeout: Duration::from_millis(config.decode_timeout_ms),
+            exclusive_requested: config.exclusive_mode,
+            exclusive_guard: None,
+            prefer_native_rate_switch: config.prefer_native_rate_switch,
 
             // Thread communication
             audio_command_sender: None,
             decoder_command_sender: None,
             status_receiver: None,
+            thread_status: ThreadStatus::new(),
             decoder_response_receiver: None,
+            decoder_response_sender: None,
+            decoder_status_sender: None,
 
             // Thread handles
             audio_thread_handle: None,
             decoder_thread_handle: None,
+            abandoned_decoder_threads: 0,
+            decoder_thread_config: config.decoder_thread.clone(),
+            output_cpu_affinity: config.cpu_affinity.clone(),
 
             // Shared state
             buffer_manager,
             is_running: Arc::new(AtomicBool::new(false)),
-            current_position: Arc::new(Mutex::new(Duration::from_secs(0))),
+            current_position: Arc::new(PositionTracker::new(sample_rate)),
+            pending_start: None,
+            current_track_path: None,
+            track_start: Duration::ZERO,
+            preloaded_track_path: None,
             current_decoder: Arc::new(Mutex::new(None)),
             next_decoder: Arc::new(Mutex::new(None)),
             gapless_enabled: Arc::new(AtomicBool::new(true)), // Enable gapless by default
+            silence_settings: Arc::new(Mutex::new(config.silence_settings())),
+            channel_layout: Arc::new(Mutex::new(config.channel_layout.clone())),
+            oversampling: Arc::new(Mutex::new(config.oversampling_factor)),
 
-            runtime,
             performance_profiler,
             buffer_allocator,
+            clip_monitor: Arc::new(ClipMonitor::new()),
+            eq: Arc::new(ParametricEq::new()),
+            fades: Arc::new(FadeControl::new(
+                Duration::from_millis(config.fade_out_ms),
+                Duration::from_millis(config.fade_in_ms),
+            )),
+            channel_control: Arc::new(ChannelControl::new(config.output_channels())),
+            crossfeed: Arc::new(CrossfeedControl::new(config.crossfeed_settings())),
+            dither: Arc::new(DitherControl::new(config.dither)),
+            suspend_output_after_ms: Arc::new(AtomicU64::new(config.suspend_output_after_secs * 1000)),
+            spectrum_tap: Arc::new(SpectrumTap::new()),
+            underrun_monitor: Arc::new(UnderrunMonitor::new()),
+            prebuffer: Arc::new(PrebufferGate::new()),
+            prebuffer_ms: AtomicU64::new(config.prebuffer_ms),
+            prebuffer_armed_at: None,
+            adaptive_buffer: AdaptiveBufferController::new(buffer_settings.adaptive_policy())
+                .with_target(buffer_settings.target_buffer_ms, sample_rate),
             next_track_provider: None,
-        })
+        };
+
+        engine.warn_if_output_buffer_clamped();
+        engine.publish_buffer_settings();
+        Ok(engine)
     }
 
     /// Get the current sample rate
@@ -214,9 +554,220 @@ impl AudioEngineImpl {
         self.channels
     }
 
+    /// Get the configured buffer settings
+    pub fn buffer_settings(&self) -> BufferSettings {
+        self.buffer_settings
+    }
+
+    /// Device buffer size actually used, after clamping to the device's supported range
+    pub fn effective_output_buffer_frames(&self) -> u32 {
+        let requested = self.buffer_settings.output_buffer_frames;
+        self.device_manager.current_device()
+            .and_then(|device| device.default_output_config().ok())
+            .map(|config| clamp_output_buffer_frames(requested, config.buffer_size()))
+            .unwrap_or(requested)
+    }
+
+    /// Latency of one device buffer at the current sample rate, in milliseconds
+    pub fn output_latency_ms(&self) -> f32 {
+        if self.sample_rate == 0 {
+            return 0.0;
+        }
+        self.effective_output_buffer_frames() as f32 * 1000.0 / self.sample_rate as f32
+    }
+
+    /// Set the device buffer size in frames (0 = device default), restarting the stream if needed
+    ///
+    /// Returns the effective size after clamping to the device's supported range.
+    pub fn set_output_buffer_frames(&mut self, frames: u32) -> Result<u32, AudioError> {
+        self.buffer_settings.output_buffer_frames = frames;
+        self.warn_if_output_buffer_clamped();
+
+        if self.is_running.load(Ordering::Relaxed) {
+            self.update_config(self.sample_rate, self.bit_depth, self.channels)?;
+        } else {
+            self.publish_buffer_settings();
+        }
+
+        Ok(self.effective_output_buffer_frames())
+    }
+
+    /// Request exclusive device access, restarting the stream if needed
+    ///
+    /// Returns whether exclusive access is held; `false` after enabling means the engine fell back
+    /// to shared mode.
+    pub fn set_exclusive_mode(&mut self, enable: bool) -> Result<bool, AudioError> {
+        self.exclusive_requested = enable;
+        if !enable {
+            self.exclusive_guard = None;
+        }
+
+        if self.is_running.load(Ordering::Relaxed) {
+            self.update_config(self.sample_rate, self.bit_depth, self.channels)?;
+        } else {
+            self.acquire_exclusive_if_requested();
+        }
+
+        Ok(self.is_exclusive_active())
+    }
+
+    /// Check if exclusive device access is currently held
+    pub fn is_exclusive_active(&self) -> bool {
+        self.exclusive_guard.is_some()
+    }
+
+    /// Take exclusive access to the current device if requested, falling back to shared mode
+    fn acquire_exclusive_if_requested(&mut self) {
+        if !self.exclusive_requested {
+            return;
+        }
+
+        let device_name = match self.device_manager.current_device_name() {
+            Ok(Some(name)) => name,
+            _ => return,
+        };
+        if self.exclusive_guard.as_ref().map(|guard| guard.device_name()) == Some(device_name.as_str()) {
+            return;
+        }
+
+        self.exclusive_guard = None;
+        match exclusive::acquire(&device_name) {
+            Ok(guard) => {
+                log::info!("Exclusive mode acquired on {}", device_name);
+                self.exclusive_guard = Some(guard);
+            }
+            Err(e) => {
+                log::warn!("Exclusive mode unavailable on {} ({}); using shared mode", device_name, e);
+            }
+        }
+    }
+
+    /// Monitors shared with the output callback
+    fn output_monitors(&self) -> OutputMonitors {
+        OutputMonitors {
+            underruns: Arc::clone(&self.underrun_monitor),
+            prebuffer: Arc::clone(&self.prebuffer),
+            chain: self.chain_controls(),
+        }
+    }
+
+    /// The output chain's settings, as the output callback uses them
+    pub fn chain_controls(&self) -> ChainControls {
+        ChainControls {
+            clipping: Arc::clone(&self.clip_monitor),
+            eq: Arc::clone(&self.eq),
+            fades: Arc::clone(&self.fades),
+            channels: Arc::clone(&self.channel_control),
+            crossfeed: Arc::clone(&self.crossfeed),
+            dither: Arc::clone(&self.dither),
+        }
+    }
+
+    /// Total underruns counted by the output callback
+    pub fn underrun_count(&self) -> u64 {
+        self.underrun_monitor.underrun_count()
+    }
+
+    /// Check the underrun counter and grow the buffer after repeated underruns
+    ///
+    /// Call periodically from the control thread; returns the adjustment when one was made.
+    pub fn check_underruns(&mut self) -> Option<BufferAdjustment> {
+        let total = self.underrun_monitor.underrun_count();
+        let target_ms = self.buffer_manager.target_buffer_ms();
+        let adjustment = self.adaptive_buffer.observe(total, target_ms, Instant::now())?;
+
+        self.buffer_settings.target_buffer_ms = adjustment.new_target_ms;
+        let target_frames = (self.sample_rate as u64 * adjustment.new_target_ms / 1000) as usize;
+        if !self.buffer_manager.resize(target_frames) {
+            // Bursts may ask for more than the ring buffer holds; rebuild it larger
+            self.buffer_settings.ring_buffer_ms = adjustment.new_target_ms * 5 / 4;
+            if let Err(e) = self.update_config(self.sample_rate, self.bit_depth, self.channels) {
+                log::warn!("Could not grow the ring buffer: {}", e);
+            }
+        }
+        log::warn!("Adaptive buffering: {}", adjustment.description());
+        if adjustment.pause_ms > 0 {
+            self.underrun_monitor.hold_output(self.sample_rate as u64 * adjustment.pause_ms / 1000);
+        }
+        self.performance_profiler.record_buffer_adjustment(adjustment.new_target_ms);
+
+        Some(adjustment)
+    }
+
+    /// Change how much audio is buffered before a track starts (zero starts at once)
+    pub fn set_prebuffer(&self, prebuffer: Duration) {
+        self.prebuffer_ms.store(prebuffer.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Hold the output back until the ring buffer has filled to the pre-buffer
+    fn arm_prebuffer(&mut self) {
+        let ring_buffer = self.buffer_manager.ring_buffer();
+        // Leave the decoder room to write a whole packet on top of the target
+        let target_frames = (ring_buffer.sample_rate() as u64 * self.prebuffer_ms.load(Ordering::Relaxed) / 1000)
+            .min(ring_buffer.capacity_frames() as u64 * 4 / 5);
+        self.prebuffer.arm(target_frames);
+        self.prebuffer_armed_at = Some(Instant::now());
+    }
+
+    /// How long playback has been waiting for the pre-buffer, if it is
+    pub fn buffering_for(&self) -> Option<Duration> {
+        if self.playback_state.load() != PlaybackState::Playing || !self.prebuffer.is_waiting() {
+            return None;
+        }
+        self.prebuffer_armed_at.map(|armed_at| armed_at.elapsed())
+    }
+
+    /// Stop playback when the pre-buffer has not filled within `PREBUFFER_TIMEOUT`
+    ///
+    /// Called periodically; the error is returned once, after the output has been stopped.
+    pub fn check_prebuffer(&mut self) -> Result<(), AudioError> {
+        let Some(waited) = self.buffering_for().filter(|waited| *waited >= PREBUFFER_TIMEOUT) else {
+            return Ok(());
+        };
+        let ring_buffer = self.buffer_manager.ring_buffer();
+        let rate = ring_buffer.sample_rate().max(1) as u64;
+        let error = AudioError::PrebufferTimeout {
+            waited_secs: waited.as_secs(),
+            buffered_ms: ring_buffer.available_read_frames() as u64 * 1000 / rate,
+            target_ms: self.prebuffer.target_frames() * 1000 / rate,
+        };
+        self.stop()?;
+        Err(error)
+    }
+
+    /// Stream buffer size for cpal from the effective buffer settings
+    fn stream_buffer_size(&self) -> cpal::BufferSize {
+        match self.effective_output_buffer_frames() {
+            0 => cpal::BufferSize::Default,
+            frames => cpal::BufferSize::Fixed(frames),
+        }
+    }
+
+    /// Log a warning when the requested device buffer is outside the supported range
+    fn warn_if_output_buffer_clamped(&self) {
+        let requested = self.buffer_settings.output_buffer_frames;
+        let effective = self.effective_output_buffer_frames();
+        if requested != effective {
+            log::warn!(
+                "Requested buffer of {} frames is not supported by the device; using {} frames",
+                requested, effective
+            );
+        }
+    }
+
+    /// Publish the effective buffer settings to the performance profiler
+    fn publish_buffer_settings(&self) {
+        self.performance_profiler.update_buffer_settings(
+            self.effective_output_buffer_frames(),
+            self.output_latency_ms(),
+            self.buffer_settings.ring_buffer_ms,
+            self.buffer_settings.target_buffer_ms,
+        );
+    }
+
     /// Get the current playback state
     pub fn playback_state(&self) -> PlaybackState {
-        self.playback_state.lock().unwrap().clone()
+        self.playback_state.load()
     }
 
     /// Get the current volume (0.0 to 1.0)
@@ -240,15 +791,20 @@ impl AudioEngineImpl {
         self.decoder_command_sender = Some(decoder_cmd_tx);
         self.status_receiver = Some(status_rx);
         self.decoder_response_receiver = Some(decoder_resp_rx);
+        self.decoder_response_sender = Some(decoder_resp_tx.clone());
+        self.decoder_status_sender = Some(status_tx.clone());
 
-        // Start audio thread
-        self.start_audio_thread(audio_cmd_rx, status_tx.clone())?;
-
-        // Start decoder thread
-        self.start_decoder_thread(decoder_cmd_rx, decoder_resp_tx, status_tx)?;
-
+        // Both threads run while this is set, so raise it before they start
         self.is_running.store(true, Ordering::Relaxed);
-        Ok(())
+
+        // Start audio thread
+        let started = self.start_audio_thread(audio_cmd_rx, status_tx.clone())
+            // Start decoder thread
+            .and_then(|()| self.start_decoder_thread(decoder_cmd_rx, decoder_resp_tx, status_tx));
+        if started.is_err() {
+            self.is_running.store(false, Ordering::Relaxed);
+        }
+        started
     }
 
     /// Start the high-priority audio output thread
@@ -257,6 +813,8 @@ impl AudioEngineImpl {
         command_receiver: Receiver<AudioCommand>,
         status_sender: tokio_mpsc::UnboundedSender<ThreadStatus>,
     ) -> Result<(), AudioError> {
+        self.acquire_exclusive_if_requested();
+
         let device = self.device_manager.current_device()
             .ok_or_else(|| AudioError::InitializationFailed("No device selected".to_string()))?
             .clone();
@@ -264,7 +822,7 @@ impl AudioEngineImpl {
         let config = StreamConfig {
             channels: self.channels,
             sample_rate: SampleRate(self.sample_rate),
-            buffer_size: cpal::BufferSize::Default,
+            buffer_size: self.stream_buffer_size(),
         };
 
         let playback_state = Arc::clone(&self.playback_state);
@@ -272,6 +830,9 @@ impl AudioEngineImpl {
         let is_running = Arc::clone(&self.is_running);
         let buffer_manager = Arc::clone(&self.buffer_manager);
         let current_position = Arc::clone(&self.current_position);
+        let monitors = self.output_monitors();
+        let suspend_after_ms = Arc::clone(&self.suspend_output_after_ms);
+        let pre_roll_frames = self.sample_rate as u64 * RESUME_PRE_ROLL.as_millis() as u64 / 1000;
 
         // Get the default sample format
         let default_config = device.default_output_config()
@@ -279,25 +840,17 @@ impl AudioEngineImpl {
 
         let sample_format = default_config.sample_format();
         let ring_buffer = buffer_manager.ring_buffer();
+        let cpu_affinity = self.output_cpu_affinity.clone();
 
         // Create the audio thread
         let audio_thread = thread::Builder::new()
             .name("audio-output".to_string())
             .spawn(move || {
-                // Set high priority for audio thread (platform-specific)
-                #[cfg(target_os = "macos")]
-                {
-                    unsafe {
-                        let thread = libc::pthread_self();
-                        let mut policy: libc::c_int = 0;
-                        let mut param: libc::sched_param = std::mem::zeroed();
-
-                        if libc::pthread_getschedparam(thread, &mut policy, &mut param) == 0 {
-                            param.sched_priority = 63; // High priority
-                            let _ = libc::pthread_setschedparam(thread, libc::SCHED_FIFO, &param);
-                        }
-                    }
+                if let Some(cores) = &cpu_affinity {
+                    crate::audio::priority::pin_current_thread(cores, "audio output");
                 }
+                // Set high priority for audio thread (platform-specific)
+                crate::audio::priority::elevate_current_thread();
 
                 let mut last_status_update = Instant::now();
                 let status_update_interval = Duration::from_millis(100); // 10Hz status updates
@@ -305,13 +858,13 @@ impl AudioEngineImpl {
                 // Create the audio stream based on sample format
                 let stream_result = match sample_format {
                     SampleFormat::F32 => Self::create_audio_stream::<f32>(
-                        &device, &config, &playback_state, &volume, &ring_buffer, &current_position
+                        &device, &config, &playback_state, &volume, &ring_buffer, &current_position, &monitors
                     ),
                     SampleFormat::I16 => Self::create_audio_stream::<i16>(
-                        &device, &config, &playback_state, &volume, &ring_buffer, &current_position
+                        &device, &config, &playback_state, &volume, &ring_buffer, &current_position, &monitors
                     ),
                     SampleFormat::U16 => Self::create_audio_stream::<u16>(
-                        &device, &config, &playback_state, &volume, &ring_buffer, &current_position
+                        &device, &config, &playback_state, &volume, &ring_buffer, &current_position, &monitors
                     ),
                     _ => {
                         eprintln!("Unsupported sample format: {:?}", sample_format);
@@ -333,27 +886,28 @@ impl AudioEngineImpl {
                     return;
                 }
 
+                // Set while paused or stopped; the stream is suspended once that lasts long enough
+                let mut idle_since: Option<Instant> = None;
+                let mut suspended = false;
+
                 // Audio thread main loop
                 while is_running.load(Ordering::Relaxed) {
                     // Process commands
                     while let Ok(command) = command_receiver.try_recv() {
                         match command {
                             AudioCommand::Play => {
-                                *playback_state.lock().unwrap() = PlaybackState::Playing;
+                                playback_state.store(PlaybackState::Playing);
                             }
                             AudioCommand::Pause => {
-                                *playback_state.lock().unwrap() = PlaybackState::Paused;
+                                playback_state.store(PlaybackState::Paused);
                             }
                             AudioCommand::Stop => {
-                                *playback_state.lock().unwrap() = PlaybackState::Stopped;
-                                *current_position.lock().unwrap() = Duration::from_secs(0);
+                                // The engine puts the position back to the track's start
+                                playback_state.store(PlaybackState::Stopped);
                             }
                             AudioCommand::SetVolume(_) => {
                                 // Volume is handled via atomic variable
                             }
-                            AudioCommand::Seek(position) => {
-                                *current_position.lock().unwrap() = position;
-                            }
                             AudioCommand::Shutdown => {
                                 is_running.store(false, Ordering::Relaxed);
                                 break;
@@ -361,17 +915,46 @@ impl AudioEngineImpl {
                         }
                     }
 
+                    // The callback plays silence while idle but keeps the device busy; suspending
+                    // the stream lets the OS power it down
+                    if playback_state.load() == PlaybackState::Playing {
+                        idle_since = None;
+                        if suspended {
+                            suspended = false;
+                            monitors.underruns.hold_output(pre_roll_frames);
+                            if let Err(e) = stream.play() {
+                                eprintln!("Failed to restart audio stream: {}", e);
+                            }
+                        }
+                    } else {
+                        let idle_for = idle_since.get_or_insert_with(Instant::now).elapsed();
+                        let after_ms = suspend_after_ms.load(Ordering::Relaxed);
+                        if !suspended && after_ms > 0 && idle_for >= Duration::from_millis(after_ms) {
+                            match stream.pause() {
+                                Ok(()) => suspended = true,
+                                Err(e) => {
+                                    // Not every backend can pause a stream; keep it running
+                                    log::debug!("Could not suspend audio stream: {}", e);
+                                    idle_since = Some(Instant::now());
+                                }
+                            }
+                        }
+                    }
+
                     // Send status updates periodically
                     if last_status_update.elapsed() >= status_update_interval {
-                        let state = playback_state.lock().unwrap().clone();
-                        let position = current_position.lock().unwrap().clone();
+                        let state = playback_state.load();
+                        let position = current_position.position();
                         let buffer_fill = ring_buffer.fill_level();
 
                         let status = ThreadStatus {
+                            origin: StatusOrigin::Output,
                             playback_state: state,
                             position,
                             buffer_fill,
                             current_file: None, // Will be set by decoder thread
+                            source_format: None,
+                            resampling: false,
                         };
 
                         let _ = status_sender.send(status);
@@ -395,10 +978,11 @@ impl AudioEngineImpl {
     fn create_audio_stream<T>(
         device: &cpal::Device,
         config: &StreamConfig,
-        playback_state: &Arc<Mutex<PlaybackState>>,
+        playback_state: &Arc<AtomicPlaybackState>,
         volume: &Arc<AtomicU32>,
         ring_buffer: &Arc<RingBuffer>,
-        current_position: &Arc<Mutex<Duration>>,
+        current_position: &Arc<PositionTracker>,
+        monitors: &OutputMonitors,
     ) -> Result<Stream, AudioError>
     where
         T: cpal::Sample + cpal::SizedSample + Send + 'static,
@@ -408,42 +992,55 @@ impl AudioEngineImpl {
         let volume = Arc::clone(volume);
         let ring_buffer = Arc::clone(ring_buffer);
         let current_position = Arc::clone(current_position);
-        let sample_rate = config.sample_rate.0 as f64;
+        let monitors = monitors.clone();
         let channels = config.channels as usize;
+        // Reused between callbacks so the hot path neither locks nor allocates
+        let mut audio_data = callback_scratch(config);
+        let mut chain = monitors.chain.chain(config.sample_rate.0, channels, T::FORMAT);
 
         let stream = device.build_output_stream(
             config,
             move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
-                let state = playback_state.lock().unwrap().clone();
+                let state = playback_state.load();
                 let current_volume = f32::from_bits(volume.load(Ordering::Relaxed));
 
                 match state {
                     PlaybackState::Playing => {
+                        // Let the decoder refill while an underrun hold or the pre-buffer is active
+                        let frames_needed = data.len() / channels;
+                        if monitors.prebuffer.hold(ring_buffer.available_read_frames() as u64)
+                            || monitors.underruns.consume_hold(frames_needed as u64)
+                        {
+                            for sample in data.iter_mut() {
+                                *sample = cpal::Sample::from_sample(0.0f32);
+                            }
+                            return;
+                        }
+
                         // Read audio data from ring buffer
-                        let _frames_needed = data.len() / channels;
-                        let mut audio_data = vec![0.0f32; data.len()];
+                        audio_data.resize(data.len(), 0.0);
                         let samples_read = ring_buffer.read(&mut audio_data);
+                        if samples_read < data.len() {
+                            monitors.underruns.record_short_read();
+                        }
 
-                        // Apply volume and convert to output format
+                        chain.process(&mut audio_data[..samples_read], current_volume);
+
+                        // Convert to output format
                         for (i, sample) in data.iter_mut().enumerate() {
                             let audio_sample = if i < samples_read {
-                                audio_data[i] * current_volume
+                                audio_data[i]
                             } else {
                                 0.0 // Silence if not enough data
                             };
                             *sample = cpal::Sample::from_sample(audio_sample);
                         }
 
-                        // Update position based on samples consumed
-                        if samples_read > 0 {
-                            let frames_consumed = samples_read / channels;
-                            let time_consumed = Duration::from_secs_f64(frames_consumed as f64 / sample_rate);
-                            if let Ok(mut pos) = current_position.lock() {
-                                *pos += time_consumed;
-                            }
-                        }
+                        // Count frames consumed; the reader converts them to a position
+                        current_position.advance((samples_read / channels) as u64);
                     }
                     PlaybackState::Paused | PlaybackState::Stopped => {
+                        chain.reset_fades();
                         // Output silence
                         for sample in data.iter_mut() {
                             *sample = cpal::Sample::from_sample(0.0f32);
@@ -461,49 +1058,194 @@ impl AudioEngineImpl {
         Ok(stream)
     }
 
-    /// Start the background decoder thread
+    /// Start the decoder on its own OS thread with a single-threaded Tokio runtime
+    ///
+    /// The thread gets the configured stack size and scheduling priority, so decoding never
+    /// competes with other async work for a runtime worker. Once `MAX_ABANDONED_DECODER_THREADS`
+    /// stuck threads have been left behind, no new thread is started.
     fn start_decoder_thread(
         &mut self,
-        mut command_receiver: tokio_mpsc::UnboundedReceiver<DecoderCommand>,
+        command_receiver: tokio_mpsc::UnboundedReceiver<DecoderCommand>,
         response_sender: tokio_mpsc::UnboundedSender<DecoderResponse>,
         status_sender: tokio_mpsc::UnboundedSender<ThreadStatus>,
     ) -> Result<(), AudioError> {
-        let buffer_manager = Arc::clone(&self.buffer_manager);
-        let current_decoder = Arc::clone(&self.current_decoder);
-        let next_decoder = Arc::clone(&self.next_decoder);
-        let gapless_enabled = Arc::clone(&self.gapless_enabled);
-        let is_running = Arc::clone(&self.is_running);
-        let runtime = Arc::clone(&self.runtime);
-        let next_track_provider = self.next_track_provider.clone();
-
-        let decoder_thread = runtime.spawn(async move {
-            let mut current_file: Option<std::path::PathBuf> = None;
-            let mut next_file: Option<std::path::PathBuf> = None;
-            let mut decode_position = Duration::from_secs(0);
-            let mut is_transitioning = false;
-
-            while is_running.load(Ordering::Relaxed) {
-                // Process commands
-                tokio::select! {
-                    command = command_receiver.recv() => {
-                        match command {
-                            Some(DecoderCommand::LoadFile(path)) => {
-                                // Load new audio file
-                                match Self::load_audio_file(&path).await {
+        if self.abandoned_decoder_threads >= MAX_ABANDONED_DECODER_THREADS {
+            return Err(AudioError::InitializationFailed(format!(
+                "{} decoder threads are stuck in decodes that never returned; restart the player to keep decoding",
+                self.abandoned_decoder_threads
+            )));
+        }
+
+        let context = DecoderContext {
+            buffer_manager: Arc::clone(&self.buffer_manager),
+            current_decoder: Arc::clone(&self.current_decoder),
+            next_decoder: Arc::clone(&self.next_decoder),
+            gapless_enabled: Arc::clone(&self.gapless_enabled),
+            is_running: Arc::clone(&self.is_running),
+            decode_timeout: self.decode_timeout,
+            next_track_provider: self.next_track_provider.clone(),
+            spectrum_tap: Arc::clone(&self.spectrum_tap),
+            underrun_monitor: Arc::clone(&self.underrun_monitor),
+            prebuffer: Arc::clone(&self.prebuffer),
+            performance_profiler: Arc::clone(&self.performance_profiler),
+            current_position: Arc::clone(&self.current_position),
+            silence_settings: Arc::clone(&self.silence_settings),
+            channel_layout: Arc::clone(&self.channel_layout),
+            oversampling: Arc::clone(&self.oversampling),
+            crossfeed: Arc::clone(&self.crossfeed),
+        };
+
+        let decoder_thread = spawn_decoder_thread(
+            &self.decoder_thread_config,
+            Self::run_decoder_loop(context, command_receiver, response_sender, status_sender),
+        )
+        .map_err(|e| AudioError::InitializationFailed(format!("Failed to create decoder thread: {}", e)))?;
+
+        self.decoder_thread_handle = Some(decoder_thread);
+        Ok(())
+    }
+
+    /// Start a new decoder thread in place of one stuck in `decode_next`
+    ///
+    /// The stuck thread is detached with its command channel. If its call ever returns, it sees
+    /// that the watchdog gave up on it and exits without touching the shared state; until then
+    /// it counts towards `MAX_ABANDONED_DECODER_THREADS`.
+    fn replace_stuck_decoder_thread(&mut self) {
+        self.underrun_monitor.set_source_active(false);
+        let (Some(response_sender), Some(status_sender)) =
+            (self.decoder_response_sender.clone(), self.decoder_status_sender.clone())
+        else {
+            return;
+        };
+        log::warn!("Decoder thread is stuck in a decode; starting a new one");
+        let (command_sender, command_receiver) = tokio_mpsc::unbounded_channel();
+        self.decoder_command_sender = Some(command_sender);
+        self.decoder_thread_handle = None;
+        self.abandoned_decoder_threads += 1;
+        if let Err(e) = self.start_decoder_thread(command_receiver, response_sender, status_sender) {
+            log::error!("{}", e);
+        }
+    }
+
+    /// Decode ahead of the output until the engine stops running
+    async fn run_decoder_loop(
+        context: DecoderContext,
+        mut command_receiver: tokio_mpsc::UnboundedReceiver<DecoderCommand>,
+        response_sender: tokio_mpsc::UnboundedSender<DecoderResponse>,
+        status_sender: tokio_mpsc::UnboundedSender<ThreadStatus>,
+    ) {
+        let DecoderContext {
+            buffer_manager,
+            current_decoder,
+            next_decoder,
+            gapless_enabled,
+            is_running,
+            decode_timeout,
+            next_track_provider,
+            spectrum_tap,
+            underrun_monitor,
+            prebuffer,
+            performance_profiler,
+            current_position,
+            silence_settings,
+            channel_layout,
+            oversampling,
+            crossfeed,
+        } = context;
+
+        let mut current_file: Option<std::path::PathBuf> = None;
+        let mut next_file: Option<std::path::PathBuf> = None;
+        let mut source_format: Option<AudioFormat> = None;
+        let mut trimmer = GaplessTrimmer::new(0, 0);
+        let mut silence = SilenceSkipper::new(Duration::ZERO);
+        let mut decode_position = Duration::from_secs(0);
+        let mut is_transitioning = false;
+        let mut loop_region: Option<LoopRegion> = None;
+        let mut rate_adapter = RateAdapter::new();
+        // Stopped: the rewound track waits instead of decoding on into the next one
+        let mut parked = false;
+        // A single bad packet is skipped; a file that keeps failing is abandoned
+        let mut decode_errors = 0u32;
+        // Stream and title last reported with `StreamMetadata`
+        let mut stream_title: Option<(std::path::PathBuf, Option<String>)> = None;
+        // decode_next runs on this thread; a call that hangs is reported by the watchdog so the
+        // engine can start a new decoder thread while this one is stuck
+        let watchdog = Arc::new(DecodeWatchdog::new());
+        let _monitor = if decode_timeout.is_zero() {
+            None
+        } else {
+            let timeout_sender = response_sender.clone();
+            let reported = watchdog.monitor(decode_timeout, move |path| {
+                let error = AudioError::StreamError(DECODE_TIMEOUT_MESSAGE.to_string());
+                let _ = timeout_sender.send(DecoderResponse::TrackFailed { path, error });
+            });
+            reported.map_err(|e| log::warn!("Could not start the decode watchdog; decodes are unbounded: {}", e)).ok()
+        };
+
+        while is_running.load(Ordering::Relaxed) {
+            // Process commands
+            tokio::select! {
+                command = command_receiver.recv() => {
+                    match command {
+                        Some(DecoderCommand::LoadFile(path)) => {
+                            // Nothing is buffered for the new track yet
+                            underrun_monitor.set_source_active(false);
+                            parked = false;
+
+                            // Load new audio file
+                            match Self::open_decoder(&path) {
+                                Ok(decoder) => {
+                                    Self::log_decoder_opened(&path, decoder.as_ref());
+                                    let duration = decoder.duration();
+                                    let sample_rate = decoder.sample_rate();
+                                    let bit_depth = decoder.bit_depth();
+                                    let channels = decoder.channels();
+
+                                    source_format = Some(Self::source_format(decoder.as_ref()));
+
+                                    trimmer = GaplessTrimmer::for_decoder(decoder.as_ref());
+
+                                    silence = SilenceSkipper::for_decoder(decoder.as_ref());
+
+                                    // Clean up previous decoder
+                                    *current_decoder.lock().unwrap() = None;
+                                    *current_decoder.lock().unwrap() = Some(decoder);
+                                    current_file = Some(path.clone());
+                                    decode_position = Duration::from_secs(0);
+                                    is_transitioning = false;
+                                    loop_region = None;
+                                    current_position.set_loop(None);
+                                    decode_errors = 0;
+                                    rate_adapter.reset();
+
+                                    let _ = response_sender.send(DecoderResponse::FileLoaded {
+                                        path,
+                                        duration,
+                                        sample_rate,
+                                        bit_depth,
+                                        channels,
+                                    });
+                                }
+                                Err(error) => {
+                                    let _ = response_sender.send(DecoderResponse::TrackFailed { path, error });
+                                }
+                            }
+                        }
+                        Some(DecoderCommand::PreloadNext(path)) => {
+                            // Preload next track for gapless playback
+                            if gapless_enabled.load(Ordering::Relaxed) {
+                                match Self::open_decoder(&path) {
                                     Ok(decoder) => {
+                                        Self::log_decoder_opened(&path, decoder.as_ref());
                                         let duration = decoder.duration();
                                         let sample_rate = decoder.sample_rate();
                                         let bit_depth = decoder.bit_depth();
                                         let channels = decoder.channels();
 
-                                        // Clean up previous decoder
-                                        *current_decoder.lock().unwrap() = None;
-                                        *current_decoder.lock().unwrap() = Some(decoder);
-                                        current_file = Some(path);
-                                        decode_position = Duration::from_secs(0);
-                                        is_transitioning = false;
+                                        *next_decoder.lock().unwrap() = Some(decoder);
+                                        next_file = Some(path);
 
-                                        let _ = response_sender.send(DecoderResponse::FileLoaded {
+                                        let _ = response_sender.send(DecoderResponse::NextTrackPreloaded {
                                             duration,
                                             sample_rate,
                                             bit_depth,
@@ -515,293 +1257,500 @@ impl AudioEngineImpl {
                                     }
                                 }
                             }
-                            Some(DecoderCommand::PreloadNext(path)) => {
-                                // Preload next track for gapless playback
-                                if gapless_enabled.load(Ordering::Relaxed) {
-                                    match Self::load_audio_file(&path).await {
-                                        Ok(decoder) => {
-                                            let duration = decoder.duration();
-                                            let sample_rate = decoder.sample_rate();
-                                            let bit_depth = decoder.bit_depth();
-                                            let channels = decoder.channels();
-
-                                            *next_decoder.lock().unwrap() = Some(decoder);
-                                            next_file = Some(path);
-
-                                            let _ = response_sender.send(DecoderResponse::NextTrackPreloaded {
-                                                duration,
-                                                sample_rate,
-                                                bit_depth,
-                                                channels,
-                                            });
-                                        }
-                                        Err(e) => {
-                                            let _ = response_sender.send(DecoderResponse::Error(e));
-                                        }
-                                    }
-                                }
-                            }
-                            Some(DecoderCommand::NextTrack) => {
-                                // Transition to next track when requested or when preloaded
-                                if let Some(next_dec) = next_decoder.lock().unwrap().take() {
-                                    // Move next decoder to current
-                                    *current_decoder.lock().unwrap() = Some(next_dec);
-                                    current_file = next_file.take();
-                                    decode_position = Duration::from_secs(0);
-                                    is_transitioning = true;
+                        }
+                        Some(DecoderCommand::DiscardPreload) => {
+                            *next_decoder.lock().unwrap() = None;
+                            next_file = None;
+                        }
+                        Some(DecoderCommand::NextTrack) => {
+                            // Transition to next track when requested or when preloaded
+                            if let Some(next_dec) = next_decoder.lock().unwrap().take() {
+                                // Move next decoder to current
+                                source_format = Some(Self::source_format(next_dec.as_ref()));
+                                trimmer = GaplessTrimmer::for_decoder(next_dec.as_ref());
+                                silence = SilenceSkipper::for_decoder(next_dec.as_ref());
+                                *current_decoder.lock().unwrap() = Some(next_dec);
+                                current_file = next_file.take();
+                                decode_position = Duration::from_secs(0);
+                                is_transitioning = true;
+                                loop_region = None;
+                                current_position.set_loop(None);
 
-                                    let _ = response_sender.send(DecoderResponse::TrackTransitioned);
+                                let _ = response_sender.send(DecoderResponse::TrackTransitioned);
+                            }
+                        }
+                        Some(DecoderCommand::Seek(position)) => {
+                            // Take the decoder out to avoid holding a MutexGuard across .await
+                            let mut taken = current_decoder.lock().unwrap().take();
+                            if let Some(decoder) = taken.as_mut() {
+                                match decoder.seek(position) {
+                                    Ok(()) => {
+                                        // Output may have counted stale frames since the engine set the position
+                                        buffer_manager.ring_buffer().clear();
+                                        current_position.set(position);
+                                        decode_position = position;
+                                        rate_adapter.reset();
+                                        trimmer.seek(position);
+                                        silence.seek(position);
+                                    }
+                                    Err(e) => {
+                                        // Playback carries on from the decoder's position; put the clock back there
+                                        let actual = decode_position
+                                            .saturating_sub(buffer_manager.ring_buffer().buffered_duration());
+                                        current_position.set(actual);
+                                        let _ = response_sender.send(DecoderResponse::SeekFailed {
+                                            requested: position,
+                                            position: actual,
+                                            reason: e.to_string(),
+                                        });
+                                    }
                                 }
                             }
-                            Some(DecoderCommand::Seek(position)) => {
-                                // Take the decoder out to avoid holding a MutexGuard across .await
+                            // Put decoder back (if still present)
+                            *current_decoder.lock().unwrap() = taken;
+                        }
+                        Some(DecoderCommand::SetLoop(region)) => {
+                            loop_region = region;
+                            // Audio past B is already buffered; drop it and restart from A
+                            if let Some(region) = region.filter(|region| decode_position > region.end) {
                                 let mut taken = current_decoder.lock().unwrap().take();
                                 if let Some(decoder) = taken.as_mut() {
-                                    if let Err(e) = decoder.seek(position) {
-                                        eprintln!("Seek error: {}", e);
-                                    } else {
-                                        decode_position = position;
+                                    match decoder.seek(region.start) {
+                                        Ok(()) => {
+                                            buffer_manager.ring_buffer().clear();
+                                            decode_position = region.start;
+                                            rate_adapter.reset();
+                                            trimmer.seek(region.start);
+                                            silence.seek(region.start);
+                                            current_position.set(region.start);
+                                        }
+                                        Err(e) => eprintln!("Loop seek error: {}", e),
                                     }
                                 }
-                                // Put decoder back (if still present)
                                 *current_decoder.lock().unwrap() = taken;
                             }
-                            Some(DecoderCommand::Stop) => {
-                                *current_decoder.lock().unwrap() = None;
-                                *next_decoder.lock().unwrap() = None;
-                                current_file = None;
-                                next_file = None;
-                                decode_position = Duration::from_secs(0);
-                                is_transitioning = false;
+                        }
+                        Some(DecoderCommand::Stop { path, start }) => {
+                            underrun_monitor.set_source_active(false);
+                            parked = true;
+                            // The track stays loaded for the next play, from its start
+                            buffer_manager.ring_buffer().clear();
+                            rate_adapter.reset();
+                            is_transitioning = false;
+                            loop_region = None;
+                            current_position.set_loop(None);
+                            decode_errors = 0;
+                            let mut taken = current_decoder.lock().unwrap().take();
+                            if taken.as_mut().is_none_or(|decoder| decoder.seek(start).is_err()) {
+                                // Reopen the file when the decoder cannot go back
+                                taken = path.as_ref()
+                                    .and_then(|path| Self::open_decoder(path).ok())
+                                    .and_then(|mut decoder| (start.is_zero() || decoder.seek(start).is_ok()).then_some(decoder));
                             }
-                            Some(DecoderCommand::Shutdown) => {
-                                break;
+                            if let Some(decoder) = taken.as_ref() {
+                                source_format = Some(Self::source_format(decoder.as_ref()));
+                                trimmer = GaplessTrimmer::for_decoder(decoder.as_ref());
+                                trimmer.seek(start);
+                                silence = SilenceSkipper::for_decoder(decoder.as_ref());
+                                silence.seek(start);
+                                decode_position = start;
+                                current_file = path.or(current_file);
+                            } else if let Some(path) = path {
+                                let error = AudioError::StreamError("Could not rewind the stopped track".to_string());
+                                let _ = response_sender.send(DecoderResponse::TrackFailed { path, error });
                             }
-                            None => break,
+                            *current_decoder.lock().unwrap() = taken;
                         }
+                        Some(DecoderCommand::Resume) => {
+                            parked = false;
+                        }
+                        Some(DecoderCommand::Unload) => {
+                            underrun_monitor.set_source_active(false);
+                            // Audio left over from the unloaded track must not play ahead of the next one
+                            buffer_manager.ring_buffer().clear();
+                            *current_decoder.lock().unwrap() = None;
+                            rate_adapter.reset();
+                            *next_decoder.lock().unwrap() = None;
+                            current_file = None;
+                            next_file = None;
+                            source_format = None;
+                            trimmer = GaplessTrimmer::new(0, 0);
+                            silence = SilenceSkipper::new(Duration::ZERO);
+                            decode_position = Duration::from_secs(0);
+                            is_transitioning = false;
+                            loop_region = None;
+                            current_position.set_loop(None);
+                        }
+                        Some(DecoderCommand::Shutdown) => {
+                            break;
+                        }
+                        None => break,
                     }
+                }
 
-                    // Fill buffer if needed
-                    _ = tokio::time::sleep(Duration::from_millis(10)) => {
-                        if buffer_manager.needs_data() {
-                            // Take the decoder to avoid holding a MutexGuard across .await
-                            let mut taken_decoder = current_decoder.lock().unwrap().take();
-                            if let Some(decoder) = taken_decoder.as_mut() {
-                                match decoder.decode_next() {
-                                    Ok(Some(audio_buffer)) => {
-                                        let ring_buffer = buffer_manager.ring_buffer();
-                                        // Determine ring buffer channel count
-                                        let rb_channels = ring_buffer.channels();
-
-                                        let frames_written = if audio_buffer.channels == rb_channels {
-                                            {
-                                                let target_sr = ring_buffer.sample_rate();
-                                                if audio_buffer.sample_rate != target_sr {
-                                                    let mut rs = LinearResampler::new(audio_buffer.sample_rate, target_sr, rb_channels as usize);
-
-                                                    let resampled = rs.process_audio_buffer(&audio_buffer);
-                                                    ring_buffer.write_audio_buffer(&resampled)
-                                                } else {
-                                                    ring_buffer.write_audio_buffer(&audio_buffer)
-                                                }
-                                            }
-                                        } else {
-                                            // Upmix/downmix to match ring buffer channels
-                                            let src_ch = audio_buffer.channels as usize;
-                                            let dst_ch = rb_channels as usize;
-                                            let frames = audio_buffer.frames;
-                                            let src = &audio_buffer.samples;
-                                            let mut dst_samples = Vec::with_capacity(frames * dst_ch);
-
-                                            if dst_ch == 1 {
-                                                // Downmix to mono by averaging channels
-                                                for f in 0..frames {
-                                                    let mut acc = 0.0f32;
-                                                    for c in 0..src_ch {
-                                                        acc += src[f * src_ch + c];
-                                                    }
-                                                    dst_samples.push(acc / src_ch as f32);
-                                                }
-                                            } else if dst_ch == 2 && src_ch == 1 {
-                                                // Upmix mono to stereo by duplicating
-                                                for f in 0..frames {
-                                                    let s = src[f];
-                                                    dst_samples.push(s);
-                                                    dst_samples.push(s);
-                                                }
-                                            } else {
-                                                // Generic channel mapping: copy available channels, pad with silence
-                                                for f in 0..frames {
-                                                    for c in 0..dst_ch {
-                                                        let s = if c < src_ch { src[f * src_ch + c] } else { 0.0 };
-                                                        dst_samples.push(s);
-                                                    }
-                                                }
-                                            }
-
-                                            let converted = crate::models::AudioBuffer {
-                                                samples: dst_samples,
-                                                channels: rb_channels,
-                                                sample_rate: audio_buffer.sample_rate,
-                                                frames,
-                                            };
-                                            {
-                                                let target_sr = ring_buffer.sample_rate();
-                                                if converted.sample_rate != target_sr {
-                                                    let mut rs = LinearResampler::new(converted.sample_rate, target_sr, rb_channels as usize);
-
-                                                    let resampled = rs.process_audio_buffer(&converted);
-                                                    ring_buffer.write_audio_buffer(&resampled)
-                                                } else {
-                                                    ring_buffer.write_audio_buffer(&converted)
-                                                }
-                                            }
-                                        };
-
-                                        if frames_written > 0 {
-                                            let time_decoded = Duration::from_secs_f64(
-                                                frames_written as f64 / audio_buffer.sample_rate as f64
-                                            );
-                                            decode_position += time_decoded;
-
-                                            let _ = response_sender.send(DecoderResponse::BufferFilled(frames_written));
+                // Fill buffer if needed
+                _ = tokio::time::sleep(Duration::from_millis(10)) => {
+                    // A pre-buffer longer than the refill target is filled before the output starts
+                    if !parked && (buffer_manager.needs_data() || prebuffer.is_waiting()) {
+                        // Take the decoder so the lock is not held while it decodes
+                        let taken = current_decoder.lock().unwrap().take();
+                        if let Some(decoder) = taken {
+                            let decode_profile = performance_profiler.start_decode_profile();
+                            // A stream bounds its own waits, and reconnecting can outlast the timeout
+                            let stream = current_file.as_deref().and_then(crate::audio::source::stream_url);
+                            let timeout = if stream.is_some() { Duration::ZERO } else { decode_timeout };
+                            if let Some(status) = stream.and_then(crate::audio::source::stream_status) {
+                                let path = current_file.clone().unwrap_or_default();
+                                let reported = (path.clone(), status.title);
+                                if stream_title.as_ref() != Some(&reported) {
+                                    if let Some(title) = reported.1.clone() {
+                                        let _ = response_sender.send(DecoderResponse::StreamMetadata { path, title });
+                                    }
+                                    stream_title = Some(reported);
+                                }
+                            }
+                            let mut decoder = decoder;
+                            watchdog.arm(current_file.as_deref().unwrap_or(std::path::Path::new("")), timeout);
+                            let decoded = decoder.decode_next();
+                            if !watchdog.disarm() {
+                                // The watchdog reported the hang and the engine replaced this thread;
+                                // leave the shared state to the new one and drop the hung decoder
+                                return;
+                            }
+                            decode_profile.finish(decoder.sample_rate(), decoder.bit_depth());
+                            let mut taken_decoder = Some(decoder);
+                            let decoder = taken_decoder.as_mut().unwrap();
+                            // Drop encoder delay/padding so tracks join sample-accurately, then cut silence at the edges
+                            let decoded = decoded.map(|buffer| buffer.and_then(|buffer| {
+                                let mut settings = *silence_settings.lock().unwrap();
+                                // A loop plays exactly what the listener marked
+                                settings.enabled &= loop_region.is_none();
+                                match silence.process(trimmer.process(buffer), &settings) {
+                                    SilenceStep::Play { buffer, skipped, cut_at } => {
+                                        if !skipped.is_zero() {
+                                            decode_position += skipped;
+                                            // Counted when the output reaches the cut, not while it is buffered
+                                            let ring_buffer = buffer_manager.ring_buffer();
+                                            let cut_at = cut_at as u64 * ring_buffer.sample_rate() as u64 / buffer.sample_rate.max(1) as u64;
+                                            current_position.skip_after(ring_buffer.available_read_frames() as u64 + cut_at, skipped);
                                         }
-                                        // Put the decoder back for subsequent decode iterations
+                                        Some(buffer)
+                                    }
+                                    SilenceStep::EndOfTrack => None,
+                                }
+                            }));
+                            match decoded {
+                                Ok(Some(mut audio_buffer)) => {
+                                    decode_errors = 0;
+                                    // Cut the buffer at B so the loop restarts on the exact frame
+                                    let loop_end_reached = loop_region.is_some_and(|region| {
+                                        let remaining = region.end.saturating_sub(decode_position);
+                                        let frames = (remaining.as_secs_f64() * audio_buffer.sample_rate as f64).round() as usize;
+                                        audio_buffer.truncate(frames);
+                                        frames <= audio_buffer.frames
+                                    });
+                                    let ring_buffer = buffer_manager.ring_buffer();
+                                    let source_channels = audio_buffer.channels;
+
+                                    // The stream keeps its rate across tracks; other rates are resampled into it,
+                                    // or oversampled when the stream was opened at a multiple of the track's
+                                    rate_adapter.set_oversampling(*oversampling.lock().unwrap());
+                                    let layout = channel_layout.lock().unwrap().clone();
+                                    let converted = chain::to_output_format(
+                                        audio_buffer, ring_buffer.channels(), layout, &mut rate_adapter, ring_buffer.sample_rate(),
+                                    );
+                                    let frames_written = ring_buffer.write_audio_buffer(&converted);
+
+                                    if frames_written > 0 {
+                                        underrun_monitor.set_source_active(true);
+                                        // Crossfeed leaves mono and surround sources alone
+                                        crossfeed.set_source_channels(source_channels);
+                                        spectrum_tap.push(&converted);
+
+                                        // Written frames are at the ring buffer's (possibly resampled) rate
+                                        let time_decoded = Duration::from_secs_f64(
+                                            frames_written as f64 / ring_buffer.sample_rate() as f64
+                                        );
+                                        decode_position += time_decoded;
+
+                                        let _ = response_sender.send(DecoderResponse::BufferFilled(frames_written));
+                                    }
+                                    if let Some(region) = loop_region.filter(|_| loop_end_reached) {
+                                        Self::restart_loop(decoder.as_mut(), region, &mut decode_position, &mut trimmer, &mut silence, &mut loop_region, &current_position);
+                                    }
+                                    // Put the decoder back for subsequent decode iterations
+                                    *current_decoder.lock().unwrap() = taken_decoder;
+                                }
+                                Ok(None) => {
+                                    // A loop ending at the very end of the file wraps instead of finishing
+                                    if let Some(region) = loop_region {
+                                        Self::restart_loop(decoder.as_mut(), region, &mut decode_position, &mut trimmer, &mut silence, &mut loop_region, &current_position);
                                         *current_decoder.lock().unwrap() = taken_decoder;
+                                        continue;
                                     }
-                                    Ok(None) => {
-                                        // End of current file - check for preloaded next track and transition
-                                        if next_decoder.lock().unwrap().is_some() {
-                                            // Seamlessly transition to next track
-                                            if let Some(next_dec) = next_decoder.lock().unwrap().take() {
-                                                *current_decoder.lock().unwrap() = Some(next_dec);
-                                                current_file = next_file.take();
-                                                decode_position = Duration::from_secs(0);
-                                                is_transitioning = true;
-
-                                                let _ = response_sender.send(DecoderResponse::TrackTransitioned);
-
-                                                // Continue decoding from the new track immediately
-                                                continue;
-                                            }
+
+                                    // End of current file - check for preloaded next track and transition
+                                    if next_decoder.lock().unwrap().is_some() {
+                                        // Seamlessly transition to next track
+                                        if let Some(next_dec) = next_decoder.lock().unwrap().take() {
+                                            source_format = Some(Self::source_format(next_dec.as_ref()));
+                                            trimmer = GaplessTrimmer::for_decoder(next_dec.as_ref());
+                                            silence = SilenceSkipper::for_decoder(next_dec.as_ref());
+                                            *current_decoder.lock().unwrap() = Some(next_dec);
+                                            current_file = next_file.take();
+                                            decode_position = Duration::from_secs(0);
+                                            is_transitioning = true;
+
+                                            let _ = response_sender.send(DecoderResponse::TrackTransitioned);
+
+                                            // Continue decoding from the new track immediately
+                                            continue;
                                         }
+                                    }
 
-                                        // No preloaded track available; try provider for next track
-                                        if let Some(provider) = &next_track_provider {
-                                            if let Some(path) = provider.request_next() {
-                                                match Self::load_audio_file(&path).await {
-                                                    Ok(decoder) => {
-                                                        let duration = decoder.duration();
-                                                        let sample_rate = decoder.sample_rate();
-                                                        let bit_depth = decoder.bit_depth();
-                                                        let channels = decoder.channels();
-
-                                                        // Switch to the provided next track immediately
-                                                        *current_decoder.lock().unwrap() = Some(decoder);
-                                                        current_file = Some(path);
-                                                        decode_position = Duration::from_secs(0);
-                                                        is_transitioning = false;
-
-                                                        let _ = response_sender.send(DecoderResponse::FileLoaded {
-                                                            duration,
-                                                            sample_rate,
-                                                            bit_depth,
-                                                            channels,
-                                                        });
-
-                                                        // Continue decoding from the new track immediately
-                                                        continue;
-                                                    }
-                                                    Err(_) => {
-                                                        // Fall through to EndOfFile if provider failed
-                                                    }
-                                                    // Put back the decoder if still held
+                                    // No preloaded track available; try provider for next track
+                                    if let Some(provider) = &next_track_provider {
+                                        if let Some(path) = provider.request_next() {
+                                            match Self::open_decoder(&path) {
+                                                Ok(decoder) => {
+                                                    Self::log_decoder_opened(&path, decoder.as_ref());
+                                                    let duration = decoder.duration();
+                                                    let sample_rate = decoder.sample_rate();
+                                                    let bit_depth = decoder.bit_depth();
+                                                    let channels = decoder.channels();
+
+                                                    // Switch to the provided next track immediately
+                                                    source_format = Some(Self::source_format(decoder.as_ref()));
+                                                    trimmer = GaplessTrimmer::for_decoder(decoder.as_ref());
+                                                    silence = SilenceSkipper::for_decoder(decoder.as_ref());
+                                                    *current_decoder.lock().unwrap() = Some(decoder);
+                                                    current_file = Some(path.clone());
+                                                    decode_position = Duration::from_secs(0);
+                                                    is_transitioning = false;
+
+                                                    let _ = response_sender.send(DecoderResponse::FileLoaded {
+                                                        path,
+                                                        duration,
+                                                        sample_rate,
+                                                        bit_depth,
+                                                        channels,
+                                                    });
+
+                                                    // Continue decoding from the new track immediately
+                                                    continue;
+                                                }
+                                                Err(error) => {
+                                                    // The finished track is let go too; the controller picks what plays next
+                                                    underrun_monitor.set_source_active(false);
+                                                    let _ = response_sender.send(DecoderResponse::TrackFailed { path, error });
+                                                    continue;
                                                 }
                                             }
                                         }
-                                        // Put back the decoder if still held
-                                        if taken_decoder.is_some() {
-                                            *current_decoder.lock().unwrap() = taken_decoder;
-                                        }
+                                    }
+                                    // Put back the decoder if still held
+                                    if taken_decoder.is_some() {
+                                        *current_decoder.lock().unwrap() = taken_decoder;
+                                    }
 
-                                        // Still no track to play; signal end of file
-                                        let _ = response_sender.send(DecoderResponse::EndOfFile);
+                                    // Still no track to play; the output draining the buffer is not an underrun
+                                    underrun_monitor.set_source_active(false);
+                                    // A track shorter than the pre-buffer starts with what there is
+                                    prebuffer.finish_source();
+                                    let _ = response_sender.send(DecoderResponse::EndOfFile);
+                                }
+                                Err(e) => {
+                                    let path = current_file.clone().unwrap_or_default();
+                                    log::debug!(
+                                        path:% = path.display(), sample_rate = decoder.sample_rate(), bit_depth = decoder.bit_depth();
+                                        "Decode error in '{}': {}", path.display(), e
+                                    );
+                                    let error = AudioError::StreamError(format!("Decode error: {}", e));
+                                    decode_errors += 1;
+                                    if decode_errors >= MAX_CONSECUTIVE_DECODE_ERRORS {
+                                        // Drop the decoder; what is already buffered still plays out
+                                        decode_errors = 0;
+                                        underrun_monitor.set_source_active(false);
+                                        let _ = response_sender.send(DecoderResponse::TrackFailed { path, error });
+                                        continue;
                                     }
-                                    Err(e) => {
-                                        let audio_error = AudioError::StreamError(format!("Decode error: {}", e));
-                                        let _ = response_sender.send(DecoderResponse::Error(audio_error));
-                                        // Put the decoder back after error so we can retry or handle further
-                                        if taken_decoder.is_some() {
-                                            *current_decoder.lock().unwrap() = taken_decoder;
-                                        }
+                                    let _ = response_sender.send(DecoderResponse::Error(error));
+                                    // Put the decoder back so the next packet is tried
+                                    if taken_decoder.is_some() {
+                                        *current_decoder.lock().unwrap() = taken_decoder;
                                     }
                                 }
                             }
                         }
                     }
                 }
+            }
 
-                // Send periodic status updates
-                let status = ThreadStatus {
-                    playback_state: PlaybackState::Stopped, // Will be overridden by audio thread
-                    position: decode_position,
-                    buffer_fill: buffer_manager.ring_buffer().fill_level(),
-                    current_file: current_file.clone(),
-                };
-                let _ = status_sender.send(status);
+            // Send periodic status updates
+            let ring_buffer = buffer_manager.ring_buffer();
+            let status = ThreadStatus {
+                origin: StatusOrigin::Decoder,
+                playback_state: PlaybackState::Stopped, // Will be overridden by audio thread
+                position: decode_position,
+                buffer_fill: ring_buffer.fill_level(),
+                current_file: current_file.clone(),
+                resampling: source_format.as_ref()
+                    .is_some_and(|format| format.sample_rate != ring_buffer.sample_rate()),
+                source_format: source_format.clone(),
+            };
+            let _ = status_sender.send(status);
+        }
+    }
+
+    /// Seek the decoder back to the loop start once B has been decoded; a failed seek ends the loop
+    fn restart_loop(
+        decoder: &mut dyn AudioDecoder,
+        region: LoopRegion,
+        decode_position: &mut Duration,
+        trimmer: &mut GaplessTrimmer,
+        silence: &mut SilenceSkipper,
+        loop_region: &mut Option<LoopRegion>,
+        position: &PositionTracker,
+    ) {
+        match decoder.seek(region.start) {
+            Ok(()) => {
+                *decode_position = region.start;
+                trimmer.seek(region.start);
+                silence.seek(region.start);
             }
-        });
+            Err(e) => {
+                eprintln!("Loop seek error: {}", e);
+                *loop_region = None;
+                position.set_loop(None);
+            }
+        }
+    }
 
-        self.decoder_thread_handle = Some(decoder_thread);
-        Ok(())
+    /// Note a newly opened file in the diagnostic log, with its format as structured fields
+    fn log_decoder_opened(path: &std::path::Path, decoder: &dyn AudioDecoder) {
+        let (sample_rate, bit_depth, channels) = (decoder.sample_rate(), decoder.bit_depth(), decoder.channels());
+        log::debug!(
+            path:% = path.display(), sample_rate = sample_rate, bit_depth = bit_depth, channels = channels;
+            "Decoding '{}': {} Hz, {}-bit, {} channels", path.display(), sample_rate, bit_depth, channels
+        );
+    }
+
+    /// Format of a decoder's source stream
+    fn source_format(decoder: &dyn AudioDecoder) -> AudioFormat {
+        AudioFormat::new(decoder.sample_rate(), decoder.bit_depth(), decoder.channels(), decoder.codec())
     }
 
     /// Load an audio file and create a decoder (async)
-    async fn load_audio_file(path: &std::path::Path) -> Result<Box<dyn AudioDecoder>, AudioError> {
+    /// Open the decoder for `path`, picked by extension or, failing that, by the file's first bytes
+    pub fn open_decoder(path: &std::path::Path) -> Result<Box<dyn AudioDecoder>, AudioError> {
+        use crate::audio::decoders::flac::FlacDecoder;
+        use crate::audio::decoders::wav::WavDecoder;
+        use crate::audio::decoders::mp3::Mp3Decoder;
+        use crate::audio::decoders::ogg::OggDecoder;
+        use crate::audio::decoders::m4a::M4aDecoder;
+        use crate::audio::decoders::wavpack::WavPackDecoder;
+
+        if let Some(url) = crate::audio::source::stream_url(path) {
+            return Self::open_stream(url);
+        }
+
+        // Detect file format based on extension, or on the file's contents when that is missing or unknown
+        let extension = crate::audio::format_detection::decoder_extension(path)
+            .ok_or_else(|| AudioError::UnsupportedFormat {
+                format: "No file extension".to_string(),
+            })?;
+
+        // Create appropriate decoder based on file extension
+        match extension.as_str() {
+            "flac" => {
+                let decoder = FlacDecoder::new(path)
+                    .map_err(|e| AudioError::InitializationFailed(format!("FLAC decoder error: {}", e)))?;
+                Ok(Box::new(decoder))
+            }
+            "wav" | "wave" => {
+                let decoder = WavDecoder::new(path)
+                    .map_err(|e| AudioError::InitializationFailed(format!("WAV decoder error: {}", e)))?;
+                Ok(Box::new(decoder))
+            }
+            "mp3" => {
+                let decoder = Mp3Decoder::new(path)
+                    .map_err(|e| AudioError::InitializationFailed(format!("MP3 decoder error: {}", e)))?;
+                Ok(Box::new(decoder))
+            }
+            "ogg" | "oga" => {
+                let decoder = OggDecoder::new(path)
+                    .map_err(|e| AudioError::InitializationFailed(format!("OGG decoder error: {}", e)))?;
+                Ok(Box::new(decoder))
+            }
+            "m4a" | "mp4" | "m4b" => {
+                let decoder = M4aDecoder::new(path)
+                    .map_err(|e| AudioError::InitializationFailed(format!("M4A/MP4 decoder error: {}", e)))?;
+                Ok(Box::new(decoder))
+            }
+            "wv" => {
+                let decoder = WavPackDecoder::new(path)
+                    .map_err(|e| AudioError::InitializationFailed(format!("WavPack decoder error: {}", e)))?;
+                Ok(Box::new(decoder))
+            }
+            _ => {
+                Err(AudioError::UnsupportedFormat {
+                    format: format!("Unsupported file extension: {}", extension),
+                })
+            }
+        }
+    }
+
+    /// Connect to an HTTP(S) stream and open the decoder its URL or content type calls for
+    fn open_stream(url: &str) -> Result<Box<dyn AudioDecoder>, AudioError> {
+        use crate::audio::source::{self, HttpSource, StreamOptions};
+
+        let stream = HttpSource::open(url, StreamOptions::default())
+            .map_err(|e| AudioError::StreamError(e.to_string()))?;
+        let extension = source::stream_extension(url, stream.content_type())
+            .ok_or_else(|| AudioError::UnsupportedFormat {
+                format: format!("Unknown stream type: {}", stream.content_type().unwrap_or("no content type")),
+            })?;
+        Self::open_source(Box::new(stream), &extension)
+    }
+
+    /// Open the decoder for `extension` over a source other than a local file
+    pub fn open_source(source: Box<dyn MediaSource>, extension: &str) -> Result<Box<dyn AudioDecoder>, AudioError> {
         use crate::audio::decoders::flac::FlacDecoder;
         use crate::audio::decoders::wav::WavDecoder;
         use crate::audio::decoders::mp3::Mp3Decoder;
         use crate::audio::decoders::ogg::OggDecoder;
         use crate::audio::decoders::m4a::M4aDecoder;
 
-        // Detect file format based on extension
-        let extension = path.extension()
-            .and_then(|ext| ext.to_str())
-            .map(|s| s.to_lowercase())
-            .ok_or_else(|| AudioError::UnsupportedFormat {
-                format: "No file extension".to_string(),
-            })?;
-
-        // Create appropriate decoder based on file extension
-        match extension.as_str() {
+        let hint = Some(extension);
+        match extension {
             "flac" => {
-                let decoder = FlacDecoder::new(path)
+                let decoder = FlacDecoder::from_source(source, hint)
                     .map_err(|e| AudioError::InitializationFailed(format!("FLAC decoder error: {}", e)))?;
                 Ok(Box::new(decoder))
             }
-            "wav" => {
-                let decoder = WavDecoder::new(path)
+            "wav" | "wave" => {
+                let decoder = WavDecoder::from_source(source, hint)
                     .map_err(|e| AudioError::InitializationFailed(format!("WAV decoder error: {}", e)))?;
                 Ok(Box::new(decoder))
             }
             "mp3" => {
-                let decoder = Mp3Decoder::new(path)
+                let decoder = Mp3Decoder::from_source(source, hint)
                     .map_err(|e| AudioError::InitializationFailed(format!("MP3 decoder error: {}", e)))?;
                 Ok(Box::new(decoder))
             }
             "ogg" | "oga" => {
-                let decoder = OggDecoder::new(path)
+                let decoder = OggDecoder::from_source(source, hint)
                     .map_err(|e| AudioError::InitializationFailed(format!("OGG decoder error: {}", e)))?;
                 Ok(Box::new(decoder))
             }
-            "m4a" | "mp4" | "m4b" => {
-                let decoder = M4aDecoder::new(path)
+            "m4a" | "mp4" | "m4b" | "aac" => {
+                let decoder = M4aDecoder::from_source(source, hint)
                     .map_err(|e| AudioError::InitializationFailed(format!("M4A/MP4 decoder error: {}", e)))?;
                 Ok(Box::new(decoder))
             }
             _ => {
                 Err(AudioError::UnsupportedFormat {
-                    format: format!("Unsupported file extension: {}", extension),
+                    format: format!("Unsupported stream format: {}", extension),
                 })
             }
         }
@@ -809,6 +1758,8 @@ impl AudioEngineImpl {
 
     /// Initialize audio stream with the current device and configuration
     fn initialize_stream(&mut self) -> Result<(), AudioError> {
+        self.acquire_exclusive_if_requested();
+
         let device = self.device_manager.current_device()
             .ok_or_else(|| AudioError::InitializationFailed("No device selected".to_string()))?;
 
@@ -816,7 +1767,7 @@ impl AudioEngineImpl {
         let config = StreamConfig {
             channels: self.channels,
             sample_rate: SampleRate(self.sample_rate),
-            buffer_size: cpal::BufferSize::Fixed(4096),
+            buffer_size: self.stream_buffer_size(),
         };
 
         // Create shared state for the audio callback
@@ -852,7 +1803,7 @@ impl AudioEngineImpl {
         &self,
         device: &cpal::Device,
         config: &StreamConfig,
-        playback_state: Arc<Mutex<PlaybackState>>,
+        playback_state: Arc<AtomicPlaybackState>,
         volume: Arc<AtomicU32>,
         is_running: Arc<AtomicBool>,
         command_receiver: Receiver<AudioCommand>,
@@ -864,9 +1815,11 @@ impl AudioEngineImpl {
         // Get references to the ring buffer and position tracker
         let ring_buffer = Arc::clone(&self.buffer_manager.ring_buffer());
         let current_position = Arc::clone(&self.current_position);
+        let monitors = self.output_monitors();
         let mut sample_clock = 0f32;
-        let sample_rate = config.sample_rate.0 as f32;
         let channels = config.channels as usize;
+        let mut audio_data = callback_scratch(config);
+        let mut chain = monitors.chain.chain(config.sample_rate.0, channels, T::FORMAT);
 
         let stream = device.build_output_stream(
             config,
@@ -875,22 +1828,18 @@ impl AudioEngineImpl {
                 while let Ok(command) = command_receiver.try_recv() {
                     match command {
                         AudioCommand::Play => {
-                            *playback_state.lock().unwrap() = PlaybackState::Playing;
+                            playback_state.store(PlaybackState::Playing);
                         }
                         AudioCommand::Pause => {
-                            *playback_state.lock().unwrap() = PlaybackState::Paused;
+                            playback_state.store(PlaybackState::Paused);
                         }
                         AudioCommand::Stop => {
-                            *playback_state.lock().unwrap() = PlaybackState::Stopped;
+                            playback_state.store(PlaybackState::Stopped);
                             sample_clock = 0.0;
                         }
                         AudioCommand::SetVolume(_vol) => {
                             // Volume is handled via the atomic variable
                         }
-                        AudioCommand::Seek(position) => {
-                            // Reset sample clock for test tone generation based on seek position
-                            sample_clock = (position.as_secs_f32() * sample_rate) % sample_rate;
-                        }
                         AudioCommand::Shutdown => {
                             is_running.store(false, Ordering::Relaxed);
                             return;
@@ -898,46 +1847,46 @@ impl AudioEngineImpl {
                     }
                 }
 
-                let state = playback_state.lock().unwrap().clone();
+                let state = playback_state.load();
                 let current_volume = f32::from_bits(volume.load(Ordering::Relaxed));
 
                 match state {
                     PlaybackState::Playing => {
-                        // Read audio data from ring buffer
+                        // Let the decoder refill while an underrun hold or the pre-buffer is active
                         let frames_needed = data.len() / channels;
-                        let mut audio_data = vec![0.0f32; data.len()];
+                        if monitors.prebuffer.hold(ring_buffer.available_read_frames() as u64)
+                            || monitors.underruns.consume_hold(frames_needed as u64)
+                        {
+                            for sample in data.iter_mut() {
+                                *sample = cpal::Sample::from_sample(0.0f32);
+                            }
+                            return;
+                        }
+
+                        // Read audio data from ring buffer; short reads are counted, never printed
+                        audio_data.resize(data.len(), 0.0);
                         let samples_read = ring_buffer.read(&mut audio_data);
-                        let frames_read = samples_read / channels;
-                        if frames_read < frames_needed {
-                            eprintln!(
-                                "Audio underrun: needed {} frames, got {} frames; fill={:.0}% (~{} ms)",
-                                frames_needed,
-                                frames_read,
-                                ring_buffer.fill_level() * 100.0,
-                                ring_buffer.buffered_duration().as_millis()
-                            );
+                        if samples_read < data.len() {
+                            monitors.underruns.record_short_read();
                         }
 
-                        // Apply volume and convert to output format
+                        chain.process(&mut audio_data[..samples_read], current_volume);
+
+                        // Convert to output format
                         for (i, sample) in data.iter_mut().enumerate() {
                             let audio_sample = if i < samples_read {
-                                audio_data[i] * current_volume
+                                audio_data[i]
                             } else {
                                 0.0 // Silence if not enough data
                             };
                             *sample = cpal::Sample::from_sample(audio_sample);
                         }
 
-                        // Update position based on samples consumed
-                        if samples_read > 0 {
-                            let frames_consumed = samples_read / channels;
-                            let time_consumed = Duration::from_secs_f64(frames_consumed as f64 / sample_rate as f64);
-                            if let Ok(mut pos) = current_position.lock() {
-                                *pos += time_consumed;
-                            }
-                        }
+                        // Count frames consumed; the reader converts them to a position
+                        current_position.advance((samples_read / channels) as u64);
                     }
                     PlaybackState::Paused | PlaybackState::Stopped => {
+                        chain.reset_fades();
                         // Output silence
                         for sample in data.iter_mut() {
                             *sample = cpal::Sample::from_sample(0.0f32);
@@ -955,10 +1904,37 @@ impl AudioEngineImpl {
         Ok(stream)
     }
 
+    /// Channels to open the output with for a track: the track's own, up to what the device has
+    ///
+    /// A 5.1 track plays on all six speakers of a surround device and is folded down to
+    /// stereo on headphones; the decoder routes between the two layouts.
+    fn output_channels_for(&self, track_channels: u16) -> u16 {
+        let max_channels = self.device_manager.current_device_name().ok().flatten()
+            .and_then(|name| self.device_manager.get_capabilities(&name).map(|caps| caps.max_channels));
+        max_channels.map_or(track_channels, |max| track_channels.min(max).max(1))
+    }
+
+    /// Rate to open the output at for a track: its own, or that times the oversampling factor
+    /// when the device supports the multiplied rate
+    fn output_rate_for(&self, track_rate: u32) -> u32 {
+        let Some(factor) = *self.oversampling.lock().unwrap() else {
+            return track_rate;
+        };
+        let oversampled = track_rate * factor;
+        let supported = self.device_manager.current_device_name().ok().flatten()
+            .and_then(|name| self.device_manager.get_capabilities(&name))
+            .is_some_and(|caps| caps.supported_sample_rates.contains(&oversampled));
+        if !supported {
+            log::info!("Not oversampling {} Hz {}x: the device does not support {} Hz", track_rate, factor, oversampled);
+            return track_rate;
+        }
+        oversampled
+    }
+
     /// Update the audio configuration for a new sample rate and bit depth
     pub fn update_config(&mut self, sample_rate: u32, bit_depth: u16, channels: u16) -> Result<(), AudioError> {
         // Remember whether we were playing to resume after reconfiguration.
-        let was_playing = matches!(*self.playback_state.lock().unwrap(), PlaybackState::Playing);
+        let was_playing = matches!(self.playback_state.load(), PlaybackState::Playing);
 
         // Stop audio/decoder threads if running so we can rebuild the stream with the new config.
         if self.is_running.load(Ordering::Relaxed) {
@@ -969,16 +1945,11 @@ impl AudioEngineImpl {
         self.sample_rate = sample_rate;
         self.bit_depth = bit_depth;
         self.channels = channels;
+        self.current_position.set_sample_rate(sample_rate);
 
         // Rebuild buffer manager to match new stream configuration.
-        let buffer_frames = (sample_rate as f64 * 1.0) as usize; // ~1000ms
-        self.buffer_manager = Arc::new(BufferManager::new(
-            buffer_frames,
-            channels,
-            sample_rate,
-            300, // target buffer ms
-            150, // min buffer ms
-        ));
+        self.buffer_manager = Arc::new(self.buffer_settings.buffer_manager(sample_rate, channels));
+        self.publish_buffer_settings();
 
         // Restart threads (audio/decoder) with the new configuration.
         self.initialize_threads()?;
@@ -1042,10 +2013,69 @@ impl AudioEngineImpl {
             self.initialize_threads()?;
         }
 
+        self.pending_start = None;
+        self.current_track_path = Some(path.clone());
+        self.track_start = Duration::ZERO;
+        self.arm_prebuffer();
         self.send_decoder_command(DecoderCommand::LoadFile(path))?;
         Ok(())
     }
 
+    /// Load an audio file and start decoding at `start` (e.g. a CUE sheet track inside an album file)
+    pub fn load_file_at(&mut self, path: std::path::PathBuf, start: Duration) -> Result<(), AudioError> {
+        self.load_file(path)?;
+        self.track_start = start;
+        if !start.is_zero() {
+            self.send_decoder_command(DecoderCommand::Seek(start))?;
+            self.current_position.set(start);
+        }
+        Ok(())
+    }
+
+    /// Start playback of `path` with the decoder positioned at `start` within its file
+    ///
+    /// A position sought while stopped takes the place of `start` if it lies within the track.
+    /// `stop` rewinds to `start` and keeps the track loaded.
+    pub fn start_playback_at(&mut self, path: std::path::PathBuf, mut decoder: Box<dyn AudioDecoder>, start: Duration) -> Result<(), AudioError> {
+        let position = self.pending_start.take()
+            .filter(|pending| *pending >= start && decoder.duration().is_none_or(|duration| *pending <= duration))
+            .unwrap_or(start);
+        if !position.is_zero() {
+            decoder.seek(position)
+                .map_err(|e| AudioError::StreamError(format!("Seek to track start failed: {}", e)))?;
+        }
+        self.start_playback(decoder)?;
+        self.current_track_path = Some(path);
+        self.track_start = start;
+        self.current_position.set(position);
+        Ok(())
+    }
+
+    /// Path of the loaded track, kept across `stop` until `unload`
+    pub fn current_track_path(&self) -> Option<std::path::PathBuf> {
+        self.current_track_path.clone()
+    }
+
+    /// Stop playback and let go of the current and preloaded tracks
+    pub fn unload(&mut self) -> Result<(), AudioError> {
+        self.halt_output();
+        self.current_track_path = None;
+        self.preloaded_track_path = None;
+        self.track_start = Duration::ZERO;
+        self.current_position.set(Duration::ZERO);
+        self.send_decoder_command(DecoderCommand::Unload)
+    }
+
+    /// Fade out and stop the output thread; the decoder is left to the caller
+    fn halt_output(&mut self) {
+        self.fade_out_before_stop();
+        self.pending_start = None;
+        self.prebuffer.release();
+        self.prebuffer_armed_at = None;
+        self.playback_state.store(PlaybackState::Stopped);
+        let _ = self.send_audio_command(AudioCommand::Stop);
+    }
+
     /// Preload the next track for gapless playback
     pub fn preload_next_track(&mut self, path: std::path::PathBuf) -> Result<(), AudioError> {
         // Initialize threads if not already running
@@ -1053,10 +2083,17 @@ impl AudioEngineImpl {
             self.initialize_threads()?;
         }
 
+        self.preloaded_track_path = Some(path.clone());
         self.send_decoder_command(DecoderCommand::PreloadNext(path))?;
         Ok(())
     }
 
+    /// Forget the preloaded next track so the end of the current one asks the queue again
+    pub fn discard_preloaded_track(&mut self) -> Result<(), AudioError> {
+        self.preloaded_track_path = None;
+        self.send_decoder_command(DecoderCommand::DiscardPreload)
+    }
+
     /// Transition to the next preloaded track
     pub fn transition_to_next_track(&mut self) -> Result<(), AudioError> {
         self.send_decoder_command(DecoderCommand::NextTrack)?;
@@ -1073,17 +2110,37 @@ impl AudioEngineImpl {
         self.gapless_enabled.store(enabled, Ordering::Relaxed);
     }
 
+    /// Rebuild the stream at each gapless track's own rate, taking a gap, instead of resampling
+    pub fn set_prefer_native_rate_switch(&mut self, enabled: bool) {
+        self.prefer_native_rate_switch = enabled;
+    }
+
+    /// Whether a gapless transition to another format rebuilds the stream
+    ///
+    /// Exclusive access is asked for to get bit-perfect output, which resampling would defeat.
+    fn native_rate_switch_required(&self) -> bool {
+        self.prefer_native_rate_switch || self.is_exclusive_active()
+    }
+
     /// Check if gapless playback is enabled
     pub fn is_gapless_enabled(&self) -> bool {
         self.gapless_enabled.load(Ordering::Relaxed)
     }
 
     /// Seek to a specific position in the current track
+    ///
+    /// The playback state is left as it is: a paused track refills the buffer from the new
+    /// position and stays paused. While stopped the position is kept for the next
+    /// `start_playback_at`. If the decoder fails to seek, the position is rolled back and
+    /// `DecoderResponse::SeekFailed` sent.
     pub fn seek(&mut self, position: Duration) -> Result<(), AudioError> {
         // Validate position against current track duration if available
         if let Some(decoder) = self.current_decoder.lock().unwrap().as_ref() {
-            let duration = decoder.duration();
-            if position > duration {
+            if !decoder.supports_seek() {
+                return Err(AudioError::SeekUnsupported);
+            }
+            // A live stream has no length to check against
+            if let Some(duration) = decoder.duration().filter(|duration| position > *duration) {
                 return Err(AudioError::InvalidSeekPosition {
                     position: position.as_secs_f64(),
                     duration: duration.as_secs_f64(),
@@ -1091,26 +2148,61 @@ impl AudioEngineImpl {
             }
         }
 
-        // Send seek commands to both threads
-        self.send_audio_command(AudioCommand::Seek(position))?;
-        self.send_decoder_command(DecoderCommand::Seek(position))?;
+        if self.playback_state.load() == PlaybackState::Stopped {
+            self.pending_start = Some(position);
+            self.current_position.set(position);
+            return Ok(());
+        }
+
+        // Seeking out of an A-B loop ends it; seeks inside keep looping
+        if self.loop_region().is_some_and(|region| !region.contains(position)) {
+            self.clear_loop()?;
+        }
 
-        // Update position tracker immediately for responsive UI
-        *self.current_position.lock().unwrap() = position;
+        // Update position tracker immediately for responsive UI; the decoder thread
+        // puts it back if the seek fails
+        self.current_position.set(position);
+        self.send_decoder_command(DecoderCommand::Seek(position))?;
 
         Ok(())
     }
 
+    /// Loop playback between two positions in the current file
+    ///
+    /// If B has already been decoded, buffered audio is dropped and playback restarts at A.
+    pub fn set_loop(&mut self, start: Duration, end: Duration) -> Result<LoopRegion, AudioError> {
+        let region = LoopRegion::new(start, end).ok_or(AudioError::InvalidLoopRegion {
+            start: start.as_secs_f64(),
+            end: end.as_secs_f64(),
+        })?;
+        self.validate_seek_position(end)?;
+
+        self.current_position.set_loop(Some(region));
+        self.send_decoder_command(DecoderCommand::SetLoop(Some(region)))?;
+        Ok(region)
+    }
+
+    /// Stop looping; playback carries on from the current position
+    pub fn clear_loop(&mut self) -> Result<(), AudioError> {
+        self.current_position.set_loop(None);
+        self.send_decoder_command(DecoderCommand::SetLoop(None))
+    }
+
+    /// Active A-B loop (file positions)
+    pub fn loop_region(&self) -> Option<LoopRegion> {
+        self.current_position.loop_region()
+    }
+
     /// Get the current playback position
     pub fn current_position(&self) -> Duration {
-        self.current_position.lock().unwrap().clone()
+        self.current_position.position()
     }
 
     /// Get the duration of the current track
     pub fn current_duration(&self) -> Option<Duration> {
         self.current_decoder.lock().unwrap()
             .as_ref()
-            .map(|decoder| decoder.duration())
+            .and_then(|decoder| decoder.duration())
     }
 
     /// Validate seek position against track bounds
@@ -1129,18 +2221,14 @@ impl AudioEngineImpl {
         }
     }
 
-    /// Get the current thread status
-    pub fn get_status(&mut self) -> Option<ThreadStatus> {
+    /// Drain pending thread status updates and return the merged snapshot
+    pub fn get_status(&mut self) -> &ThreadStatus {
         if let Some(receiver) = &mut self.status_receiver {
-            // Get the most recent status update
-            let mut latest_status = None;
             while let Ok(status) = receiver.try_recv() {
-                latest_status = Some(status);
+                self.thread_status.merge(status);
             }
-            latest_status
-        } else {
-            None
         }
+        &self.thread_status
     }
 
     /// Get a reference to the device manager
@@ -1158,32 +2246,55 @@ impl AudioEngineImpl {
         if let Some(receiver) = &mut self.decoder_response_receiver {
             if let Ok(resp) = receiver.try_recv() {
                 match &resp {
-                    DecoderResponse::FileLoaded { sample_rate, bit_depth, channels, .. } => {
-                        if *sample_rate != self.sample_rate
+                    DecoderResponse::FileLoaded { path, sample_rate, bit_depth, channels, .. } => {
+                        self.clip_monitor.reset();
+                        // Tracks the decoder asked the queue for were never loaded through the engine
+                        if self.current_track_path.as_ref() != Some(path) {
+                            self.current_track_path = Some(path.clone());
+                            self.track_start = Duration::ZERO;
+                        }
+                        let channels = self.output_channels_for(*channels);
+                        let sample_rate = self.output_rate_for(*sample_rate);
+                        if sample_rate != self.sample_rate
                             || *bit_depth != self.bit_depth
-                            || *channels != self.channels
+                            || channels != self.channels
                         {
                             // Attempt to reconfigure stream to match the track
 
-                            let _ = self.update_config(*sample_rate, *bit_depth, *channels);
+                            let _ = self.update_config(sample_rate, *bit_depth, channels);
                         }
                     }
                     DecoderResponse::TrackTransitioned => {
-                        // Extract needed config while holding the lock, then drop it before reconfiguring.
-                        let mut reconfig: Option<(u32, u16, u16)> = None;
-                        {
-                            if let Some(decoder) = self.current_decoder.lock().unwrap().as_ref() {
-                                let sr = decoder.sample_rate();
-                                let bd = decoder.bit_depth();
-                                let ch = decoder.channels();
-                                if sr != self.sample_rate || bd != self.bit_depth || ch != self.channels {
-                                    reconfig = Some((sr, bd, ch));
+                        self.clip_monitor.reset();
+                        self.current_track_path = self.preloaded_track_path.take();
+                        self.track_start = Duration::ZERO;
+
+                        // Rebuilding the stream drops the previous track's buffered tail and takes
+                        // hundreds of milliseconds, so a gapless join normally keeps the stream and
+                        // the decoder resamples the new track into it.
+                        if self.native_rate_switch_required() {
+                            // Extract needed config while holding the lock, then drop it before reconfiguring.
+                            let mut reconfig: Option<(u32, u16, u16)> = None;
+                            {
+                                if let Some(decoder) = self.current_decoder.lock().unwrap().as_ref() {
+                                    let sr = self.output_rate_for(decoder.sample_rate());
+                                    let bd = decoder.bit_depth();
+                                    let ch = self.output_channels_for(decoder.channels());
+                                    if sr != self.sample_rate || bd != self.bit_depth || ch != self.channels {
+                                        reconfig = Some((sr, bd, ch));
+                                    }
                                 }
                             }
+                            if let Some((sr, bd, ch)) = reconfig {
+                                // Reconfigure after the transition, taking the gap
+                                let _ = self.update_config(sr, bd, ch);
+                            }
                         }
-                        if let Some((sr, bd, ch)) = reconfig {
-                            // Reconfigure after seamless transition to next track
-                            let _ = self.update_config(sr, bd, ch);
+                    }
+                    DecoderResponse::TrackFailed { error, .. } => {
+                        self.current_track_path = None;
+                        if matches!(error, AudioError::StreamError(msg) if msg == DECODE_TIMEOUT_MESSAGE) {
+                            self.replace_stuck_decoder_thread();
                         }
                     }
                     _ => {}
@@ -1216,10 +2327,18 @@ impl AudioEngineImpl {
         let memory_stats = self.buffer_allocator.memory_stats();
         self.performance_profiler.update_memory_usage(memory_stats.current_usage as u64);
 
-        // Check buffer status
-        let buffer_status = self.buffer_manager.buffer_status();
-        if buffer_status.is_underrun {
-            self.performance_profiler.record_buffer_underrun();
+        // Publish underruns counted by the output callback
+        self.performance_profiler.update_buffer_underruns(self.underrun_monitor.underrun_count() as usize);
+
+        // Publish clipping statistics for the current track
+        self.performance_profiler.update_clipped_samples(self.clip_monitor.clipped_samples());
+
+        // Publish decoder threads left behind by the decode watchdog
+        self.performance_profiler.update_abandoned_decoder_threads(self.abandoned_decoder_threads);
+
+        // Sample the ring buffer fill while audio is flowing
+        if self.playback_state.load() == PlaybackState::Playing {
+            self.performance_profiler.record_buffer_fill_level(self.buffer_manager.ring_buffer().fill_level() * 100.0);
         }
     }
 
@@ -1228,6 +2347,126 @@ impl AudioEngineImpl {
         self.performance_profiler.performance_report()
     }
 
+    /// Enable or disable the output soft limiter
+    pub fn set_limiter_enabled(&self, enabled: bool) {
+        self.clip_monitor.set_limiter_enabled(enabled);
+    }
+
+    /// Check if the output soft limiter is enabled
+    pub fn is_limiter_enabled(&self) -> bool {
+        self.clip_monitor.is_limiter_enabled()
+    }
+
+    /// Replace the equalizer bands; the output callback picks them up on its next block
+    pub fn set_eq_bands(&self, bands: Vec<EqBand>) {
+        self.eq.set_bands(bands);
+    }
+
+    /// Current equalizer bands (empty when flat)
+    pub fn eq_bands(&self) -> Vec<EqBand> {
+        self.eq.bands()
+    }
+
+    /// Set how long `stop` fades out and playback fades in (zero = off)
+    pub fn set_fade_durations(&self, fade_out: Duration, fade_in: Duration) {
+        self.fades.set_durations(fade_out, fade_in);
+    }
+
+    /// Change mono/swap and balance; the output callback picks them up on its next block
+    pub fn set_output_channels(&self, settings: OutputChannels) {
+        self.channel_control.set(settings);
+    }
+
+    /// Current mono/swap and balance settings
+    pub fn output_channels(&self) -> OutputChannels {
+        self.channel_control.get()
+    }
+
+    /// Turn headphone crossfeed on or off and set its level; heard on the next output block
+    pub fn set_crossfeed(&self, settings: CrossfeedSettings) {
+        self.crossfeed.set(settings);
+    }
+
+    /// Current crossfeed settings
+    pub fn crossfeed(&self) -> CrossfeedSettings {
+        self.crossfeed.get()
+    }
+
+    /// Dither output streams narrower than the audio; heard on the next output block
+    pub fn set_dither(&self, enabled: bool) {
+        self.dither.set_enabled(enabled);
+    }
+
+    pub fn is_dither_enabled(&self) -> bool {
+        self.dither.is_enabled()
+    }
+
+    /// Suspend the output stream once paused or stopped for `after` (zero = never)
+    pub fn set_suspend_output_after(&self, after: Duration) {
+        self.suspend_output_after_ms.store(after.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Change silence skipping; the decoder thread picks it up on its next buffer
+    pub fn set_silence_skipping(&self, settings: SilenceSettings) {
+        *self.silence_settings.lock().unwrap() = settings;
+    }
+
+    /// Set the output's speaker order, or None for the platform's usual one
+    pub fn set_channel_layout(&self, layout: Option<ChannelMap>) {
+        *self.channel_layout.lock().unwrap() = layout;
+    }
+
+    /// Upsample by a whole factor from the next track on, which opens the output at the multiplied rate
+    pub fn set_oversampling(&self, factor: Option<u32>) {
+        *self.oversampling.lock().unwrap() = factor;
+    }
+
+    /// Fade to silence before the stream is stopped, so stopping doesn't click
+    ///
+    /// Waits for the output callback to finish the fade, or a little longer than the fade
+    /// should take if the callback isn't running.
+    fn fade_out_before_stop(&self) {
+        let duration = self.fades.fade_out_duration();
+        if duration.is_zero() || !self.is_running.load(Ordering::Relaxed) || self.playback_state.load() != PlaybackState::Playing {
+            return;
+        }
+        self.fades.request_fade_out();
+        let deadline = Instant::now() + duration + Duration::from_millis(100);
+        while !self.fades.fade_out_finished() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Number of samples clipped on the current track
+    pub fn clipped_samples(&self) -> u64 {
+        self.clip_monitor.clipped_samples()
+    }
+
+    /// Return the clip count once, when clipping first occurs on the current track
+    pub fn take_first_clip(&self) -> Option<u64> {
+        self.clip_monitor.take_first_clip()
+    }
+
+    /// Get the tap feeding decoded audio to the spectrum analyzer
+    pub fn spectrum_tap(&self) -> Arc<SpectrumTap> {
+        Arc::clone(&self.spectrum_tap)
+    }
+
+    /// The newest `frames` frames the decoder wrote to the ring buffer, at the output rate
+    pub fn latest_decoded(&self, frames: usize) -> crate::models::AudioBuffer {
+        self.buffer_manager.ring_buffer().latest(frames)
+    }
+
+    /// Peak and RMS levels of the last 100 ms written to the ring buffer
+    pub fn output_levels(&self) -> crate::models::ChannelLevels {
+        self.buffer_manager.ring_buffer().level_meter().levels()
+    }
+
+    /// Whether the current decoder can seek; true while no file is loaded
+    pub fn supports_seek(&self) -> bool {
+        self.current_decoder.lock().unwrap().as_ref().is_none_or(|decoder| decoder.supports_seek())
+    }
+
     /// Check if audio performance is healthy
     pub fn is_performance_healthy(&self) -> bool {
         self.performance_profiler.is_performance_healthy()
@@ -1251,11 +2490,19 @@ impl AudioEngineImpl {
             let _ = handle.join();
         }
 
-        // Avoid calling block_on from within a runtime. Abort the decoder task instead.
+        // The decoder sees the shutdown within one loop iteration; a decode stuck past that
+        // is left to finish on its own rather than blocking the caller
         if let Some(handle) = self.decoder_thread_handle.take() {
-            handle.abort();
-            // Optionally, give it a brief moment to wind down without blocking this thread.
-            // We intentionally do not block_on here to prevent "Cannot start a runtime from within a runtime".
+            let deadline = Instant::now() + DECODER_SHUTDOWN_TIMEOUT;
+            while !handle.is_finished() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(5));
+            }
+            if handle.is_finished() {
+                let _ = handle.join();
+            } else {
+                log::warn!("Decoder thread did not stop within {:?}; detaching it", DECODER_SHUTDOWN_TIMEOUT);
+                self.abandoned_decoder_threads += 1;
+            }
         }
 
         // Clear communication channels
@@ -1263,6 +2510,8 @@ impl AudioEngineImpl {
         self.decoder_command_sender = None;
         self.status_receiver = None;
         self.decoder_response_receiver = None;
+        self.decoder_response_sender = None;
+        self.decoder_status_sender = None;
 
         Ok(())
     }
@@ -1277,26 +2526,50 @@ impl AudioEngine for AudioEngineImpl {
 
         // Store the decoder
         *self.current_decoder.lock().unwrap() = Some(decoder);
-
-        // Send play command to audio thread
+        self.pending_start = None;
+        self.clip_monitor.reset();
+        self.fades.request_fade_in();
+        self.arm_prebuffer();
+
+        // Send play command to audio thread; the state is stored here too so a seek
+        // straight after sees it
+        self.playback_state.store(PlaybackState::Playing);
         self.send_audio_command(AudioCommand::Play)?;
+        self.send_decoder_command(DecoderCommand::Resume)?;
         Ok(())
     }
 
     fn pause(&mut self) -> Result<(), AudioError> {
+        self.playback_state.store(PlaybackState::Paused);
         self.send_audio_command(AudioCommand::Pause)?;
         Ok(())
     }
 
     fn resume(&mut self) -> Result<(), AudioError> {
+        if self.playback_state.load() == PlaybackState::Stopped {
+            // The stopped track is still loaded; start it over, or from where it was sought to
+            if let Some(position) = self.pending_start.take() {
+                self.current_position.set(position);
+                self.send_decoder_command(DecoderCommand::Seek(position))?;
+            }
+            self.clip_monitor.reset();
+            self.fades.request_fade_in();
+            self.arm_prebuffer();
+            self.send_decoder_command(DecoderCommand::Resume)?;
+        }
+        self.playback_state.store(PlaybackState::Playing);
         self.send_audio_command(AudioCommand::Play)?;
         Ok(())
     }
 
+    /// Stop playback and rewind the current track, which stays loaded for the next play
     fn stop(&mut self) -> Result<(), AudioError> {
-        self.send_audio_command(AudioCommand::Stop)?;
-        self.send_decoder_command(DecoderCommand::Stop)?;
-        Ok(())
+        self.halt_output();
+        self.current_position.set(self.track_start);
+        self.send_decoder_command(DecoderCommand::Stop {
+            path: self.current_track_path.clone(),
+            start: self.track_start,
+        })
     }
 
     fn set_volume(&mut self, volume: f32) -> Result<(), AudioError> {
@@ -1308,12 +2581,15 @@ impl AudioEngine for AudioEngineImpl {
     }
 
     fn set_device(&mut self, device_name: &str) -> Result<(), AudioError> {
-        // Stop current playback
+        // Stop current playback; the restarted threads begin without a track
         if self.is_running.load(Ordering::Relaxed) {
-            self.stop()?;
+            self.unload()?;
             self.shutdown_threads()?;
         }
 
+        // Release exclusive access to the old device before switching
+        self.exclusive_guard = None;
+
         // Select new device
         self.device_manager.select_device(Some(device_name))?;
 
@@ -1335,9 +2611,264 @@ impl AudioEngine for AudioEngineImpl {
             _ => 32,
         };
 
-        self.update_config(sample_rate, bit_depth, channels)?;
+        self.update_config(sample_rate, bit_depth, channels)?;
+
+        Ok(())
+    }
+}
+
+// Forwards to the inherent methods, which stay callable without the trait in scope
+impl AudioEngineControl for AudioEngineImpl {
+    fn start_playback_at(&mut self, path: std::path::PathBuf, decoder: Box<dyn AudioDecoder>, start: Duration) -> Result<(), AudioError> {
+        AudioEngineImpl::start_playback_at(self, path, decoder, start)
+    }
+
+    fn unload(&mut self) -> Result<(), AudioError> {
+        AudioEngineImpl::unload(self)
+    }
+
+    fn current_track_path(&self) -> Option<std::path::PathBuf> {
+        AudioEngineImpl::current_track_path(self)
+    }
+
+    fn load_file_at(&mut self, path: std::path::PathBuf, start: Duration) -> Result<(), AudioError> {
+        AudioEngineImpl::load_file_at(self, path, start)
+    }
+
+    fn discard_preloaded_track(&mut self) -> Result<(), AudioError> {
+        AudioEngineImpl::discard_preloaded_track(self)
+    }
+
+    fn set_next_track_provider(&mut self, provider: Arc<dyn NextTrackProvider>) {
+        AudioEngineImpl::set_next_track_provider(self, provider)
+    }
+
+    fn get_decoder_response(&mut self) -> Option<DecoderResponse> {
+        AudioEngineImpl::get_decoder_response(self)
+    }
+
+    fn get_status(&mut self) -> &ThreadStatus {
+        AudioEngineImpl::get_status(self)
+    }
+
+    fn playback_state(&self) -> PlaybackState {
+        AudioEngineImpl::playback_state(self)
+    }
+
+    fn current_position(&self) -> Duration {
+        AudioEngineImpl::current_position(self)
+    }
+
+    fn current_duration(&self) -> Option<Duration> {
+        AudioEngineImpl::current_duration(self)
+    }
+
+    fn validate_seek_position(&self, position: Duration) -> Result<Duration, AudioError> {
+        AudioEngineImpl::validate_seek_position(self, position)
+    }
+
+    fn seek(&mut self, position: Duration) -> Result<(), AudioError> {
+        AudioEngineImpl::seek(self, position)
+    }
+
+    fn set_loop(&mut self, start: Duration, end: Duration) -> Result<LoopRegion, AudioError> {
+        AudioEngineImpl::set_loop(self, start, end)
+    }
+
+    fn clear_loop(&mut self) -> Result<(), AudioError> {
+        AudioEngineImpl::clear_loop(self)
+    }
+
+    fn loop_region(&self) -> Option<LoopRegion> {
+        AudioEngineImpl::loop_region(self)
+    }
+
+    fn volume(&self) -> f32 {
+        AudioEngineImpl::volume(self)
+    }
+
+    fn set_gapless_enabled(&mut self, enabled: bool) {
+        AudioEngineImpl::set_gapless_enabled(self, enabled)
+    }
+
+    fn set_prefer_native_rate_switch(&mut self, enabled: bool) {
+        AudioEngineImpl::set_prefer_native_rate_switch(self, enabled)
+    }
+
+    fn is_gapless_enabled(&self) -> bool {
+        AudioEngineImpl::is_gapless_enabled(self)
+    }
+
+    fn sample_rate(&self) -> u32 {
+        AudioEngineImpl::sample_rate(self)
+    }
+
+    fn bit_depth(&self) -> u16 {
+        AudioEngineImpl::bit_depth(self)
+    }
+
+    fn channels(&self) -> u16 {
+        AudioEngineImpl::channels(self)
+    }
+
+    fn buffer_settings(&self) -> BufferSettings {
+        AudioEngineImpl::buffer_settings(self)
+    }
+
+    fn set_output_buffer_frames(&mut self, frames: u32) -> Result<u32, AudioError> {
+        AudioEngineImpl::set_output_buffer_frames(self, frames)
+    }
+
+    fn effective_output_buffer_frames(&self) -> u32 {
+        AudioEngineImpl::effective_output_buffer_frames(self)
+    }
+
+    fn output_latency_ms(&self) -> f32 {
+        AudioEngineImpl::output_latency_ms(self)
+    }
+
+    fn check_underruns(&mut self) -> Option<BufferAdjustment> {
+        AudioEngineImpl::check_underruns(self)
+    }
+
+    fn underrun_count(&self) -> u64 {
+        AudioEngineImpl::underrun_count(self)
+    }
+
+    fn set_prebuffer(&self, prebuffer: Duration) {
+        AudioEngineImpl::set_prebuffer(self, prebuffer)
+    }
+
+    fn buffering_for(&self) -> Option<Duration> {
+        AudioEngineImpl::buffering_for(self)
+    }
+
+    fn check_prebuffer(&mut self) -> Result<(), AudioError> {
+        AudioEngineImpl::check_prebuffer(self)
+    }
+
+    fn set_exclusive_mode(&mut self, enable: bool) -> Result<bool, AudioError> {
+        AudioEngineImpl::set_exclusive_mode(self, enable)
+    }
+
+    fn is_exclusive_active(&self) -> bool {
+        AudioEngineImpl::is_exclusive_active(self)
+    }
+
+    fn set_limiter_enabled(&self, enabled: bool) {
+        AudioEngineImpl::set_limiter_enabled(self, enabled)
+    }
+
+    fn is_limiter_enabled(&self) -> bool {
+        AudioEngineImpl::is_limiter_enabled(self)
+    }
+
+    fn clipped_samples(&self) -> u64 {
+        AudioEngineImpl::clipped_samples(self)
+    }
+
+    fn take_first_clip(&self) -> Option<u64> {
+        AudioEngineImpl::take_first_clip(self)
+    }
+
+    fn eq_bands(&self) -> Vec<EqBand> {
+        AudioEngineImpl::eq_bands(self)
+    }
+
+    fn set_eq_bands(&self, bands: Vec<EqBand>) {
+        AudioEngineImpl::set_eq_bands(self, bands)
+    }
+
+    fn set_fade_durations(&self, fade_out: Duration, fade_in: Duration) {
+        AudioEngineImpl::set_fade_durations(self, fade_out, fade_in)
+    }
+
+    fn set_silence_skipping(&self, settings: SilenceSettings) {
+        AudioEngineImpl::set_silence_skipping(self, settings)
+    }
+
+    fn set_channel_layout(&self, layout: Option<ChannelMap>) {
+        AudioEngineImpl::set_channel_layout(self, layout)
+    }
+
+    fn set_oversampling(&self, factor: Option<u32>) {
+        AudioEngineImpl::set_oversampling(self, factor)
+    }
+
+    fn set_output_channels(&self, settings: OutputChannels) {
+        AudioEngineImpl::set_output_channels(self, settings)
+    }
+
+    fn output_channels(&self) -> OutputChannels {
+        AudioEngineImpl::output_channels(self)
+    }
+
+    fn set_crossfeed(&self, settings: CrossfeedSettings) {
+        AudioEngineImpl::set_crossfeed(self, settings)
+    }
+
+    fn crossfeed(&self) -> CrossfeedSettings {
+        AudioEngineImpl::crossfeed(self)
+    }
+
+    fn set_dither(&self, enabled: bool) {
+        AudioEngineImpl::set_dither(self, enabled)
+    }
+
+    fn is_dither_enabled(&self) -> bool {
+        AudioEngineImpl::is_dither_enabled(self)
+    }
+
+    fn chain_controls(&self) -> ChainControls {
+        AudioEngineImpl::chain_controls(self)
+    }
+
+    fn set_suspend_output_after(&self, after: Duration) {
+        AudioEngineImpl::set_suspend_output_after(self, after)
+    }
+
+    fn spectrum_tap(&self) -> Arc<SpectrumTap> {
+        AudioEngineImpl::spectrum_tap(self)
+    }
+
+    fn latest_decoded(&self, frames: usize) -> crate::models::AudioBuffer {
+        AudioEngineImpl::latest_decoded(self, frames)
+    }
+
+    fn output_levels(&self) -> crate::models::ChannelLevels {
+        AudioEngineImpl::output_levels(self)
+    }
+
+    fn supports_seek(&self) -> bool {
+        AudioEngineImpl::supports_seek(self)
+    }
+
+    fn update_performance_monitoring(&self) {
+        AudioEngineImpl::update_performance_monitoring(self)
+    }
+
+    fn get_performance_report(&self) -> PerformanceReport {
+        AudioEngineImpl::get_performance_report(self)
+    }
+
+    fn list_devices(&self) -> Vec<String> {
+        self.device_manager.list_devices()
+    }
+
+    fn current_device_name(&self) -> Result<Option<String>, AudioError> {
+        self.device_manager.current_device_name()
+    }
 
-        Ok(())
+    fn default_device_name(&self) -> Option<String> {
+        self.device_manager.default_device_name()
+    }
+
+    fn refresh_devices(&mut self) -> Result<(), AudioError> {
+        self.device_manager.refresh_devices()
+    }
+
+    fn device_capabilities(&self, device_name: &str) -> Option<DeviceCapabilities> {
+        self.device_manager.get_capabilities(device_name).cloned()
     }
 }
 
@@ -1351,9 +2882,11 @@ impl Drop for AudioEngineImpl {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::audio::{AudioEngine, AudioDecoder, AudioBuffer, AudioMetadata};
+    use crate::audio::{AudioEngine, AudioDecoder, AudioBuffer, AudioCodec, AudioMetadata};
     use crate::error::DecodeError;
+    use crate::audio::priority::ThreadPriority;
     use std::time::Duration;
+    use std::path::PathBuf;
 
     /// Mock audio decoder for testing
     struct MockDecoder {
@@ -1376,6 +2909,7 @@ mod tests {
                     track_number: Some(1),
                     year: Some(2023),
                     genre: Some("Test".to_string()),
+                    ..Default::default()
                 },
             }
         }
@@ -1400,8 +2934,8 @@ mod tests {
             &self.metadata
         }
 
-        fn duration(&self) -> Duration {
-            self.duration
+        fn duration(&self) -> Option<Duration> {
+            Some(self.duration)
         }
 
         fn sample_rate(&self) -> u32 {
@@ -1411,6 +2945,763 @@ mod tests {
         fn bit_depth(&self) -> u16 {
             self.bit_depth
         }
+
+        fn channels(&self) -> u16 {
+            2
+        }
+
+        fn codec(&self) -> AudioCodec {
+            AudioCodec::Flac
+        }
+    }
+
+    /// Decoder that hangs in `decode_next`, as decoders sometimes do on corrupted files
+    struct HangingDecoder {
+        inner: MockDecoder,
+        release: mpsc::Receiver<()>,
+    }
+
+    impl AudioDecoder for HangingDecoder {
+        fn decode_next(&mut self) -> Result<Option<AudioBuffer>, DecodeError> {
+            let _ = self.release.recv_timeout(Duration::from_secs(15));
+            self.inner.decode_next()
+        }
+
+        fn seek(&mut self, position: Duration) -> Result<(), DecodeError> {
+            self.inner.seek(position)
+        }
+
+        fn metadata(&self) -> &AudioMetadata {
+            self.inner.metadata()
+        }
+
+        fn duration(&self) -> Option<Duration> {
+            self.inner.duration()
+        }
+
+        fn sample_rate(&self) -> u32 {
+            self.inner.sample_rate()
+        }
+
+        fn bit_depth(&self) -> u16 {
+            self.inner.bit_depth()
+        }
+
+        fn channels(&self) -> u16 {
+            self.inner.channels()
+        }
+
+        fn codec(&self) -> AudioCodec {
+            self.inner.codec()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_hanging_decoder_is_reported_by_the_watchdog() {
+        use crate::error::PlayerError;
+        use crate::error_recovery::{ErrorRecoveryManager, RecoveryActions};
+        use crate::logging::AudioLogger;
+
+        struct NoDevices;
+        impl RecoveryActions for NoDevices {
+            fn switch_to_default_device(&mut self) -> Result<String, PlayerError> {
+                Err(PlayerError::Audio(AudioError::DeviceNotFound { device: "default".to_string() }))
+            }
+        }
+
+        let is_running = Arc::new(AtomicBool::new(true));
+        let mut context = decoder_context(&is_running);
+        context.decode_timeout = Duration::from_millis(50);
+        let (release, hang) = mpsc::channel();
+        *context.current_decoder.lock().unwrap() = Some(Box::new(HangingDecoder { inner: MockDecoder::new(), release: hang }));
+        let current_decoder = Arc::clone(&context.current_decoder);
+        let (_command_sender, command_receiver) = tokio_mpsc::unbounded_channel();
+        let (response_sender, mut responses) = tokio_mpsc::unbounded_channel();
+        let (status_sender, _status) = tokio_mpsc::unbounded_channel();
+        let config = DecoderThreadConfig { priority: ThreadPriority::Normal, ..DecoderThreadConfig::default() };
+
+        let started = Instant::now();
+        let handle = spawn_decoder_thread(
+            &config,
+            AudioEngineImpl::run_decoder_loop(context, command_receiver, response_sender, status_sender),
+        )
+        .unwrap();
+        match wait_for_response(&mut responses, |response| matches!(response, DecoderResponse::TrackFailed { .. })).await {
+            DecoderResponse::TrackFailed { error: AudioError::StreamError(message), .. } => {
+                assert_eq!(message, DECODE_TIMEOUT_MESSAGE);
+            }
+            other => panic!("expected a decode timeout, got {:?}", other),
+        }
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // Once the hung call returns, the abandoned thread exits without putting the decoder back
+        drop(release);
+        tokio::task::spawn_blocking(move || handle.join()).await.unwrap().unwrap();
+        assert!(current_decoder.lock().unwrap().is_none());
+        assert!(is_running.load(Ordering::Relaxed));
+
+        let error = PlayerError::Audio(AudioError::StreamError(DECODE_TIMEOUT_MESSAGE.to_string()));
+        let recovery = ErrorRecoveryManager::new(AudioLogger::new()).attempt_recovery(&error, &mut NoDevices).await;
+        assert!(recovery.is_retry());
+        assert!(recovery.message().contains("corrupted"));
+    }
+
+    /// Decoder that sends `probe()` from inside each `decode_next` call, then decodes silence
+    struct ProbeDecoder<T> {
+        inner: MockDecoder,
+        probe: fn() -> T,
+        sender: mpsc::Sender<T>,
+    }
+
+    impl<T: Send + 'static> AudioDecoder for ProbeDecoder<T> {
+        fn decode_next(&mut self) -> Result<Option<AudioBuffer>, DecodeError> {
+            let _ = self.sender.send((self.probe)());
+            self.inner.decode_next()
+        }
+
+        fn seek(&mut self, position: Duration) -> Result<(), DecodeError> {
+            self.inner.seek(position)
+        }
+
+        fn metadata(&self) -> &AudioMetadata {
+            self.inner.metadata()
+        }
+
+        fn duration(&self) -> Option<Duration> {
+            self.inner.duration()
+        }
+
+        fn sample_rate(&self) -> u32 {
+            self.inner.sample_rate()
+        }
+
+        fn bit_depth(&self) -> u16 {
+            self.inner.bit_depth()
+        }
+
+        fn channels(&self) -> u16 {
+            self.inner.channels()
+        }
+
+        fn codec(&self) -> AudioCodec {
+            self.inner.codec()
+        }
+    }
+
+    /// Run a decoder loop on the `audio-decoder` thread over `probe` until it has reported once
+    fn probe_decoding_thread<T: Send + 'static>(config: &DecoderThreadConfig, probe: fn() -> T) -> T {
+        let is_running = Arc::new(AtomicBool::new(true));
+        let context = decoder_context(&is_running);
+        let (sender, probes) = mpsc::channel();
+        *context.current_decoder.lock().unwrap() = Some(Box::new(ProbeDecoder { inner: MockDecoder::new(), probe, sender }));
+        let (command_sender, command_receiver) = tokio_mpsc::unbounded_channel();
+        let (response_sender, _responses) = tokio_mpsc::unbounded_channel();
+        let (status_sender, _status) = tokio_mpsc::unbounded_channel();
+        let handle = spawn_decoder_thread(
+            config,
+            AudioEngineImpl::run_decoder_loop(context, command_receiver, response_sender, status_sender),
+        )
+        .unwrap();
+
+        let probed = probes.recv_timeout(Duration::from_secs(10)).expect("decoder loop should decode");
+        is_running.store(false, Ordering::Relaxed);
+        command_sender.send(DecoderCommand::Shutdown).unwrap();
+        handle.join().unwrap();
+        probed
+    }
+
+    #[test]
+    fn test_decode_next_runs_on_the_decoder_thread() {
+        let config = DecoderThreadConfig { priority: ThreadPriority::Normal, ..DecoderThreadConfig::default() };
+        let name = probe_decoding_thread(&config, || thread::current().name().map(str::to_string));
+        assert_eq!(name.as_deref(), Some("audio-decoder"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_decode_next_runs_on_the_pinned_thread() {
+        /// Cores the calling thread may run on
+        fn current_affinity() -> Vec<usize> {
+            unsafe {
+                let mut set: libc::cpu_set_t = std::mem::zeroed();
+                assert_eq!(libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set), 0);
+                (0..libc::CPU_SETSIZE as usize).filter(|&core| libc::CPU_ISSET(core, &set)).collect()
+            }
+        }
+
+        // Pinning may be refused where the process is restricted to other cores
+        let pinnable = thread::spawn(|| priority::pin_current_thread(&[0], "test")).join().unwrap();
+        let config = DecoderThreadConfig {
+            priority: ThreadPriority::Normal,
+            cpu_affinity: Some(vec![0]),
+            ..DecoderThreadConfig::default()
+        };
+        let cores = probe_decoding_thread(&config, current_affinity);
+        if pinnable {
+            assert_eq!(cores, vec![0]);
+        }
+    }
+
+    #[test]
+    fn test_pinning_skips_missing_cores() {
+        let missing = priority::core_count().min(priority::MAX_AFFINITY_CORES);
+        assert_eq!(priority::missing_cores(&[0, missing, missing + 1]), vec![missing, missing + 1]);
+
+        // Neither list reaches the OS, so neither fails with EINVAL
+        let pinned = thread::spawn(move || {
+            (priority::pin_current_thread(&[], "test"), priority::pin_current_thread(&[missing], "test"))
+        })
+        .join()
+        .unwrap();
+        assert_eq!(pinned, (false, false));
+    }
+
+    /// A silent 16-bit stereo PCM WAV file
+    fn write_silent_wav(path: &std::path::Path, sample_rate: u32, frames: usize) {
+        write_stereo_wav(path, sample_rate, &vec![0u8; frames * 4]);
+    }
+
+    /// Both channels of each frame hold the frame's index, so a sample tells where it was decoded from
+    fn write_ramp_wav(path: &std::path::Path, sample_rate: u32, frames: usize) {
+        let data: Vec<u8> = (0..frames as i16).flat_map(|frame| [frame.to_le_bytes(), frame.to_le_bytes()].concat()).collect();
+        write_stereo_wav(path, sample_rate, &data);
+    }
+
+    fn write_stereo_wav(path: &std::path::Path, sample_rate: u32, data: &[u8]) {
+        crate::audio::test_files::write_wav(path, sample_rate, 2, 16, data);
+    }
+
+    /// Shared state for a decoder loop run without an output stream, with a one-second ring buffer at 48 kHz
+    fn decoder_context(is_running: &Arc<AtomicBool>) -> DecoderContext {
+        DecoderContext {
+            buffer_manager: Arc::new(BufferManager::new(48000, 2, 48000, 200, 50)),
+            current_decoder: Arc::new(Mutex::new(None)),
+            next_decoder: Arc::new(Mutex::new(None)),
+            gapless_enabled: Arc::new(AtomicBool::new(true)),
+            is_running: Arc::clone(is_running),
+            decode_timeout: Duration::from_secs(5),
+            next_track_provider: None,
+            spectrum_tap: Arc::new(SpectrumTap::new()),
+            underrun_monitor: Arc::new(UnderrunMonitor::new()),
+            prebuffer: Arc::new(PrebufferGate::new()),
+            performance_profiler: Arc::new(AudioPerformanceProfiler::new()),
+            current_position: Arc::new(PositionTracker::new(48000)),
+            silence_settings: Arc::new(Mutex::new(SilenceSettings::default())),
+            channel_layout: Arc::new(Mutex::new(None)),
+            oversampling: Arc::new(Mutex::new(None)),
+            crossfeed: Arc::new(CrossfeedControl::default()),
+        }
+    }
+
+    /// Wait for the first decoder response `want` accepts, skipping the others
+    async fn wait_for_response(
+        responses: &mut tokio_mpsc::UnboundedReceiver<DecoderResponse>,
+        want: impl Fn(&DecoderResponse) -> bool,
+    ) -> DecoderResponse {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match responses.recv().await {
+                    Some(response) if want(&response) => break response,
+                    Some(_) => continue,
+                    None => panic!("decoder thread exited before answering"),
+                }
+            }
+        })
+        .await
+        .expect("decoder thread should answer")
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_decoder_thread_runs_apart_from_runtime_and_loads_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("silence.wav");
+        write_silent_wav(&path, 48000, 4800);
+
+        let is_running = Arc::new(AtomicBool::new(true));
+        let context = decoder_context(&is_running);
+        let (command_sender, command_receiver) = tokio_mpsc::unbounded_channel();
+        let (response_sender, mut responses) = tokio_mpsc::unbounded_channel();
+        let (status_sender, _status) = tokio_mpsc::unbounded_channel();
+
+        // Normal priority so the test doesn't depend on scheduling permissions
+        let config = DecoderThreadConfig { priority: ThreadPriority::Normal, ..DecoderThreadConfig::default() };
+        let handle = spawn_decoder_thread(
+            &config,
+            AudioEngineImpl::run_decoder_loop(context, command_receiver, response_sender, status_sender),
+        )
+        .unwrap();
+        assert_ne!(handle.thread().id(), thread::current().id());
+        assert_eq!(handle.thread().name(), Some("audio-decoder"));
+
+        command_sender.send(DecoderCommand::LoadFile(path)).unwrap();
+        let response = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match responses.recv().await {
+                    Some(response @ (DecoderResponse::FileLoaded { .. } | DecoderResponse::TrackFailed { .. })) => break response,
+                    Some(_) => continue,
+                    None => panic!("decoder thread exited before answering"),
+                }
+            }
+        })
+        .await
+        .expect("decoder thread should answer LoadFile");
+        match response {
+            DecoderResponse::FileLoaded { sample_rate, channels, bit_depth, .. } => {
+                assert_eq!((sample_rate, channels, bit_depth), (48000, 2, 16));
+            }
+            other => panic!("expected FileLoaded, got {:?}", other),
+        }
+
+        // A file that cannot be opened is handed back with its path
+        let garbage = dir.path().join("garbage.wav");
+        std::fs::write(&garbage, b"not audio at all").unwrap();
+        command_sender.send(DecoderCommand::LoadFile(garbage.clone())).unwrap();
+        let failed = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match responses.recv().await {
+                    Some(DecoderResponse::TrackFailed { path, .. }) => break path,
+                    Some(_) => continue,
+                    None => panic!("decoder thread exited before answering"),
+                }
+            }
+        })
+        .await
+        .expect("decoder thread should report the unplayable file");
+        assert_eq!(failed, garbage);
+
+        is_running.store(false, Ordering::Relaxed);
+        command_sender.send(DecoderCommand::Shutdown).unwrap();
+        tokio::task::spawn_blocking(move || handle.join()).await.unwrap().unwrap();
+    }
+
+    async fn wait_for_buffered(ring_buffer: &RingBuffer, target: Duration) {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while ring_buffer.buffered_duration() < target {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("decoder should fill the ring buffer");
+    }
+
+    #[test]
+    fn test_decoder_thread_starts_with_and_without_cpu_affinity() {
+        for cpu_affinity in [None, Some(vec![0])] {
+            let config = DecoderThreadConfig {
+                priority: ThreadPriority::Normal,
+                cpu_affinity,
+                ..DecoderThreadConfig::default()
+            };
+            let (sender, receiver) = std::sync::mpsc::channel();
+            // Pinning may be refused or unsupported; the thread runs either way
+            let handle = spawn_decoder_thread(&config, async move {
+                sender.send(thread::current().name().map(str::to_string)).unwrap();
+            })
+            .unwrap();
+            assert_eq!(receiver.recv_timeout(Duration::from_secs(10)).unwrap().as_deref(), Some("audio-decoder"));
+            handle.join().unwrap();
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_decoder_seek_refills_the_buffer_from_the_new_position() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("ramp.wav");
+        write_ramp_wav(&path, 48000, 30000);
+
+        let is_running = Arc::new(AtomicBool::new(true));
+        let context = decoder_context(&is_running);
+        let ring_buffer = context.buffer_manager.ring_buffer();
+        let position = Arc::clone(&context.current_position);
+        let (command_sender, command_receiver) = tokio_mpsc::unbounded_channel();
+        let (response_sender, mut responses) = tokio_mpsc::unbounded_channel();
+        let (status_sender, _status) = tokio_mpsc::unbounded_channel();
+        let config = DecoderThreadConfig { priority: ThreadPriority::Normal, ..DecoderThreadConfig::default() };
+        let handle = spawn_decoder_thread(
+            &config,
+            AudioEngineImpl::run_decoder_loop(context, command_receiver, response_sender, status_sender),
+        )
+        .unwrap();
+
+        // Nothing reads the ring buffer, as while paused, so decoding stops at the 200 ms target
+        command_sender.send(DecoderCommand::LoadFile(path)).unwrap();
+        wait_for_response(&mut responses, |response| matches!(response, DecoderResponse::BufferFilled(_))).await;
+        wait_for_buffered(&ring_buffer, Duration::from_millis(200)).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        while responses.try_recv().is_ok() {}
+
+        command_sender.send(DecoderCommand::Seek(Duration::from_millis(500))).unwrap();
+        wait_for_response(&mut responses, |response| matches!(response, DecoderResponse::BufferFilled(_))).await;
+        wait_for_response(&mut responses, |response| matches!(response, DecoderResponse::EndOfFile)).await;
+
+        // The buffer was flushed and refilled from frame 24000 to the end, ready for playback to resume
+        assert_eq!(position.position(), Duration::from_millis(500));
+        assert_eq!(ring_buffer.available_read_frames(), 6000);
+        let mut first = [0.0f32; 2];
+        ring_buffer.read(&mut first);
+        assert_eq!(first, [24000.0 / 32768.0; 2]);
+
+        is_running.store(false, Ordering::Relaxed);
+        command_sender.send(DecoderCommand::Shutdown).unwrap();
+        tokio::task::spawn_blocking(move || handle.join()).await.unwrap().unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_decoder_stop_rewinds_and_holds_the_track() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("ramp.wav");
+        write_ramp_wav(&path, 48000, 30000);
+
+        let is_running = Arc::new(AtomicBool::new(true));
+        let context = decoder_context(&is_running);
+        let ring_buffer = context.buffer_manager.ring_buffer();
+        let current_decoder = Arc::clone(&context.current_decoder);
+        let (command_sender, command_receiver) = tokio_mpsc::unbounded_channel();
+        let (response_sender, mut responses) = tokio_mpsc::unbounded_channel();
+        let (status_sender, _status) = tokio_mpsc::unbounded_channel();
+        let config = DecoderThreadConfig { priority: ThreadPriority::Normal, ..DecoderThreadConfig::default() };
+        let handle = spawn_decoder_thread(
+            &config,
+            AudioEngineImpl::run_decoder_loop(context, command_receiver, response_sender, status_sender),
+        )
+        .unwrap();
+
+        command_sender.send(DecoderCommand::LoadFile(path.clone())).unwrap();
+        wait_for_response(&mut responses, |response| matches!(response, DecoderResponse::BufferFilled(_))).await;
+        wait_for_buffered(&ring_buffer, Duration::from_millis(200)).await;
+
+        // Stopped, the buffer is dropped and the track waits at its start instead of decoding on
+        command_sender.send(DecoderCommand::Stop { path: Some(path), start: Duration::from_millis(500) }).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        while responses.try_recv().is_ok() {}
+        assert_eq!(ring_buffer.available_read_frames(), 0);
+        assert!(current_decoder.lock().unwrap().is_some());
+
+        // Resuming decodes from the rewound position to the end
+        command_sender.send(DecoderCommand::Resume).unwrap();
+        wait_for_response(&mut responses, |response| matches!(response, DecoderResponse::EndOfFile)).await;
+        assert_eq!(ring_buffer.available_read_frames(), 6000);
+        let mut first = [0.0f32; 2];
+        ring_buffer.read(&mut first);
+        assert_eq!(first, [24000.0 / 32768.0; 2]);
+
+        is_running.store(false, Ordering::Relaxed);
+        command_sender.send(DecoderCommand::Shutdown).unwrap();
+        tokio::task::spawn_blocking(move || handle.join()).await.unwrap().unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_position_holds_while_paused_and_counts_cuts_when_played() {
+        // Half a second of silence, then sound
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("lead-in.wav");
+        let data: Vec<u8> = (0..48_000usize)
+            .flat_map(|frame| if frame < 24_000 { [0u8; 4] } else { [0xe8, 0x03, 0xe8, 0x03] })
+            .collect();
+        write_stereo_wav(&path, 48_000, &data);
+
+        let is_running = Arc::new(AtomicBool::new(true));
+        let context = decoder_context(&is_running);
+        *context.silence_settings.lock().unwrap() = SilenceSettings {
+            enabled: true,
+            min_leading: Duration::from_millis(100),
+            ..SilenceSettings::default()
+        };
+        let ring_buffer = context.buffer_manager.ring_buffer();
+        let position = Arc::clone(&context.current_position);
+        let (command_sender, command_receiver) = tokio_mpsc::unbounded_channel();
+        let (response_sender, mut responses) = tokio_mpsc::unbounded_channel();
+        let (status_sender, _status) = tokio_mpsc::unbounded_channel();
+        let config = DecoderThreadConfig { priority: ThreadPriority::Normal, ..DecoderThreadConfig::default() };
+        let handle = spawn_decoder_thread(
+            &config,
+            AudioEngineImpl::run_decoder_loop(context, command_receiver, response_sender, status_sender),
+        )
+        .unwrap();
+
+        // Paused: nothing is read, and the cut the decoder made ahead doesn't move the position
+        command_sender.send(DecoderCommand::LoadFile(path)).unwrap();
+        wait_for_response(&mut responses, |response| matches!(response, DecoderResponse::BufferFilled(_))).await;
+        wait_for_buffered(&ring_buffer, Duration::from_millis(200)).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(position.position(), Duration::ZERO);
+
+        // Playing the kept 100 ms of silence takes the position over the cut to the sound
+        let mut played = vec![0.0f32; 4800 * 2];
+        assert_eq!(ring_buffer.read(&mut played), played.len());
+        position.advance(4800);
+        assert!(played.iter().all(|sample| *sample == 0.0));
+        assert_eq!(position.position(), Duration::from_millis(500));
+        let mut next = [0.0f32; 2];
+        ring_buffer.read(&mut next);
+        position.advance(1);
+        assert_eq!(next, [1000.0 / 32768.0; 2]);
+
+        // Paused again with the decoder refilling; the position stays where the output is
+        let paused_at = position.position();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(position.position(), paused_at);
+
+        is_running.store(false, Ordering::Relaxed);
+        command_sender.send(DecoderCommand::Shutdown).unwrap();
+        tokio::task::spawn_blocking(move || handle.join()).await.unwrap().unwrap();
+    }
+
+    /// Finite stereo decoder whose samples give their time in seconds, counted from `start`
+    struct TimeRampDecoder {
+        inner: MockDecoder,
+        start: f64,
+        frames: usize,
+        decoded: usize,
+    }
+
+    impl TimeRampDecoder {
+        fn new(sample_rate: u32, start: f64, frames: usize) -> Self {
+            let mut inner = MockDecoder::new();
+            inner.sample_rate = sample_rate;
+            inner.duration = Duration::from_secs_f64(frames as f64 / sample_rate as f64);
+            Self { inner, start, frames, decoded: 0 }
+        }
+    }
+
+    impl AudioDecoder for TimeRampDecoder {
+        fn decode_next(&mut self) -> Result<Option<AudioBuffer>, DecodeError> {
+            let frames = (self.frames - self.decoded).min(500);
+            if frames == 0 {
+                return Ok(None);
+            }
+            let rate = self.inner.sample_rate as f64;
+            let samples = (self.decoded..self.decoded + frames)
+                .flat_map(|frame| [(self.start + frame as f64 / rate) as f32; 2])
+                .collect();
+            self.decoded += frames;
+            Ok(Some(AudioBuffer { samples, channels: 2, sample_rate: self.inner.sample_rate, frames }))
+        }
+
+        fn seek(&mut self, _position: Duration) -> Result<(), DecodeError> {
+            Ok(())
+        }
+
+        fn metadata(&self) -> &AudioMetadata {
+            self.inner.metadata()
+        }
+
+        fn duration(&self) -> Option<Duration> {
+            self.inner.duration()
+        }
+
+        fn sample_rate(&self) -> u32 {
+            self.inner.sample_rate()
+        }
+
+        fn bit_depth(&self) -> u16 {
+            self.inner.bit_depth()
+        }
+
+        fn channels(&self) -> u16 {
+            2
+        }
+
+        fn codec(&self) -> AudioCodec {
+            self.inner.codec()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_gapless_join_resamples_across_a_rate_change_without_losing_frames() {
+        // 50 ms at each rate, played gaplessly into the context's 48 kHz ring buffer
+        for (first_rate, second_rate) in [(44_100u32, 48_000u32), (48_000, 44_100)] {
+            let is_running = Arc::new(AtomicBool::new(true));
+            let context = decoder_context(&is_running);
+            let ring_buffer = context.buffer_manager.ring_buffer();
+            *context.current_decoder.lock().unwrap() = Some(Box::new(TimeRampDecoder::new(first_rate, 0.0, first_rate as usize / 20)));
+            *context.next_decoder.lock().unwrap() = Some(Box::new(TimeRampDecoder::new(second_rate, 0.05, second_rate as usize / 20)));
+            let (command_sender, command_receiver) = tokio_mpsc::unbounded_channel();
+            let (response_sender, mut responses) = tokio_mpsc::unbounded_channel();
+            let (status_sender, _status) = tokio_mpsc::unbounded_channel();
+            let config = DecoderThreadConfig { priority: ThreadPriority::Normal, ..DecoderThreadConfig::default() };
+            let handle = spawn_decoder_thread(
+                &config,
+                AudioEngineImpl::run_decoder_loop(context, command_receiver, response_sender, status_sender),
+            )
+            .unwrap();
+
+            wait_for_response(&mut responses, |response| matches!(response, DecoderResponse::TrackTransitioned)).await;
+            wait_for_response(&mut responses, |response| matches!(response, DecoderResponse::EndOfFile)).await;
+
+            // Every output frame sits at its own time: nothing dropped, repeated or zeroed at the seam.
+            // Only the second track's very last frame may still wait in the resampler.
+            let frames = ring_buffer.available_read_frames();
+            assert!((4799..=4800).contains(&frames), "{} Hz → {} Hz: {} frames", first_rate, second_rate, frames);
+            let mut samples = vec![0.0f32; frames * 2];
+            ring_buffer.read(&mut samples);
+            for (index, frame) in samples.chunks(2).enumerate() {
+                let expected = index as f32 / 48_000.0;
+                assert!(
+                    frame.iter().all(|sample| (sample - expected).abs() < 1e-6),
+                    "{} Hz → {} Hz: frame {} is {:?}, expected {}", first_rate, second_rate, index, frame, expected
+                );
+            }
+
+            is_running.store(false, Ordering::Relaxed);
+            command_sender.send(DecoderCommand::Shutdown).unwrap();
+            tokio::task::spawn_blocking(move || handle.join()).await.unwrap().unwrap();
+        }
+    }
+
+    /// Decoder on a slow network drive: 100 ms packets, the second of which takes 300 ms to arrive
+    struct SlowStartDecoder {
+        inner: MockDecoder,
+        packets: usize,
+    }
+
+    impl AudioDecoder for SlowStartDecoder {
+        fn decode_next(&mut self) -> Result<Option<AudioBuffer>, DecodeError> {
+            let delay = if self.packets == 1 { 300 } else { 5 };
+            std::thread::sleep(Duration::from_millis(delay));
+            self.packets += 1;
+            Ok(Some(AudioBuffer { samples: vec![0.1; 4800 * 2], channels: 2, sample_rate: 48_000, frames: 4800 }))
+        }
+
+        fn seek(&mut self, _position: Duration) -> Result<(), DecodeError> {
+            Ok(())
+        }
+
+        fn metadata(&self) -> &AudioMetadata {
+            self.inner.metadata()
+        }
+
+        fn duration(&self) -> Option<Duration> {
+            self.inner.duration()
+        }
+
+        fn sample_rate(&self) -> u32 {
+            48_000
+        }
+
+        fn bit_depth(&self) -> u16 {
+            self.inner.bit_depth()
+        }
+
+        fn channels(&self) -> u16 {
+            2
+        }
+
+        fn codec(&self) -> AudioCodec {
+            self.inner.codec()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_prebuffer_keeps_a_slow_source_from_underrunning() {
+        // Without a pre-buffer the stall after the first packet empties the buffer; 500 ms rides it out
+        for (prebuffer_frames, expect_underruns) in [(0u64, true), (24_000, false)] {
+            let is_running = Arc::new(AtomicBool::new(true));
+            let context = decoder_context(&is_running);
+            let ring_buffer = context.buffer_manager.ring_buffer();
+            let underruns = Arc::clone(&context.underrun_monitor);
+            let prebuffer = Arc::clone(&context.prebuffer);
+            prebuffer.arm(prebuffer_frames);
+            *context.current_decoder.lock().unwrap() = Some(Box::new(SlowStartDecoder { inner: MockDecoder::new(), packets: 0 }));
+            let (command_sender, command_receiver) = tokio_mpsc::unbounded_channel();
+            let (response_sender, _responses) = tokio_mpsc::unbounded_channel();
+            let (status_sender, _status) = tokio_mpsc::unbounded_channel();
+            let config = DecoderThreadConfig { priority: ThreadPriority::Normal, ..DecoderThreadConfig::default() };
+            let handle = spawn_decoder_thread(
+                &config,
+                AudioEngineImpl::run_decoder_loop(context, command_receiver, response_sender, status_sender),
+            )
+            .unwrap();
+
+            // Play like the output callback: silent while the pre-buffer holds, then 10 ms blocks for a second
+            let mut block = vec![0.0f32; 480 * 2];
+            let mut blocks_played = 0;
+            let started = Instant::now();
+            while blocks_played < 100 && started.elapsed() < Duration::from_secs(10) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                if prebuffer.hold(ring_buffer.available_read_frames() as u64) {
+                    continue;
+                }
+                if ring_buffer.read(&mut block) < block.len() {
+                    underruns.record_short_read();
+                }
+                blocks_played += 1;
+            }
+
+            assert_eq!(blocks_played, 100, "pre-buffer of {} frames never filled", prebuffer_frames);
+            assert_eq!(
+                underruns.underrun_count() > 0, expect_underruns,
+                "pre-buffer of {} frames: {} underruns", prebuffer_frames, underruns.underrun_count()
+            );
+
+            is_running.store(false, Ordering::Relaxed);
+            command_sender.send(DecoderCommand::Shutdown).unwrap();
+            tokio::task::spawn_blocking(move || handle.join()).await.unwrap().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_exclusive_mode_falls_back_gracefully() {
+        let mut engine = AudioEngineImpl::new().expect("Failed to create AudioEngine");
+
+        // Either exclusive access is granted or the engine keeps working in shared mode
+        let acquired = engine.set_exclusive_mode(true).expect("Exclusive request should not fail");
+        assert_eq!(acquired, engine.is_exclusive_active());
+
+        assert!(!engine.set_exclusive_mode(false).unwrap());
+        assert!(!engine.is_exclusive_active());
+    }
+
+    #[test]
+    fn test_clamp_output_buffer_frames() {
+        let range = SupportedBufferSize::Range { min: 64, max: 8192 };
+        assert_eq!(clamp_output_buffer_frames(2048, &range), 2048);
+        assert_eq!(clamp_output_buffer_frames(16, &range), 64);
+        assert_eq!(clamp_output_buffer_frames(65536, &range), 8192);
+        assert_eq!(clamp_output_buffer_frames(0, &range), 0, "0 keeps the device default");
+        assert_eq!(clamp_output_buffer_frames(100_000, &SupportedBufferSize::Unknown), 100_000);
+    }
+
+    #[test]
+    fn test_callback_scratch_covers_a_device_buffer() {
+        let mut config = StreamConfig { channels: 2, sample_rate: cpal::SampleRate(48000), buffer_size: cpal::BufferSize::Fixed(512) };
+        assert!(callback_scratch(&config).capacity() >= 1024);
+        config.buffer_size = cpal::BufferSize::Default;
+        assert!(callback_scratch(&config).capacity() >= DEFAULT_CALLBACK_FRAMES * 2);
+    }
+
+    #[test]
+    fn test_buffer_settings_from_config() {
+        let config = PlayerConfig {
+            buffer_size: 1024,
+            ring_buffer_ms: 500,
+            target_buffer_ms: 200,
+            ..PlayerConfig::default()
+        };
+        let settings = BufferSettings::from_config(&config);
+        assert_eq!(settings.output_buffer_frames, 1024);
+        assert_eq!(settings.ring_buffer_ms, 500);
+        assert_eq!(settings.target_buffer_ms, 200);
+        assert_eq!(settings.min_buffer_ms(), 100);
+
+        let manager = settings.buffer_manager(96000, 2);
+        assert_eq!(manager.ring_buffer().capacity_frames(), 48000);
+
+        // Target can never exceed the ring buffer
+        let settings = BufferSettings::from_config(&PlayerConfig {
+            ring_buffer_ms: 200,
+            target_buffer_ms: 900,
+            ..PlayerConfig::default()
+        });
+        assert_eq!(settings.target_buffer_ms, 200);
+
+        // A fixed output_buffer_frames takes precedence over buffer_size
+        let settings = BufferSettings::from_config(&PlayerConfig {
+            buffer_size: 1024,
+            output_buffer_frames: Some(256),
+            ..PlayerConfig::default()
+        });
+        assert_eq!(settings.output_buffer_frames, 256);
     }
 
     #[test]
@@ -1505,6 +3796,81 @@ mod tests {
         assert!(result.is_ok(), "Stopping should succeed");
     }
 
+    #[test]
+    fn test_thread_status_merge_keeps_each_threads_fields() {
+        let mut merged = ThreadStatus::new();
+
+        let mut decoder_update = ThreadStatus::new();
+        decoder_update.origin = StatusOrigin::Decoder;
+        decoder_update.buffer_fill = 0.5;
+        decoder_update.current_file = Some(std::path::PathBuf::from("/music/a.flac"));
+        decoder_update.source_format = Some(AudioFormat::new(96000, 24, 2, AudioCodec::Flac));
+        decoder_update.resampling = true;
+        merged.merge(decoder_update);
+
+        // The output thread knows nothing about the file; it must not erase it
+        let mut output_update = ThreadStatus::new();
+        output_update.playback_state = PlaybackState::Playing;
+        output_update.position = Duration::from_secs(3);
+        output_update.buffer_fill = 0.75;
+        merged.merge(output_update);
+
+        assert_eq!(merged.playback_state, PlaybackState::Playing);
+        assert_eq!(merged.position, Duration::from_secs(3));
+        assert_eq!(merged.buffer_fill, 0.75);
+        assert_eq!(merged.current_file, Some(std::path::PathBuf::from("/music/a.flac")));
+        assert_eq!(merged.source_format.map(|format| format.codec), Some(AudioCodec::Flac));
+        assert!(merged.resampling);
+    }
+
+    #[test]
+    fn test_atomic_playback_state_round_trip() {
+        let state = AtomicPlaybackState::new(PlaybackState::Stopped);
+        assert_eq!(state.load(), PlaybackState::Stopped);
+
+        for expected in [PlaybackState::Playing, PlaybackState::Paused, PlaybackState::Stopped] {
+            state.store(expected.clone());
+            assert_eq!(state.load(), expected);
+        }
+    }
+
+    #[test]
+    fn test_status_polling_does_not_cause_underruns() {
+        let mut engine = AudioEngineImpl::new().expect("Failed to create AudioEngine");
+        engine.start_playback(Box::new(MockDecoder::new())).expect("Starting playback should succeed");
+
+        // Let the decoder fill the buffer before measuring
+        std::thread::sleep(Duration::from_millis(200));
+        let underruns_before = engine.underrun_count();
+
+        // Hammer the state the callback shares, as status polling and the decoder do
+        let done = Arc::new(AtomicBool::new(false));
+        let pollers: Vec<_> = (0..4)
+            .map(|_| {
+                let playback_state = Arc::clone(&engine.playback_state);
+                let position = Arc::clone(&engine.current_position);
+                let done = Arc::clone(&done);
+                std::thread::spawn(move || {
+                    let mut queries = 0u64;
+                    while !done.load(Ordering::Relaxed) {
+                        let _ = playback_state.load();
+                        let _ = position.position();
+                        queries += 1;
+                    }
+                    queries
+                })
+            })
+            .collect();
+
+        std::thread::sleep(Duration::from_secs(1));
+        done.store(true, Ordering::Relaxed);
+        let queries: u64 = pollers.into_iter().map(|poller| poller.join().unwrap()).sum();
+        engine.stop().expect("Stopping should succeed");
+
+        assert!(queries > 0);
+        assert_eq!(engine.underrun_count(), underruns_before, "status polling must not starve the audio callback");
+    }
+
     #[test]
     fn test_device_management() {
         let mut engine = AudioEngineImpl::new().expect("Failed to create AudioEngine");
@@ -1820,6 +4186,28 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_seek_keeps_paused_and_stopped_states() {
+        let mut engine = AudioEngineImpl::new().expect("Failed to create AudioEngine");
+        engine.start_playback(Box::new(MockDecoder::new())).unwrap();
+
+        // Paused: the position moves, playback doesn't resume
+        engine.pause().unwrap();
+        engine.seek(Duration::from_secs(60)).unwrap();
+        assert_eq!(engine.playback_state(), PlaybackState::Paused);
+        assert_eq!(engine.current_position(), Duration::from_secs(60));
+
+        // Stopped: the next start begins at the sought position
+        engine.stop().unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        engine.seek(Duration::from_secs(90)).unwrap();
+        assert_eq!(engine.playback_state(), PlaybackState::Stopped);
+        engine.start_playback_at(PathBuf::from("track.flac"), Box::new(MockDecoder::new()), Duration::ZERO).unwrap();
+        assert_eq!(engine.current_position(), Duration::from_secs(90));
+
+        engine.stop().unwrap();
+    }
+
     #[test]
     fn test_seek_boundary_conditions() {
         let mut engine = AudioEngineImpl::new().expect("Failed to create AudioEngine");
@@ -1846,6 +4234,34 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_ab_loop_lifecycle() {
+        let mut engine = AudioEngineImpl::new().expect("Failed to create AudioEngine");
+        *engine.current_decoder.lock().unwrap() = Some(Box::new(MockDecoder::new()));
+
+        // B before A and B past the end are rejected
+        assert!(matches!(
+            engine.set_loop(Duration::from_secs(90), Duration::from_secs(60)),
+            Err(AudioError::InvalidLoopRegion { .. })
+        ));
+        assert!(engine.set_loop(Duration::from_secs(60), Duration::from_secs(200)).is_err());
+        assert_eq!(engine.loop_region(), None);
+
+        let region = engine.set_loop(Duration::from_secs(60), Duration::from_secs(90)).unwrap();
+        assert_eq!(engine.loop_region(), Some(region));
+
+        // Seeking inside the loop keeps it, seeking outside clears it
+        engine.seek(Duration::from_secs(75)).unwrap();
+        assert_eq!(engine.loop_region(), Some(region));
+        engine.seek(Duration::from_secs(120)).unwrap();
+        assert_eq!(engine.loop_region(), None);
+        assert_eq!(engine.current_position(), Duration::from_secs(120));
+
+        engine.set_loop(Duration::from_secs(60), Duration::from_secs(90)).unwrap();
+        engine.clear_loop().unwrap();
+        assert_eq!(engine.loop_region(), None);
+    }
+
     #[test]
     fn test_playback_with_different_configurations() {
         let mut engine = AudioEngineImpl::new().expect("Failed to create AudioEngine");
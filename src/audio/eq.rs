@@ -0,0 +1,326 @@
+/*!
+Parametric equalizer for the output path.
+
+- `ParametricEq` is shared between the engine and the output callback and holds the current
+  bands. Changing them bumps a generation counter.
+- Each output stream owns an `EqProcessor` with the filter state. The callback checks the
+  generation once per block and only then `try_lock`s the bands to recompute coefficients, so
+  the hot path never waits on a lock or allocates.
+- Bands are RBJ-cookbook biquads (peaking, low shelf, high shelf) run in series on every channel.
+*/
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+
+/// Most bands the equalizer runs at once
+pub const MAX_BANDS: usize = 10;
+
+/// Accepted band center or corner frequencies in Hz
+pub const FREQUENCY_RANGE: std::ops::RangeInclusive<f32> = 20.0..=20_000.0;
+
+/// Accepted band gains in dB
+pub const GAIN_RANGE: std::ops::RangeInclusive<f32> = -24.0..=24.0;
+
+/// Accepted band Q (bandwidth) values
+pub const Q_RANGE: std::ops::RangeInclusive<f32> = 0.1..=10.0;
+
+/// Shape of one equalizer band
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EqFilter {
+    /// Bell around the frequency
+    Peak,
+    /// Everything below the frequency
+    LowShelf,
+    /// Everything above the frequency
+    HighShelf,
+}
+
+impl EqFilter {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EqFilter::Peak => "peak",
+            EqFilter::LowShelf => "low-shelf",
+            EqFilter::HighShelf => "high-shelf",
+        }
+    }
+}
+
+impl std::str::FromStr for EqFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "peak" | "bell" => Ok(EqFilter::Peak),
+            "low-shelf" | "lowshelf" | "low" => Ok(EqFilter::LowShelf),
+            "high-shelf" | "highshelf" | "high" => Ok(EqFilter::HighShelf),
+            _ => Err(format!("unknown filter '{}' (expected peak, low-shelf or high-shelf)", s)),
+        }
+    }
+}
+
+/// One equalizer band
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EqBand {
+    pub filter: EqFilter,
+    pub frequency_hz: f32,
+    pub gain_db: f32,
+    pub q: f32,
+}
+
+impl EqBand {
+    /// Q of a Butterworth shelf, the usual default
+    pub const DEFAULT_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+    pub const fn new(filter: EqFilter, frequency_hz: f32, gain_db: f32, q: f32) -> Self {
+        Self { filter, frequency_hz, gain_db, q }
+    }
+
+    /// Check the band is within the accepted frequency, gain and Q ranges
+    pub fn validate(&self) -> Result<(), String> {
+        if !FREQUENCY_RANGE.contains(&self.frequency_hz) {
+            return Err(format!(
+                "frequency {} Hz is outside {}-{} Hz",
+                self.frequency_hz, FREQUENCY_RANGE.start(), FREQUENCY_RANGE.end()
+            ));
+        }
+        if !GAIN_RANGE.contains(&self.gain_db) {
+            return Err(format!("gain {} dB is outside {} to +{} dB", self.gain_db, GAIN_RANGE.start(), GAIN_RANGE.end()));
+        }
+        if !Q_RANGE.contains(&self.q) {
+            return Err(format!("Q {} is outside {}-{}", self.q, Q_RANGE.start(), Q_RANGE.end()));
+        }
+        Ok(())
+    }
+
+    /// One-line description, e.g. `peak 1000 Hz +3.0 dB Q 1.41`
+    pub fn describe(&self) -> String {
+        format!("{} {} Hz {:+.1} dB Q {:.2}", self.filter.as_str(), self.frequency_hz, self.gain_db, self.q)
+    }
+}
+
+/// Current equalizer bands, shared with the output callback
+#[derive(Debug, Default)]
+pub struct ParametricEq {
+    bands: Mutex<Vec<EqBand>>,
+    generation: AtomicU64,
+}
+
+impl ParametricEq {
+    /// Create an equalizer with no bands (flat)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the bands; anything past `MAX_BANDS` is ignored
+    pub fn set_bands(&self, mut bands: Vec<EqBand>) {
+        bands.truncate(MAX_BANDS);
+        *self.bands.lock().unwrap() = bands;
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    /// Current bands
+    pub fn bands(&self) -> Vec<EqBand> {
+        self.bands.lock().unwrap().clone()
+    }
+
+    /// Filter state for one output stream
+    pub fn processor(&self, sample_rate: u32, channels: usize) -> EqProcessor {
+        EqProcessor {
+            filters: vec![Biquad::default(); MAX_BANDS],
+            state: vec![[0.0; 2]; MAX_BANDS * channels.max(1)],
+            active: 0,
+            generation: u64::MAX,
+            sample_rate: sample_rate as f64,
+            channels: channels.max(1),
+        }
+    }
+}
+
+/// Filter coefficients and state owned by one output stream
+#[derive(Debug)]
+pub struct EqProcessor {
+    filters: Vec<Biquad>,
+    /// Transposed direct form II state, `MAX_BANDS` entries per channel
+    state: Vec<[f64; 2]>,
+    active: usize,
+    generation: u64,
+    sample_rate: f64,
+    channels: usize,
+}
+
+impl EqProcessor {
+    /// Filter interleaved samples in place, picking up band changes first
+    pub fn process(&mut self, eq: &ParametricEq, samples: &mut [f32]) {
+        self.refresh(eq);
+        if self.active == 0 {
+            return;
+        }
+
+        for frame in samples.chunks_mut(self.channels) {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let state = &mut self.state[channel * MAX_BANDS..][..self.active];
+                let mut value = *sample as f64;
+                for (filter, state) in self.filters[..self.active].iter().zip(state.iter_mut()) {
+                    value = filter.run(value, state);
+                }
+                *sample = value as f32;
+            }
+        }
+    }
+
+    /// Recompute coefficients when the bands changed; skipped while the engine holds the lock
+    fn refresh(&mut self, eq: &ParametricEq) {
+        let generation = eq.generation.load(Ordering::Acquire);
+        if generation == self.generation {
+            return;
+        }
+        let Ok(bands) = eq.bands.try_lock() else {
+            return;
+        };
+
+        self.active = bands.len().min(MAX_BANDS);
+        for (filter, band) in self.filters.iter_mut().zip(bands.iter()) {
+            *filter = Biquad::for_band(band, self.sample_rate);
+        }
+        self.state.iter_mut().for_each(|state| *state = [0.0; 2]);
+        self.generation = generation;
+    }
+}
+
+/// Normalized biquad coefficients (a0 = 1)
+#[derive(Debug, Clone, Copy, Default)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+impl Biquad {
+    /// RBJ Audio EQ Cookbook coefficients for a band
+    fn for_band(band: &EqBand, sample_rate: f64) -> Self {
+        // Keep the frequency below Nyquist for low output rates
+        let frequency = (band.frequency_hz as f64).min(sample_rate * 0.49);
+        let a = 10f64.powf(band.gain_db as f64 / 40.0);
+        let w0 = 2.0 * std::f64::consts::PI * frequency / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * band.q.max(0.01) as f64);
+        let shelf = 2.0 * a.sqrt() * alpha;
+
+        let (b0, b1, b2, a0, a1, a2) = match band.filter {
+            EqFilter::Peak => (
+                1.0 + alpha * a,
+                -2.0 * cos,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos,
+                1.0 - alpha / a,
+            ),
+            EqFilter::LowShelf => (
+                a * ((a + 1.0) - (a - 1.0) * cos + shelf),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - shelf),
+                (a + 1.0) + (a - 1.0) * cos + shelf,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - shelf,
+            ),
+            EqFilter::HighShelf => (
+                a * ((a + 1.0) + (a - 1.0) * cos + shelf),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - shelf),
+                (a + 1.0) - (a - 1.0) * cos + shelf,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - shelf,
+            ),
+        };
+
+        Self { b0: b0 / a0, b1: b1 / a0, b2: b2 / a0, a1: a1 / a0, a2: a2 / a0 }
+    }
+
+    fn run(&self, input: f64, state: &mut [f64; 2]) -> f64 {
+        let output = self.b0 * input + state[0];
+        state[0] = self.b1 * input - self.a1 * output + state[1];
+        state[1] = self.b2 * input - self.a2 * output;
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gain in dB the EQ applies to a sine, measured by RMS once the filters have settled
+    fn gain_db(eq: &ParametricEq, frequency: f32) -> f32 {
+        let sample_rate = 48_000;
+        let mut processor = eq.processor(sample_rate, 2);
+        let mut samples: Vec<f32> = (0..sample_rate as usize)
+            .flat_map(|n| {
+                let value = 0.25 * (2.0 * std::f32::consts::PI * frequency * n as f32 / sample_rate as f32).sin();
+                [value, value]
+            })
+            .collect();
+        processor.process(eq, &mut samples);
+        let settled = &samples[samples.len() / 2..];
+        let rms = (settled.iter().map(|s| s * s).sum::<f32>() / settled.len() as f32).sqrt();
+        20.0 * (rms / (0.25 / std::f32::consts::SQRT_2)).log10()
+    }
+
+    #[test]
+    fn flat_eq_leaves_samples_untouched() {
+        let eq = ParametricEq::new();
+        let mut processor = eq.processor(44_100, 2);
+        let mut samples = vec![0.5, -0.25, 0.125, 1.0];
+        processor.process(&eq, &mut samples);
+        assert_eq!(samples, vec![0.5, -0.25, 0.125, 1.0]);
+    }
+
+    #[test]
+    fn peak_band_boosts_its_center_frequency() {
+        let eq = ParametricEq::new();
+        eq.set_bands(vec![EqBand::new(EqFilter::Peak, 1000.0, 6.0, 1.0)]);
+
+        assert!((gain_db(&eq, 1000.0) - 6.0).abs() < 0.2);
+        assert!(gain_db(&eq, 10_000.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn shelves_affect_their_side_of_the_spectrum() {
+        let eq = ParametricEq::new();
+        eq.set_bands(vec![
+            EqBand::new(EqFilter::LowShelf, 200.0, -6.0, EqBand::DEFAULT_Q),
+            EqBand::new(EqFilter::HighShelf, 4000.0, 6.0, EqBand::DEFAULT_Q),
+        ]);
+
+        assert!((gain_db(&eq, 40.0) + 6.0).abs() < 0.5);
+        assert!((gain_db(&eq, 16_000.0) - 6.0).abs() < 0.5);
+        assert!(gain_db(&eq, 1000.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn processor_picks_up_new_bands() {
+        let eq = ParametricEq::new();
+        let mut processor = eq.processor(48_000, 1);
+        let mut samples = vec![0.5; 8];
+        processor.process(&eq, &mut samples);
+        assert_eq!(samples, vec![0.5; 8]);
+
+        eq.set_bands(vec![EqBand::new(EqFilter::LowShelf, 100.0, 12.0, EqBand::DEFAULT_Q)]);
+        let mut samples = vec![0.5; 4800];
+        processor.process(&eq, &mut samples);
+        // DC sits fully inside the low shelf: +12 dB is roughly 4x
+        assert!((samples[4799] / 0.5 - 3.98).abs() < 0.05);
+    }
+
+    #[test]
+    fn band_validation() {
+        assert!(EqBand::new(EqFilter::Peak, 1000.0, 3.0, 1.0).validate().is_ok());
+        assert!(EqBand::new(EqFilter::Peak, 10.0, 3.0, 1.0).validate().is_err());
+        assert!(EqBand::new(EqFilter::Peak, 1000.0, 30.0, 1.0).validate().is_err());
+        assert!(EqBand::new(EqFilter::Peak, 1000.0, 3.0, 0.0).validate().is_err());
+        assert_eq!("low-shelf".parse::<EqFilter>(), Ok(EqFilter::LowShelf));
+        assert!("notch".parse::<EqFilter>().is_err());
+    }
+}
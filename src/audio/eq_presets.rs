@@ -0,0 +1,211 @@
+/*!
+Named equalizer presets
+
+`EqPresetStore` keeps user presets in `eq_presets.json` next to `config.toml`.
+The built-in presets (Flat, Bass-Boost, Treble-Boost, Loudness) are constants:
+they are always listed, cannot be deleted, and their names cannot be reused.
+Names are matched without regard to case.
+*/
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::audio::eq::{EqBand, EqFilter, MAX_BANDS};
+use crate::config::store;
+
+/// Saved presets, in the config directory
+pub const EQ_PRESETS_FILE: &str = "eq_presets.json";
+
+/// Format version written by this build
+const FORMAT_VERSION: u32 = 1;
+
+/// Presets that ship with the player
+pub const BUILTIN_PRESETS: &[(&str, &[EqBand])] = &[
+    ("Flat", &[]),
+    ("Bass-Boost", &[EqBand::new(EqFilter::LowShelf, 100.0, 6.0, EqBand::DEFAULT_Q)]),
+    ("Treble-Boost", &[EqBand::new(EqFilter::HighShelf, 8000.0, 6.0, EqBand::DEFAULT_Q)]),
+    ("Loudness", &[
+        EqBand::new(EqFilter::LowShelf, 80.0, 6.0, EqBand::DEFAULT_Q),
+        EqBand::new(EqFilter::HighShelf, 10_000.0, 4.0, EqBand::DEFAULT_Q),
+    ]),
+];
+
+/// A preset as listed by `EqPresetStore::list`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EqPreset {
+    pub name: String,
+    pub bands: Vec<EqBand>,
+    pub builtin: bool,
+}
+
+/// On-disk layout of `eq_presets.json`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PresetsFile {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    presets: BTreeMap<String, Vec<EqBand>>,
+}
+
+/// User presets saved as JSON, plus the built-in ones
+#[derive(Debug)]
+pub struct EqPresetStore {
+    path: PathBuf,
+}
+
+impl EqPresetStore {
+    /// Store backed by `path`; the file is created on the first save
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Built-in presets followed by the saved ones in name order
+    pub fn list(&self) -> io::Result<Vec<EqPreset>> {
+        let builtin = BUILTIN_PRESETS.iter().map(|(name, bands)| EqPreset {
+            name: name.to_string(),
+            bands: bands.to_vec(),
+            builtin: true,
+        });
+        let saved = self.read()?.presets.into_iter().map(|(name, bands)| EqPreset { name, bands, builtin: false });
+        Ok(builtin.chain(saved).collect())
+    }
+
+    /// Bands of the preset called `name`
+    pub fn load(&self, name: &str) -> io::Result<EqPreset> {
+        self.list()?
+            .into_iter()
+            .find(|preset| preset.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no EQ preset named '{}'", name)))
+    }
+
+    /// Save `bands` as `name`, replacing a saved preset of the same name
+    pub fn save(&self, name: &str, bands: &[EqBand]) -> io::Result<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "preset name must not be empty"));
+        }
+        if let Some(builtin) = Self::builtin_name(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("'{}' is a built-in preset; choose another name", builtin),
+            ));
+        }
+        if bands.len() > MAX_BANDS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("a preset holds at most {} bands", MAX_BANDS),
+            ));
+        }
+        if let Some(reason) = bands.iter().find_map(|band| band.validate().err()) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, reason));
+        }
+
+        let mut file = self.read()?;
+        file.presets.retain(|saved, _| !saved.eq_ignore_ascii_case(name));
+        file.presets.insert(name.to_string(), bands.to_vec());
+        self.write(&mut file)
+    }
+
+    /// Delete the saved preset called `name`; built-in presets cannot be deleted
+    pub fn delete(&self, name: &str) -> io::Result<()> {
+        if let Some(builtin) = Self::builtin_name(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("'{}' is a built-in preset and cannot be deleted", builtin),
+            ));
+        }
+        let mut file = self.read()?;
+        let before = file.presets.len();
+        file.presets.retain(|saved, _| !saved.eq_ignore_ascii_case(name));
+        if file.presets.len() == before {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no EQ preset named '{}'", name)));
+        }
+        self.write(&mut file)
+    }
+
+    fn builtin_name(name: &str) -> Option<&'static str> {
+        BUILTIN_PRESETS.iter()
+            .map(|(builtin, _)| *builtin)
+            .find(|builtin| builtin.eq_ignore_ascii_case(name.trim()))
+    }
+
+    fn read(&self) -> io::Result<PresetsFile> {
+        store::load_json(&self.path)
+    }
+
+    fn write(&self, file: &mut PresetsFile) -> io::Result<()> {
+        file.version = file.version.max(FORMAT_VERSION);
+        store::save_json(&self.path, file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn store(dir: &TempDir) -> EqPresetStore {
+        EqPresetStore::new(dir.path().join(EQ_PRESETS_FILE))
+    }
+
+    #[test]
+    fn test_saved_preset_reloads_with_identical_bands() {
+        let dir = TempDir::new().unwrap();
+        let bands = vec![
+            EqBand::new(EqFilter::LowShelf, 90.0, 4.5, 0.7),
+            EqBand::new(EqFilter::Peak, 2500.0, -3.25, 1.8),
+            EqBand::new(EqFilter::HighShelf, 12_000.0, 2.0, 0.5),
+        ];
+        store(&dir).save("Late Night", &bands).unwrap();
+
+        // A fresh store reads it back from disk
+        let preset = store(&dir).load("late night").unwrap();
+        assert_eq!(preset.name, "Late Night");
+        assert_eq!(preset.bands, bands);
+        assert!(!preset.builtin);
+        assert!(!dir.path().join("eq_presets.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_builtin_presets_are_listed_and_protected() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir);
+
+        let names: Vec<String> = store.list().unwrap().into_iter().map(|preset| preset.name).collect();
+        assert_eq!(names, vec!["Flat", "Bass-Boost", "Treble-Boost", "Loudness"]);
+        assert!(store.load("flat").unwrap().bands.is_empty());
+        assert!(BUILTIN_PRESETS.iter().flat_map(|(_, bands)| bands.iter()).all(|band| band.validate().is_ok()));
+
+        assert_eq!(store.delete("Bass-Boost").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(store.save("loudness", &[]).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(store.load("Bass-Boost").is_ok());
+    }
+
+    #[test]
+    fn test_delete_and_replace_saved_presets() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir);
+        let band = EqBand::new(EqFilter::Peak, 1000.0, 3.0, 1.0);
+
+        store.save("Vocal", &[band]).unwrap();
+        store.save("VOCAL", &[band, band]).unwrap();
+        let saved: Vec<EqPreset> = store.list().unwrap().into_iter().filter(|preset| !preset.builtin).collect();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].bands.len(), 2);
+
+        store.delete("vocal").unwrap();
+        assert_eq!(store.load("Vocal").unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(store.delete("Vocal").unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_invalid_bands_are_rejected() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir);
+        let loud = EqBand::new(EqFilter::Peak, 1000.0, 40.0, 1.0);
+        assert_eq!(store.save("Too Loud", &[loud]).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(store.save(" ", &[]).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(store.list().unwrap().len(), BUILTIN_PRESETS.len());
+    }
+}
@@ -0,0 +1,211 @@
+/*!
+Exclusive device access for bit-perfect output.
+
+- On macOS the device is "hogged" by setting `kAudioDevicePropertyHogMode` to our process id,
+  which stops other applications (and the system mixer) from using it. The returned
+  `ExclusiveGuard` hands the device back when dropped.
+- cpal 0.15 only opens WASAPI streams in shared mode and exposes no exclusive configuration,
+  so on Windows (and on other hosts) acquisition reports `Unsupported` and the caller falls back
+  to shared mode.
+*/
+
+use std::fmt;
+
+/// Why exclusive access could not be acquired
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub enum ExclusiveError {
+    /// The audio backend on this platform cannot open devices exclusively
+    Unsupported,
+    /// No device with this name was found
+    DeviceNotFound(String),
+    /// The OS refused the request (e.g. another process already owns the device)
+    Denied(String),
+}
+
+impl fmt::Display for ExclusiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExclusiveError::Unsupported => write!(f, "exclusive mode is not supported by this audio backend"),
+            ExclusiveError::DeviceNotFound(name) => write!(f, "device '{}' not found", name),
+            ExclusiveError::Denied(reason) => write!(f, "exclusive access denied: {}", reason),
+        }
+    }
+}
+
+/// Holds exclusive access to an output device until dropped
+#[derive(Debug)]
+pub struct ExclusiveGuard {
+    device_name: String,
+    #[cfg(target_os = "macos")]
+    device_id: coreaudio_sys::AudioDeviceID,
+}
+
+impl ExclusiveGuard {
+    /// Name of the device held exclusively
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+}
+
+/// Try to take exclusive access to the named output device
+pub fn acquire(device_name: &str) -> Result<ExclusiveGuard, ExclusiveError> {
+    #[cfg(target_os = "macos")]
+    {
+        let device_id = macos::find_device(device_name)
+            .ok_or_else(|| ExclusiveError::DeviceNotFound(device_name.to_string()))?;
+        macos::set_hog_mode(device_id, true)?;
+        Ok(ExclusiveGuard { device_name: device_name.to_string(), device_id })
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = device_name;
+        Err(ExclusiveError::Unsupported)
+    }
+}
+
+impl Drop for ExclusiveGuard {
+    fn drop(&mut self) {
+        #[cfg(target_os = "macos")]
+        {
+            if let Err(e) = macos::set_hog_mode(self.device_id, false) {
+                log::warn!("Failed to release exclusive access to {}: {}", self.device_name, e);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::ExclusiveError;
+    use crate::audio::device::device_names_match;
+    use core_foundation_sys::base::CFRelease;
+    use core_foundation_sys::string::{CFStringGetCString, CFStringRef};
+    use coreaudio_sys::{
+        kAudioDevicePropertyDeviceNameCFString, kAudioDevicePropertyHogMode,
+        kAudioHardwareNoError, kAudioHardwarePropertyDevices, kAudioObjectPropertyElementMaster,
+        kAudioObjectPropertyScopeGlobal, kAudioObjectSystemObject, kCFStringEncodingUTF8,
+        AudioDeviceID, AudioObjectGetPropertyData, AudioObjectGetPropertyDataSize,
+        AudioObjectPropertyAddress, AudioObjectSetPropertyData,
+    };
+    use std::ffi::CStr;
+    use std::mem;
+    use std::os::raw::c_char;
+    use std::ptr::null;
+
+    fn address(selector: u32) -> AudioObjectPropertyAddress {
+        AudioObjectPropertyAddress {
+            mSelector: selector,
+            mScope: kAudioObjectPropertyScopeGlobal,
+            mElement: kAudioObjectPropertyElementMaster,
+        }
+    }
+
+    /// Look up a CoreAudio device id by its display name
+    pub(super) fn find_device(name: &str) -> Option<AudioDeviceID> {
+        let property = address(kAudioHardwarePropertyDevices);
+        unsafe {
+            let mut data_size = 0u32;
+            let status = AudioObjectGetPropertyDataSize(kAudioObjectSystemObject, &property, 0, null(), &mut data_size);
+            if status != kAudioHardwareNoError as i32 {
+                return None;
+            }
+
+            let count = data_size as usize / mem::size_of::<AudioDeviceID>();
+            let mut devices: Vec<AudioDeviceID> = vec![0; count];
+            let status = AudioObjectGetPropertyData(
+                kAudioObjectSystemObject,
+                &property,
+                0,
+                null(),
+                &mut data_size,
+                devices.as_mut_ptr() as *mut _,
+            );
+            if status != kAudioHardwareNoError as i32 {
+                return None;
+            }
+
+            devices.into_iter().find(|&id| device_name(id).is_some_and(|known| device_names_match(&known, name)))
+        }
+    }
+
+    unsafe fn device_name(device_id: AudioDeviceID) -> Option<String> {
+        let property = address(kAudioDevicePropertyDeviceNameCFString);
+        let mut name: CFStringRef = null();
+        let mut data_size = mem::size_of::<CFStringRef>() as u32;
+        let status = AudioObjectGetPropertyData(
+            device_id,
+            &property,
+            0,
+            null(),
+            &mut data_size,
+            &mut name as *mut _ as *mut _,
+        );
+        if status != kAudioHardwareNoError as i32 || name.is_null() {
+            return None;
+        }
+
+        let mut buffer = [0 as c_char; 256];
+        let ok = CFStringGetCString(name, buffer.as_mut_ptr(), buffer.len() as _, kCFStringEncodingUTF8);
+        CFRelease(name as *const _);
+        if ok == 0 {
+            return None;
+        }
+        Some(CStr::from_ptr(buffer.as_ptr()).to_string_lossy().into_owned())
+    }
+
+    /// Take (or release) hog mode on a device, verifying the owner afterwards
+    pub(super) fn set_hog_mode(device_id: AudioDeviceID, take: bool) -> Result<(), ExclusiveError> {
+        let property = address(kAudioDevicePropertyHogMode);
+        let our_pid: libc::pid_t = unsafe { libc::getpid() };
+        let requested: libc::pid_t = if take { our_pid } else { -1 };
+
+        unsafe {
+            let status = AudioObjectSetPropertyData(
+                device_id,
+                &property,
+                0,
+                null(),
+                mem::size_of::<libc::pid_t>() as u32,
+                &requested as *const _ as *const _,
+            );
+            if status != kAudioHardwareNoError as i32 {
+                return Err(ExclusiveError::Denied(format!("OSStatus {}", status)));
+            }
+
+            let mut owner: libc::pid_t = -1;
+            let mut data_size = mem::size_of::<libc::pid_t>() as u32;
+            let status = AudioObjectGetPropertyData(
+                device_id,
+                &property,
+                0,
+                null(),
+                &mut data_size,
+                &mut owner as *mut _ as *mut _,
+            );
+            if status != kAudioHardwareNoError as i32 {
+                return Err(ExclusiveError::Denied(format!("OSStatus {}", status)));
+            }
+            if take && owner != our_pid {
+                return Err(ExclusiveError::Denied(format!("device is owned by process {}", owner)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_device_falls_back_without_panicking() {
+        match acquire("Nonexistent Virtual Device") {
+            Ok(_) => panic!("a nonexistent device cannot be acquired"),
+            Err(ExclusiveError::Unsupported) | Err(ExclusiveError::DeviceNotFound(_)) => {}
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+}
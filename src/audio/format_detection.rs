@@ -0,0 +1,120 @@
+/*!
+Audio format detection from file contents
+
+Files named `track.bin`, or with the wrong extension, are recognized by the
+magic bytes at the start of the file: `fLaC`, `RIFF`/`RF64` WAVE headers, an
+`ID3` tag or MPEG audio frame sync, `OggS`, an MP4 `ftyp` box and `wvpk`.
+*/
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use crate::models::AudioCodec;
+
+/// Bytes read from the start of a file for detection
+const HEADER_LEN: usize = 12;
+
+/// Codecs the decoders can play, in the order their extensions are checked
+const DECODABLE: [AudioCodec; 7] = [
+    AudioCodec::Flac,
+    AudioCodec::Wav,
+    AudioCodec::Alac,
+    AudioCodec::Mp3,
+    AudioCodec::OggVorbis,
+    AudioCodec::Aac,
+    AudioCodec::WavPack,
+];
+
+/// Detect the audio format of `path` from its first bytes
+pub fn detect_audio_format(path: &Path) -> Option<AudioCodec> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    File::open(path).ok()?.take(HEADER_LEN as u64).read_to_end(&mut header).ok()?;
+    detect_from_header(&header)
+}
+
+/// Detect the audio format from the first bytes of a file
+///
+/// An MP4 container cannot be told apart from its header alone; it is reported as AAC.
+pub fn detect_from_header(header: &[u8]) -> Option<AudioCodec> {
+    let is_wave = |riff: &[u8]| header.starts_with(riff) && header.get(8..12) == Some(b"WAVE");
+
+    if header.starts_with(b"fLaC") {
+        Some(AudioCodec::Flac)
+    } else if is_wave(b"RIFF") || is_wave(b"RF64") {
+        Some(AudioCodec::Wav)
+    } else if header.starts_with(b"OggS") {
+        Some(AudioCodec::OggVorbis)
+    } else if header.starts_with(b"wvpk") {
+        Some(AudioCodec::WavPack)
+    } else if header.get(4..8) == Some(b"ftyp") {
+        Some(AudioCodec::Aac)
+    } else if header.starts_with(b"ID3") || is_mpeg_frame_sync(header) {
+        Some(AudioCodec::Mp3)
+    } else {
+        None
+    }
+}
+
+/// Extension to pick a decoder by: the file's own when it names a playable format, otherwise one matching its contents
+///
+/// Falls back to the file's own extension when the contents are not recognized either.
+pub fn decoder_extension(path: &Path) -> Option<String> {
+    let extension = path.extension().and_then(|ext| ext.to_str()).map(str::to_lowercase);
+    match extension {
+        Some(extension) if DECODABLE.iter().any(|codec| codec.extensions().contains(&extension.as_str())) => Some(extension),
+        _ => detect_audio_format(path).map(|codec| codec.extensions()[0].to_string()).or(extension),
+    }
+}
+
+/// 11-bit MPEG frame sync with a valid layer, which rules out ADTS AAC (layer bits 00)
+fn is_mpeg_frame_sync(header: &[u8]) -> bool {
+    matches!(header, [0xFF, second, ..] if second & 0xE0 == 0xE0 && second & 0x06 != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect_from_magic_bytes() {
+        assert_eq!(detect_from_header(b"fLaC\x00\x00\x00\x22\x10\x00\x10\x00"), Some(AudioCodec::Flac));
+        assert_eq!(detect_from_header(b"RIFF\x24\x08\x00\x00WAVE"), Some(AudioCodec::Wav));
+        assert_eq!(detect_from_header(b"RF64\xff\xff\xff\xffWAVE"), Some(AudioCodec::Wav));
+        assert_eq!(detect_from_header(b"ID3\x04\x00\x00\x00\x00\x00\x21TI"), Some(AudioCodec::Mp3));
+        assert_eq!(detect_from_header(&[0xFF, 0xFB, 0x90, 0x64, 0x00]), Some(AudioCodec::Mp3));
+        assert_eq!(detect_from_header(b"OggS\x00\x02\x00\x00\x00\x00\x00\x00"), Some(AudioCodec::OggVorbis));
+        assert_eq!(detect_from_header(b"\x00\x00\x00\x20ftypM4A "), Some(AudioCodec::Aac));
+        assert_eq!(detect_from_header(b"wvpk\x2a\x00\x00\x00\x10\x04\x00\x00"), Some(AudioCodec::WavPack));
+    }
+
+    #[test]
+    fn test_unknown_headers_are_rejected() {
+        assert_eq!(detect_from_header(b""), None);
+        assert_eq!(detect_from_header(b"fLa"), None);
+        assert_eq!(detect_from_header(b"RIFF\x24\x08\x00\x00AVI "), None);
+        assert_eq!(detect_from_header(b"%PDF-1.7\n%\xe2\xe3"), None);
+        // ADTS AAC shares the sync word but has layer bits 00
+        assert_eq!(detect_from_header(&[0xFF, 0xF1, 0x50, 0x80]), None);
+    }
+
+    #[test]
+    fn test_decoder_extension_falls_back_to_contents() {
+        let temp_dir = TempDir::new().unwrap();
+        let renamed = temp_dir.path().join("track.bin");
+        fs::write(&renamed, b"fLaC\x00\x00\x00\x22").unwrap();
+        let unnamed = temp_dir.path().join("track");
+        fs::write(&unnamed, b"OggS\x00\x02").unwrap();
+        let named = temp_dir.path().join("track.MP3");
+        fs::write(&named, b"fLaC").unwrap();
+
+        assert_eq!(detect_audio_format(&renamed), Some(AudioCodec::Flac));
+        assert_eq!(decoder_extension(&renamed).as_deref(), Some("flac"));
+        assert_eq!(decoder_extension(&unnamed).as_deref(), Some("ogg"));
+        // A known extension is trusted
+        assert_eq!(decoder_extension(&named).as_deref(), Some("mp3"));
+        assert_eq!(decoder_extension(&temp_dir.path().join("missing.bin")).as_deref(), Some("bin"));
+        assert_eq!(decoder_extension(&temp_dir.path().join("missing")), None);
+    }
+}
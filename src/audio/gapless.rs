@@ -3,7 +3,7 @@ use std::time::Duration;
 use crate::audio::{AudioEngine, AudioDecoder};
 use crate::audio::engine::AudioEngineImpl;
 use crate::queue::QueueManager;
-use crate::models::{TrackInfo, PlaybackState};
+use crate::models::{AudioBuffer, TrackInfo, PlaybackState};
 use crate::error::AudioError;
 
 /// Manages gapless playback between tracks in a queue
@@ -208,6 +208,11 @@ impl GaplessManager {
                     .map_err(|e| AudioError::StreamError(format!("Failed to create OGG decoder: {}", e)))?;
                 Ok(Box::new(decoder))
             }
+            "wv" => {
+                let decoder = crate::audio::decoders::WavPackDecoder::new(&track.path)
+                    .map_err(|e| AudioError::StreamError(format!("Failed to create WavPack decoder: {}", e)))?;
+                Ok(Box::new(decoder))
+            }
             _ => {
                 Err(AudioError::StreamError(format!("Unsupported audio format: {}", extension)))
             }
@@ -227,7 +232,7 @@ impl GaplessManager {
     /// Clean up resources
     pub fn cleanup(&mut self) -> Result<(), AudioError> {
         if let Ok(mut engine) = self.audio_engine.lock() {
-            engine.stop()?;
+            engine.unload()?;
         }
         
         self.current_track = None;
@@ -237,6 +242,62 @@ impl GaplessManager {
     }
 }
 
+/// Drops encoder delay and padding so consecutive tracks join without silence
+pub struct GaplessTrimmer {
+    delay_frames: u64,
+    padding_frames: usize,
+    /// Lead-in frames still to be dropped
+    skip_frames: u64,
+    /// The most recent `padding_frames` frames, held back until more audio follows
+    pending: Vec<f32>,
+}
+
+impl GaplessTrimmer {
+    pub fn new(delay_frames: u32, padding_frames: u32) -> Self {
+        Self {
+            delay_frames: delay_frames as u64,
+            padding_frames: padding_frames as usize,
+            skip_frames: delay_frames as u64,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Trimmer for a freshly opened decoder
+    pub fn for_decoder(decoder: &dyn AudioDecoder) -> Self {
+        Self::new(decoder.encoder_delay_frames(), decoder.encoder_padding_frames())
+    }
+
+    /// Trim a decoded buffer. The trailing padding is held back, so whatever is still pending
+    /// when the decoder reaches the end of the file is the padding and is never emitted.
+    pub fn process(&mut self, mut buffer: AudioBuffer) -> AudioBuffer {
+        if self.skip_frames == 0 && self.padding_frames == 0 {
+            return buffer;
+        }
+
+        let channels = buffer.channels.max(1) as usize;
+        let skip = (self.skip_frames as usize).min(buffer.frames);
+        self.skip_frames -= skip as u64;
+        buffer.samples.drain(..(skip * channels).min(buffer.samples.len()));
+
+        if self.padding_frames > 0 {
+            let mut samples = std::mem::take(&mut self.pending);
+            samples.extend_from_slice(&buffer.samples);
+            let held = (self.padding_frames * channels).min(samples.len());
+            self.pending = samples.split_off(samples.len() - held);
+            buffer.samples = samples;
+        }
+
+        buffer.frames = buffer.samples.len() / channels;
+        buffer
+    }
+
+    /// Reset after a seek; the lead-in is only dropped when seeking back to the start
+    pub fn seek(&mut self, position: Duration) {
+        self.pending.clear();
+        self.skip_frames = if position.is_zero() { self.delay_frames } else { 0 };
+    }
+}
+
 
 
 #[cfg(test)]
@@ -322,6 +383,42 @@ mod tests {
         assert!(track2.path.to_string_lossy().ends_with(".mp3"));
     }
 
+    #[test]
+    fn test_trimmer_drops_exactly_delay_and_padding() {
+        // LAME's iTunSMPB: 2112 delay, 448 padding, 3200 real frames = 5 MP3 frames of 1152
+        let (delay, padding, total) = (2112u32, 448u32, 3200usize);
+        let mut trimmer = GaplessTrimmer::new(delay, padding);
+
+        let mut output = Vec::new();
+        let mut next = 0.0f32;
+        for _ in 0..5 {
+            let samples: Vec<f32> = (0..1152 * 2).map(|i| next + (i / 2) as f32).collect();
+            next += 1152.0;
+            let buffer = AudioBuffer { samples, channels: 2, sample_rate: 44100, frames: 1152 };
+            let trimmed = trimmer.process(buffer);
+            assert_eq!(trimmed.samples.len(), trimmed.frames * 2);
+            output.extend(trimmed.samples);
+        }
+
+        assert_eq!(output.len() / 2, total);
+        // First emitted frame is the first frame after the encoder delay
+        assert_eq!(output[0], delay as f32);
+        assert_eq!(output[output.len() - 1], (delay as usize + total - 1) as f32);
+    }
+
+    #[test]
+    fn test_trimmer_passthrough_and_seek() {
+        let buffer = AudioBuffer { samples: vec![0.5; 20], channels: 2, sample_rate: 44100, frames: 10 };
+        let mut passthrough = GaplessTrimmer::new(0, 0);
+        assert_eq!(passthrough.process(buffer.clone()).frames, 10);
+
+        let mut trimmer = GaplessTrimmer::new(4, 0);
+        trimmer.seek(Duration::from_secs(5));
+        assert_eq!(trimmer.process(buffer.clone()).frames, 10);
+        trimmer.seek(Duration::ZERO);
+        assert_eq!(trimmer.process(buffer).frames, 6);
+    }
+
     #[test]
     fn test_gapless_state_management() {
         // Test basic state management without audio engine dependency
@@ -0,0 +1,196 @@
+/*!
+Peak and RMS level metering for the `watch` view.
+
+- The decoder thread feeds every buffer it writes to the ring buffer through `LevelMeter::process`.
+  The window is split into ten 10 ms slots; each write updates the newest slot and publishes the
+  peak and RMS over all ten as atomics, so readers never block the decoder.
+- The meter reflects decoded audio, which is up to `target_buffer_ms` ahead of what is heard.
+- Mono sources report the same level on both sides; with more than two channels only the first
+  two are metered.
+*/
+
+use crate::models::ChannelLevels;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Length of the rolling window the levels are measured over
+pub const LEVEL_WINDOW: Duration = Duration::from_millis(100);
+
+/// Slots the window is divided into; the oldest one is dropped as a new one starts
+const SLOTS: usize = 10;
+
+/// Peak and sum of squares of one channel over one slot
+#[derive(Debug, Clone, Copy, Default)]
+struct Slot {
+    peak: f32,
+    sum_squares: f64,
+}
+
+#[derive(Debug)]
+struct Window {
+    /// Left and right slots, newest at `current`
+    slots: [[Slot; 2]; SLOTS],
+    current: usize,
+    frames_in_slot: usize,
+    /// Frames held by each completed slot, and by the newest one so far
+    slot_frames: [usize; SLOTS],
+}
+
+impl Window {
+    fn new() -> Self {
+        Self {
+            slots: [[Slot::default(); 2]; SLOTS],
+            current: 0,
+            frames_in_slot: 0,
+            slot_frames: [0; SLOTS],
+        }
+    }
+}
+
+/// Rolling peak and RMS levels of the left and right channels
+#[derive(Debug)]
+pub struct LevelMeter {
+    channels: usize,
+    frames_per_slot: usize,
+    window: Mutex<Window>,
+    peak: [AtomicU32; 2],
+    rms: [AtomicU32; 2],
+}
+
+impl LevelMeter {
+    pub fn new(channels: u16, sample_rate: u32) -> Self {
+        let window_frames = (LEVEL_WINDOW.as_secs_f64() * sample_rate as f64) as usize;
+        Self {
+            channels: channels.max(1) as usize,
+            frames_per_slot: (window_frames / SLOTS).max(1),
+            window: Mutex::new(Window::new()),
+            peak: [AtomicU32::new(0), AtomicU32::new(0)],
+            rms: [AtomicU32::new(0), AtomicU32::new(0)],
+        }
+    }
+
+    /// Add interleaved samples to the window and publish the new levels
+    pub fn process(&self, samples: &[f32]) {
+        // Only the decoder thread writes, so the lock is never contended
+        let Ok(mut window) = self.window.try_lock() else {
+            return;
+        };
+        for frame in samples.chunks_exact(self.channels) {
+            let current = window.current;
+            let right = if self.channels > 1 { frame[1] } else { frame[0] };
+            for (slot, sample) in window.slots[current].iter_mut().zip([frame[0], right]) {
+                slot.peak = slot.peak.max(sample.abs());
+                slot.sum_squares += (sample as f64) * (sample as f64);
+            }
+            window.frames_in_slot += 1;
+            window.slot_frames[current] = window.frames_in_slot;
+
+            if window.frames_in_slot == self.frames_per_slot {
+                let next = (current + 1) % SLOTS;
+                window.current = next;
+                window.frames_in_slot = 0;
+                window.slots[next] = [Slot::default(); 2];
+                window.slot_frames[next] = 0;
+            }
+        }
+        self.publish(&window);
+    }
+
+    fn publish(&self, window: &Window) {
+        let frames: usize = window.slot_frames.iter().sum();
+        for channel in 0..2 {
+            let slots = window.slots.iter().map(|slots| slots[channel]);
+            let peak = slots.clone().map(|slot| slot.peak).fold(0.0f32, f32::max);
+            let sum_squares: f64 = slots.map(|slot| slot.sum_squares).sum();
+            let rms = if frames > 0 { (sum_squares / frames as f64).sqrt() as f32 } else { 0.0 };
+            self.peak[channel].store(peak.to_bits(), Ordering::Relaxed);
+            self.rms[channel].store(rms.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// Forget the window, e.g. after a seek or stop
+    pub fn reset(&self) {
+        if let Ok(mut window) = self.window.lock() {
+            *window = Window::new();
+        }
+        for level in self.peak.iter().chain(&self.rms) {
+            level.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn peak_left(&self) -> f32 {
+        f32::from_bits(self.peak[0].load(Ordering::Relaxed))
+    }
+
+    pub fn peak_right(&self) -> f32 {
+        f32::from_bits(self.peak[1].load(Ordering::Relaxed))
+    }
+
+    pub fn rms_left(&self) -> f32 {
+        f32::from_bits(self.rms[0].load(Ordering::Relaxed))
+    }
+
+    pub fn rms_right(&self) -> f32 {
+        f32::from_bits(self.rms[1].load(Ordering::Relaxed))
+    }
+
+    /// All four levels at once, for the player status
+    pub fn levels(&self) -> ChannelLevels {
+        ChannelLevels {
+            peak_left: self.peak_left(),
+            peak_right: self.peak_right(),
+            rms_left: self.rms_left(),
+            rms_right: self.rms_right(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    fn sine(frames: usize, channels: usize, amplitude: f32) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|frame| {
+                let value = amplitude * (2.0 * std::f32::consts::PI * 1_000.0 * frame as f32 / RATE as f32).sin();
+                std::iter::repeat_n(value, channels)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_full_scale_sine_peaks_at_one() {
+        let meter = LevelMeter::new(1, RATE);
+        meter.process(&sine(1024, 1, 1.0));
+
+        assert!((meter.peak_left() - 1.0).abs() < 0.01);
+        assert_eq!(meter.peak_left(), meter.peak_right(), "mono is shown on both sides");
+        assert!((meter.rms_left() - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_levels_roll_off_after_the_window() {
+        let meter = LevelMeter::new(2, RATE);
+        let mut samples = sine(4_800, 2, 0.5);
+        // Right channel silent
+        samples.iter_mut().skip(1).step_by(2).for_each(|sample| *sample = 0.0);
+        meter.process(&samples);
+        assert!((meter.peak_left() - 0.5).abs() < 0.01);
+        assert_eq!(meter.peak_right(), 0.0);
+
+        // Half a window of silence lowers the RMS, a full window clears the peak
+        let rms = meter.rms_left();
+        meter.process(&vec![0.0; 2_400 * 2]);
+        assert!((meter.peak_left() - 0.5).abs() < 0.01);
+        assert!(meter.rms_left() < rms * 0.75 && meter.rms_left() > rms * 0.5);
+        meter.process(&vec![0.0; 2_400 * 2]);
+        assert_eq!(meter.peak_left(), 0.0);
+
+        meter.process(&sine(480, 2, 1.0));
+        meter.reset();
+        assert_eq!(meter.levels(), ChannelLevels::default());
+    }
+}
@@ -0,0 +1,156 @@
+/*!
+Clipping detection and an optional soft limiter for the output path.
+
+- `ClipMonitor` is shared between the engine and the output callback. The callback runs every
+  block through `ClipMonitor::process`, which applies volume, counts samples that would leave
+  the ±1.0 range, and (when enabled) soft-limits them instead of letting them hard-clip.
+- Everything is atomic so the audio callback never takes a lock or allocates.
+- Counters are per track: the engine calls `reset()` whenever a new track starts.
+*/
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Level above which the soft limiter starts compressing
+const LIMITER_THRESHOLD: f32 = 0.9;
+
+/// Shared clipping statistics and limiter switch for the output callback
+#[derive(Debug, Default)]
+pub struct ClipMonitor {
+    clipped_samples: AtomicU64,
+    limiter_enabled: AtomicBool,
+    clip_reported: AtomicBool,
+}
+
+impl ClipMonitor {
+    /// Create a monitor with the limiter disabled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply volume in place, then limit or count clipped samples
+    pub fn process(&self, samples: &mut [f32], volume: f32) {
+        let limit = self.limiter_enabled.load(Ordering::Relaxed);
+        let mut clipped = 0u64;
+
+        for sample in samples.iter_mut() {
+            let scaled = *sample * volume;
+            if limit {
+                *sample = soft_limit(scaled);
+            } else {
+                if scaled.abs() > 1.0 {
+                    clipped += 1;
+                }
+                *sample = scaled;
+            }
+        }
+
+        if clipped > 0 {
+            self.clipped_samples.fetch_add(clipped, Ordering::Relaxed);
+        }
+    }
+
+    /// Number of samples clipped since the last reset
+    pub fn clipped_samples(&self) -> u64 {
+        self.clipped_samples.load(Ordering::Relaxed)
+    }
+
+    /// Enable or disable the soft limiter
+    pub fn set_limiter_enabled(&self, enabled: bool) {
+        self.limiter_enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Check if the soft limiter is enabled
+    pub fn is_limiter_enabled(&self) -> bool {
+        self.limiter_enabled.load(Ordering::Relaxed)
+    }
+
+    /// Reset per-track counters (call on track change)
+    pub fn reset(&self) {
+        self.clipped_samples.store(0, Ordering::Relaxed);
+        self.clip_reported.store(false, Ordering::Relaxed);
+    }
+
+    /// Return the clip count the first time clipping is seen on the current track
+    pub fn take_first_clip(&self) -> Option<u64> {
+        let clipped = self.clipped_samples();
+        if clipped > 0 && !self.clip_reported.swap(true, Ordering::Relaxed) {
+            Some(clipped)
+        } else {
+            None
+        }
+    }
+}
+
+/// Soft-knee limiter: linear below the threshold, tanh-compressed above, never beyond ±1.0
+pub fn soft_limit(sample: f32) -> f32 {
+    let magnitude = sample.abs();
+    if magnitude <= LIMITER_THRESHOLD {
+        return sample;
+    }
+
+    let headroom = 1.0 - LIMITER_THRESHOLD;
+    let over = (magnitude - LIMITER_THRESHOLD) / headroom;
+    (LIMITER_THRESHOLD + headroom * over.tanh()).copysign(sample)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hot_buffer() -> Vec<f32> {
+        // Sine at +6 dB: roughly a third of the samples exceed full scale
+        (0..1000)
+            .map(|n| 2.0 * (2.0 * std::f32::consts::PI * n as f32 / 100.0).sin())
+            .collect()
+    }
+
+    #[test]
+    fn counts_clipped_samples_in_hot_buffer() {
+        let monitor = ClipMonitor::new();
+        let mut buffer = hot_buffer();
+        let expected = buffer.iter().filter(|s| s.abs() > 1.0).count() as u64;
+
+        monitor.process(&mut buffer, 1.0);
+
+        assert!(expected > 0);
+        assert_eq!(monitor.clipped_samples(), expected);
+        assert_eq!(monitor.take_first_clip(), Some(expected));
+        assert_eq!(monitor.take_first_clip(), None, "clipping is reported once per track");
+
+        monitor.reset();
+        assert_eq!(monitor.clipped_samples(), 0);
+    }
+
+    #[test]
+    fn volume_is_applied_before_detection() {
+        let monitor = ClipMonitor::new();
+        let mut buffer = hot_buffer();
+
+        monitor.process(&mut buffer, 0.5);
+
+        assert_eq!(monitor.clipped_samples(), 0);
+        assert!(buffer.iter().all(|s| s.abs() <= 1.0));
+    }
+
+    #[test]
+    fn limiter_keeps_output_within_full_scale() {
+        let monitor = ClipMonitor::new();
+        monitor.set_limiter_enabled(true);
+        let mut buffer = hot_buffer();
+
+        monitor.process(&mut buffer, 1.0);
+
+        let peak = buffer.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+        assert!(peak <= 1.0, "limited peak {} exceeds full scale", peak);
+        assert!(peak > LIMITER_THRESHOLD);
+        assert_eq!(monitor.clipped_samples(), 0);
+    }
+
+    #[test]
+    fn soft_limit_is_transparent_below_threshold() {
+        assert_eq!(soft_limit(0.5), 0.5);
+        assert_eq!(soft_limit(-0.9), -0.9);
+        assert!(soft_limit(10.0) <= 1.0);
+        assert!(soft_limit(-10.0) >= -1.0);
+    }
+}
@@ -0,0 +1,366 @@
+/*!
+EBU R128 loudness measurement for `scan-loudness`
+
+- `LoudnessMeter` follows ITU-R BS.1770-4: every channel goes through the two-stage
+  K-weighting filter, the weighted mean squares are collected per 100 ms, and
+  400 ms blocks overlapping by 75% are gated at -70 LUFS and then at 10 LU below
+  the loudness of the blocks that passed the first gate.
+- True peak is read from the signal oversampled 4x below 96 kHz and 2x below
+  192 kHz with a windowed-sinc interpolator, as BS.1770 Annex 2 describes.
+- The ReplayGain 2.0 track gain is the distance to a -18 LUFS reference.
+*/
+
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde::{Deserialize, Serialize};
+use crate::audio::AudioDecoder;
+use crate::error::DecodeError;
+
+/// Loudness ReplayGain 2.0 normalizes to
+pub const REFERENCE_LUFS: f64 = -18.0;
+
+/// Blocks quieter than this never count
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+
+/// Blocks this far below the absolute-gated loudness are dropped
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+/// 100 ms steps in one 400 ms gating block
+const BLOCK_STEPS: usize = 4;
+
+/// Input samples on each side of an interpolated true-peak point
+const HALF_TAPS: usize = 8;
+
+/// Measured loudness of one file
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Loudness {
+    /// Gated integrated loudness in LUFS
+    pub integrated_lufs: f64,
+    /// Largest absolute value of the oversampled signal, 1.0 being full scale
+    pub true_peak: f64,
+}
+
+impl Loudness {
+    /// ReplayGain track gain in dB: the change that brings the file to `REFERENCE_LUFS`
+    pub fn track_gain_db(&self) -> f64 {
+        REFERENCE_LUFS - self.integrated_lufs
+    }
+
+    /// True peak in dBTP
+    pub fn true_peak_dbtp(&self) -> f64 {
+        20.0 * self.true_peak.log10()
+    }
+}
+
+/// One K-weighting stage, transposed direct form II
+#[derive(Debug, Clone, Copy)]
+struct Stage {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Stage {
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b0 * input + self.z1;
+        self.z1 = self.b1 * input - self.a1 * output + self.z2;
+        self.z2 = self.b2 * input - self.a2 * output;
+        output
+    }
+}
+
+/// The BS.1770 high shelf and high pass, derived for `sample_rate` so rates other than 48 kHz match the spec's response
+fn k_weighting(sample_rate: f64) -> [Stage; 2] {
+    let shelf = {
+        let k = (PI * 1681.974450955533 / sample_rate).tan();
+        let q = 0.7071752369554196;
+        let vh = 10f64.powf(3.999843853973347 / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        Stage {
+            b0: (vh + vb * k / q + k * k) / a0,
+            b1: 2.0 * (k * k - vh) / a0,
+            b2: (vh - vb * k / q + k * k) / a0,
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    };
+    let high_pass = {
+        let k = (PI * 38.13547087602444 / sample_rate).tan();
+        let q = 0.5003270373238773;
+        let a0 = 1.0 + k / q + k * k;
+        Stage {
+            b0: 1.0,
+            b1: -2.0,
+            b2: 1.0,
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    };
+    [shelf, high_pass]
+}
+
+/// BS.1770 channel weight: surrounds count 1.41, the LFE of a 5.1 stream not at all
+fn channel_weight(channels: usize, index: usize) -> f64 {
+    match (channels, index) {
+        (6, 3) => 0.0,
+        (5, 3 | 4) | (6, 4 | 5) => 1.41,
+        _ => 1.0,
+    }
+}
+
+fn lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+fn power(lufs: f64) -> f64 {
+    10f64.powf((lufs + 0.691) / 10.0)
+}
+
+/// Peak of a signal and of the points a windowed-sinc interpolator puts between its samples
+#[derive(Debug)]
+struct TruePeak {
+    /// Interpolation taps for each point between two samples, newest sample first
+    phases: Vec<[f64; 2 * HALF_TAPS]>,
+    /// The last `2 * HALF_TAPS` samples of each channel, newest at `next - 1`
+    history: Vec<[f64; 2 * HALF_TAPS]>,
+    next: usize,
+    peak: f64,
+}
+
+impl TruePeak {
+    fn new(channels: usize, sample_rate: u32) -> Self {
+        let factor = match sample_rate {
+            0..96_000 => 4,
+            96_000..192_000 => 2,
+            _ => 1,
+        };
+        let phases = (1..factor)
+            .map(|phase| {
+                let mut taps = [0.0; 2 * HALF_TAPS];
+                for (k, tap) in taps.iter_mut().enumerate() {
+                    // Distance from the sample `k` back to the point being interpolated
+                    let distance = k as f64 - HALF_TAPS as f64 + phase as f64 / factor as f64;
+                    let sinc = (PI * distance).sin() / (PI * distance);
+                    let window = (PI * distance / (2.0 * HALF_TAPS as f64)).cos().powi(2);
+                    *tap = sinc * window;
+                }
+                let sum: f64 = taps.iter().sum();
+                taps.map(|tap| tap / sum)
+            })
+            .collect();
+        Self { phases, history: vec![[0.0; 2 * HALF_TAPS]; channels], next: 0, peak: 0.0 }
+    }
+
+    fn process(&mut self, frame: &[f32]) {
+        let len = 2 * HALF_TAPS;
+        for (history, &sample) in self.history.iter_mut().zip(frame) {
+            history[self.next] = sample as f64;
+            self.peak = self.peak.max(sample.abs() as f64);
+            for taps in &self.phases {
+                let value: f64 = taps.iter().enumerate()
+                    .map(|(k, tap)| tap * history[(self.next + len - k) % len])
+                    .sum();
+                self.peak = self.peak.max(value.abs());
+            }
+        }
+        self.next = (self.next + 1) % len;
+    }
+}
+
+/// Integrated loudness and true peak of an interleaved stream
+#[derive(Debug)]
+pub struct LoudnessMeter {
+    channels: usize,
+    weights: Vec<f64>,
+    filters: Vec<[Stage; 2]>,
+    true_peak: TruePeak,
+    step_frames: usize,
+    frames_in_step: usize,
+    /// Channel-weighted sum of squares of the current step
+    step_sum: f64,
+    /// Mean power of the last steps, enough for one block
+    recent: VecDeque<f64>,
+    /// Mean power of every complete block so far
+    blocks: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    pub fn new(channels: u16, sample_rate: u32) -> Self {
+        let channels = channels.max(1) as usize;
+        Self {
+            channels,
+            weights: (0..channels).map(|index| channel_weight(channels, index)).collect(),
+            filters: vec![k_weighting(sample_rate.max(1) as f64); channels],
+            true_peak: TruePeak::new(channels, sample_rate),
+            step_frames: (sample_rate as usize / 10).max(1),
+            frames_in_step: 0,
+            step_sum: 0.0,
+            recent: VecDeque::with_capacity(BLOCK_STEPS),
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Feed interleaved samples
+    pub fn process(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (channel, &sample) in frame.iter().enumerate() {
+                let filtered = self.filters[channel].iter_mut().fold(sample as f64, |value, stage| stage.process(value));
+                self.step_sum += self.weights[channel] * filtered * filtered;
+            }
+            self.true_peak.process(frame);
+
+            self.frames_in_step += 1;
+            if self.frames_in_step == self.step_frames {
+                if self.recent.len() == BLOCK_STEPS {
+                    self.recent.pop_front();
+                }
+                self.recent.push_back(self.step_sum / self.step_frames as f64);
+                if self.recent.len() == BLOCK_STEPS {
+                    self.blocks.push(self.recent.iter().sum::<f64>() / BLOCK_STEPS as f64);
+                }
+                self.step_sum = 0.0;
+                self.frames_in_step = 0;
+            }
+        }
+    }
+
+    /// Loudness of everything fed so far; `None` when no block rises above the absolute gate,
+    /// as for silence or audio shorter than 400 ms
+    pub fn finish(&self) -> Option<Loudness> {
+        let absolute = power(ABSOLUTE_GATE_LUFS);
+        let gated: Vec<f64> = self.blocks.iter().copied().filter(|&block| block > absolute).collect();
+        if gated.is_empty() {
+            return None;
+        }
+        let relative = gated.iter().sum::<f64>() / gated.len() as f64 * 10f64.powf(RELATIVE_GATE_LU / 10.0);
+        let passed: Vec<f64> = gated.into_iter().filter(|&block| block > relative).collect();
+        let mean = passed.iter().sum::<f64>() / passed.len() as f64;
+        Some(Loudness { integrated_lufs: lufs(mean), true_peak: self.true_peak.peak })
+    }
+}
+
+/// Decode everything `decoder` produces and measure it
+///
+/// Stops early once `cancel` is set, measuring what was decoded so far; `Ok(None)` when
+/// nothing rises above the absolute gate.
+pub fn measure(decoder: &mut dyn AudioDecoder, cancel: &AtomicBool) -> Result<Option<Loudness>, DecodeError> {
+    let mut meter = LoudnessMeter::new(decoder.channels(), decoder.sample_rate());
+    while !cancel.load(Ordering::Relaxed) {
+        match decoder.decode_next()? {
+            Some(buffer) => meter.process(&buffer.samples),
+            None => break,
+        }
+    }
+    Ok(meter.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `seconds` of a sine at `frequency` with peak `amplitude`, the same on every channel
+    fn sine(channels: usize, sample_rate: u32, frequency: f64, amplitude: f64, seconds: f64) -> Vec<f32> {
+        let frames = (sample_rate as f64 * seconds) as usize;
+        (0..frames)
+            .flat_map(|n| {
+                let value = (amplitude * (2.0 * PI * frequency * n as f64 / sample_rate as f64).sin()) as f32;
+                std::iter::repeat_n(value, channels)
+            })
+            .collect()
+    }
+
+    fn measure(channels: u16, sample_rate: u32, samples: &[f32]) -> Option<Loudness> {
+        let mut meter = LoudnessMeter::new(channels, sample_rate);
+        meter.process(samples);
+        meter.finish()
+    }
+
+    fn db(value: f64) -> f64 {
+        10f64.powf(value / 20.0)
+    }
+
+    #[test]
+    fn test_stereo_sine_matches_tech_3341() {
+        // EBU Tech 3341 case 1 and 2: a stereo 1 kHz sine at -23 and -33 dBFS reads -23 and -33 LUFS
+        for rate in [44_100, 48_000, 96_000] {
+            for level in [-23.0, -33.0] {
+                let loudness = measure(2, rate, &sine(2, rate, 1000.0, db(level), 5.0)).unwrap();
+                assert!((loudness.integrated_lufs - level).abs() < 0.1, "{} Hz at {} dBFS read {}", rate, level, loudness.integrated_lufs);
+            }
+        }
+        // One channel carries half the power
+        let loudness = measure(1, 48_000, &sine(1, 48_000, 1000.0, db(-20.0), 3.0)).unwrap();
+        assert!((loudness.integrated_lufs + 23.0).abs() < 0.1);
+        assert!((loudness.track_gain_db() - 5.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_gating_drops_silence_and_quiet_passages() {
+        // Silence between the tones is below the absolute gate
+        let tone = sine(2, 48_000, 1000.0, db(-23.0), 3.0);
+        let mut samples = tone.clone();
+        samples.extend(vec![0.0; 48_000 * 2 * 3]);
+        samples.extend(&tone);
+        let loudness = measure(2, 48_000, &samples).unwrap();
+        // Blocks straddling the edges of the gap are partly silent and pull it down a little
+        assert!(loudness.integrated_lufs < -23.0 && loudness.integrated_lufs > -23.5, "{}", loudness.integrated_lufs);
+
+        // Tech 3341 case 3: -36, -23 and -36 dBFS tones read -23 LUFS because the quiet ones fall below the relative gate
+        let mut samples = sine(2, 48_000, 1000.0, db(-36.0), 2.0);
+        samples.extend(sine(2, 48_000, 1000.0, db(-23.0), 20.0));
+        samples.extend(sine(2, 48_000, 1000.0, db(-36.0), 2.0));
+        let loudness = measure(2, 48_000, &samples).unwrap();
+        assert!((loudness.integrated_lufs + 23.0).abs() < 0.1);
+
+        assert_eq!(measure(2, 48_000, &vec![0.0; 48_000 * 2]), None);
+        assert_eq!(measure(2, 48_000, &sine(2, 48_000, 1000.0, 0.5, 0.3)), None);
+    }
+
+    #[test]
+    fn test_noise_loudness_follows_its_level() {
+        // Uniform white noise from a fixed LCG; 6 dB more level reads 6 LU louder
+        let mut state = 0x1234_5678u32;
+        let noise: Vec<f32> = (0..48_000 * 2 * 4)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 8) as f32 / (1 << 23) as f32 - 1.0
+            })
+            .collect();
+        let quiet: Vec<f32> = noise.iter().map(|sample| sample * 0.25).collect();
+        let loud: Vec<f32> = noise.iter().map(|sample| sample * 0.5).collect();
+
+        let quiet = measure(2, 48_000, &quiet).unwrap();
+        let loud = measure(2, 48_000, &loud).unwrap();
+        assert!((loud.integrated_lufs - quiet.integrated_lufs - 6.02).abs() < 0.05);
+        // K-weighting lifts white noise, which has most of its power above the shelf, over its plain RMS
+        let rms_dbfs = 10.0 * (0.25f64.powi(2) / 3.0 * 2.0).log10();
+        assert!(quiet.integrated_lufs > rms_dbfs && quiet.integrated_lufs < rms_dbfs + 4.0, "{}", quiet.integrated_lufs);
+    }
+
+    #[test]
+    fn test_true_peak_finds_peaks_between_samples() {
+        // A quarter-rate sine sampled 45° off its peaks: samples reach -3 dB, the waveform 0 dB
+        let samples: Vec<f32> = (0..48_000)
+            .map(|n| (PI / 2.0 * n as f64 + PI / 4.0).sin() as f32 * 0.5)
+            .collect();
+        let sample_peak = samples.iter().fold(0f32, |peak, sample| peak.max(sample.abs())) as f64;
+        assert!((20.0 * (sample_peak / 0.5).log10() + 3.01).abs() < 0.01);
+
+        let loudness = measure(1, 48_000, &samples).unwrap();
+        assert!((loudness.true_peak_dbtp() - 20.0 * 0.5f64.log10()).abs() < 0.5, "{}", loudness.true_peak_dbtp());
+
+        // Low frequencies have no inter-sample overs
+        let loudness = measure(2, 48_000, &sine(2, 48_000, 100.0, 0.5, 2.0)).unwrap();
+        assert!((loudness.true_peak - 0.5).abs() < 0.005);
+    }
+}
@@ -1,13 +1,49 @@
-use std::path::Path;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+use symphonia::core::codecs::VerificationCheck;
 use symphonia::core::formats::FormatOptions;
 use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, Value};
-use symphonia::core::probe::Hint;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, StandardVisualKey, Tag, Value};
+use symphonia::core::probe::{Hint, ProbeResult};
 
+use crate::audio::{AudioDecoder, WavPackDecoder};
+use crate::audio::loudness::Loudness;
 use crate::error::DecodeError;
-use crate::models::{AudioMetadata, AudioFormat, AudioCodec};
+use crate::models::{Artwork, AudioMetadata, AudioFormat, AudioCodec};
+
+/// Cover images looked for next to a track without embedded art, in order
+pub const COVER_FILE_NAMES: &[&str] = &["cover.jpg", "folder.jpg"];
+
+/// Everything `info` reports about a file, read from its headers without decoding
+#[derive(Debug, Clone, Default)]
+pub struct FileReport {
+    pub container: String,
+    /// Codec name as the decoder library knows it, e.g. "Free Lossless Audio Codec"
+    pub codec: String,
+    pub sample_rate: Option<u32>,
+    pub bit_depth: Option<u16>,
+    pub channels: Option<u16>,
+    pub duration: Duration,
+    pub file_size: u64,
+    /// Encoder tag, or the FLAC vendor string
+    pub encoder: Option<String>,
+    /// STREAMINFO MD5 of the decoded audio, in hex; FLAC only
+    pub md5: Option<String>,
+    /// All tags, with the ones without a field of their own in `extra_tags`
+    pub metadata: AudioMetadata,
+    /// Result of an earlier `scan-loudness`, filled in by the caller
+    pub loudness: Option<Loudness>,
+}
+
+impl FileReport {
+    /// Average bitrate over the whole file in kbit/s, tags and headers included
+    pub fn average_bitrate(&self) -> Option<u32> {
+        let seconds = self.duration.as_secs_f64();
+        (seconds > 0.0).then(|| (self.file_size as f64 * 8.0 / seconds / 1000.0).round() as u32)
+    }
+}
 
 /// Metadata extractor for audio files using symphonia
 pub struct MetadataExtractor;
@@ -15,30 +51,7 @@ pub struct MetadataExtractor;
 impl MetadataExtractor {
     /// Extract metadata from an audio file
     pub fn extract_from_file<P: AsRef<Path>>(path: P) -> Result<(AudioMetadata, AudioFormat, Duration), DecodeError> {
-        let file = std::fs::File::open(&path).map_err(|e| {
-            DecodeError::DecodeFailed(format!("Failed to open file: {}", e))
-        })?;
-
-        let media_source = MediaSourceStream::new(
-            Box::new(file),
-            Default::default(),
-        );
-
-        // Create a hint to help the format registry guess the format
-        let mut hint = Hint::new();
-        if let Some(extension) = path.as_ref().extension() {
-            if let Some(ext_str) = extension.to_str() {
-                hint.with_extension(ext_str);
-            }
-        }
-
-        // Probe the media source for a format
-        let probed = symphonia::default::get_probe()
-            .format(&hint, media_source, &FormatOptions::default(), &MetadataOptions::default())
-            .map_err(|e| DecodeError::UnsupportedFormat {
-                format: format!("Probe failed: {}", e),
-            })?;
-
+        let probed = Self::probe(&path)?;
         let format_reader = probed.format;
 
         // Find the first audio track
@@ -74,6 +87,107 @@ impl MetadataExtractor {
         Ok((metadata, audio_format, duration))
     }
 
+    /// Read the full technical details and every tag of a file for `info`
+    pub fn full_report<P: AsRef<Path>>(path: P) -> Result<FileReport, DecodeError> {
+        let path = path.as_ref();
+        let file_size = std::fs::metadata(path)
+            .map_err(|e| DecodeError::DecodeFailed(format!("Failed to open file: {}", e)))?
+            .len();
+
+        // Symphonia has no WavPack reader; the decoder reads the block headers and APEv2 tag
+        if path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ext.eq_ignore_ascii_case("wv")) {
+            let decoder = WavPackDecoder::new(path)?;
+            return Ok(FileReport {
+                container: AudioCodec::WavPack.container_name().to_string(),
+                codec: AudioCodec::WavPack.name().to_string(),
+                sample_rate: Some(decoder.sample_rate()),
+                bit_depth: Some(decoder.bit_depth()),
+                channels: Some(decoder.channels()),
+                duration: decoder.duration().unwrap_or_default(),
+                file_size,
+                metadata: decoder.metadata().clone(),
+                ..Default::default()
+            });
+        }
+
+        let mut probed = Self::probe(path)?;
+        let track = probed.format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
+            .ok_or_else(|| DecodeError::UnsupportedFormat {
+                format: "No audio track found".to_string(),
+            })?;
+        let params = &track.codec_params;
+        let codec = Self::determine_codec(path, params.codec)?;
+
+        let mut report = FileReport {
+            container: codec.container_name().to_string(),
+            codec: symphonia::default::get_codecs()
+                .get_codec(params.codec)
+                .map_or_else(|| codec.name().to_string(), |descriptor| descriptor.long_name.to_string()),
+            sample_rate: params.sample_rate,
+            bit_depth: params.bits_per_sample.map(|bits| bits as u16),
+            channels: params.channels.map(|channels| channels.count() as u16),
+            duration: match (params.n_frames, params.sample_rate) {
+                (Some(frames), Some(rate)) if rate > 0 => Duration::from_secs_f64(frames as f64 / rate as f64),
+                _ => Duration::ZERO,
+            },
+            file_size,
+            md5: match params.verification_check {
+                Some(VerificationCheck::Md5(md5)) => Some(crate::queue::integrity::to_hex(&md5)),
+                _ => None,
+            },
+            ..Default::default()
+        };
+
+        // Container tags (FLAC, Ogg, MP4, RIFF INFO) come from the reader, ID3 tags ahead of an MP3 stream with the probe
+        let mut revisions = Vec::new();
+        revisions.extend(probed.format.metadata().current().cloned());
+        revisions.extend(probed.metadata.get().and_then(|metadata| metadata.current().cloned()));
+        for revision in &revisions {
+            Self::extract_from_revision(revision, &mut report.metadata);
+            let encoder = revision.tags().iter().find(|tag| tag.std_key == Some(StandardTagKey::Encoder));
+            report.encoder = report.encoder.take().or_else(|| encoder.map(|tag| tag.value.to_string()));
+        }
+        if codec == AudioCodec::Flac && report.encoder.is_none() {
+            report.encoder = Self::flac_vendor(path);
+        }
+        Ok(report)
+    }
+
+    /// Vendor string of a FLAC file's Vorbis comment block, which names the encoder
+    fn flac_vendor(path: &Path) -> Option<String> {
+        let mut file = std::fs::File::open(path).ok()?;
+        let mut marker = [0u8; 4];
+        file.read_exact(&mut marker).ok()?;
+        if &marker != b"fLaC" {
+            return None;
+        }
+        loop {
+            let mut header = [0u8; 4];
+            file.read_exact(&mut header).ok()?;
+            let length = u32::from_be_bytes([0, header[1], header[2], header[3]]);
+            // Block type 4 is VORBIS_COMMENT; its vendor string is little-endian length-prefixed
+            if header[0] & 0x7f == 4 {
+                let mut vendor_length = [0u8; 4];
+                file.read_exact(&mut vendor_length).ok()?;
+                let vendor_length = u32::from_le_bytes(vendor_length);
+                if vendor_length > length.saturating_sub(4) {
+                    return None;
+                }
+                let mut vendor = vec![0u8; vendor_length as usize];
+                file.read_exact(&mut vendor).ok()?;
+                let vendor = String::from_utf8_lossy(&vendor).trim().to_string();
+                return (!vendor.is_empty()).then_some(vendor);
+            }
+            if header[0] & 0x80 != 0 {
+                return None;
+            }
+            file.seek(SeekFrom::Current(length as i64)).ok()?;
+        }
+    }
+
     /// Extract metadata from probed metadata
     fn extract_metadata_from_probed(
         mut probed_metadata: symphonia::core::probe::ProbedMetadata,
@@ -90,6 +204,131 @@ impl MetadataExtractor {
         metadata
     }
 
+    /// Open and probe an audio file
+    fn probe<P: AsRef<Path>>(path: P) -> Result<ProbeResult, DecodeError> {
+        let file = std::fs::File::open(&path).map_err(|e| {
+            DecodeError::DecodeFailed(format!("Failed to open file: {}", e))
+        })?;
+
+        let media_source = MediaSourceStream::new(
+            Box::new(file),
+            Default::default(),
+        );
+
+        // Create a hint to help the format registry guess the format
+        let mut hint = Hint::new();
+        if let Some(extension) = path.as_ref().extension() {
+            if let Some(ext_str) = extension.to_str() {
+                hint.with_extension(ext_str);
+            }
+        }
+
+        // Probe the media source for a format
+        symphonia::default::get_probe()
+            .format(&hint, media_source, &FormatOptions::default(), &MetadataOptions::default())
+            .map_err(|e| DecodeError::UnsupportedFormat {
+                format: format!("Probe failed: {}", e),
+            })
+    }
+
+    /// Extract the embedded cover picture of a file, preferring the one tagged as front cover
+    ///
+    /// Pictures are large, so this is only done on request rather than with the other tags.
+    pub fn extract_artwork<P: AsRef<Path>>(path: P) -> Result<Option<Artwork>, DecodeError> {
+        let mut probed = Self::probe(path)?;
+
+        // FLAC and MP4 keep pictures in the container, ID3 tags ahead of an MP3 stream come with the probe
+        let reader_metadata = probed.format.metadata();
+        if let Some(artwork) = reader_metadata.current().and_then(Self::cover_from_revision) {
+            return Ok(Some(artwork));
+        }
+        Ok(probed.metadata.get().and_then(|metadata| metadata.current().and_then(Self::cover_from_revision)))
+    }
+
+    fn cover_from_revision(revision: &MetadataRevision) -> Option<Artwork> {
+        let visuals = revision.visuals();
+        visuals.iter()
+            .find(|visual| visual.usage == Some(StandardVisualKey::FrontCover))
+            .or_else(|| visuals.first())
+            .map(|visual| Artwork { data: visual.data.to_vec(), mime: visual.media_type.clone() })
+    }
+
+    /// First of `COVER_FILE_NAMES` present in the track's directory
+    pub fn find_cover_file<P: AsRef<Path>>(path: P) -> Option<PathBuf> {
+        let dir = path.as_ref().parent()?;
+        COVER_FILE_NAMES.iter()
+            .map(|name| dir.join(name))
+            .find(|cover| cover.is_file())
+    }
+
+    /// Apply a track/disc numbering, composer, BPM or lyrics tag, which every container maps the same way
+    pub fn apply_common_tag(key: StandardTagKey, value: &Value, metadata: &mut AudioMetadata) {
+        match key {
+            StandardTagKey::TrackNumber => {
+                let (number, total) = Self::parse_position(value);
+                metadata.track_number = number.or(metadata.track_number);
+                metadata.total_tracks = total.or(metadata.total_tracks);
+            }
+            StandardTagKey::TrackTotal => {
+                metadata.total_tracks = Self::parse_position(value).0.or(metadata.total_tracks);
+            }
+            StandardTagKey::DiscNumber => {
+                let (number, total) = Self::parse_position(value);
+                metadata.disc_number = number.or(metadata.disc_number);
+                metadata.total_discs = total.or(metadata.total_discs);
+            }
+            StandardTagKey::DiscTotal => {
+                metadata.total_discs = Self::parse_position(value).0.or(metadata.total_discs);
+            }
+            StandardTagKey::Composer => {
+                if let Value::String(composer) = value {
+                    metadata.composer = Some(composer.clone());
+                }
+            }
+            StandardTagKey::Bpm => {
+                metadata.bpm = Self::parse_bpm(value).or(metadata.bpm);
+            }
+            StandardTagKey::AlbumArtist => {
+                if let Value::String(album_artist) = value {
+                    metadata.album_artist = Some(album_artist.clone());
+                }
+            }
+            StandardTagKey::Lyrics => {
+                if let Value::String(lyrics) = value {
+                    metadata.lyrics = Some(lyrics.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Parse a BPM tag (`128`, `128.5`); zero or unparsable tempos count as untagged
+    pub fn parse_bpm(value: &Value) -> Option<f32> {
+        let bpm = match value {
+            Value::UnsignedInt(num) => *num as f32,
+            Value::SignedInt(num) => *num as f32,
+            Value::Float(num) => *num as f32,
+            Value::String(text) => text.trim().replace(',', ".").parse().ok()?,
+            _ => return None,
+        };
+        (bpm.is_finite() && bpm > 0.0).then_some(bpm)
+    }
+
+    /// Parse a track or disc position, either `X` or `X/Y`
+    pub fn parse_position(value: &Value) -> (Option<u32>, Option<u32>) {
+        match value {
+            Value::UnsignedInt(num) => (Some(*num as u32), None),
+            Value::SignedInt(num) => (u32::try_from(*num).ok(), None),
+            Value::String(text) => {
+                let mut parts = text.trim().splitn(2, '/');
+                let num = parts.next().and_then(|n| n.trim().parse::<u32>().ok());
+                let total = parts.next().and_then(|t| t.trim().parse::<u32>().ok());
+                (num, total)
+            }
+            _ => (None, None),
+        }
+    }
+
     /// Extract metadata from a metadata revision
     fn extract_from_revision(revision: &MetadataRevision, metadata: &mut AudioMetadata) {
         for tag in revision.tags() {
@@ -110,20 +349,14 @@ impl MetadataExtractor {
                             metadata.album = Some(album.clone());
                         }
                     }
-                    StandardTagKey::TrackNumber => {
-                        match &tag.value {
-                            Value::UnsignedInt(track_num) => {
-                                metadata.track_number = Some(*track_num as u32);
-                            }
-                            Value::String(track_str) => {
-                                // Handle "1/12" format or just "1"
-                                let track_part = track_str.split('/').next().unwrap_or(track_str);
-                                if let Ok(track_num) = track_part.parse::<u32>() {
-                                    metadata.track_number = Some(track_num);
-                                }
-                            }
-                            _ => {}
-                        }
+                    StandardTagKey::TrackNumber
+                    | StandardTagKey::TrackTotal
+                    | StandardTagKey::DiscNumber
+                    | StandardTagKey::DiscTotal
+                    | StandardTagKey::Composer
+                    | StandardTagKey::Bpm
+                    | StandardTagKey::Lyrics => {
+                        Self::apply_common_tag(std_key, &tag.value, metadata);
                     }
                     StandardTagKey::Date => {
                         match &tag.value {
@@ -149,13 +382,14 @@ impl MetadataExtractor {
                     // Handle additional common tags
                     StandardTagKey::AlbumArtist => {
                         if let Value::String(album_artist) = &tag.value {
+                            metadata.album_artist = Some(album_artist.clone());
                             // If no artist is set, use album artist
                             if metadata.artist.is_none() {
                                 metadata.artist = Some(album_artist.clone());
                             }
                         }
                     }
-                    _ => {} // Ignore other tags for now
+                    _ => Self::keep_extra_tag(tag, metadata),
                 }
             } else {
                 let key = &tag.key;
@@ -204,12 +438,19 @@ impl MetadataExtractor {
                             _ => {}
                         }
                     }
-                    _ => {} // Ignore other non-standard tags
+                    _ => Self::keep_extra_tag(tag, metadata),
                 }
             }
         }
     }
 
+    /// Keep a tag without a field of its own in `extra_tags`; binary values are left out
+    fn keep_extra_tag(tag: &Tag, metadata: &mut AudioMetadata) {
+        if !matches!(tag.value, Value::Binary(_)) {
+            metadata.extra_tags.push((tag.key.clone(), tag.value.to_string()));
+        }
+    }
+
     /// Determine codec from file path and symphonia codec type
     fn determine_codec<P: AsRef<Path>>(path: P, codec_type: symphonia::core::codecs::CodecType) -> Result<AudioCodec, DecodeError> {
         // First try to determine from file extension
@@ -221,6 +462,7 @@ impl MetadataExtractor {
                     "m4a" | "alac" => return Ok(AudioCodec::Alac),
                     "mp3" => return Ok(AudioCodec::Mp3),
                     "ogg" | "oga" => return Ok(AudioCodec::OggVorbis),
+                    "wv" => return Ok(AudioCodec::WavPack),
                     _ => {}
                 }
             }
@@ -256,6 +498,7 @@ impl MetadataExtractor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audio::test_files;
     use tempfile::NamedTempFile;
 
     #[test]
@@ -387,6 +630,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_bpm_parsing() {
+        let test_cases = [
+            (Value::String("128.5".to_string()), Some(128.5)),
+            (Value::String(" 120 ".to_string()), Some(120.0)),
+            (Value::String("97,5".to_string()), Some(97.5)),
+            (Value::UnsignedInt(140), Some(140.0)),
+            (Value::SignedInt(-1), None),
+            (Value::String("0".to_string()), None),
+            (Value::String("fast".to_string()), None),
+        ];
+
+        for (value, expected) in test_cases {
+            assert_eq!(MetadataExtractor::parse_bpm(&value), expected, "Failed for value: {:?}", value);
+        }
+    }
+
     #[test]
     fn test_year_parsing() {
         // Test various date formats that might be encountered
@@ -466,6 +726,82 @@ mod tests {
         }
     }
 
+    /// Mono 16-bit 44.1 kHz FLAC with a PICTURE block of the given type and 256 silent samples
+    fn flac_with_picture(picture_type: u32, mime: &str, data: &[u8]) -> Vec<u8> {
+        let mut picture = picture_type.to_be_bytes().to_vec();
+        picture.extend_from_slice(&(mime.len() as u32).to_be_bytes());
+        picture.extend_from_slice(mime.as_bytes());
+        // No description; width, height, depth and palette size left at zero
+        picture.extend_from_slice(&[0; 20]);
+        picture.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        picture.extend_from_slice(data);
+
+        let mut bytes = b"fLaC".to_vec();
+        bytes.extend(test_files::flac_block(0, false, &test_files::flac_streaminfo(256, 44_100, 1, 16, 256, [0; 16])));
+        bytes.extend(test_files::flac_block(6, true, &picture));
+        // One 256-sample frame holding a CONSTANT subframe of zero
+        bytes.extend(test_files::flac_frame(&[0xFF, 0xF8, 0x89, 0x08, 0x00], &[0x00, 0x00, 0x00]));
+        bytes
+    }
+
+    #[test]
+    fn test_extract_artwork_from_flac_picture_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cover.flac");
+        // Type 3 is the front cover
+        std::fs::write(&path, flac_with_picture(3, "image/png", b"\x89PNG fake")).unwrap();
+
+        let artwork = MetadataExtractor::extract_artwork(&path).unwrap().unwrap();
+        assert_eq!(artwork.mime, "image/png");
+        assert_eq!(artwork.data, b"\x89PNG fake");
+        assert_eq!(artwork.extension(), "png");
+    }
+
+    #[test]
+    fn test_extract_artwork_from_id3_apic_frame() {
+        // APIC: UTF-8, media type, front cover, empty description, picture data
+        let mut apic = vec![3];
+        apic.extend(b"image/jpeg\0");
+        apic.push(3);
+        apic.push(0);
+        apic.extend(b"jpeg data");
+        let mut frames = b"APIC".to_vec();
+        frames.extend((apic.len() as u32).to_be_bytes());
+        frames.extend([0, 0]);
+        frames.extend(apic);
+
+        let mut bytes = b"ID3\x04\x00\x00".to_vec();
+        bytes.extend([0, 0, 0, frames.len() as u8]);
+        bytes.extend(frames);
+        // Two silent 128 kbps MPEG-1 Layer III frames
+        let mut frame = vec![0u8; 417];
+        frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x64]);
+        bytes.extend(frame.repeat(2));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tagged.mp3");
+        std::fs::write(&path, bytes).unwrap();
+
+        let artwork = MetadataExtractor::extract_artwork(&path).unwrap().unwrap();
+        assert_eq!(artwork.mime, "image/jpeg");
+        assert_eq!(artwork.data, b"jpeg data");
+    }
+
+    #[test]
+    fn test_cover_file_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        let track = dir.path().join("song.flac");
+        std::fs::write(&track, flac_with_picture(0, "image/png", b"")).unwrap();
+        assert_eq!(MetadataExtractor::find_cover_file(&track), None);
+
+        std::fs::write(dir.path().join("folder.jpg"), b"folder").unwrap();
+        assert_eq!(MetadataExtractor::find_cover_file(&track), Some(dir.path().join("folder.jpg")));
+
+        // cover.jpg is preferred over folder.jpg
+        std::fs::write(dir.path().join("cover.jpg"), b"cover").unwrap();
+        assert_eq!(MetadataExtractor::find_cover_file(&track), Some(dir.path().join("cover.jpg")));
+    }
+
     #[test]
     #[ignore] // Ignored by default since it requires actual audio files
     fn test_metadata_extraction_with_real_files() {
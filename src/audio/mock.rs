@@ -0,0 +1,581 @@
+/*!
+An `AudioEngineControl` without a sound card, for testing the application controller.
+
+`MockAudioEngine` records every call that changes playback or output settings as an
+`EngineCall` and answers queries from its own state. Clones share that state, so a test
+can keep one clone after boxing another into the controller. Decoder responses and the
+device list are scripted by the test.
+*/
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::audio::buffer::BufferAdjustment;
+use crate::audio::chain::ChainControls;
+use crate::audio::channels::{ChannelControl, OutputChannels};
+use crate::audio::crossfeed::{CrossfeedControl, CrossfeedSettings};
+use crate::audio::device::ChannelMap;
+use crate::audio::dither::DitherControl;
+use crate::audio::engine::{BufferSettings, DecoderResponse, NextTrackProvider, PlaybackState, ThreadStatus, PREBUFFER_TIMEOUT};
+use crate::audio::eq::EqBand;
+use crate::audio::silence::SilenceSettings;
+use crate::audio::spectrum::SpectrumTap;
+use crate::audio::{AudioDecoder, AudioEngine, AudioEngineControl, AudioPerformanceProfiler, DeviceCapabilities, PerformanceReport};
+use crate::error::AudioError;
+use crate::models::{AudioBuffer, AudioFormat, ChannelLevels, LoopRegion};
+
+/// A call that changed the mock engine, in the order it was made
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineCall {
+    StartPlayback { start: Duration },
+    LoadFile { path: PathBuf, start: Duration },
+    DiscardPreloadedTrack,
+    Pause,
+    Resume,
+    Stop,
+    Unload,
+    SetVolume(f32),
+    SetDevice(String),
+    Seek(Duration),
+    SetLoop { start: Duration, end: Duration },
+    ClearLoop,
+    SetGapless(bool),
+    SetPreferNativeRateSwitch(bool),
+    SetOutputBufferFrames(u32),
+    SetExclusiveMode(bool),
+    SetLimiter(bool),
+    SetEqBands(Vec<EqBand>),
+    SetFadeDurations { fade_out: Duration, fade_in: Duration },
+    SetSilenceSkipping(SilenceSettings),
+    SetChannelLayout(Option<ChannelMap>),
+    SetOversampling(Option<u32>),
+    SetOutputChannels(OutputChannels),
+    SetCrossfeed(CrossfeedSettings),
+    SetDither(bool),
+    SetSuspendOutputAfter(Duration),
+    SetPrebuffer(Duration),
+    RefreshDevices,
+}
+
+#[derive(Debug)]
+struct MockState {
+    calls: Vec<EngineCall>,
+    decoder_responses: VecDeque<DecoderResponse>,
+    playback_state: PlaybackState,
+    /// File started with `start_playback_at` or `load_file_at`, kept until `unload`
+    loaded_track: Option<PathBuf>,
+    /// Where the loaded track starts in its file; `stop` rewinds to here
+    track_start: Duration,
+    /// Duration of the file started with `start_playback_at`
+    duration: Option<Duration>,
+    position: Duration,
+    /// Position sought while stopped, picked up by the next `start_playback_at`
+    pending_start: Option<Duration>,
+    loop_region: Option<LoopRegion>,
+    volume: f32,
+    gapless: bool,
+    output_buffer_frames: u32,
+    exclusive: bool,
+    limiter: bool,
+    eq_bands: Vec<EqBand>,
+    output_channels: OutputChannels,
+    crossfeed: CrossfeedSettings,
+    dither: bool,
+    devices: Vec<String>,
+    current_device: Option<String>,
+    /// Returned by `latest_decoded`
+    latest_decoded: AudioBuffer,
+    /// Returned by `output_levels`
+    output_levels: ChannelLevels,
+    seekable: bool,
+    /// Returned by `device_capabilities` for every device
+    device_capabilities: Option<DeviceCapabilities>,
+    /// Reported by `get_status` as the decoder's source format
+    source_format: Option<AudioFormat>,
+    /// How long playback has waited for the pre-buffer, while it does
+    buffering_for: Option<Duration>,
+}
+
+/// Engine double that records calls and returns scripted responses
+#[derive(Clone)]
+pub struct MockAudioEngine {
+    state: Arc<Mutex<MockState>>,
+    status: ThreadStatus,
+    spectrum_tap: Arc<SpectrumTap>,
+    profiler: Arc<AudioPerformanceProfiler>,
+}
+
+impl MockAudioEngine {
+    /// Output sample rate the mock reports
+    pub const SAMPLE_RATE: u32 = 48_000;
+
+    /// A stopped engine with one output device, "Mock Output"
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(MockState {
+                calls: Vec::new(),
+                decoder_responses: VecDeque::new(),
+                playback_state: PlaybackState::Stopped,
+                loaded_track: None,
+                track_start: Duration::ZERO,
+                duration: None,
+                position: Duration::ZERO,
+                pending_start: None,
+                loop_region: None,
+                volume: 1.0,
+                gapless: true,
+                output_buffer_frames: 0,
+                exclusive: false,
+                limiter: true,
+                eq_bands: Vec::new(),
+                output_channels: OutputChannels::default(),
+                crossfeed: CrossfeedSettings::default(),
+                dither: false,
+                devices: vec!["Mock Output".to_string()],
+                current_device: Some("Mock Output".to_string()),
+                latest_decoded: AudioBuffer::empty(),
+                output_levels: ChannelLevels::default(),
+                seekable: true,
+                device_capabilities: None,
+                source_format: None,
+                buffering_for: None,
+            })),
+            status: ThreadStatus::default(),
+            spectrum_tap: Arc::new(SpectrumTap::new()),
+            profiler: Arc::new(AudioPerformanceProfiler::new()),
+        }
+    }
+
+    /// Calls made so far
+    pub fn calls(&self) -> Vec<EngineCall> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    /// Queue a response for `get_decoder_response` to return
+    pub fn push_decoder_response(&self, response: DecoderResponse) {
+        self.state.lock().unwrap().decoder_responses.push_back(response);
+    }
+
+    /// Replace the output devices `list_devices` reports
+    pub fn set_devices(&self, devices: &[&str]) {
+        self.state.lock().unwrap().devices = devices.iter().map(|device| device.to_string()).collect();
+    }
+
+    /// Audio for `latest_decoded` to return, as if the decoder had just written it
+    pub fn set_latest_decoded(&self, buffer: AudioBuffer) {
+        self.state.lock().unwrap().latest_decoded = buffer;
+    }
+
+    /// Levels for `output_levels` to report
+    pub fn set_output_levels(&self, levels: ChannelLevels) {
+        self.state.lock().unwrap().output_levels = levels;
+    }
+
+    /// Make the loaded file seekable or not
+    pub fn set_seekable(&self, seekable: bool) {
+        self.state.lock().unwrap().seekable = seekable;
+    }
+
+    /// What the decoder reports as the source format of the playing file
+    pub fn set_source_format(&self, format: AudioFormat) {
+        self.state.lock().unwrap().source_format = Some(format);
+    }
+
+    /// Capabilities `device_capabilities` reports for any device
+    pub fn set_device_capabilities(&self, capabilities: DeviceCapabilities) {
+        self.state.lock().unwrap().device_capabilities = Some(capabilities);
+    }
+
+    /// Report playback as waiting this long for the pre-buffer (`None` = playing)
+    pub fn set_buffering_for(&self, waited: Option<Duration>) {
+        self.state.lock().unwrap().buffering_for = waited;
+    }
+
+    /// Move the playback position, as the output callback would
+    pub fn set_position(&self, position: Duration) {
+        self.state.lock().unwrap().position = position;
+    }
+
+    /// Log `call` and return the state for the call to change
+    fn record(&self, call: EngineCall) -> MutexGuard<'_, MockState> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(call);
+        state
+    }
+}
+
+impl AudioEngine for MockAudioEngine {
+    fn start_playback(&mut self, decoder: Box<dyn AudioDecoder>) -> Result<(), AudioError> {
+        self.start_playback_at(PathBuf::new(), decoder, Duration::ZERO)
+    }
+
+    fn pause(&mut self) -> Result<(), AudioError> {
+        self.record(EngineCall::Pause).playback_state = PlaybackState::Paused;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<(), AudioError> {
+        let mut state = self.record(EngineCall::Resume);
+        if let Some(position) = state.pending_start.take() {
+            state.position = position;
+        }
+        state.playback_state = PlaybackState::Playing;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), AudioError> {
+        let mut state = self.record(EngineCall::Stop);
+        state.playback_state = PlaybackState::Stopped;
+        state.position = state.track_start;
+        state.pending_start = None;
+        Ok(())
+    }
+
+    fn set_volume(&mut self, volume: f32) -> Result<(), AudioError> {
+        self.record(EngineCall::SetVolume(volume)).volume = volume.clamp(0.0, 1.0);
+        Ok(())
+    }
+
+    fn set_device(&mut self, device_name: &str) -> Result<(), AudioError> {
+        let mut state = self.record(EngineCall::SetDevice(device_name.to_string()));
+        // The output restarts without a track, as after `unload`
+        state.playback_state = PlaybackState::Stopped;
+        state.loaded_track = None;
+        state.duration = None;
+        state.position = Duration::ZERO;
+        if !state.devices.iter().any(|device| device == device_name) {
+            return Err(AudioError::DeviceNotFound { device: device_name.to_string() });
+        }
+        state.current_device = Some(device_name.to_string());
+        Ok(())
+    }
+}
+
+impl AudioEngineControl for MockAudioEngine {
+    fn start_playback_at(&mut self, path: PathBuf, decoder: Box<dyn AudioDecoder>, start: Duration) -> Result<(), AudioError> {
+        let mut state = self.record(EngineCall::StartPlayback { start });
+        state.playback_state = PlaybackState::Playing;
+        state.loaded_track = Some(path);
+        state.track_start = start;
+        state.duration = decoder.duration();
+        state.position = state.pending_start.take()
+            .filter(|pending| *pending >= start && decoder.duration().is_none_or(|duration| *pending <= duration))
+            .unwrap_or(start);
+        Ok(())
+    }
+
+    fn load_file_at(&mut self, path: PathBuf, start: Duration) -> Result<(), AudioError> {
+        let mut state = self.record(EngineCall::LoadFile { path: path.clone(), start });
+        state.loaded_track = Some(path);
+        state.track_start = start;
+        state.duration = None;
+        state.position = start;
+        state.pending_start = None;
+        Ok(())
+    }
+
+    fn unload(&mut self) -> Result<(), AudioError> {
+        let mut state = self.record(EngineCall::Unload);
+        state.playback_state = PlaybackState::Stopped;
+        state.loaded_track = None;
+        state.track_start = Duration::ZERO;
+        state.duration = None;
+        state.position = Duration::ZERO;
+        state.pending_start = None;
+        Ok(())
+    }
+
+    fn current_track_path(&self) -> Option<PathBuf> {
+        self.state.lock().unwrap().loaded_track.clone()
+    }
+
+    fn discard_preloaded_track(&mut self) -> Result<(), AudioError> {
+        drop(self.record(EngineCall::DiscardPreloadedTrack));
+        Ok(())
+    }
+
+    fn set_next_track_provider(&mut self, _provider: Arc<dyn NextTrackProvider>) {}
+
+    fn get_decoder_response(&mut self) -> Option<DecoderResponse> {
+        let mut state = self.state.lock().unwrap();
+        let response = state.decoder_responses.pop_front();
+        match &response {
+            Some(DecoderResponse::FileLoaded { path, .. }) => state.loaded_track = Some(path.clone()),
+            Some(DecoderResponse::TrackFailed { .. }) => state.loaded_track = None,
+            _ => {}
+        }
+        response
+    }
+
+    fn get_status(&mut self) -> &ThreadStatus {
+        let state = self.state.lock().unwrap();
+        self.status.playback_state = state.playback_state.clone();
+        self.status.position = state.position;
+        self.status.source_format = state.source_format.clone();
+        drop(state);
+        &self.status
+    }
+
+    fn playback_state(&self) -> PlaybackState {
+        self.state.lock().unwrap().playback_state.clone()
+    }
+
+    fn current_position(&self) -> Duration {
+        self.state.lock().unwrap().position
+    }
+
+    fn current_duration(&self) -> Option<Duration> {
+        self.state.lock().unwrap().duration
+    }
+
+    fn validate_seek_position(&self, position: Duration) -> Result<Duration, AudioError> {
+        match self.state.lock().unwrap().duration {
+            Some(duration) if position > duration => Err(AudioError::InvalidSeekPosition {
+                position: position.as_secs_f64(),
+                duration: duration.as_secs_f64(),
+            }),
+            _ => Ok(position),
+        }
+    }
+
+    fn seek(&mut self, position: Duration) -> Result<(), AudioError> {
+        let mut state = self.record(EngineCall::Seek(position));
+        if !state.seekable {
+            return Err(AudioError::SeekUnsupported);
+        }
+        if state.playback_state == PlaybackState::Stopped {
+            state.pending_start = Some(position);
+        }
+        state.position = position;
+        Ok(())
+    }
+
+    fn set_loop(&mut self, start: Duration, end: Duration) -> Result<LoopRegion, AudioError> {
+        let region = LoopRegion::new(start, end).ok_or(AudioError::InvalidLoopRegion {
+            start: start.as_secs_f64(),
+            end: end.as_secs_f64(),
+        })?;
+        self.record(EngineCall::SetLoop { start, end }).loop_region = Some(region);
+        Ok(region)
+    }
+
+    fn clear_loop(&mut self) -> Result<(), AudioError> {
+        self.record(EngineCall::ClearLoop).loop_region = None;
+        Ok(())
+    }
+
+    fn loop_region(&self) -> Option<LoopRegion> {
+        self.state.lock().unwrap().loop_region
+    }
+
+    fn volume(&self) -> f32 {
+        self.state.lock().unwrap().volume
+    }
+
+    fn set_gapless_enabled(&mut self, enabled: bool) {
+        self.record(EngineCall::SetGapless(enabled)).gapless = enabled;
+    }
+
+    fn is_gapless_enabled(&self) -> bool {
+        self.state.lock().unwrap().gapless
+    }
+
+    fn set_prefer_native_rate_switch(&mut self, enabled: bool) {
+        drop(self.record(EngineCall::SetPreferNativeRateSwitch(enabled)));
+    }
+
+    fn sample_rate(&self) -> u32 {
+        Self::SAMPLE_RATE
+    }
+
+    fn bit_depth(&self) -> u16 {
+        32
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn buffer_settings(&self) -> BufferSettings {
+        BufferSettings::default()
+    }
+
+    fn set_output_buffer_frames(&mut self, frames: u32) -> Result<u32, AudioError> {
+        self.record(EngineCall::SetOutputBufferFrames(frames)).output_buffer_frames = frames;
+        Ok(frames)
+    }
+
+    fn effective_output_buffer_frames(&self) -> u32 {
+        self.state.lock().unwrap().output_buffer_frames
+    }
+
+    fn output_latency_ms(&self) -> f32 {
+        self.effective_output_buffer_frames() as f32 * 1000.0 / Self::SAMPLE_RATE as f32
+    }
+
+    fn check_underruns(&mut self) -> Option<BufferAdjustment> {
+        None
+    }
+
+    fn underrun_count(&self) -> u64 {
+        0
+    }
+
+    fn set_prebuffer(&self, prebuffer: Duration) {
+        drop(self.record(EngineCall::SetPrebuffer(prebuffer)));
+    }
+
+    fn buffering_for(&self) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+        state.buffering_for.filter(|_| state.playback_state == PlaybackState::Playing)
+    }
+
+    fn check_prebuffer(&mut self) -> Result<(), AudioError> {
+        let Some(waited) = self.buffering_for().filter(|waited| *waited >= PREBUFFER_TIMEOUT) else {
+            return Ok(());
+        };
+        self.state.lock().unwrap().buffering_for = None;
+        self.stop()?;
+        Err(AudioError::PrebufferTimeout { waited_secs: waited.as_secs(), buffered_ms: 0, target_ms: 500 })
+    }
+
+    fn set_exclusive_mode(&mut self, enable: bool) -> Result<bool, AudioError> {
+        self.record(EngineCall::SetExclusiveMode(enable)).exclusive = enable;
+        Ok(enable)
+    }
+
+    fn is_exclusive_active(&self) -> bool {
+        self.state.lock().unwrap().exclusive
+    }
+
+    fn set_limiter_enabled(&self, enabled: bool) {
+        self.record(EngineCall::SetLimiter(enabled)).limiter = enabled;
+    }
+
+    fn is_limiter_enabled(&self) -> bool {
+        self.state.lock().unwrap().limiter
+    }
+
+    fn clipped_samples(&self) -> u64 {
+        0
+    }
+
+    fn take_first_clip(&self) -> Option<u64> {
+        None
+    }
+
+    fn eq_bands(&self) -> Vec<EqBand> {
+        self.state.lock().unwrap().eq_bands.clone()
+    }
+
+    fn set_eq_bands(&self, bands: Vec<EqBand>) {
+        let mut state = self.record(EngineCall::SetEqBands(bands.clone()));
+        state.eq_bands = bands;
+    }
+
+    fn set_fade_durations(&self, fade_out: Duration, fade_in: Duration) {
+        drop(self.record(EngineCall::SetFadeDurations { fade_out, fade_in }));
+    }
+
+    fn set_silence_skipping(&self, settings: SilenceSettings) {
+        drop(self.record(EngineCall::SetSilenceSkipping(settings)));
+    }
+
+    fn set_channel_layout(&self, layout: Option<ChannelMap>) {
+        drop(self.record(EngineCall::SetChannelLayout(layout)));
+    }
+
+    fn set_oversampling(&self, factor: Option<u32>) {
+        drop(self.record(EngineCall::SetOversampling(factor)));
+    }
+
+    fn set_output_channels(&self, settings: OutputChannels) {
+        self.record(EngineCall::SetOutputChannels(settings)).output_channels = settings;
+    }
+
+    fn output_channels(&self) -> OutputChannels {
+        self.state.lock().unwrap().output_channels
+    }
+
+    fn set_crossfeed(&self, settings: CrossfeedSettings) {
+        self.record(EngineCall::SetCrossfeed(settings)).crossfeed = settings;
+    }
+
+    fn crossfeed(&self) -> CrossfeedSettings {
+        self.state.lock().unwrap().crossfeed
+    }
+
+    fn set_dither(&self, enabled: bool) {
+        self.record(EngineCall::SetDither(enabled)).dither = enabled;
+    }
+
+    fn is_dither_enabled(&self) -> bool {
+        self.state.lock().unwrap().dither
+    }
+
+    fn chain_controls(&self) -> ChainControls {
+        let state = self.state.lock().unwrap();
+        let controls = ChainControls {
+            channels: Arc::new(ChannelControl::new(state.output_channels)),
+            crossfeed: Arc::new(CrossfeedControl::new(state.crossfeed)),
+            dither: Arc::new(DitherControl::new(state.dither)),
+            ..ChainControls::default()
+        };
+        controls.clipping.set_limiter_enabled(state.limiter);
+        controls.eq.set_bands(state.eq_bands.clone());
+        controls
+    }
+
+    fn set_suspend_output_after(&self, after: Duration) {
+        drop(self.record(EngineCall::SetSuspendOutputAfter(after)));
+    }
+
+    fn spectrum_tap(&self) -> Arc<SpectrumTap> {
+        Arc::clone(&self.spectrum_tap)
+    }
+
+    fn latest_decoded(&self, frames: usize) -> AudioBuffer {
+        let mut buffer = self.state.lock().unwrap().latest_decoded.clone();
+        let excess = buffer.frames.saturating_sub(frames);
+        buffer.samples.drain(..excess * buffer.channels as usize);
+        buffer.frames -= excess;
+        buffer
+    }
+
+    fn output_levels(&self) -> ChannelLevels {
+        self.state.lock().unwrap().output_levels
+    }
+
+    fn supports_seek(&self) -> bool {
+        self.state.lock().unwrap().seekable
+    }
+
+    fn update_performance_monitoring(&self) {}
+
+    fn get_performance_report(&self) -> PerformanceReport {
+        self.profiler.performance_report()
+    }
+
+    fn list_devices(&self) -> Vec<String> {
+        self.state.lock().unwrap().devices.clone()
+    }
+
+    fn current_device_name(&self) -> Result<Option<String>, AudioError> {
+        Ok(self.state.lock().unwrap().current_device.clone())
+    }
+
+    fn default_device_name(&self) -> Option<String> {
+        self.state.lock().unwrap().devices.first().cloned()
+    }
+
+    fn refresh_devices(&mut self) -> Result<(), AudioError> {
+        drop(self.record(EngineCall::RefreshDevices));
+        Ok(())
+    }
+
+    fn device_capabilities(&self, _device_name: &str) -> Option<DeviceCapabilities> {
+        self.state.lock().unwrap().device_capabilities.clone()
+    }
+}
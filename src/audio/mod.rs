@@ -1,13 +1,36 @@
 pub mod engine;
 pub mod decoders;
+pub mod encoders;
 pub mod device;
 pub mod buffer;
 pub mod metadata;
 pub mod position;
 pub mod gapless;
+pub mod silence;
 pub mod performance;
 pub mod memory;
 pub mod resampler;
+pub mod limiter;
+pub mod effects;
+pub mod chain;
+pub mod render;
+pub mod channels;
+pub mod crossfeed;
+pub mod dither;
+pub mod level;
+pub mod eq;
+pub mod eq_presets;
+pub mod spectrum;
+pub mod exclusive;
+pub mod format_detection;
+pub mod priority;
+pub mod watchdog;
+pub mod loudness;
+pub mod source;
+#[cfg(test)]
+pub mod mock;
+#[cfg(test)]
+pub mod test_files;
 
 #[cfg(test)]
 pub mod tests;
@@ -16,10 +39,10 @@ use std::time::Duration;
 use crate::error::{AudioError, DecodeError};
 
 // Re-export device management types
-pub use device::{DeviceManager, DeviceCapabilities};
+pub use device::{DeviceManager, DeviceCapabilities, DeviceMonitor, DeviceEvent};
 
 // Re-export decoder types
-pub use decoders::{FlacDecoder, WavDecoder, AlacDecoder, Mp3Decoder, OggDecoder, M4aDecoder};
+pub use decoders::{FlacDecoder, WavDecoder, AlacDecoder, Mp3Decoder, OggDecoder, M4aDecoder, WavPackDecoder};
 
 // Re-export buffer management types
 pub use buffer::{RingBuffer, BufferManager, BufferStatus};
@@ -31,7 +54,7 @@ pub use crate::models::{AudioBuffer, AudioMetadata, AudioFormat, AudioCodec};
 pub use metadata::MetadataExtractor;
 
 // Re-export position tracking
-pub use position::{PositionTracker, PositionUpdate};
+pub use position::PositionTracker;
 
 // Re-export gapless playback
 pub use gapless::GaplessManager;
@@ -41,7 +64,7 @@ pub use performance::{AudioPerformanceProfiler, PerformanceReport, PerformanceSt
 
 // Re-export memory management
 pub use memory::{AudioMemoryManager, HighResBufferAllocator, ManagedAudioBuffer, MemoryStats};
-pub use resampler::LinearResampler;
+pub use resampler::RateAdapter;
 
 /// Core trait for audio decoding functionality
 pub trait AudioDecoder: Send {
@@ -51,11 +74,17 @@ pub trait AudioDecoder: Send {
     /// Seek to a specific position in the audio stream
     fn seek(&mut self, position: Duration) -> Result<(), DecodeError>;
 
+    /// Whether `seek` can be used; false once the stream has turned out not to be seekable
+    fn supports_seek(&self) -> bool {
+        true
+    }
+
     /// Get metadata information about the audio file
     fn metadata(&self) -> &AudioMetadata;
 
-    /// Get the total duration of the audio file
-    fn duration(&self) -> Duration;
+    /// Get the total duration of the audio file, or `None` when it is
+    /// unknown (an endless radio stream)
+    fn duration(&self) -> Option<Duration>;
 
     /// Get the sample rate of the audio file
     fn sample_rate(&self) -> u32;
@@ -65,6 +94,19 @@ pub trait AudioDecoder: Send {
 
     /// Get the number of audio channels
     fn channels(&self) -> u16;
+
+    /// Get the codec the stream is encoded with
+    fn codec(&self) -> AudioCodec;
+
+    /// Silent frames the encoder added at the start, trimmed for gapless playback
+    fn encoder_delay_frames(&self) -> u32 {
+        0
+    }
+
+    /// Silent frames the encoder added at the end, trimmed for gapless playback
+    fn encoder_padding_frames(&self) -> u32 {
+        0
+    }
 }
 
 /// Core trait for audio engine functionality
@@ -87,3 +129,170 @@ pub trait AudioEngine {
     /// Set the output device
     fn set_device(&mut self, device_name: &str) -> Result<(), AudioError>;
 }
+
+/// Everything the application controller needs from an engine beyond `AudioEngine`
+///
+/// Implemented by `AudioEngineImpl`; tests drive the controller with `mock::MockAudioEngine`.
+pub trait AudioEngineControl: AudioEngine {
+    /// Start playback of `path` with the given decoder, `start` into the file
+    fn start_playback_at(&mut self, path: std::path::PathBuf, decoder: Box<dyn AudioDecoder>, start: Duration) -> Result<(), AudioError>;
+
+    /// Stop playback and let go of the current track; `stop` keeps it loaded
+    fn unload(&mut self) -> Result<(), AudioError>;
+
+    /// File of the loaded track, playing or stopped
+    fn current_track_path(&self) -> Option<std::path::PathBuf>;
+
+    /// Decode `path` from `start` on the decoder thread, replacing the current track
+    fn load_file_at(&mut self, path: std::path::PathBuf, start: Duration) -> Result<(), AudioError>;
+
+    /// Drop a track preloaded for gapless playback because the queue changed
+    fn discard_preloaded_track(&mut self) -> Result<(), AudioError>;
+
+    /// Source of the next track for gapless preloading
+    fn set_next_track_provider(&mut self, provider: std::sync::Arc<dyn engine::NextTrackProvider>);
+
+    /// Next pending response from the decoder thread
+    fn get_decoder_response(&mut self) -> Option<engine::DecoderResponse>;
+
+    /// Latest status reported by the output and decoder threads
+    fn get_status(&mut self) -> &engine::ThreadStatus;
+
+    fn playback_state(&self) -> engine::PlaybackState;
+
+    /// Position in the current file
+    fn current_position(&self) -> Duration;
+
+    /// Length of the current file, if one is loaded
+    fn current_duration(&self) -> Option<Duration>;
+
+    /// Clamp a seek target to the current file, or fail when nothing is loaded
+    fn validate_seek_position(&self, position: Duration) -> Result<Duration, AudioError>;
+
+    fn seek(&mut self, position: Duration) -> Result<(), AudioError>;
+
+    /// Repeat `start..end` of the current file
+    fn set_loop(&mut self, start: Duration, end: Duration) -> Result<crate::models::LoopRegion, AudioError>;
+
+    fn clear_loop(&mut self) -> Result<(), AudioError>;
+
+    fn loop_region(&self) -> Option<crate::models::LoopRegion>;
+
+    /// Output volume (0.0 to 1.0)
+    fn volume(&self) -> f32;
+
+    fn set_gapless_enabled(&mut self, enabled: bool);
+
+    fn is_gapless_enabled(&self) -> bool;
+
+    /// Rebuild the stream at each gapless track's own rate instead of resampling into it
+    fn set_prefer_native_rate_switch(&mut self, enabled: bool);
+
+    /// Output sample rate in Hz
+    fn sample_rate(&self) -> u32;
+
+    fn bit_depth(&self) -> u16;
+
+    fn channels(&self) -> u16;
+
+    fn buffer_settings(&self) -> engine::BufferSettings;
+
+    /// Request a device buffer size, returning the size actually used (0 = device default)
+    fn set_output_buffer_frames(&mut self, frames: u32) -> Result<u32, AudioError>;
+
+    fn effective_output_buffer_frames(&self) -> u32;
+
+    fn output_latency_ms(&self) -> f32;
+
+    /// Adapt the buffer target to recent underruns, if any
+    fn check_underruns(&mut self) -> Option<buffer::BufferAdjustment>;
+
+    fn underrun_count(&self) -> u64;
+
+    /// Audio to buffer before a track starts playing (zero = start at once)
+    fn set_prebuffer(&self, prebuffer: Duration);
+
+    /// How long playback has been waiting for the pre-buffer to fill, if it is
+    fn buffering_for(&self) -> Option<Duration>;
+
+    /// Stop playback if the pre-buffer took too long to fill, returning why
+    fn check_prebuffer(&mut self) -> Result<(), AudioError>;
+
+    /// Ask for exclusive device access; returns whether it was granted
+    fn set_exclusive_mode(&mut self, enable: bool) -> Result<bool, AudioError>;
+
+    fn is_exclusive_active(&self) -> bool;
+
+    fn set_limiter_enabled(&self, enabled: bool);
+
+    fn is_limiter_enabled(&self) -> bool;
+
+    /// Samples clipped in the current track
+    fn clipped_samples(&self) -> u64;
+
+    /// Position of the first clip since the last call, in frames
+    fn take_first_clip(&self) -> Option<u64>;
+
+    fn eq_bands(&self) -> Vec<eq::EqBand>;
+
+    fn set_eq_bands(&self, bands: Vec<eq::EqBand>);
+
+    fn set_fade_durations(&self, fade_out: Duration, fade_in: Duration);
+
+    /// Change silence skipping; the decoder applies it from its next buffer
+    fn set_silence_skipping(&self, settings: silence::SilenceSettings);
+
+    /// Set the output's speaker order; heard from the next decoded buffer
+    fn set_channel_layout(&self, layout: Option<device::ChannelMap>);
+
+    /// Set the oversampling factor; applies from the next track
+    fn set_oversampling(&self, factor: Option<u32>);
+
+    /// Change mono/swap and balance; heard on the next output block
+    fn set_output_channels(&self, settings: channels::OutputChannels);
+
+    fn output_channels(&self) -> channels::OutputChannels;
+
+    /// Change headphone crossfeed; heard on the next output block
+    fn set_crossfeed(&self, settings: crossfeed::CrossfeedSettings);
+
+    fn crossfeed(&self) -> crossfeed::CrossfeedSettings;
+
+    /// Dither output streams narrower than the audio
+    fn set_dither(&self, enabled: bool);
+
+    fn is_dither_enabled(&self) -> bool;
+
+    /// The output chain's settings, for rendering with them off-line
+    fn chain_controls(&self) -> chain::ChainControls;
+
+    /// Suspend the output stream once paused or stopped this long (zero = never)
+    fn set_suspend_output_after(&self, after: Duration);
+
+    fn spectrum_tap(&self) -> std::sync::Arc<spectrum::SpectrumTap>;
+
+    fn latest_decoded(&self, frames: usize) -> crate::models::AudioBuffer;
+
+    fn output_levels(&self) -> crate::models::ChannelLevels;
+
+    /// Whether the current file can be seeked
+    fn supports_seek(&self) -> bool;
+
+    fn update_performance_monitoring(&self);
+
+    fn get_performance_report(&self) -> PerformanceReport;
+
+    /// Names of the output devices found by the last refresh
+    fn list_devices(&self) -> Vec<String>;
+
+    /// Name of the selected output device
+    fn current_device_name(&self) -> Result<Option<String>, AudioError>;
+
+    /// Name of the system's default output device
+    fn default_device_name(&self) -> Option<String>;
+
+    /// Look for added and removed devices
+    fn refresh_devices(&mut self) -> Result<(), AudioError>;
+
+    fn device_capabilities(&self, device_name: &str) -> Option<DeviceCapabilities>;
+}
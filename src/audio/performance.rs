@@ -1,7 +1,12 @@
-use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use std::collections::VecDeque;
+use std::fmt::Display;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use sysinfo::{System, SystemExt, ProcessExt, CpuExt};
 
 /// Performance profiler for high-resolution audio processing
@@ -23,7 +28,16 @@ pub struct AudioPerformanceProfiler {
     
     // Buffer performance
     buffer_underruns: AtomicUsize,
+    clipped_samples: AtomicU64,
+    abandoned_decoder_threads: AtomicUsize,
+    output_buffer_frames: AtomicU32,
+    output_latency_ms: AtomicU32, // f32 bits
+    ring_buffer_ms: AtomicU64,
+    target_buffer_ms: AtomicU64,
+    buffer_adjustments: AtomicUsize,
     buffer_fill_times: Arc<Mutex<VecDeque<Duration>>>,
+    buffer_fill_levels: Arc<Mutex<VecDeque<f32>>>,
+    last_glitch: Mutex<Instant>,
     
     // High-res specific metrics
     high_res_decode_times: Arc<Mutex<VecDeque<Duration>>>,
@@ -32,12 +46,17 @@ pub struct AudioPerformanceProfiler {
 }
 
 /// Performance statistics for specific configurations
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformanceStats {
+    #[serde(rename = "avg_decode_ms", with = "duration_millis")]
     pub avg_decode_time: Duration,
+    #[serde(rename = "max_decode_ms", with = "duration_millis")]
     pub max_decode_time: Duration,
+    #[serde(rename = "min_decode_ms", with = "duration_millis")]
     pub min_decode_time: Duration,
+    #[serde(rename = "samples")]
     pub sample_count: usize,
+    #[serde(rename = "total_ms", with = "duration_millis")]
     pub total_time: Duration,
 }
 
@@ -86,7 +105,16 @@ impl AudioPerformanceProfiler {
             decode_count: AtomicUsize::new(0),
             
             buffer_underruns: AtomicUsize::new(0),
+            clipped_samples: AtomicU64::new(0),
+            abandoned_decoder_threads: AtomicUsize::new(0),
+            output_buffer_frames: AtomicU32::new(0),
+            output_latency_ms: AtomicU32::new(0.0f32.to_bits()),
+            ring_buffer_ms: AtomicU64::new(0),
+            target_buffer_ms: AtomicU64::new(0),
+            buffer_adjustments: AtomicUsize::new(0),
             buffer_fill_times: Arc::new(Mutex::new(VecDeque::new())),
+            buffer_fill_levels: Arc::new(Mutex::new(VecDeque::new())),
+            last_glitch: Mutex::new(Instant::now()),
             
             high_res_decode_times: Arc::new(Mutex::new(VecDeque::new())),
             sample_rate_performance: Arc::new(Mutex::new(std::collections::HashMap::new())),
@@ -179,6 +207,43 @@ impl AudioPerformanceProfiler {
     /// Record buffer underrun
     pub fn record_buffer_underrun(&self) {
         self.buffer_underruns.fetch_add(1, Ordering::Relaxed);
+        *self.last_glitch.lock().unwrap() = Instant::now();
+    }
+
+    /// Update the clipped sample count for the current track
+    pub fn update_clipped_samples(&self, count: u64) {
+        self.clipped_samples.store(count, Ordering::Relaxed);
+    }
+
+    /// Get clipped sample count for the current track
+    pub fn clipped_sample_count(&self) -> u64 {
+        self.clipped_samples.load(Ordering::Relaxed)
+    }
+
+    /// Update the number of stuck decoder threads the engine has detached
+    pub fn update_abandoned_decoder_threads(&self, count: usize) {
+        self.abandoned_decoder_threads.store(count, Ordering::Relaxed);
+    }
+
+    /// Update the effective output buffer size, its latency and the ring buffer levels
+    pub fn update_buffer_settings(&self, output_buffer_frames: u32, output_latency_ms: f32, ring_buffer_ms: u64, target_buffer_ms: u64) {
+        self.output_buffer_frames.store(output_buffer_frames, Ordering::Relaxed);
+        self.output_latency_ms.store(output_latency_ms.to_bits(), Ordering::Relaxed);
+        self.ring_buffer_ms.store(ring_buffer_ms, Ordering::Relaxed);
+        self.target_buffer_ms.store(target_buffer_ms, Ordering::Relaxed);
+    }
+
+    /// Set the underrun total counted by the output callback
+    pub fn update_buffer_underruns(&self, count: usize) {
+        if self.buffer_underruns.swap(count, Ordering::Relaxed) < count {
+            *self.last_glitch.lock().unwrap() = Instant::now();
+        }
+    }
+
+    /// Record an adaptive buffer adjustment to a new target level
+    pub fn record_buffer_adjustment(&self, new_target_ms: u64) {
+        self.buffer_adjustments.fetch_add(1, Ordering::Relaxed);
+        self.target_buffer_ms.store(new_target_ms, Ordering::Relaxed);
     }
 
     /// Record buffer fill time
@@ -190,6 +255,29 @@ impl AudioPerformanceProfiler {
         }
     }
 
+    /// Record a ring buffer fill sample (0-100%)
+    pub fn record_buffer_fill_level(&self, percent: f32) {
+        let mut fill_levels = self.buffer_fill_levels.lock().unwrap();
+        fill_levels.push_back(percent);
+        if fill_levels.len() > self.max_cpu_samples {
+            fill_levels.pop_front();
+        }
+    }
+
+    /// Average ring buffer fill over recent samples, in percent
+    pub fn average_buffer_fill_percent(&self) -> f32 {
+        let fill_levels = self.buffer_fill_levels.lock().unwrap();
+        if fill_levels.is_empty() {
+            return 0.0;
+        }
+        fill_levels.iter().sum::<f32>() / fill_levels.len() as f32
+    }
+
+    /// Time since the last underrun, or since monitoring started
+    pub fn glitch_free_duration(&self) -> Duration {
+        self.last_glitch.lock().unwrap().elapsed()
+    }
+
     /// Get current CPU usage percentage
     pub fn current_cpu_usage(&self) -> f32 {
         let cpu_samples = self.cpu_samples.lock().unwrap();
@@ -207,6 +295,11 @@ impl AudioPerformanceProfiler {
         sum / cpu_samples.len() as f32
     }
 
+    /// Highest CPU usage over recent samples
+    pub fn peak_cpu_usage(&self) -> f32 {
+        self.cpu_samples.lock().unwrap().iter().copied().fold(0.0, f32::max)
+    }
+
     /// Get current memory usage in bytes
     pub fn current_memory_usage(&self) -> u64 {
         self.memory_usage.load(Ordering::Relaxed)
@@ -228,11 +321,27 @@ impl AudioPerformanceProfiler {
         Duration::from_nanos(total_nanos / count as u64)
     }
 
+    /// Total time spent decoding since the last reset
+    pub fn total_decode_time(&self) -> Duration {
+        Duration::from_nanos(self.total_decode_time.load(Ordering::Relaxed))
+    }
+
     /// Get recent decode times
     pub fn recent_decode_times(&self) -> Vec<Duration> {
         self.decode_times.lock().unwrap().iter().copied().collect()
     }
 
+    /// 95th percentile of recent decode times
+    pub fn p95_decode_time(&self) -> Duration {
+        let mut times = self.recent_decode_times();
+        if times.is_empty() {
+            return Duration::ZERO;
+        }
+        times.sort();
+        let rank = (times.len() * 95).div_ceil(100);
+        times[rank.saturating_sub(1)]
+    }
+
     /// Get high-resolution decode performance
     pub fn high_res_decode_performance(&self) -> Vec<Duration> {
         self.high_res_decode_times.lock().unwrap().iter().copied().collect()
@@ -259,7 +368,9 @@ impl AudioPerformanceProfiler {
         let memory_usage = self.current_memory_usage();
         let peak_memory = self.peak_memory_usage();
         let avg_decode_time = self.average_decode_time();
+        let p95_decode_time = self.p95_decode_time();
         let underrun_count = self.buffer_underrun_count();
+        let healthy = self.is_performance_healthy();
         
         let high_res_times = self.high_res_decode_performance();
         let high_res_avg = if !high_res_times.is_empty() {
@@ -274,6 +385,7 @@ impl AudioPerformanceProfiler {
         for (&rate, stats) in sample_rate_perf.iter() {
             sample_rate_summary.push((rate, stats.clone()));
         }
+        sample_rate_summary.sort_by_key(|(rate, _)| *rate);
         
         // Get bit depth performance summary
         let bit_depth_perf = self.bit_depth_performance.lock().unwrap();
@@ -281,17 +393,31 @@ impl AudioPerformanceProfiler {
         for (&depth, stats) in bit_depth_perf.iter() {
             bit_depth_summary.push((depth, stats.clone()));
         }
+        bit_depth_summary.sort_by_key(|(depth, _)| *depth);
         
         PerformanceReport {
             cpu_usage_percent: cpu_usage,
+            peak_cpu_percent: self.peak_cpu_usage(),
             memory_usage_bytes: memory_usage,
             peak_memory_bytes: peak_memory,
+            total_decode_time: self.total_decode_time(),
             average_decode_time: avg_decode_time,
+            p95_decode_time,
             high_res_average_decode_time: high_res_avg,
             buffer_underruns: underrun_count,
+            glitch_free_duration: self.glitch_free_duration(),
+            clipped_samples: self.clipped_sample_count(),
+            abandoned_decoder_threads: self.abandoned_decoder_threads.load(Ordering::Relaxed),
+            output_buffer_frames: self.output_buffer_frames.load(Ordering::Relaxed),
+            output_latency_ms: f32::from_bits(self.output_latency_ms.load(Ordering::Relaxed)),
+            ring_buffer_ms: self.ring_buffer_ms.load(Ordering::Relaxed),
+            target_buffer_ms: self.target_buffer_ms.load(Ordering::Relaxed),
+            buffer_adjustments: self.buffer_adjustments.load(Ordering::Relaxed),
+            average_buffer_fill_percent: self.average_buffer_fill_percent(),
             total_decodes: self.decode_count.load(Ordering::Relaxed),
             sample_rate_performance: sample_rate_summary,
             bit_depth_performance: bit_depth_summary,
+            healthy,
         }
     }
 
@@ -331,6 +457,8 @@ impl AudioPerformanceProfiler {
         self.decode_times.lock().unwrap().clear();
         self.cpu_samples.lock().unwrap().clear();
         self.buffer_fill_times.lock().unwrap().clear();
+        self.buffer_fill_levels.lock().unwrap().clear();
+        *self.last_glitch.lock().unwrap() = Instant::now();
         self.high_res_decode_times.lock().unwrap().clear();
         self.sample_rate_performance.lock().unwrap().clear();
         self.bit_depth_performance.lock().unwrap().clear();
@@ -354,17 +482,42 @@ impl<'a> DecodeProfiler<'a> {
 }
 
 /// Comprehensive performance report
-#[derive(Debug, Clone)]
+///
+/// Serializes with times in milliseconds and per-format stats keyed by sample rate or bit depth.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformanceReport {
     pub cpu_usage_percent: f32,
+    pub peak_cpu_percent: f32,
     pub memory_usage_bytes: u64,
     pub peak_memory_bytes: u64,
+    #[serde(rename = "total_decode_ms", with = "duration_millis")]
+    pub total_decode_time: Duration,
+    #[serde(rename = "average_decode_ms", with = "duration_millis")]
     pub average_decode_time: Duration,
+    #[serde(rename = "p95_decode_ms", with = "duration_millis")]
+    pub p95_decode_time: Duration,
+    #[serde(rename = "high_res_average_decode_ms", with = "duration_millis")]
     pub high_res_average_decode_time: Duration,
     pub buffer_underruns: usize,
+    /// Time since the last underrun
+    #[serde(rename = "glitch_free_ms", with = "duration_millis")]
+    pub glitch_free_duration: Duration,
+    pub clipped_samples: u64,
+    /// Decoder threads left stuck in a decode; each one still holds its stack and decoder
+    pub abandoned_decoder_threads: usize,
+    pub output_buffer_frames: u32,
+    pub output_latency_ms: f32,
+    pub ring_buffer_ms: u64,
+    pub target_buffer_ms: u64,
+    pub buffer_adjustments: usize,
+    pub average_buffer_fill_percent: f32,
     pub total_decodes: usize,
+    #[serde(with = "keyed_stats")]
     pub sample_rate_performance: Vec<(u32, PerformanceStats)>,
+    #[serde(with = "keyed_stats")]
     pub bit_depth_performance: Vec<(u16, PerformanceStats)>,
+    /// CPU, decode times and underruns are within real-time limits
+    pub healthy: bool,
 }
 
 impl PerformanceReport {
@@ -373,12 +526,27 @@ impl PerformanceReport {
         let mut report = String::new();
         
         report.push_str("=== Audio Performance Report ===\n");
-        report.push_str(&format!("CPU Usage: {:.1}%\n", self.cpu_usage_percent));
+        report.push_str(&format!("CPU Usage: {:.1}% (peak {:.1}%)\n", self.cpu_usage_percent, self.peak_cpu_percent));
         report.push_str(&format!("Memory Usage: {:.2} MB\n", self.memory_usage_bytes as f64 / 1024.0 / 1024.0));
         report.push_str(&format!("Peak Memory: {:.2} MB\n", self.peak_memory_bytes as f64 / 1024.0 / 1024.0));
-        report.push_str(&format!("Average Decode Time: {:.2}ms\n", self.average_decode_time.as_millis()));
-        report.push_str(&format!("High-Res Decode Time: {:.2}ms\n", self.high_res_average_decode_time.as_millis()));
+        report.push_str(&format!("Average Decode Time: {:.2}ms\n", millis(self.average_decode_time)));
+        report.push_str(&format!("P95 Decode Time: {:.2}ms\n", millis(self.p95_decode_time)));
+        report.push_str(&format!("High-Res Decode Time: {:.2}ms\n", millis(self.high_res_average_decode_time)));
+        report.push_str(&format!("Total Decode Time: {:.2}ms\n", millis(self.total_decode_time)));
         report.push_str(&format!("Buffer Underruns: {}\n", self.buffer_underruns));
+        report.push_str(&format!("Glitch-Free For: {:.1}s\n", self.glitch_free_duration.as_secs_f64()));
+        report.push_str(&format!("Clipped Samples: {}\n", self.clipped_samples));
+        if self.abandoned_decoder_threads > 0 {
+            report.push_str(&format!("Abandoned Decoder Threads: {}\n", self.abandoned_decoder_threads));
+        }
+        if self.output_buffer_frames > 0 {
+            report.push_str(&format!("Output Buffer: {} frames ({:.1}ms)\n", self.output_buffer_frames, self.output_latency_ms));
+        } else {
+            report.push_str("Output Buffer: device default\n");
+        }
+        report.push_str(&format!("Ring Buffer: {}ms (target {}ms)\n", self.ring_buffer_ms, self.target_buffer_ms));
+        report.push_str(&format!("Average Buffer Fill: {:.1}%\n", self.average_buffer_fill_percent));
+        report.push_str(&format!("Buffer Adjustments: {}\n", self.buffer_adjustments));
         report.push_str(&format!("Total Decodes: {}\n", self.total_decodes));
         
         if !self.sample_rate_performance.is_empty() {
@@ -387,8 +555,8 @@ impl PerformanceReport {
                 report.push_str(&format!(
                     "{} Hz: avg={:.2}ms, max={:.2}ms, samples={}\n",
                     rate,
-                    stats.avg_decode_time.as_millis(),
-                    stats.max_decode_time.as_millis(),
+                    millis(stats.avg_decode_time),
+                    millis(stats.max_decode_time),
                     stats.sample_count
                 ));
             }
@@ -400,15 +568,100 @@ impl PerformanceReport {
                 report.push_str(&format!(
                     "{}-bit: avg={:.2}ms, max={:.2}ms, samples={}\n",
                     depth,
-                    stats.avg_decode_time.as_millis(),
-                    stats.max_decode_time.as_millis(),
+                    millis(stats.avg_decode_time),
+                    millis(stats.max_decode_time),
                     stats.sample_count
                 ));
             }
         }
-        
+
+        report.push_str(&format!("\nHealth: {}\n", if self.healthy { "OK" } else { "DEGRADED" }));
         report
     }
+
+    /// Serialize the report as pretty-printed JSON, with times in milliseconds
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Serialize the headline figures as CSV: a header row and one row of values
+    pub fn to_csv(&self) -> String {
+        let columns = [
+            ("cpu_usage_percent", format!("{:.1}", self.cpu_usage_percent)),
+            ("peak_cpu_percent", format!("{:.1}", self.peak_cpu_percent)),
+            ("memory_usage_bytes", self.memory_usage_bytes.to_string()),
+            ("peak_memory_bytes", self.peak_memory_bytes.to_string()),
+            ("total_decode_ms", format!("{:.3}", millis(self.total_decode_time))),
+            ("average_decode_ms", format!("{:.3}", millis(self.average_decode_time))),
+            ("p95_decode_ms", format!("{:.3}", millis(self.p95_decode_time))),
+            ("buffer_underruns", self.buffer_underruns.to_string()),
+            ("glitch_free_ms", format!("{:.0}", millis(self.glitch_free_duration))),
+            ("average_buffer_fill_percent", format!("{:.1}", self.average_buffer_fill_percent)),
+            ("clipped_samples", self.clipped_samples.to_string()),
+            ("abandoned_decoder_threads", self.abandoned_decoder_threads.to_string()),
+            ("total_decodes", self.total_decodes.to_string()),
+            ("healthy", self.healthy.to_string()),
+        ];
+        let header: Vec<&str> = columns.iter().map(|(name, _)| *name).collect();
+        let values: Vec<&str> = columns.iter().map(|(_, value)| value.as_str()).collect();
+        format!("{}\n{}\n", header.join(","), values.join(","))
+    }
+
+    /// Write the report to `path` as JSON or CSV, chosen by the file extension
+    pub fn write_to(&self, path: &Path) -> io::Result<()> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).map(str::to_lowercase);
+        let contents = match extension.as_deref() {
+            Some("json") => self.to_json()? + "\n",
+            Some("csv") => self.to_csv(),
+            _ => return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot export a report to '{}': use a .json or .csv file", path.display()),
+            )),
+        };
+        std::fs::write(path, contents)
+    }
+}
+
+/// Serde helpers for durations stored as fractional milliseconds
+mod duration_millis {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(millis(*duration))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let ms = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(ms / 1000.0).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Serde helpers for per-format stats, written as a map keyed by sample rate or bit depth
+mod keyed_stats {
+    use super::*;
+
+    pub fn serialize<K: Display, S: Serializer>(stats: &[(K, PerformanceStats)], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(stats.iter().map(|(key, stats)| (key.to_string(), stats)))
+    }
+
+    pub fn deserialize<'de, K, D>(deserializer: D) -> Result<Vec<(K, PerformanceStats)>, D::Error>
+    where
+        K: FromStr + Ord,
+        K::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let map = std::collections::HashMap::<String, PerformanceStats>::deserialize(deserializer)?;
+        let mut stats = map.into_iter()
+            .map(|(key, stats)| key.parse().map(|key| (key, stats)).map_err(serde::de::Error::custom))
+            .collect::<Result<Vec<(K, PerformanceStats)>, D::Error>>()?;
+        stats.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(stats)
+    }
+}
+
+/// Duration in fractional milliseconds
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
 }
 
 #[cfg(test)]
@@ -554,6 +807,141 @@ mod tests {
         assert!(formatted.contains("Memory Usage"));
     }
 
+    #[test]
+    fn test_buffer_settings_in_report() {
+        let profiler = AudioPerformanceProfiler::new();
+        profiler.update_buffer_settings(2048, 42.7, 500, 300);
+
+        let report = profiler.performance_report();
+        assert_eq!(report.output_buffer_frames, 2048);
+        assert_eq!(report.ring_buffer_ms, 500);
+        assert!(report.format_report().contains("Output Buffer: 2048 frames (42.7ms)"));
+
+        profiler.record_buffer_adjustment(450);
+        let report = profiler.performance_report();
+        assert_eq!(report.buffer_adjustments, 1);
+        assert_eq!(report.target_buffer_ms, 450);
+
+        profiler.update_buffer_settings(0, 0.0, 500, 300);
+        assert!(profiler.performance_report().format_report().contains("Output Buffer: device default"));
+    }
+
+    #[test]
+    fn test_p95_decode_time() {
+        let profiler = AudioPerformanceProfiler::new();
+        assert_eq!(profiler.p95_decode_time(), Duration::ZERO);
+
+        for ms in 1..=20 {
+            profiler.record_decode_performance(Duration::from_millis(ms), 44100, 16, false);
+        }
+        assert_eq!(profiler.p95_decode_time(), Duration::from_millis(19));
+    }
+
+    #[test]
+    fn test_synthetic_report_formatting() {
+        let mut stats = PerformanceStats::new();
+        stats.update(Duration::from_micros(1500));
+        let report = PerformanceReport {
+            cpu_usage_percent: 12.5,
+            peak_cpu_percent: 30.0,
+            memory_usage_bytes: 3 * 1024 * 1024,
+            peak_memory_bytes: 4 * 1024 * 1024,
+            total_decode_time: Duration::from_millis(50),
+            average_decode_time: Duration::from_micros(1250),
+            p95_decode_time: Duration::from_micros(2750),
+            high_res_average_decode_time: Duration::from_micros(1500),
+            buffer_underruns: 2,
+            glitch_free_duration: Duration::from_secs(90),
+            clipped_samples: 0,
+            abandoned_decoder_threads: 1,
+            output_buffer_frames: 1024,
+            output_latency_ms: 5.3,
+            ring_buffer_ms: 1000,
+            target_buffer_ms: 300,
+            buffer_adjustments: 0,
+            average_buffer_fill_percent: 75.0,
+            total_decodes: 40,
+            sample_rate_performance: vec![(192000, stats.clone())],
+            bit_depth_performance: vec![(24, stats)],
+            healthy: false,
+        };
+
+        let formatted = report.format_report();
+        assert!(formatted.contains("CPU Usage: 12.5%"));
+        assert!(formatted.contains("Memory Usage: 3.00 MB"));
+        assert!(formatted.contains("Average Decode Time: 1.25ms"));
+        assert!(formatted.contains("P95 Decode Time: 2.75ms"));
+        assert!(formatted.contains("Buffer Underruns: 2"));
+        assert!(formatted.contains("Abandoned Decoder Threads: 1"));
+        assert!(formatted.contains("192000 Hz: avg=1.50ms, max=1.50ms, samples=1"));
+        assert!(formatted.contains("Health: DEGRADED"));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["p95_decode_ms"], 2.75);
+        assert_eq!(json["memory_usage_bytes"], 3 * 1024 * 1024);
+        assert_eq!(json["sample_rate_performance"]["192000"]["samples"], 1);
+        assert_eq!(json["healthy"], false);
+        assert_eq!(json["glitch_free_ms"], 90_000.0);
+        let parsed: PerformanceReport = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.p95_decode_time, report.p95_decode_time);
+        assert_eq!(parsed.bit_depth_performance[0].0, 24);
+    }
+
+    #[test]
+    fn test_report_export_round_trip() {
+        let profiler = AudioPerformanceProfiler::new();
+
+        // Simulated playback: decodes, buffer fill samples and one underrun
+        profiler.update_memory_usage(8 * 1024 * 1024);
+        for _ in 0..10 {
+            profiler.record_decode_performance(Duration::from_micros(800), 96000, 24, true);
+            profiler.record_buffer_fill_level(60.0);
+        }
+        profiler.record_buffer_fill_level(80.0);
+        profiler.update_buffer_underruns(1);
+        thread::sleep(Duration::from_millis(5));
+
+        let report = profiler.performance_report();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        let json_path = temp_dir.path().join("perf.json");
+        report.write_to(&json_path).unwrap();
+        let exported: PerformanceReport = serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(exported.total_decode_time, Duration::from_millis(8));
+        assert_eq!(exported.memory_usage_bytes, 8 * 1024 * 1024);
+        assert_eq!(exported.buffer_underruns, 1);
+        assert!(exported.glitch_free_duration >= Duration::from_millis(5));
+        assert!(exported.average_buffer_fill_percent > 60.0);
+        assert_eq!(exported.sample_rate_performance[0].0, 96000);
+        assert_eq!(exported.sample_rate_performance[0].1.sample_count, 10);
+
+        let csv_path = temp_dir.path().join("perf.CSV");
+        report.write_to(&csv_path).unwrap();
+        let csv = std::fs::read_to_string(&csv_path).unwrap();
+        let mut lines = csv.lines();
+        let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+        let values: Vec<&str> = lines.next().unwrap().split(',').collect();
+        let column = |name: &str| values[header.iter().position(|column| *column == name).unwrap()].parse::<f64>().unwrap();
+        assert_eq!(column("total_decode_ms"), 8.0);
+        assert_eq!(column("memory_usage_bytes"), (8 * 1024 * 1024) as f64);
+        assert_eq!(column("buffer_underruns"), 1.0);
+        assert!(column("average_buffer_fill_percent") > 60.0);
+        assert!(column("glitch_free_ms") >= 5.0);
+
+        assert!(report.write_to(&temp_dir.path().join("perf.txt")).is_err());
+    }
+
+    #[test]
+    fn test_glitch_free_duration_restarts_on_underrun() {
+        let profiler = AudioPerformanceProfiler::new();
+        thread::sleep(Duration::from_millis(20));
+        profiler.update_buffer_underruns(0);
+        assert!(profiler.glitch_free_duration() >= Duration::from_millis(20));
+
+        profiler.update_buffer_underruns(1);
+        assert!(profiler.glitch_free_duration() < Duration::from_millis(20));
+    }
+
     #[test]
     fn test_stats_reset() {
         let profiler = AudioPerformanceProfiler::new();
@@ -1,577 +1,461 @@
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
-use tokio::time::interval;
-use crate::models::{PlayerStatus, PlaybackState};
-
-/// Real-time position tracker for audio playback
-#[derive(Debug, Clone)]
+use std::sync::atomic::{fence, AtomicU32, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use crate::models::LoopRegion;
+
+/// Playback position advanced by the audio callback without locking
+///
+/// The callback only adds to a count of frames consumed that never goes back. A seek records a
+/// base: the position it moved to and the count at that moment. The position is then
+/// `base_position + (samples_consumed - base_samples_consumed) / sample_rate`, converted on the
+/// reader side so no rounding accumulates per callback.
+///
+/// The base changes from the control and decoder threads while the callback keeps counting,
+/// so it is published as a whole: writers take `write_lock` and bump `sequence` around their
+/// stores, and readers retry until they see the same even sequence before and after reading.
+/// A reader never pairs a new base position with an old base count.
+///
+/// While an A-B loop is set the decoder feeds the loop again after B, so positions past B are
+/// folded back into the loop when read.
+#[derive(Debug)]
 pub struct PositionTracker {
-    inner: Arc<Mutex<PositionTrackerInner>>,
+    /// Frames the output has played; only ever grows
+    samples_consumed: AtomicU64,
+    /// Odd while a writer is changing the fields below
+    sequence: AtomicU64,
+    base_position_nanos: AtomicU64,
+    base_samples_consumed: AtomicU64,
+    sample_rate: AtomicU32,
+    /// Skip that counts once `samples_consumed` reaches `pending_skip_at`; 0 = none
+    pending_skip_nanos: AtomicU64,
+    pending_skip_at: AtomicU64,
+    loop_start_nanos: AtomicU64,
+    /// 0 = no loop
+    loop_end_nanos: AtomicU64,
+    write_lock: Mutex<()>,
 }
 
-#[derive(Debug)]
-struct PositionTrackerInner {
-    /// Current playback position
-    position: Duration,
-    /// When the position was last updated
-    last_update: Instant,
-    /// Current playback state
-    state: PlaybackState,
-    /// Track duration for bounds checking
-    duration: Duration,
-    /// Whether position tracking is active
-    active: bool,
+/// The fields of a `PositionTracker` that change together
+#[derive(Debug, Clone, Copy)]
+struct Base {
+    position_nanos: u64,
+    samples_consumed: u64,
+    sample_rate: u32,
+    pending_skip_nanos: u64,
+    pending_skip_at: u64,
+    loop_start_nanos: u64,
+    loop_end_nanos: u64,
 }
 
 impl PositionTracker {
-    /// Create a new position tracker
-    pub fn new() -> Self {
+    /// Create a position at zero for output running at `sample_rate`
+    pub fn new(sample_rate: u32) -> Self {
         Self {
-            inner: Arc::new(Mutex::new(PositionTrackerInner {
-                position: Duration::from_secs(0),
-                last_update: Instant::now(),
-                state: PlaybackState::Stopped,
-                duration: Duration::from_secs(0),
-                active: false,
-            })),
+            samples_consumed: AtomicU64::new(0),
+            sequence: AtomicU64::new(0),
+            base_position_nanos: AtomicU64::new(0),
+            base_samples_consumed: AtomicU64::new(0),
+            sample_rate: AtomicU32::new(sample_rate.max(1)),
+            pending_skip_nanos: AtomicU64::new(0),
+            pending_skip_at: AtomicU64::new(0),
+            loop_start_nanos: AtomicU64::new(0),
+            loop_end_nanos: AtomicU64::new(0),
+            write_lock: Mutex::new(()),
         }
     }
 
-    /// Start tracking position for a new track
-    pub fn start_tracking(&self, initial_position: Duration, duration: Duration) {
-        if let Ok(mut inner) = self.inner.lock() {
-            inner.position = initial_position;
-            inner.duration = duration;
-            inner.last_update = Instant::now();
-            inner.state = PlaybackState::Playing;
-            inner.active = true;
-        }
+    /// Count frames played by the output (called from the audio callback)
+    pub fn advance(&self, frames: u64) {
+        self.samples_consumed.fetch_add(frames, Ordering::Relaxed);
     }
 
-    /// Stop position tracking
-    pub fn stop_tracking(&self) {
-        if let Ok(mut inner) = self.inner.lock() {
-            inner.active = false;
-            inner.state = PlaybackState::Stopped;
-            inner.position = Duration::from_secs(0);
-        }
+    /// Move the position (seek or stop)
+    pub fn set(&self, position: Duration) {
+        self.update(|base, consumed| Base {
+            position_nanos: position.as_nanos() as u64,
+            samples_consumed: consumed,
+            pending_skip_nanos: 0,
+            ..base
+        });
     }
 
-    /// Pause position tracking
-    pub fn pause(&self) {
-        if let Ok(mut inner) = self.inner.lock() {
-            // Update position before pausing
-            if inner.state == PlaybackState::Playing {
-                let elapsed = inner.last_update.elapsed();
-                inner.position = inner.position.saturating_add(elapsed);
-                inner.position = inner.position.min(inner.duration);
+    /// Move the position ahead by audio cut after the next `frames_ahead` frames to play
+    ///
+    /// The decoder cuts audio ahead of the output; the position only jumps once the output
+    /// gets there, so it stays put while paused with the buffer full. A skip still waiting
+    /// when another comes in is counted at the later one's frame.
+    pub fn skip_after(&self, frames_ahead: u64, by: Duration) {
+        self.update(|base, consumed| {
+            let (position_nanos, waiting) = if consumed >= base.pending_skip_at {
+                (base.position_nanos + base.pending_skip_nanos, 0)
+            } else {
+                (base.position_nanos, base.pending_skip_nanos)
+            };
+            Base {
+                position_nanos,
+                pending_skip_nanos: waiting + by.as_nanos() as u64,
+                pending_skip_at: consumed + frames_ahead,
+                ..base
             }
-            inner.state = PlaybackState::Paused;
-            inner.last_update = Instant::now();
-        }
-    }
-
-    /// Resume position tracking
-    pub fn resume(&self) {
-        if let Ok(mut inner) = self.inner.lock() {
-            inner.state = PlaybackState::Playing;
-            inner.last_update = Instant::now();
-        }
+        });
     }
 
-    /// Seek to a specific position
-    pub fn seek(&self, position: Duration) -> Result<(), String> {
-        if let Ok(mut inner) = self.inner.lock() {
-            // For zero-duration tracks, only allow seeking to position 0
-            if inner.duration.as_secs() == 0 && position.as_secs() > 0 {
-                return Err(format!(
-                    "Cannot seek to position {:.2}s in zero-duration track",
-                    position.as_secs_f64()
-                ));
-            }
-            
-            // For tracks with known duration, validate against it
-            if inner.duration.as_secs() > 0 && position > inner.duration {
-                return Err(format!(
-                    "Seek position {:.2}s exceeds track duration {:.2}s",
-                    position.as_secs_f64(),
-                    inner.duration.as_secs_f64()
-                ));
-            }
-            
-            inner.position = position.min(inner.duration);
-            inner.last_update = Instant::now();
-            Ok(())
-        } else {
-            Err("Failed to acquire position tracker lock".to_string())
-        }
+    /// Change the output sample rate, keeping the current position
+    pub fn set_sample_rate(&self, sample_rate: u32) {
+        self.update(|base, consumed| Base {
+            position_nanos: base.position_at(consumed).as_nanos() as u64,
+            samples_consumed: consumed,
+            sample_rate: sample_rate.max(1),
+            pending_skip_nanos: 0,
+            ..base
+        });
     }
 
-    /// Seek to a specific position with validation
-    pub fn seek_validated(&self, position: Duration) -> Result<Duration, String> {
-        if let Ok(inner) = self.inner.lock() {
-            let duration = inner.duration;
-            drop(inner); // Release lock before calling seek
-            
-            // For zero-duration tracks, only allow seeking to position 0
-            if duration.as_secs() == 0 && position.as_secs() > 0 {
-                return Err(format!(
-                    "Cannot seek to position {:.2}s in zero-duration track",
-                    position.as_secs_f64()
-                ));
-            }
-            
-            // For tracks with known duration, validate against it
-            if duration.as_secs() > 0 && position > duration {
-                return Err(format!(
-                    "Seek position {:.2}s exceeds track duration {:.2}s",
-                    position.as_secs_f64(),
-                    duration.as_secs_f64()
-                ));
-            }
-            
-            let clamped_position = position.min(duration);
-            self.seek(clamped_position)?;
-            Ok(clamped_position)
-        } else {
-            Err("Failed to acquire position tracker lock".to_string())
+    /// Current position
+    pub fn position(&self) -> Duration {
+        let base = self.base();
+        let position = base.position_at(self.samples_consumed.load(Ordering::Relaxed));
+        match base.loop_region() {
+            Some(region) => region.wrap(position),
+            None => position,
         }
     }
 
-    /// Get current position (calculated in real-time)
-    pub fn current_position(&self) -> Duration {
-        if let Ok(mut inner) = self.inner.lock() {
-            if inner.state == PlaybackState::Playing && inner.active {
-                let elapsed = inner.last_update.elapsed();
-                inner.position = inner.position.saturating_add(elapsed);
-                inner.position = inner.position.min(inner.duration);
-                inner.last_update = Instant::now();
+    /// Set or clear the A-B loop, keeping the current position
+    pub fn set_loop(&self, region: Option<LoopRegion>) {
+        let (start, end) = region.map_or((0, 0), |region| (region.start.as_nanos() as u64, region.end.as_nanos() as u64));
+        self.update(|base, consumed| {
+            let position = base.position_at(consumed);
+            let position = base.loop_region().map_or(position, |region| region.wrap(position));
+            Base {
+                position_nanos: position.as_nanos() as u64,
+                samples_consumed: consumed,
+                pending_skip_nanos: 0,
+                loop_start_nanos: start,
+                loop_end_nanos: end,
+                ..base
             }
-            inner.position
-        } else {
-            Duration::from_secs(0)
-        }
-    }
-
-    /// Get current playback state
-    pub fn current_state(&self) -> PlaybackState {
-        if let Ok(inner) = self.inner.lock() {
-            inner.state
-        } else {
-            PlaybackState::Stopped
-        }
-    }
-
-    /// Get track duration
-    pub fn duration(&self) -> Duration {
-        if let Ok(inner) = self.inner.lock() {
-            inner.duration
-        } else {
-            Duration::from_secs(0)
-        }
-    }
-
-    /// Check if tracking is active
-    pub fn is_active(&self) -> bool {
-        if let Ok(inner) = self.inner.lock() {
-            inner.active
-        } else {
-            false
-        }
-    }
-
-    /// Update the player status with current position
-    pub fn update_status(&self, status: &mut PlayerStatus) {
-        status.position = self.current_position();
-        status.state = self.current_state();
-    }
-
-    /// Calculate progress as a percentage (0.0 to 1.0)
-    pub fn progress(&self) -> f32 {
-        let position = self.current_position();
-        let duration = self.duration();
-        
-        if duration.as_secs() > 0 {
-            position.as_secs_f32() / duration.as_secs_f32()
-        } else {
-            0.0
-        }
-    }
-
-    /// Get remaining time
-    pub fn remaining_time(&self) -> Duration {
-        let position = self.current_position();
-        let duration = self.duration();
-        duration.saturating_sub(position)
+        });
     }
 
-    /// Check if playback has reached the end
-    pub fn is_finished(&self) -> bool {
-        let position = self.current_position();
-        let duration = self.duration();
-        
-        duration.as_secs() > 0 && position >= duration
+    /// Active A-B loop
+    pub fn loop_region(&self) -> Option<LoopRegion> {
+        self.base().loop_region()
     }
 
-    /// Start a background task for periodic position updates
-    pub async fn start_update_task(
-        &self,
-        mut status_callback: impl FnMut(Duration, PlaybackState) + Send + 'static,
-    ) {
-        let tracker = self.clone();
-        let mut interval = interval(Duration::from_millis(100)); // Update every 100ms
-        
-        tokio::spawn(async move {
-            loop {
-                interval.tick().await;
-                
-                if !tracker.is_active() {
-                    tokio::time::sleep(Duration::from_millis(500)).await;
-                    continue;
-                }
-                
-                let position = tracker.current_position();
-                let state = tracker.current_state();
-                
-                status_callback(position, state);
-                
-                // Check if playback finished
-                if tracker.is_finished() && state == PlaybackState::Playing {
-                    // Notify that track finished
-                    status_callback(tracker.duration(), PlaybackState::Stopped);
-                    break;
-                }
+    /// A consistent copy of the base, retried while a writer is halfway through changing it
+    fn base(&self) -> Base {
+        loop {
+            let before = self.sequence.load(Ordering::Acquire);
+            if before % 2 == 1 {
+                std::hint::spin_loop();
+                continue;
             }
-        });
+            let base = Base {
+                position_nanos: self.base_position_nanos.load(Ordering::Relaxed),
+                samples_consumed: self.base_samples_consumed.load(Ordering::Relaxed),
+                sample_rate: self.sample_rate.load(Ordering::Relaxed),
+                pending_skip_nanos: self.pending_skip_nanos.load(Ordering::Relaxed),
+                pending_skip_at: self.pending_skip_at.load(Ordering::Relaxed),
+                loop_start_nanos: self.loop_start_nanos.load(Ordering::Relaxed),
+                loop_end_nanos: self.loop_end_nanos.load(Ordering::Relaxed),
+            };
+            fence(Ordering::Acquire);
+            if self.sequence.load(Ordering::Relaxed) == before {
+                return base;
+            }
+        }
     }
-}
 
-impl Default for PositionTracker {
-    fn default() -> Self {
-        Self::new()
+    /// Replace the base with `change(base, samples consumed now)`
+    fn update(&self, change: impl FnOnce(Base, u64) -> Base) {
+        let _writer = self.write_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let base = self.base();
+        let next = change(base, self.samples_consumed.load(Ordering::Relaxed));
+
+        let sequence = self.sequence.load(Ordering::Relaxed);
+        self.sequence.store(sequence + 1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        self.base_position_nanos.store(next.position_nanos, Ordering::Relaxed);
+        self.base_samples_consumed.store(next.samples_consumed, Ordering::Relaxed);
+        self.sample_rate.store(next.sample_rate, Ordering::Relaxed);
+        self.pending_skip_nanos.store(next.pending_skip_nanos, Ordering::Relaxed);
+        self.pending_skip_at.store(next.pending_skip_at, Ordering::Relaxed);
+        self.loop_start_nanos.store(next.loop_start_nanos, Ordering::Relaxed);
+        self.loop_end_nanos.store(next.loop_end_nanos, Ordering::Relaxed);
+        self.sequence.store(sequence + 2, Ordering::Release);
     }
 }
 
-/// Position update event for callbacks
-#[derive(Debug, Clone)]
-pub struct PositionUpdate {
-    pub position: Duration,
-    pub state: PlaybackState,
-    pub progress: f32,
-    pub remaining: Duration,
-}
-
-impl PositionUpdate {
-    pub fn new(position: Duration, state: PlaybackState, duration: Duration) -> Self {
-        let progress = if duration.as_secs() > 0 {
-            position.as_secs_f32() / duration.as_secs_f32()
+impl Base {
+    /// Unfolded position once the output has consumed `consumed` frames
+    fn position_at(&self, consumed: u64) -> Duration {
+        let played = consumed.saturating_sub(self.samples_consumed) as u128;
+        let played_nanos = played * 1_000_000_000 / self.sample_rate as u128;
+        let skipped_nanos = if self.pending_skip_nanos > 0 && consumed >= self.pending_skip_at {
+            self.pending_skip_nanos
         } else {
-            0.0
+            0
         };
-        
-        let remaining = duration.saturating_sub(position);
-        
-        Self {
-            position,
-            state,
-            progress,
-            remaining,
-        }
+        Duration::from_nanos(self.position_nanos + skipped_nanos) + Duration::from_nanos(played_nanos as u64)
+    }
+
+    fn loop_region(&self) -> Option<LoopRegion> {
+        LoopRegion::new(Duration::from_nanos(self.loop_start_nanos), Duration::from_nanos(self.loop_end_nanos))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tokio::time::{sleep, Duration as TokioDuration};
+    use std::sync::Arc;
 
-    #[test]
-    fn test_position_tracker_creation() {
-        let tracker = PositionTracker::new();
-        
-        assert_eq!(tracker.current_position(), Duration::from_secs(0));
-        assert_eq!(tracker.current_state(), PlaybackState::Stopped);
-        assert_eq!(tracker.duration(), Duration::from_secs(0));
-        assert!(!tracker.is_active());
+    /// Frames played since the last seek
+    fn frames_played(position: &PositionTracker) -> u64 {
+        position.samples_consumed.load(Ordering::Relaxed).saturating_sub(position.base().samples_consumed)
     }
 
     #[test]
-    fn test_start_tracking() {
-        let tracker = PositionTracker::new();
-        let initial_position = Duration::from_secs(30);
-        let duration = Duration::from_secs(180);
-        
-        tracker.start_tracking(initial_position, duration);
-        
-        assert_eq!(tracker.current_state(), PlaybackState::Playing);
-        assert_eq!(tracker.duration(), duration);
-        assert!(tracker.is_active());
-        
-        // Position should be close to initial position (may have small elapsed time)
-        let current_pos = tracker.current_position();
-        assert!(current_pos >= initial_position);
-        assert!(current_pos <= initial_position + Duration::from_millis(100));
-    }
+    fn test_position_converts_on_read() {
+        let position = PositionTracker::new(48_000);
+        position.advance(24_000);
+        assert_eq!(position.position(), Duration::from_millis(500));
 
-    #[test]
-    fn test_pause_and_resume() {
-        let tracker = PositionTracker::new();
-        tracker.start_tracking(Duration::from_secs(0), Duration::from_secs(180));
-        
-        // Let it play for a bit
-        std::thread::sleep(std::time::Duration::from_millis(50));
-        
-        tracker.pause();
-        assert_eq!(tracker.current_state(), PlaybackState::Paused);
-        
-        let paused_position = tracker.current_position();
-        
-        // Wait while paused - position shouldn't change
-        std::thread::sleep(std::time::Duration::from_millis(50));
-        assert_eq!(tracker.current_position(), paused_position);
-        
-        tracker.resume();
-        assert_eq!(tracker.current_state(), PlaybackState::Playing);
-    }
+        // Seeks keep full precision
+        position.set(Duration::from_micros(1_234_567));
+        assert_eq!(position.position(), Duration::from_micros(1_234_567));
+        assert_eq!(frames_played(&position), 0);
 
-    #[test]
-    fn test_seek() {
-        let tracker = PositionTracker::new();
-        tracker.start_tracking(Duration::from_secs(0), Duration::from_secs(180));
-        
-        let seek_position = Duration::from_secs(60);
-        let result = tracker.seek(seek_position);
-        assert!(result.is_ok());
-        
-        let current_pos = tracker.current_position();
-        assert!(current_pos >= seek_position);
-        assert!(current_pos <= seek_position + Duration::from_millis(100));
+        position.advance(48_000);
+        position.set_sample_rate(96_000);
+        assert_eq!(position.position(), Duration::from_micros(2_234_567));
+        position.advance(96_000);
+        assert_eq!(position.position(), Duration::from_micros(3_234_567));
     }
 
     #[test]
-    fn test_seek_beyond_duration() {
-        let tracker = PositionTracker::new();
-        let duration = Duration::from_secs(180);
-        tracker.start_tracking(Duration::from_secs(0), duration);
-        
-        // Try to seek beyond duration - should return error
-        let result = tracker.seek(Duration::from_secs(300));
-        assert!(result.is_err());
-        
-        // Position should remain unchanged
-        let current_pos = tracker.current_position();
-        assert!(current_pos <= Duration::from_secs(1)); // Should be close to start
+    fn test_new_tracker_starts_at_zero() {
+        let position = PositionTracker::new(44_100);
+        assert_eq!(position.position(), Duration::ZERO);
+        assert_eq!(frames_played(&position), 0);
+        assert_eq!(position.loop_region(), None);
     }
 
     #[test]
-    fn test_seek_validated() {
-        let tracker = PositionTracker::new();
-        let duration = Duration::from_secs(180);
-        tracker.start_tracking(Duration::from_secs(0), duration);
-        
-        // Test valid seek
-        let result = tracker.seek_validated(Duration::from_secs(60));
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), Duration::from_secs(60));
-        
-        // Test seek beyond duration
-        let result = tracker.seek_validated(Duration::from_secs(300));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("exceeds track duration"));
-        
-        // Test seek at exact duration
-        let result = tracker.seek_validated(duration);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), duration);
-    }
+    fn test_position_holds_while_paused_and_continues_on_resume() {
+        let position = PositionTracker::new(48_000);
+        position.advance(48_000);
 
-    #[test]
-    fn test_seek_precision() {
-        let tracker = PositionTracker::new();
-        tracker.start_tracking(Duration::from_secs(0), Duration::from_secs(180));
-        
-        // Test fractional second seeking
-        let seek_position = Duration::from_millis(30500); // 30.5 seconds
-        let result = tracker.seek(seek_position);
-        assert!(result.is_ok());
-        
-        let current_pos = tracker.current_position();
-        assert!(current_pos >= seek_position);
-        assert!(current_pos <= seek_position + Duration::from_millis(100));
-    }
+        // Paused: the callback plays silence and counts nothing, however long it lasts
+        let paused_at = position.position();
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(position.position(), paused_at);
+        assert_eq!(paused_at, Duration::from_secs(1));
 
-    #[test]
-    fn test_seek_zero_duration() {
-        let tracker = PositionTracker::new();
-        tracker.start_tracking(Duration::from_secs(0), Duration::from_secs(0));
-        
-        // Seeking in zero-duration track should work for position 0
-        let result = tracker.seek(Duration::from_secs(0));
-        assert!(result.is_ok());
-        
-        // But not for any positive position
-        let result = tracker.seek(Duration::from_secs(1));
-        assert!(result.is_err());
+        // A seek while paused is where playback picks up
+        position.set(Duration::from_secs(90));
+        assert_eq!(position.position(), Duration::from_secs(90));
+
+        // Resumed
+        position.advance(24_000);
+        assert_eq!(position.position(), Duration::from_millis(90_500));
     }
 
     #[test]
-    fn test_seek_during_playback_states() {
-        let tracker = PositionTracker::new();
-        tracker.start_tracking(Duration::from_secs(0), Duration::from_secs(180));
-        
-        // Seek while playing
-        assert_eq!(tracker.current_state(), PlaybackState::Playing);
-        let result = tracker.seek(Duration::from_secs(60));
-        assert!(result.is_ok());
-        assert_eq!(tracker.current_state(), PlaybackState::Playing); // State should remain
-        
-        // Seek while paused
-        tracker.pause();
-        assert_eq!(tracker.current_state(), PlaybackState::Paused);
-        let result = tracker.seek(Duration::from_secs(90));
-        assert!(result.is_ok());
-        assert_eq!(tracker.current_state(), PlaybackState::Paused); // State should remain
-        
-        // Seek while stopped
-        tracker.stop_tracking();
-        assert_eq!(tracker.current_state(), PlaybackState::Stopped);
-        let result = tracker.seek(Duration::from_secs(30));
-        assert!(result.is_ok()); // Should still work even when stopped
+    fn test_stop_resets_to_the_track_start() {
+        let position = PositionTracker::new(44_100);
+        position.set(Duration::from_secs(30));
+        position.advance(441_000);
+        position.skip_after(100, Duration::from_secs(2));
+        assert_eq!(position.position(), Duration::from_secs(40));
+
+        // Stop rewinds to where the track starts in its file; a cut not yet played is dropped
+        position.set(Duration::from_secs(30));
+        assert_eq!(position.position(), Duration::from_secs(30));
+        assert_eq!(frames_played(&position), 0);
+        position.advance(200);
+        assert_eq!(frames_played(&position), 200);
+        assert!(position.position() < Duration::from_secs(31));
+
+        position.set(Duration::ZERO);
+        assert_eq!(position.position(), Duration::ZERO);
     }
 
     #[test]
-    fn test_stop_tracking() {
-        let tracker = PositionTracker::new();
-        tracker.start_tracking(Duration::from_secs(30), Duration::from_secs(180));
-        
-        assert!(tracker.is_active());
-        
-        tracker.stop_tracking();
-        
-        assert!(!tracker.is_active());
-        assert_eq!(tracker.current_state(), PlaybackState::Stopped);
-        assert_eq!(tracker.current_position(), Duration::from_secs(0));
+    fn test_position_after_a_track_change() {
+        let position = PositionTracker::new(44_100);
+        position.set(Duration::from_secs(170));
+        position.advance(44_100 * 10);
+        assert_eq!(position.position(), Duration::from_secs(180));
+
+        // The next track starts from zero, and its stream may run at another rate
+        position.set(Duration::ZERO);
+        position.set_sample_rate(96_000);
+        assert_eq!(position.position(), Duration::ZERO);
+        position.advance(48_000);
+        assert_eq!(position.position(), Duration::from_millis(500));
+
+        // A loop on the previous track doesn't follow into the next
+        position.set_loop(LoopRegion::new(Duration::from_secs(1), Duration::from_secs(2)));
+        position.set_loop(None);
+        position.set(Duration::ZERO);
+        position.advance(96_000 * 3);
+        assert_eq!(position.position(), Duration::from_secs(3));
     }
 
     #[test]
-    fn test_progress_calculation() {
-        let tracker = PositionTracker::new();
-        let duration = Duration::from_secs(100);
-        tracker.start_tracking(Duration::from_secs(25), duration);
-        
-        let progress = tracker.progress();
-        assert!((progress - 0.25).abs() < 0.01); // Should be approximately 25%
-        
-        tracker.seek(Duration::from_secs(50));
-        let progress = tracker.progress();
-        assert!((progress - 0.5).abs() < 0.01); // Should be approximately 50%
+    fn test_seek_keeps_fractional_seconds() {
+        let position = PositionTracker::new(44_100);
+        position.set(Duration::from_millis(30_500));
+        assert_eq!(position.position(), Duration::from_millis(30_500));
+        position.advance(22_050);
+        assert_eq!(position.position(), Duration::from_secs(31));
     }
 
     #[test]
-    fn test_remaining_time() {
-        let tracker = PositionTracker::new();
-        let duration = Duration::from_secs(180);
-        tracker.start_tracking(Duration::from_secs(60), duration);
-        
-        let remaining = tracker.remaining_time();
-        assert!(remaining <= Duration::from_secs(120));
-        assert!(remaining >= Duration::from_secs(119)); // Account for small elapsed time
+    fn test_seek_then_play_continues_from_the_seek() {
+        let position = PositionTracker::new(44_100);
+        // Audio before the seek was consumed but no longer counts
+        position.advance(10_000);
+        position.set(Duration::from_secs(30));
+        assert_eq!(position.position(), Duration::from_secs(30));
+
+        // One second of callbacks
+        for _ in 0..100 {
+            position.advance(441);
+        }
+        assert_eq!(frames_played(&position), 44_100);
+        assert_eq!(position.position(), Duration::from_secs(31));
     }
 
     #[test]
-    fn test_is_finished() {
-        let tracker = PositionTracker::new();
-        let duration = Duration::from_secs(100);
-        tracker.start_tracking(Duration::from_secs(0), duration);
-        
-        assert!(!tracker.is_finished());
-        
-        tracker.seek(duration);
-        assert!(tracker.is_finished());
+    fn test_position_does_not_drift_over_many_callbacks() {
+        const CALLBACKS: u64 = 10_000;
+        const FRAMES_PER_CALLBACK: u64 = 512;
+        let position = PositionTracker::new(44_100);
+        for _ in 0..CALLBACKS {
+            position.advance(FRAMES_PER_CALLBACK);
+        }
+
+        let expected = (CALLBACKS * FRAMES_PER_CALLBACK) as f64 / 44_100.0;
+        assert_eq!(frames_played(&position), CALLBACKS * FRAMES_PER_CALLBACK);
+        assert!((position.position().as_secs_f64() - expected).abs() < 1e-6);
+
+        // A seek restarts the count from the new position
+        position.set(Duration::from_secs(60));
+        for _ in 0..CALLBACKS {
+            position.advance(FRAMES_PER_CALLBACK);
+        }
+        assert!((position.position().as_secs_f64() - (60.0 + expected)).abs() < 1e-6);
     }
 
     #[test]
-    fn test_update_status() {
-        let tracker = PositionTracker::new();
-        tracker.start_tracking(Duration::from_secs(30), Duration::from_secs(180));
-        
-        let mut status = PlayerStatus::new();
-        tracker.update_status(&mut status);
-        
-        assert_eq!(status.state, PlaybackState::Playing);
-        assert!(status.position >= Duration::from_secs(30));
-        assert!(status.position <= Duration::from_secs(31)); // Small tolerance for elapsed time
+    fn test_skip_counts_once_the_output_gets_there() {
+        let position = PositionTracker::new(1_000);
+        position.advance(100);
+
+        // Cut by the decoder 400 frames ahead of the output: nothing moves while paused
+        position.skip_after(400, Duration::from_secs(5));
+        assert_eq!(position.position(), Duration::from_millis(100));
+        position.advance(399);
+        assert_eq!(position.position(), Duration::from_millis(499));
+        position.advance(1);
+        assert_eq!(position.position(), Duration::from_millis(5_500));
+
+        // A second cut folds in the first, which has been reached
+        position.skip_after(200, Duration::from_secs(1));
+        position.advance(200);
+        assert_eq!(position.position(), Duration::from_millis(6_700));
+
+        // A seek drops a cut that was never played
+        position.skip_after(200, Duration::from_secs(1));
+        position.set(Duration::from_secs(2));
+        position.advance(500);
+        assert_eq!(position.position(), Duration::from_millis(2_500));
     }
 
     #[test]
-    fn test_position_update_creation() {
-        let position = Duration::from_secs(60);
-        let state = PlaybackState::Playing;
-        let duration = Duration::from_secs(180);
-        
-        let update = PositionUpdate::new(position, state, duration);
-        
-        assert_eq!(update.position, position);
-        assert_eq!(update.state, state);
-        assert!((update.progress - (1.0/3.0)).abs() < 0.01); // 60/180 = 1/3
-        assert_eq!(update.remaining, Duration::from_secs(120));
-    }
+    fn test_position_folds_into_loop() {
+        let position = PositionTracker::new(1_000);
+        position.set(Duration::from_secs(9));
+        position.set_loop(LoopRegion::new(Duration::from_secs(5), Duration::from_secs(10)));
 
-    #[tokio::test]
-    async fn test_position_tracking_over_time() {
-        let tracker = PositionTracker::new();
-        tracker.start_tracking(Duration::from_secs(0), Duration::from_secs(10));
-        
-        let initial_position = tracker.current_position();
-        
-        // Wait for some time
-        sleep(TokioDuration::from_millis(100)).await;
-        
-        let later_position = tracker.current_position();
-        
-        // Position should have advanced
-        assert!(later_position > initial_position);
-        assert!(later_position <= Duration::from_millis(150)); // Should be reasonable
+        position.advance(2_000);
+        assert_eq!(position.position(), Duration::from_secs(6));
+        position.advance(5_000);
+        assert_eq!(position.position(), Duration::from_secs(6));
+
+        // Clearing the loop carries on from where the loop was
+        position.set_loop(None);
+        assert_eq!(position.loop_region(), None);
+        position.advance(1_000);
+        assert_eq!(position.position(), Duration::from_secs(7));
     }
 
     #[test]
-    fn test_concurrent_access() {
-        use std::sync::Arc;
-        use std::thread;
-        
-        let tracker = Arc::new(PositionTracker::new());
-        tracker.start_tracking(Duration::from_secs(0), Duration::from_secs(100));
-        
-        let tracker_clone = tracker.clone();
-        let handle = thread::spawn(move || {
-            for i in 0..10 {
-                tracker_clone.seek(Duration::from_secs(i * 10));
-                thread::sleep(std::time::Duration::from_millis(10));
-            }
-        });
-        
-        // Access tracker from main thread while other thread is modifying
-        for _ in 0..10 {
-            let _position = tracker.current_position();
-            let _progress = tracker.progress();
-            thread::sleep(std::time::Duration::from_millis(10));
+    fn test_position_under_concurrent_readers() {
+        let position = Arc::new(PositionTracker::new(44_100));
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        // Readers hammer the shared state like status polling does
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let (position, done) = (Arc::clone(&position), Arc::clone(&done));
+                std::thread::spawn(move || {
+                    let mut last = Duration::ZERO;
+                    while !done.load(Ordering::Relaxed) {
+                        let now = position.position();
+                        assert!(now >= last, "position must never go backwards");
+                        last = now;
+                    }
+                })
+            })
+            .collect();
+
+        for _ in 0..44_100 {
+            position.advance(1);
+        }
+        done.store(true, Ordering::Relaxed);
+        for reader in readers {
+            reader.join().unwrap();
         }
-        
-        handle.join().unwrap();
-        
-        // Should not panic and should have reasonable final state
-        assert!(tracker.current_position() <= Duration::from_secs(100));
+
+        assert_eq!(frames_played(&position), 44_100);
+        assert_eq!(position.position(), Duration::from_secs(1));
     }
 
     #[test]
-    fn test_zero_duration_handling() {
-        let tracker = PositionTracker::new();
-        tracker.start_tracking(Duration::from_secs(0), Duration::from_secs(0));
-        
-        assert_eq!(tracker.progress(), 0.0);
-        assert_eq!(tracker.remaining_time(), Duration::from_secs(0));
-        // Zero duration should not be considered finished unless explicitly at the end
-        // This is because zero duration means unknown duration, not a finished track
-        assert!(!tracker.is_finished());
+    fn test_seeks_from_another_thread_never_mix_with_the_callback_count() {
+        // Each seek lands on a whole minute and the callback plays under a second after one
+        let position = Arc::new(PositionTracker::new(48_000));
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let callback = {
+            let (position, done) = (Arc::clone(&position), Arc::clone(&done));
+            std::thread::spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    // Half a second at most between seeks
+                    if frames_played(&position) < 24_000 {
+                        position.advance(16);
+                    }
+                }
+            })
+        };
+        let seeker = {
+            let position = Arc::clone(&position);
+            std::thread::spawn(move || {
+                for minute in 0..20_000u64 {
+                    position.set(Duration::from_secs(60 * (minute % 7)));
+                }
+            })
+        };
+
+        while !seeker.is_finished() {
+            let now = position.position();
+            let into_minute = now.as_secs_f64() % 60.0;
+            assert!(into_minute < 1.0, "{:?} pairs a seek with frames from before it", now);
+        }
+        done.store(true, Ordering::Relaxed);
+        seeker.join().unwrap();
+        callback.join().unwrap();
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,241 @@
+/*!
+Realtime scheduling for the audio output and decoder threads.
+
+- macOS and Linux switch the thread to `SCHED_FIFO` with `pthread_setschedparam`. Linux only
+  allows this with `CAP_SYS_NICE` or an `rtprio` limit, so a refusal is logged and playback
+  continues at normal priority.
+- Windows raises the thread with `SetThreadPriority`.
+- The decoder runs one step below the output thread, so a long decode can never delay a
+  device callback.
+
+Either thread can also be pinned to a set of CPU cores (`cpu_affinity`), so it stops migrating
+between cores and losing its caches. Linux uses `sched_setaffinity` and Windows
+`SetThreadAffinityMask`; macOS has no thread affinity, so the setting is ignored there. The
+`core_affinity` crate only pins a thread to a single core, so the one call per platform is made
+here directly. Cores this machine lacks are dropped with a warning before the call, and a list
+with no usable core leaves the thread unpinned rather than failing with `EINVAL`.
+*/
+
+use serde::{Deserialize, Serialize};
+
+/// `SCHED_FIFO` priority for the audio thread
+#[cfg(target_os = "macos")]
+const FIFO_PRIORITY: i32 = 63;
+/// `SCHED_FIFO` priority for the audio thread, kept below the kernel's threaded IRQ handlers (50)
+#[cfg(target_os = "linux")]
+const FIFO_PRIORITY: i32 = 40;
+
+/// CPU indices the affinity call can express: the size of `cpu_set_t`
+#[cfg(target_os = "linux")]
+pub const MAX_AFFINITY_CORES: usize = libc::CPU_SETSIZE as usize;
+/// CPU indices the affinity call can express: one processor group's mask
+#[cfg(windows)]
+pub const MAX_AFFINITY_CORES: usize = usize::BITS as usize;
+#[cfg(not(any(target_os = "linux", windows)))]
+pub const MAX_AFFINITY_CORES: usize = 1024;
+
+/// `SCHED_FIFO` priority for a realtime decoder thread
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+const DECODER_FIFO_PRIORITY: i32 = FIFO_PRIORITY - 10;
+
+/// Scheduling requested for the decoder thread
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThreadPriority {
+    /// Leave the thread at the operating system's default
+    Normal,
+    /// Ahead of ordinary threads: the lowest `SCHED_FIFO` priority, or `THREAD_PRIORITY_HIGHEST`
+    High,
+    /// Just below the output thread: `SCHED_FIFO`, or `THREAD_PRIORITY_TIME_CRITICAL`
+    #[default]
+    Realtime,
+}
+
+impl ThreadPriority {
+    pub const ALL: [ThreadPriority; 3] = [ThreadPriority::Normal, ThreadPriority::High, ThreadPriority::Realtime];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ThreadPriority::Normal => "normal",
+            ThreadPriority::High => "high",
+            ThreadPriority::Realtime => "realtime",
+        }
+    }
+}
+
+impl std::str::FromStr for ThreadPriority {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value_lower = value.trim().to_lowercase();
+        ThreadPriority::ALL
+            .into_iter()
+            .find(|priority| priority.as_str() == value_lower)
+            .ok_or_else(|| format!("unknown thread priority '{}'", value))
+    }
+}
+
+/// Raise the calling thread's priority for low-latency output, logging when the OS refuses
+pub fn elevate_current_thread() -> bool {
+    match elevate(Level::Output) {
+        Ok(()) => true,
+        Err(reason) => {
+            log::warn!("Could not raise audio thread priority: {}", reason);
+            false
+        }
+    }
+}
+
+/// Apply `priority` to the calling decoder thread, logging when the OS refuses
+pub fn set_decoder_thread_priority(priority: ThreadPriority) -> bool {
+    let level = match priority {
+        ThreadPriority::Normal => return true,
+        ThreadPriority::High => Level::High,
+        ThreadPriority::Realtime => Level::Decoder,
+    };
+    match elevate(level) {
+        Ok(()) => true,
+        Err(reason) => {
+            log::warn!("Could not raise decoder thread priority: {}", reason);
+            false
+        }
+    }
+}
+
+/// Pin the calling thread to the given CPU cores, logging when the OS refuses
+///
+/// `thread` names the thread in the log. Platforms without thread affinity leave the thread
+/// where the scheduler puts it.
+pub fn pin_current_thread(cores: &[usize], thread: &str) -> bool {
+    let missing = missing_cores(cores);
+    if !missing.is_empty() {
+        log::warn!("CPUs {:?} do not exist on this machine ({} cores); {} thread not pinned to them", missing, core_count(), thread);
+    }
+    let cores: Vec<usize> = cores.iter().copied().filter(|core| !missing.contains(core)).collect();
+    if cores.is_empty() {
+        log::warn!("No usable CPU in the affinity list; {} thread not pinned", thread);
+        return false;
+    }
+    match set_affinity(&cores) {
+        Ok(true) => true,
+        Ok(false) => {
+            log::debug!("CPU affinity is not supported on this platform; {} thread not pinned", thread);
+            false
+        }
+        Err(reason) => {
+            log::warn!("Could not pin {} thread to CPUs {:?}: {}", thread, cores, reason);
+            false
+        }
+    }
+}
+
+/// CPU cores on this machine, including ones the process may not currently run on
+pub fn core_count() -> usize {
+    #[cfg(target_os = "linux")]
+    {
+        let configured = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_CONF) };
+        if configured > 0 {
+            return configured as usize;
+        }
+    }
+    std::thread::available_parallelism().map_or(1, |count| count.get())
+}
+
+/// Entries of `cores` this machine lacks or the platform's affinity call cannot express
+pub fn missing_cores(cores: &[usize]) -> Vec<usize> {
+    let limit = core_count().min(MAX_AFFINITY_CORES);
+    cores.iter().copied().filter(|&core| core >= limit).collect()
+}
+
+/// Where a thread is placed among the player's threads
+#[derive(Debug, Clone, Copy)]
+enum Level {
+    High,
+    Decoder,
+    Output,
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn elevate(level: Level) -> Result<(), String> {
+    unsafe {
+        let thread = libc::pthread_self();
+        let mut policy: libc::c_int = 0;
+        let mut param: libc::sched_param = std::mem::zeroed();
+
+        let result = libc::pthread_getschedparam(thread, &mut policy, &mut param);
+        if result != 0 {
+            return Err(std::io::Error::from_raw_os_error(result).to_string());
+        }
+        param.sched_priority = match level {
+            Level::High => libc::sched_get_priority_min(libc::SCHED_FIFO),
+            Level::Decoder => DECODER_FIFO_PRIORITY,
+            Level::Output => FIFO_PRIORITY,
+        };
+        match libc::pthread_setschedparam(thread, libc::SCHED_FIFO, &param) {
+            0 => Ok(()),
+            error => Err(std::io::Error::from_raw_os_error(error).to_string()),
+        }
+    }
+}
+
+#[cfg(windows)]
+fn elevate(level: Level) -> Result<(), String> {
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_HIGHEST, THREAD_PRIORITY_TIME_CRITICAL,
+    };
+
+    let priority = match level {
+        Level::High => THREAD_PRIORITY_HIGHEST,
+        Level::Decoder | Level::Output => THREAD_PRIORITY_TIME_CRITICAL,
+    };
+    if unsafe { SetThreadPriority(GetCurrentThread(), priority) } != 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error().to_string())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
+fn elevate(_level: Level) -> Result<(), String> {
+    Err("not supported on this platform".to_string())
+}
+
+/// Restrict the calling thread to `cores`; `Ok(false)` where the platform has no affinity
+#[cfg(target_os = "linux")]
+fn set_affinity(cores: &[usize]) -> Result<bool, String> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        for &core in cores.iter().filter(|&&core| core < libc::CPU_SETSIZE as usize) {
+            libc::CPU_SET(core, &mut set);
+        }
+        // Thread ID 0 is the calling thread
+        match libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) {
+            0 => Ok(true),
+            _ => Err(std::io::Error::last_os_error().to_string()),
+        }
+    }
+}
+
+#[cfg(windows)]
+fn set_affinity(cores: &[usize]) -> Result<bool, String> {
+    use windows_sys::Win32::System::Threading::{GetCurrentThread, SetThreadAffinityMask};
+
+    // The mask covers the thread's processor group, at most 64 cores
+    let mask = cores.iter()
+        .filter(|&&core| core < usize::BITS as usize)
+        .fold(0usize, |mask, &core| mask | 1 << core);
+    if mask == 0 {
+        return Err("no CPU in the first processor group".to_string());
+    }
+    if unsafe { SetThreadAffinityMask(GetCurrentThread(), mask) } != 0 {
+        Ok(true)
+    } else {
+        Err(std::io::Error::last_os_error().to_string())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn set_affinity(_cores: &[usize]) -> Result<bool, String> {
+    Ok(false)
+}
@@ -0,0 +1,311 @@
+/*!
+Rendering tracks to a file for `export`.
+
+Each file is decoded, trimmed of encoder delay and padding, brought to the export format
+by `chain::to_output_format` and run through an `OutputChain` with the playback settings,
+then written by the encoder the output's extension names. Tracks join as they would
+gaplessly, without the sound card setting the pace, so an album renders as fast as it decodes.
+
+The chain gets its own controls (`ChainControls::detached`), so rendering leaves the clip
+count and fades of what is playing alone. Volume is not applied: the file is at full scale.
+*/
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use cpal::SampleFormat;
+
+use crate::audio::chain::{self, ChainControls};
+use crate::audio::encoders::{self, EncoderFormat};
+use crate::audio::engine::AudioEngineImpl;
+use crate::audio::gapless::GaplessTrimmer;
+use crate::audio::{AudioDecoder, RateAdapter};
+
+/// `progress` is called each time this much more of the audio has been rendered
+const PROGRESS_STEP: u32 = 10;
+const MIN_SAMPLE_RATE: u32 = 8_000;
+const MAX_SAMPLE_RATE: u32 = 768_000;
+
+/// Format choices of an `export`; what is left `None` follows the first file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderOptions {
+    pub sample_rate: Option<u32>,
+    pub bit_depth: Option<u16>,
+    /// Oversampling factor, used when the export rate is that multiple of a file's rate
+    pub oversampling: Option<u32>,
+}
+
+/// Outcome of an `export`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderSummary {
+    pub output: PathBuf,
+    pub format: EncoderFormat,
+    /// Files rendered completely
+    pub rendered: usize,
+    /// Files that could not be opened or stopped decoding, with why
+    pub failures: Vec<(PathBuf, String)>,
+    /// Length of the audio written
+    pub duration: Duration,
+    /// Samples the chain clipped (or limited)
+    pub clipped_samples: u64,
+    /// Whether the run was cancelled, in which case the output was removed
+    pub cancelled: bool,
+}
+
+/// Format of a file rendered from audio like `decoder`'s to `output`
+///
+/// Without a requested depth the source's is used, or the nearest the encoder writes
+/// (32-bit audio goes to 24-bit FLAC, lossy files to 16 bits).
+pub fn output_format(decoder: &dyn AudioDecoder, output: &Path, options: RenderOptions) -> io::Result<EncoderFormat> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let depths = encoders::supported_bit_depths(output);
+    if depths.is_empty() {
+        return Err(invalid(format!("can't export to {}: use a .wav or .flac file", output.display())));
+    }
+    let bit_depth = match options.bit_depth {
+        Some(bits) if !depths.contains(&bits) => {
+            let supported: Vec<String> = depths.iter().map(u16::to_string).collect();
+            let listed = match supported.split_last() {
+                Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+                _ => supported.concat(),
+            };
+            return Err(invalid(format!("{} export writes {} bits", extension_name(output), listed)));
+        }
+        Some(bits) => bits,
+        None => depths.iter().copied().find(|&bits| bits >= decoder.bit_depth()).unwrap_or(depths[depths.len() - 1]),
+    };
+    let sample_rate = options.sample_rate.unwrap_or(decoder.sample_rate());
+    if !(MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&sample_rate) {
+        return Err(invalid(format!("can't export at {} Hz ({} to {} Hz)", sample_rate, MIN_SAMPLE_RATE, MAX_SAMPLE_RATE)));
+    }
+    Ok(EncoderFormat { sample_rate, channels: decoder.channels().max(1), bit_depth })
+}
+
+fn extension_name(path: &Path) -> String {
+    path.extension().map(|extension| extension.to_string_lossy().to_uppercase()).unwrap_or_default()
+}
+
+/// Render `files` one after another into `output`, calling `progress` with the percentage done
+///
+/// Files that can't be decoded are left out and reported. Setting `cancel` stops the run and
+/// deletes the partial output; errors writing it are returned.
+pub fn run(
+    files: &[PathBuf],
+    output: &Path,
+    options: RenderOptions,
+    controls: &ChainControls,
+    cancel: &AtomicBool,
+    mut progress: impl FnMut(u32),
+) -> io::Result<RenderSummary> {
+    // Open everything first, for the format and the total length progress is measured against
+    let mut failures = Vec::new();
+    let mut sources = Vec::new();
+    let mut format = None;
+    for path in files {
+        match AudioEngineImpl::open_decoder(path) {
+            Ok(decoder) => {
+                if format.is_none() {
+                    format = Some(output_format(decoder.as_ref(), output, options)?);
+                }
+                sources.push((path.clone(), decoder.duration().unwrap_or_default()));
+            }
+            Err(e) => failures.push((path.clone(), e.to_string())),
+        }
+    }
+    let Some(format) = format else {
+        let reason = failures.first().map(|(path, e)| format!("{}: {}", path.display(), e));
+        return Err(io::Error::other(reason.unwrap_or_else(|| "nothing to export".to_string())));
+    };
+
+    let controls = controls.detached();
+    // Only a 16-bit file is narrower than the chain's output
+    let sample_format = if format.bit_depth == 16 { SampleFormat::I16 } else { SampleFormat::I32 };
+    let mut chain = controls.chain(format.sample_rate, format.channels as usize, sample_format);
+    let mut rate_adapter = RateAdapter::new();
+    rate_adapter.set_oversampling(options.oversampling);
+    let mut encoder = encoders::create(output, format)?;
+
+    let total: Duration = sources.iter().map(|(_, duration)| *duration).sum();
+    let mut finished = Duration::ZERO;
+    let mut reported = 0;
+    let mut frames = 0u64;
+    let mut rendered = 0;
+    for (path, duration) in &sources {
+        let mut decoder = match AudioEngineImpl::open_decoder(path) {
+            Ok(decoder) => decoder,
+            Err(e) => {
+                failures.push((path.clone(), e.to_string()));
+                continue;
+            }
+        };
+        let mut trimmer = GaplessTrimmer::for_decoder(decoder.as_ref());
+        let mut decoded = Duration::ZERO;
+        let complete = loop {
+            if cancel.load(Ordering::Relaxed) {
+                drop(encoder);
+                std::fs::remove_file(output)?;
+                return Ok(RenderSummary {
+                    output: output.to_path_buf(),
+                    format,
+                    rendered,
+                    failures,
+                    duration: frames_duration(frames, format.sample_rate),
+                    clipped_samples: controls.clipping.clipped_samples(),
+                    cancelled: true,
+                });
+            }
+            let buffer = match decoder.decode_next() {
+                Ok(Some(buffer)) => buffer,
+                Ok(None) => break true,
+                Err(e) => {
+                    failures.push((path.clone(), format!("stopped decoding after {:.1}s: {}", decoded.as_secs_f64(), e)));
+                    break false;
+                }
+            };
+            decoded += frames_duration(buffer.frames as u64, buffer.sample_rate);
+
+            let buffer = trimmer.process(buffer);
+            if buffer.frames > 0 {
+                let mut buffer = chain::to_output_format(buffer, format.channels, None, &mut rate_adapter, format.sample_rate);
+                chain.process(&mut buffer.samples, 1.0);
+                encoder.write(&buffer.samples)?;
+                frames += buffer.frames as u64;
+            }
+
+            if !total.is_zero() {
+                let done = (finished + decoded.min(*duration)).as_secs_f64() / total.as_secs_f64();
+                let step = (done * 100.0) as u32 / PROGRESS_STEP * PROGRESS_STEP;
+                if step > reported && step < 100 {
+                    reported = step;
+                    progress(step);
+                }
+            }
+        };
+        finished += *duration;
+        if complete {
+            rendered += 1;
+        }
+    }
+    encoder.finish()?;
+    progress(100);
+
+    Ok(RenderSummary {
+        output: output.to_path_buf(),
+        format,
+        rendered,
+        failures,
+        duration: frames_duration(frames, format.sample_rate),
+        clipped_samples: controls.clipping.clipped_samples(),
+        cancelled: false,
+    })
+}
+
+fn frames_duration(frames: u64, sample_rate: u32) -> Duration {
+    Duration::from_secs_f64(frames as f64 / sample_rate.max(1) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::decoders::WavDecoder;
+    use crate::audio::eq::{EqBand, EqFilter};
+
+    fn write_tone(path: &Path, sample_rate: u32, channels: u16, frames: usize) {
+        let format = EncoderFormat { sample_rate, channels, bit_depth: 16 };
+        let mut encoder = encoders::create(path, format).unwrap();
+        let samples: Vec<f32> = (0..frames * channels as usize)
+            .map(|index| 0.25 * (index as f32 / channels as f32 * 0.05).sin())
+            .collect();
+        encoder.write(&samples).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    fn read(path: &Path) -> (Vec<f32>, u32, u16, u16) {
+        let mut decoder = WavDecoder::new(path).unwrap();
+        let mut samples = Vec::new();
+        while let Some(buffer) = decoder.decode_next().unwrap() {
+            samples.extend(buffer.samples);
+        }
+        (samples, decoder.sample_rate(), decoder.channels(), decoder.bit_depth())
+    }
+
+    #[test]
+    fn test_renders_tracks_back_to_back_in_the_first_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.wav");
+        let second = dir.path().join("second.wav");
+        write_tone(&first, 44_100, 2, 44_100);
+        write_tone(&second, 44_100, 1, 22_050);
+        let missing = dir.path().join("missing.wav");
+        let output = dir.path().join("out.wav");
+
+        let mut steps = Vec::new();
+        let files = [first.clone(), missing.clone(), second];
+        let summary = run(&files, &output, RenderOptions::default(), &ChainControls::default(), &AtomicBool::new(false), |step| steps.push(step))
+            .unwrap();
+
+        assert_eq!(summary.format, EncoderFormat { sample_rate: 44_100, channels: 2, bit_depth: 16 });
+        assert_eq!(summary.rendered, 2);
+        assert_eq!(summary.failures.len(), 1);
+        assert_eq!(summary.failures[0].0, missing);
+        assert_eq!(summary.duration, Duration::from_millis(1500));
+        assert_eq!(steps.first(), Some(&10));
+        assert_eq!(steps.last(), Some(&100));
+
+        // The neutral chain leaves 16-bit audio as it was
+        let (rendered, sample_rate, channels, bit_depth) = read(&output);
+        assert_eq!((sample_rate, channels, bit_depth), (44_100, 2, 16));
+        assert_eq!(rendered.len(), 3 * 44_100);
+        let (original, ..) = read(&first);
+        assert_eq!(&rendered[..original.len()], &original[..]);
+    }
+
+    #[test]
+    fn test_applies_the_chain_and_the_requested_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.wav");
+        write_tone(&input, 48_000, 2, 4_800);
+        let output = dir.path().join("out.wav");
+
+        let controls = ChainControls::default();
+        controls.eq.set_bands(vec![EqBand { filter: EqFilter::Peak, frequency_hz: 380.0, gain_db: -12.0, q: 1.0 }]);
+        let options = RenderOptions { sample_rate: Some(96_000), bit_depth: Some(24), oversampling: None };
+        let summary = run(std::slice::from_ref(&input), &output, options, &controls, &AtomicBool::new(false), |_| {}).unwrap();
+        assert_eq!(summary.format, EncoderFormat { sample_rate: 96_000, channels: 2, bit_depth: 24 });
+
+        let (rendered, sample_rate, _, bit_depth) = read(&output);
+        let (original, ..) = read(&input);
+        assert_eq!((sample_rate, bit_depth), (96_000, 24));
+        let level = |samples: &[f32]| samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32;
+        assert!(level(&rendered) < level(&original) / 4.0, "the EQ cut is in the file");
+    }
+
+    #[test]
+    fn test_rejects_formats_the_encoders_cannot_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.wav");
+        write_tone(&input, 44_100, 2, 100);
+        let cancel = AtomicBool::new(false);
+        let render = |output: &str, options| run(std::slice::from_ref(&input), &dir.path().join(output), options, &ChainControls::default(), &cancel, |_| {});
+
+        let error = render("out.mp3", RenderOptions::default()).unwrap_err();
+        assert!(error.to_string().contains(".wav or .flac"), "{}", error);
+        let error = render("out.flac", RenderOptions { bit_depth: Some(32), ..RenderOptions::default() }).unwrap_err();
+        assert!(error.to_string().contains("FLAC export writes 16 or 24 bits"), "{}", error);
+        assert!(render("out.wav", RenderOptions { sample_rate: Some(100), ..RenderOptions::default() }).is_err());
+        assert!(render("out.flac", RenderOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn test_cancel_removes_the_partial_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.wav");
+        write_tone(&input, 44_100, 2, 44_100);
+        let output = dir.path().join("out.flac");
+        let summary = run(&[input], &output, RenderOptions::default(), &ChainControls::default(), &AtomicBool::new(true), |_| {}).unwrap();
+        assert!(summary.cancelled);
+        assert!(!output.exists());
+    }
+}
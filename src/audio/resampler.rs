@@ -23,6 +23,12 @@ Notes:
 - If your output device forces a fixed sample rate (e.g. 48 kHz), you can instantiate this with
   src_rate = decoded file sample rate, dst_rate = device stream rate, and pass the decoded f32
   blocks through process() before writing to the ring buffer.
+- `RateAdapter` does that for the decoder: it passes audio already at the stream's rate through
+  untouched and keeps interpolating across a change of source rate between tracks, so a gapless
+  join into a stream at a fixed rate neither drops nor repeats frames.
+- `PolyphaseUpsamplerWrapper` oversamples by a whole factor (2x, 4x, 8x) with a Kaiser-windowed
+  sinc filter instead. `RateAdapter` uses it when oversampling is on and the stream runs at that
+  multiple of the track's rate.
 */
 
 use std::cmp::min;
@@ -129,9 +135,9 @@ impl LinearResampler {
         let expected_out_frames = ((in_frames as f64) * (self.dst_rate as f64 / self.src_rate as f64)).ceil() as usize + 4;
         let mut out = Vec::with_capacity(expected_out_frames * ch);
 
-        // Generate output frames until we don't have at least (i+1) frames for interpolation.
-        // We require (pos + 1.0) < total_frames => pos < total_frames - 1
-        while self.pos + 1.0 <= (total_frames as f64 - 1.0) {
+        // Generate output frames while frame i+1 exists for interpolation: pos < total_frames - 1.
+        // Positions from the last frame on are produced next call, once the frame after it arrives.
+        while self.pos < total_frames as f64 - 1.0 {
             let i = self.pos.floor() as usize;
             let frac = (self.pos - i as f64) as f32;
 
@@ -223,6 +229,248 @@ impl LinearResampler {
     }
 }
 
+/// Oversampling factors the polyphase upsampler accepts
+pub const OVERSAMPLING_FACTORS: [u32; 3] = [2, 4, 8];
+
+/// Taps of each phase of the polyphase filter, i.e. input frames each output frame is built from
+const TAPS_PER_PHASE: usize = 48;
+/// Kaiser window shape; about 80 dB of stopband attenuation
+const KAISER_BETA: f64 = 8.0;
+
+/// The lowpass filter of an integer upsampler, split into one set of taps per output phase
+#[derive(Debug, Clone)]
+struct PolyphaseFilterBank {
+    /// `phases[p][j]` weighs input frame `i - (TAPS_PER_PHASE - 1 - j)` for output frame `i * factor + p`
+    phases: Vec<Vec<f32>>,
+}
+
+impl PolyphaseFilterBank {
+    fn new(factor: usize) -> Self {
+        // Windowed sinc cut off at the input's Nyquist frequency, in cycles per output sample
+        let length = factor * TAPS_PER_PHASE;
+        let cutoff = 0.5 / factor as f64;
+        let center = (length - 1) as f64 / 2.0;
+        let prototype: Vec<f64> = (0..length)
+            .map(|n| {
+                let t = n as f64 - center;
+                let sinc = if t == 0.0 { 2.0 * cutoff } else { (2.0 * std::f64::consts::PI * cutoff * t).sin() / (std::f64::consts::PI * t) };
+                let ratio = 2.0 * n as f64 / (length - 1) as f64 - 1.0;
+                let window = bessel_i0(KAISER_BETA * (1.0 - ratio * ratio).max(0.0).sqrt()) / bessel_i0(KAISER_BETA);
+                sinc * window
+            })
+            .collect();
+
+        // Each phase sums to one so DC keeps its level; zero stuffing would otherwise divide it by the factor
+        let phases = (0..factor)
+            .map(|phase| {
+                let taps: Vec<f64> = (0..TAPS_PER_PHASE).rev().map(|k| prototype[phase + k * factor]).collect();
+                let sum: f64 = taps.iter().sum();
+                taps.iter().map(|&tap| (tap / sum) as f32).collect()
+            })
+            .collect();
+        Self { phases }
+    }
+}
+
+/// Zeroth-order modified Bessel function of the first kind, for the Kaiser window
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half = x / 2.0;
+    for k in 1..50 {
+        term *= half / k as f64;
+        sum += term * term;
+        if term * term < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
+
+/// Streams interleaved buffers through a polyphase filter bank, multiplying their rate by a whole factor
+///
+/// The filter bank for the factor is computed once, at construction. Every input frame becomes
+/// `factor` output frames, each the weighted sum of the last `TAPS_PER_PHASE` input frames, so
+/// nothing is spent on the zeros a plain zero-stuffing upsampler would filter. The filter delays
+/// the audio by half its length, about 24 input frames.
+#[derive(Debug, Clone)]
+pub struct PolyphaseUpsamplerWrapper {
+    factor: usize,
+    bank: PolyphaseFilterBank,
+    channels: usize,
+    /// Last `TAPS_PER_PHASE - 1` input frames of each channel, oldest first
+    history: Vec<Vec<f32>>,
+}
+
+impl PolyphaseUpsamplerWrapper {
+    /// An upsampler for one of `OVERSAMPLING_FACTORS`; None for any other factor
+    pub fn new(factor: u32) -> Option<Self> {
+        OVERSAMPLING_FACTORS.contains(&factor).then(|| Self {
+            factor: factor as usize,
+            bank: PolyphaseFilterBank::new(factor as usize),
+            channels: 0,
+            history: Vec::new(),
+        })
+    }
+
+    pub fn factor(&self) -> u32 {
+        self.factor as u32
+    }
+
+    /// Forget the audio before a seek or another track
+    pub fn reset(&mut self) {
+        self.history.clear();
+    }
+
+    /// `buffer` at `factor` times its rate, continuing from the previous buffer
+    pub fn process(&mut self, buffer: &crate::models::AudioBuffer) -> crate::models::AudioBuffer {
+        let channels = buffer.channels as usize;
+        if channels != self.channels || self.history.len() != channels {
+            self.channels = channels;
+            self.history = vec![vec![0.0; TAPS_PER_PHASE - 1]; channels];
+        }
+
+        let frames = buffer.samples.len() / channels.max(1);
+        let mut samples = vec![0.0; frames * self.factor * channels];
+        let mut work = Vec::with_capacity(TAPS_PER_PHASE - 1 + frames);
+        for (channel, history) in self.history.iter_mut().enumerate() {
+            work.clear();
+            work.extend_from_slice(history);
+            work.extend(buffer.samples.iter().skip(channel).step_by(channels).take(frames));
+            for frame in 0..frames {
+                let window = &work[frame..frame + TAPS_PER_PHASE];
+                for (phase, taps) in self.bank.phases.iter().enumerate() {
+                    let sample: f32 = window.iter().zip(taps).map(|(x, tap)| x * tap).sum();
+                    samples[(frame * self.factor + phase) * channels + channel] = sample;
+                }
+            }
+            history.copy_from_slice(&work[work.len() - (TAPS_PER_PHASE - 1)..]);
+        }
+
+        crate::models::AudioBuffer {
+            frames: frames * self.factor,
+            samples,
+            channels: buffer.channels,
+            sample_rate: buffer.sample_rate * self.factor as u32,
+        }
+    }
+}
+
+/// Brings decoded buffers to the output stream's rate, continuously across tracks of different rates
+#[derive(Debug, Clone)]
+pub struct RateAdapter {
+    resampler: LinearResampler,
+    /// Whether the last buffer went through the resampler, which still holds its final frame
+    resampling: bool,
+    /// Last frame of the last buffer passed through unresampled
+    last_frame: Vec<f32>,
+    /// Used instead of the resampler when the stream runs at a multiple of the source rate
+    oversampler: Option<PolyphaseUpsamplerWrapper>,
+}
+
+impl Default for RateAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateAdapter {
+    pub fn new() -> Self {
+        Self {
+            resampler: LinearResampler::new(0, 0, 0),
+            resampling: false,
+            last_frame: Vec::new(),
+            oversampler: None,
+        }
+    }
+
+    /// Forget the audio before a seek, stop or newly loaded file
+    pub fn reset(&mut self) {
+        self.resampler.reset();
+        self.resampling = false;
+        self.last_frame.clear();
+        if let Some(oversampler) = &mut self.oversampler {
+            oversampler.reset();
+        }
+    }
+
+    /// Oversample by `factor` with the polyphase filter whenever the stream runs at that multiple of the source
+    pub fn set_oversampling(&mut self, factor: Option<u32>) {
+        if self.oversampler.as_ref().map(PolyphaseUpsamplerWrapper::factor) != factor {
+            self.oversampler = factor.and_then(PolyphaseUpsamplerWrapper::new);
+        }
+    }
+
+    /// Convert `buffer` to `dst_rate`, continuing from the buffer before whatever its rate was
+    pub fn process(&mut self, buffer: crate::models::AudioBuffer, dst_rate: u32) -> crate::models::AudioBuffer {
+        let channels = buffer.channels as usize;
+        if channels == 0 || buffer.frames == 0 {
+            return buffer;
+        }
+
+        let buffer = match &mut self.oversampler {
+            Some(oversampler) if buffer.sample_rate * oversampler.factor() == dst_rate => oversampler.process(&buffer),
+            Some(oversampler) => {
+                oversampler.reset();
+                buffer
+            }
+            None => buffer,
+        };
+
+        if buffer.sample_rate == dst_rate {
+            // Frames the resampler still owed between the previous track's end and this buffer
+            let pending = self.finish_resampling(&buffer.samples[..channels]);
+            self.last_frame = buffer.samples[buffer.samples.len() - channels..].to_vec();
+            if pending.is_empty() {
+                return buffer;
+            }
+            let mut samples = pending;
+            samples.extend_from_slice(&buffer.samples);
+            return crate::models::AudioBuffer {
+                frames: samples.len() / channels,
+                samples,
+                channels: buffer.channels,
+                sample_rate: dst_rate,
+            };
+        }
+
+        if self.resampling && self.resampler.channels == channels {
+            self.resampler.set_rates(buffer.sample_rate, dst_rate, false);
+        } else {
+            // Interpolate on from the last frame written, which has already been played out
+            self.resampler = LinearResampler::new(buffer.sample_rate, dst_rate, channels);
+            self.resampler.prev_frame = if self.last_frame.len() == channels {
+                std::mem::take(&mut self.last_frame)
+            } else {
+                vec![0.0; channels]
+            };
+            self.resampler.pos = 1.0;
+            self.resampling = true;
+        }
+
+        self.resampler.process_audio_buffer(&buffer)
+    }
+
+    /// Output frames between the resampler's last input frame and `next_frame`, ending resampling
+    fn finish_resampling(&mut self, next_frame: &[f32]) -> Vec<f32> {
+        let mut pending = Vec::new();
+        if !self.resampling || self.resampler.prev_frame.len() != next_frame.len() || self.resampler.step <= 0.0 {
+            self.resampling = false;
+            return pending;
+        }
+        let resampler = &mut self.resampler;
+        // A position a rounding error short of 1.0 is `next_frame` itself, which the caller writes
+        while resampler.pos < 1.0 - 1e-9 {
+            let frac = resampler.pos as f32;
+            pending.extend(resampler.prev_frame.iter().zip(next_frame).map(|(s0, s1)| s0 + (s1 - s0) * frac));
+            resampler.pos += resampler.step;
+        }
+        resampler.reset();
+        self.resampling = false;
+        pending
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -300,6 +548,75 @@ mod tests {
         }
     }
 
+    fn audio_buffer(samples: Vec<f32>, channels: u16, sample_rate: u32) -> crate::models::AudioBuffer {
+        let frames = samples.len() / channels as usize;
+        crate::models::AudioBuffer { samples, channels, sample_rate, frames }
+    }
+
+    /// Amplitude of the `f_hz` component of `samples`, by correlation with a sine and cosine
+    fn amplitude_at(samples: &[f32], f_hz: f32, sr: u32) -> f32 {
+        let (mut re, mut im) = (0.0f64, 0.0f64);
+        for (n, &s) in samples.iter().enumerate() {
+            let phase = 2.0 * std::f64::consts::PI * f_hz as f64 * n as f64 / sr as f64;
+            re += s as f64 * phase.cos();
+            im += s as f64 * phase.sin();
+        }
+        (2.0 * (re * re + im * im).sqrt() / samples.len() as f64) as f32
+    }
+
+    #[test]
+    fn polyphase_upsample_4x_keeps_the_baseband() {
+        // 10 samples a cycle, so the analysed stretch holds whole cycles of the tone and its images
+        let input = audio_buffer(gen_sine(4410.0, 44_100, 100, 1), 1, 44_100);
+        let mut upsampler = PolyphaseUpsamplerWrapper::new(4).unwrap();
+        let output = upsampler.process(&input);
+        assert_eq!(output.samples.len(), 400);
+        assert_eq!((output.frames, output.sample_rate), (400, 176_400));
+
+        // Once the filter has filled, the output is the same sine at the new rate, delayed by half the filter
+        let delay = (4 * TAPS_PER_PHASE - 1) as f32 / 2.0;
+        let settled = 4 * TAPS_PER_PHASE;
+        for (m, &sample) in output.samples.iter().enumerate().skip(settled) {
+            let expected = (2.0 * std::f32::consts::PI * 4410.0 * (m as f32 - delay) / 176_400.0).sin();
+            assert!((sample - expected).abs() < 2e-3, "sample {}: {} vs {}", m, sample, expected);
+        }
+        // No images of the tone around multiples of the old sample rate
+        let steady = &output.samples[settled..settled + 200];
+        assert!((amplitude_at(steady, 4410.0, 176_400) - 1.0).abs() < 0.01);
+        for image in [39_690.0, 48_510.0, 83_790.0] {
+            assert!(amplitude_at(steady, image, 176_400) < 1e-3, "image at {} Hz", image);
+        }
+    }
+
+    #[test]
+    fn polyphase_upsample_streams_across_buffers() {
+        let input = gen_sine(440.0, 48_000, 1000, 2);
+        let mut one = PolyphaseUpsamplerWrapper::new(2).unwrap();
+        let whole = one.process(&audio_buffer(input.clone(), 2, 48_000));
+
+        let mut two = PolyphaseUpsamplerWrapper::new(2).unwrap();
+        let mut streamed = Vec::new();
+        for piece in input.chunks(333 * 2) {
+            streamed.extend(two.process(&audio_buffer(piece.to_vec(), 2, 48_000)).samples);
+        }
+        assert_eq!(streamed, whole.samples);
+        assert!(PolyphaseUpsamplerWrapper::new(3).is_none());
+    }
+
+    #[test]
+    fn rate_adapter_oversamples_into_a_multiple_of_the_source_rate() {
+        let mut adapter = RateAdapter::new();
+        adapter.set_oversampling(Some(8));
+        let buffer = audio_buffer(gen_sine(1000.0, 44_100, 64, 2), 2, 44_100);
+        let output = adapter.process(buffer.clone(), 352_800);
+        assert_eq!((output.frames, output.sample_rate), (512, 352_800));
+
+        // Any other stream rate is resampled as before
+        let output = adapter.process(buffer, 48_000);
+        assert_eq!(output.sample_rate, 48_000);
+        assert!(output.frames < 80);
+    }
+
     #[test]
     fn resample_stereo_no_crash() {
         let src = 48_000;
@@ -0,0 +1,257 @@
+/*!
+Skipping silent lead-ins and lead-outs
+
+With `skip_silence` on, silence at the start of a file is cut once it has played for
+`silence_leading_ms`, so playback moves on to the first sound. Silence near the end that
+lasts longer than `silence_trailing_ms` is treated as the end of the file, starting the
+next track early. Only the first and last `EDGE_WINDOW` of a file are looked at, so quiet
+passages in the middle of a track are never cut.
+*/
+
+use std::time::Duration;
+use crate::audio::AudioDecoder;
+use crate::models::AudioBuffer;
+
+/// How far from either end of a file silence is acted on
+pub const EDGE_WINDOW: Duration = Duration::from_secs(10);
+
+/// When audio counts as silent and how much of it is let through
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SilenceSettings {
+    pub enabled: bool,
+    /// Frames whose peak stays below this level (dBFS) are silent
+    pub threshold_db: f32,
+    /// Leading silence played before the rest of it is cut
+    pub min_leading: Duration,
+    /// Silence near the end that finishes the track once exceeded
+    pub min_trailing: Duration,
+}
+
+impl Default for SilenceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_db: -60.0,
+            min_leading: Duration::from_secs(1),
+            min_trailing: Duration::from_secs(2),
+        }
+    }
+}
+
+impl SilenceSettings {
+    /// Linear peak level below `threshold_db`
+    pub fn threshold(&self) -> f32 {
+        10f32.powf(self.threshold_db / 20.0)
+    }
+}
+
+/// What to do with a decoded buffer
+#[derive(Debug)]
+pub enum SilenceStep {
+    /// Play the buffer, from which `skipped` of leading silence has been cut after its
+    /// first `cut_at` frames
+    Play { buffer: AudioBuffer, skipped: Duration, cut_at: usize },
+    /// Silence near the end has gone on long enough; treat the file as finished
+    EndOfTrack,
+}
+
+/// Follows one file's decoded audio and cuts silence at its edges
+///
+/// Silence is tracked even while skipping is off, so turning it on mid-track acts on the
+/// silence that has already gone by.
+#[derive(Debug)]
+pub struct SilenceSkipper {
+    duration: Duration,
+    /// File position of the next decoded frame
+    position: Duration,
+    /// Length of the silence that ends at `position`
+    silent: Duration,
+    /// No sound yet since the start of the file
+    leading: bool,
+    /// The end was cut; nothing after it plays
+    ended: bool,
+}
+
+impl SilenceSkipper {
+    /// Skipper for a file lasting `duration` (zero when unknown: the end is never cut)
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            position: Duration::ZERO,
+            silent: Duration::ZERO,
+            leading: true,
+            ended: false,
+        }
+    }
+
+    /// Skipper for a freshly opened decoder
+    pub fn for_decoder(decoder: &dyn AudioDecoder) -> Self {
+        Self::new(decoder.duration().unwrap_or_default())
+    }
+
+    /// Pass the next decoded buffer through
+    pub fn process(&mut self, mut buffer: AudioBuffer, settings: &SilenceSettings) -> SilenceStep {
+        if self.ended {
+            return SilenceStep::EndOfTrack;
+        }
+        let frames = buffer.frames;
+        let rate = buffer.sample_rate.max(1) as f64;
+        let frames_to_duration = |frames: usize| Duration::from_secs_f64(frames as f64 / rate);
+        let start = self.position;
+        self.position += frames_to_duration(frames);
+
+        let threshold = settings.threshold();
+        let channels = buffer.channels.max(1) as usize;
+        let is_sound = |frame: &[f32]| frame.iter().any(|sample| sample.abs() >= threshold);
+        let first_sound = buffer.samples.chunks(channels).position(is_sound);
+        let last_sound = buffer.samples.chunks(channels).rposition(is_sound);
+
+        let mut skipped = Duration::ZERO;
+        let mut cut_at = 0;
+        if start >= EDGE_WINDOW {
+            self.leading = false;
+        }
+        if self.leading && settings.enabled {
+            // Let `min_leading` of silence play, then cut up to the first sound
+            let silent_prefix = first_sound.unwrap_or(frames);
+            let allowed = settings.min_leading.saturating_sub(self.silent);
+            let kept = ((allowed.as_secs_f64() * rate).round() as usize).min(silent_prefix);
+            if kept < silent_prefix {
+                buffer.samples.drain(kept * channels..silent_prefix * channels);
+                buffer.frames -= silent_prefix - kept;
+                skipped = frames_to_duration(silent_prefix - kept);
+                cut_at = kept;
+            }
+        }
+
+        match last_sound {
+            Some(last) => {
+                self.silent = frames_to_duration(frames - 1 - last);
+                self.leading = false;
+            }
+            None => self.silent += frames_to_duration(frames),
+        }
+
+        let near_end = !self.duration.is_zero() && self.position + EDGE_WINDOW >= self.duration;
+        if settings.enabled && near_end && !self.leading && last_sound.is_none() && self.silent > settings.min_trailing {
+            self.ended = true;
+            return SilenceStep::EndOfTrack;
+        }
+        SilenceStep::Play { buffer, skipped, cut_at }
+    }
+
+    /// Start following from `position` after a seek; the lead-in only counts from the start
+    pub fn seek(&mut self, position: Duration) {
+        self.position = position;
+        self.silent = Duration::ZERO;
+        self.leading = position.is_zero();
+        self.ended = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 1000;
+
+    fn enabled() -> SilenceSettings {
+        SilenceSettings { enabled: true, ..Default::default() }
+    }
+
+    /// Stereo buffer of `frames` frames at `level`
+    fn buffer(frames: usize, level: f32) -> AudioBuffer {
+        AudioBuffer { samples: vec![level; frames * 2], channels: 2, sample_rate: RATE, frames }
+    }
+
+    /// Feed 100 ms buffers, louder than -60 dBFS where `loud` says so; returns frames played and skipped, and whether the track ended
+    fn feed(skipper: &mut SilenceSkipper, settings: &SilenceSettings, loud: impl Fn(usize) -> bool, buffers: usize) -> (usize, Duration, Option<usize>) {
+        let (mut played, mut skipped) = (0, Duration::ZERO);
+        for index in 0..buffers {
+            let level = if loud(index) { 0.1 } else { 0.0005 };
+            match skipper.process(buffer(100, level), settings) {
+                SilenceStep::Play { buffer, skipped: cut, .. } => {
+                    assert_eq!(buffer.samples.len(), buffer.frames * 2);
+                    played += buffer.frames;
+                    skipped += cut;
+                }
+                SilenceStep::EndOfTrack => return (played, skipped, Some(index)),
+            }
+        }
+        (played, skipped, None)
+    }
+
+    #[test]
+    fn test_threshold_level() {
+        assert!((SilenceSettings::default().threshold() - 0.001).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_leading_silence_is_cut_after_a_second() {
+        // 3 s of silence, then sound
+        let mut skipper = SilenceSkipper::new(Duration::from_secs(60));
+        let (played, skipped, ended) = feed(&mut skipper, &enabled(), |index| index >= 30, 40);
+        assert_eq!(played, 1000 + 1000);
+        assert_eq!(skipped, Duration::from_secs(2));
+        assert_eq!(ended, None);
+    }
+
+    #[test]
+    fn test_sound_inside_a_buffer_is_kept_to_the_frame() {
+        let mut skipper = SilenceSkipper::new(Duration::from_secs(60));
+        let mut first = buffer(1500, 0.0);
+        first.samples[1400 * 2] = 0.5;
+        match skipper.process(first, &enabled()) {
+            SilenceStep::Play { buffer, skipped, cut_at } => {
+                assert_eq!((buffer.frames, skipped, cut_at), (1100, Duration::from_millis(400), 1000));
+                assert_eq!(buffer.samples[1000 * 2], 0.5);
+            }
+            SilenceStep::EndOfTrack => panic!("the start of a track never ends it"),
+        }
+    }
+
+    #[test]
+    fn test_short_lead_ins_and_disabled_skipping_play_everything() {
+        let mut skipper = SilenceSkipper::new(Duration::from_secs(60));
+        assert_eq!(feed(&mut skipper, &enabled(), |index| index >= 8, 20), (2000, Duration::ZERO, None));
+
+        let mut skipper = SilenceSkipper::new(Duration::from_secs(60));
+        assert_eq!(feed(&mut skipper, &SilenceSettings::default(), |index| index >= 30, 40), (4000, Duration::ZERO, None));
+    }
+
+    #[test]
+    fn test_trailing_silence_ends_the_track() {
+        // Sound for 55 s of a 60 s file, then silence: ends 2 s into it
+        let mut skipper = SilenceSkipper::new(Duration::from_secs(60));
+        let (_, _, ended) = feed(&mut skipper, &enabled(), |index| index < 550, 600);
+        assert_eq!(ended, Some(570));
+        // A hidden track after the silence stays cut
+        assert_eq!(feed(&mut skipper, &enabled(), |_| true, 10).2, Some(0));
+    }
+
+    #[test]
+    fn test_quiet_passages_mid_track_are_left_alone() {
+        // 5 s of silence from 20 s in a 60 s file, and 1.5 s right before the end
+        let mut skipper = SilenceSkipper::new(Duration::from_secs(60));
+        let loud = |index: usize| !(200..250).contains(&index) && !(580..595).contains(&index);
+        assert_eq!(feed(&mut skipper, &enabled(), loud, 600), (60_000, Duration::ZERO, None));
+
+        // Without a known length the end is never cut
+        let mut skipper = SilenceSkipper::new(Duration::ZERO);
+        assert_eq!(feed(&mut skipper, &enabled(), |index| index < 100, 200).2, None);
+    }
+
+    #[test]
+    fn test_seek_resets_the_silence_run() {
+        let mut skipper = SilenceSkipper::new(Duration::from_secs(60));
+        feed(&mut skipper, &enabled(), |_| true, 10);
+        // Seeking into the silence at the end starts counting again
+        skipper.seek(Duration::from_secs(55));
+        assert_eq!(feed(&mut skipper, &enabled(), |_| false, 50).2, Some(20));
+
+        // Seeking back to the start looks for a lead-in again
+        skipper.seek(Duration::ZERO);
+        let (played, skipped, _) = feed(&mut skipper, &enabled(), |index| index >= 20, 21);
+        assert_eq!((played, skipped), (1100, Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,842 @@
+/*!
+HTTP(S) streams for the decoders
+
+`HttpSource` is a symphonia `MediaSource` over a URL. A download thread reads the response
+ahead of the decoder into a shared buffer, so a slow network shows up as buffering rather than
+as a decode error.
+
+- Servers that accept range requests and report a length can seek: a seek outside the buffered
+  bytes restarts the download at the new offset. Other streams, such as internet radio, play
+  forward only and their tracks can't be seeked.
+- A download that goes quiet for the stall timeout, or whose connection drops, is reconnected,
+  resuming at the same byte when ranges work. The pause before each retry doubles, up to
+  `MAX_RECONNECT_DELAY`. After `reconnect_attempts` failures in a row the decoder gets the error.
+- A station (a response with `icy-` headers and no length) has no end: when the server closes
+  the connection it is reconnected like a drop, and playback carries on from the live audio.
+- Shoutcast and Icecast metadata (`Icy-MetaData: 1`) is taken out of the audio. A new
+  `StreamTitle` becomes the status title once the reader reaches the audio it came with, not
+  when it is downloaded, so the title changes with the song.
+*/
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::{mpsc, Arc, Condvar, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{ACCEPT_RANGES, CONTENT_TYPE, RANGE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use symphonia::core::io::MediaSource;
+
+use crate::error::DecodeError;
+
+/// Read ahead at most this far, so a long stream isn't held in memory
+const MAX_AHEAD: usize = 16 * 1024 * 1024;
+/// Bytes kept behind the read position for the short seeks back that probing makes
+const KEEP_BEHIND: usize = 1024 * 1024;
+const CHUNK_SIZE: usize = 16 * 1024;
+/// Pause before the first reconnect, doubled for each failure after it
+const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(8);
+
+/// The URL of a queue entry that is a network stream rather than a file
+pub fn stream_url(path: &Path) -> Option<&str> {
+    let url = path.to_str()?;
+    let (scheme, _) = url.split_once("://")?;
+    (scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")).then_some(url)
+}
+
+/// Extension of the decoder for a stream: the one in the URL, or one matching its content type
+pub fn stream_extension(url: &str, content_type: Option<&str>) -> Option<String> {
+    const EXTENSIONS: [&str; 10] = ["flac", "wav", "wave", "mp3", "ogg", "oga", "m4a", "mp4", "m4b", "aac"];
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let path = without_scheme.split(['?', '#']).next().unwrap_or_default();
+    let name = path.split_once('/').and_then(|(_, path)| path.rsplit('/').next()).unwrap_or_default();
+    let from_name = name.rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .filter(|extension| EXTENSIONS.contains(&extension.as_str()));
+    from_name.or_else(|| {
+        let extension = match content_type? {
+            "audio/flac" | "audio/x-flac" => "flac",
+            "audio/wav" | "audio/x-wav" | "audio/wave" => "wav",
+            "audio/mpeg" | "audio/mp3" => "mp3",
+            "audio/ogg" | "audio/vorbis" | "application/ogg" => "ogg",
+            "audio/mp4" | "audio/x-m4a" => "m4a",
+            "audio/aac" | "audio/aacp" => "aac",
+            _ => return None,
+        };
+        Some(extension.to_string())
+    })
+}
+
+/// Timeouts and buffer sizes of a stream
+#[derive(Debug, Clone, Copy)]
+pub struct StreamOptions {
+    /// How long to wait for a connection to the server
+    pub connect_timeout: Duration,
+    /// How long the server may go without sending anything before the stream is reconnected
+    pub stall_timeout: Duration,
+    /// Reconnects in a row before the stream fails
+    pub reconnect_attempts: u32,
+    /// Bytes buffered before playback starts, and again whenever the buffer runs dry
+    pub prebuffer_bytes: usize,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            stall_timeout: Duration::from_secs(10),
+            reconnect_attempts: 5,
+            prebuffer_bytes: 128 * 1024,
+        }
+    }
+}
+
+/// What `status` shows about a stream
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamStatus {
+    /// How full the prebuffer is, in percent, while playback waits for it
+    pub buffering: Option<u8>,
+    /// The station's current `StreamTitle`, usually "Artist - Title"
+    pub title: Option<String>,
+    /// An endless station rather than a file: no length and no seeking
+    pub live: bool,
+}
+
+/// Streams opened by this process, so `status` can find the one playing by its URL
+static OPEN_STREAMS: Mutex<Vec<(String, Weak<Shared>)>> = Mutex::new(Vec::new());
+
+/// Buffering and title of the stream of `url` opened last, while it is open
+pub fn stream_status(url: &str) -> Option<StreamStatus> {
+    let mut streams = OPEN_STREAMS.lock().unwrap();
+    streams.retain(|(_, shared)| shared.strong_count() > 0);
+    streams.iter().rev()
+        .filter(|(open, _)| open == url)
+        .filter_map(|(_, shared)| shared.upgrade())
+        .find_map(|shared| {
+            let transfer = shared.transfer.lock().unwrap();
+            (!transfer.closed).then(|| transfer.status.clone())
+        })
+}
+
+/// State shared by a source and its download thread
+#[derive(Default)]
+struct Transfer {
+    /// Downloaded bytes not yet dropped, the first at stream offset `start`
+    buffer: VecDeque<u8>,
+    start: u64,
+    /// Where the source is reading; the download waits once it is `MAX_AHEAD` past this
+    read_position: u64,
+    /// Offset the source seeked to, for the download to restart at
+    restart_at: Option<u64>,
+    ended: bool,
+    /// Why the download gave up
+    error: Option<String>,
+    /// Set when the source is dropped
+    closed: bool,
+    status: StreamStatus,
+    /// Titles downloaded but not yet reached, by the stream offset they take effect at
+    titles: VecDeque<(u64, Option<String>)>,
+}
+
+impl Transfer {
+    fn end(&self) -> u64 {
+        self.start + self.buffer.len() as u64
+    }
+
+    fn ahead(&self) -> usize {
+        self.end().saturating_sub(self.read_position) as usize
+    }
+}
+
+struct Shared {
+    transfer: Mutex<Transfer>,
+    changed: Condvar,
+}
+
+/// What the first response said about the stream
+struct Head {
+    length: Option<u64>,
+    ranges: bool,
+    content_type: Option<String>,
+    /// Shoutcast and Icecast answer with `icy-name`, `icy-br`, `icy-metaint` and the like
+    icy: bool,
+}
+
+impl Head {
+    fn of(response: &Response) -> Self {
+        let header = |name| response.headers().get(name).and_then(|value| value.to_str().ok());
+        Self {
+            length: response.content_length(),
+            ranges: header(ACCEPT_RANGES).is_some_and(|value| value.eq_ignore_ascii_case("bytes")),
+            content_type: header(CONTENT_TYPE)
+                .map(|value| value.split(';').next().unwrap_or_default().trim().to_ascii_lowercase()),
+            icy: response.headers().keys().any(|name| name.as_str().starts_with("icy-")),
+        }
+    }
+
+    fn seekable(&self) -> bool {
+        self.ranges && self.length.is_some()
+    }
+
+    fn live(&self) -> bool {
+        self.icy && self.length.is_none()
+    }
+}
+
+/// A network stream the decoders read like a file
+pub struct HttpSource {
+    shared: Arc<Shared>,
+    position: u64,
+    length: Option<u64>,
+    seekable: bool,
+    content_type: Option<String>,
+    prebuffer_bytes: usize,
+}
+
+impl HttpSource {
+    /// Connect to `url` and start downloading; fails when the server can't be reached or refuses
+    pub fn open(url: &str, options: StreamOptions) -> Result<Self, DecodeError> {
+        let failed = |reason: String| DecodeError::StreamFailed { url: url.to_string(), reason };
+        // Playback starts once a prebuffer is in, as after running dry
+        let transfer = Transfer { status: StreamStatus { buffering: Some(0), ..StreamStatus::default() }, ..Transfer::default() };
+        let shared = Arc::new(Shared { transfer: Mutex::new(transfer), changed: Condvar::new() });
+        let download = Download { url: url.to_string(), options, shared: Arc::clone(&shared) };
+        let (head_sender, head_receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("http-stream".to_string())
+            .spawn(move || download.run(head_sender))
+            .map_err(|e| failed(e.to_string()))?;
+
+        // The client gives up by itself; the margin covers its startup
+        let wait = options.connect_timeout + options.stall_timeout + Duration::from_secs(1);
+        let head = match head_receiver.recv_timeout(wait) {
+            Ok(Ok(head)) => head,
+            Ok(Err(reason)) => return Err(failed(reason)),
+            Err(_) => {
+                shared.transfer.lock().unwrap().closed = true;
+                return Err(failed("the server did not answer".to_string()));
+            }
+        };
+
+        OPEN_STREAMS.lock().unwrap().push((url.to_string(), Arc::downgrade(&shared)));
+        Ok(Self {
+            shared,
+            position: 0,
+            seekable: head.seekable(),
+            length: head.length,
+            content_type: head.content_type,
+            prebuffer_bytes: options.prebuffer_bytes.max(1),
+        })
+    }
+
+    /// Media type the server sent, without parameters, e.g. "audio/mpeg"
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+}
+
+impl Read for HttpSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut transfer = self.shared.transfer.lock().unwrap();
+        loop {
+            let available = transfer.ahead();
+            // After running dry, wait for a full prebuffer rather than stuttering on every chunk
+            let ready = transfer.status.buffering.is_none()
+                || available >= self.prebuffer_bytes
+                || transfer.ended
+                || transfer.error.is_some();
+            if available > 0 && ready {
+                transfer.status.buffering = None;
+                let offset = (self.position - transfer.start) as usize;
+                let count = available.min(buf.len());
+                for (byte, &downloaded) in buf.iter_mut().zip(transfer.buffer.range(offset..offset + count)) {
+                    *byte = downloaded;
+                }
+                self.position += count as u64;
+                transfer.read_position = self.position;
+                while transfer.titles.front().is_some_and(|(at, _)| *at <= self.position) {
+                    transfer.status.title = transfer.titles.pop_front().and_then(|(_, title)| title);
+                }
+                let behind = (self.position - transfer.start) as usize;
+                if behind > KEEP_BEHIND {
+                    let dropped = behind - KEEP_BEHIND;
+                    transfer.buffer.drain(..dropped);
+                    transfer.start += dropped as u64;
+                }
+                self.shared.changed.notify_all();
+                return Ok(count);
+            }
+            if available == 0 {
+                if transfer.ended {
+                    return Ok(0);
+                }
+                if let Some(error) = &transfer.error {
+                    return Err(io::Error::other(error.clone()));
+                }
+            }
+            let percent = (available * 100 / self.prebuffer_bytes).min(99);
+            transfer.status.buffering = Some(percent as u8);
+            transfer = self.shared.changed.wait(transfer).unwrap();
+        }
+    }
+}
+
+impl Seek for HttpSource {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let target = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.length.and_then(|length| length.checked_add_signed(delta)),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek outside the stream"))?;
+
+        let mut transfer = self.shared.transfer.lock().unwrap();
+        // Inside the buffer, or at its end where the download carries on anyway
+        if !(transfer.start..=transfer.end()).contains(&target) {
+            if !self.seekable {
+                return Err(io::Error::new(io::ErrorKind::Unsupported, "this stream can't seek"));
+            }
+            transfer.buffer.clear();
+            transfer.titles.clear();
+            transfer.start = target;
+            transfer.ended = false;
+            transfer.restart_at = Some(target);
+        }
+        self.position = target;
+        transfer.read_position = target;
+        self.shared.changed.notify_all();
+        Ok(target)
+    }
+}
+
+impl MediaSource for HttpSource {
+    fn is_seekable(&self) -> bool {
+        self.seekable
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.length
+    }
+}
+
+impl Drop for HttpSource {
+    fn drop(&mut self) {
+        self.shared.transfer.lock().unwrap().closed = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+/// How a response body stopped being copied into the buffer
+enum Copied {
+    Closed,
+    Restart(u64),
+    Ended,
+    Failed(String),
+}
+
+/// The download thread of one source
+struct Download {
+    url: String,
+    options: StreamOptions,
+    shared: Arc<Shared>,
+}
+
+impl Download {
+    fn run(self, head_sender: mpsc::Sender<Result<Head, String>>) {
+        // A blocking client can't be built inside the engine's runtime, so it lives here
+        let connected = Client::builder()
+            .connect_timeout(self.options.connect_timeout)
+            // Per read for a blocking client, which is how a stall shows up
+            .timeout(self.options.stall_timeout)
+            .build()
+            .map_err(|e| describe(&e))
+            .and_then(|client| Ok((self.request(&client, None)?, client)));
+        let (mut response, client) = match connected {
+            Ok(connected) => connected,
+            Err(reason) => {
+                let _ = head_sender.send(Err(reason));
+                return;
+            }
+        };
+        let head = Head::of(&response);
+        let (seekable, length, live) = (head.seekable(), head.length, head.live());
+        self.shared.transfer.lock().unwrap().status.live = live;
+        if head_sender.send(Ok(head)).is_err() {
+            return;
+        }
+
+        let mut offset = 0;
+        let mut failures = 0;
+        loop {
+            let before = offset;
+            let mut failure = match self.copy(&mut response, &mut offset, length) {
+                Copied::Closed => return,
+                Copied::Restart(target) => {
+                    offset = target;
+                    None
+                }
+                Copied::Ended if live => Some("the station closed the connection".to_string()),
+                Copied::Ended => match self.wait_for_restart() {
+                    Some(target) => {
+                        offset = target;
+                        None
+                    }
+                    None => return,
+                },
+                // Starting over would play the beginning again
+                Copied::Failed(reason) if !seekable && length.is_some() => {
+                    self.fail(reason);
+                    return;
+                }
+                Copied::Failed(reason) => Some(reason),
+            };
+            if offset != before {
+                failures = 0;
+            }
+
+            // Live streams pick up wherever the station is now
+            response = loop {
+                if let Some(reason) = failure.take() {
+                    failures += 1;
+                    if failures > self.options.reconnect_attempts {
+                        self.fail(format!("{} (gave up after {} reconnects)", reason, self.options.reconnect_attempts));
+                        return;
+                    }
+                    log::warn!("Stream {} interrupted ({}), reconnecting", self.url, reason);
+                    thread::sleep(reconnect_delay(failures));
+                    if self.shared.transfer.lock().unwrap().closed {
+                        return;
+                    }
+                }
+                match self.request(&client, seekable.then_some(offset)) {
+                    Ok(response) => break response,
+                    Err(reason) => failure = Some(reason),
+                }
+            };
+        }
+    }
+
+    /// GET the stream, from `offset` on when given
+    fn request(&self, client: &Client, offset: Option<u64>) -> Result<Response, String> {
+        let offset = offset.filter(|&offset| offset > 0);
+        let mut request = client.get(&self.url).header("Icy-MetaData", "1");
+        if let Some(offset) = offset {
+            request = request.header(RANGE, format!("bytes={}-", offset));
+        }
+        let response = request.send().map_err(|e| describe(&e))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("the server answered {}", status));
+        }
+        if offset.is_some() && status != StatusCode::PARTIAL_CONTENT {
+            return Err("the server ignored the range request".to_string());
+        }
+        Ok(response)
+    }
+
+    /// Copy the body of `response` into the buffer until it ends, fails or the source seeks away
+    fn copy(&self, response: &mut Response, offset: &mut u64, length: Option<u64>) -> Copied {
+        let mut icy = response.headers().get("icy-metaint")
+            .and_then(|value| value.to_str().ok()?.parse().ok())
+            .filter(|&interval: &usize| interval > 0)
+            .map(IcyMetadata::new);
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let mut audio = Vec::with_capacity(CHUNK_SIZE);
+        loop {
+            {
+                let mut transfer = self.shared.transfer.lock().unwrap();
+                while !transfer.closed && transfer.restart_at.is_none() && transfer.ahead() >= MAX_AHEAD {
+                    transfer = self.shared.changed.wait(transfer).unwrap();
+                }
+                if transfer.closed {
+                    return Copied::Closed;
+                }
+                if let Some(target) = transfer.restart_at.take() {
+                    return Copied::Restart(target);
+                }
+            }
+
+            let read = match response.read(&mut chunk) {
+                Ok(0) if length.is_some_and(|length| *offset < length) => {
+                    return Copied::Failed("the connection closed early".to_string());
+                }
+                Ok(0) => return Copied::Ended,
+                Ok(read) => read,
+                Err(e) => return Copied::Failed(e.to_string()),
+            };
+            audio.clear();
+            let title = match &mut icy {
+                Some(icy) => icy.strip(&chunk[..read], &mut audio),
+                None => {
+                    audio.extend_from_slice(&chunk[..read]);
+                    None
+                }
+            };
+
+            let mut transfer = self.shared.transfer.lock().unwrap();
+            // Bytes from before a seek; the next pass restarts the download
+            if transfer.closed || transfer.restart_at.is_some() {
+                continue;
+            }
+            transfer.buffer.extend(&audio);
+            *offset += audio.len() as u64;
+            if let Some(title) = title {
+                transfer.titles.push_back((*offset, Some(title).filter(|title| !title.is_empty())));
+            }
+            self.shared.changed.notify_all();
+        }
+    }
+
+    /// Wait at the end of the stream for a seek back; None once the source is dropped
+    fn wait_for_restart(&self) -> Option<u64> {
+        let mut transfer = self.shared.transfer.lock().unwrap();
+        transfer.ended = true;
+        self.shared.changed.notify_all();
+        loop {
+            if transfer.closed {
+                return None;
+            }
+            if let Some(target) = transfer.restart_at.take() {
+                return Some(target);
+            }
+            transfer = self.shared.changed.wait(transfer).unwrap();
+        }
+    }
+
+    fn fail(&self, reason: String) {
+        self.shared.transfer.lock().unwrap().error = Some(reason);
+        self.shared.changed.notify_all();
+    }
+}
+
+/// Pause before reconnect number `failures` (from 1): 0.5s, 1s, 2s, ... up to 8s
+fn reconnect_delay(failures: u32) -> Duration {
+    let doublings = failures.saturating_sub(1).min(16);
+    (RECONNECT_DELAY * 2u32.pow(doublings)).min(MAX_RECONNECT_DELAY)
+}
+
+/// A reqwest error as its root cause, e.g. "could not connect: Connection refused (os error 111)"
+fn describe(error: &reqwest::Error) -> String {
+    let mut cause: &dyn std::error::Error = error;
+    while let Some(source) = cause.source() {
+        cause = source;
+    }
+    if error.is_timeout() {
+        "timed out".to_string()
+    } else if error.is_connect() {
+        format!("could not connect: {}", cause)
+    } else {
+        cause.to_string()
+    }
+}
+
+/// Takes the Shoutcast/Icecast metadata blocks out of a response body
+///
+/// Every `interval` audio bytes the server sends a length byte (blocks of 16) and that many
+/// bytes of text such as `StreamTitle='Artist - Title';`, padded with NULs.
+struct IcyMetadata {
+    interval: usize,
+    /// Audio bytes left before the next length byte
+    audio_left: usize,
+    block: Vec<u8>,
+    block_len: Option<usize>,
+}
+
+impl IcyMetadata {
+    fn new(interval: usize) -> Self {
+        Self { interval, audio_left: interval, block: Vec::new(), block_len: None }
+    }
+
+    /// Append the audio in `data` to `audio`; returns the last `StreamTitle` completed in it
+    fn strip(&mut self, mut data: &[u8], audio: &mut Vec<u8>) -> Option<String> {
+        let mut title = None;
+        while !data.is_empty() {
+            if self.audio_left > 0 {
+                let count = self.audio_left.min(data.len());
+                audio.extend_from_slice(&data[..count]);
+                self.audio_left -= count;
+                data = &data[count..];
+                continue;
+            }
+            let Some(block_len) = self.block_len else {
+                self.block_len = Some(data[0] as usize * 16);
+                data = &data[1..];
+                if self.block_len == Some(0) {
+                    self.block_len = None;
+                    self.audio_left = self.interval;
+                }
+                continue;
+            };
+            let count = (block_len - self.block.len()).min(data.len());
+            self.block.extend_from_slice(&data[..count]);
+            data = &data[count..];
+            if self.block.len() == block_len {
+                title = stream_title(&self.block).or(title);
+                self.block.clear();
+                self.block_len = None;
+                self.audio_left = self.interval;
+            }
+        }
+        title
+    }
+}
+
+/// The `StreamTitle` of a metadata block
+fn stream_title(block: &[u8]) -> Option<String> {
+    const KEY: &str = "StreamTitle='";
+    let text = String::from_utf8_lossy(block);
+    let text = text.trim_end_matches('\0');
+    let rest = &text[text.find(KEY)? + KEY.len()..];
+    let title = match rest.find("';") {
+        Some(end) => &rest[..end],
+        None => rest.trim_end_matches(';').trim_end_matches('\''),
+    };
+    Some(title.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// How the test server answers
+    #[derive(Clone, Default)]
+    struct Serve {
+        body: Vec<u8>,
+        ranges: bool,
+        /// Send an ICY block with this title every `metaint` bytes
+        icy: Option<(usize, &'static str)>,
+        /// Drop the first connection after this many body bytes
+        drop_first_after: Option<usize>,
+        status: Option<&'static str>,
+    }
+
+    /// Serve on a local port; returns the base URL and the number of requests so far
+    fn serve(serve: Serve) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let request = counter.fetch_add(1, Ordering::SeqCst);
+                let serve = serve.clone();
+                thread::spawn(move || answer(stream, &serve, request));
+            }
+        });
+        (url, requests)
+    }
+
+    fn answer(mut stream: TcpStream, serve: &Serve, request: usize) {
+        let mut range_start = 0;
+        let mut icy_requested = false;
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                break;
+            }
+            let lower = line.to_ascii_lowercase();
+            if let Some(range) = lower.strip_prefix("range: bytes=") {
+                range_start = range.trim().trim_end_matches('-').parse().unwrap();
+            }
+            icy_requested |= lower.starts_with("icy-metadata: 1");
+        }
+
+        if let Some(status) = serve.status {
+            let _ = write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+            return;
+        }
+        let mut headers = "Content-Type: audio/wav\r\nConnection: close\r\n".to_string();
+        let icy = serve.icy.filter(|_| icy_requested);
+        if let Some((metaint, _)) = icy {
+            headers += &format!("icy-metaint: {}\r\n", metaint);
+        }
+        let body = &serve.body[range_start..];
+        let status = if range_start > 0 && serve.ranges { "206 Partial Content" } else { "200 OK" };
+        if serve.ranges {
+            headers += &format!("Accept-Ranges: bytes\r\nContent-Length: {}\r\n", body.len());
+        }
+        let _ = write!(stream, "HTTP/1.1 {}\r\n{}\r\n", status, headers);
+
+        let mut sent = Vec::new();
+        match icy {
+            Some((metaint, title)) => {
+                let mut block = format!("StreamTitle='{}';", title).into_bytes();
+                block.resize(block.len().div_ceil(16) * 16, 0);
+                for (index, audio) in body.chunks(metaint).enumerate() {
+                    sent.extend_from_slice(audio);
+                    if audio.len() == metaint {
+                        // The title once, then empty blocks
+                        if index == 0 {
+                            sent.push((block.len() / 16) as u8);
+                            sent.extend_from_slice(&block);
+                        } else {
+                            sent.push(0);
+                        }
+                    }
+                }
+            }
+            None => sent.extend_from_slice(body),
+        }
+        if request == 0 {
+            if let Some(limit) = serve.drop_first_after {
+                sent.truncate(limit);
+            }
+        }
+        let _ = stream.write_all(&sent);
+    }
+
+    fn pattern(length: usize) -> Vec<u8> {
+        (0..length).map(|index| (index % 251) as u8).collect()
+    }
+
+    fn options() -> StreamOptions {
+        StreamOptions {
+            connect_timeout: Duration::from_secs(2),
+            stall_timeout: Duration::from_secs(2),
+            reconnect_attempts: 2,
+            prebuffer_bytes: 4096,
+        }
+    }
+
+    #[test]
+    fn test_stream_url_and_extension() {
+        assert_eq!(stream_url(Path::new("https://example.com/track.flac")), Some("https://example.com/track.flac"));
+        assert_eq!(stream_url(Path::new("HTTP://example.com/")), Some("HTTP://example.com/"));
+        assert_eq!(stream_url(Path::new("/music/http:/track.flac")), None);
+        assert_eq!(stream_url(Path::new("ftp://example.com/track.flac")), None);
+
+        assert_eq!(stream_extension("https://example.com/a/track.FLAC?token=1", None).as_deref(), Some("flac"));
+        // A host name is not a file name
+        assert_eq!(stream_extension("http://radio.example.com", Some("audio/mpeg")).as_deref(), Some("mp3"));
+        assert_eq!(stream_extension("http://radio.example.com/live", Some("audio/aacp")).as_deref(), Some("aac"));
+        assert_eq!(stream_extension("http://radio.example.com/live", Some("text/html")), None);
+    }
+
+    #[test]
+    fn test_icy_metadata_is_taken_out_across_chunks() {
+        let mut icy = IcyMetadata::new(4);
+        let mut block = b"StreamTitle='Artist - Song';".to_vec();
+        block.resize(32, 0);
+        let mut body = b"abcd".to_vec();
+        body.push(2);
+        body.extend_from_slice(&block);
+        body.extend_from_slice(b"efgh");
+        body.push(0);
+        body.extend_from_slice(b"ij");
+
+        let mut audio = Vec::new();
+        let mut titles = Vec::new();
+        for chunk in body.chunks(3) {
+            titles.extend(icy.strip(chunk, &mut audio));
+        }
+        assert_eq!(audio, b"abcdefghij");
+        assert_eq!(titles, vec!["Artist - Song".to_string()]);
+        assert_eq!(stream_title(b"StreamTitle='';StreamUrl='';").as_deref(), Some(""));
+        assert_eq!(stream_title(b"StreamUrl='x';"), None);
+    }
+
+    #[test]
+    fn test_reads_and_seeks_a_stream_with_ranges() {
+        let body = pattern(3 * 1024 * 1024);
+        let (url, requests) = serve(Serve { body: body.clone(), ranges: true, ..Serve::default() });
+        let mut source = HttpSource::open(&format!("{}/song.wav", url), options()).unwrap();
+        assert!(source.is_seekable());
+        assert_eq!(source.byte_len(), Some(body.len() as u64));
+        assert_eq!(source.content_type(), Some("audio/wav"));
+
+        let mut start = vec![0; 1000];
+        source.read_exact(&mut start).unwrap();
+        assert_eq!(start, body[..1000]);
+
+        // Far enough ahead that the start is dropped, then back: a new range request
+        source.seek(SeekFrom::Start(2 * 1024 * 1024)).unwrap();
+        let mut middle = vec![0; 1000];
+        source.read_exact(&mut middle).unwrap();
+        assert_eq!(middle, body[2 * 1024 * 1024..][..1000]);
+        source.seek(SeekFrom::Start(10)).unwrap();
+        source.read_exact(&mut start).unwrap();
+        assert_eq!(start, body[10..1010]);
+        assert!(requests.load(Ordering::SeqCst) >= 2);
+
+        source.seek(SeekFrom::End(-100)).unwrap();
+        let mut end = Vec::new();
+        source.read_to_end(&mut end).unwrap();
+        assert_eq!(end, body[body.len() - 100..]);
+    }
+
+    #[test]
+    fn test_dropped_connection_resumes_where_it_stopped() {
+        let body = pattern(200_000);
+        let (url, requests) = serve(Serve { body: body.clone(), ranges: true, drop_first_after: Some(50_000), ..Serve::default() });
+        let mut source = HttpSource::open(&url, options()).unwrap();
+        let mut read = Vec::new();
+        source.read_to_end(&mut read).unwrap();
+        assert_eq!(read.len(), body.len());
+        assert!(read == body, "the resumed download must continue at the right byte");
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_radio_stream_keeps_the_title_and_cannot_seek() {
+        let body = pattern(20_000);
+        let (url, requests) = serve(Serve { body: body.clone(), icy: Some((1000, "Artist - Song")), ..Serve::default() });
+        let url = format!("{}/live", url);
+        let mut source = HttpSource::open(&url, options()).unwrap();
+        assert!(!source.is_seekable());
+        assert_eq!(source.byte_len(), None);
+
+        let mut read = vec![0; body.len()];
+        source.read_exact(&mut read).unwrap();
+        assert!(read == body, "metadata blocks must not reach the decoder");
+        let status = stream_status(&url).unwrap();
+        assert_eq!(status.title.as_deref(), Some("Artist - Song"));
+        assert!(status.live);
+        // Only within what is still buffered
+        assert_eq!(source.seek(SeekFrom::Start(0)).unwrap(), 0);
+        assert_eq!(source.seek(SeekFrom::Start(10_000_000)).unwrap_err().kind(), io::ErrorKind::Unsupported);
+
+        // The station closing the connection is a drop, not the end
+        source.seek(SeekFrom::Start(body.len() as u64)).unwrap();
+        source.read_exact(&mut read).unwrap();
+        assert!(read == body);
+        assert!(requests.load(Ordering::SeqCst) >= 2);
+
+        drop(source);
+        assert_eq!(stream_status(&url), None);
+    }
+
+    #[test]
+    fn test_reconnect_delay_doubles_up_to_the_cap() {
+        let delays: Vec<_> = (1..=6).map(reconnect_delay).collect();
+        assert_eq!(delays, [500, 1000, 2000, 4000, 8000, 8000].map(Duration::from_millis));
+        assert_eq!(reconnect_delay(u32::MAX), MAX_RECONNECT_DELAY);
+    }
+
+    #[test]
+    fn test_open_reports_what_went_wrong() {
+        let (url, _) = serve(Serve { status: Some("404 Not Found"), ..Serve::default() });
+        match HttpSource::open(&url, options()) {
+            Err(DecodeError::StreamFailed { reason, .. }) => assert_eq!(reason, "the server answered 404 Not Found"),
+            other => panic!("expected a stream error, got {:?}", other.err()),
+        }
+
+        // Nothing listens on a port that was just released
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        match HttpSource::open(&format!("http://127.0.0.1:{}/", port), options()) {
+            Err(DecodeError::StreamFailed { reason, .. }) => assert!(reason.starts_with("could not connect"), "{}", reason),
+            other => panic!("expected a stream error, got {:?}", other.err()),
+        }
+    }
+}
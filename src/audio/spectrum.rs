@@ -0,0 +1,377 @@
+/*!
+Spectrum analysis for the `watch` view and the one-shot `spectrum` command.
+
+- `SpectrumTap` is a lock-free mono copy of decoded audio. The decoder thread pushes into it
+  only while the tap is enabled and silently drops samples when it is full, so the tap can never
+  stall decoding or the output callback.
+- `SpectrumAnalyzer` runs a Hann-windowed FFT over the most recent samples and folds the
+  magnitudes into log-spaced bands from 20 Hz up to the Nyquist frequency of the source, so
+  96 kHz material shows its content above 22 kHz. `SpectrumAnalyzer::snapshot` is the
+  `spectrum` command's variant: a 2048-sample frame and bands stopping at 20 kHz.
+- `render_bars` turns band levels into rows of unicode block characters.
+*/
+
+use crate::audio::RingBuffer;
+use crate::models::AudioBuffer;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// Samples per FFT frame
+pub const FFT_SIZE: usize = 4096;
+
+/// Default number of displayed bands
+pub const DEFAULT_BANDS: usize = 24;
+
+/// Samples per FFT frame for the one-shot `spectrum` command
+pub const SNAPSHOT_FFT_SIZE: usize = 2048;
+
+/// Lowest analysed frequency in Hz
+const MIN_FREQUENCY: f32 = 20.0;
+
+/// Top of the audible range, where the `spectrum` command's bands stop
+const AUDIBLE_MAX_FREQUENCY: f32 = 20_000.0;
+
+/// Level (dBFS) mapped to an empty bar
+const FLOOR_DB: f32 = -90.0;
+
+/// Lock-free tap on decoded audio feeding the spectrum analyzer
+#[derive(Debug)]
+pub struct SpectrumTap {
+    ring: RingBuffer,
+    enabled: AtomicBool,
+    sample_rate: AtomicU32,
+}
+
+impl SpectrumTap {
+    /// Create a disabled tap holding up to four FFT frames
+    pub fn new() -> Self {
+        Self {
+            ring: RingBuffer::new(FFT_SIZE * 4, 1, 0),
+            enabled: AtomicBool::new(false),
+            sample_rate: AtomicU32::new(0),
+        }
+    }
+
+    /// Enable or disable the tap (disabled taps cost nothing in the decoder)
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.ring.clear();
+        }
+    }
+
+    /// Check if the tap is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Sample rate of the most recently pushed audio
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate.load(Ordering::Relaxed)
+    }
+
+    /// Push a decoded buffer as mono; excess samples are dropped when the tap is full
+    pub fn push(&self, buffer: &AudioBuffer) {
+        if !self.is_enabled() || buffer.channels == 0 {
+            return;
+        }
+
+        self.sample_rate.store(buffer.sample_rate, Ordering::Relaxed);
+        let channels = buffer.channels as usize;
+        let frames = buffer.frames.min(self.ring.available_write());
+        let mono: Vec<f32> = buffer.samples
+            .chunks_exact(channels)
+            .take(frames)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+        self.ring.write(&mono);
+    }
+
+    /// Drain pending samples, keeping the newest `FFT_SIZE` in `window`
+    pub fn drain_into(&self, window: &mut Vec<f32>) {
+        let mut chunk = vec![0.0f32; self.ring.available_read()];
+        let read = self.ring.read(&mut chunk);
+        window.extend_from_slice(&chunk[..read]);
+        if window.len() > FFT_SIZE {
+            let excess = window.len() - FFT_SIZE;
+            window.drain(..excess);
+        }
+    }
+}
+
+impl Default for SpectrumTap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// FFT-based log-frequency band analyzer
+pub struct SpectrumAnalyzer {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    bands: usize,
+    /// Top band edge in Hz, when it should stop short of the Nyquist frequency
+    max_frequency: Option<f32>,
+}
+
+impl SpectrumAnalyzer {
+    /// Create an analyzer producing the given number of bands
+    pub fn new(bands: usize) -> Self {
+        Self::with_fft_size(bands, FFT_SIZE)
+    }
+
+    /// Create an analyzer over frames of `fft_size` samples
+    pub fn with_fft_size(bands: usize, fft_size: usize) -> Self {
+        let fft_size = fft_size.max(2);
+        let fft = FftPlanner::new().plan_fft_forward(fft_size);
+        let window = (0..fft_size)
+            .map(|n| {
+                let phase = 2.0 * std::f32::consts::PI * n as f32 / (fft_size - 1) as f32;
+                0.5 - 0.5 * phase.cos()
+            })
+            .collect();
+
+        Self { fft, window, bands: bands.max(1), max_frequency: None }
+    }
+
+    /// The `spectrum` command's analyzer: 24 bands from 20 Hz to 20 kHz over 2048 samples
+    pub fn snapshot() -> Self {
+        Self {
+            max_frequency: Some(AUDIBLE_MAX_FREQUENCY),
+            ..Self::with_fft_size(DEFAULT_BANDS, SNAPSHOT_FFT_SIZE)
+        }
+    }
+
+    /// Samples per FFT frame
+    pub fn fft_size(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Upper frequency edges of each band for a sample rate
+    pub fn band_edges(&self, sample_rate: u32) -> Vec<f32> {
+        let nyquist = sample_rate as f32 / 2.0;
+        let top = self.max_frequency.map_or(nyquist, |max| max.min(nyquist)).max(MIN_FREQUENCY * 2.0);
+        let ratio = top / MIN_FREQUENCY;
+        (1..=self.bands)
+            .map(|band| MIN_FREQUENCY * ratio.powf(band as f32 / self.bands as f32))
+            .collect()
+    }
+
+    /// Geometric centre of each band, for axis labels
+    pub fn band_centers(&self, sample_rate: u32) -> Vec<f32> {
+        let mut low = MIN_FREQUENCY;
+        self.band_edges(sample_rate)
+            .into_iter()
+            .map(|high| {
+                let center = (low * high).sqrt();
+                low = high;
+                center
+            })
+            .collect()
+    }
+
+    /// Peak magnitude in each band, 1.0 for a full-scale sine
+    pub fn magnitudes(&self, samples: &[f32], sample_rate: u32) -> Vec<f32> {
+        if samples.is_empty() || sample_rate == 0 {
+            return vec![0.0; self.bands];
+        }
+        let fft_size = self.fft_size();
+
+        // Right-align the newest samples in the frame and zero-pad the rest
+        let offset = fft_size.saturating_sub(samples.len());
+        let recent = &samples[samples.len().saturating_sub(fft_size)..];
+        let mut frame = vec![Complex::new(0.0f32, 0.0); fft_size];
+        for (i, &sample) in recent.iter().enumerate() {
+            frame[offset + i] = Complex::new(sample * self.window[offset + i], 0.0);
+        }
+        self.fft.process(&mut frame);
+
+        // Hann window has a coherent gain of 0.5
+        let scale = 2.0 / (fft_size as f32 * 0.5);
+        let bin_width = sample_rate as f32 / fft_size as f32;
+
+        let mut magnitudes = Vec::with_capacity(self.bands);
+        let mut low = MIN_FREQUENCY;
+        for high in self.band_edges(sample_rate) {
+            let first_bin = ((low / bin_width).floor() as usize).max(1);
+            let last_bin = ((high / bin_width).ceil() as usize).clamp(first_bin + 1, fft_size / 2);
+            let peak = frame[first_bin..last_bin]
+                .iter()
+                .map(|bin| bin.norm() * scale)
+                .fold(0.0f32, f32::max);
+            magnitudes.push(peak);
+            low = high;
+        }
+
+        magnitudes
+    }
+
+    /// Compute band levels in the range 0.0 (floor) to 1.0 (full scale)
+    pub fn analyze(&self, samples: &[f32], sample_rate: u32) -> Vec<f32> {
+        self.magnitudes(samples, sample_rate)
+            .into_iter()
+            .map(|peak| {
+                let db = 20.0 * peak.max(1e-9).log10();
+                ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+            })
+            .collect()
+    }
+}
+
+/// Render band levels as rows of unicode bars (top row first)
+pub fn render_bars(levels: &[f32], height: usize) -> Vec<String> {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let height = height.max(1);
+
+    (0..height)
+        .rev()
+        .map(|row| {
+            levels.iter()
+                .map(|&level| {
+                    let filled = level.clamp(0.0, 1.0) * height as f32 - row as f32;
+                    if filled >= 1.0 {
+                        '█'
+                    } else if filled > 0.0 {
+                        BLOCKS[((filled * 8.0) as usize).min(7)]
+                    } else {
+                        ' '
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Format a frequency for the spectrum axis (e.g. "48k")
+pub fn format_frequency(hz: f32) -> String {
+    if hz >= 1000.0 {
+        format!("{:.0}k", hz / 1000.0)
+    } else {
+        format!("{:.0}", hz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f32, sample_rate: u32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .map(|n| (2.0 * std::f32::consts::PI * frequency * n as f32 / sample_rate as f32).sin())
+            .collect()
+    }
+
+    fn loudest_band(levels: &[f32]) -> usize {
+        levels.iter()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+            .map(|(index, _)| index)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_band_edges_reach_nyquist() {
+        let analyzer = SpectrumAnalyzer::new(DEFAULT_BANDS);
+
+        let edges = analyzer.band_edges(96_000);
+        assert_eq!(edges.len(), DEFAULT_BANDS);
+        assert!((edges[DEFAULT_BANDS - 1] - 48_000.0).abs() < 1.0);
+        assert!(edges.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let cd_edges = analyzer.band_edges(44_100);
+        assert!((cd_edges[DEFAULT_BANDS - 1] - 22_050.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_ultrasonic_tone_lands_in_top_bands() {
+        let analyzer = SpectrumAnalyzer::new(DEFAULT_BANDS);
+        let samples = sine(30_000.0, 96_000, FFT_SIZE);
+
+        let levels = analyzer.analyze(&samples, 96_000);
+        let band = loudest_band(&levels);
+        let edges = analyzer.band_edges(96_000);
+
+        assert!(edges[band] >= 30_000.0, "30 kHz tone should be above 22 kHz bands");
+        assert!(levels[band] > 0.9, "full-scale tone should be near the top");
+        assert!(levels[0] < 0.5, "low bands should stay quiet");
+    }
+
+    #[test]
+    fn test_snapshot_puts_1khz_sine_in_its_band() {
+        let analyzer = SpectrumAnalyzer::snapshot();
+        let samples = sine(1_000.0, 48_000, SNAPSHOT_FFT_SIZE);
+
+        let magnitudes = analyzer.magnitudes(&samples, 48_000);
+        assert_eq!(magnitudes.len(), DEFAULT_BANDS);
+        let band = loudest_band(&magnitudes);
+        let edges = analyzer.band_edges(48_000);
+        let low = if band == 0 { MIN_FREQUENCY } else { edges[band - 1] };
+        assert!(low <= 1_000.0 && 1_000.0 < edges[band], "1 kHz should fall in the loudest band");
+        for (other, &magnitude) in magnitudes.iter().enumerate().filter(|&(other, _)| other != band) {
+            assert!(magnitudes[band] >= 10.0 * magnitude, "band {} is within 20 dB of the tone", other);
+        }
+        assert!((edges[DEFAULT_BANDS - 1] - 20_000.0).abs() < 1.0, "bands stop at 20 kHz");
+    }
+
+    #[test]
+    fn test_silence_is_empty() {
+        let analyzer = SpectrumAnalyzer::new(8);
+        let levels = analyzer.analyze(&vec![0.0; FFT_SIZE], 48_000);
+        assert!(levels.iter().all(|&level| level == 0.0));
+        assert_eq!(analyzer.analyze(&[], 48_000), vec![0.0; 8]);
+    }
+
+    #[test]
+    fn test_tap_downmixes_and_drops_when_disabled() {
+        let tap = SpectrumTap::new();
+        let stereo = AudioBuffer {
+            samples: vec![1.0, 0.0, 1.0, 0.0],
+            channels: 2,
+            sample_rate: 96_000,
+            frames: 2,
+        };
+
+        tap.push(&stereo);
+        let mut window = Vec::new();
+        tap.drain_into(&mut window);
+        assert!(window.is_empty(), "disabled tap ignores audio");
+
+        tap.set_enabled(true);
+        tap.push(&stereo);
+        tap.drain_into(&mut window);
+        assert_eq!(window, vec![0.5, 0.5]);
+        assert_eq!(tap.sample_rate(), 96_000);
+    }
+
+    #[test]
+    fn test_tap_never_blocks_when_full() {
+        let tap = SpectrumTap::new();
+        tap.set_enabled(true);
+        let buffer = AudioBuffer {
+            samples: vec![0.25; FFT_SIZE],
+            channels: 1,
+            sample_rate: 48_000,
+            frames: FFT_SIZE,
+        };
+
+        for _ in 0..10 {
+            tap.push(&buffer);
+        }
+
+        let mut window = Vec::new();
+        tap.drain_into(&mut window);
+        assert_eq!(window.len(), FFT_SIZE);
+    }
+
+    #[test]
+    fn test_render_bars() {
+        let rows = render_bars(&[0.0, 0.5, 1.0], 2);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0], "  █");
+        assert_eq!(rows[1], " ██");
+        assert_eq!(format_frequency(48_000.0), "48k");
+        assert_eq!(format_frequency(20.0), "20");
+    }
+}
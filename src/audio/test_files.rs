@@ -0,0 +1,93 @@
+/*!
+Small audio files for tests, built byte by byte so a test controls every field.
+
+WAV files are a plain `fmt ` chunk followed by `data`. FLAC files are put together from
+STREAMINFO, other metadata blocks and frames; `flac_frame` fills in the frame's CRC-8 and
+CRC-16 with symphonia's checksums, so callers only write the header and subframes.
+*/
+
+use std::path::Path;
+
+use symphonia::core::checksum::{Crc16Ansi, Crc8Ccitt};
+use symphonia::core::io::Monitor;
+
+/// Little-endian bytes of 16-bit samples, as a WAV `data` chunk holds them
+pub fn pcm16(samples: &[i16]) -> Vec<u8> {
+    samples.iter().flat_map(|sample| sample.to_le_bytes()).collect()
+}
+
+/// Body of a `fmt ` chunk for integer PCM
+pub fn wav_fmt(sample_rate: u32, channels: u16, bit_depth: u16) -> Vec<u8> {
+    let block_align = channels * bit_depth / 8;
+    let mut fmt = Vec::new();
+    fmt.extend_from_slice(&1u16.to_le_bytes());
+    fmt.extend_from_slice(&channels.to_le_bytes());
+    fmt.extend_from_slice(&sample_rate.to_le_bytes());
+    fmt.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    fmt.extend_from_slice(&block_align.to_le_bytes());
+    fmt.extend_from_slice(&bit_depth.to_le_bytes());
+    fmt
+}
+
+/// A RIFF WAVE file of integer PCM holding `data`
+pub fn wav_bytes(sample_rate: u32, channels: u16, bit_depth: u16, data: &[u8]) -> Vec<u8> {
+    let fmt = wav_fmt(sample_rate, channels, bit_depth);
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(20 + fmt.len() as u32 + data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&fmt);
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+/// Write `wav_bytes` to `path`
+pub fn write_wav(path: &Path, sample_rate: u32, channels: u16, bit_depth: u16, data: &[u8]) {
+    std::fs::write(path, wav_bytes(sample_rate, channels, bit_depth, data)).unwrap();
+}
+
+/// STREAMINFO body, 34 bytes, with every block `block_size` samples and no frame sizes
+pub fn flac_streaminfo(
+    block_size: u16,
+    sample_rate: u32,
+    channels: u16,
+    bit_depth: u16,
+    total_samples: u64,
+    md5: [u8; 16],
+) -> Vec<u8> {
+    let mut info = Vec::with_capacity(34);
+    info.extend_from_slice(&block_size.to_be_bytes());
+    info.extend_from_slice(&block_size.to_be_bytes());
+    info.extend_from_slice(&[0; 6]);
+    let packed = (sample_rate as u64) << 44
+        | (channels as u64 - 1) << 41
+        | (bit_depth as u64 - 1) << 36
+        | total_samples;
+    info.extend_from_slice(&packed.to_be_bytes());
+    info.extend_from_slice(&md5);
+    info
+}
+
+/// A metadata block: its header, with the last-block flag, then `body`
+pub fn flac_block(block_type: u8, last: bool, body: &[u8]) -> Vec<u8> {
+    let mut block = vec![block_type | if last { 0x80 } else { 0 }];
+    block.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+    block.extend_from_slice(body);
+    block
+}
+
+/// A frame: `header` up to its CRC-8, then `subframes`, then the CRC-16 over all of it
+pub fn flac_frame(header: &[u8], subframes: &[u8]) -> Vec<u8> {
+    let mut frame = header.to_vec();
+    let mut crc8 = Crc8Ccitt::new(0);
+    crc8.process_buf_bytes(&frame);
+    frame.push(crc8.crc());
+    frame.extend_from_slice(subframes);
+    let mut crc16 = Crc16Ansi::new(0);
+    crc16.process_buf_bytes(&frame);
+    frame.extend_from_slice(&crc16.crc().to_be_bytes());
+    frame
+}
@@ -4,7 +4,7 @@ use std::thread;
 
 use crate::audio::{
     AudioPerformanceProfiler, HighResBufferAllocator, AudioMemoryManager,
-    AudioBuffer, AudioDecoder, AudioMetadata
+    AudioBuffer, AudioCodec, AudioDecoder, AudioMetadata
 };
 use crate::error::DecodeError;
 
@@ -70,8 +70,8 @@ impl AudioDecoder for MockHighResDecoder {
         &self.metadata
     }
 
-    fn duration(&self) -> Duration {
-        self.duration
+    fn duration(&self) -> Option<Duration> {
+        Some(self.duration)
     }
 
     fn sample_rate(&self) -> u32 {
@@ -81,6 +81,14 @@ impl AudioDecoder for MockHighResDecoder {
     fn bit_depth(&self) -> u16 {
         self.bit_depth
     }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn codec(&self) -> AudioCodec {
+        AudioCodec::Flac
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,169 @@
+//! Decode deadline for the decoder thread
+//!
+//! A hung `decode_next` call is a blocking call inside a synchronous decoder, so nothing can
+//! interrupt it: a `tokio::time::timeout` on the decoder's current-thread runtime would never
+//! get to run. The watchdog can only give up on the call. The engine then detaches the stuck
+//! thread and starts a new one, which leaks the old thread, its stack and the decoder it holds
+//! for as long as the call stays blocked. The engine caps how many threads it abandons this
+//! way and reports the count in `perf`.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// No decode is being timed
+const IDLE: u64 = 0;
+/// The monitor gave up on the decode that was running
+const EXPIRED: u64 = u64::MAX;
+
+/// Bounds on how often the monitor thread checks the deadline
+const MIN_CHECK_INTERVAL: Duration = Duration::from_millis(5);
+const MAX_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Deadline for the `decode_next` call running on the decoder thread
+///
+/// The decoder thread arms a deadline before each call and disarms it afterwards, so decoding
+/// stays on the thread that was pinned and given its priority. A monitor thread checks the
+/// deadline; when a call overruns it, the monitor claims the decode as abandoned and reports
+/// it once. Disarming and claiming race on one atomic, so exactly one of them wins: either the
+/// call finished in time or the decoder thread learns on return that it was given up on.
+#[derive(Debug)]
+pub struct DecodeWatchdog {
+    epoch: Instant,
+    /// Milliseconds after `epoch` the running call must end by, `IDLE` or `EXPIRED`
+    deadline_ms: AtomicU64,
+    path: Mutex<PathBuf>,
+    stopped: AtomicBool,
+}
+
+impl Default for DecodeWatchdog {
+    fn default() -> Self {
+        Self {
+            epoch: Instant::now(),
+            deadline_ms: AtomicU64::new(IDLE),
+            path: Mutex::new(PathBuf::new()),
+            stopped: AtomicBool::new(false),
+        }
+    }
+}
+
+impl DecodeWatchdog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start timing a decode of `path`; a zero `timeout` leaves the call unbounded
+    pub fn arm(&self, path: &Path, timeout: Duration) {
+        if timeout.is_zero() {
+            return;
+        }
+        *self.path.lock().unwrap() = path.to_path_buf();
+        let deadline = (self.epoch.elapsed() + timeout).as_millis() as u64;
+        self.deadline_ms.store(deadline.clamp(1, EXPIRED - 1), Ordering::Release);
+    }
+
+    /// End the timed call; false when the monitor already gave up on it
+    pub fn disarm(&self) -> bool {
+        self.deadline_ms.swap(IDLE, Ordering::AcqRel) != EXPIRED
+    }
+
+    /// Claim a decode that is past its deadline, returning the path it was decoding
+    fn expire(&self) -> Option<PathBuf> {
+        let deadline = self.deadline_ms.load(Ordering::Acquire);
+        if deadline == IDLE || deadline == EXPIRED || (self.epoch.elapsed().as_millis() as u64) < deadline {
+            return None;
+        }
+        self.deadline_ms.compare_exchange(deadline, EXPIRED, Ordering::AcqRel, Ordering::Acquire).ok()?;
+        Some(self.path.lock().unwrap().clone())
+    }
+
+    /// Check the deadline on a `decode-watchdog` thread until the returned monitor is dropped
+    ///
+    /// `on_expired` runs once, with the path of the decode that was given up on; the thread
+    /// then exits, since the decoder thread it watched is done once the call returns.
+    pub fn monitor(
+        self: &Arc<Self>,
+        timeout: Duration,
+        on_expired: impl FnOnce(PathBuf) + Send + 'static,
+    ) -> std::io::Result<WatchdogMonitor> {
+        let watchdog = Arc::clone(self);
+        let interval = (timeout / 5).clamp(MIN_CHECK_INTERVAL, MAX_CHECK_INTERVAL);
+        let handle = thread::Builder::new()
+            .name("decode-watchdog".to_string())
+            .spawn(move || {
+                while !watchdog.stopped.load(Ordering::Relaxed) {
+                    if let Some(path) = watchdog.expire() {
+                        on_expired(path);
+                        return;
+                    }
+                    thread::park_timeout(interval);
+                }
+            })?;
+        Ok(WatchdogMonitor { watchdog: Arc::clone(self), handle: Some(handle) })
+    }
+}
+
+/// The running monitor thread; dropping it stops the thread
+#[derive(Debug)]
+pub struct WatchdogMonitor {
+    watchdog: Arc<DecodeWatchdog>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for WatchdogMonitor {
+    fn drop(&mut self) {
+        self.watchdog.stopped.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_a_call_that_returns_in_time_is_kept() {
+        let watchdog = Arc::new(DecodeWatchdog::new());
+        let (sender, expired) = mpsc::channel();
+        let _monitor = watchdog.monitor(Duration::from_millis(50), move |path| sender.send(path).unwrap()).unwrap();
+
+        for _ in 0..3 {
+            watchdog.arm(Path::new("fine.flac"), Duration::from_millis(50));
+            thread::sleep(Duration::from_millis(10));
+            assert!(watchdog.disarm());
+        }
+        // Idle between calls is not counted against the deadline
+        assert!(expired.recv_timeout(Duration::from_millis(150)).is_err());
+    }
+
+    #[test]
+    fn test_an_overrunning_call_is_reported_once() {
+        let watchdog = Arc::new(DecodeWatchdog::new());
+        let (sender, expired) = mpsc::channel();
+        let monitor = watchdog.monitor(Duration::from_millis(20), move |path| sender.send(path).unwrap()).unwrap();
+
+        watchdog.arm(Path::new("stuck.flac"), Duration::from_millis(20));
+        assert_eq!(expired.recv_timeout(Duration::from_secs(5)).unwrap(), PathBuf::from("stuck.flac"));
+        // The decoder thread finds out when the call finally returns
+        assert!(!watchdog.disarm());
+        drop(monitor);
+        assert!(expired.recv().is_err());
+    }
+
+    #[test]
+    fn test_a_zero_timeout_never_expires() {
+        let watchdog = Arc::new(DecodeWatchdog::new());
+        let (sender, expired) = mpsc::channel();
+        let _monitor = watchdog.monitor(Duration::from_millis(5), move |path| sender.send(path).unwrap()).unwrap();
+
+        watchdog.arm(Path::new("stream"), Duration::ZERO);
+        assert!(expired.recv_timeout(Duration::from_millis(50)).is_err());
+        assert!(watchdog.disarm());
+    }
+}
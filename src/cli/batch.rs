@@ -0,0 +1,112 @@
+use crate::cli::{CliApp, Commands, ParseError};
+use crate::error::PlayerError;
+
+/// Outcome of a batch run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BatchReport {
+    /// Commands that ran successfully
+    pub executed: usize,
+    /// Lines that could not be parsed or whose command failed
+    pub failed: usize,
+    /// Stopped by `exit`/`quit` or, with abort on error, by a failure
+    pub stopped: bool,
+}
+
+/// Run the commands in `script` in order, one per line
+///
+/// Blank lines and lines starting with `#` are skipped. A line that fails is reported with its
+/// line number; later lines still run unless `abort_on_error` is set.
+pub async fn run_batch(
+    script: &str,
+    abort_on_error: bool,
+    mut execute: impl AsyncFnMut(Commands) -> Result<(), PlayerError>,
+) -> BatchReport {
+    let mut report = BatchReport::default();
+
+    for (index, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line == "exit" || line == "quit" {
+            report.stopped = true;
+            break;
+        }
+
+        let result = match CliApp::parse_command(line) {
+            Ok(command) => execute(command).await,
+            Err(ParseError::HelpRequested) => {
+                CliApp::display_help();
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(()) => report.executed += 1,
+            Err(e) => {
+                eprintln!("Error: line {}: {}", index + 1, e);
+                report.failed += 1;
+                if abort_on_error {
+                    report.stopped = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::QueueAction;
+    use std::io::Write;
+
+    /// Commands as they were executed, failing `queue clear` like a player would on error
+    async fn run(script: &str, abort_on_error: bool) -> (BatchReport, Vec<String>) {
+        let mut executed = Vec::new();
+        let report = run_batch(script, abort_on_error, async |command| {
+            executed.push(format!("{:?}", command));
+            match command {
+                Commands::Queue { action: QueueAction::Clear } => Err(crate::error::QueueError::EmptyQueue.into()),
+                _ => Ok(()),
+            }
+        })
+        .await;
+        (report, executed)
+    }
+
+    #[tokio::test]
+    async fn test_batch_file_runs_commands_in_order() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "# evening setup\n\nvolume 50\n  queue add /tmp\n").unwrap();
+        let script = std::fs::read_to_string(file.path()).unwrap();
+
+        let (report, executed) = run(&script, false).await;
+        assert_eq!(report, BatchReport { executed: 2, failed: 0, stopped: false });
+        assert_eq!(executed, vec![
+            format!("{:?}", Commands::Volume { level: 50 }),
+            format!("{:?}", Commands::Queue { action: QueueAction::Add { path: "/tmp".into(), filters: Default::default() } }),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_bad_line_does_not_stop_later_lines() {
+        let (report, executed) = run("volume 50\nvolume loud\nqueue add /tmp\nqueue clear\nnext", false).await;
+        assert_eq!(report, BatchReport { executed: 3, failed: 2, stopped: false });
+        assert_eq!(executed.len(), 4);
+        assert!(executed[1].starts_with("Queue"));
+    }
+
+    #[tokio::test]
+    async fn test_abort_on_error_and_exit() {
+        let (report, executed) = run("volume 50\nvolume loud\nqueue add /tmp", true).await;
+        assert_eq!(report, BatchReport { executed: 1, failed: 1, stopped: true });
+        assert_eq!(executed.len(), 1);
+
+        let (report, executed) = run("volume 50\nquit\nqueue add /tmp", false).await;
+        assert_eq!(report, BatchReport { executed: 1, failed: 0, stopped: true });
+        assert_eq!(executed.len(), 1);
+    }
+}
@@ -0,0 +1,83 @@
+use rustyline::ExternalPrinter;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// Output for notifications that can arrive while the user is typing
+///
+/// With a line editor attached, messages go through its external printer, which clears the
+/// prompt and pending input, prints the message and redraws both. Otherwise they go to stdout.
+#[derive(Clone, Default)]
+pub struct ConsoleOutput {
+    printer: Option<Arc<Mutex<Box<dyn ExternalPrinter + Send>>>>,
+}
+
+impl ConsoleOutput {
+    /// Plain stdout, for when no prompt is shown
+    pub fn stdout() -> Self {
+        Self::default()
+    }
+
+    /// Route messages through a line editor's printer
+    pub fn with_printer(printer: Box<dyn ExternalPrinter + Send>) -> Self {
+        Self { printer: Some(Arc::new(Mutex::new(printer))) }
+    }
+
+    /// Print a notification without breaking the prompt
+    pub fn print_async(&self, message: &str) {
+        let line = notice_line(message);
+        if let Some(printer) = &self.printer {
+            match printer.lock().unwrap().print(line.clone()) {
+                Ok(()) => return,
+                Err(e) => log::debug!("External printer failed, writing to stdout: {}", e),
+            }
+        }
+        let mut stdout = std::io::stdout();
+        let _ = stdout.write_all(line.as_bytes());
+        let _ = stdout.flush();
+    }
+}
+
+/// The message on lines of its own: blank lines around it dropped, newline-terminated
+///
+/// The printer clears the prompt line before writing, so messages no longer need a leading
+/// newline to get off it; a trailing one stops the redrawn prompt from joining the message.
+fn notice_line(message: &str) -> String {
+    format!("{}\n", message.trim_matches(|c| c == '\n' || c == '\r'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records what would have been written above the prompt
+    struct RecordingPrinter(Arc<Mutex<Vec<String>>>);
+
+    impl ExternalPrinter for RecordingPrinter {
+        fn print(&mut self, msg: String) -> rustyline::Result<()> {
+            self.0.lock().unwrap().push(msg);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_notice_line_stands_alone() {
+        assert_eq!(notice_line("Now playing: Song - Artist"), "Now playing: Song - Artist\n");
+        assert_eq!(notice_line("\nQueue finished"), "Queue finished\n");
+        assert_eq!(notice_line("Device connected: DAC\r\n\n"), "Device connected: DAC\n");
+        assert_eq!(notice_line("first\nsecond"), "first\nsecond\n");
+    }
+
+    #[test]
+    fn test_print_async_goes_through_printer() {
+        let printed = Arc::new(Mutex::new(Vec::new()));
+        let console = ConsoleOutput::with_printer(Box::new(RecordingPrinter(printed.clone())));
+
+        console.print_async("Now playing: Song - Artist");
+        console.clone().print_async("\nQueue finished");
+
+        assert_eq!(*printed.lock().unwrap(), vec![
+            "Now playing: Song - Artist\n".to_string(),
+            "Queue finished\n".to_string(),
+        ]);
+    }
+}
@@ -0,0 +1,387 @@
+use crate::cli::{CliApp, ConsoleOutput};
+use crate::config::ConfigManager;
+use crate::queue::{QueueManager, QueueManagerImpl, SortKey};
+use clap::CommandFactory;
+use rustyline::completion::{Completer, Pair};
+use rustyline::config::{CompletionType, Config};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::{FileHistory, History};
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, ExternalPrinter, Helper};
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+
+/// Commands kept in the history file
+const MAX_HISTORY: usize = 1000;
+
+/// Tab completion for interactive commands and their arguments
+pub struct CommandCompleter {
+    queue_manager: Arc<Mutex<QueueManagerImpl>>,
+    devices: Arc<Mutex<Vec<String>>>,
+}
+
+impl CommandCompleter {
+    /// `devices` is a snapshot of output device names, replaced when devices change
+    pub fn new(queue_manager: Arc<Mutex<QueueManagerImpl>>, devices: Arc<Mutex<Vec<String>>>) -> Self {
+        Self { queue_manager, devices }
+    }
+
+    /// Start of the word being completed and the candidates for it
+    pub fn complete_line(&self, line: &str, pos: usize) -> (usize, Vec<Pair>) {
+        let head = &line[..pos];
+        let words = word_offsets(head);
+        let current = if words.is_empty() || head.ends_with(char::is_whitespace) {
+            words.len()
+        } else {
+            words.len() - 1
+        };
+        // Paths and names may contain spaces, so they run to the cursor
+        let arg_start = |index: usize| words.get(index).map_or(pos, |&(start, _)| start);
+        let word = |index: usize| words.get(index).map(|&(_, word)| word);
+
+        if current == 0 {
+            let start = arg_start(0);
+            return (start, matching(command_names(), &head[start..]));
+        }
+
+        let command = word(0).unwrap_or_default();
+        let subcommand = word(1).unwrap_or_default();
+        let (start, candidates) = match (command, current) {
+            ("play" | "info", _) => {
+                let start = arg_start(1);
+                return (start, complete_path(&head[start..]));
+            }
+            ("queue", 2..) if matches!(subcommand, "add" | "add-next") => {
+                let start = arg_start(2);
+                return (start, complete_path(&head[start..]));
+            }
+            ("queue", 3..) if subcommand == "insert" => {
+                let start = arg_start(3);
+                return (start, complete_path(&head[start..]));
+            }
+            ("queue", 2) if subcommand == "sort" => (arg_start(2), SortKey::ALL.iter().map(|key| key.as_str().to_string()).collect()),
+            ("playlist", 2..) if matches!(subcommand, "load" | "delete" | "save" | "rename" | "merge") => (arg_start(2), self.playlist_names()),
+            ("device", 2..) if matches!(subcommand, "set" | "capabilities" | "caps") => (arg_start(2), self.devices.lock().unwrap().clone()),
+            ("device", 2) if subcommand == "exclusive" => (arg_start(2), on_off()),
+            ("limiter" | "silence" | "crossfeed" | "fav" | "favorite", 1) => (arg_start(1), on_off()),
+            ("export", 1) => (arg_start(1), vec!["json".to_string(), "csv".to_string()]),
+            ("export", 2..) => {
+                let start = arg_start(2);
+                return (start, complete_path(&head[start..]));
+            }
+            ("config", 2) if matches!(subcommand, "get" | "set") => (arg_start(2), ConfigManager::SETTABLE_KEYS.iter().map(|key| key.to_string()).collect()),
+            (_, 1) => (arg_start(1), subcommand_names(command)),
+            _ => return (pos, Vec::new()),
+        };
+        (start, matching(candidates, &head[start..]))
+    }
+
+    fn playlist_names(&self) -> Vec<String> {
+        self.queue_manager.lock().unwrap().list_playlists().unwrap_or_default()
+    }
+}
+
+impl Completer for CommandCompleter {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        Ok(self.complete_line(line, pos))
+    }
+}
+
+impl Hinter for CommandCompleter {
+    type Hint = String;
+}
+
+impl Highlighter for CommandCompleter {}
+
+impl Validator for CommandCompleter {}
+
+impl Helper for CommandCompleter {}
+
+/// Reads commands on its own thread so engine events keep being polled
+pub struct LineReader {
+    lines: tokio::sync::mpsc::UnboundedReceiver<String>,
+    ready: mpsc::Sender<()>,
+    console: ConsoleOutput,
+}
+
+impl LineReader {
+    /// Start the editor; history is loaded from and appended to `history_path`
+    pub fn spawn(completer: CommandCompleter, history_path: Option<PathBuf>) -> Self {
+        let (line_tx, lines) = tokio::sync::mpsc::unbounded_channel();
+        let (ready, ready_rx) = mpsc::channel();
+        let (printer_tx, printer_rx) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("line-editor".to_string())
+            .spawn(move || run_editor(completer, history_path, line_tx, ready_rx, printer_tx))
+            .expect("Failed to spawn line editor thread");
+        // Without a terminal there is no prompt to protect
+        let console = match printer_rx.recv() {
+            Ok(Some(printer)) => ConsoleOutput::with_printer(printer),
+            _ => ConsoleOutput::stdout(),
+        };
+        Self { lines, ready, console }
+    }
+
+    /// Output for notifications printed while the prompt is showing
+    pub fn console(&self) -> ConsoleOutput {
+        self.console.clone()
+    }
+
+    /// Next entered line, or `None` on Ctrl-C, Ctrl-D or end of input
+    pub async fn next_line(&mut self) -> Option<String> {
+        self.lines.recv().await
+    }
+
+    /// Show the prompt again once the previous command's output is printed
+    pub fn prompt(&self) {
+        let _ = self.ready.send(());
+    }
+
+    /// `command_history` in the app directory
+    pub fn default_history_path() -> Option<PathBuf> {
+        crate::config::app_dir().map(|dir| dir.join("command_history"))
+    }
+}
+
+fn run_editor(
+    completer: CommandCompleter,
+    history_path: Option<PathBuf>,
+    lines: tokio::sync::mpsc::UnboundedSender<String>,
+    ready: mpsc::Receiver<()>,
+    printer: mpsc::Sender<Option<Box<dyn ExternalPrinter + Send>>>,
+) {
+    let config = Config::builder()
+        .max_history_size(MAX_HISTORY)
+        .and_then(|builder| builder.history_ignore_dups(true))
+        .map(|builder| builder.completion_type(CompletionType::List).auto_add_history(false).build())
+        .unwrap_or_default();
+    let mut editor: Editor<CommandCompleter, FileHistory> = match Editor::with_config(config) {
+        Ok(editor) => editor,
+        Err(e) => {
+            log::error!("Could not start line editor: {}", e);
+            let _ = printer.send(None);
+            return;
+        }
+    };
+    editor.set_helper(Some(completer));
+    let _ = printer.send(editor.create_external_printer().ok().map(|printer| Box::new(printer) as Box<dyn ExternalPrinter + Send>));
+
+    if let Some(path) = &history_path {
+        match editor.load_history(path) {
+            Ok(()) => {}
+            Err(ReadlineError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("Could not load command history from {}: {}", path.display(), e),
+        }
+    }
+
+    loop {
+        match editor.readline("> ") {
+            Ok(line) => {
+                let line = line.trim().to_string();
+                if !line.is_empty() && editor.add_history_entry(line.as_str()).unwrap_or(false) {
+                    if let Some(path) = &history_path {
+                        save_history(editor.history_mut(), path);
+                    }
+                }
+                if lines.send(line).is_err() || ready.recv().is_err() {
+                    break;
+                }
+            }
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                log::warn!("Line editor stopped: {}", e);
+                break;
+            }
+        }
+    }
+}
+
+fn save_history(history: &mut FileHistory, path: &std::path::Path) {
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Err(e) = history.append(path) {
+        log::warn!("Could not save command history to {}: {}", path.display(), e);
+    }
+}
+
+/// Byte offset and text of each whitespace separated word
+fn word_offsets(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (index, c) in text.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(begin)) => {
+                words.push((begin, &text[begin..index]));
+                start = None;
+            }
+            (false, None) => start = Some(index),
+            _ => {}
+        }
+    }
+    if let Some(begin) = start {
+        words.push((begin, &text[begin..]));
+    }
+    words
+}
+
+/// Candidates starting with `prefix`, ignoring case
+fn matching(candidates: Vec<String>, prefix: &str) -> Vec<Pair> {
+    let prefix = prefix.to_lowercase();
+    let mut pairs: Vec<Pair> = candidates
+        .into_iter()
+        .filter(|candidate| candidate.to_lowercase().starts_with(&prefix))
+        .map(|candidate| Pair { display: candidate.clone(), replacement: candidate })
+        .collect();
+    pairs.sort_by(|a, b| a.replacement.cmp(&b.replacement));
+    pairs.dedup_by(|a, b| a.replacement == b.replacement);
+    pairs
+}
+
+fn command_names() -> Vec<String> {
+    let mut names: Vec<String> = CliApp::command()
+        .get_subcommands()
+        .map(|command| command.get_name().to_string())
+        .collect();
+    names.extend(["help", "exit", "quit"].map(String::from));
+    names
+}
+
+fn subcommand_names(command: &str) -> Vec<String> {
+    CliApp::command()
+        .find_subcommand(command)
+        .map(|command| command.get_subcommands().map(|sub| sub.get_name().to_string()).collect())
+        .unwrap_or_default()
+}
+
+fn on_off() -> Vec<String> {
+    vec!["on".to_string(), "off".to_string()]
+}
+
+/// Directory entries completing `prefix`, as typed (spaces are not escaped)
+fn complete_path(prefix: &str) -> Vec<Pair> {
+    let (dir_part, name_prefix) = match prefix.rfind('/') {
+        Some(index) => prefix.split_at(index + 1),
+        None if prefix == "~" => return vec![Pair { display: "~/".to_string(), replacement: "~/".to_string() }],
+        None => ("", prefix),
+    };
+    let dir = if dir_part.is_empty() { PathBuf::from(".") } else { CliApp::expand_path(dir_part) };
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut pairs: Vec<Pair> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with(name_prefix) || (name.starts_with('.') && !name_prefix.starts_with('.')) {
+                return None;
+            }
+            let display = if entry.path().is_dir() { format!("{}/", name) } else { name };
+            Some(Pair { replacement: format!("{}{}", dir_part, display), display })
+        })
+        .collect();
+    pairs.sort_by(|a, b| a.display.cmp(&b.display));
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::ParseError;
+    use tempfile::TempDir;
+
+    fn completer(playlist_dir: &std::path::Path) -> CommandCompleter {
+        let queue_manager = QueueManagerImpl::with_playlist_directory(playlist_dir.to_path_buf()).unwrap();
+        CommandCompleter::new(Arc::new(Mutex::new(queue_manager)), Arc::new(Mutex::new(Vec::new())))
+    }
+
+    fn replacements(result: (usize, Vec<Pair>)) -> (usize, Vec<String>) {
+        (result.0, result.1.into_iter().map(|pair| pair.replacement).collect())
+    }
+
+    #[test]
+    fn test_completes_commands_and_subcommands() {
+        let dir = TempDir::new().unwrap();
+        let completer = completer(dir.path());
+
+        let (start, names) = replacements(completer.complete_line("pl", 2));
+        assert_eq!(start, 0);
+        assert_eq!(names, vec!["play", "playlist"]);
+
+        let (start, names) = replacements(completer.complete_line("queue s", 7));
+        assert_eq!(start, 6);
+        assert_eq!(names, vec!["shuffle", "sort"]);
+
+        let (_, names) = replacements(completer.complete_line("queue sort d", 12));
+        assert_eq!(names, vec!["disc", "duration"]);
+
+        let (_, names) = replacements(completer.complete_line("limiter o", 9));
+        assert_eq!(names, vec!["off", "on"]);
+
+        let (_, names) = replacements(completer.complete_line("config s", 8));
+        assert_eq!(names, vec!["set", "show"]);
+
+        let (start, names) = replacements(completer.complete_line("config set log_f", 16));
+        assert_eq!(start, 11);
+        assert_eq!(names, vec!["log_file", "log_file_path", "log_format"]);
+    }
+
+    #[test]
+    fn test_every_completed_command_is_understood() {
+        let dir = TempDir::new().unwrap();
+        let completer = completer(dir.path());
+        let (_, names) = replacements(completer.complete_line("", 0));
+        for name in names.iter().filter(|name| !matches!(name.as_str(), "exit" | "quit")) {
+            let result = CliApp::parse_command(name);
+            assert!(
+                !matches!(result, Err(ParseError::UnknownCommand { .. })),
+                "'{}' is offered but not understood",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_completes_playlist_and_device_names() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("Evening Jazz.m3u"), "").unwrap();
+        std::fs::write(dir.path().join("Workout.m3u"), "").unwrap();
+        let completer = completer(dir.path());
+        *completer.devices.lock().unwrap() = vec!["USB DAC".to_string(), "Built-in Output".to_string()];
+
+        let (start, names) = replacements(completer.complete_line("playlist load ev", 16));
+        assert_eq!(start, 14);
+        assert_eq!(names, vec!["Evening Jazz"]);
+
+        let (start, names) = replacements(completer.complete_line("device set usb", 14));
+        assert_eq!(start, 11);
+        assert_eq!(names, vec!["USB DAC"]);
+    }
+
+    #[test]
+    fn test_completes_paths_with_spaces() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("Live Albums")).unwrap();
+        std::fs::write(dir.path().join("Live Track.flac"), "").unwrap();
+        std::fs::write(dir.path().join(".hidden.flac"), "").unwrap();
+        let completer = completer(dir.path());
+
+        let line = format!("queue add {}/Live", dir.path().display());
+        let (start, names) = replacements(completer.complete_line(&line, line.len()));
+        assert_eq!(start, 10);
+        assert_eq!(names, vec![
+            format!("{}/Live Albums/", dir.path().display()),
+            format!("{}/Live Track.flac", dir.path().display()),
+        ]);
+
+        let line = format!("play {}/", dir.path().display());
+        let (_, names) = replacements(completer.complete_line(&line, line.len()));
+        assert_eq!(names.len(), 2);
+    }
+}
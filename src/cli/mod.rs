@@ -1,10 +1,22 @@
+use crate::audio::channels::MAX_BALANCE;
+use crate::audio::crossfeed;
+use crate::audio::eq::{EqBand, EqFilter};
 use crate::error::PlayerError;
 use crate::models::PlayerStatus;
-use clap::{Parser, Subcommand};
+use crate::queue::{DirectoryAddOptions, SortKey};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+pub mod batch;
+pub mod console;
+pub mod editor;
+pub mod queue_view;
 pub mod status;
+pub use console::ConsoleOutput;
+pub use editor::{CommandCompleter, LineReader};
 pub use status::StatusDisplay;
 
 /// High-Resolution Audio Player CLI
@@ -12,13 +24,20 @@ pub use status::StatusDisplay;
 #[command(name = "hires-audio-player")]
 #[command(about = "A high-performance CLI audio player for high-resolution audio files")]
 #[command(version = "0.1.0")]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct CliApp {
     #[command(subcommand)]
     pub command: Option<Commands>,
+    /// Run the commands in a file, one per line, before the interactive prompt
+    #[arg(long, value_name = "FILE")]
+    pub batch: Option<PathBuf>,
+    /// Stop the batch at the first command that fails
+    #[arg(long, requires = "batch")]
+    pub batch_abort_on_error: bool,
 }
 
 /// Available CLI commands
-#[derive(Debug, Subcommand)]
+#[derive(Debug, Clone, Subcommand, Serialize, Deserialize)]
 pub enum Commands {
     /// Start playback of current file or queue
     Play {
@@ -29,8 +48,10 @@ pub enum Commands {
     Pause,
     /// Resume playback from paused position
     Resume,
-    /// Stop playback and reset position
+    /// Stop playback and rewind; the track stays loaded for the next play
     Stop,
+    /// Stop playback and unload the current track
+    Unload,
     /// Advance to next track in queue
     Next,
     /// Go back to previous track in queue
@@ -38,13 +59,65 @@ pub enum Commands {
     Prev,
     /// Seek to specific time position
     Seek {
-        /// Time offset (e.g., "1:30", "90", "90s")
+        /// Time offset (e.g., "1:30", "90", "90s"), or "end-30" for 30 seconds before the end
         position: String,
     },
+    /// A-B loop: 'a' marks the start, 'b' marks the end and starts looping, 'off' clears it
+    Loop {
+        /// "a", "b", "off", or the loop start time (e.g., "1:00")
+        point: String,
+        /// Loop end time when a start time is given (e.g., "1:30")
+        end: Option<String>,
+    },
     /// Display current player status and track information
-    Status,
+    Status {
+        /// Print status as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show tags and technical details for the current track, a queued track or a file
+    Info {
+        /// Queue position (1-based) or audio file to inspect (defaults to the current track)
+        path: Option<PathBuf>,
+        /// Print track information as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the lyrics of the current track, from its tags or a .lrc/.txt file next to it
+    Lyrics,
+    /// Save the current track's cover art to a file and print its path, or open it in `art_viewer`
+    Art,
+    /// Decode files without playing them and report corrupt, truncated or mismatched ones
+    Verify {
+        /// File or directory, "queue", a saved playlist name, or "cancel" to stop a running check
+        target: String,
+    },
+    /// Measure EBU R128 loudness and true peak so files without ReplayGain tags have a gain too
+    ScanLoudness {
+        /// File or directory, "queue", a saved playlist name, or "cancel" to stop a running scan
+        target: String,
+        /// Also write REPLAYGAIN_TRACK_GAIN and REPLAYGAIN_TRACK_PEAK to FLAC and Ogg Vorbis files
+        #[arg(long)]
+        write_tags: bool,
+    },
+    /// Show or edit the tags of FLAC and Ogg Vorbis files
+    Tag {
+        #[command(subcommand)]
+        action: TagAction,
+    },
+    /// Queue audio files as they appear in watched directories
+    WatchDir {
+        #[command(subcommand)]
+        action: WatchDirAction,
+    },
     /// Continuously update status every 100ms (live view)
-    Watch,
+    Watch {
+        /// Show a real-time spectrum analyzer under the track info
+        #[arg(long)]
+        spectrum: bool,
+    },
+    /// Print a one-shot frequency spectrum of the most recently decoded audio
+    Spectrum,
     /// Set playback volume (0-100)
     Volume {
         /// Volume level (0-100)
@@ -65,31 +138,276 @@ pub enum Commands {
         #[command(subcommand)]
         action: DeviceAction,
     },
+    /// Enable or disable the output soft limiter
+    Limiter {
+        /// Limiter state (on/off)
+        state: Toggle,
+    },
+    /// Skip silence at the start and end of tracks
+    Silence {
+        /// Silence skipping state (on/off)
+        state: Toggle,
+    },
+    /// Headphone crossfeed for stereo output (shows the current setting without arguments)
+    Crossfeed {
+        /// Crossfeed state (on/off)
+        state: Option<Toggle>,
+        /// Level of the crossfed signal in dB below the direct one (3-15)
+        level: Option<f32>,
+    },
+    /// Output channel processing: mono, left/right swap and balance (shows the current settings without a subcommand)
+    Output {
+        #[command(subcommand)]
+        action: Option<OutputAction>,
+    },
+    /// Parametric equalizer bands and presets (shows the current bands without a subcommand)
+    Eq {
+        #[command(subcommand)]
+        action: Option<EqAction>,
+    },
+    /// Set the output buffer size (e.g. '2048' frames or '50ms'; 0 = device default)
+    Buffer {
+        /// Buffer size in frames, or milliseconds with an 'ms' suffix
+        size: BufferSize,
+    },
+    /// Show decode times, CPU and memory usage, underruns and a health verdict
+    Perf {
+        #[command(subcommand)]
+        action: Option<PerfAction>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+        /// Write the report to a .json or .csv file
+        #[arg(long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Persistent playback event log
+    Log {
+        #[command(subcommand)]
+        action: LogAction,
+    },
+    /// Automatic error recovery
+    Recovery {
+        #[command(subcommand)]
+        action: RecoveryAction,
+    },
+    /// Show the tracks played this session, most recent first
+    History {
+        #[command(subcommand)]
+        action: Option<HistoryAction>,
+        /// Number of tracks to show (default 20)
+        limit: Option<usize>,
+        /// Show the persistent history of earlier sessions instead
+        #[arg(long)]
+        all: bool,
+    },
+    /// Show or change configuration settings (`config <key> <value>` is short for `config set`)
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Rate the current track (1-5 stars, 0 clears the rating)
+    Rate {
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=5))]
+        stars: u8,
+    },
+    /// Mark the current track as a favorite (toggles without an argument)
+    Fav {
+        #[arg(value_enum)]
+        state: Option<Toggle>,
+    },
+    /// Export the queue's track metadata for other tools
+    Export {
+        /// Output format
+        #[arg(value_parser = ["json", "csv"])]
+        format: String,
+        /// File to write
+        output: PathBuf,
+    },
+    /// Render the current track or the queue through the output chain to a WAV or FLAC file
+    #[command(name = "export-audio")]
+    ExportAudio {
+        /// File to write (.wav or .flac), or "cancel" to stop a running export
+        output: PathBuf,
+        /// The current track or the whole queue
+        #[arg(value_parser = ["track", "queue"], default_value = "track")]
+        target: String,
+        /// Sample rate in Hz (defaults to the first track's)
+        #[arg(long)]
+        rate: Option<u32>,
+        /// Bits per sample: 16, 24 or 32 for WAV, 16 or 24 for FLAC (defaults to the first track's)
+        #[arg(long)]
+        bits: Option<u16>,
+    },
+    /// Start the REST API for remote control (runs without a prompt from the command line)
+    Serve,
+}
+
+/// Output buffer size given in frames or milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BufferSize {
+    Frames(u32),
+    Millis(u32),
+}
+
+impl BufferSize {
+    /// Convert to frames at the given sample rate
+    pub fn to_frames(self, sample_rate: u32) -> u32 {
+        match self {
+            BufferSize::Frames(frames) => frames,
+            BufferSize::Millis(ms) => (sample_rate as u64 * ms as u64 / 1000) as u32,
+        }
+    }
+}
+
+impl std::str::FromStr for BufferSize {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim().to_lowercase();
+        let parsed = match value.strip_suffix("ms") {
+            Some(ms) => ms.trim().parse().map(BufferSize::Millis),
+            None => value.strip_suffix("frames").unwrap_or(&value).trim().parse().map(BufferSize::Frames),
+        };
+        parsed.map_err(|_| format!("invalid buffer size '{}'", value))
+    }
+}
+
+/// On/off switch argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum Toggle {
+    On,
+    Off,
+}
+
+impl Toggle {
+    /// Parse "on"/"off" (case-insensitive)
+    pub fn from_str_arg(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "on" => Some(Toggle::On),
+            "off" => Some(Toggle::Off),
+            _ => None,
+        }
+    }
+
+    /// Check if the switch is on
+    pub fn is_on(self) -> bool {
+        self == Toggle::On
+    }
+
+    /// Display name
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Toggle::On => "on",
+            Toggle::Off => "off",
+        }
+    }
 }
 
 /// Queue management subcommands
-#[derive(Debug, Subcommand)]
+#[derive(Debug, Clone, Subcommand, Serialize, Deserialize)]
 pub enum QueueAction {
     /// Add file or directory to queue
     Add {
         /// Path to file or directory
         path: PathBuf,
+        #[command(flatten)]
+        #[serde(default)]
+        filters: DirectoryFilters,
+    },
+    /// List all tracks in current queue, grouped by album
+    List {
+        /// Show only albums with their track counts
+        #[arg(long, conflicts_with = "flat")]
+        compact: bool,
+        /// One line per track without album headers, for scripts
+        #[arg(long)]
+        flat: bool,
     },
-    /// List all tracks in current queue
-    List,
     /// Clear all tracks from queue
     Clear,
     /// Show current queue position
     Position,
+    /// Add file or directory right after the current track
+    AddNext {
+        /// Path to file or directory
+        path: PathBuf,
+    },
+    /// Insert file or directory at a queue position
+    Insert {
+        /// Position the first inserted track takes (1-based)
+        #[arg(value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        index: usize,
+        /// Path to file or directory
+        path: PathBuf,
+    },
+    /// Remove repeated entries, keeping the first of each
+    #[command(alias = "deduplicate")]
+    Dedupe {
+        /// Also treat tracks with the same artist, title and length as repeats
+        #[arg(long)]
+        metadata: bool,
+    },
+    /// Check that every queued file still exists and can be read
+    Validate {
+        /// Remove the entries that failed the check
+        #[arg(long)]
+        prune: bool,
+    },
+    /// Reorder the queue
+    Sort {
+        /// Sort key (artist, album, title, track, path, duration, random, disc, bpm)
+        key: SortKey,
+    },
+    /// Shuffle the queue; the same seed always gives the same order
+    Shuffle {
+        /// Seed for the shuffle (random when omitted)
+        seed: Option<u64>,
+    },
+}
+
+/// Which files `queue add` takes from a directory, and whether it verifies them
+#[derive(Debug, Clone, Default, PartialEq, Args, Serialize, Deserialize)]
+pub struct DirectoryFilters {
+    /// Only add these extensions (e.g. "flac,wav")
+    #[arg(long = "ext", value_name = "EXTENSIONS", value_delimiter = ',')]
+    pub extensions: Vec<String>,
+    /// Skip tracks shorter than this (e.g. "30s", "1:30")
+    #[arg(long, value_parser = CliApp::parse_time)]
+    pub min_duration: Option<Duration>,
+    /// Only add files directly in the directory
+    #[arg(long, conflicts_with = "max_depth")]
+    pub no_recurse: bool,
+    /// Descend at most this many subdirectory levels
+    #[arg(long)]
+    pub max_depth: Option<usize>,
+    /// Check each file's SHA-256 against a .sha256 sidecar and FLAC audio against its MD5 signature
+    #[arg(long)]
+    #[serde(default)]
+    pub verify: bool,
+}
+
+impl DirectoryFilters {
+    pub fn options(&self) -> DirectoryAddOptions {
+        DirectoryAddOptions {
+            max_depth: if self.no_recurse { Some(0) } else { self.max_depth },
+            extensions: self.extensions.clone(),
+            min_duration: self.min_duration,
+            verify: self.verify,
+        }
+    }
 }
 
 /// Playlist management subcommands
-#[derive(Debug, Subcommand)]
+#[derive(Debug, Clone, Subcommand, Serialize, Deserialize)]
 pub enum PlaylistAction {
     /// Save current queue as playlist
     Save {
         /// Playlist name
         name: String,
+        /// Write paths relative to the playlist file so it can move with the music
+        #[arg(long)]
+        relative: bool,
     },
     /// Load playlist into current queue
     Load {
@@ -103,10 +421,170 @@ pub enum PlaylistAction {
         /// Playlist name
         name: String,
     },
+    /// Rename a playlist
+    Rename {
+        /// Current playlist name
+        old_name: String,
+        /// New playlist name
+        new_name: String,
+    },
+    /// Combine playlists into a new one, each track once
+    Merge {
+        /// Playlists to combine, in order
+        names: Vec<String>,
+        /// Name of the new playlist
+        #[arg(long)]
+        output: String,
+    },
+    /// Save a smart playlist that picks tracks by their tags and format
+    CreateSmart {
+        /// Playlist name
+        name: String,
+        /// Criteria as JSON, e.g. '{"min_sample_rate": 96000, "artist_contains": "Miles Davis"}'
+        criteria_json: String,
+    },
+    /// Fill the queue with the library tracks matching a smart playlist
+    LoadSmart {
+        /// Playlist name
+        name: String,
+        /// Directory to scan (defaults to `library_path` from the config)
+        library_path: Option<PathBuf>,
+    },
+}
+
+/// Performance report subcommands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Subcommand, Serialize, Deserialize)]
+pub enum PerfAction {
+    /// Refresh the report every second
+    Watch,
+}
+
+/// Session history subcommands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Subcommand, Serialize, Deserialize)]
+pub enum HistoryAction {
+    /// Forget the tracks played this session
+    Clear,
+}
+
+/// Where `seek` goes, before the length of the current track is known
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeekOffset {
+    FromStart(Duration),
+    BeforeEnd(Duration),
+}
+
+impl SeekOffset {
+    /// Position within a track lasting `length`; going back past the start gives the start
+    pub fn resolve(self, length: Duration) -> Duration {
+        match self {
+            SeekOffset::FromStart(position) => position,
+            SeekOffset::BeforeEnd(offset) => length.saturating_sub(offset),
+        }
+    }
+}
+
+/// Output channel subcommands
+#[derive(Debug, Clone, PartialEq, Subcommand, Serialize, Deserialize)]
+pub enum OutputAction {
+    /// Play left and right as they are
+    Stereo,
+    /// Mix down to mono and play it on both channels
+    Mono,
+    /// Swap the left and right channels
+    Swap,
+    /// Shift the balance between the speakers
+    Balance {
+        /// -100 (left only) to 100 (right only), 0 = center
+        #[arg(allow_hyphen_values = true)]
+        balance: i8,
+    },
+}
+
+/// Equalizer subcommands
+#[derive(Debug, Clone, PartialEq, Subcommand, Serialize, Deserialize)]
+pub enum EqAction {
+    /// Show the current bands
+    Show,
+    /// Add a band to the current EQ
+    Band {
+        /// Filter shape: peak, low-shelf or high-shelf
+        filter: EqFilter,
+        /// Center (peak) or corner (shelf) frequency in Hz
+        frequency: f32,
+        /// Gain in dB (negative cuts)
+        #[arg(allow_hyphen_values = true)]
+        gain: f32,
+        /// Bandwidth (default 0.707)
+        q: Option<f32>,
+    },
+    /// Remove all bands
+    Clear,
+    /// Save the current bands as a named preset
+    #[command(alias = "save")]
+    SavePreset { name: String },
+    /// Replace the current bands with a preset
+    #[command(alias = "load")]
+    LoadPreset { name: String },
+    /// List built-in and saved presets
+    #[command(alias = "presets")]
+    ListPresets,
+    /// Delete a saved preset (built-in presets cannot be deleted)
+    #[command(alias = "delete")]
+    DeletePreset { name: String },
+}
+
+/// Configuration subcommands
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand, Serialize, Deserialize)]
+pub enum ConfigAction {
+    /// Print every setting with its current value
+    Show,
+    /// Print the settings in effect as TOML, in the form config.toml takes
+    List,
+    /// Print the value of one setting
+    Get {
+        /// Setting name (e.g. volume, output_buffer_frames)
+        key: String,
+    },
+    /// Change a setting; it is saved and applied right away where possible
+    Set {
+        /// Setting name (e.g. volume, output_buffer_frames)
+        key: String,
+        /// New value
+        value: String,
+        /// With playlist_directory: move the saved playlists to the new directory
+        #[arg(long = "move")]
+        move_playlists: bool,
+    },
+    /// Restore the default settings
+    Reset,
+    /// Check config.toml for syntax errors and invalid values
+    Validate,
+}
+
+impl ConfigAction {
+    /// Subcommand names, which cannot be used as a setting in the `config <key> <value>` shorthand
+    const NAMES: [&'static str; 6] = ["show", "list", "get", "set", "reset", "validate"];
+}
+
+/// Event log subcommands
+#[derive(Debug, Clone, Subcommand, Serialize, Deserialize)]
+pub enum LogAction {
+    /// Print the most recent events from the log file
+    Show {
+        /// Number of events to print (default 20)
+        count: Option<usize>,
+    },
+}
+
+/// Error recovery subcommands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Subcommand, Serialize, Deserialize)]
+pub enum RecoveryAction {
+    /// Print recovery attempts and their outcomes since startup
+    Stats,
 }
 
 /// Device management subcommands
-#[derive(Debug, Subcommand)]
+#[derive(Debug, Clone, Subcommand, Serialize, Deserialize)]
 pub enum DeviceAction {
     /// List available audio output devices
     List,
@@ -115,31 +593,169 @@ pub enum DeviceAction {
         /// Device name or ID
         device: String,
     },
+    /// Enable or disable exclusive (bit-perfect) device access
+    #[command(name = "exclusive")]
+    SetExclusive {
+        /// Exclusive mode state (on/off)
+        #[arg(action = clap::ArgAction::Set, value_parser = clap::builder::BoolishValueParser::new())]
+        enable: bool,
+    },
+    /// Forget the preferred device and switch to the system default
+    #[command(name = "default")]
+    SetDefault,
+    /// Restart the audio backend on the current device, or the default one if it is gone
+    Reset,
+    /// Show supported sample rates, bit depths, channels and exclusive mode
+    Capabilities {
+        /// Device name or ID
+        device: String,
+        /// Print capabilities as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Tag editing subcommands
+#[derive(Debug, Clone, Subcommand, Serialize, Deserialize)]
+pub enum TagAction {
+    /// List the tags of a file
+    Show {
+        path: PathBuf,
+    },
+    /// Set tags, replacing any with the same name
+    Set {
+        path: PathBuf,
+        /// FIELD=value pairs, e.g. title="Song" tracknumber=3
+        #[arg(required = true)]
+        fields: Vec<String>,
+    },
+    /// Remove a tag
+    Clear {
+        path: PathBuf,
+        /// Tag name, e.g. comment
+        field: String,
+    },
+}
+
+/// Directory watching subcommands
+#[derive(Debug, Clone, Subcommand, Serialize, Deserialize)]
+pub enum WatchDirAction {
+    /// Start watching a directory and everything below it
+    Add {
+        path: PathBuf,
+    },
+    /// Stop watching a directory
+    Remove {
+        path: PathBuf,
+    },
+    /// List watched directories
+    List,
 }
 
 impl CliApp {
     pub fn new() -> Result<Self, PlayerError> {
-        Ok(Self { command: None })
+        Ok(Self { command: None, batch: None, batch_abort_on_error: false })
     }
 
     /// Parse command line arguments
     pub fn parse() -> Self {
-        <Self as clap::Parser>::parse()
+        <Self as clap::Parser>::parse_from(Self::config_key_as_set(std::env::args_os()))
     }
 
-    /// Expand tilde (~) in path to home directory
+    /// Let `config <key> <value>` read the same as `config set <key> <value>`
+    fn config_key_as_set(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+        let mut args: Vec<OsString> = args.into_iter().collect();
+        let command = args.iter().skip(1).position(|arg| !arg.to_string_lossy().starts_with('-')).map(|index| index + 1);
+        if let Some(index) = command {
+            let is_setting = args.get(index + 1).is_some_and(|arg| {
+                let arg = arg.to_string_lossy();
+                !arg.starts_with('-') && !ConfigAction::NAMES.contains(&arg.as_ref()) && arg != "help"
+            });
+            if args[index] == "config" && is_setting {
+                args.insert(index + 1, OsString::from("set"));
+            }
+        }
+        args
+    }
+
+    /// Expand a leading `~` or `~user` and `$VAR` / `${VAR}` references; unknown names are left as typed
+    ///
+    /// HTTP(S) URLs are returned as typed. On Windows `~\Music` works like `~/Music`, a drive-relative path such as `D:Music` is resolved
+    /// against that drive's current directory, and UNC paths (`\\server\share`) pass through unchanged.
     pub fn expand_path(path: &str) -> PathBuf {
-        if path.starts_with("~/") {
-            if let Some(home_dir) = dirs::home_dir() {
-                home_dir.join(&path[2..])
-            } else {
-                PathBuf::from(path)
-            }
-        } else if path == "~" {
-            dirs::home_dir().unwrap_or_else(|| PathBuf::from(path))
-        } else {
-            PathBuf::from(path)
+        if crate::audio::source::stream_url(Path::new(path)).is_some() {
+            return PathBuf::from(path);
+        }
+        let mut expanded = OsString::new();
+        let mut rest = path;
+        if let Some(after_tilde) = path.strip_prefix('~') {
+            let (user, remainder) = after_tilde.split_at(after_tilde.find(std::path::is_separator).unwrap_or(after_tilde.len()));
+            let home = if user.is_empty() { dirs::home_dir() } else { Self::user_home_dir(user) };
+            if let Some(home) = home {
+                expanded.push(home);
+                rest = remainder;
+            }
+        }
+        expanded.push(Self::expand_env_vars(rest));
+        Self::resolve_drive_relative(PathBuf::from(expanded))
+    }
+
+    /// `D:Music` means `Music` in drive D's current directory; make it absolute so it stays put
+    #[cfg(windows)]
+    fn resolve_drive_relative(path: PathBuf) -> PathBuf {
+        match path.components().next() {
+            Some(std::path::Component::Prefix(_)) if !path.has_root() => std::path::absolute(&path).unwrap_or(path),
+            _ => path,
+        }
+    }
+
+    #[cfg(not(windows))]
+    fn resolve_drive_relative(path: PathBuf) -> PathBuf {
+        path
+    }
+
+    /// Replace `$NAME` and `${NAME}` with the variable's value; unset variables are left as typed
+    fn expand_env_vars(text: &str) -> OsString {
+        let mut expanded = OsString::new();
+        let mut rest = text;
+        while let Some(dollar) = rest.find('$') {
+            expanded.push(&rest[..dollar]);
+            let after = &rest[dollar + 1..];
+            let (name, consumed) = match after.strip_prefix('{') {
+                Some(braced) => match braced.find('}') {
+                    Some(end) => (&braced[..end], end + 2),
+                    None => ("", 0),
+                },
+                None => {
+                    let len = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+                    (&after[..len], len)
+                }
+            };
+            match std::env::var_os(name).filter(|_| !name.is_empty()) {
+                Some(value) => expanded.push(value),
+                None => expanded.push(&rest[dollar..=dollar + consumed]),
+            }
+            rest = &after[consumed..];
+        }
+        expanded.push(rest);
+        expanded
+    }
+
+    /// Home directory of another user, for `~user/`
+    fn user_home_dir(user: &str) -> Option<PathBuf> {
+        #[cfg(unix)]
+        if let Ok(passwd) = std::fs::read_to_string("/etc/passwd") {
+            let home = passwd.lines().find_map(|line| {
+                let fields: Vec<&str> = line.split(':').collect();
+                (fields.len() >= 6 && fields[0] == user).then(|| PathBuf::from(fields[5]))
+            });
+            if home.is_some() {
+                return home;
+            }
         }
+        // Users missing from the passwd file (e.g. macOS directory services) usually live next to us
+        let sibling = dirs::home_dir()?.parent()?.join(user);
+        sibling.is_dir().then_some(sibling)
     }
 
     /// Parse command from string (for interactive mode)
@@ -162,6 +778,7 @@ impl CliApp {
             "pause" => Ok(Commands::Pause),
             "resume" => Ok(Commands::Resume),
             "stop" => Ok(Commands::Stop),
+            "unload" => Ok(Commands::Unload),
             "next" => Ok(Commands::Next),
             "prev" | "previous" => Ok(Commands::Prev),
             "seek" => {
@@ -176,8 +793,171 @@ impl CliApp {
                     })
                 }
             }
-            "status" => Ok(Commands::Status),
-            "watch" => Ok(Commands::Watch),
+            "loop" => {
+                let point = args.get(1).ok_or_else(|| ParseError::MissingArgument {
+                    command: "loop".to_string(),
+                    argument: "a|b|off|start".to_string(),
+                })?;
+                let end = args.get(2).map(|end| end.to_string());
+                let is_marker = matches!(point.to_lowercase().as_str(), "a" | "b" | "off");
+                if !is_marker && end.is_none() {
+                    return Err(ParseError::MissingArgument {
+                        command: "loop".to_string(),
+                        argument: "end".to_string(),
+                    });
+                }
+                Ok(Commands::Loop {
+                    point: point.to_string(),
+                    end,
+                })
+            }
+            "status" => match args.get(1) {
+                None => Ok(Commands::Status { json: false }),
+                Some(&"--json") => Ok(Commands::Status { json: true }),
+                Some(other) => Err(ParseError::InvalidArgument {
+                    argument: "status option".to_string(),
+                    value: other.to_string(),
+                    expected: "--json".to_string(),
+                }),
+            },
+            "info" => {
+                let json = args[1..].contains(&"--json");
+                let path: Vec<&str> = args[1..].iter()
+                    .copied()
+                    .filter(|arg| *arg != "--json")
+                    .collect();
+                let path = if path.is_empty() {
+                    None
+                } else {
+                    Some(Self::expand_path(&path.join(" ")))
+                };
+                Ok(Commands::Info { path, json })
+            }
+            "lyrics" => match args.get(1) {
+                None => Ok(Commands::Lyrics),
+                Some(other) => Err(ParseError::InvalidArgument {
+                    argument: "lyrics option".to_string(),
+                    value: other.to_string(),
+                    expected: "no arguments".to_string(),
+                }),
+            },
+            "art" => match args.get(1) {
+                None => Ok(Commands::Art),
+                Some(other) => Err(ParseError::InvalidArgument {
+                    argument: "art option".to_string(),
+                    value: other.to_string(),
+                    expected: "no arguments".to_string(),
+                }),
+            },
+            "verify" => {
+                if args.len() < 2 {
+                    return Err(ParseError::MissingArgument {
+                        command: "verify".to_string(),
+                        argument: "path|queue|playlist".to_string(),
+                    });
+                }
+                Ok(Commands::Verify { target: args[1..].join(" ") })
+            }
+            "tag" => {
+                let missing = |argument: &str| ParseError::MissingArgument {
+                    command: format!("tag {}", args.get(1).copied().unwrap_or_default()).trim_end().to_string(),
+                    argument: argument.to_string(),
+                };
+                let rest = args.get(2..).unwrap_or_default();
+                match args.get(1).copied() {
+                    None => Err(missing("show|set|clear")),
+                    Some("show") if rest.is_empty() => Err(missing("path")),
+                    Some("show") => Ok(Commands::Tag {
+                        action: TagAction::Show { path: Self::expand_path(&rest.join(" ")) },
+                    }),
+                    Some("set") => {
+                        // The path runs up to the first FIELD=value; a value runs up to the next one
+                        let is_field = |word: &str| word.split_once('=').is_some_and(|(key, _)| {
+                            key.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+                                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                        });
+                        let split = rest.iter().position(|word| is_field(word)).unwrap_or(rest.len());
+                        if split == 0 {
+                            return Err(missing("path"));
+                        }
+                        let mut fields: Vec<String> = Vec::new();
+                        for word in &rest[split..] {
+                            match fields.last_mut() {
+                                Some(field) if !is_field(word) => {
+                                    field.push(' ');
+                                    field.push_str(word);
+                                }
+                                _ => fields.push(word.to_string()),
+                            }
+                        }
+                        if fields.is_empty() {
+                            return Err(missing("field=value"));
+                        }
+                        let fields = fields.into_iter()
+                            .map(|field| {
+                                let (key, value) = field.split_once('=').unwrap_or_default();
+                                let value = value.strip_prefix('"').and_then(|value| value.strip_suffix('"')).unwrap_or(value);
+                                format!("{}={}", key, value)
+                            })
+                            .collect();
+                        Ok(Commands::Tag {
+                            action: TagAction::Set { path: Self::expand_path(&rest[..split].join(" ")), fields },
+                        })
+                    }
+                    Some("clear") if rest.len() < 2 => Err(missing("path and field")),
+                    Some("clear") => Ok(Commands::Tag {
+                        action: TagAction::Clear {
+                            path: Self::expand_path(&rest[..rest.len() - 1].join(" ")),
+                            field: rest[rest.len() - 1].to_string(),
+                        },
+                    }),
+                    Some(other) => Err(ParseError::InvalidArgument {
+                        argument: "tag action".to_string(),
+                        value: other.to_string(),
+                        expected: "show, set or clear".to_string(),
+                    }),
+                }
+            }
+            "watch-dir" => {
+                let path = || match args.get(2..) {
+                    Some(words) if !words.is_empty() => Ok(Self::expand_path(&words.join(" "))),
+                    _ => Err(ParseError::MissingArgument {
+                        command: format!("watch-dir {}", args[1]),
+                        argument: "path".to_string(),
+                    }),
+                };
+                match args.get(1).copied() {
+                    None | Some("list") => Ok(Commands::WatchDir { action: WatchDirAction::List }),
+                    Some("add") => Ok(Commands::WatchDir { action: WatchDirAction::Add { path: path()? } }),
+                    Some("remove") => Ok(Commands::WatchDir { action: WatchDirAction::Remove { path: path()? } }),
+                    Some(other) => Err(ParseError::InvalidArgument {
+                        argument: "watch-dir action".to_string(),
+                        value: other.to_string(),
+                        expected: "add, remove or list".to_string(),
+                    }),
+                }
+            }
+            "scan-loudness" => {
+                let write_tags = args[1..].contains(&"--write-tags");
+                let target: Vec<&str> = args[1..].iter().copied().filter(|&arg| arg != "--write-tags").collect();
+                if target.is_empty() {
+                    return Err(ParseError::MissingArgument {
+                        command: "scan-loudness".to_string(),
+                        argument: "path|queue|playlist".to_string(),
+                    });
+                }
+                Ok(Commands::ScanLoudness { target: target.join(" "), write_tags })
+            }
+            "watch" => match args.get(1) {
+                None => Ok(Commands::Watch { spectrum: false }),
+                Some(&"--spectrum") => Ok(Commands::Watch { spectrum: true }),
+                Some(other) => Err(ParseError::InvalidArgument {
+                    argument: "watch option".to_string(),
+                    value: other.to_string(),
+                    expected: "--spectrum".to_string(),
+                }),
+            },
+            "spectrum" => Ok(Commands::Spectrum),
             "volume" => {
                 if args.len() > 1 {
                     match args[1].parse::<u8>() {
@@ -209,11 +989,11 @@ impl CliApp {
                 }
                 match args[1] {
                     "add" => {
-                        if args.len() > 2 {
-                            let path_str = args[2..].join(" ");
-                            let path = Self::expand_path(&path_str);
+                        let (words, filters) = Self::parse_directory_filters(&args[2..])?;
+                        if !words.is_empty() {
+                            let path = Self::expand_path(&words.join(" "));
                             Ok(Commands::Queue {
-                                action: QueueAction::Add { path },
+                                action: QueueAction::Add { path, filters },
                             })
                         } else {
                             Err(ParseError::MissingArgument {
@@ -222,15 +1002,118 @@ impl CliApp {
                             })
                         }
                     }
-                    "list" => Ok(Commands::Queue {
-                        action: QueueAction::List,
-                    }),
+                    "list" => {
+                        let (compact, flat) = match args.get(2) {
+                            None => (false, false),
+                            Some(&"--compact") => (true, false),
+                            Some(&"--flat") => (false, true),
+                            Some(other) => {
+                                return Err(ParseError::InvalidArgument {
+                                    argument: "queue list option".to_string(),
+                                    value: other.to_string(),
+                                    expected: "--compact or --flat".to_string(),
+                                })
+                            }
+                        };
+                        Ok(Commands::Queue {
+                            action: QueueAction::List { compact, flat },
+                        })
+                    }
                     "clear" => Ok(Commands::Queue {
                         action: QueueAction::Clear,
                     }),
                     "position" => Ok(Commands::Queue {
                         action: QueueAction::Position,
                     }),
+                    "dedupe" | "deduplicate" => match args.get(2) {
+                        None => Ok(Commands::Queue {
+                            action: QueueAction::Dedupe { metadata: false },
+                        }),
+                        Some(&"--metadata") => Ok(Commands::Queue {
+                            action: QueueAction::Dedupe { metadata: true },
+                        }),
+                        Some(other) => Err(ParseError::InvalidArgument {
+                            argument: "queue dedupe option".to_string(),
+                            value: other.to_string(),
+                            expected: "--metadata".to_string(),
+                        }),
+                    },
+                    "validate" => match args.get(2) {
+                        None => Ok(Commands::Queue {
+                            action: QueueAction::Validate { prune: false },
+                        }),
+                        Some(&"--prune") => Ok(Commands::Queue {
+                            action: QueueAction::Validate { prune: true },
+                        }),
+                        Some(other) => Err(ParseError::InvalidArgument {
+                            argument: "queue validate option".to_string(),
+                            value: other.to_string(),
+                            expected: "--prune".to_string(),
+                        }),
+                    },
+                    "add-next" => {
+                        if args.len() > 2 {
+                            let path = Self::expand_path(&args[2..].join(" "));
+                            Ok(Commands::Queue {
+                                action: QueueAction::AddNext { path },
+                            })
+                        } else {
+                            Err(ParseError::MissingArgument {
+                                command: "queue add-next".to_string(),
+                                argument: "path".to_string(),
+                            })
+                        }
+                    }
+                    "insert" => {
+                        let value = args.get(2).ok_or_else(|| ParseError::MissingArgument {
+                            command: "queue insert".to_string(),
+                            argument: "index".to_string(),
+                        })?;
+                        let index = match value.parse::<usize>() {
+                            Ok(index) if index >= 1 => index,
+                            _ => {
+                                return Err(ParseError::InvalidArgument {
+                                    argument: "index".to_string(),
+                                    value: value.to_string(),
+                                    expected: "a queue position starting at 1".to_string(),
+                                })
+                            }
+                        };
+                        if args.len() < 4 {
+                            return Err(ParseError::MissingArgument {
+                                command: "queue insert".to_string(),
+                                argument: "path".to_string(),
+                            });
+                        }
+                        let path = Self::expand_path(&args[3..].join(" "));
+                        Ok(Commands::Queue {
+                            action: QueueAction::Insert { index, path },
+                        })
+                    }
+                    "sort" => {
+                        let value = args.get(2).ok_or_else(|| ParseError::MissingArgument {
+                            command: "queue sort".to_string(),
+                            argument: "key".to_string(),
+                        })?;
+                        let key = value.parse().map_err(|_| ParseError::InvalidArgument {
+                            argument: "key".to_string(),
+                            value: value.to_string(),
+                            expected: "artist, album, title, track, path, duration, random, disc or bpm".to_string(),
+                        })?;
+                        Ok(Commands::Queue {
+                            action: QueueAction::Sort { key },
+                        })
+                    }
+                    "shuffle" => {
+                        let seed = args.get(2).map(|value| value.parse().map_err(|_| ParseError::InvalidArgument {
+                            argument: "seed".to_string(),
+                            value: value.to_string(),
+                            expected: "a whole number".to_string(),
+                        })).transpose()?;
+                        Ok(Commands::Queue {
+                            action: QueueAction::Shuffle { seed },
+                        })
+                    }
                     _ => Err(ParseError::UnknownCommand {
                         command: format!("queue {}", args[1]),
                     }),
@@ -245,10 +1128,16 @@ impl CliApp {
                 }
                 match args[1] {
                     "save" => {
-                        if args.len() > 2 {
+                        let relative = args[2..].contains(&"--relative");
+                        let name: Vec<&str> = args[2..].iter()
+                            .copied()
+                            .filter(|arg| *arg != "--relative")
+                            .collect();
+                        if !name.is_empty() {
                             Ok(Commands::Playlist {
                                 action: PlaylistAction::Save {
-                                    name: args[2..].join(" "),
+                                    name: name.join(" "),
+                                    relative,
                                 },
                             })
                         } else {
@@ -289,6 +1178,82 @@ impl CliApp {
                             })
                         }
                     }
+                    "rename" => {
+                        let old_name = args.get(2).ok_or_else(|| ParseError::MissingArgument {
+                            command: "playlist rename".to_string(),
+                            argument: "old name".to_string(),
+                        })?;
+                        if args.len() < 4 {
+                            return Err(ParseError::MissingArgument {
+                                command: "playlist rename".to_string(),
+                                argument: "new name".to_string(),
+                            });
+                        }
+                        Ok(Commands::Playlist {
+                            action: PlaylistAction::Rename {
+                                old_name: old_name.to_string(),
+                                new_name: args[3..].join(" "),
+                            },
+                        })
+                    }
+                    "merge" => {
+                        let Some(flag) = args.iter().position(|arg| *arg == "--output") else {
+                            return Err(ParseError::MissingArgument {
+                                command: "playlist merge".to_string(),
+                                argument: "--output <name>".to_string(),
+                            });
+                        };
+                        let names: Vec<String> = args[2..flag].iter().map(|name| name.to_string()).collect();
+                        if names.is_empty() {
+                            return Err(ParseError::MissingArgument {
+                                command: "playlist merge".to_string(),
+                                argument: "playlist names".to_string(),
+                            });
+                        }
+                        if flag + 1 >= args.len() {
+                            return Err(ParseError::MissingArgument {
+                                command: "playlist merge".to_string(),
+                                argument: "--output <name>".to_string(),
+                            });
+                        }
+                        Ok(Commands::Playlist {
+                            action: PlaylistAction::Merge {
+                                names,
+                                output: args[flag + 1..].join(" "),
+                            },
+                        })
+                    }
+                    "create-smart" => {
+                        let name = args.get(2).ok_or_else(|| ParseError::MissingArgument {
+                            command: "playlist create-smart".to_string(),
+                            argument: "name".to_string(),
+                        })?;
+                        if args.len() < 4 {
+                            return Err(ParseError::MissingArgument {
+                                command: "playlist create-smart".to_string(),
+                                argument: "criteria".to_string(),
+                            });
+                        }
+                        Ok(Commands::Playlist {
+                            action: PlaylistAction::CreateSmart {
+                                name: name.to_string(),
+                                criteria_json: args[3..].join(" "),
+                            },
+                        })
+                    }
+                    "load-smart" => {
+                        let name = args.get(2).ok_or_else(|| ParseError::MissingArgument {
+                            command: "playlist load-smart".to_string(),
+                            argument: "name".to_string(),
+                        })?;
+                        let library_path = (args.len() > 3).then(|| Self::expand_path(&args[3..].join(" ")));
+                        Ok(Commands::Playlist {
+                            action: PlaylistAction::LoadSmart {
+                                name: name.to_string(),
+                                library_path,
+                            },
+                        })
+                    }
                     _ => Err(ParseError::UnknownCommand {
                         command: format!("playlist {}", args[1]),
                     }),
@@ -305,6 +1270,12 @@ impl CliApp {
                     "list" => Ok(Commands::Device {
                         action: DeviceAction::List,
                     }),
+                    "default" => Ok(Commands::Device {
+                        action: DeviceAction::SetDefault,
+                    }),
+                    "reset" => Ok(Commands::Device {
+                        action: DeviceAction::Reset,
+                    }),
                     "set" => {
                         if args.len() > 2 {
                             Ok(Commands::Device {
@@ -319,11 +1290,356 @@ impl CliApp {
                             })
                         }
                     }
+                    "exclusive" => {
+                        let state = args.get(2).ok_or_else(|| ParseError::MissingArgument {
+                            command: "device exclusive".to_string(),
+                            argument: "state".to_string(),
+                        })?;
+                        Toggle::from_str_arg(state)
+                            .map(|toggle| Commands::Device {
+                                action: DeviceAction::SetExclusive { enable: toggle.is_on() },
+                            })
+                            .ok_or_else(|| ParseError::InvalidArgument {
+                                argument: "exclusive mode".to_string(),
+                                value: state.to_string(),
+                                expected: "on or off".to_string(),
+                            })
+                    }
+                    "capabilities" | "caps" => {
+                        let json = args[2..].contains(&"--json");
+                        let device: Vec<&str> = args[2..].iter()
+                            .copied()
+                            .filter(|arg| *arg != "--json")
+                            .collect();
+                        if device.is_empty() {
+                            return Err(ParseError::MissingArgument {
+                                command: "device capabilities".to_string(),
+                                argument: "device".to_string(),
+                            });
+                        }
+                        Ok(Commands::Device {
+                            action: DeviceAction::Capabilities {
+                                device: device.join(" "),
+                                json,
+                            },
+                        })
+                    }
                     _ => Err(ParseError::UnknownCommand {
                         command: format!("device {}", args[1]),
                     }),
                 }
             }
+            "eq" => {
+                // Preset names may contain spaces
+                let name = |subcommand: &str| {
+                    if args.len() < 3 {
+                        return Err(ParseError::MissingArgument {
+                            command: format!("eq {}", subcommand),
+                            argument: "name".to_string(),
+                        });
+                    }
+                    Ok(args[2..].join(" "))
+                };
+                let action = match args.get(1).copied() {
+                    None | Some("show") => EqAction::Show,
+                    Some("clear") => EqAction::Clear,
+                    Some("band") => Self::parse_eq_band(&args[2..])?,
+                    Some(subcommand @ ("save-preset" | "save")) => EqAction::SavePreset { name: name(subcommand)? },
+                    Some(subcommand @ ("load-preset" | "load")) => EqAction::LoadPreset { name: name(subcommand)? },
+                    Some(subcommand @ ("delete-preset" | "delete")) => EqAction::DeletePreset { name: name(subcommand)? },
+                    Some("list-presets" | "presets") => EqAction::ListPresets,
+                    Some(other) => {
+                        return Err(ParseError::UnknownCommand {
+                            command: format!("eq {}", other),
+                        });
+                    }
+                };
+                Ok(Commands::Eq { action: Some(action) })
+            }
+            "limiter" => {
+                if args.len() < 2 {
+                    return Err(ParseError::MissingArgument {
+                        command: "limiter".to_string(),
+                        argument: "state".to_string(),
+                    });
+                }
+                Toggle::from_str_arg(args[1])
+                    .map(|state| Commands::Limiter { state })
+                    .ok_or_else(|| ParseError::InvalidArgument {
+                        argument: "limiter state".to_string(),
+                        value: args[1].to_string(),
+                        expected: "on or off".to_string(),
+                    })
+            }
+            "output" => {
+                let action = match args.get(1).map(|subcommand| subcommand.to_lowercase()).as_deref() {
+                    None => return Ok(Commands::Output { action: None }),
+                    Some("stereo" | "normal") => OutputAction::Stereo,
+                    Some("mono") => OutputAction::Mono,
+                    Some("swap") => OutputAction::Swap,
+                    Some("balance") => {
+                        let value = args.get(2).ok_or_else(|| ParseError::MissingArgument {
+                            command: "output balance".to_string(),
+                            argument: "balance".to_string(),
+                        })?;
+                        let balance = value.parse::<i8>().ok()
+                            .filter(|balance| (-MAX_BALANCE..=MAX_BALANCE).contains(balance))
+                            .ok_or_else(|| ParseError::InvalidArgument {
+                                argument: "balance".to_string(),
+                                value: value.to_string(),
+                                expected: "a number from -100 (left) to 100 (right)".to_string(),
+                            })?;
+                        OutputAction::Balance { balance }
+                    }
+                    Some(_) => {
+                        return Err(ParseError::UnknownCommand {
+                            command: format!("output {}", args[1]),
+                        });
+                    }
+                };
+                Ok(Commands::Output { action: Some(action) })
+            }
+            "crossfeed" => {
+                let Some(state) = args.get(1) else {
+                    return Ok(Commands::Crossfeed { state: None, level: None });
+                };
+                let state = Toggle::from_str_arg(state).ok_or_else(|| ParseError::InvalidArgument {
+                    argument: "crossfeed state".to_string(),
+                    value: state.to_string(),
+                    expected: "on or off".to_string(),
+                })?;
+                let level = match args.get(2) {
+                    Some(value) => Some(value.parse::<f32>().ok()
+                        .filter(|level| crossfeed::LEVEL_RANGE.contains(level))
+                        .ok_or_else(|| ParseError::InvalidArgument {
+                            argument: "crossfeed level".to_string(),
+                            value: value.to_string(),
+                            expected: "a level in dB from 3 to 15".to_string(),
+                        })?),
+                    None => None,
+                };
+                Ok(Commands::Crossfeed { state: Some(state), level })
+            }
+            "silence" => {
+                if args.len() < 2 {
+                    return Err(ParseError::MissingArgument {
+                        command: "silence".to_string(),
+                        argument: "state".to_string(),
+                    });
+                }
+                Toggle::from_str_arg(args[1])
+                    .map(|state| Commands::Silence { state })
+                    .ok_or_else(|| ParseError::InvalidArgument {
+                        argument: "silence state".to_string(),
+                        value: args[1].to_string(),
+                        expected: "on or off".to_string(),
+                    })
+            }
+            "buffer" => {
+                if args.len() < 2 {
+                    return Err(ParseError::MissingArgument {
+                        command: "buffer".to_string(),
+                        argument: "size".to_string(),
+                    });
+                }
+                args[1].parse()
+                    .map(|size| Commands::Buffer { size })
+                    .map_err(|_| ParseError::InvalidArgument {
+                        argument: "buffer size".to_string(),
+                        value: args[1].to_string(),
+                        expected: "frames (e.g. 2048) or milliseconds (e.g. 50ms)".to_string(),
+                    })
+            }
+            "perf" => {
+                let json = args[1..].contains(&"--json");
+                let mut rest: Vec<&str> = args[1..].iter()
+                    .copied()
+                    .filter(|arg| *arg != "--json")
+                    .collect();
+                let output = match rest.iter().position(|arg| *arg == "--output") {
+                    Some(index) => {
+                        let file = rest.get(index + 1).ok_or_else(|| ParseError::MissingArgument {
+                            command: "perf".to_string(),
+                            argument: "output file".to_string(),
+                        })?;
+                        let file = Self::expand_path(file);
+                        rest.drain(index..=index + 1);
+                        Some(file)
+                    }
+                    None => None,
+                };
+                match rest.as_slice() {
+                    [] => Ok(Commands::Perf { action: None, json, output }),
+                    ["watch"] => Ok(Commands::Perf { action: Some(PerfAction::Watch), json, output }),
+                    [other, ..] => Err(ParseError::InvalidArgument {
+                        argument: "perf option".to_string(),
+                        value: other.to_string(),
+                        expected: "watch, --json or --output <file>".to_string(),
+                    }),
+                }
+            }
+            "log" => {
+                if args.len() < 2 {
+                    return Err(ParseError::MissingArgument {
+                        command: "log".to_string(),
+                        argument: "action".to_string(),
+                    });
+                }
+                match args[1] {
+                    "show" => {
+                        let count = match args.get(2) {
+                            Some(value) => Some(value.parse().map_err(|_| ParseError::InvalidArgument {
+                                argument: "log count".to_string(),
+                                value: value.to_string(),
+                                expected: "a number of events".to_string(),
+                            })?),
+                            None => None,
+                        };
+                        Ok(Commands::Log {
+                            action: LogAction::Show { count },
+                        })
+                    }
+                    _ => Err(ParseError::UnknownCommand {
+                        command: format!("log {}", args[1]),
+                    }),
+                }
+            }
+            "recovery" => match args.get(1) {
+                Some(&"stats") => Ok(Commands::Recovery { action: RecoveryAction::Stats }),
+                Some(other) => Err(ParseError::UnknownCommand {
+                    command: format!("recovery {}", other),
+                }),
+                None => Err(ParseError::MissingArgument {
+                    command: "recovery".to_string(),
+                    argument: "action".to_string(),
+                }),
+            },
+            "history" => {
+                let all = args[1..].contains(&"--all");
+                let rest: Vec<&str> = args[1..].iter()
+                    .copied()
+                    .filter(|arg| *arg != "--all")
+                    .collect();
+                match rest.as_slice() {
+                    ["clear"] if !all => Ok(Commands::History { action: Some(HistoryAction::Clear), limit: None, all }),
+                    [] => Ok(Commands::History { action: None, limit: None, all }),
+                    [value] => {
+                        let limit = value.parse().map_err(|_| ParseError::InvalidArgument {
+                            argument: "history limit".to_string(),
+                            value: value.to_string(),
+                            expected: "a number of tracks or clear".to_string(),
+                        })?;
+                        Ok(Commands::History { action: None, limit: Some(limit), all })
+                    }
+                    [_, other, ..] => Err(ParseError::InvalidArgument {
+                        argument: "history option".to_string(),
+                        value: other.to_string(),
+                        expected: "[n] [--all] or clear".to_string(),
+                    }),
+                }
+            }
+            "config" => {
+                let action = match args.get(1).copied() {
+                    None => {
+                        return Err(ParseError::MissingArgument {
+                            command: "config".to_string(),
+                            argument: "show, list, get, set, reset, validate or a setting".to_string(),
+                        });
+                    }
+                    Some("show") if args.len() == 2 => ConfigAction::Show,
+                    Some("list") if args.len() == 2 => ConfigAction::List,
+                    Some("reset") if args.len() == 2 => ConfigAction::Reset,
+                    Some("validate") if args.len() == 2 => ConfigAction::Validate,
+                    Some(subcommand @ ("show" | "list" | "reset" | "validate")) => {
+                        return Err(ParseError::InvalidArgument {
+                            argument: format!("config {} option", subcommand),
+                            value: args[2].to_string(),
+                            expected: "no arguments".to_string(),
+                        });
+                    }
+                    Some("get") => match &args[2..] {
+                        [key] => ConfigAction::Get { key: key.to_string() },
+                        [] => {
+                            return Err(ParseError::MissingArgument {
+                                command: "config get".to_string(),
+                                argument: "key".to_string(),
+                            });
+                        }
+                        [_, extra, ..] => {
+                            return Err(ParseError::InvalidArgument {
+                                argument: "config get option".to_string(),
+                                value: extra.to_string(),
+                                expected: "a single setting name".to_string(),
+                            });
+                        }
+                    },
+                    Some(first) => {
+                        let rest = if first == "set" { &args[2..] } else { &args[1..] };
+                        let move_playlists = rest.contains(&"--move");
+                        let rest: Vec<&str> = rest.iter().copied().filter(|arg| *arg != "--move").collect();
+                        if rest.len() < 2 {
+                            return Err(ParseError::MissingArgument {
+                                command: "config".to_string(),
+                                argument: if rest.is_empty() { "key" } else { "value" }.to_string(),
+                            });
+                        }
+                        ConfigAction::Set {
+                            key: rest[0].to_string(),
+                            // Paths may contain spaces
+                            value: rest[1..].join(" "),
+                            move_playlists,
+                        }
+                    }
+                };
+                Ok(Commands::Config { action })
+            }
+            "rate" => {
+                if args.len() < 2 {
+                    return Err(ParseError::MissingArgument {
+                        command: "rate".to_string(),
+                        argument: "stars".to_string(),
+                    });
+                }
+                match args[1].parse::<u8>() {
+                    Ok(stars) if stars <= 5 => Ok(Commands::Rate { stars }),
+                    _ => Err(ParseError::InvalidArgument {
+                        argument: "rating".to_string(),
+                        value: args[1].to_string(),
+                        expected: "1-5 stars, or 0 to clear".to_string(),
+                    }),
+                }
+            }
+            "fav" | "favorite" => {
+                let state = match args.get(1) {
+                    Some(value) => Some(Toggle::from_str_arg(value).ok_or_else(|| ParseError::InvalidArgument {
+                        argument: "favorite state".to_string(),
+                        value: value.to_string(),
+                        expected: "on or off".to_string(),
+                    })?),
+                    None => None,
+                };
+                Ok(Commands::Fav { state })
+            }
+            "export" => {
+                let Some(format) = args.get(1).map(|format| format.to_lowercase()) else {
+                    return Err(ParseError::MissingArgument {
+                        command: "export".to_string(),
+                        argument: "format or audio file".to_string(),
+                    });
+                };
+                if format.parse::<crate::queue::export::ExportFormat>().is_err() {
+                    return Self::parse_export_audio(&args[1..]);
+                }
+                if args.len() < 3 {
+                    return Err(ParseError::MissingArgument {
+                        command: "export".to_string(),
+                        argument: "output file".to_string(),
+                    });
+                }
+                Ok(Commands::Export { format, output: Self::expand_path(&args[2..].join(" ")) })
+            }
+            "export-audio" => Self::parse_export_audio(&args[1..]),
+            "serve" => Ok(Commands::Serve),
             "help" => Err(ParseError::HelpRequested),
             _ => Err(ParseError::UnknownCommand {
                 command: args[0].to_string(),
@@ -346,37 +1662,101 @@ impl CliApp {
         println!("High-Resolution Audio Player - Available Commands:");
         println!();
         println!("Playback Control:");
-        println!("  play [path]     - Start playback (optionally specify file/directory)");
+        println!("  play [path]     - Start playback (optionally specify file/directory, http(s) URL, 'favorites' or 'last')");
         println!("  pause           - Pause playback");
         println!("  resume          - Resume playback");
-        println!("  stop            - Stop playback and reset position");
+        println!("  stop            - Stop playback and rewind to the start of the track");
+        println!("  unload          - Stop playback and unload the current track");
         println!("  next            - Next track");
         println!("  prev            - Previous track");
-        println!("  seek <time>     - Seek to position (e.g., '1:30', '90s')");
+        println!("  seek <time>     - Seek to position (e.g., '1:30', '1:02:03', '90s', 'end-30')");
+        println!("  loop <a|b|off>  - Mark loop start/end at the current position, or clear the loop");
+        println!("  loop <start> <end> - Loop between two times (e.g., 'loop 1:00 1:30')");
         println!();
         println!("Information:");
-        println!("  status          - Show current player status");
+        println!("  status [--json] - Show current player status");
+        println!("  info [--json] [index|path] - Show tags and technical details for a track or file");
+        println!("  lyrics          - Show the current track's lyrics (tag, or a .lrc/.txt file next to it)");
+        println!("  art             - Show the current track's cover (embedded, or cover.jpg/folder.jpg)");
+        println!("  verify <path|queue|playlist> - Decode every file and report corrupt or truncated ones and FLAC MD5 mismatches");
+        println!("  verify cancel   - Stop a running verify");
+        println!("  scan-loudness [--write-tags] <path|queue|playlist> - Measure EBU R128 loudness and true peak");
+        println!("  scan-loudness cancel - Stop a running loudness scan");
+        println!("  tag show <path>     - List the tags of a FLAC or Ogg Vorbis file");
+        println!("  tag set <path> field=value ... - Set tags, e.g. title=\"Song\" tracknumber=3");
+        println!("  tag clear <path> <field> - Remove a tag");
+        println!("  watch-dir add <path> - Queue audio files as they appear under a directory");
+        println!("  watch-dir remove <path> - Stop watching a directory");
+        println!("  watch-dir [list]    - List watched directories");
+        println!("  watch [--spectrum] - Live status view (optionally with spectrum analyzer)");
+        println!("  spectrum        - Print a spectrum snapshot of the audio just decoded");
         println!("  volume <0-100>  - Set volume level");
+        println!("  limiter <on|off> - Soft-limit output instead of hard clipping");
+        println!("  silence <on|off> - Cut silent lead-ins and end tracks early on trailing silence");
+        println!("  crossfeed [on|off] [3-15] - Show or set headphone crossfeed and its level in dB");
+        println!("  output [stereo|mono|swap] - Show or change how left and right reach the speakers");
+        println!("  output balance <-100..100> - Shift the balance left (negative) or right");
+        println!("  eq [show|clear] - Show or remove the equalizer bands");
+        println!("  eq band <peak|low-shelf|high-shelf> <Hz> <dB> [Q] - Add an equalizer band");
+        println!("  eq save <name> / eq load <name> / eq delete <name> - Manage EQ presets");
+        println!("  eq presets      - List presets (Flat, Bass-Boost, Treble-Boost, Loudness and saved ones)");
+        println!("  buffer <frames|Nms> - Set output buffer size (0 = device default)");
+        println!("  config show - Print every setting with its current value");
+        println!("  config list - Print the settings in effect as config.toml would hold them");
+        println!("  config get <key> - Print one setting");
+        println!("  config [set] <key> <value> - Change a setting (volume <0.0-1.0|N%>, output_buffer_frames <64-65536|default>, enable_gapless <on|off>, ...)");
+        println!("  config reset - Restore the default settings");
+        println!("  config validate - Check config.toml for syntax errors and out-of-range values");
+        println!("  config set playlist-dir <path> [--move] - Keep playlists in another directory (--move takes the saved ones along)");
+        println!("  perf [watch] [--json] [--output <file.json|file.csv>] - Decode times, CPU/memory usage, underruns and health");
+        println!("  log show [n]    - Show the last n logged playback events (default 20)");
+        println!("  recovery stats  - Recovery attempts by error category, with successes and failures");
+        println!("  history [n] [--all] - Show the last n tracks played this session (default 20; --all: earlier sessions)");
+        println!("  history clear   - Forget the tracks played this session");
+        println!("  rate <1-5>      - Rate the current track (0 clears the rating)");
+        println!("  fav [on|off]    - Toggle or set the current track as a favorite");
         println!();
         println!("Queue Management:");
-        println!("  queue add <path>    - Add file/directory to queue");
-        println!("  queue list          - List queue contents");
+        println!("  queue add <path>    - Add file/directory or http(s) URL to queue ('favorites' adds all favorites)");
+        println!("      [--ext flac,wav] [--min-duration 30s] [--no-recurse | --max-depth n] - Filter directory adds");
+        println!("      [--verify] - Check files against .sha256 sidecars and FLAC MD5 signatures");
+        println!("  queue list [--compact|--flat] - List queue contents grouped by album");
+        println!("  queue add-next <path> - Add file/directory to play right after the current track");
+        println!("  queue insert <n> <path> - Insert file/directory at queue position n");
         println!("  queue clear         - Clear queue");
+        println!("  queue dedupe [--metadata] - Remove repeated entries, keeping the first");
+        println!("  queue validate [--prune] - Report (and remove) entries whose file is missing or unreadable");
         println!("  queue position      - Show current position in queue");
+        println!("  queue sort <key>    - Sort queue by artist, album, title, track, path, duration, random, disc or bpm");
+        println!("  queue shuffle [seed] - Shuffle queue; the same seed repeats the same order");
         println!();
         println!("Playlist Management:");
-        println!("  playlist save <name>    - Save current queue as playlist");
+        println!("  playlist save [--relative] <name> - Save current queue as playlist (--relative: paths relative to the playlist file)");
         println!("  playlist load <name>    - Load playlist");
         println!("  playlist list           - List available playlists");
         println!("  playlist delete <name>  - Delete playlist");
+        println!("  playlist rename <old> <new> - Rename playlist");
+        println!("  playlist merge <name>... --output <new> - Combine playlists into a new one, each track once");
+        println!("  playlist create-smart <name> <json> - Save a smart playlist, e.g. {{\"codec\": \"flac\", \"min_bit_depth\": 24}}");
+        println!("  playlist load-smart <name> [library] - Queue the library tracks matching a smart playlist");
+        println!("  export <json|csv> <file> - Export the queue's track metadata (path, tags, duration, format)");
+        println!("  export <file.wav|file.flac> [track|queue] [--rate <Hz>] [--bits <16|24|32>] - Render through the EQ and effects to a file");
+        println!("  export cancel - Stop a running audio export");
         println!();
         println!("Device Management:");
         println!("  device list         - List available audio devices");
         println!("  device set <name>   - Set audio output device");
+        println!("  device capabilities [--json] <name> - Show supported formats");
+        println!("  device exclusive <on|off> - Exclusive (bit-perfect) device access");
+        println!("  device default      - Forget the preferred device and use the system default");
+        println!("  device reset        - Restart the audio backend (default device if the current one is gone)");
         println!();
         println!("General:");
+        println!("  serve           - Start the REST API for remote control");
         println!("  help            - Show this help message");
         println!("  exit, quit      - Exit the player");
+        println!();
+        println!("Tab completes commands, playlist and device names, and paths; Up/Down and Ctrl-R search previous commands.");
     }
 
     /// Truncate string to fit display width
@@ -388,54 +1768,202 @@ impl CliApp {
         }
     }
 
+    /// Split `queue add` arguments into the path words and the directory filter flags
+    fn parse_directory_filters<'a>(args: &[&'a str]) -> Result<(Vec<&'a str>, DirectoryFilters), ParseError> {
+        let mut words = Vec::new();
+        let mut filters = DirectoryFilters::default();
+        let mut args = args.iter().copied();
+        while let Some(arg) = args.next() {
+            let mut value = |expected: &str| args.next().ok_or_else(|| ParseError::MissingArgument {
+                command: format!("queue add {}", arg),
+                argument: expected.to_string(),
+            });
+            match arg {
+                "--ext" => {
+                    filters.extensions = value("extensions")?
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|ext| !ext.is_empty())
+                        .map(str::to_string)
+                        .collect();
+                }
+                "--min-duration" => filters.min_duration = Some(Self::parse_time(value("duration")?)?),
+                "--max-depth" => {
+                    let depth = value("depth")?;
+                    filters.max_depth = Some(depth.parse().map_err(|_| ParseError::InvalidArgument {
+                        argument: "max depth".to_string(),
+                        value: depth.to_string(),
+                        expected: "a number of directory levels".to_string(),
+                    })?);
+                }
+                "--no-recurse" => filters.no_recurse = true,
+                "--verify" => filters.verify = true,
+                _ => words.push(arg),
+            }
+        }
+        if filters.no_recurse && filters.max_depth.is_some() {
+            return Err(ParseError::InvalidArgument {
+                argument: "queue add option".to_string(),
+                value: "--no-recurse".to_string(),
+                expected: "either --no-recurse or --max-depth, not both".to_string(),
+            });
+        }
+        Ok((words, filters))
+    }
+
+    /// Parse `export <file.wav|file.flac> [track|queue] [--rate <Hz>] [--bits <bits>]` or `export cancel`
+    fn parse_export_audio(args: &[&str]) -> Result<Commands, ParseError> {
+        let mut words = Vec::new();
+        let (mut rate, mut bits) = (None, None);
+        let mut args = args.iter().copied();
+        while let Some(arg) = args.next() {
+            let mut value = |argument: &str| -> Result<u32, ParseError> {
+                let value = args.next().ok_or_else(|| ParseError::MissingArgument {
+                    command: format!("export {}", arg),
+                    argument: argument.to_string(),
+                })?;
+                value.parse().map_err(|_| ParseError::InvalidArgument {
+                    argument: argument.to_string(),
+                    value: value.to_string(),
+                    expected: "a whole number".to_string(),
+                })
+            };
+            match arg {
+                "--rate" => rate = Some(value("sample rate")?),
+                "--bits" => {
+                    let depth = value("bit depth")?;
+                    if ![16, 24, 32].contains(&depth) {
+                        return Err(ParseError::InvalidArgument {
+                            argument: "bit depth".to_string(),
+                            value: depth.to_string(),
+                            expected: "16, 24 or 32".to_string(),
+                        });
+                    }
+                    bits = Some(depth as u16);
+                }
+                _ => words.push(arg),
+            }
+        }
+        let target = match words.last() {
+            Some(word) if word.eq_ignore_ascii_case("track") || word.eq_ignore_ascii_case("queue") => {
+                words.pop().unwrap_or_default().to_lowercase()
+            }
+            _ => "track".to_string(),
+        };
+        if words.is_empty() {
+            return Err(ParseError::MissingArgument { command: "export".to_string(), argument: "output file".to_string() });
+        }
+        let output = Self::expand_path(&words.join(" "));
+        let cancel = words.len() == 1 && words[0].eq_ignore_ascii_case("cancel");
+        if !cancel && crate::audio::encoders::supported_bit_depths(&output).is_empty() {
+            return Err(ParseError::InvalidArgument {
+                argument: "export format".to_string(),
+                value: words.join(" "),
+                expected: "json, csv, or a .wav or .flac file".to_string(),
+            });
+        }
+        Ok(Commands::ExportAudio { output, target, rate, bits })
+    }
+
+    /// Parse `eq band <filter> <Hz> <dB> [Q]`, checking the values are in range
+    fn parse_eq_band(args: &[&str]) -> Result<EqAction, ParseError> {
+        let [filter, frequency, gain, rest @ ..] = args else {
+            return Err(ParseError::MissingArgument {
+                command: "eq band".to_string(),
+                argument: ["filter", "frequency", "gain"][args.len()].to_string(),
+            });
+        };
+        let number = |argument: &str, value: &str, expected: &str| {
+            value.trim_end_matches(|c: char| c.is_ascii_alphabetic()).parse::<f32>().map_err(|_| ParseError::InvalidArgument {
+                argument: argument.to_string(),
+                value: value.to_string(),
+                expected: expected.to_string(),
+            })
+        };
+        let band = EqBand::new(
+            filter.parse().map_err(|_| ParseError::InvalidArgument {
+                argument: "eq filter".to_string(),
+                value: filter.to_string(),
+                expected: "peak, low-shelf or high-shelf".to_string(),
+            })?,
+            number("eq frequency", frequency, "a frequency in Hz")?,
+            number("eq gain", gain, "a gain in dB")?,
+            match rest {
+                [] => EqBand::DEFAULT_Q,
+                [q] => number("eq Q", q, "a bandwidth such as 0.7")?,
+                [_, extra, ..] => {
+                    return Err(ParseError::InvalidArgument {
+                        argument: "eq band option".to_string(),
+                        value: extra.to_string(),
+                        expected: "<filter> <Hz> <dB> [Q]".to_string(),
+                    });
+                }
+            },
+        );
+        band.validate().map_err(|reason| ParseError::InvalidArgument {
+            argument: "eq band".to_string(),
+            value: args.join(" "),
+            expected: reason,
+        })?;
+        Ok(EqAction::Band { filter: band.filter, frequency: band.frequency_hz, gain: band.gain_db, q: Some(band.q) })
+    }
+
+    /// Parse a seek target: a time from the start, or `end-<time>` for a time before the end
+    pub fn parse_seek_offset(time_str: &str) -> Result<SeekOffset, ParseError> {
+        match time_str.trim().strip_prefix("end-") {
+            Some(before_end) => Self::parse_time(before_end).map(SeekOffset::BeforeEnd).map_err(|error| match error {
+                ParseError::InvalidTimeFormat { reason, .. } => ParseError::InvalidTimeFormat { input: time_str.to_string(), reason },
+                error => error,
+            }),
+            None => Self::parse_time(time_str).map(SeekOffset::FromStart),
+        }
+    }
+
     /// Parse time string to Duration with enhanced validation
     pub fn parse_time(time_str: &str) -> Result<Duration, ParseError> {
         let trimmed = time_str.trim();
+        let invalid = |reason: &str| ParseError::InvalidTimeFormat {
+            input: time_str.to_string(),
+            reason: reason.to_string(),
+        };
 
         if trimmed.is_empty() {
-            return Err(ParseError::InvalidTimeFormat {
-                input: time_str.to_string(),
-            });
+            return Err(invalid("expected seconds, MM:SS or HH:MM:SS"));
         }
 
-        // Handle different time formats: "1:30", "90", "90s", "1:30.5"
+        // Handle different time formats: "1:30", "1:02:03", "90", "90s", "1:30.5"
         if trimmed.contains(':') {
-            // MM:SS or MM:SS.ms format
+            // MM:SS, HH:MM:SS, with an optional fraction of a second
             let parts: Vec<&str> = trimmed.split(':').collect();
-            if parts.len() != 2 {
-                return Err(ParseError::InvalidTimeFormat {
-                    input: time_str.to_string(),
-                });
+            if parts.len() > 3 {
+                return Err(invalid("expected MM:SS or HH:MM:SS"));
             }
 
-            let minutes: u64 = parts[0].parse().map_err(|_| ParseError::InvalidTimeFormat {
-                input: time_str.to_string(),
-            })?;
+            let whole = |part: &str| part.parse::<u64>().map_err(|_| invalid("expected MM:SS or HH:MM:SS"));
+            let (hours, minutes) = match parts.len() {
+                3 => (whole(parts[0])?, whole(parts[1])?),
+                _ => (0, whole(parts[0])?),
+            };
+            if parts.len() == 3 && minutes >= 60 {
+                return Err(invalid("minutes out of range"));
+            }
 
             // Handle seconds with optional decimal part
-            let seconds_f64: f64 = parts[1].parse().map_err(|_| ParseError::InvalidTimeFormat {
-                input: time_str.to_string(),
-            })?;
+            let seconds_f64: f64 = parts[parts.len() - 1].parse().map_err(|_| invalid("expected MM:SS or HH:MM:SS"))?;
 
-            if seconds_f64 < 0.0 || seconds_f64 >= 60.0 {
-                return Err(ParseError::InvalidTimeFormat {
-                    input: time_str.to_string(),
-                });
+            if !(0.0..60.0).contains(&seconds_f64) {
+                return Err(invalid("seconds out of range"));
             }
 
-            let total_seconds = minutes as f64 * 60.0 + seconds_f64;
+            let total_seconds = (hours * 3600 + minutes * 60) as f64 + seconds_f64;
             Ok(Duration::from_secs_f64(total_seconds))
         } else {
             // Seconds format (with or without 's' suffix), support decimal
             let seconds_str = trimmed.trim_end_matches('s');
-            let seconds_f64: f64 = seconds_str.parse().map_err(|_| ParseError::InvalidTimeFormat {
-                input: time_str.to_string(),
-            })?;
+            let seconds_f64: f64 = seconds_str.parse().map_err(|_| invalid("expected seconds, MM:SS or HH:MM:SS"))?;
 
             if seconds_f64 < 0.0 {
-                return Err(ParseError::InvalidTimeFormat {
-                    input: time_str.to_string(),
-                });
+                return Err(invalid("seconds out of range"));
             }
 
             Ok(Duration::from_secs_f64(seconds_f64))
@@ -489,8 +2017,8 @@ pub enum ParseError {
         expected: String,
     },
 
-    #[error("Invalid time format: {input}")]
-    InvalidTimeFormat { input: String },
+    #[error("Invalid time format: {input} ({reason})")]
+    InvalidTimeFormat { input: String, reason: String },
 
     #[error("Seek position {position:.2}s exceeds track duration {duration:.2}s")]
     SeekBeyondDuration { position: f64, duration: f64 },
@@ -542,4 +2070,68 @@ mod path_tests {
         // Should remain unchanged
         assert_eq!(expanded.to_string_lossy(), path);
     }
+
+    #[test]
+    fn test_expand_path_other_user() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(CliApp::expand_path("~no-such-user-here/Music"), PathBuf::from("~no-such-user-here/Music"));
+        #[cfg(target_os = "linux")]
+        assert_eq!(CliApp::expand_path("~root/Music"), PathBuf::from("/root/Music"));
+        assert_eq!(CliApp::expand_path("~/Music"), home.join("Music"));
+    }
+
+    #[test]
+    fn test_expand_path_keeps_urls() {
+        std::env::set_var("RMUSIC_TEST_TOKEN", "expanded");
+        let url = "https://example.com/~radio/live.mp3?token=$RMUSIC_TEST_TOKEN";
+        assert_eq!(CliApp::expand_path(url), PathBuf::from(url));
+        assert!(matches!(
+            CliApp::parse_command(&format!("queue add {}", url)),
+            Ok(Commands::Queue { action: QueueAction::Add { path, .. } }) if path == Path::new(url)
+        ));
+    }
+
+    #[test]
+    fn test_expand_path_env_vars() {
+        std::env::set_var("RMUSIC_TEST_MUSIC", "/srv/music");
+        assert_eq!(CliApp::expand_path("${RMUSIC_TEST_MUSIC}/Albums"), PathBuf::from("/srv/music/Albums"));
+        assert_eq!(CliApp::expand_path("$RMUSIC_TEST_MUSIC/Albums"), PathBuf::from("/srv/music/Albums"));
+
+        // Unset or malformed references are left as typed
+        assert_eq!(CliApp::expand_path("/music/$RMUSIC_TEST_UNSET/a$"), PathBuf::from("/music/$RMUSIC_TEST_UNSET/a$"));
+        assert_eq!(CliApp::expand_path("/music/${RMUSIC_TEST_MUSIC"), PathBuf::from("/music/${RMUSIC_TEST_MUSIC"));
+    }
+
+    #[test]
+    fn test_expand_path_unc_unchanged() {
+        // `C$` is an admin share, not a variable
+        let unc = r"\\server\C$\Music\album";
+        assert_eq!(CliApp::expand_path(unc), PathBuf::from(unc));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_expand_path_windows_forms() {
+        use std::path::{Component, Prefix};
+
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(CliApp::expand_path(r"~\Music\album"), home.join("Music").join("album"));
+
+        let drive_relative = CliApp::expand_path("C:Music");
+        assert!(drive_relative.is_absolute());
+        assert!(drive_relative.ends_with("Music"));
+
+        let unc = CliApp::expand_path(r"\\server\share\album");
+        match unc.components().next() {
+            Some(Component::Prefix(prefix)) => assert!(matches!(prefix.kind(), Prefix::UNC(..))),
+            other => panic!("expected a UNC prefix, got {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_expand_path_backslash_is_literal_on_unix() {
+        assert_eq!(CliApp::expand_path(r"~\Music"), PathBuf::from(r"~\Music"));
+        assert_eq!(CliApp::expand_path("C:Music"), PathBuf::from("C:Music"));
+    }
 }
@@ -0,0 +1,231 @@
+use crate::cli::status::StatusDisplay;
+use crate::models::TrackInfo;
+use std::ops::Range;
+use std::time::Duration;
+
+/// Queue listings: flat, grouped by album, or one line per album
+pub struct QueueView;
+
+impl QueueView {
+    /// One line per track, as scripts expect: `> 3: Artist - Title`
+    pub fn flat_lines(tracks: &[TrackInfo], current: usize) -> Vec<String> {
+        tracks
+            .iter()
+            .enumerate()
+            .map(|(i, track)| {
+                format!("{} {}: {} - {}{}", marker(i == current), i + 1, track.artist_name(), track.display_name(), entry_suffix(track))
+            })
+            .collect()
+    }
+
+    /// Tracks under a header for each run of consecutive tracks from the same album
+    pub fn grouped_lines(tracks: &[TrackInfo], current: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        for run in album_runs(tracks) {
+            let album = &tracks[run.clone()];
+            let Some(header) = album_header(album) else {
+                // Untagged tracks stand on their own
+                for (i, track) in run.clone().zip(album) {
+                    lines.push(format!(
+                        "{} {} - {}  {}{}",
+                        marker(i == current),
+                        track.artist_name(),
+                        track.display_name(),
+                        StatusDisplay::format_duration(track.duration),
+                        entry_suffix(track)
+                    ));
+                }
+                continue;
+            };
+
+            lines.push(header);
+            let mixed_artists = common_artist(album).is_none();
+            for (offset, (i, track)) in run.clone().zip(album).enumerate() {
+                let number = track.metadata.track_number.unwrap_or(offset as u32 + 1);
+                let title = if mixed_artists {
+                    format!("{} - {}", track.artist_name(), track.display_name())
+                } else {
+                    track.display_name()
+                };
+                lines.push(format!(
+                    "  {} {:02}. {}  {}{}",
+                    marker(i == current),
+                    number,
+                    title,
+                    StatusDisplay::format_duration(track.duration),
+                    entry_suffix(track)
+                ));
+            }
+        }
+        lines
+    }
+
+    /// One line per album run with its track count and length
+    pub fn compact_lines(tracks: &[TrackInfo], current: usize) -> Vec<String> {
+        album_runs(tracks)
+            .into_iter()
+            .map(|run| {
+                let album = &tracks[run.clone()];
+                let name = album_header(album).unwrap_or_else(|| "(no album tag)".to_string());
+                format!(
+                    "{} {} - {} track{}, {}",
+                    marker(run.contains(&current)),
+                    name,
+                    album.len(),
+                    if album.len() == 1 { "" } else { "s" },
+                    StatusDisplay::format_duration(total_duration(album))
+                )
+            })
+            .collect()
+    }
+
+    /// Closing line with the length of the whole queue
+    pub fn total_line(tracks: &[TrackInfo]) -> String {
+        format!("Total: {} tracks, {}", tracks.len(), StatusDisplay::format_duration(total_duration(tracks)))
+    }
+}
+
+fn marker(current: bool) -> &'static str {
+    if current { ">" } else { " " }
+}
+
+/// Rating and favorite marks, and a flag on entries playback had to skip
+fn entry_suffix(track: &TrackInfo) -> String {
+    let mut suffix = String::new();
+    let rating = track.rating_label();
+    if !rating.is_empty() {
+        suffix.push_str("  ");
+        suffix.push_str(&rating);
+    }
+    if track.failed {
+        suffix.push_str("  ✗ unplayable");
+    }
+    suffix
+}
+
+fn total_duration(tracks: &[TrackInfo]) -> Duration {
+    tracks.iter().map(|track| track.duration).sum()
+}
+
+/// Index ranges of consecutive tracks sharing an album tag (or all lacking one)
+fn album_runs(tracks: &[TrackInfo]) -> Vec<Range<usize>> {
+    let mut runs: Vec<Range<usize>> = Vec::new();
+    for (i, track) in tracks.iter().enumerate() {
+        match runs.last_mut() {
+            Some(run) if tracks[run.start].metadata.album == track.metadata.album => run.end = i + 1,
+            _ => runs.push(i..i + 1),
+        }
+    }
+    runs
+}
+
+/// The artist shared by every track, if there is one
+fn common_artist(album: &[TrackInfo]) -> Option<&str> {
+    let artist = album.first()?.metadata.artist.as_deref()?;
+    album
+        .iter()
+        .all(|track| track.metadata.artist.as_deref() == Some(artist))
+        .then_some(artist)
+}
+
+/// `Artist — Album (year) [24/96 FLAC]`; None for tracks without an album tag
+fn album_header(album: &[TrackInfo]) -> Option<String> {
+    let first = album.first()?;
+    let name = first.metadata.album.as_deref()?;
+    let artist = common_artist(album).unwrap_or("Various Artists");
+
+    let mut header = format!("{} — {}", artist, name);
+    if let Some(year) = album.iter().find_map(|track| track.metadata.year) {
+        header.push_str(&format!(" ({})", year));
+    }
+    // Only shown when the whole run shares one format
+    let format = first.format_label();
+    if format.is_some() && album.iter().all(|track| track.format_label() == format) {
+        header.push_str(&format!(" [{}]", format.unwrap_or_default()));
+    }
+    Some(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AudioCodec, AudioMetadata};
+    use std::path::PathBuf;
+
+    fn track(artist: &str, album: Option<&str>, number: u32, title: &str, secs: u64) -> TrackInfo {
+        let metadata = AudioMetadata {
+            title: Some(title.to_string()),
+            artist: Some(artist.to_string()),
+            album: album.map(str::to_string),
+            track_number: Some(number),
+            year: Some(1973),
+            ..Default::default()
+        };
+        let mut track = TrackInfo::new(PathBuf::from(format!("/music/{}.flac", title)), metadata, Duration::from_secs(secs), 0);
+        track.codec = Some(AudioCodec::Flac);
+        track.sample_rate = Some(96000);
+        track.bit_depth = Some(24);
+        track
+    }
+
+    fn queue() -> Vec<TrackInfo> {
+        vec![
+            track("Pink Floyd", Some("The Dark Side of the Moon"), 1, "Speak to Me", 67),
+            track("Pink Floyd", Some("The Dark Side of the Moon"), 2, "Breathe", 169),
+            track("Miles Davis", None, 4, "Blue in Green", 337),
+            track("Various", Some("Jazz Sampler"), 1, "So What", 545),
+        ]
+    }
+
+    #[test]
+    fn test_grouped_lines_put_tracks_under_album_headers() {
+        assert_eq!(QueueView::grouped_lines(&queue(), 1), vec![
+            "Pink Floyd — The Dark Side of the Moon (1973) [24/96 FLAC]",
+            "    01. Speak to Me  01:07",
+            "  > 02. Breathe  02:49",
+            "  Miles Davis - Blue in Green  05:37",
+            "Various — Jazz Sampler (1973) [24/96 FLAC]",
+            "    01. So What  09:05",
+        ]);
+    }
+
+    #[test]
+    fn test_header_drops_mixed_formats_and_artists() {
+        let mut tracks = queue();
+        tracks[1].metadata.artist = Some("Roger Waters".to_string());
+        tracks[1].sample_rate = Some(44100);
+        tracks[1].bit_depth = Some(16);
+
+        let lines = QueueView::grouped_lines(&tracks[..2], 0);
+        assert_eq!(lines[0], "Various Artists — The Dark Side of the Moon (1973)");
+        assert_eq!(lines[2], "    02. Roger Waters - Breathe  02:49");
+    }
+
+    #[test]
+    fn test_compact_lines_and_total() {
+        let tracks = queue();
+        assert_eq!(QueueView::compact_lines(&tracks, 2), vec![
+            "  Pink Floyd — The Dark Side of the Moon (1973) [24/96 FLAC] - 2 tracks, 03:56",
+            "> (no album tag) - 1 track, 05:37",
+            "  Various — Jazz Sampler (1973) [24/96 FLAC] - 1 track, 09:05",
+        ]);
+        assert_eq!(QueueView::total_line(&tracks), "Total: 4 tracks, 18:38");
+    }
+
+    #[test]
+    fn test_flat_lines_keep_script_format() {
+        assert_eq!(QueueView::flat_lines(&queue()[..2], 0), vec![
+            "> 1: Pink Floyd - Speak to Me",
+            "  2: Pink Floyd - Breathe",
+        ]);
+    }
+
+    #[test]
+    fn test_failed_entries_are_flagged() {
+        let mut tracks = queue();
+        tracks[1].failed = true;
+        tracks[1].rating = Some(4);
+        assert_eq!(QueueView::flat_lines(&tracks[..2], 0)[1], "  2: Pink Floyd - Breathe  ★★★★☆  ✗ unplayable");
+        assert_eq!(QueueView::grouped_lines(&tracks[..2], 0)[2], "    02. Breathe  02:49  ★★★★☆  ✗ unplayable");
+    }
+}
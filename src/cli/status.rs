@@ -1,5 +1,11 @@
 use std::time::Duration;
-use crate::models::{PlayerStatus, TrackInfo, PlaybackState};
+use crate::models::{ChannelLevels, PlayerStatus, TrackInfo, PlaybackState};
+use crate::audio::device::DeviceCapabilities;
+use crate::audio::metadata::FileReport;
+use crate::audio::render::RenderSummary;
+use crate::queue::lyrics::{LyricLine, Lyrics};
+use crate::queue::loudness::ScanSummary;
+use crate::queue::verify::VerifySummary;
 
 /// Status display formatter for the CLI
 pub struct StatusDisplay;
@@ -14,10 +20,13 @@ impl StatusDisplay {
                 Self::display_track_info(track);
                 Self::display_playback_info(status);
                 Self::display_technical_info(status);
+                if let Some(warning) = &status.degradation_warning {
+                    println!("│ ⚠ {}", warning);
+                }
             }
             None => {
                 println!("│ No track loaded");
-                println!("│ Status: {}", status.state.as_str());
+                println!("│ Status: {}", Self::state_label(status));
             }
         }
         
@@ -32,17 +41,21 @@ impl StatusDisplay {
                 let title = Self::truncate(&track.display_name(), 30);
                 let artist = Self::truncate(&track.artist_name(), 25);
                 let position = Self::format_duration(status.position);
-                let duration = Self::format_duration(track.duration);
-                let progress_percent = (status.progress() * 100.0) as u8;
+                let progress = if status.stream.as_ref().is_some_and(|stream| stream.live) {
+                    format!("{} live", position)
+                } else {
+                    let duration = Self::format_duration(track.duration);
+                    format!("{}/{} ({}%)", position, duration, (status.progress() * 100.0) as u8)
+                };
                 
-                println!("{} | {} - {} | {}/{} ({}%) | {}",
-                    status.state.as_str(),
+                println!("{} | {} - {} | {} | {}",
+                    Self::state_label(status),
                     artist,
                     title,
-                    position,
-                    duration,
-                    progress_percent,
-                    if let Some(format) = &status.audio_format {
+                    progress,
+                    if let Some(path) = Self::signal_path_description(status) {
+                        path
+                    } else if let Some(format) = &status.audio_format {
                         format.format_description()
                     } else {
                         "Unknown format".to_string()
@@ -50,34 +63,186 @@ impl StatusDisplay {
                 );
             }
             None => {
-                println!("{} | No track loaded", status.state.as_str());
+                println!("{} | No track loaded", Self::state_label(status));
             }
         }
     }
 
     /// Display only track metadata information
     pub fn display_track_metadata(track: &TrackInfo) {
-        println!("┌─ Track Information ─────────────────────────────────────┐");
-        println!("│ Title: {}", Self::truncate(&track.display_name(), 50));
-        println!("│ Artist: {}", Self::truncate(&track.artist_name(), 49));
-        println!("│ Album: {}", Self::truncate(&track.album_name(), 50));
-        
-        if let Some(track_num) = track.metadata.track_number {
-            println!("│ Track: {}", track_num);
+        for line in Self::track_metadata_lines(track) {
+            println!("{}", line);
         }
-        
-        if let Some(year) = track.metadata.year {
-            println!("│ Year: {}", year);
+    }
+
+    /// Box the track's full metadata, truncating long fields but never the lyrics
+    pub fn track_metadata_lines(track: &TrackInfo) -> Vec<String> {
+        let mut lines = vec!["┌─ Track Information ─────────────────────────────────────┐".to_string()];
+        let mut in_lyrics = false;
+        for line in track.display_full_metadata().lines() {
+            in_lyrics |= line == "Lyrics:";
+            if in_lyrics {
+                lines.push(format!("│ {}", line));
+            } else {
+                lines.push(format!("│ {}", Self::truncate(line, 57)));
+            }
         }
-        
-        if let Some(genre) = &track.metadata.genre {
-            println!("│ Genre: {}", Self::truncate(genre, 50));
+        lines.push("└─────────────────────────────────────────────────────────┘".to_string());
+        lines
+    }
+
+    /// Display the technical details and remaining tags of a file
+    pub fn display_file_report(report: &FileReport, status: Option<&PlayerStatus>) {
+        for line in Self::file_report_lines(report, status) {
+            println!("{}", line);
         }
-        
-        println!("│ Duration: {}", Self::format_duration(track.duration));
-        println!("│ File Size: {}", Self::format_file_size(track.file_size));
-        println!("│ Path: {}", Self::truncate(&track.path.display().to_string(), 45));
-        println!("└─────────────────────────────────────────────────────────┘");
+    }
+
+    /// Build the `info` lines that follow the track box; a status adds the live output format when the file is playing
+    pub fn file_report_lines(report: &FileReport, status: Option<&PlayerStatus>) -> Vec<String> {
+        let mut lines = vec![
+            "┌─ File Details ──────────────────────────────────────────┐".to_string(),
+            format!("│ Container: {}", report.container),
+            format!("│ Codec: {}", Self::truncate(&report.codec, 50)),
+        ];
+
+        if let Some(sample_rate) = report.sample_rate {
+            lines.push(format!("│ Sample Rate: {} Hz", sample_rate));
+        }
+        if let Some(bit_depth) = report.bit_depth {
+            lines.push(format!("│ Bit Depth: {}-bit", bit_depth));
+        }
+        if let Some(channels) = report.channels {
+            lines.push(format!("│ Channels: {} ({})", channels, Self::channel_description(channels)));
+        }
+        // Duration and size are in the track box above
+        if let Some(bitrate) = report.average_bitrate() {
+            lines.push(format!("│ Bitrate: {} kbps (average)", bitrate));
+        }
+        if let Some(encoder) = &report.encoder {
+            lines.push(format!("│ Encoder: {}", Self::truncate(encoder, 48)));
+        }
+        if let Some(md5) = &report.md5 {
+            lines.push(format!("│ MD5: {}", md5));
+        }
+        if let Some(loudness) = &report.loudness {
+            lines.push(format!("│ Loudness: {:.1} LUFS, true peak {:.1} dBTP (scanned)",
+                loudness.integrated_lufs, loudness.true_peak_dbtp()));
+            lines.push(format!("│ Track Gain: {:+.2} dB", loudness.track_gain_db()));
+        }
+
+        if !report.metadata.extra_tags.is_empty() {
+            lines.push("│ Other Tags:".to_string());
+            lines.extend(report.metadata.extra_tags.iter()
+                .map(|(key, value)| format!("│   {}: {}", key, Self::truncate(value, 50))));
+        }
+
+        if let Some(status) = status.filter(|status| status.output_sample_rate != 0) {
+            let source_rate = status.audio_format.as_ref().map(|format| format.sample_rate).or(report.sample_rate);
+            let source_bits = status.audio_format.as_ref().map(|format| format.bit_depth).or(report.bit_depth);
+            lines.push(format!("│ Output: {}-bit / {} Hz, {} ({})",
+                status.output_bit_depth,
+                status.output_sample_rate,
+                status.output_channels,
+                Self::channel_description(status.output_channels),
+            ));
+            lines.push(match source_rate {
+                Some(rate) if status.resampling || rate != status.output_sample_rate => {
+                    format!("│ Resampling: {} → {} Hz", rate, status.output_sample_rate)
+                }
+                _ => "│ Resampling: No".to_string(),
+            });
+            lines.push(match source_bits {
+                Some(bits) if bits < status.output_bit_depth => {
+                    format!("│ Bit-Depth Conversion: {} → {}-bit (padded)", bits, status.output_bit_depth)
+                }
+                Some(bits) if bits > status.output_bit_depth => {
+                    format!("│ Bit-Depth Conversion: {} → {}-bit (truncated)", bits, status.output_bit_depth)
+                }
+                _ => "│ Bit-Depth Conversion: No".to_string(),
+            });
+        }
+
+        lines.push("└─────────────────────────────────────────────────────────┘".to_string());
+        lines
+    }
+
+    /// Build the `verify` summary: counts, then one row per failed file
+    pub fn verify_summary_lines(summary: &VerifySummary) -> Vec<String> {
+        let failed = summary.failed();
+        let mut lines = vec![
+            "┌─ Verify Summary ────────────────────────────────────────┐".to_string(),
+            format!("│ Checked: {}", summary.checks.len()),
+            format!("│ Passed: {}", summary.checks.len() - failed),
+            format!("│ Failed: {}", failed),
+        ];
+        if summary.skipped > 0 {
+            lines.push(format!("│ Skipped: {} (cancelled)", summary.skipped));
+        }
+        if failed > 0 {
+            lines.push("├─ Problem ── File ───────────────────────────────────────┤".to_string());
+            for check in summary.failures() {
+                let Some(problem) = &check.problem else { continue };
+                lines.push(format!("│ {:<10} {}", problem.label(), check.path.display()));
+                lines.push(format!("│ {:<10} {}", "", Self::truncate(&problem.to_string(), 50)));
+            }
+        }
+        lines.push("└─────────────────────────────────────────────────────────┘".to_string());
+        lines
+    }
+
+    /// Build the `export` summary: the file and its format, then one row per file left out
+    pub fn export_summary_lines(summary: &RenderSummary) -> Vec<String> {
+        let format = summary.format;
+        let mut lines = vec![
+            "┌─ Export ────────────────────────────────────────────────┐".to_string(),
+            format!("│ File: {}", summary.output.display()),
+            format!("│ Format: {} kHz, {}-bit, {} ch", Self::format_khz(format.sample_rate), format.bit_depth, format.channels),
+            format!("│ Tracks: {}", summary.rendered),
+            format!("│ Length: {}", Self::format_duration(summary.duration)),
+        ];
+        if summary.clipped_samples > 0 {
+            lines.push(format!("│ Clipped samples: {}", summary.clipped_samples));
+        }
+        if summary.cancelled {
+            lines.push("│ Cancelled: the partial file was removed".to_string());
+        }
+        if !summary.failures.is_empty() {
+            lines.push("├─ Left Out ──────────────────────────────────────────────┤".to_string());
+            for (path, problem) in &summary.failures {
+                lines.push(format!("│ {}", path.display()));
+                lines.push(format!("│   {}", Self::truncate(problem, 54)));
+            }
+        }
+        lines.push("└─────────────────────────────────────────────────────────┘".to_string());
+        lines
+    }
+
+    /// Build the `scan-loudness` summary: counts, then one row per failed file
+    pub fn loudness_summary_lines(summary: &ScanSummary) -> Vec<String> {
+        let failed = summary.failed();
+        let mut lines = vec![
+            "┌─ Loudness Scan ─────────────────────────────────────────┐".to_string(),
+            format!("│ Scanned: {}", summary.scans.len()),
+            format!("│ Measured: {}", summary.scans.iter().filter(|scan| scan.loudness.is_some()).count()),
+            format!("│ Failed: {}", failed),
+        ];
+        if summary.tagged() > 0 {
+            lines.push(format!("│ Tagged: {}", summary.tagged()));
+        }
+        if summary.skipped > 0 {
+            lines.push(format!("│ Skipped: {} (cancelled)", summary.skipped));
+        }
+        if failed > 0 {
+            lines.push("├─ Failed Files ──────────────────────────────────────────┤".to_string());
+            for scan in summary.failures() {
+                let Some(problem) = &scan.problem else { continue };
+                lines.push(format!("│ {}", scan.path.display()));
+                lines.push(format!("│   {}", Self::truncate(problem, 54)));
+            }
+        }
+        lines.push("└─────────────────────────────────────────────────────────┘".to_string());
+        lines
     }
 
     /// Display technical audio format information
@@ -86,6 +251,9 @@ impl StatusDisplay {
             println!("│");
             println!("│ ┌─ Technical Information ─────────────────────────────┐");
             println!("│ │ Format: {}", format.codec.name());
+            if let Some(path) = Self::signal_path_description(status) {
+                println!("│ │ Signal Path: {}", path);
+            }
             println!("│ │ Sample Rate: {} Hz", format.sample_rate);
             println!("│ │ Bit Depth: {}-bit", format.bit_depth);
             println!("│ │ Channels: {} ({})", 
@@ -134,14 +302,40 @@ impl StatusDisplay {
         if let Some(genre) = &track.metadata.genre {
             println!("│ Genre: {}", Self::truncate(genre, 50));
         }
+
+        if let Some(composer) = &track.metadata.composer {
+            println!("│ Composer: {}", Self::truncate(composer, 47));
+        }
+
+        if let Some(bpm) = track.metadata.bpm {
+            println!("│ BPM: {}", bpm);
+        }
+
+        let rating = track.rating_label();
+        if !rating.is_empty() {
+            println!("│ Rating: {}", rating);
+        }
     }
 
     /// Display playback information section
     fn display_playback_info(status: &PlayerStatus) {
         println!("│");
-        println!("│ Status: {}", status.state.as_str());
+        println!("│ Status: {}", Self::state_label(status));
+        if let Some(description) = Self::stream_description(status) {
+            println!("│ {}", description);
+        }
         
-        if let Some(track) = &status.current_track {
+        // A live stream has no end to measure progress against
+        if let Some(lines) = Self::live_stream_lines(status) {
+            for line in lines {
+                println!("│ {}", line);
+            }
+            if let Some(levels) = &status.levels {
+                for line in Self::level_meter_lines(levels) {
+                    println!("│ {}", line);
+                }
+            }
+        } else if let Some(track) = &status.current_track {
             println!("│ Position: {} / {}", 
                 Self::format_duration(status.position), 
                 Self::format_duration(track.duration)
@@ -157,28 +351,309 @@ impl StatusDisplay {
                 "░".repeat(empty)
             );
             println!("│ Progress: [{}] {:.1}%", progress_bar, progress * 100.0);
+            if let Some(levels) = &status.levels {
+                for line in Self::level_meter_lines(levels) {
+                    println!("│ {}", line);
+                }
+            }
             
             // Time remaining
             let remaining = track.duration.saturating_sub(status.position);
             println!("│ Remaining: {}", Self::format_duration(remaining));
+            if !status.seekable {
+                println!("│ Seek: unavailable for this file");
+            }
+        }
+
+        if let Some(description) = Self::loop_description(status) {
+            println!("│ {}", description);
+        }
+
+        if let Some(warning) = Self::clipping_warning(status) {
+            println!("│ {}", warning);
         }
     }
 
+    /// Playback state, or "Buffering…" while playback waits for the pre-buffer
+    pub fn state_label(status: &PlayerStatus) -> &'static str {
+        if status.buffering && status.state == PlaybackState::Playing {
+            "Buffering…"
+        } else {
+            status.state.as_str()
+        }
+    }
+
+    /// Active A-B loop, e.g. "Loop: 01:00 - 01:30 (00:30)"
+    pub fn loop_description(status: &PlayerStatus) -> Option<String> {
+        let region = status.loop_region?;
+        Some(format!("Loop: {} - {} ({})",
+            Self::format_duration(region.start),
+            Self::format_duration(region.end),
+            Self::format_duration(region.length()),
+        ))
+    }
+
+    /// How far a stream has buffered while playback waits for it, e.g. "Stream: buffering 64%"
+    pub fn stream_description(status: &PlayerStatus) -> Option<String> {
+        let percent = status.stream.as_ref()?.buffering?;
+        Some(format!("Stream: buffering {}%", percent))
+    }
+
+    /// What replaces the position and progress bar while a live stream plays: the station's
+    /// current title (the track name until it sends one) and how long it has been listened to
+    pub fn live_stream_lines(status: &PlayerStatus) -> Option<[String; 2]> {
+        let stream = status.stream.as_ref().filter(|stream| stream.live)?;
+        let title = match (&stream.title, &status.current_track) {
+            (Some(title), _) => title.clone(),
+            (None, Some(track)) => track.display_name(),
+            (None, None) => return None,
+        };
+        Some([
+            format!("Live: {}", title),
+            format!("Listening: {}", Self::format_duration(status.position)),
+        ])
+    }
+
+    /// Peak meter bars for the left and right channels, scaled from -60 to 0 dBFS
+    pub fn level_meter_lines(levels: &ChannelLevels) -> [String; 2] {
+        const WIDTH: usize = 40;
+        const FLOOR_DB: f32 = -60.0;
+        let line = |label: &str, peak: f32| {
+            let db = 20.0 * peak.log10();
+            let filled = if db > FLOOR_DB {
+                (((db - FLOOR_DB) / -FLOOR_DB).min(1.0) * WIDTH as f32).round() as usize
+            } else {
+                0
+            };
+            let reading = if db > FLOOR_DB { format!("{:6.1}", db) } else { "  -inf".to_string() };
+            format!("{}: [{}{}] {} dBFS", label, "#".repeat(filled), "-".repeat(WIDTH - filled), reading)
+        };
+        [line("L", levels.peak_left), line("R", levels.peak_right)]
+    }
+
+    /// Warning line for samples clipped on the current track, if any
+    pub fn clipping_warning(status: &PlayerStatus) -> Option<String> {
+        if status.clipped_samples == 0 {
+            return None;
+        }
+        Some(format!("⚠ {} clipped samples this track", status.clipped_samples))
+    }
+
     /// Display system information section
     fn display_system_info(status: &PlayerStatus) {
         println!("│");
         println!("│ Volume: {}%", (status.volume * 100.0) as u8);
+        if status.limiter_enabled {
+            println!("│ Limiter: On");
+        }
+        if status.dither && (1..=16).contains(&status.output_bit_depth) {
+            println!("│ Dither: On");
+        }
+        if status.exclusive_mode {
+            println!("│ Exclusive Mode: On");
+        }
+        if !status.output_mode.is_neutral() {
+            println!("│ Output: {}", status.output_mode);
+        }
+        if status.crossfeed.enabled {
+            println!("│ Crossfeed: {}", status.crossfeed);
+        }
         
         if let Some(device) = &status.output_device {
             println!("│ Device: {}", Self::truncate(device, 49));
         } else {
             println!("│ Device: Default");
         }
+        println!("│ {}", Self::buffer_description(status));
+    }
+
+    /// Describe source vs. output format, e.g. "FLAC 24/192 → 24/192 (bit-perfect), buffer 87%"
+    pub fn signal_path_description(status: &PlayerStatus) -> Option<String> {
+        let format = status.audio_format.as_ref()?;
+        if status.output_sample_rate == 0 {
+            return None;
+        }
+
+        let note = if status.is_bit_perfect() {
+            "bit-perfect".to_string()
+        } else if let Some(factor) = status.oversampling {
+            format!("oversampled {}x", factor)
+        } else if status.resampling || format.sample_rate != status.output_sample_rate {
+            "resampled".to_string()
+        } else if format.channels != status.output_channels {
+            "remixed".to_string()
+        } else if !status.output_mode.is_neutral() {
+            status.output_mode.to_string()
+        } else if status.crossfeed.enabled && format.channels == 2 {
+            "crossfeed".to_string()
+        } else if status.volume != 1.0 {
+            format!("volume {}%", (status.volume * 100.0).round() as u32)
+        } else {
+            "truncated".to_string()
+        };
+
+        Some(format!("{} {}/{} → {}/{} ({}), buffer {}%",
+            format.codec.name(),
+            format.bit_depth,
+            Self::format_khz(format.sample_rate),
+            status.output_bit_depth,
+            Self::format_khz(status.output_sample_rate),
+            note,
+            (status.buffer_fill.clamp(0.0, 1.0) * 100.0).round() as u32,
+        ))
+    }
+
+    /// Sample rate in kHz without a trailing ".0" (44100 -> "44.1", 192000 -> "192")
+    fn format_khz(sample_rate: u32) -> String {
+        if sample_rate.is_multiple_of(1000) {
+            (sample_rate / 1000).to_string()
+        } else {
+            format!("{:.1}", sample_rate as f32 / 1000.0)
+        }
+    }
+
+    /// Describe the effective output and ring buffer sizes
+    pub fn buffer_description(status: &PlayerStatus) -> String {
+        let output = if status.output_buffer_frames > 0 {
+            format!("{} frames ({:.1} ms)", status.output_buffer_frames, status.output_latency_ms)
+        } else {
+            "device default".to_string()
+        };
+        format!("Buffer: {}, ring {} ms", output, status.ring_buffer_ms)
+    }
+
+    /// Display lyrics: plain text, or timed lines with their start times
+    pub fn display_lyrics(lyrics: &Lyrics) {
+        for line in Self::lyrics_lines(lyrics) {
+            println!("{}", line);
+        }
+    }
+
+    pub fn lyrics_lines(lyrics: &Lyrics) -> Vec<String> {
+        match lyrics {
+            Lyrics::Plain(text) => text.lines().map(str::to_string).collect(),
+            Lyrics::Synced(lines) => lines.iter()
+                .map(|line| format!("[{}] {}", Self::format_duration(line.time), line.text))
+                .collect(),
+        }
+    }
+
+    /// Display the `watch` lyrics panel
+    pub fn display_synced_lyrics(lines: &[LyricLine], current: Option<usize>) {
+        for line in Self::synced_lyrics_lines(lines, current) {
+            println!("{}", line);
+        }
+    }
+
+    /// Build the `watch` lyrics panel: the line being sung, marked, with one line before it and two after
+    pub fn synced_lyrics_lines(lines: &[LyricLine], current: Option<usize>) -> Vec<String> {
+        let start = current.map_or(0, |index| index.saturating_sub(1));
+        let end = current.map_or(0, |index| index + 1).saturating_add(2).min(lines.len());
+        let mut panel = vec!["┌─ Lyrics".to_string()];
+        panel.extend((start..end).map(|index| {
+            let marker = if Some(index) == current { "▶" } else { " " };
+            format!("│ {} {}", marker, lines[index].text)
+        }));
+        panel.push("└".to_string());
+        panel
+    }
+
+    /// Display spectrum analyzer bars with a frequency axis
+    pub fn display_spectrum(levels: &[f32], sample_rate: u32) {
+        for line in Self::spectrum_lines(levels, sample_rate) {
+            println!("{}", line);
+        }
+    }
+
+    /// Build spectrum analyzer lines (bars, then axis labels)
+    pub fn spectrum_lines(levels: &[f32], sample_rate: u32) -> Vec<String> {
+        use crate::audio::spectrum::{format_frequency, render_bars};
+
+        let mut lines = vec![format!("┌─ Spectrum {}", "─".repeat(levels.len().saturating_sub(9)))];
+        lines.extend(render_bars(levels, 8).into_iter().map(|row| format!("│{}", row)));
+
+        let low = "20";
+        let high = if sample_rate > 0 {
+            format_frequency(sample_rate as f32 / 2.0)
+        } else {
+            "-".to_string()
+        };
+        let gap = levels.len().saturating_sub(low.len() + high.len());
+        lines.push(format!("└{}{}{}", low, " ".repeat(gap), high));
+        lines
+    }
+
+    /// Build the `spectrum` snapshot: an ASCII column per band, dBFS on the left, band centres below
+    pub fn spectrum_snapshot_lines(levels: &[f32], centers: &[f32]) -> Vec<String> {
+        use crate::audio::spectrum::format_frequency;
+        const ROWS: usize = 10;
+        const FLOOR_DB: f32 = -90.0;
+        const LABEL_EVERY: usize = 4;
+
+        let mut lines = Vec::with_capacity(ROWS + 2);
+        for row in (0..ROWS).rev() {
+            // Label the level at the top of every third row, counting from the top
+            let label = if (ROWS - 1 - row).is_multiple_of(3) {
+                format!("{:>4}", (FLOOR_DB - FLOOR_DB * (row + 1) as f32 / ROWS as f32).round() as i32)
+            } else {
+                String::new()
+            };
+            let bars: String = levels.iter()
+                .map(|&level| if level * ROWS as f32 > row as f32 { "## " } else { "   " })
+                .collect();
+            lines.push(format!("{:>4} |{}", label, bars.trim_end()));
+        }
+        lines.push(format!("  dB +{}", "-".repeat(levels.len() * 3)));
+
+        let mut axis = String::from("  Hz  ");
+        for (band, &center) in centers.iter().enumerate().step_by(LABEL_EVERY) {
+            let column = 6 + band * 3;
+            axis.push_str(&" ".repeat(column.saturating_sub(axis.chars().count())));
+            axis.push_str(&format_frequency(center));
+        }
+        lines.push(axis);
+        lines
+    }
+
+    /// Display the capabilities of an output device
+    pub fn display_device_capabilities(capabilities: &DeviceCapabilities) {
+        for line in Self::device_capabilities_lines(capabilities) {
+            println!("{}", line);
+        }
+    }
+
+    /// Build device capability lines (rates, bit depths, channels, exclusive mode)
+    pub fn device_capabilities_lines(capabilities: &DeviceCapabilities) -> Vec<String> {
+        let rates: Vec<String> = capabilities.supported_sample_rates
+            .iter()
+            .map(|&rate| format!("{:.1} kHz", rate as f32 / 1000.0))
+            .collect();
+        let depths: Vec<String> = capabilities.supported_bit_depths
+            .iter()
+            .map(|depth| format!("{}-bit", depth))
+            .collect();
+        let default_config = &capabilities.default_config;
+
+        vec![
+            format!("┌─ Device: {}", capabilities.name),
+            format!("│ Sample Rates: {}", rates.join(", ")),
+            format!("│ Bit Depths: {}", depths.join(", ")),
+            format!("│ Channels: up to {} ({})", capabilities.max_channels, Self::channel_description(capabilities.max_channels)),
+            format!("│ Default: {:.1} kHz, {} ch, {:?}",
+                default_config.sample_rate().0 as f32 / 1000.0,
+                default_config.channels(),
+                default_config.sample_format()),
+            format!("└ Exclusive Mode: {}", if capabilities.supports_exclusive_mode { "Available" } else { "Unavailable" }),
+        ]
     }
 
     /// Display real-time position update (single line)
     pub fn display_position_update(status: &PlayerStatus) {
-        if let Some(track) = &status.current_track {
+        if let Some([title, listening]) = Self::live_stream_lines(status) {
+            print!("\r{} {} | {}", Self::state_label(status), title, listening);
+            use std::io::{self, Write};
+            let _ = io::stdout().flush();
+        } else if let Some(track) = &status.current_track {
             let progress = status.progress();
             let bar_width = 30;
             let filled = (progress * bar_width as f32) as usize;
@@ -189,7 +664,7 @@ impl StatusDisplay {
             );
             
             print!("\r{} [{}] {}/{} ({:.1}%)",
-                status.state.as_str(),
+                Self::state_label(status),
                 progress_bar,
                 Self::format_duration(status.position),
                 Self::format_duration(track.duration),
@@ -443,7 +918,10 @@ impl StatusDisplay {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{AudioMetadata, AudioFormat, AudioCodec, TrackInfo};
+    use crate::models::{AudioMetadata, AudioFormat, AudioCodec, LoopRegion, TrackInfo};
+    use crate::audio::channels::{ChannelMode, OutputChannels};
+    use crate::audio::crossfeed::CrossfeedSettings;
+    use crate::audio::source::StreamStatus;
     use std::path::PathBuf;
 
     fn create_test_track() -> TrackInfo {
@@ -454,6 +932,7 @@ mod tests {
             track_number: Some(1),
             year: Some(2023),
             genre: Some("Test Genre".to_string()),
+            ..Default::default()
         };
         
         TrackInfo::new(
@@ -499,6 +978,357 @@ mod tests {
         assert_eq!(StatusDisplay::format_file_size(1536 * 1024 * 1024), "1.5 GB");
     }
 
+    #[test]
+    fn test_clipping_warning() {
+        let mut status = create_test_status();
+        assert!(StatusDisplay::clipping_warning(&status).is_none());
+
+        status.clipped_samples = 342;
+        assert_eq!(
+            StatusDisplay::clipping_warning(&status).as_deref(),
+            Some("⚠ 342 clipped samples this track")
+        );
+    }
+
+    #[test]
+    fn test_level_meter_lines() {
+        let levels = ChannelLevels { peak_left: 1.0, peak_right: 0.5, ..ChannelLevels::default() };
+        let [left, right] = StatusDisplay::level_meter_lines(&levels);
+        assert_eq!(left, format!("L: [{}]    0.0 dBFS", "#".repeat(40)));
+        // -6 dB is a tenth of the way down a 60 dB scale
+        assert_eq!(right, format!("R: [{}{}]   -6.0 dBFS", "#".repeat(36), "-".repeat(4)));
+
+        let [silent, _] = StatusDisplay::level_meter_lines(&ChannelLevels::default());
+        assert_eq!(silent, format!("L: [{}]   -inf dBFS", "-".repeat(40)));
+    }
+
+    #[test]
+    fn test_loop_description() {
+        let mut status = create_test_status();
+        assert!(StatusDisplay::loop_description(&status).is_none());
+
+        status.loop_region = LoopRegion::new(Duration::from_secs(60), Duration::from_secs(90));
+        assert_eq!(
+            StatusDisplay::loop_description(&status).as_deref(),
+            Some("Loop: 01:00 - 01:30 (00:30)")
+        );
+    }
+
+    #[test]
+    fn test_state_label_shows_buffering_while_playing() {
+        let mut status = create_test_status();
+        status.state = PlaybackState::Playing;
+        assert_eq!(StatusDisplay::state_label(&status), "Playing");
+
+        status.buffering = true;
+        assert_eq!(StatusDisplay::state_label(&status), "Buffering…");
+        status.state = PlaybackState::Paused;
+        assert_eq!(StatusDisplay::state_label(&status), "Paused");
+    }
+
+    #[test]
+    fn test_stream_description() {
+        let mut status = create_test_status();
+        assert!(StatusDisplay::stream_description(&status).is_none());
+
+        status.stream = Some(StreamStatus { title: Some("Artist - Song".to_string()), ..StreamStatus::default() });
+        assert!(StatusDisplay::stream_description(&status).is_none());
+        status.stream = Some(StreamStatus { buffering: Some(64), ..StreamStatus::default() });
+        assert_eq!(StatusDisplay::stream_description(&status).as_deref(), Some("Stream: buffering 64%"));
+    }
+
+    #[test]
+    fn test_live_stream_lines() {
+        let mut status = create_test_status();
+        status.position = Duration::from_secs(3725);
+        // A stream with a length shows progress as usual
+        status.stream = Some(StreamStatus::default());
+        assert!(StatusDisplay::live_stream_lines(&status).is_none());
+
+        status.stream = Some(StreamStatus { live: true, ..StreamStatus::default() });
+        assert_eq!(StatusDisplay::live_stream_lines(&status), Some(["Live: Test Song".to_string(), "Listening: 01:02:05".to_string()]));
+        status.stream = Some(StreamStatus { live: true, title: Some("Artist - Song".to_string()), ..StreamStatus::default() });
+        assert_eq!(StatusDisplay::live_stream_lines(&status).unwrap()[0], "Live: Artist - Song");
+    }
+
+    #[test]
+    fn test_buffer_description() {
+        let mut status = create_test_status();
+        status.output_buffer_frames = 4096;
+        status.output_latency_ms = 42.666;
+        status.ring_buffer_ms = 1000;
+        assert_eq!(StatusDisplay::buffer_description(&status), "Buffer: 4096 frames (42.7 ms), ring 1000 ms");
+
+        status.output_buffer_frames = 0;
+        assert_eq!(StatusDisplay::buffer_description(&status), "Buffer: device default, ring 1000 ms");
+    }
+
+    #[test]
+    fn test_signal_path_description() {
+        let mut status = create_test_status();
+        status.audio_format = Some(AudioFormat::new(192000, 24, 2, AudioCodec::Flac));
+        status.volume = 1.0;
+        status.output_sample_rate = 192000;
+        status.output_bit_depth = 24;
+        status.output_channels = 2;
+        status.buffer_fill = 0.87;
+        assert_eq!(
+            StatusDisplay::signal_path_description(&status).unwrap(),
+            "FLAC 24/192 → 24/192 (bit-perfect), buffer 87%"
+        );
+
+        status.audio_format = Some(AudioFormat::new(44100, 16, 2, AudioCodec::Mp3));
+        status.output_sample_rate = 48000;
+        status.resampling = true;
+        assert_eq!(
+            StatusDisplay::signal_path_description(&status).unwrap(),
+            "MP3 16/44.1 → 24/48 (resampled), buffer 87%"
+        );
+
+        status.output_sample_rate = 176_400;
+        status.oversampling = Some(4);
+        assert_eq!(
+            StatusDisplay::signal_path_description(&status).unwrap(),
+            "MP3 16/44.1 → 24/176.4 (oversampled 4x), buffer 87%"
+        );
+        status.oversampling = None;
+
+        status.output_sample_rate = 44100;
+        status.resampling = false;
+        status.output_mode = OutputChannels { mode: ChannelMode::Mono, balance: 20 };
+        assert_eq!(
+            StatusDisplay::signal_path_description(&status).unwrap(),
+            "MP3 16/44.1 → 24/44.1 (mono, balance R20), buffer 87%"
+        );
+
+        status.output_mode = OutputChannels::default();
+        status.crossfeed = CrossfeedSettings { enabled: true, ..Default::default() };
+        assert_eq!(
+            StatusDisplay::signal_path_description(&status).unwrap(),
+            "MP3 16/44.1 → 24/44.1 (crossfeed), buffer 87%"
+        );
+
+        status.output_sample_rate = 0;
+        assert!(StatusDisplay::signal_path_description(&status).is_none());
+    }
+
+    #[test]
+    fn test_spectrum_lines() {
+        let levels = vec![1.0; 24];
+        let lines = StatusDisplay::spectrum_lines(&levels, 96_000);
+
+        // Header, eight bar rows, axis
+        assert_eq!(lines.len(), 10);
+        assert!(lines[1].ends_with(&"█".repeat(24)));
+        assert!(lines[9].starts_with("└20"));
+        assert!(lines[9].ends_with("48k"));
+    }
+
+    #[test]
+    fn test_spectrum_snapshot_lines() {
+        let mut levels = vec![0.0; 24];
+        levels[0] = 1.0;
+        levels[13] = 0.55;
+        let centers: Vec<f32> = (0..24).map(|band| 20.0 * 1.333f32.powi(band) * 1.15).collect();
+        let lines = StatusDisplay::spectrum_snapshot_lines(&levels, &centers);
+
+        // Ten bar rows, the axis and the frequency labels
+        assert_eq!(lines.len(), 12);
+        assert_eq!(lines[0], "   0 |##");
+        assert_eq!(lines[1], "     |##");
+        assert_eq!(lines[3], " -27 |##");
+        assert_eq!(lines[4], format!("     |##{}##", " ".repeat(37)));
+        assert_eq!(lines[9], format!(" -81 |##{}##", " ".repeat(37)));
+        assert_eq!(lines[10], format!("  dB +{}", "-".repeat(72)));
+        assert!(lines[11].starts_with("  Hz  23"));
+        assert_eq!(lines[11].find("724"), Some(6 + 12 * 3));
+    }
+
+    #[test]
+    fn test_device_capabilities_lines() {
+        use cpal::{SampleFormat, SampleRate, SupportedBufferSize, SupportedStreamConfig};
+
+        let capabilities = DeviceCapabilities {
+            name: "USB DAC".to_string(),
+            supported_sample_rates: vec![44100, 96000, 192000],
+            supported_bit_depths: vec![16, 24, 32],
+            max_channels: 2,
+            supports_exclusive_mode: true,
+            default_config: SupportedStreamConfig::new(2, SampleRate(96000), SupportedBufferSize::Unknown, SampleFormat::I32),
+        };
+
+        let lines = StatusDisplay::device_capabilities_lines(&capabilities);
+        assert!(lines[0].contains("USB DAC"));
+        assert!(lines[1].contains("44.1 kHz, 96.0 kHz, 192.0 kHz"));
+        assert!(lines[2].contains("16-bit, 24-bit, 32-bit"));
+        assert!(lines[3].contains("Stereo"));
+        assert!(lines.last().unwrap().contains("Exclusive Mode: Available"));
+    }
+
+    #[test]
+    fn test_synced_lyrics_lines() {
+        let lines: Vec<LyricLine> = ["One", "Two", "Three", "Four", "Five"].iter().enumerate()
+            .map(|(i, text)| LyricLine { time: Duration::from_secs(i as u64 * 5), text: text.to_string() })
+            .collect();
+
+        assert_eq!(StatusDisplay::synced_lyrics_lines(&lines, None), vec!["┌─ Lyrics", "│   One", "│   Two", "└"]);
+        assert_eq!(
+            StatusDisplay::synced_lyrics_lines(&lines, Some(2)),
+            vec!["┌─ Lyrics", "│   Two", "│ ▶ Three", "│   Four", "│   Five", "└"]
+        );
+        assert_eq!(StatusDisplay::synced_lyrics_lines(&lines, Some(4)), vec!["┌─ Lyrics", "│   Four", "│ ▶ Five", "└"]);
+        assert_eq!(
+            StatusDisplay::lyrics_lines(&Lyrics::Synced(lines[..2].to_vec())),
+            vec!["[00:00] One", "[00:05] Two"]
+        );
+    }
+
+    #[test]
+    fn test_track_metadata_lines() {
+        let mut track = create_test_track();
+        track.metadata.total_tracks = Some(12);
+        track.metadata.disc_number = Some(2);
+        track.metadata.composer = Some("Test Composer".to_string());
+        track.metadata.comment = Some("Remastered".to_string());
+        track.metadata.lyrics = Some("First line\nSecond line".to_string());
+        track.metadata.compilation = true;
+
+        let lines = StatusDisplay::track_metadata_lines(&track);
+        assert!(lines.contains(&"│ Track: 1/12".to_string()));
+        assert!(lines.contains(&"│ Disc: 2".to_string()));
+
+        track.metadata.total_discs = Some(3);
+        assert!(StatusDisplay::track_metadata_lines(&track).contains(&"│ Disc: 2/3".to_string()));
+        assert!(lines.contains(&"│ Composer: Test Composer".to_string()));
+        assert!(lines.contains(&"│ Compilation: Yes".to_string()));
+        assert!(lines.contains(&"│ Comment: Remastered".to_string()));
+        assert!(lines.contains(&"│   Second line".to_string()));
+
+        let plain = StatusDisplay::track_metadata_lines(&create_test_track());
+        assert!(plain.contains(&"│ Track: 1".to_string()));
+        assert!(!plain.iter().any(|line| line.contains("Lyrics") || line.contains("Disc") || line.contains("Rating")));
+
+        track.rating = Some(3);
+        track.favorite = true;
+        assert!(StatusDisplay::track_metadata_lines(&track).contains(&"│ Rating: ★★★☆☆ ♥".to_string()));
+    }
+
+    #[test]
+    fn test_verify_summary_lines() {
+        use crate::queue::verify::{FileCheck, Problem};
+        let check = |path: &str, problem| FileCheck { path: PathBuf::from(path), decoded: Duration::from_secs(5), problem };
+        let mut summary = VerifySummary {
+            checks: vec![check("/music/good.flac", None), check("/music/cut.flac", Some(Problem::Truncated {
+                expected: Duration::from_secs(240),
+                decoded: Duration::from_secs(5),
+            }))],
+            skipped: 0,
+        };
+
+        let lines = StatusDisplay::verify_summary_lines(&summary);
+        assert!(lines.contains(&"│ Checked: 2".to_string()));
+        assert!(lines.contains(&"│ Failed: 1".to_string()));
+        assert!(lines.contains(&"│ truncated  /music/cut.flac".to_string()));
+        assert!(lines.contains(&"│            decoded 5.0s of 240.0s".to_string()));
+        assert!(!lines.iter().any(|line| line.contains("Skipped") || line.contains("good.flac")));
+
+        summary.checks.truncate(1);
+        summary.skipped = 3;
+        let lines = StatusDisplay::verify_summary_lines(&summary);
+        assert!(lines.contains(&"│ Skipped: 3 (cancelled)".to_string()));
+        assert!(!lines.iter().any(|line| line.contains("Problem")));
+    }
+
+    #[test]
+    fn test_loudness_summary_lines() {
+        use crate::audio::loudness::Loudness;
+        use crate::queue::loudness::FileScan;
+        let loudness = Loudness { integrated_lufs: -9.5, true_peak: 1.0 };
+        let summary = ScanSummary {
+            scans: vec![
+                FileScan { path: PathBuf::from("/music/loud.flac"), loudness: Some(loudness), problem: None, tagged: true },
+                FileScan { path: PathBuf::from("/music/quiet.wav"), loudness: None, problem: Some("silent, or shorter than 400 ms".to_string()), tagged: false },
+            ],
+            skipped: 2,
+        };
+
+        let lines = StatusDisplay::loudness_summary_lines(&summary);
+        assert!(lines.contains(&"│ Scanned: 2".to_string()));
+        assert!(lines.contains(&"│ Measured: 1".to_string()));
+        assert!(lines.contains(&"│ Tagged: 1".to_string()));
+        assert!(lines.contains(&"│ Skipped: 2 (cancelled)".to_string()));
+        assert!(lines.contains(&"│ /music/quiet.wav".to_string()));
+        assert!(lines.contains(&"│   silent, or shorter than 400 ms".to_string()));
+        assert!(!lines.iter().any(|line| line.contains("loud.flac")));
+    }
+
+    #[test]
+    fn test_export_summary_lines() {
+        use crate::audio::encoders::EncoderFormat;
+        let summary = RenderSummary {
+            output: PathBuf::from("/tmp/album.flac"),
+            format: EncoderFormat { sample_rate: 44_100, channels: 2, bit_depth: 24 },
+            rendered: 9,
+            failures: vec![(PathBuf::from("/music/broken.mp3"), "MP3 decoder error".to_string())],
+            duration: Duration::from_secs(2_730),
+            clipped_samples: 0,
+            cancelled: false,
+        };
+
+        let lines = StatusDisplay::export_summary_lines(&summary);
+        assert!(lines.contains(&"│ File: /tmp/album.flac".to_string()));
+        assert!(lines.contains(&"│ Format: 44.1 kHz, 24-bit, 2 ch".to_string()));
+        assert!(lines.contains(&"│ Tracks: 9".to_string()));
+        assert!(lines.contains(&"│ Length: 45:30".to_string()));
+        assert!(lines.contains(&"│ /music/broken.mp3".to_string()));
+        assert!(!lines.iter().any(|line| line.contains("Clipped") || line.contains("Cancelled")));
+    }
+
+    #[test]
+    fn test_file_report_lines() {
+        let mut report = FileReport {
+            container: "FLAC".to_string(),
+            codec: "Free Lossless Audio Codec".to_string(),
+            sample_rate: Some(44100),
+            bit_depth: Some(16),
+            channels: Some(2),
+            duration: Duration::from_secs(10),
+            file_size: 1_000_000,
+            encoder: Some("reference libFLAC 1.4.3".to_string()),
+            md5: Some("00112233445566778899aabbccddeeff".to_string()),
+            ..Default::default()
+        };
+        report.metadata.extra_tags.push(("REPLAYGAIN_TRACK_GAIN".to_string(), "-6.20 dB".to_string()));
+
+        let lines = StatusDisplay::file_report_lines(&report, None);
+        assert!(lines.contains(&"│ Channels: 2 (Stereo)".to_string()));
+        assert!(lines.contains(&"│ Bitrate: 800 kbps (average)".to_string()));
+        assert!(lines.contains(&"│ Encoder: reference libFLAC 1.4.3".to_string()));
+        assert!(lines.contains(&"│ MD5: 00112233445566778899aabbccddeeff".to_string()));
+        assert!(lines.contains(&"│   REPLAYGAIN_TRACK_GAIN: -6.20 dB".to_string()));
+        assert!(!lines.iter().any(|line| line.contains("Output") || line.contains("Loudness")));
+
+        report.loudness = Some(crate::audio::loudness::Loudness { integrated_lufs: -12.34, true_peak: 0.5 });
+        let lines = StatusDisplay::file_report_lines(&report, None);
+        assert!(lines.contains(&"│ Loudness: -12.3 LUFS, true peak -6.0 dBTP (scanned)".to_string()));
+        assert!(lines.contains(&"│ Track Gain: -5.66 dB".to_string()));
+
+        let mut status = PlayerStatus::new();
+        status.output_sample_rate = 48000;
+        status.output_bit_depth = 24;
+        status.output_channels = 2;
+        let lines = StatusDisplay::file_report_lines(&report, Some(&status));
+        assert!(lines.contains(&"│ Output: 24-bit / 48000 Hz, 2 (Stereo)".to_string()));
+        assert!(lines.contains(&"│ Resampling: 44100 → 48000 Hz".to_string()));
+        assert!(lines.contains(&"│ Bit-Depth Conversion: 16 → 24-bit (padded)".to_string()));
+
+        status.output_sample_rate = 44100;
+        status.output_bit_depth = 16;
+        let lines = StatusDisplay::file_report_lines(&report, Some(&status));
+        assert!(lines.contains(&"│ Resampling: No".to_string()));
+        assert!(lines.contains(&"│ Bit-Depth Conversion: No".to_string()));
+    }
+
     #[test]
     fn test_channel_description() {
         assert_eq!(StatusDisplay::channel_description(1), "Mono");
@@ -587,6 +1417,7 @@ mod tests {
             track_number: Some(1),
             year: Some(2023),
             genre: Some("This is a very long genre name".to_string()),
+            ..Default::default()
         };
         
         let track = TrackInfo::new(
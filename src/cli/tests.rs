@@ -1,8 +1,11 @@
 #[cfg(test)]
 mod tests {
-    use crate::cli::{CliApp, Commands, QueueAction, PlaylistAction, DeviceAction, ParseError};
+    use crate::cli::{CliApp, Commands, QueueAction, PlaylistAction, DeviceAction, ParseError, Toggle, BufferSize, LogAction, PerfAction, HistoryAction, RecoveryAction, ConfigAction, EqAction, OutputAction, DirectoryFilters, SeekOffset, TagAction, WatchDirAction};
+    use crate::audio::eq::{EqBand, EqFilter};
     use crate::models::{AudioFormat, AudioCodec, AudioMetadata, TrackInfo, PlayerStatus};
     use crate::error::PlayerError;
+    use crate::queue::SortKey;
+    use std::ffi::OsString;
     use std::path::PathBuf;
     use std::time::Duration;
 
@@ -54,6 +57,11 @@ mod tests {
         assert!(result.is_ok());
         assert!(matches!(result.unwrap(), Commands::Stop));
 
+        // Test unload
+        let result = CliApp::parse_command("unload");
+        assert!(result.is_ok());
+        assert!(matches!(result.unwrap(), Commands::Unload));
+
         // Test next
         let result = CliApp::parse_command("next");
         assert!(result.is_ok());
@@ -72,7 +80,118 @@ mod tests {
         // Test status
         let result = CliApp::parse_command("status");
         assert!(result.is_ok());
-        assert!(matches!(result.unwrap(), Commands::Status));
+        assert!(matches!(result.unwrap(), Commands::Status { json: false }));
+
+        let result = CliApp::parse_command("status --json");
+        assert!(matches!(result.unwrap(), Commands::Status { json: true }));
+
+        assert!(CliApp::parse_command("status --xml").is_err());
+    }
+
+    #[test]
+    fn test_parse_command_info() {
+        let result = CliApp::parse_command("info");
+        assert!(matches!(result.unwrap(), Commands::Info { path: None, json: false }));
+
+        match CliApp::parse_command("info --json /music/My Song.mp3").unwrap() {
+            Commands::Info { path, json } => {
+                assert_eq!(path, Some(PathBuf::from("/music/My Song.mp3")));
+                assert!(json);
+            }
+            _ => panic!("Expected Info command"),
+        }
+
+        // Queue positions are told apart from file names when the command runs
+        match CliApp::parse_command("info 2").unwrap() {
+            Commands::Info { path, json } => assert_eq!((path, json), (Some(PathBuf::from("2")), false)),
+            _ => panic!("Expected Info command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_command_lyrics() {
+        assert!(matches!(CliApp::parse_command("lyrics").unwrap(), Commands::Lyrics));
+        assert!(CliApp::parse_command("lyrics now").is_err());
+    }
+
+    #[test]
+    fn test_parse_command_art() {
+        assert!(matches!(CliApp::parse_command("art").unwrap(), Commands::Art));
+        assert!(CliApp::parse_command("art big").is_err());
+    }
+
+    #[test]
+    fn test_parse_command_verify() {
+        match CliApp::parse_command("verify queue").unwrap() {
+            Commands::Verify { target } => assert_eq!(target, "queue"),
+            _ => panic!("Expected Verify command"),
+        }
+        // Playlist names and paths may hold spaces
+        match CliApp::parse_command("verify Road Trip").unwrap() {
+            Commands::Verify { target } => assert_eq!(target, "Road Trip"),
+            _ => panic!("Expected Verify command"),
+        }
+        assert!(CliApp::parse_command("verify").is_err());
+    }
+
+    #[test]
+    fn test_parse_command_tag() {
+        match CliApp::parse_command("tag show /music/My Song.flac").unwrap() {
+            Commands::Tag { action: TagAction::Show { path } } => assert_eq!(path, PathBuf::from("/music/My Song.flac")),
+            _ => panic!("Expected Tag Show command"),
+        }
+        match CliApp::parse_command(r#"tag set /music/My Song.flac title="A New Day" artist=Band tracknumber=3"#).unwrap() {
+            Commands::Tag { action: TagAction::Set { path, fields } } => {
+                assert_eq!(path, PathBuf::from("/music/My Song.flac"));
+                assert_eq!(fields, vec!["title=A New Day", "artist=Band", "tracknumber=3"]);
+            }
+            _ => panic!("Expected Tag Set command"),
+        }
+        match CliApp::parse_command("tag clear /music/My Song.flac comment").unwrap() {
+            Commands::Tag { action: TagAction::Clear { path, field } } => {
+                assert_eq!(path, PathBuf::from("/music/My Song.flac"));
+                assert_eq!(field, "comment");
+            }
+            _ => panic!("Expected Tag Clear command"),
+        }
+        assert!(CliApp::parse_command("tag").is_err());
+        assert!(CliApp::parse_command("tag show").is_err());
+        assert!(CliApp::parse_command("tag set /music/song.flac").is_err());
+        assert!(CliApp::parse_command("tag set title=Song").is_err());
+        assert!(CliApp::parse_command("tag clear comment").is_err());
+        assert!(CliApp::parse_command("tag rename a b").is_err());
+    }
+
+    #[test]
+    fn test_parse_command_watch_dir() {
+        match CliApp::parse_command("watch-dir add /music/New Downloads").unwrap() {
+            Commands::WatchDir { action: WatchDirAction::Add { path } } => assert_eq!(path, PathBuf::from("/music/New Downloads")),
+            _ => panic!("Expected WatchDir Add command"),
+        }
+        match CliApp::parse_command("watch-dir remove /music").unwrap() {
+            Commands::WatchDir { action: WatchDirAction::Remove { path } } => assert_eq!(path, PathBuf::from("/music")),
+            _ => panic!("Expected WatchDir Remove command"),
+        }
+        assert!(matches!(CliApp::parse_command("watch-dir").unwrap(), Commands::WatchDir { action: WatchDirAction::List }));
+        assert!(matches!(CliApp::parse_command("watch-dir list").unwrap(), Commands::WatchDir { action: WatchDirAction::List }));
+        assert!(matches!(CliApp::parse_command("watch-dir add"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("watch-dir clear"), Err(ParseError::InvalidArgument { .. })));
+    }
+
+    #[test]
+    fn test_parse_command_scan_loudness() {
+        assert!(CliApp::parse_command("scan-loudness --write-tags").is_err());
+        match CliApp::parse_command("scan-loudness --write-tags My Albums").unwrap() {
+            Commands::ScanLoudness { target, write_tags } => {
+                assert_eq!(target, "My Albums");
+                assert!(write_tags);
+            }
+            _ => panic!("Expected ScanLoudness command"),
+        }
+        assert!(matches!(
+            CliApp::parse_command("scan-loudness queue"),
+            Ok(Commands::ScanLoudness { target, write_tags: false }) if target == "queue"
+        ));
     }
 
     #[test]
@@ -97,6 +216,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_command_loop() {
+        match CliApp::parse_command("loop a").unwrap() {
+            Commands::Loop { point, end } => {
+                assert_eq!(point, "a");
+                assert_eq!(end, None);
+            }
+            _ => panic!("Expected Loop command"),
+        }
+
+        match CliApp::parse_command("loop 1:00 1:30").unwrap() {
+            Commands::Loop { point, end } => {
+                assert_eq!(point, "1:00");
+                assert_eq!(end.as_deref(), Some("1:30"));
+            }
+            _ => panic!("Expected Loop command"),
+        }
+
+        assert!(matches!(CliApp::parse_command("loop OFF").unwrap(), Commands::Loop { .. }));
+        assert!(matches!(CliApp::parse_command("loop"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("loop 1:00"), Err(ParseError::MissingArgument { .. })));
+    }
+
     #[test]
     fn test_parse_command_volume() {
         // Test valid volume
@@ -158,13 +300,96 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_command_watch() {
+        let result = CliApp::parse_command("watch");
+        assert!(matches!(result, Ok(Commands::Watch { spectrum: false })));
+
+        let result = CliApp::parse_command("watch --spectrum");
+        assert!(matches!(result, Ok(Commands::Watch { spectrum: true })));
+
+        assert!(matches!(CliApp::parse_command("spectrum"), Ok(Commands::Spectrum)));
+
+        let result = CliApp::parse_command("watch --fast");
+        assert!(matches!(result, Err(ParseError::InvalidArgument { .. })));
+    }
+
+    #[test]
+    fn test_parse_command_limiter() {
+        let result = CliApp::parse_command("limiter on");
+        assert!(matches!(result, Ok(Commands::Limiter { state: Toggle::On })));
+
+        let result = CliApp::parse_command("limiter OFF");
+        assert!(matches!(result, Ok(Commands::Limiter { state: Toggle::Off })));
+
+        let result = CliApp::parse_command("limiter maybe");
+        assert!(matches!(result, Err(ParseError::InvalidArgument { .. })));
+
+        let result = CliApp::parse_command("limiter");
+        assert!(matches!(result, Err(ParseError::MissingArgument { .. })));
+    }
+
+    #[test]
+    fn test_parse_command_output() {
+        let result = CliApp::parse_command("output");
+        assert!(matches!(result, Ok(Commands::Output { action: None })));
+
+        let result = CliApp::parse_command("output Mono");
+        assert!(matches!(result, Ok(Commands::Output { action: Some(OutputAction::Mono) })));
+
+        let result = CliApp::parse_command("output swap");
+        assert!(matches!(result, Ok(Commands::Output { action: Some(OutputAction::Swap) })));
+
+        let result = CliApp::parse_command("output balance -35");
+        assert!(matches!(result, Ok(Commands::Output { action: Some(OutputAction::Balance { balance: -35 }) })));
+
+        let result = CliApp::parse_command("output balance 150");
+        assert!(matches!(result, Err(ParseError::InvalidArgument { .. })));
+
+        let result = CliApp::parse_command("output balance");
+        assert!(matches!(result, Err(ParseError::MissingArgument { .. })));
+
+        let result = CliApp::parse_command("output quad");
+        assert!(matches!(result, Err(ParseError::UnknownCommand { .. })));
+    }
+
+    #[test]
+    fn test_parse_command_crossfeed() {
+        let result = CliApp::parse_command("crossfeed");
+        assert!(matches!(result, Ok(Commands::Crossfeed { state: None, level: None })));
+
+        let result = CliApp::parse_command("crossfeed on 9");
+        assert!(matches!(result, Ok(Commands::Crossfeed { state: Some(Toggle::On), level: Some(level) }) if level == 9.0));
+
+        let result = CliApp::parse_command("crossfeed off");
+        assert!(matches!(result, Ok(Commands::Crossfeed { state: Some(Toggle::Off), level: None })));
+
+        let result = CliApp::parse_command("crossfeed on 30");
+        assert!(matches!(result, Err(ParseError::InvalidArgument { .. })));
+
+        let result = CliApp::parse_command("crossfeed wide");
+        assert!(matches!(result, Err(ParseError::InvalidArgument { .. })));
+    }
+
+    #[test]
+    fn test_parse_command_silence() {
+        let result = CliApp::parse_command("silence on");
+        assert!(matches!(result, Ok(Commands::Silence { state: Toggle::On })));
+
+        let result = CliApp::parse_command("silence quiet");
+        assert!(matches!(result, Err(ParseError::InvalidArgument { .. })));
+
+        let result = CliApp::parse_command("silence");
+        assert!(matches!(result, Err(ParseError::MissingArgument { .. })));
+    }
+
     #[test]
     fn test_parse_command_queue() {
         // Test queue add
         let result = CliApp::parse_command("queue add /path/to/music");
         assert!(result.is_ok());
         match result.unwrap() {
-            Commands::Queue { action: QueueAction::Add { path } } => {
+            Commands::Queue { action: QueueAction::Add { path, .. } } => {
                 assert_eq!(path, PathBuf::from("/path/to/music"));
             }
             _ => panic!("Expected Queue Add command"),
@@ -174,10 +399,101 @@ mod tests {
         let result = CliApp::parse_command("queue list");
         assert!(result.is_ok());
         match result.unwrap() {
-            Commands::Queue { action: QueueAction::List } => {}
+            Commands::Queue { action: QueueAction::List { compact: false, flat: false } } => {}
             _ => panic!("Expected Queue List command"),
         }
 
+        assert!(matches!(
+            CliApp::parse_command("queue list --compact"),
+            Ok(Commands::Queue { action: QueueAction::List { compact: true, flat: false } })
+        ));
+        assert!(matches!(
+            CliApp::parse_command("queue list --flat"),
+            Ok(Commands::Queue { action: QueueAction::List { compact: false, flat: true } })
+        ));
+        assert!(matches!(
+            CliApp::parse_command("queue list --grouped"),
+            Err(ParseError::InvalidArgument { .. })
+        ));
+        assert!(<CliApp as clap::Parser>::try_parse_from(["rmusic", "queue", "list", "--compact", "--flat"]).is_err());
+    }
+
+    #[test]
+    fn test_smart_playlist_commands() {
+        match CliApp::parse_command(r#"playlist create-smart hires {"min_sample_rate": 96000}"#).unwrap() {
+            Commands::Playlist { action: PlaylistAction::CreateSmart { name, criteria_json } } => {
+                assert_eq!(name, "hires");
+                assert_eq!(criteria_json, r#"{"min_sample_rate": 96000}"#);
+            }
+            other => panic!("Expected Playlist CreateSmart command, got {:?}", other),
+        }
+        match CliApp::parse_command("playlist load-smart hires /music/My Library").unwrap() {
+            Commands::Playlist { action: PlaylistAction::LoadSmart { name, library_path } } => {
+                assert_eq!(name, "hires");
+                assert_eq!(library_path, Some(PathBuf::from("/music/My Library")));
+            }
+            other => panic!("Expected Playlist LoadSmart command, got {:?}", other),
+        }
+        assert!(matches!(
+            CliApp::parse_command("playlist load-smart hires").unwrap(),
+            Commands::Playlist { action: PlaylistAction::LoadSmart { library_path: None, .. } }
+        ));
+        assert!(matches!(CliApp::parse_command("playlist create-smart hires"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("playlist load-smart"), Err(ParseError::MissingArgument { .. })));
+    }
+
+    #[test]
+    fn test_queue_maintenance_commands() {
+        assert!(matches!(
+            CliApp::parse_command("queue dedupe"),
+            Ok(Commands::Queue { action: QueueAction::Dedupe { metadata: false } })
+        ));
+        assert!(matches!(
+            CliApp::parse_command("queue deduplicate --metadata"),
+            Ok(Commands::Queue { action: QueueAction::Dedupe { metadata: true } })
+        ));
+        assert!(matches!(CliApp::parse_command("queue dedupe --tags"), Err(ParseError::InvalidArgument { .. })));
+        assert!(matches!(
+            CliApp::parse_command("queue validate"),
+            Ok(Commands::Queue { action: QueueAction::Validate { prune: false } })
+        ));
+        assert!(matches!(
+            CliApp::parse_command("queue validate --prune"),
+            Ok(Commands::Queue { action: QueueAction::Validate { prune: true } })
+        ));
+        assert!(matches!(
+            CliApp::parse_command("queue validate --fix"),
+            Err(ParseError::InvalidArgument { .. })
+        ));
+    }
+
+    #[test]
+    fn test_queue_insertion_commands() {
+        use clap::Parser;
+
+        match CliApp::parse_command("queue add-next /music/Live Album").unwrap() {
+            Commands::Queue { action: QueueAction::AddNext { path } } => assert_eq!(path, PathBuf::from("/music/Live Album")),
+            other => panic!("Expected Queue AddNext command, got {:?}", other),
+        }
+        match CliApp::parse_command("queue insert 3 /music/song.flac").unwrap() {
+            Commands::Queue { action: QueueAction::Insert { index, path } } => {
+                assert_eq!(index, 3);
+                assert_eq!(path, PathBuf::from("/music/song.flac"));
+            }
+            other => panic!("Expected Queue Insert command, got {:?}", other),
+        }
+
+        assert!(matches!(CliApp::parse_command("queue add-next"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("queue insert"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("queue insert 2"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("queue insert 0 /music"), Err(ParseError::InvalidArgument { .. })));
+        assert!(matches!(CliApp::parse_command("queue insert two /music"), Err(ParseError::InvalidArgument { .. })));
+
+        let cli = CliApp::try_parse_from(["rmusic", "queue", "insert", "1", "/music"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Queue { action: QueueAction::Insert { index: 1, .. } })));
+        assert!(CliApp::try_parse_from(["rmusic", "queue", "insert", "0", "/music"]).is_err());
+        assert!(CliApp::try_parse_from(["rmusic", "queue", "add-next", "/music"]).is_ok());
+
         // Test queue clear
         let result = CliApp::parse_command("queue clear");
         assert!(result.is_ok());
@@ -194,6 +510,33 @@ mod tests {
             _ => panic!("Expected Queue Position command"),
         }
 
+        // Test queue sort
+        assert!(matches!(
+            CliApp::parse_command("queue sort disc").unwrap(),
+            Commands::Queue { action: QueueAction::Sort { key: SortKey::Disc } }
+        ));
+        assert!(matches!(
+            CliApp::parse_command("queue sort Album").unwrap(),
+            Commands::Queue { action: QueueAction::Sort { key: SortKey::Album } }
+        ));
+        assert!(matches!(
+            <CliApp as clap::Parser>::try_parse_from(["rmusic", "queue", "sort", "random"]).unwrap().command,
+            Some(Commands::Queue { action: QueueAction::Sort { key: SortKey::Random } })
+        ));
+        assert!(matches!(CliApp::parse_command("queue sort"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("queue sort colour"), Err(ParseError::InvalidArgument { .. })));
+
+        // Test queue shuffle
+        assert!(matches!(
+            CliApp::parse_command("queue shuffle 42").unwrap(),
+            Commands::Queue { action: QueueAction::Shuffle { seed: Some(42) } }
+        ));
+        assert!(matches!(
+            CliApp::parse_command("queue shuffle").unwrap(),
+            Commands::Queue { action: QueueAction::Shuffle { seed: None } }
+        ));
+        assert!(matches!(CliApp::parse_command("queue shuffle -1"), Err(ParseError::InvalidArgument { .. })));
+
         // Test queue without action
         let result = CliApp::parse_command("queue");
         assert!(result.is_err());
@@ -233,7 +576,7 @@ mod tests {
         let result = CliApp::parse_command("playlist save my_playlist");
         assert!(result.is_ok());
         match result.unwrap() {
-            Commands::Playlist { action: PlaylistAction::Save { name } } => {
+            Commands::Playlist { action: PlaylistAction::Save { name, relative: false } } => {
                 assert_eq!(name, "my_playlist");
             }
             _ => panic!("Expected Playlist Save command"),
@@ -267,15 +610,323 @@ mod tests {
             _ => panic!("Expected Playlist Delete command"),
         }
 
+        // Test playlist rename
+        match CliApp::parse_command("playlist rename mix road trip").unwrap() {
+            Commands::Playlist { action: PlaylistAction::Rename { old_name, new_name } } => {
+                assert_eq!(old_name, "mix");
+                assert_eq!(new_name, "road trip");
+            }
+            _ => panic!("Expected Playlist Rename command"),
+        }
+        assert!(matches!(CliApp::parse_command("playlist rename mix"), Err(ParseError::MissingArgument { .. })));
+
+        // Test playlist merge
+        match CliApp::parse_command("playlist merge list1 list2 list3 --output combined").unwrap() {
+            Commands::Playlist { action: PlaylistAction::Merge { names, output } } => {
+                assert_eq!(names, ["list1", "list2", "list3"]);
+                assert_eq!(output, "combined");
+            }
+            _ => panic!("Expected Playlist Merge command"),
+        }
+        assert!(matches!(CliApp::parse_command("playlist merge list1 list2"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("playlist merge --output combined"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("playlist merge list1 --output"), Err(ParseError::MissingArgument { .. })));
+
         // Test playlist with name containing spaces
         let result = CliApp::parse_command("playlist save my favorite songs");
         assert!(result.is_ok());
         match result.unwrap() {
-            Commands::Playlist { action: PlaylistAction::Save { name } } => {
+            Commands::Playlist { action: PlaylistAction::Save { name, relative: false } } => {
                 assert_eq!(name, "my favorite songs");
             }
             _ => panic!("Expected Playlist Save command"),
         }
+
+        // Relative paths, flag before or after the name
+        for input in ["playlist save --relative road trip", "playlist save road trip --relative"] {
+            match CliApp::parse_command(input).unwrap() {
+                Commands::Playlist { action: PlaylistAction::Save { name, relative: true } } => {
+                    assert_eq!(name, "road trip");
+                }
+                other => panic!("Expected relative Playlist Save for '{}', got {:?}", input, other),
+            }
+        }
+        assert!(matches!(
+            CliApp::parse_command("playlist save --relative"),
+            Err(ParseError::MissingArgument { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_command_buffer() {
+        match CliApp::parse_command("buffer 2048").unwrap() {
+            Commands::Buffer { size } => assert_eq!(size, BufferSize::Frames(2048)),
+            _ => panic!("Expected Buffer command"),
+        }
+
+        match CliApp::parse_command("buffer 50ms").unwrap() {
+            Commands::Buffer { size } => {
+                assert_eq!(size, BufferSize::Millis(50));
+                assert_eq!(size.to_frames(96000), 4800);
+            }
+            _ => panic!("Expected Buffer command"),
+        }
+
+        assert!(matches!(CliApp::parse_command("buffer"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("buffer fast"), Err(ParseError::InvalidArgument { .. })));
+    }
+
+    #[test]
+    fn test_parse_command_perf() {
+        assert!(matches!(CliApp::parse_command("perf").unwrap(), Commands::Perf { action: None, json: false, .. }));
+        assert!(matches!(CliApp::parse_command("perf --json").unwrap(), Commands::Perf { action: None, json: true, .. }));
+        assert!(matches!(
+            CliApp::parse_command("perf watch --json").unwrap(),
+            Commands::Perf { action: Some(PerfAction::Watch), json: true, .. }
+        ));
+        assert!(matches!(CliApp::parse_command("perf fast"), Err(ParseError::InvalidArgument { .. })));
+        match CliApp::parse_command("perf --output /tmp/perf.csv").unwrap() {
+            Commands::Perf { action: None, json: false, output } => assert_eq!(output, Some(PathBuf::from("/tmp/perf.csv"))),
+            other => panic!("expected perf with output, got {:?}", other),
+        }
+        assert!(matches!(
+            CliApp::parse_command("perf watch --output /tmp/perf.json").unwrap(),
+            Commands::Perf { action: Some(PerfAction::Watch), output: Some(_), .. }
+        ));
+        assert!(matches!(CliApp::parse_command("perf --output"), Err(ParseError::MissingArgument { .. })));
+    }
+
+    #[test]
+    fn test_parse_command_log() {
+        match CliApp::parse_command("log show").unwrap() {
+            Commands::Log { action: LogAction::Show { count } } => assert_eq!(count, None),
+            _ => panic!("Expected Log Show command"),
+        }
+
+        match CliApp::parse_command("log show 50").unwrap() {
+            Commands::Log { action: LogAction::Show { count } } => assert_eq!(count, Some(50)),
+            _ => panic!("Expected Log Show command"),
+        }
+
+        assert!(matches!(CliApp::parse_command("log"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("log show all"), Err(ParseError::InvalidArgument { .. })));
+        assert!(matches!(CliApp::parse_command("log tail"), Err(ParseError::UnknownCommand { .. })));
+    }
+
+    #[test]
+    fn test_parse_command_recovery() {
+        assert!(matches!(
+            CliApp::parse_command("recovery stats"),
+            Ok(Commands::Recovery { action: RecoveryAction::Stats })
+        ));
+        assert!(matches!(CliApp::parse_command("recovery"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("recovery reset"), Err(ParseError::UnknownCommand { .. })));
+    }
+
+    #[test]
+    fn test_parse_command_history() {
+        assert!(matches!(
+            CliApp::parse_command("history").unwrap(),
+            Commands::History { action: None, limit: None, all: false }
+        ));
+        assert!(matches!(
+            CliApp::parse_command("history 5").unwrap(),
+            Commands::History { action: None, limit: Some(5), all: false }
+        ));
+        assert!(matches!(
+            CliApp::parse_command("history --all 50").unwrap(),
+            Commands::History { action: None, limit: Some(50), all: true }
+        ));
+        assert!(matches!(
+            CliApp::parse_command("history clear").unwrap(),
+            Commands::History { action: Some(HistoryAction::Clear), .. }
+        ));
+        assert!(matches!(
+            <CliApp as clap::Parser>::try_parse_from(["rmusic", "history", "clear"]).unwrap().command,
+            Some(Commands::History { action: Some(HistoryAction::Clear), .. })
+        ));
+        assert!(matches!(
+            <CliApp as clap::Parser>::try_parse_from(["rmusic", "history", "5"]).unwrap().command,
+            Some(Commands::History { action: None, limit: Some(5), .. })
+        ));
+        assert!(matches!(CliApp::parse_command("history all"), Err(ParseError::InvalidArgument { .. })));
+        assert!(matches!(CliApp::parse_command("history clear 5"), Err(ParseError::InvalidArgument { .. })));
+
+        match CliApp::parse_command("play last").unwrap() {
+            Commands::Play { path } => assert_eq!(path, Some(PathBuf::from("last"))),
+            _ => panic!("Expected Play command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_command_config() {
+        match CliApp::parse_command("config output_buffer_frames 512").unwrap() {
+            Commands::Config { action: ConfigAction::Set { key, value, move_playlists } } => {
+                assert_eq!(key, "output_buffer_frames");
+                assert_eq!(value, "512");
+                assert!(!move_playlists);
+            }
+            _ => panic!("Expected Config command"),
+        }
+        match CliApp::parse_command("config set playlist-dir /music/My Playlists --move").unwrap() {
+            Commands::Config { action: ConfigAction::Set { key, value, move_playlists } } => {
+                assert_eq!(key, "playlist-dir");
+                assert_eq!(value, "/music/My Playlists");
+                assert!(move_playlists);
+            }
+            _ => panic!("Expected Config command"),
+        }
+        assert!(matches!(CliApp::parse_command("config show").unwrap(), Commands::Config { action: ConfigAction::Show }));
+        assert!(matches!(CliApp::parse_command("config list").unwrap(), Commands::Config { action: ConfigAction::List }));
+        assert!(matches!(CliApp::parse_command("config reset").unwrap(), Commands::Config { action: ConfigAction::Reset }));
+        assert!(matches!(
+            CliApp::parse_command("config get volume").unwrap(),
+            Commands::Config { action: ConfigAction::Get { key } } if key == "volume"
+        ));
+        assert!(matches!(CliApp::parse_command("config get"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("config show all"), Err(ParseError::InvalidArgument { .. })));
+
+        let parse_args = |args: &[&str]| {
+            let args = CliApp::config_key_as_set(args.iter().map(OsString::from));
+            <CliApp as clap::Parser>::try_parse_from(args).unwrap().command
+        };
+        assert!(matches!(
+            parse_args(&["rmusic", "config", "set", "playlist-dir", "/lists", "--move"]),
+            Some(Commands::Config { action: ConfigAction::Set { key, value, move_playlists: true } }) if key == "playlist-dir" && value == "/lists"
+        ));
+        assert!(matches!(
+            parse_args(&["rmusic", "config", "volume", "0.5"]),
+            Some(Commands::Config { action: ConfigAction::Set { key, value, .. } }) if key == "volume" && value == "0.5"
+        ));
+        assert!(matches!(parse_args(&["rmusic", "config", "show"]), Some(Commands::Config { action: ConfigAction::Show })));
+        assert!(matches!(parse_args(&["rmusic", "config", "reset"]), Some(Commands::Config { action: ConfigAction::Reset })));
+        assert!(matches!(parse_args(&["rmusic", "config", "validate"]), Some(Commands::Config { action: ConfigAction::Validate })));
+        assert!(matches!(CliApp::parse_command("config validate").unwrap(), Commands::Config { action: ConfigAction::Validate }));
+        assert!(matches!(CliApp::parse_command("config"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(
+            CliApp::parse_command("config output_buffer_frames"),
+            Err(ParseError::MissingArgument { argument, .. }) if argument == "value"
+        ));
+        assert!(matches!(
+            CliApp::parse_command("config set"),
+            Err(ParseError::MissingArgument { argument, .. }) if argument == "key"
+        ));
+    }
+
+    #[test]
+    fn test_parse_command_eq() {
+        assert!(matches!(CliApp::parse_command("eq").unwrap(), Commands::Eq { action: Some(EqAction::Show) }));
+        assert!(matches!(CliApp::parse_command("eq clear").unwrap(), Commands::Eq { action: Some(EqAction::Clear) }));
+        assert!(matches!(CliApp::parse_command("eq presets").unwrap(), Commands::Eq { action: Some(EqAction::ListPresets) }));
+        assert!(matches!(
+            CliApp::parse_command("eq load bass-boost").unwrap(),
+            Commands::Eq { action: Some(EqAction::LoadPreset { name }) } if name == "bass-boost"
+        ));
+        assert!(matches!(
+            CliApp::parse_command("eq save-preset Late Night").unwrap(),
+            Commands::Eq { action: Some(EqAction::SavePreset { name }) } if name == "Late Night"
+        ));
+        assert!(matches!(
+            CliApp::parse_command("eq delete Late Night").unwrap(),
+            Commands::Eq { action: Some(EqAction::DeletePreset { name }) } if name == "Late Night"
+        ));
+        assert_eq!(
+            match CliApp::parse_command("eq band low-shelf 100Hz -3").unwrap() {
+                Commands::Eq { action: Some(action) } => action,
+                other => panic!("expected eq band, got {:?}", other),
+            },
+            EqAction::Band { filter: EqFilter::LowShelf, frequency: 100.0, gain: -3.0, q: Some(EqBand::DEFAULT_Q) }
+        );
+        assert!(matches!(
+            CliApp::parse_command("eq band peak 1000 3 1.4").unwrap(),
+            Commands::Eq { action: Some(EqAction::Band { q: Some(q), .. }) } if q == 1.4
+        ));
+
+        assert!(matches!(CliApp::parse_command("eq load"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("eq band peak 1000"), Err(ParseError::MissingArgument { argument, .. }) if argument == "gain"));
+        assert!(matches!(CliApp::parse_command("eq band notch 1000 3"), Err(ParseError::InvalidArgument { .. })));
+        assert!(matches!(CliApp::parse_command("eq band peak 1000 30"), Err(ParseError::InvalidArgument { .. })));
+        assert!(matches!(CliApp::parse_command("eq boost"), Err(ParseError::UnknownCommand { .. })));
+
+        use clap::Parser;
+        let cli = CliApp::try_parse_from(["rmusic", "eq", "band", "high-shelf", "8000", "-2.5"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Eq { action: Some(EqAction::Band { filter: EqFilter::HighShelf, gain, q: None, .. }) }) if gain == -2.5
+        ));
+        let cli = CliApp::try_parse_from(["rmusic", "eq", "load", "Loudness"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Eq { action: Some(EqAction::LoadPreset { .. }) })));
+    }
+
+    #[test]
+    fn test_parse_command_rate_and_fav() {
+        assert!(matches!(CliApp::parse_command("rate 4").unwrap(), Commands::Rate { stars: 4 }));
+        assert!(matches!(CliApp::parse_command("rate 0").unwrap(), Commands::Rate { stars: 0 }));
+        assert!(matches!(CliApp::parse_command("rate"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("rate 6"), Err(ParseError::InvalidArgument { .. })));
+        assert!(matches!(CliApp::parse_command("rate five"), Err(ParseError::InvalidArgument { .. })));
+
+        assert!(matches!(CliApp::parse_command("fav").unwrap(), Commands::Fav { state: None }));
+        assert!(matches!(CliApp::parse_command("fav off").unwrap(), Commands::Fav { state: Some(Toggle::Off) }));
+        assert!(matches!(CliApp::parse_command("fav maybe"), Err(ParseError::InvalidArgument { .. })));
+    }
+
+    #[test]
+    fn test_batch_flags() {
+        use clap::Parser;
+
+        let cli = CliApp::try_parse_from(["rmusic", "--batch", "setup.txt", "--batch-abort-on-error"]).unwrap();
+        assert_eq!(cli.batch, Some(PathBuf::from("setup.txt")));
+        assert!(cli.batch_abort_on_error);
+        assert!(cli.command.is_none());
+
+        assert!(CliApp::try_parse_from(["rmusic", "--batch-abort-on-error"]).is_err());
+        assert!(CliApp::try_parse_from(["rmusic", "--batch", "setup.txt", "play"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_command_export() {
+        use clap::Parser;
+
+        match CliApp::parse_command("export CSV /tmp/my queue.csv").unwrap() {
+            Commands::Export { format, output } => {
+                assert_eq!(format, "csv");
+                assert_eq!(output, PathBuf::from("/tmp/my queue.csv"));
+            }
+            _ => panic!("Expected Export command"),
+        }
+        assert!(matches!(CliApp::parse_command("export json"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("export xml out.xml"), Err(ParseError::InvalidArgument { .. })));
+
+        match CliApp::parse_command("export /tmp/my album.FLAC queue --rate 96000 --bits 24").unwrap() {
+            Commands::ExportAudio { output, target, rate, bits } => {
+                assert_eq!(output, PathBuf::from("/tmp/my album.FLAC"));
+                assert_eq!(target, "queue");
+                assert_eq!((rate, bits), (Some(96000), Some(24)));
+            }
+            _ => panic!("Expected ExportAudio command"),
+        }
+        assert!(matches!(
+            CliApp::parse_command("export out.wav"),
+            Ok(Commands::ExportAudio { ref target, rate: None, bits: None, .. }) if target == "track"
+        ));
+        assert!(matches!(CliApp::parse_command("export cancel"), Ok(Commands::ExportAudio { ref output, .. }) if output.as_os_str() == "cancel"));
+        assert!(matches!(CliApp::parse_command("export out.mp3 queue"), Err(ParseError::InvalidArgument { .. })));
+        assert!(matches!(CliApp::parse_command("export out.wav --bits 20"), Err(ParseError::InvalidArgument { .. })));
+        assert!(matches!(CliApp::parse_command("export out.wav --rate"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("export --bits 16"), Err(ParseError::MissingArgument { .. })));
+
+        let cli = CliApp::try_parse_from(["rmusic", "export-audio", "out.flac", "queue", "--bits", "16"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::ExportAudio { bits: Some(16), ref target, .. }) if target == "queue"));
+
+        let cli = CliApp::try_parse_from(["rmusic", "export", "json", "queue.json"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Export { ref format, .. }) if format == "json"));
+        assert!(CliApp::try_parse_from(["rmusic", "export", "xml", "queue.xml"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_command_serve() {
+        assert!(matches!(CliApp::parse_command("serve").unwrap(), Commands::Serve));
     }
 
     #[test]
@@ -297,6 +948,39 @@ mod tests {
             }
             _ => panic!("Expected Device Set command"),
         }
+
+        // Test device default
+        let result = CliApp::parse_command("device default");
+        assert!(matches!(result, Ok(Commands::Device { action: DeviceAction::SetDefault })));
+
+        // Test device reset
+        let result = CliApp::parse_command("device reset");
+        assert!(matches!(result, Ok(Commands::Device { action: DeviceAction::Reset })));
+
+        // Test device capabilities with JSON output
+        let result = CliApp::parse_command("device capabilities --json USB DAC");
+        match result.unwrap() {
+            Commands::Device { action: DeviceAction::Capabilities { device, json } } => {
+                assert_eq!(device, "USB DAC");
+                assert!(json);
+            }
+            _ => panic!("Expected Device Capabilities command"),
+        }
+
+        assert!(matches!(
+            CliApp::parse_command("device capabilities"),
+            Err(ParseError::MissingArgument { .. })
+        ));
+
+        // Test exclusive mode toggle
+        match CliApp::parse_command("device exclusive on").unwrap() {
+            Commands::Device { action: DeviceAction::SetExclusive { enable } } => assert!(enable),
+            _ => panic!("Expected Device SetExclusive command"),
+        }
+        assert!(matches!(
+            CliApp::parse_command("device exclusive maybe"),
+            Err(ParseError::InvalidArgument { .. })
+        ));
     }
 
     #[test]
@@ -374,7 +1058,7 @@ mod tests {
         let result = CliApp::parse_time("1:60"); // Invalid seconds
         assert!(result.is_err());
 
-        let result = CliApp::parse_time("1:2:3"); // Too many parts
+        let result = CliApp::parse_time("1:2:3:4"); // Too many parts
         assert!(result.is_err());
 
         let result = CliApp::parse_time("abc");
@@ -455,6 +1139,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_seek_offset_before_end() {
+        assert_eq!(CliApp::parse_seek_offset("end-30").unwrap(), SeekOffset::BeforeEnd(Duration::from_secs(30)));
+        assert_eq!(CliApp::parse_seek_offset("end-1:30").unwrap(), SeekOffset::BeforeEnd(Duration::from_secs(90)));
+        assert_eq!(CliApp::parse_seek_offset("1:30").unwrap(), SeekOffset::FromStart(Duration::from_secs(90)));
+        assert!(CliApp::parse_seek_offset("end-").is_err());
+        assert!(CliApp::parse_seek_offset("end-soon").is_err());
+
+        // Three minutes long: 30 s before the end is 2:30; too far back is the start
+        let length = Duration::from_secs(180);
+        assert_eq!(CliApp::parse_seek_offset("end-30").unwrap().resolve(length), Duration::from_secs(150));
+        assert_eq!(SeekOffset::BeforeEnd(Duration::from_secs(600)).resolve(length), Duration::ZERO);
+        assert_eq!(SeekOffset::FromStart(Duration::from_secs(45)).resolve(length), Duration::from_secs(45));
+    }
+
     #[test]
     fn test_parse_time_precision() {
         // Test high precision parsing
@@ -475,6 +1174,27 @@ mod tests {
         assert_eq!(duration.as_millis(), 100);
     }
 
+    #[test]
+    fn test_parse_time_hours() {
+        assert_eq!(CliApp::parse_time("1:02:03").unwrap(), Duration::from_secs(3723));
+        assert_eq!(CliApp::parse_time("0:00:00").unwrap(), Duration::ZERO);
+        assert_eq!(CliApp::parse_time("12:00:00.250").unwrap().as_millis(), 12 * 3600 * 1000 + 250);
+        assert_eq!(CliApp::parse_seek_offset("end-1:00:00").unwrap(), SeekOffset::BeforeEnd(Duration::from_secs(3600)));
+
+        let reason = |input: &str| match CliApp::parse_time(input) {
+            Err(ParseError::InvalidTimeFormat { reason, .. }) => reason,
+            other => panic!("Expected an invalid time for {}, got {:?}", input, other),
+        };
+        assert_eq!(reason("1:60:00"), "minutes out of range");
+        assert_eq!(reason("1:00:60"), "seconds out of range");
+        assert_eq!(reason("0:60"), "seconds out of range");
+        assert_eq!(reason("1:2:3:4"), "expected MM:SS or HH:MM:SS");
+        assert_eq!(reason("1:a:03"), "expected MM:SS or HH:MM:SS");
+        assert_eq!(reason("1::03"), "expected MM:SS or HH:MM:SS");
+        // Minutes are unbounded without an hours part
+        assert_eq!(CliApp::parse_time("75:00").unwrap(), Duration::from_secs(4500));
+    }
+
     #[test]
     fn test_parse_time_edge_cases() {
         // Test boundary values
@@ -594,8 +1314,9 @@ mod tests {
 
         let error = ParseError::InvalidTimeFormat {
             input: "1:60".to_string(),
+            reason: "seconds out of range".to_string(),
         };
-        assert_eq!(format!("{}", error), "Invalid time format: 1:60");
+        assert_eq!(format!("{}", error), "Invalid time format: 1:60 (seconds out of range)");
 
         let error = ParseError::HelpRequested;
         assert_eq!(format!("{}", error), "Help requested");
@@ -632,12 +1353,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_queue_add_filters() {
+        let expected = DirectoryFilters {
+            extensions: vec!["flac".to_string(), "wav".to_string()],
+            min_duration: Some(Duration::from_secs(30)),
+            no_recurse: false,
+            max_depth: Some(2),
+            verify: false,
+        };
+        match CliApp::parse_command("queue add /music/My Albums --ext flac,wav --min-duration 30s --max-depth 2").unwrap() {
+            Commands::Queue { action: QueueAction::Add { path, filters } } => {
+                assert_eq!(path, PathBuf::from("/music/My Albums"));
+                assert_eq!(filters, expected);
+                assert_eq!(filters.options().max_depth, Some(2));
+            }
+            _ => panic!("Expected Queue Add command"),
+        }
+
+        match <CliApp as clap::Parser>::try_parse_from([
+            "rmusic", "queue", "add", "/music", "--ext", "flac,wav", "--min-duration", "30s", "--max-depth", "2",
+        ]).unwrap().command {
+            Some(Commands::Queue { action: QueueAction::Add { filters, .. } }) => assert_eq!(filters, expected),
+            _ => panic!("Expected Queue Add command"),
+        }
+
+        match CliApp::parse_command("queue add --no-recurse /music").unwrap() {
+            Commands::Queue { action: QueueAction::Add { path, filters } } => {
+                assert_eq!(path, PathBuf::from("/music"));
+                assert_eq!(filters.options().max_depth, Some(0));
+            }
+            _ => panic!("Expected Queue Add command"),
+        }
+
+        match CliApp::parse_command("queue add /music/song.flac --verify").unwrap() {
+            Commands::Queue { action: QueueAction::Add { path, filters } } => {
+                assert_eq!(path, PathBuf::from("/music/song.flac"));
+                assert!(filters.options().verify);
+            }
+            _ => panic!("Expected Queue Add command"),
+        }
+
+        assert!(matches!(CliApp::parse_command("queue add /music --min-duration soon"), Err(ParseError::InvalidTimeFormat { .. })));
+        assert!(matches!(CliApp::parse_command("queue add /music --max-depth"), Err(ParseError::MissingArgument { .. })));
+        assert!(matches!(CliApp::parse_command("queue add /music --no-recurse --max-depth 1"), Err(ParseError::InvalidArgument { .. })));
+        assert!(matches!(CliApp::parse_command("queue add --ext flac"), Err(ParseError::MissingArgument { .. })));
+        assert!(<CliApp as clap::Parser>::try_parse_from(["rmusic", "queue", "add", "/music", "--no-recurse", "--max-depth", "1"]).is_err());
+    }
+
     #[test]
     fn test_queue_commands_comprehensive() {
         // Test all queue subcommands
         let commands = vec![
-            ("queue add /music", QueueAction::Add { path: PathBuf::from("/music") }),
-            ("queue list", QueueAction::List),
+            ("queue add /music", QueueAction::Add { path: PathBuf::from("/music"), filters: Default::default() }),
+            ("queue list", QueueAction::List { compact: false, flat: false }),
             ("queue clear", QueueAction::Clear),
             ("queue position", QueueAction::Position),
         ];
@@ -649,10 +1418,12 @@ mod tests {
             match result.unwrap() {
                 Commands::Queue { action } => {
                     match (&action, &expected_action) {
-                        (QueueAction::Add { path: p1 }, QueueAction::Add { path: p2 }) => {
-                            assert_eq!(p1, p2);
+                        (QueueAction::Add { path: p1, filters: f1 }, QueueAction::Add { path: p2, filters: f2 }) => {
+                            assert_eq!((p1, f1), (p2, f2));
+                        }
+                        (QueueAction::List { compact: c1, flat: f1 }, QueueAction::List { compact: c2, flat: f2 }) => {
+                            assert_eq!((c1, f1), (c2, f2));
                         }
-                        (QueueAction::List, QueueAction::List) => {}
                         (QueueAction::Clear, QueueAction::Clear) => {}
                         (QueueAction::Position, QueueAction::Position) => {}
                         _ => panic!("Action mismatch for: {}", input),
@@ -1,15 +1,161 @@
 use std::path::PathBuf;
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
 use crate::error::ConfigError;
+use crate::audio::channels::{ChannelMode, OutputChannels, MAX_BALANCE};
+use crate::audio::crossfeed::{self, CrossfeedSettings};
+use crate::audio::device::ChannelMap;
+use crate::audio::priority::{self, ThreadPriority};
+use crate::audio::resampler::OVERSAMPLING_FACTORS;
+use crate::audio::silence::SilenceSettings;
+use crate::logging::{LogConfig, LogFormat};
+
+pub mod store;
+pub mod watch;
 
 /// Player configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlayerConfig {
     pub default_volume: f32,
     pub preferred_device: Option<String>,
+    /// Output device buffer size in frames (0 = device default)
     pub buffer_size: usize,
+    /// Fixed device buffer in frames, a power of two from 64 to 65536; overrides `buffer_size`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_buffer_frames: Option<u32>,
+    /// Decoded audio buffered ahead of the output, in milliseconds
+    #[serde(default = "default_ring_buffer_ms")]
+    pub ring_buffer_ms: u64,
+    /// Buffer level the decoder refills to, in milliseconds
+    #[serde(default = "default_target_buffer_ms")]
+    pub target_buffer_ms: u64,
+    /// Silence played after repeated underruns so the decoder can refill, in milliseconds (0 = off)
+    #[serde(default = "default_underrun_pause_ms")]
+    pub underrun_pause_ms: u64,
+    /// Audio buffered before a track starts playing, in milliseconds (0 = start at once)
+    #[serde(default = "default_prebuffer_ms")]
+    pub prebuffer_ms: u64,
+    /// Abandon a file when decoding a single packet takes longer than this, in milliseconds (0 = off)
+    #[serde(default = "default_decode_timeout_ms")]
+    pub decode_timeout_ms: u64,
+    /// Stop skipping unplayable tracks after this many in a row
+    #[serde(default = "default_max_consecutive_failures")]
+    pub max_consecutive_failures: u32,
+    /// Seconds after which a recovery attempt no longer counts towards the attempt limit
+    #[serde(default = "default_recovery_cooldown_secs")]
+    pub recovery_cooldown_secs: u64,
+    /// Fade to silence on `stop`, in milliseconds (0 = stop immediately)
+    #[serde(default = "default_fade_out_ms")]
+    pub fade_out_ms: u64,
+    /// Fade in when playback starts, in milliseconds (0 = off)
+    #[serde(default)]
+    pub fade_in_ms: u64,
+    /// Cut silence at the start of tracks and end them early on silence at the end
+    #[serde(default)]
+    pub skip_silence: bool,
+    /// Level below which `skip_silence` counts audio as silent, in dBFS
+    #[serde(default = "default_silence_threshold_db")]
+    pub silence_threshold_db: f32,
+    /// Leading silence played before the rest of it is cut, in milliseconds
+    #[serde(default = "default_silence_leading_ms")]
+    pub silence_leading_ms: u64,
+    /// Silence near the end of a track that starts the next one early, in milliseconds
+    #[serde(default = "default_silence_trailing_ms")]
+    pub silence_trailing_ms: u64,
+    /// How left and right reach the output: stereo, mono or swap
+    #[serde(default)]
+    pub output_mode: ChannelMode,
+    /// Output balance from -100 (left only) to 100 (right only)
+    #[serde(default)]
+    pub balance: i8,
+    /// Speaker order of a multichannel output, when it isn't the host's usual one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_layout: Option<ChannelMap>,
+    /// Headphone crossfeed on stereo output
+    #[serde(default)]
+    pub crossfeed: bool,
+    /// Level of the crossfed signal in dB below the direct one (3 to 15)
+    #[serde(default = "default_crossfeed_level_db")]
+    pub crossfeed_level_db: f32,
+    /// TPDF-dither output streams narrower than the audio (16-bit devices)
+    #[serde(default, alias = "dither_enabled")]
+    pub dither: bool,
+    /// Suspend the output stream after this long paused or stopped, to let the device
+    /// power down (0 = keep it running)
+    #[serde(default)]
+    pub suspend_output_after_secs: u64,
+    /// Scheduling and stack of the dedicated decoder thread
+    #[serde(default)]
+    pub decoder_thread: DecoderThreadConfig,
+    /// CPU cores the audio output thread is pinned to (none = wherever the OS schedules it)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_affinity: Option<Vec<usize>>,
     pub enable_gapless: bool,
+    /// Rebuild the output stream at each gapless track's own rate, taking a gap, instead of
+    /// resampling tracks at other rates into the running stream
+    #[serde(default)]
+    pub prefer_native_rate_switch: bool,
+    /// Upsample tracks by this whole factor (2, 4 or 8) when the device reaches the multiplied rate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oversampling_factor: Option<u32>,
     pub playlist_directory: PathBuf,
+    /// Switch output to a newly connected device automatically
+    #[serde(default)]
+    pub auto_switch_to_new_device: bool,
+    /// Show the spectrum analyzer in `watch` by default
+    #[serde(default)]
+    pub show_spectrum: bool,
+    /// Request exclusive device access for bit-perfect output
+    #[serde(default)]
+    pub exclusive_mode: bool,
+    /// Persistent playback event log (empty = file logging off)
+    #[serde(default = "default_log_file")]
+    pub log_file: Option<PathBuf>,
+    /// Event log line format: "text" or "json"
+    #[serde(default)]
+    pub log_format: LogFormat,
+    /// Rotate the event log once it grows past this many bytes (0 = never)
+    #[serde(default = "default_log_max_bytes")]
+    pub log_max_bytes: u64,
+    /// Diagnostic log of the player's warnings and errors (empty = off)
+    #[serde(default = "default_log_file_path")]
+    pub log_file_path: Option<PathBuf>,
+    /// Rotate the diagnostic log once it grows past this many megabytes (0 = never)
+    #[serde(default = "default_max_log_size_mb")]
+    pub max_log_size_mb: u64,
+    /// Rotated diagnostic logs to keep, `player.log.1` being the newest
+    #[serde(default = "default_max_log_files")]
+    pub max_log_files: usize,
+    /// Plays kept in the history (0 = history off)
+    #[serde(default = "default_history_size")]
+    pub history_size: usize,
+    /// Offer to resume the track that was playing when the player was killed
+    #[serde(default)]
+    pub restore_session: bool,
+    /// Address the REST API listens on in `serve` mode
+    #[serde(default = "default_api_address")]
+    pub api_address: String,
+    /// Port the REST API listens on
+    #[serde(default = "default_api_port")]
+    pub api_port: u16,
+    /// Leave out files already in the queue when adding a directory
+    #[serde(default)]
+    pub skip_duplicates: bool,
+    /// Follow symbolic links to files and directories when scanning a directory
+    #[serde(default = "default_follow_symlinks")]
+    pub follow_symlinks: bool,
+    /// Directories holding a file with one of these names are left out of scans, with everything below them
+    #[serde(default = "default_nomusic_files")]
+    pub nomusic_files: Vec<String>,
+    /// Music library smart playlists are built from when no path is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub library_path: Option<PathBuf>,
+    /// Program `art` opens cover images with; `{}` stands for the image path, which is appended otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub art_viewer: Option<String>,
+    /// Directories whose new audio files are queued, managed with `watch-dir`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub watch_dirs: Vec<PathBuf>,
 }
 
 impl Default for PlayerConfig {
@@ -18,80 +164,814 @@ impl Default for PlayerConfig {
             default_volume: 0.8,
             preferred_device: None,
             buffer_size: 4096,
+            output_buffer_frames: None,
+            ring_buffer_ms: default_ring_buffer_ms(),
+            target_buffer_ms: default_target_buffer_ms(),
+            underrun_pause_ms: default_underrun_pause_ms(),
+            prebuffer_ms: default_prebuffer_ms(),
+            decode_timeout_ms: default_decode_timeout_ms(),
+            max_consecutive_failures: default_max_consecutive_failures(),
+            recovery_cooldown_secs: default_recovery_cooldown_secs(),
+            fade_out_ms: default_fade_out_ms(),
+            fade_in_ms: 0,
+            skip_silence: false,
+            silence_threshold_db: default_silence_threshold_db(),
+            silence_leading_ms: default_silence_leading_ms(),
+            silence_trailing_ms: default_silence_trailing_ms(),
+            output_mode: ChannelMode::default(),
+            balance: 0,
+            channel_layout: None,
+            crossfeed: false,
+            crossfeed_level_db: default_crossfeed_level_db(),
+            dither: false,
+            suspend_output_after_secs: 0,
+            decoder_thread: DecoderThreadConfig::default(),
+            cpu_affinity: None,
             enable_gapless: true,
-            playlist_directory: dirs::home_dir()
-                .unwrap_or_else(|| PathBuf::from("."))
-                .join(".config")
-                .join("hires-player")
-                .join("playlists"),
+            prefer_native_rate_switch: false,
+            oversampling_factor: None,
+            playlist_directory: default_playlist_directory(),
+            auto_switch_to_new_device: false,
+            show_spectrum: false,
+            exclusive_mode: false,
+            log_file: default_log_file(),
+            log_format: LogFormat::default(),
+            log_max_bytes: default_log_max_bytes(),
+            log_file_path: default_log_file_path(),
+            max_log_size_mb: default_max_log_size_mb(),
+            max_log_files: default_max_log_files(),
+            history_size: default_history_size(),
+            restore_session: false,
+            api_address: default_api_address(),
+            api_port: default_api_port(),
+            skip_duplicates: false,
+            follow_symlinks: default_follow_symlinks(),
+            nomusic_files: default_nomusic_files(),
+            library_path: None,
+            art_viewer: None,
+            watch_dirs: Vec::new(),
+        }
+    }
+}
+
+/// Per-user directory for the config file, playlists and other player state
+///
+/// `hires-player` under the platform config directory: `~/.config` on Linux,
+/// `~/Library/Application Support` on macOS and `%APPDATA%` on Windows.
+pub fn app_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("hires-player"))
+}
+
+/// `playlists` in the app directory, falling back to the working directory
+pub fn default_playlist_directory() -> PathBuf {
+    app_dir().unwrap_or_else(|| PathBuf::from(".")).join("playlists")
+}
+
+/// Marker files that keep a directory out of library scans: `.nomedia` and `.nomusic`
+pub fn default_nomusic_files() -> Vec<String> {
+    vec![".nomedia".to_string(), ".nomusic".to_string()]
+}
+
+/// Smallest and largest accepted `output_buffer_frames`
+const OUTPUT_BUFFER_FRAMES_RANGE: std::ops::RangeInclusive<u32> = 64..=65536;
+
+/// `[decoder_thread]` table: how the decoder's OS thread is created
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecoderThreadConfig {
+    /// Scheduling priority: normal, high or realtime
+    #[serde(default)]
+    pub priority: ThreadPriority,
+    /// Stack size in bytes
+    #[serde(default = "default_decoder_stack_size")]
+    pub stack_size: usize,
+    /// CPU cores the decoder thread is pinned to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_affinity: Option<Vec<usize>>,
+}
+
+impl DecoderThreadConfig {
+    /// Accepted stack sizes in bytes
+    pub const STACK_SIZE_RANGE: std::ops::RangeInclusive<usize> = 256 * 1024..=64 * 1024 * 1024;
+}
+
+/// Highest CPU index accepted in a `cpu_affinity` list
+const MAX_CPU_INDEX: usize = priority::MAX_AFFINITY_CORES - 1;
+
+impl Default for DecoderThreadConfig {
+    fn default() -> Self {
+        Self {
+            priority: ThreadPriority::default(),
+            stack_size: default_decoder_stack_size(),
+            cpu_affinity: None,
         }
     }
 }
 
+fn default_decoder_stack_size() -> usize {
+    4 * 1024 * 1024
+}
+
+fn default_ring_buffer_ms() -> u64 {
+    1000
+}
+
+fn default_target_buffer_ms() -> u64 {
+    300
+}
+
+fn default_underrun_pause_ms() -> u64 {
+    200
+}
+
+fn default_prebuffer_ms() -> u64 {
+    500
+}
+
+fn default_decode_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_max_consecutive_failures() -> u32 {
+    5
+}
+
+fn default_recovery_cooldown_secs() -> u64 {
+    600
+}
+
+fn default_fade_out_ms() -> u64 {
+    50
+}
+
+fn default_crossfeed_level_db() -> f32 {
+    CrossfeedSettings::DEFAULT_LEVEL_DB
+}
+
+fn default_silence_threshold_db() -> f32 {
+    -60.0
+}
+
+fn default_silence_leading_ms() -> u64 {
+    1000
+}
+
+fn default_silence_trailing_ms() -> u64 {
+    2000
+}
+
+fn default_follow_symlinks() -> bool {
+    true
+}
+
+fn default_log_file() -> Option<PathBuf> {
+    Some(
+        app_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("player.log"),
+    )
+}
+
+fn default_log_max_bytes() -> u64 {
+    5 * 1024 * 1024
+}
+
+fn default_log_file_path() -> Option<PathBuf> {
+    Some(
+        app_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("logs")
+            .join("player.log"),
+    )
+}
+
+fn default_max_log_size_mb() -> u64 {
+    10
+}
+
+fn default_max_log_files() -> usize {
+    5
+}
+
+fn default_history_size() -> usize {
+    1000
+}
+
+fn default_api_address() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_api_port() -> u16 {
+    7878
+}
+
+impl PlayerConfig {
+    /// Event log file, or `None` when file logging is turned off
+    pub fn event_log_path(&self) -> Option<&Path> {
+        self.log_file.as_deref().filter(|path| !path.as_os_str().is_empty())
+    }
+
+    /// Where and how to keep the diagnostic log, or `None` when it is turned off
+    pub fn diagnostic_log(&self) -> Option<LogConfig> {
+        let path = self.log_file_path.as_deref().filter(|path| !path.as_os_str().is_empty())?;
+        Some(LogConfig {
+            path: path.to_path_buf(),
+            max_bytes: self.max_log_size_mb.saturating_mul(1024 * 1024),
+            max_files: self.max_log_files,
+            format: self.log_format,
+        })
+    }
+
+    /// Silence skipping as the decoder applies it
+    pub fn silence_settings(&self) -> SilenceSettings {
+        SilenceSettings {
+            enabled: self.skip_silence,
+            threshold_db: self.silence_threshold_db,
+            min_leading: Duration::from_millis(self.silence_leading_ms),
+            min_trailing: Duration::from_millis(self.silence_trailing_ms),
+        }
+    }
+
+    /// Mono/swap and balance as the output applies them
+    pub fn output_channels(&self) -> OutputChannels {
+        OutputChannels { mode: self.output_mode, balance: self.balance }
+    }
+
+    /// Crossfeed as the output applies it
+    pub fn crossfeed_settings(&self) -> CrossfeedSettings {
+        CrossfeedSettings { enabled: self.crossfeed, level_db: self.crossfeed_level_db }
+    }
+
+    /// Device buffer size to request in frames (0 = device default)
+    pub fn device_buffer_frames(&self) -> u32 {
+        self.output_buffer_frames
+            .unwrap_or_else(|| self.buffer_size.min(u32::MAX as usize) as u32)
+    }
+
+    /// Check an `output_buffer_frames` value: a power of two from 64 to 65536
+    pub fn validate_output_buffer_frames(frames: u32) -> Result<u32, ConfigError> {
+        if OUTPUT_BUFFER_FRAMES_RANGE.contains(&frames) && frames.is_power_of_two() {
+            Ok(frames)
+        } else {
+            Err(ConfigError::InvalidValue {
+                key: "output_buffer_frames".to_string(),
+                value: frames.to_string(),
+                reason: format!(
+                    "must be a power of two between {} and {} frames",
+                    OUTPUT_BUFFER_FRAMES_RANGE.start(),
+                    OUTPUT_BUFFER_FRAMES_RANGE.end()
+                ),
+            })
+        }
+    }
+}
+
+/// Prefix of environment variables that override settings, e.g. `HIRES_PLAYER_DEFAULT_VOLUME`
+pub const ENV_PREFIX: &str = "HIRES_PLAYER_";
+
 /// Configuration manager for loading and saving settings
+///
+/// `HIRES_PLAYER_<KEY>` environment variables override the file when it is loaded. They apply to
+/// the running player only: saving writes the file's own values plus any changes made since.
 pub struct ConfigManager {
+    /// Effective settings: the file with environment overrides applied
     config: PlayerConfig,
+    /// Settings as they are saved to the file
+    file_config: PlayerConfig,
     config_path: PathBuf,
+    /// File contents last read or written, so reloading our own save is a no-op
+    file_contents: Option<String>,
 }
 
 impl ConfigManager {
+    /// Keys accepted by `get_value` and `set_value`, in `config show` order
+    pub const SETTABLE_KEYS: &'static [&'static str] = &[
+        "default_volume",
+        "preferred_device",
+        "buffer_size",
+        "output_buffer_frames",
+        "ring_buffer_ms",
+        "target_buffer_ms",
+        "underrun_pause_ms",
+        "prebuffer_ms",
+        "decode_timeout_ms",
+        "max_consecutive_failures",
+        "recovery_cooldown_secs",
+        "fade_out_ms",
+        "fade_in_ms",
+        "skip_silence",
+        "silence_threshold_db",
+        "silence_leading_ms",
+        "silence_trailing_ms",
+        "output_mode",
+        "balance",
+        "channel_layout",
+        "crossfeed",
+        "crossfeed_level_db",
+        "dither",
+        "suspend_output_after_secs",
+        "decoder_thread.priority",
+        "decoder_thread.stack_size",
+        "decoder_thread.cpu_affinity",
+        "cpu_affinity",
+        "enable_gapless",
+        "prefer_native_rate_switch",
+        "oversampling_factor",
+        "playlist_directory",
+        "auto_switch_to_new_device",
+        "show_spectrum",
+        "exclusive_mode",
+        "log_file",
+        "log_format",
+        "log_max_bytes",
+        "log_file_path",
+        "max_log_size_mb",
+        "max_log_files",
+        "history_size",
+        "restore_session",
+        "api_address",
+        "api_port",
+        "skip_duplicates",
+        "follow_symlinks",
+        "nomusic_files",
+        "library_path",
+        "art_viewer",
+    ];
+
+    /// Short names accepted in place of a full key
+    const KEY_ALIASES: &'static [(&'static str, &'static str)] = &[
+        ("volume", "default_volume"),
+        ("device", "preferred_device"),
+        ("gapless", "enable_gapless"),
+        ("playlist-dir", "playlist_directory"),
+        ("dither_enabled", "dither"),
+    ];
+
     pub fn new() -> Result<Self, ConfigError> {
-        let config_path = Self::get_config_path()?;
-        let config = Self::load_config(&config_path).unwrap_or_default();
-        
-        Ok(Self {
-            config,
+        Ok(Self::with_path(Self::get_config_path()?))
+    }
+
+    /// Settings read from `config_path` rather than the per-user config directory
+    pub fn with_path(config_path: PathBuf) -> Self {
+        let (file_config, file_contents) = Self::load_or_recover(&config_path);
+
+        let mut config = Self {
+            config: PlayerConfig::default(),
+            file_config,
             config_path,
-        })
+            file_contents,
+        };
+        for error in config.apply_environment() {
+            eprintln!("Warning: Ignoring environment override: {}", error.user_message());
+        }
+        config
     }
 
     pub fn get_config(&self) -> &PlayerConfig {
         &self.config
     }
 
+    /// The settings in effect, environment overrides included, written out as `config.toml` would be
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        toml::to_string_pretty(&self.config).map_err(ConfigError::SerializationError)
+    }
+
+    /// Path of `config.toml`
+    pub fn config_path(&self) -> &Path {
+        &self.config_path
+    }
+
     pub fn update_config<F>(&mut self, updater: F) -> Result<(), ConfigError>
     where
-        F: FnOnce(&mut PlayerConfig),
+        F: Fn(&mut PlayerConfig),
     {
         updater(&mut self.config);
+        updater(&mut self.file_config);
         self.save_config()
     }
 
     pub fn set_volume(&mut self, volume: f32) -> Result<(), ConfigError> {
-        self.config.default_volume = volume.clamp(0.0, 1.0);
-        self.save_config()
+        self.update_config(|config| config.default_volume = volume.clamp(0.0, 1.0))
     }
 
     pub fn set_preferred_device(&mut self, device: Option<String>) -> Result<(), ConfigError> {
-        self.config.preferred_device = device;
-        self.save_config()
+        self.update_config(|config| config.preferred_device = device.clone())
     }
 
     pub fn set_buffer_size(&mut self, buffer_size: usize) -> Result<(), ConfigError> {
-        self.config.buffer_size = buffer_size;
-        self.save_config()
+        self.update_config(|config| config.buffer_size = buffer_size)
+    }
+
+    pub fn set_watch_dirs(&mut self, directories: Vec<PathBuf>) -> Result<(), ConfigError> {
+        self.update_config(|config| config.watch_dirs = directories.clone())
+    }
+
+    /// Re-read `config.toml` after it changed on disk, returning the keys whose effective value changed
+    ///
+    /// On a read or parse error the settings in memory are left as they were.
+    pub fn reload(&mut self) -> Result<Vec<&'static str>, ConfigError> {
+        let contents = match std::fs::read_to_string(&self.config_path) {
+            Ok(contents) => contents,
+            // Editors may briefly remove the file while saving
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(ConfigError::IoError(e)),
+        };
+        if self.file_contents.as_deref() == Some(contents.as_str()) {
+            return Ok(Vec::new());
+        }
+        let file_config: PlayerConfig = toml::from_str(&contents).map_err(ConfigError::DeserializationError)?;
+        if let Some(error) = Self::validate_config(&file_config).into_iter().next() {
+            return Err(error);
+        }
+
+        let previous = std::mem::replace(&mut self.file_config, file_config);
+        let previous = std::mem::replace(&mut self.config, previous);
+        self.file_contents = Some(contents);
+        // Overrides were already reported when the player started
+        self.apply_environment();
+
+        Ok(Self::SETTABLE_KEYS.iter()
+            .copied()
+            .filter(|key| Self::value_of(&previous, key).ok() != Self::value_of(&self.config, key).ok())
+            .collect())
+    }
+
+    /// Rebuild the effective settings from the file and the `HIRES_PLAYER_*` environment
+    fn apply_environment(&mut self) -> Vec<ConfigError> {
+        self.config = self.file_config.clone();
+        Self::apply_env_overrides(&mut self.config, std::env::vars_os().filter_map(|(name, value)| {
+            Some((name.into_string().ok()?, value.into_string().ok()?))
+        }))
+    }
+
+    /// Apply `HIRES_PLAYER_<KEY>=<value>` variables to `config`, returning the ones that were rejected
+    ///
+    /// Keys are matched like `config set` keys, so `HIRES_PLAYER_VOLUME` works as well.
+    pub fn apply_env_overrides(config: &mut PlayerConfig, vars: impl IntoIterator<Item = (String, String)>) -> Vec<ConfigError> {
+        vars.into_iter()
+            .filter_map(|(name, value)| {
+                let key = name.strip_prefix(ENV_PREFIX)?.to_lowercase().replace("__", ".");
+                Self::apply_value(config, &key, &value).err()
+            })
+            .collect()
+    }
+
+    /// Full name of a setting, resolving aliases such as `volume` and dashes for underscores
+    pub fn canonical_key(key: &str) -> Result<&'static str, ConfigError> {
+        let normalized = key.to_lowercase();
+        if let Some((_, full)) = Self::KEY_ALIASES.iter().find(|(alias, _)| *alias == normalized) {
+            return Ok(full);
+        }
+        let normalized = normalized.replace('-', "_");
+        Self::SETTABLE_KEYS.iter()
+            .find(|known| **known == normalized)
+            .copied()
+            .ok_or_else(|| ConfigError::UnknownKey { key: key.to_string() })
+    }
+
+    /// Current value of a setting, formatted the way `set_value` accepts it
+    pub fn get_value(&self, key: &str) -> Result<String, ConfigError> {
+        Self::value_of(&self.config, key)
+    }
+
+    fn value_of(config: &PlayerConfig, key: &str) -> Result<String, ConfigError> {
+        let path_or = |path: Option<&Path>, unset: &str| match path {
+            Some(path) if !path.as_os_str().is_empty() => path.display().to_string(),
+            _ => unset.to_string(),
+        };
+        let switch = |on: bool| if on { "on" } else { "off" }.to_string();
+        Ok(match Self::canonical_key(key)? {
+            "default_volume" => config.default_volume.to_string(),
+            "preferred_device" => config.preferred_device.clone().unwrap_or_else(|| "default".to_string()),
+            "buffer_size" => config.buffer_size.to_string(),
+            "output_buffer_frames" => config.output_buffer_frames.map_or_else(|| "default".to_string(), |frames| frames.to_string()),
+            "ring_buffer_ms" => config.ring_buffer_ms.to_string(),
+            "target_buffer_ms" => config.target_buffer_ms.to_string(),
+            "underrun_pause_ms" => config.underrun_pause_ms.to_string(),
+            "prebuffer_ms" => config.prebuffer_ms.to_string(),
+            "decode_timeout_ms" => config.decode_timeout_ms.to_string(),
+            "max_consecutive_failures" => config.max_consecutive_failures.to_string(),
+            "recovery_cooldown_secs" => config.recovery_cooldown_secs.to_string(),
+            "fade_out_ms" => config.fade_out_ms.to_string(),
+            "fade_in_ms" => config.fade_in_ms.to_string(),
+            "skip_silence" => switch(config.skip_silence),
+            "silence_threshold_db" => config.silence_threshold_db.to_string(),
+            "silence_leading_ms" => config.silence_leading_ms.to_string(),
+            "silence_trailing_ms" => config.silence_trailing_ms.to_string(),
+            "output_mode" => config.output_mode.as_str().to_string(),
+            "balance" => config.balance.to_string(),
+            "channel_layout" => config.channel_layout.as_ref().map_or_else(|| "auto".to_string(), ChannelMap::to_string),
+            "crossfeed" => switch(config.crossfeed),
+            "crossfeed_level_db" => config.crossfeed_level_db.to_string(),
+            "dither" => switch(config.dither),
+            "suspend_output_after_secs" => config.suspend_output_after_secs.to_string(),
+            "decoder_thread.priority" => config.decoder_thread.priority.as_str().to_string(),
+            "decoder_thread.stack_size" => config.decoder_thread.stack_size.to_string(),
+            "decoder_thread.cpu_affinity" => Self::cpu_list(&config.decoder_thread.cpu_affinity),
+            "cpu_affinity" => Self::cpu_list(&config.cpu_affinity),
+            "enable_gapless" => switch(config.enable_gapless),
+            "prefer_native_rate_switch" => switch(config.prefer_native_rate_switch),
+            "oversampling_factor" => config.oversampling_factor.map_or_else(|| "off".to_string(), |factor| factor.to_string()),
+            "playlist_directory" => config.playlist_directory.display().to_string(),
+            "auto_switch_to_new_device" => switch(config.auto_switch_to_new_device),
+            "show_spectrum" => switch(config.show_spectrum),
+            "exclusive_mode" => switch(config.exclusive_mode),
+            "log_file" => path_or(config.event_log_path(), "off"),
+            "log_format" => match config.log_format {
+                LogFormat::Text => "text",
+                LogFormat::Json => "json",
+            }.to_string(),
+            "log_max_bytes" => config.log_max_bytes.to_string(),
+            "log_file_path" => path_or(config.log_file_path.as_deref(), "off"),
+            "max_log_size_mb" => config.max_log_size_mb.to_string(),
+            "max_log_files" => config.max_log_files.to_string(),
+            "history_size" => config.history_size.to_string(),
+            "restore_session" => switch(config.restore_session),
+            "api_address" => config.api_address.clone(),
+            "api_port" => config.api_port.to_string(),
+            "skip_duplicates" => switch(config.skip_duplicates),
+            "follow_symlinks" => switch(config.follow_symlinks),
+            "nomusic_files" if config.nomusic_files.is_empty() => "none".to_string(),
+            "nomusic_files" => config.nomusic_files.join(","),
+            "library_path" => path_or(config.library_path.as_deref(), "none"),
+            "art_viewer" => config.art_viewer.clone().unwrap_or_else(|| "none".to_string()),
+            other => unreachable!("'{}' is listed in SETTABLE_KEYS but has no value", other),
+        })
+    }
+
+    /// Set a setting by name, as typed in `config set <key> <value>`
+    ///
+    /// Returns the full key name, so callers can apply the change without matching aliases.
+    pub fn set_value(&mut self, key: &str, value: &str) -> Result<&'static str, ConfigError> {
+        let mut config = self.config.clone();
+        let mut file_config = self.file_config.clone();
+        let key = Self::apply_value(&mut config, key, value)?;
+        Self::apply_value(&mut file_config, key, value)?;
+        self.config = config;
+        self.file_config = file_config;
+        self.save_config()?;
+        Ok(key)
+    }
+
+    /// Parse and store one setting in `config`
+    fn apply_value(config: &mut PlayerConfig, key: &str, value: &str) -> Result<&'static str, ConfigError> {
+        let key = Self::canonical_key(key)?;
+        match key {
+            "default_volume" => {
+                let volume = match value.strip_suffix('%') {
+                    Some(percent) => Self::parse_number::<f32>(key, percent, 0.0..=100.0, "a level from 0 to 100%")? / 100.0,
+                    None => Self::parse_number(key, value, 0.0..=1.0, "a level from 0.0 to 1.0, or a percentage")?,
+                };
+                config.default_volume = volume;
+            }
+            "preferred_device" => {
+                config.preferred_device = match value {
+                    "" | "default" | "none" => None,
+                    _ => Some(value.to_string()),
+                };
+            }
+            "buffer_size" => config.buffer_size = Self::parse_number(key, value, 0..=65536, "a number of frames up to 65536 (0 = device default)")?,
+            "output_buffer_frames" => {
+                let frames = match value {
+                    "default" | "none" => None,
+                    _ => {
+                        let frames = value.parse().map_err(|_| ConfigError::InvalidValue {
+                            key: key.to_string(),
+                            value: value.to_string(),
+                            reason: "expected a number of frames or 'default'".to_string(),
+                        })?;
+                        Some(PlayerConfig::validate_output_buffer_frames(frames)?)
+                    }
+                };
+                config.output_buffer_frames = frames;
+            }
+            "ring_buffer_ms" => {
+                let ring_buffer_ms = Self::parse_number(key, value, 100..=10_000, "milliseconds from 100 to 10000")?;
+                config.target_buffer_ms = config.target_buffer_ms.min(ring_buffer_ms);
+                config.ring_buffer_ms = ring_buffer_ms;
+            }
+            "target_buffer_ms" => {
+                let reason = format!("milliseconds from 1 to ring_buffer_ms ({})", config.ring_buffer_ms);
+                config.target_buffer_ms = Self::parse_number(key, value, 1..=config.ring_buffer_ms, &reason)?;
+            }
+            "underrun_pause_ms" => config.underrun_pause_ms = Self::parse_number(key, value, 0..=5_000, "milliseconds up to 5000 (0 = off)")?,
+            "prebuffer_ms" => config.prebuffer_ms = Self::parse_number(key, value, 0..=5_000, "milliseconds up to 5000 (0 = off)")?,
+            "decode_timeout_ms" => config.decode_timeout_ms = Self::parse_number(key, value, 0..=u64::MAX, "milliseconds (0 = off)")?,
+            "max_consecutive_failures" => config.max_consecutive_failures = Self::parse_number(key, value, 1..=1_000, "a number of tracks from 1 to 1000")?,
+            "recovery_cooldown_secs" => config.recovery_cooldown_secs = Self::parse_number(key, value, 1..=86_400, "seconds from 1 to 86400")?,
+            "fade_out_ms" => config.fade_out_ms = Self::parse_number(key, value, 0..=5_000, "milliseconds up to 5000 (0 = off)")?,
+            "fade_in_ms" => config.fade_in_ms = Self::parse_number(key, value, 0..=5_000, "milliseconds up to 5000 (0 = off)")?,
+            "skip_silence" => config.skip_silence = Self::parse_switch(key, value)?,
+            "silence_threshold_db" => config.silence_threshold_db = Self::parse_number(key, value, -120.0..=0.0, "a level in dBFS from -120 to 0")?,
+            "silence_leading_ms" => config.silence_leading_ms = Self::parse_number(key, value, 0..=10_000, "milliseconds up to 10000")?,
+            "silence_trailing_ms" => config.silence_trailing_ms = Self::parse_number(key, value, 0..=10_000, "milliseconds up to 10000")?,
+            "output_mode" => {
+                config.output_mode = value.parse().map_err(|_| ConfigError::InvalidValue {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                    reason: "expected stereo, mono or swap".to_string(),
+                })?;
+            }
+            "balance" => config.balance = Self::parse_number(key, value, -MAX_BALANCE..=MAX_BALANCE, "a balance from -100 (left) to 100 (right)")?,
+            "channel_layout" => {
+                config.channel_layout = match value {
+                    "" | "auto" | "none" => None,
+                    _ => Some(value.parse().map_err(|reason| ConfigError::InvalidValue {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                        reason,
+                    })?),
+                };
+            }
+            "crossfeed" => config.crossfeed = Self::parse_switch(key, value)?,
+            "dither" => config.dither = Self::parse_switch(key, value)?,
+            "suspend_output_after_secs" => config.suspend_output_after_secs = Self::parse_number(key, value, 0..=3_600, "seconds up to 3600 (0 = never)")?,
+            "crossfeed_level_db" => config.crossfeed_level_db = Self::parse_number(key, value, crossfeed::LEVEL_RANGE, "a level in dB from 3 to 15")?,
+            "decoder_thread.priority" => {
+                config.decoder_thread.priority = value.parse().map_err(|_| ConfigError::InvalidValue {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                    reason: "expected normal, high or realtime".to_string(),
+                })?;
+            }
+            "decoder_thread.stack_size" => {
+                config.decoder_thread.stack_size = Self::parse_number(
+                    key,
+                    value,
+                    DecoderThreadConfig::STACK_SIZE_RANGE,
+                    "a size in bytes from 262144 (256 KiB) to 67108864 (64 MiB)",
+                )?;
+            }
+            "decoder_thread.cpu_affinity" => config.decoder_thread.cpu_affinity = Self::parse_cpu_list(key, value)?,
+            "cpu_affinity" => config.cpu_affinity = Self::parse_cpu_list(key, value)?,
+            "enable_gapless" => config.enable_gapless = Self::parse_switch(key, value)?,
+            "prefer_native_rate_switch" => config.prefer_native_rate_switch = Self::parse_switch(key, value)?,
+            "oversampling_factor" => {
+                config.oversampling_factor = match value {
+                    "off" | "none" | "1" => None,
+                    _ => Some(value.parse().ok().filter(|factor| OVERSAMPLING_FACTORS.contains(factor)).ok_or_else(|| {
+                        ConfigError::InvalidValue {
+                            key: key.to_string(),
+                            value: value.to_string(),
+                            reason: "expected 2, 4, 8 or off".to_string(),
+                        }
+                    })?),
+                };
+            }
+            "auto_switch_to_new_device" => config.auto_switch_to_new_device = Self::parse_switch(key, value)?,
+            "show_spectrum" => config.show_spectrum = Self::parse_switch(key, value)?,
+            "exclusive_mode" => config.exclusive_mode = Self::parse_switch(key, value)?,
+            "log_file" => {
+                // An empty path rather than None, so the default is not restored on load
+                config.log_file = match value {
+                    "" | "off" | "none" => Some(PathBuf::new()),
+                    _ => Some(PathBuf::from(value)),
+                };
+            }
+            "log_format" => {
+                config.log_format = match value.to_lowercase().as_str() {
+                    "text" => LogFormat::Text,
+                    "json" => LogFormat::Json,
+                    _ => return Err(ConfigError::InvalidValue {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                        reason: "expected text or json".to_string(),
+                    }),
+                };
+            }
+            "log_max_bytes" => config.log_max_bytes = Self::parse_number(key, value, 0..=u64::MAX, "a size in bytes (0 = never rotate)")?,
+            "log_file_path" => {
+                config.log_file_path = match value {
+                    "" | "off" | "none" => Some(PathBuf::new()),
+                    _ => Some(PathBuf::from(value)),
+                };
+            }
+            "max_log_size_mb" => config.max_log_size_mb = Self::parse_number(key, value, 0..=1024 * 1024, "a size in megabytes (0 = never rotate)")?,
+            "max_log_files" => config.max_log_files = Self::parse_number(key, value, 0..=100, "a number of rotated files (0-100)")?,
+            "history_size" => config.history_size = Self::parse_number(key, value, 0..=usize::MAX, "a number of plays (0 = history off)")?,
+            "restore_session" => config.restore_session = Self::parse_switch(key, value)?,
+            "api_address" => {
+                if value.parse::<std::net::IpAddr>().is_err() && value != "localhost" {
+                    return Err(ConfigError::InvalidValue {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                        reason: "expected an IP address such as 127.0.0.1".to_string(),
+                    });
+                }
+                config.api_address = value.to_string();
+            }
+            "api_port" => config.api_port = Self::parse_number(key, value, 1..=u16::MAX, "a port from 1 to 65535")?,
+            "skip_duplicates" => config.skip_duplicates = Self::parse_switch(key, value)?,
+            "follow_symlinks" => config.follow_symlinks = Self::parse_switch(key, value)?,
+            "nomusic_files" => {
+                let names: Vec<String> = match value.trim() {
+                    "none" | "" => Vec::new(),
+                    list => list.split(',').map(|name| name.trim().to_string()).collect(),
+                };
+                if names.iter().any(|name| name.is_empty() || name.contains(['/', '\\'])) {
+                    return Err(ConfigError::InvalidValue {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                        reason: "expected comma-separated file names such as .nomedia,.nomusic, or none".to_string(),
+                    });
+                }
+                config.nomusic_files = names;
+            }
+            "playlist_directory" => {
+                if value.is_empty() {
+                    return Err(ConfigError::InvalidValue {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                        reason: "expected a directory".to_string(),
+                    });
+                }
+                config.playlist_directory = PathBuf::from(value);
+            }
+            "library_path" => {
+                config.library_path = match value {
+                    "none" | "" => None,
+                    _ => Some(PathBuf::from(value)),
+                };
+            }
+            "art_viewer" => {
+                config.art_viewer = match value.trim() {
+                    "none" | "" => None,
+                    command => Some(command.to_string()),
+                };
+            }
+            other => unreachable!("'{}' is listed in SETTABLE_KEYS but cannot be set", other),
+        }
+        Ok(key)
+    }
+
+    /// Parse a number within `range`
+    fn parse_number<T>(key: &str, value: &str, range: std::ops::RangeInclusive<T>, expected: &str) -> Result<T, ConfigError>
+    where
+        T: std::str::FromStr + PartialOrd,
+    {
+        value.trim().parse().ok()
+            .filter(|number| range.contains(number))
+            .ok_or_else(|| ConfigError::InvalidValue {
+                key: key.to_string(),
+                value: value.to_string(),
+                reason: format!("expected {}", expected),
+            })
+    }
+
+    /// Parse comma-separated CPU indices such as `0,1`; `none` lets the OS place the thread
+    fn parse_cpu_list(key: &str, value: &str) -> Result<Option<Vec<usize>>, ConfigError> {
+        if matches!(value.trim(), "" | "none" | "off") {
+            return Ok(None);
+        }
+        let mut cores = value.split(',')
+            .map(|core| core.trim().parse().ok().filter(|core| *core <= MAX_CPU_INDEX))
+            .collect::<Option<Vec<usize>>>()
+            .ok_or_else(|| ConfigError::InvalidValue {
+                key: key.to_string(),
+                value: value.to_string(),
+                reason: format!("expected comma-separated CPU numbers from 0 to {}, or none", MAX_CPU_INDEX),
+            })?;
+        cores.sort_unstable();
+        cores.dedup();
+        Ok(Some(cores))
+    }
+
+    fn cpu_list(cores: &Option<Vec<usize>>) -> String {
+        match cores {
+            Some(cores) => cores.iter().map(|core| core.to_string()).collect::<Vec<_>>().join(","),
+            None => "none".to_string(),
+        }
+    }
+
+    /// Parse an on/off setting
+    fn parse_switch(key: &str, value: &str) -> Result<bool, ConfigError> {
+        match value.to_lowercase().as_str() {
+            "on" | "true" | "yes" | "1" => Ok(true),
+            "off" | "false" | "no" | "0" => Ok(false),
+            _ => Err(ConfigError::InvalidValue {
+                key: key.to_string(),
+                value: value.to_string(),
+                reason: "expected on or off".to_string(),
+            }),
+        }
     }
 
     pub fn set_gapless_enabled(&mut self, enabled: bool) -> Result<(), ConfigError> {
-        self.config.enable_gapless = enabled;
-        self.save_config()
+        self.update_config(|config| config.enable_gapless = enabled)
     }
 
     pub fn set_playlist_directory(&mut self, directory: PathBuf) -> Result<(), ConfigError> {
-        self.config.playlist_directory = directory;
-        self.save_config()
+        self.update_config(|config| config.playlist_directory = directory.clone())
     }
 
+    /// Restore the defaults; environment overrides still apply on top
     pub fn reset_to_defaults(&mut self) -> Result<(), ConfigError> {
-        self.config = PlayerConfig::default();
+        self.file_config = PlayerConfig::default();
+        self.apply_environment();
         self.save_config()
     }
 
     fn get_config_path() -> Result<PathBuf, ConfigError> {
-        let config_dir = dirs::home_dir()
-            .ok_or(ConfigError::ConfigDirNotFound)?
-            .join(".config")
-            .join("hires-player");
+        let config_dir = app_dir().ok_or(ConfigError::ConfigDirNotFound)?;
         
         std::fs::create_dir_all(&config_dir)
             .map_err(|e| ConfigError::IoError(e))?;
@@ -99,6 +979,118 @@ impl ConfigManager {
         Ok(config_dir.join("config.toml"))
     }
 
+    /// Read the config file at startup
+    ///
+    /// A missing file gives the defaults. A file that doesn't parse is copied to `config.toml.bad`
+    /// and the defaults are used; invalid values in a file that does are replaced by their defaults.
+    /// Problems are printed as warnings. Also returns the contents that were read.
+    fn load_or_recover(path: &Path) -> (PlayerConfig, Option<String>) {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return (PlayerConfig::default(), None),
+            Err(e) => {
+                eprintln!("Warning: {}; using default settings", ConfigError::IoError(e).user_message());
+                return (PlayerConfig::default(), None);
+            }
+        };
+
+        let mut config: PlayerConfig = match toml::from_str(&contents) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Warning: {}", ConfigError::DeserializationError(e).user_message());
+                let mut backup = path.as_os_str().to_os_string();
+                backup.push(".bad");
+                let backup = PathBuf::from(backup);
+                match std::fs::copy(path, &backup) {
+                    Ok(_) => eprintln!("Warning: Using default settings; the file was copied to {}", backup.display()),
+                    Err(e) => eprintln!("Warning: Using default settings; could not copy the file to {}: {}", backup.display(), e),
+                }
+                return (PlayerConfig::default(), Some(contents));
+            }
+        };
+
+        let defaults = PlayerConfig::default();
+        for error in Self::validate_config(&config) {
+            eprintln!("Warning: {}; using the default", error.user_message());
+            if let ConfigError::InvalidSetting { key, .. } = &error {
+                // Buffer targets past the ring buffer are clamped by the engine if the default doesn't fit either
+                let _ = Self::value_of(&defaults, key).and_then(|value| Self::apply_value(&mut config, key, &value));
+            }
+        }
+        (config, Some(contents))
+    }
+
+    /// Check every setting in `config`, as `config validate` reports them
+    ///
+    /// Values must be ones `config set` would accept, and the playlist directory must exist or be creatable.
+    pub fn validate_config(config: &PlayerConfig) -> Vec<ConfigError> {
+        let mut errors: Vec<ConfigError> = Self::SETTABLE_KEYS.iter()
+            .filter_map(|key| {
+                let value = Self::value_of(config, key).ok()?;
+                match Self::apply_value(&mut config.clone(), key, &value) {
+                    Err(ConfigError::InvalidValue { key, value, reason }) => Some(ConfigError::InvalidSetting { key, value, reason }),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        for (key, cores) in [("cpu_affinity", &config.cpu_affinity), ("decoder_thread.cpu_affinity", &config.decoder_thread.cpu_affinity)] {
+            let reported = errors.iter().any(|error| matches!(error, ConfigError::InvalidSetting { key: reported, .. } if reported == key));
+            if let (Some(Err(reason)), false) = (cores.as_deref().map(Self::check_cpu_affinity), reported) {
+                errors.push(ConfigError::InvalidSetting { key: key.to_string(), value: Self::cpu_list(cores), reason });
+            }
+        }
+
+        if let Err(reason) = Self::check_directory_creatable(&config.playlist_directory) {
+            errors.push(ConfigError::InvalidSetting {
+                key: "playlist_directory".to_string(),
+                value: config.playlist_directory.display().to_string(),
+                reason,
+            });
+        }
+        errors
+    }
+
+    /// Problems with the config file on disk; a missing file has none
+    pub fn validate_file(&self) -> Vec<ConfigError> {
+        match Self::load_config(&self.config_path) {
+            Ok(config) => Self::validate_config(&config),
+            Err(e) => vec![e],
+        }
+    }
+
+    /// Whether every core in a `cpu_affinity` list exists on this machine
+    fn check_cpu_affinity(cores: &[usize]) -> Result<(), String> {
+        if cores.is_empty() {
+            return Err("expected at least one CPU number, or none".to_string());
+        }
+        let missing = priority::missing_cores(cores);
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let count = priority::core_count().min(priority::MAX_AFFINITY_CORES);
+        Err(format!("this machine has CPUs 0 to {}; {} not present", count - 1, Self::cpu_list(&Some(missing))))
+    }
+
+    /// Whether `directory` exists or can be created, without creating it
+    fn check_directory_creatable(directory: &Path) -> Result<(), String> {
+        if directory.as_os_str().is_empty() {
+            // Reported by the value check already
+            return Ok(());
+        }
+        let existing = directory.ancestors()
+            .find(|ancestor| !ancestor.as_os_str().is_empty() && ancestor.exists())
+            .unwrap_or(Path::new("."));
+        let metadata = std::fs::metadata(existing).map_err(|e| format!("cannot access {}: {}", existing.display(), e))?;
+        if !metadata.is_dir() {
+            return Err(format!("{} is not a directory", existing.display()));
+        }
+        if existing != directory && metadata.permissions().readonly() {
+            return Err(format!("cannot create it inside read-only {}", existing.display()));
+        }
+        Ok(())
+    }
+
     fn load_config(path: &Path) -> Result<PlayerConfig, ConfigError> {
         if !path.exists() {
             return Ok(PlayerConfig::default());
@@ -113,18 +1105,19 @@ impl ConfigManager {
         Ok(config)
     }
 
-    fn save_config(&self) -> Result<(), ConfigError> {
+    fn save_config(&mut self) -> Result<(), ConfigError> {
         // Ensure the parent directory exists
         if let Some(parent) = self.config_path.parent() {
             std::fs::create_dir_all(parent)
                 .map_err(ConfigError::IoError)?;
         }
 
-        let config_content = toml::to_string_pretty(&self.config)
+        let config_content = toml::to_string_pretty(&self.file_config)
             .map_err(ConfigError::SerializationError)?;
         
-        std::fs::write(&self.config_path, config_content)
+        std::fs::write(&self.config_path, &config_content)
             .map_err(ConfigError::IoError)?;
+        self.file_contents = Some(config_content);
         
         Ok(())
     }
@@ -144,7 +1137,9 @@ mod tests {
         
         let config_manager = ConfigManager {
             config: PlayerConfig::default(),
+            file_config: PlayerConfig::default(),
             config_path,
+            file_contents: None,
         };
         
         (config_manager, temp_dir)
@@ -159,6 +1154,76 @@ mod tests {
         assert_eq!(config.buffer_size, 4096);
         assert_eq!(config.enable_gapless, true);
         assert!(config.playlist_directory.to_string_lossy().contains("hires-player"));
+        assert!(!config.auto_switch_to_new_device);
+        assert_eq!(config.ring_buffer_ms, 1000);
+        assert_eq!(config.target_buffer_ms, 300);
+    }
+
+    #[test]
+    fn test_app_dir_under_platform_config_dir() {
+        let app_dir = app_dir().unwrap();
+        assert_eq!(Some(app_dir.clone()), dirs::config_dir().map(|dir| dir.join("hires-player")));
+        assert_eq!(PlayerConfig::default().playlist_directory, app_dir.join("playlists"));
+
+        #[cfg(target_os = "linux")]
+        if std::env::var_os("XDG_CONFIG_HOME").is_none() {
+            assert_eq!(app_dir, dirs::home_dir().unwrap().join(".config").join("hires-player"));
+        }
+        #[cfg(target_os = "macos")]
+        assert!(app_dir.ends_with("Library/Application Support/hires-player"));
+        #[cfg(windows)]
+        assert!(app_dir.ends_with(r"AppData\Roaming\hires-player"));
+    }
+
+    #[test]
+    fn test_config_without_new_fields_loads() {
+        let toml_string = r#"
+            default_volume = 0.5
+            buffer_size = 4096
+            enable_gapless = true
+            playlist_directory = "/test/playlists"
+        "#;
+
+        let config: PlayerConfig = toml::from_str(toml_string).unwrap();
+        assert_eq!(config.default_volume, 0.5);
+        assert!(!config.auto_switch_to_new_device);
+        let log = config.diagnostic_log().unwrap();
+        assert!(log.path.ends_with("hires-player/logs/player.log"));
+        assert_eq!((log.max_bytes, log.max_files), (10 * 1024 * 1024, 5));
+        assert!(config.log_file.unwrap().ends_with("hires-player/player.log"));
+        assert_eq!(config.log_format, LogFormat::Text);
+        assert_eq!(config.log_max_bytes, 5 * 1024 * 1024);
+        assert_eq!(config.history_size, 1000);
+        assert!(!config.restore_session);
+        assert_eq!(config.api_address, "127.0.0.1");
+        assert_eq!(config.api_port, 7878);
+        assert!(!config.skip_duplicates);
+        assert!(config.follow_symlinks);
+        assert_eq!(config.nomusic_files, [".nomedia", ".nomusic"]);
+        assert_eq!(config.decode_timeout_ms, 10_000);
+        assert_eq!(config.max_consecutive_failures, 5);
+        assert_eq!(config.recovery_cooldown_secs, 600);
+    }
+
+    #[test]
+    fn test_log_settings_parse() {
+        let toml_string = r#"
+            default_volume = 0.5
+            buffer_size = 4096
+            enable_gapless = true
+            playlist_directory = "/test/playlists"
+            log_file = "/var/log/rmusic.log"
+            log_format = "json"
+            log_max_bytes = 0
+        "#;
+
+        let config: PlayerConfig = toml::from_str(toml_string).unwrap();
+        assert_eq!(config.log_file, Some(PathBuf::from("/var/log/rmusic.log")));
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(config.log_max_bytes, 0);
+
+        let disabled: PlayerConfig = toml::from_str(&toml_string.replace("/var/log/rmusic.log", "")).unwrap();
+        assert_eq!(disabled.event_log_path(), None);
     }
 
     #[test]
@@ -169,6 +1234,7 @@ mod tests {
             buffer_size: 8192,
             enable_gapless: false,
             playlist_directory: PathBuf::from("/test/playlists"),
+            ..PlayerConfig::default()
         };
 
         let serialized = toml::to_string(&config).unwrap();
@@ -186,9 +1252,9 @@ mod tests {
         let (mut config_manager, _temp_dir) = create_test_config_manager();
         
         // Modify config
-        config_manager.config.default_volume = 0.6;
-        config_manager.config.preferred_device = Some("Test Device".to_string());
-        config_manager.config.buffer_size = 2048;
+        config_manager.file_config.default_volume = 0.6;
+        config_manager.file_config.preferred_device = Some("Test Device".to_string());
+        config_manager.file_config.buffer_size = 2048;
         
         // Save config
         config_manager.save_config().unwrap();
@@ -232,6 +1298,87 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_invalid_file_is_backed_up_and_defaults_used() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        let broken = "default_volume = 0.5\nbuffer_size = 2048 2048\n";
+        fs::write(&config_path, broken).unwrap();
+
+        let (config, contents) = ConfigManager::load_or_recover(&config_path);
+        assert_eq!(config.default_volume, PlayerConfig::default().default_volume);
+        assert_eq!(contents.as_deref(), Some(broken));
+        assert_eq!(fs::read_to_string(temp_dir.path().join("config.toml.bad")).unwrap(), broken);
+
+        let message = ConfigManager::load_config(&config_path).unwrap_err().user_message();
+        assert!(message.contains("line 2"), "{}", message);
+    }
+
+    #[test]
+    fn test_missing_file_uses_defaults_without_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+
+        let (config, contents) = ConfigManager::load_or_recover(&config_path);
+        assert_eq!(config.buffer_size, PlayerConfig::default().buffer_size);
+        assert!(contents.is_none());
+        assert!(!temp_dir.path().join("config.toml.bad").exists());
+    }
+
+    #[test]
+    fn test_validate_reports_each_invalid_setting() {
+        let temp_dir = TempDir::new().unwrap();
+        let blocker = temp_dir.path().join("not-a-directory");
+        fs::write(&blocker, b"").unwrap();
+
+        let mut config = PlayerConfig::default();
+        assert!(ConfigManager::validate_config(&config).is_empty());
+
+        config.default_volume = 1.5;
+        config.buffer_size = 1_000_000;
+        config.playlist_directory = blocker.join("playlists");
+        let messages: Vec<String> = ConfigManager::validate_config(&config).iter().map(ConfigError::user_message).collect();
+        assert_eq!(messages, vec![
+            "default_volume = '1.5' in config.toml is not valid: expected a level from 0.0 to 1.0, or a percentage".to_string(),
+            "buffer_size = '1000000' in config.toml is not valid: expected a number of frames up to 65536 (0 = device default)".to_string(),
+            format!("playlist_directory = '{}' in config.toml is not valid: {} is not a directory", config.playlist_directory.display(), blocker.display()),
+        ]);
+
+        config.default_volume = 0.5;
+        config.buffer_size = 2048;
+        config.playlist_directory = PathBuf::new();
+        let cores = priority::core_count().min(priority::MAX_AFFINITY_CORES);
+        config.cpu_affinity = Some(vec![0, cores]);
+        config.decoder_thread.cpu_affinity = Some(Vec::new());
+        let messages: Vec<String> = ConfigManager::validate_config(&config).iter().map(ConfigError::user_message).collect();
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].starts_with("playlist_directory = '' in config.toml is not valid"));
+        assert_eq!(messages[1], format!("cpu_affinity = '0,{}' in config.toml is not valid: this machine has CPUs 0 to {}; {} not present", cores, cores - 1, cores));
+        assert_eq!(messages[2], "decoder_thread.cpu_affinity = '' in config.toml is not valid: expected at least one CPU number, or none");
+    }
+
+    #[test]
+    fn test_invalid_values_fall_back_to_defaults_on_load() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+        let config = PlayerConfig {
+            default_volume: 3.0,
+            api_port: 0,
+            buffer_size: 1024,
+            ..PlayerConfig::default()
+        };
+        fs::write(config_manager.config_path(), toml::to_string_pretty(&config).unwrap()).unwrap();
+        assert_eq!(config_manager.validate_file().len(), 2);
+
+        let (loaded, _) = ConfigManager::load_or_recover(config_manager.config_path());
+        assert_eq!(loaded.default_volume, PlayerConfig::default().default_volume);
+        assert_eq!(loaded.api_port, PlayerConfig::default().api_port);
+        assert_eq!(loaded.buffer_size, 1024);
+
+        // A running player keeps its settings rather than reloading invalid ones
+        assert!(matches!(config_manager.reload(), Err(ConfigError::InvalidSetting { .. })));
+        assert_eq!(config_manager.get_config().buffer_size, PlayerConfig::default().buffer_size);
+    }
+
     #[test]
     fn test_update_config() {
         let (mut config_manager, _temp_dir) = create_test_config_manager();
@@ -286,6 +1433,301 @@ mod tests {
         assert_eq!(config_manager.config.buffer_size, 8192);
     }
 
+    #[test]
+    fn test_output_buffer_frames_round_trip() {
+        let config = PlayerConfig {
+            output_buffer_frames: Some(256),
+            ..PlayerConfig::default()
+        };
+        let deserialized: PlayerConfig = toml::from_str(&toml::to_string_pretty(&config).unwrap()).unwrap();
+        assert_eq!(deserialized.output_buffer_frames, Some(256));
+        assert_eq!(deserialized.device_buffer_frames(), 256);
+
+        // Unset, the key is left out and buffer_size applies
+        let toml_string = toml::to_string_pretty(&PlayerConfig::default()).unwrap();
+        assert!(!toml_string.contains("output_buffer_frames"));
+        let deserialized: PlayerConfig = toml::from_str(&toml_string).unwrap();
+        assert_eq!(deserialized.output_buffer_frames, None);
+        assert_eq!(deserialized.device_buffer_frames(), 4096);
+    }
+
+    #[test]
+    fn test_set_output_buffer_frames() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+
+        config_manager.set_value("output_buffer_frames", "512").unwrap();
+        let loaded_config = ConfigManager::load_config(&config_manager.config_path).unwrap();
+        assert_eq!(loaded_config.output_buffer_frames, Some(512));
+
+        config_manager.set_value("output_buffer_frames", "default").unwrap();
+        assert_eq!(config_manager.config.output_buffer_frames, None);
+
+        for invalid in ["1000", "32", "131072", "0", "big"] {
+            match config_manager.set_value("output_buffer_frames", invalid) {
+                Err(ConfigError::InvalidValue { key, value, reason }) => {
+                    assert_eq!(key, "output_buffer_frames");
+                    assert_eq!(value, invalid);
+                    assert!(!reason.is_empty());
+                }
+                other => panic!("Expected InvalidValue for {}, got {:?}", invalid, other),
+            }
+        }
+        let err = PlayerConfig::validate_output_buffer_frames(1000).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid value '1000' for output_buffer_frames: must be a power of two between 64 and 65536 frames"
+        );
+        assert_eq!(config_manager.config.output_buffer_frames, None);
+
+        assert!(matches!(
+            config_manager.set_value("colour", "blue"),
+            Err(ConfigError::UnknownKey { .. })
+        ));
+    }
+
+    #[test]
+    fn test_every_key_round_trips_through_get_and_set() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+
+        for key in ConfigManager::SETTABLE_KEYS {
+            let value = config_manager.get_value(key).unwrap();
+            assert_eq!(config_manager.set_value(key, &value).unwrap(), *key, "setting {} back to '{}'", key, value);
+            assert_eq!(config_manager.get_value(key).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_set_value_validates_and_resolves_aliases() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+
+        assert_eq!(config_manager.set_value("volume", "40%").unwrap(), "default_volume");
+        assert_eq!(config_manager.config.default_volume, 0.4);
+        config_manager.set_value("default-volume", "0.25").unwrap();
+        assert_eq!(config_manager.get_value("volume").unwrap(), "0.25");
+        assert!(matches!(config_manager.set_value("volume", "1.5"), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(config_manager.set_value("volume", "loud"), Err(ConfigError::InvalidValue { .. })));
+        assert_eq!(config_manager.config.default_volume, 0.25);
+
+        config_manager.set_value("gapless", "off").unwrap();
+        assert!(!config_manager.config.enable_gapless);
+        assert_eq!(config_manager.set_value("dither_enabled", "on").unwrap(), "dither");
+        assert!(config_manager.config.dither);
+        config_manager.set_value("device", "USB DAC").unwrap();
+        assert_eq!(config_manager.config.preferred_device.as_deref(), Some("USB DAC"));
+        config_manager.set_value("device", "default").unwrap();
+        assert_eq!(config_manager.config.preferred_device, None);
+
+        config_manager.set_value("ring_buffer_ms", "200").unwrap();
+        assert_eq!(config_manager.config.target_buffer_ms, 200);
+        assert!(matches!(config_manager.set_value("target_buffer_ms", "500"), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(config_manager.set_value("ring_buffer_ms", "50"), Err(ConfigError::InvalidValue { .. })));
+        config_manager.set_value("prebuffer_ms", "0").unwrap();
+        assert_eq!(config_manager.config.prebuffer_ms, 0);
+        assert!(matches!(config_manager.set_value("prebuffer_ms", "6000"), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(config_manager.set_value("api_port", "0"), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(config_manager.set_value("api_address", "not an address"), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(config_manager.set_value("max_consecutive_failures", "0"), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(config_manager.set_value("recovery_cooldown_secs", "0"), Err(ConfigError::InvalidValue { .. })));
+
+        config_manager.set_value("log_format", "JSON").unwrap();
+        assert_eq!(config_manager.config.log_format, LogFormat::Json);
+        config_manager.set_value("log_file", "off").unwrap();
+        assert_eq!(config_manager.config.event_log_path(), None);
+        let loaded_config = ConfigManager::load_config(&config_manager.config_path).unwrap();
+        assert_eq!(loaded_config.event_log_path(), None);
+        assert_eq!(config_manager.get_value("log_file").unwrap(), "off");
+
+        assert!(matches!(config_manager.get_value("colour"), Err(ConfigError::UnknownKey { .. })));
+    }
+
+    #[test]
+    fn test_set_skip_duplicates() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+
+        config_manager.set_value("skip_duplicates", "on").unwrap();
+        let loaded_config = ConfigManager::load_config(&config_manager.config_path).unwrap();
+        assert!(loaded_config.skip_duplicates);
+
+        config_manager.set_value("skip_duplicates", "OFF").unwrap();
+        assert!(!config_manager.config.skip_duplicates);
+        assert!(matches!(
+            config_manager.set_value("skip_duplicates", "sometimes"),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_set_library_path() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+        assert_eq!(config_manager.config.library_path, None);
+
+        config_manager.set_value("library_path", "/srv/music").unwrap();
+        let loaded_config = ConfigManager::load_config(&config_manager.config_path).unwrap();
+        assert_eq!(loaded_config.library_path, Some(PathBuf::from("/srv/music")));
+
+        config_manager.set_value("library_path", "none").unwrap();
+        assert_eq!(config_manager.config.library_path, None);
+    }
+
+    #[test]
+    fn test_set_art_viewer() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+        assert_eq!(config_manager.get_value("art_viewer").unwrap(), "none");
+
+        config_manager.set_value("art_viewer", "feh --scale-down {}").unwrap();
+        let loaded_config = ConfigManager::load_config(&config_manager.config_path).unwrap();
+        assert_eq!(loaded_config.art_viewer.as_deref(), Some("feh --scale-down {}"));
+
+        config_manager.set_value("art_viewer", "none").unwrap();
+        assert_eq!(config_manager.config.art_viewer, None);
+    }
+
+    #[test]
+    fn test_set_nomusic_files() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+        assert_eq!(config_manager.get_value("nomusic_files").unwrap(), ".nomedia,.nomusic");
+
+        config_manager.set_value("nomusic_files", ".nomedia, .skip").unwrap();
+        let loaded_config = ConfigManager::load_config(&config_manager.config_path).unwrap();
+        assert_eq!(loaded_config.nomusic_files, [".nomedia", ".skip"]);
+
+        config_manager.set_value("nomusic_files", "none").unwrap();
+        assert!(config_manager.config.nomusic_files.is_empty());
+        assert_eq!(config_manager.get_value("nomusic_files").unwrap(), "none");
+        assert!(matches!(
+            config_manager.set_value("nomusic_files", "podcasts/.nomedia"),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_set_log_file_path_and_retention() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+        config_manager.set_value("log_file_path", "/var/log/rmusic/player.log").unwrap();
+        config_manager.set_value("max_log_size_mb", "1").unwrap();
+        config_manager.set_value("max_log_files", "3").unwrap();
+        config_manager.set_value("log_format", "json").unwrap();
+        let loaded_config = ConfigManager::load_config(&config_manager.config_path).unwrap();
+        assert_eq!(loaded_config.diagnostic_log(), Some(LogConfig {
+            path: PathBuf::from("/var/log/rmusic/player.log"),
+            max_bytes: 1024 * 1024,
+            max_files: 3,
+            format: LogFormat::Json,
+        }));
+
+        config_manager.set_value("log_file_path", "off").unwrap();
+        assert_eq!(config_manager.config.diagnostic_log(), None);
+        assert_eq!(config_manager.get_value("log_file_path").unwrap(), "off");
+        assert!(config_manager.set_value("max_log_files", "101").is_err());
+    }
+
+    #[test]
+    fn test_set_skip_silence() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+        assert!(!config_manager.config.silence_settings().enabled);
+
+        config_manager.set_value("skip_silence", "on").unwrap();
+        config_manager.set_value("silence_threshold_db", "-50").unwrap();
+        config_manager.set_value("silence_trailing_ms", "3000").unwrap();
+        let loaded_config = ConfigManager::load_config(&config_manager.config_path).unwrap();
+        assert_eq!(loaded_config.silence_settings(), SilenceSettings {
+            enabled: true,
+            threshold_db: -50.0,
+            min_leading: Duration::from_secs(1),
+            min_trailing: Duration::from_secs(3),
+        });
+        assert_eq!(config_manager.get_value("skip_silence").unwrap(), "on");
+        assert!(config_manager.set_value("silence_threshold_db", "6").is_err());
+    }
+
+    #[test]
+    fn test_set_output_mode_and_balance() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+        assert!(config_manager.config.output_channels().is_neutral());
+
+        config_manager.set_value("output_mode", "Mono").unwrap();
+        config_manager.set_value("balance", "-30").unwrap();
+        let loaded_config = ConfigManager::load_config(&config_manager.config_path).unwrap();
+        assert_eq!(loaded_config.output_channels(), OutputChannels { mode: ChannelMode::Mono, balance: -30 });
+        assert_eq!(config_manager.get_value("output_mode").unwrap(), "mono");
+
+        assert!(config_manager.set_value("output_mode", "surround").is_err());
+        assert!(config_manager.set_value("balance", "101").is_err());
+    }
+
+    #[test]
+    fn test_set_channel_layout() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+        assert_eq!(config_manager.get_value("channel_layout").unwrap(), "auto");
+
+        config_manager.set_value("channel_layout", "fl,fr,fc,lfe,sl,sr").unwrap();
+        let loaded_config = ConfigManager::load_config(&config_manager.config_path).unwrap();
+        assert_eq!(loaded_config.channel_layout.map(|layout| layout.channels()), Some(6));
+        assert_eq!(config_manager.get_value("channel_layout").unwrap(), "FL FR FC LFE SL SR");
+
+        match config_manager.set_value("channel_layout", "FL FR FL") {
+            Err(ConfigError::InvalidValue { reason, .. }) => assert_eq!(reason, "FL is listed twice"),
+            other => panic!("expected an invalid value, got {:?}", other),
+        }
+        assert!(config_manager.set_value("channel_layout", "FL RR").is_err());
+        config_manager.set_value("channel_layout", "auto").unwrap();
+        assert!(config_manager.config.channel_layout.is_none());
+    }
+
+    #[test]
+    fn test_set_oversampling_factor() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+        assert_eq!(config_manager.get_value("oversampling_factor").unwrap(), "off");
+
+        config_manager.set_value("oversampling_factor", "4").unwrap();
+        let loaded_config = ConfigManager::load_config(&config_manager.config_path).unwrap();
+        assert_eq!(loaded_config.oversampling_factor, Some(4));
+
+        match config_manager.set_value("oversampling_factor", "3") {
+            Err(ConfigError::InvalidValue { reason, .. }) => assert_eq!(reason, "expected 2, 4, 8 or off"),
+            other => panic!("expected an invalid value, got {:?}", other),
+        }
+        config_manager.set_value("oversampling_factor", "off").unwrap();
+        assert_eq!(config_manager.config.oversampling_factor, None);
+    }
+
+    #[test]
+    fn test_set_crossfeed() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+        assert_eq!(config_manager.config.crossfeed_settings(), CrossfeedSettings::default());
+
+        config_manager.set_value("crossfeed", "on").unwrap();
+        config_manager.set_value("crossfeed_level_db", "9").unwrap();
+        let loaded_config = ConfigManager::load_config(&config_manager.config_path).unwrap();
+        assert_eq!(loaded_config.crossfeed_settings(), CrossfeedSettings { enabled: true, level_db: 9.0 });
+        assert_eq!(config_manager.get_value("crossfeed").unwrap(), "on");
+
+        assert!(config_manager.set_value("crossfeed_level_db", "-6").is_err());
+        assert!(config_manager.set_value("crossfeed_level_db", "20").is_err());
+    }
+
+    #[test]
+    fn test_set_cpu_affinity() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+        assert_eq!(config_manager.get_value("cpu_affinity").unwrap(), "none");
+
+        config_manager.set_value("cpu_affinity", "3, 1,3").unwrap();
+        config_manager.set_value("decoder_thread.cpu_affinity", "2").unwrap();
+        let loaded_config = ConfigManager::load_config(&config_manager.config_path).unwrap();
+        assert_eq!(loaded_config.cpu_affinity, Some(vec![1, 3]));
+        assert_eq!(loaded_config.decoder_thread.cpu_affinity, Some(vec![2]));
+        assert_eq!(config_manager.get_value("cpu_affinity").unwrap(), "1,3");
+
+        config_manager.set_value("cpu_affinity", "none").unwrap();
+        assert_eq!(config_manager.config.cpu_affinity, None);
+        for invalid in ["0,x", "1,,2", "4096"] {
+            assert!(matches!(
+                config_manager.set_value("cpu_affinity", invalid),
+                Err(ConfigError::InvalidValue { .. })
+            ));
+        }
+    }
+
     #[test]
     fn test_set_gapless_enabled() {
         let (mut config_manager, _temp_dir) = create_test_config_manager();
@@ -304,6 +1746,11 @@ mod tests {
         let new_dir = PathBuf::from("/custom/playlist/dir");
         config_manager.set_playlist_directory(new_dir.clone()).unwrap();
         assert_eq!(config_manager.config.playlist_directory, new_dir);
+
+        config_manager.set_value("playlist-dir", "/srv/playlists").unwrap();
+        let loaded_config = ConfigManager::load_config(&config_manager.config_path).unwrap();
+        assert_eq!(loaded_config.playlist_directory, PathBuf::from("/srv/playlists"));
+        assert!(matches!(config_manager.set_value("playlist_directory", ""), Err(ConfigError::InvalidValue { .. })));
     }
 
     #[test]
@@ -329,9 +1776,11 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let nested_path = temp_dir.path().join("nested").join("config").join("config.toml");
         
-        let config_manager = ConfigManager {
+        let mut config_manager = ConfigManager {
             config: PlayerConfig::default(),
+            file_config: PlayerConfig::default(),
             config_path: nested_path.clone(),
+            file_contents: None,
         };
         
         // Save should create the directory structure
@@ -365,6 +1814,7 @@ mod tests {
             buffer_size: 4096,
             enable_gapless: true,
             playlist_directory: PathBuf::from("/Users/test/.config/hires-player/playlists"),
+            ..PlayerConfig::default()
         };
 
         let toml_string = toml::to_string_pretty(&config).unwrap();
@@ -395,7 +1845,9 @@ mod tests {
         // Create second instance with same path
         let config_manager2 = ConfigManager {
             config: ConfigManager::load_config(&config_path).unwrap(),
+            file_config: ConfigManager::load_config(&config_path).unwrap(),
             config_path: config_path.clone(),
+            file_contents: None,
         };
         
         // Verify second instance has the same config
@@ -405,4 +1857,86 @@ mod tests {
         // Keep temp_dir alive
         drop(temp_dir);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_env_overrides_accept_keys_and_aliases() {
+        let mut config = PlayerConfig::default();
+        let vars = [
+            ("HIRES_PLAYER_VOLUME", "0.25"),
+            ("HIRES_PLAYER_RING_BUFFER_MS", "2000"),
+            ("HIRES_PLAYER_GAPLESS", "off"),
+            ("HIRES_PLAYER_BUFFER_SIZE", "lots"),
+            ("HIRES_PLAYER_NO_SUCH_SETTING", "1"),
+            ("OTHER_DEFAULT_VOLUME", "0.9"),
+        ];
+        let errors = ConfigManager::apply_env_overrides(
+            &mut config,
+            vars.iter().map(|(name, value)| (name.to_string(), value.to_string())),
+        );
+
+        assert_eq!(config.default_volume, 0.25);
+        assert_eq!(config.ring_buffer_ms, 2000);
+        assert!(!config.enable_gapless);
+        assert_eq!(config.buffer_size, PlayerConfig::default().buffer_size);
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_decoder_thread_table() {
+        let table: DecoderThreadConfig = toml::from_str("priority = \"high\"\n").unwrap();
+        assert_eq!(table.priority, ThreadPriority::High);
+        assert_eq!(table.stack_size, DecoderThreadConfig::default().stack_size);
+
+        // Config files written before the table existed still load
+        let mut older = toml::Value::try_from(PlayerConfig::default()).unwrap();
+        older.as_table_mut().unwrap().remove("decoder_thread");
+        let config: PlayerConfig = older.try_into().unwrap();
+        assert_eq!(config.decoder_thread, DecoderThreadConfig::default());
+
+        let mut config = PlayerConfig::default();
+        let vars = [
+            ("HIRES_PLAYER_DECODER_THREAD__PRIORITY", "normal"),
+            ("HIRES_PLAYER_DECODER_THREAD__STACK_SIZE", "1024"),
+        ];
+        let errors = ConfigManager::apply_env_overrides(
+            &mut config,
+            vars.iter().map(|(name, value)| (name.to_string(), value.to_string())),
+        );
+        assert_eq!(config.decoder_thread.priority, ThreadPriority::Normal);
+        assert_eq!(errors.len(), 1, "a 1 KiB stack is rejected");
+        assert_eq!(ConfigManager::value_of(&config, "decoder_thread.priority").unwrap(), "normal");
+    }
+
+    #[test]
+    fn test_reload_reports_changed_keys() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+        config_manager.set_volume(0.5).unwrap();
+        // Our own save is not a change
+        assert!(config_manager.reload().unwrap().is_empty());
+
+        let mut edited = config_manager.file_config.clone();
+        edited.default_volume = 0.7;
+        edited.skip_duplicates = !edited.skip_duplicates;
+        fs::write(config_manager.config_path(), toml::to_string_pretty(&edited).unwrap()).unwrap();
+
+        let changed = config_manager.reload().unwrap();
+        assert_eq!(changed, vec!["default_volume", "skip_duplicates"]);
+        assert_eq!(config_manager.get_config().default_volume, 0.7);
+        assert!(config_manager.reload().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reload_keeps_config_when_file_is_invalid() {
+        let (mut config_manager, _temp_dir) = create_test_config_manager();
+        config_manager.set_volume(0.4).unwrap();
+
+        fs::write(config_manager.config_path(), "default_volume = 0.9\nbuffer_size = ").unwrap();
+        assert!(matches!(config_manager.reload(), Err(ConfigError::DeserializationError(_))));
+        assert_eq!(config_manager.get_config().default_volume, 0.4);
+
+        // A file removed mid-save is not an error either
+        fs::remove_file(config_manager.config_path()).unwrap();
+        assert!(config_manager.reload().unwrap().is_empty());
+        assert_eq!(config_manager.get_config().default_volume, 0.4);
+    }
+}
@@ -0,0 +1,77 @@
+/*!
+JSON stores kept next to `config.toml`
+
+Ratings, EQ presets, loudness results and the play history are small files that are read in
+full and replaced as a whole. `replace_file` writes a temporary file beside the target and
+renames it over the target, so a crash mid-save leaves the previous contents in place
+rather than a truncated file.
+*/
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Read the JSON file at `path`, or the default value while it does not exist
+///
+/// A file that does not parse is an error rather than an empty store, so the next save
+/// does not silently replace it.
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> io::Result<T> {
+    match fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content).map_err(io::Error::from),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Save `value` as pretty-printed JSON with `replace_file`
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(value).map_err(io::Error::from)?;
+    replace_file(path, json.as_bytes())
+}
+
+/// Write `content` to `<path>.tmp`, flush it to disk and rename it over `path`
+pub fn replace_file(path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp_name = path.as_os_str().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = PathBuf::from(temp_name);
+
+    let mut temp = File::create(&temp_path)?;
+    temp.write_all(content)?;
+    temp.sync_all()?;
+    drop(temp);
+    fs::rename(&temp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_missing_store_loads_as_default_and_saves_in_place() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested").join("store.json");
+        let mut store: BTreeMap<String, u32> = load_json(&path).unwrap();
+        assert!(store.is_empty());
+
+        store.insert("plays".to_string(), 3);
+        save_json(&path, &store).unwrap();
+        assert_eq!(load_json::<BTreeMap<String, u32>>(&path).unwrap(), store);
+        assert!(!dir.path().join("nested").join("store.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_corrupt_store_is_an_error() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("store.json");
+        fs::write(&path, "{ not json").unwrap();
+        let err = load_json::<BTreeMap<String, u32>>(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
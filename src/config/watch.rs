@@ -0,0 +1,93 @@
+/*!
+Watching `config.toml` for changes made outside the player
+
+Editors often save in several steps (truncate, write, rename), so events are
+collected until the file has been quiet for `ConfigWatcher::QUIET_PERIOD`
+before a reload is reported.
+*/
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Reports when the config file has changed on disk
+pub struct ConfigWatcher {
+    events: Receiver<()>,
+    last_event: Option<Instant>,
+    // Dropping the watcher stops it
+    _watcher: RecommendedWatcher,
+}
+
+impl ConfigWatcher {
+    /// How long the file must be left alone before it is reloaded
+    pub const QUIET_PERIOD: Duration = Duration::from_millis(250);
+
+    /// Watch `config_path`; the directory is watched so replacing the file is noticed too
+    pub fn start(config_path: &Path) -> notify::Result<Self> {
+        let file_name = config_path.file_name().map(|name| name.to_os_string());
+        let directory = config_path.parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("."));
+        let (sender, events) = mpsc::channel();
+
+        let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            let Ok(event) = result else { return };
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            if event.paths.iter().any(|path| path.file_name() == file_name.as_deref()) {
+                let _ = sender.send(());
+            }
+        })?;
+        watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+
+        Ok(Self { events, last_event: None, _watcher: watcher })
+    }
+
+    /// True once per burst of changes, after the file has been quiet for `QUIET_PERIOD`
+    pub fn poll_changed(&mut self) -> bool {
+        if std::iter::from_fn(|| self.events.try_recv().ok()).count() > 0 {
+            self.last_event = Some(Instant::now());
+        }
+        match self.last_event {
+            Some(last) if last.elapsed() >= Self::QUIET_PERIOD => {
+                self.last_event = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Poll until a change is reported or `timeout` passes
+    fn wait_for_change(watcher: &mut ConfigWatcher, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if watcher.poll_changed() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        false
+    }
+
+    #[test]
+    fn test_reports_writes_to_config_file_only() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join("config.toml");
+        std::fs::write(&config_path, "default_volume = 0.5\n").unwrap();
+        let mut watcher = ConfigWatcher::start(&config_path).unwrap();
+
+        std::fs::write(dir.path().join("eq_presets.json"), "{}").unwrap();
+        assert!(!wait_for_change(&mut watcher, Duration::from_millis(600)));
+
+        std::fs::write(&config_path, "default_volume = 0.6\n").unwrap();
+        assert!(wait_for_change(&mut watcher, Duration::from_secs(5)));
+        // The burst is reported once
+        assert!(!wait_for_change(&mut watcher, Duration::from_millis(400)));
+    }
+}
@@ -0,0 +1,1101 @@
+//! `AppController::execute_command` driven against `MockAudioEngine`, without a sound card
+
+use crate::audio::AudioEngineControl;
+use crate::audio::engine::DecoderResponse;
+use crate::audio::mock::{EngineCall, MockAudioEngine};
+use crate::audio::channels::{ChannelMode, OutputChannels};
+use crate::audio::crossfeed::CrossfeedSettings;
+use crate::audio::silence::SilenceSettings;
+use crate::cli::{CliApp, ConsoleOutput};
+use crate::config::ConfigManager;
+use crate::error::{PlayerError, QueueError};
+use crate::queue::QueueManager;
+use crate::{AppController, TickState};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// A controller whose config, playlists, ratings and history all live in a temporary directory
+fn controller() -> (AppController, MockAudioEngine, TempDir) {
+    let dir = TempDir::new().unwrap();
+    let mut config_manager = ConfigManager::with_path(dir.path().join("config.toml"));
+    config_manager.update_config(|config| config.playlist_directory = dir.path().join("playlists")).unwrap();
+    let engine = MockAudioEngine::new();
+    let app = AppController::with_engine(Box::new(engine.clone()), config_manager, dir.path()).unwrap();
+    (app, engine, dir)
+}
+
+/// Parse and run a command as typed at the prompt
+async fn run(app: &mut AppController, input: &str) -> Result<(), PlayerError> {
+    let command = CliApp::parse_command(input).unwrap();
+    app.execute_command(command).await
+}
+
+/// The config file as the next start of the player would read it
+fn saved_config(dir: &TempDir) -> crate::config::PlayerConfig {
+    ConfigManager::with_path(dir.path().join("config.toml")).get_config().clone()
+}
+
+/// A silent 16-bit stereo PCM WAV file lasting `seconds`
+fn write_wav(path: &Path, seconds: u32) -> PathBuf {
+    let sample_rate = 8000u32;
+    crate::audio::test_files::write_wav(path, sample_rate, 2, 16, &vec![0u8; (sample_rate * seconds * 4) as usize]);
+    path.to_path_buf()
+}
+
+/// A file with an audio extension that no decoder can open
+fn write_garbage(path: &Path) -> PathBuf {
+    std::fs::write(path, b"definitely not audio").unwrap();
+    path.to_path_buf()
+}
+
+/// Serve `body` to every request on a local port, as a server without range support would
+fn serve_http(body: Vec<u8>) -> String {
+    use std::io::{BufRead, BufReader, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap_or(0) > 0 && line != "\r\n" {
+                line.clear();
+            }
+            let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: audio/wav\r\nContent-Length: {}\r\n\r\n", body.len());
+            let _ = stream.write_all(&body);
+        }
+    });
+    url
+}
+
+/// Collects the messages the controller prints from the engine poll
+struct RecordingPrinter(Arc<Mutex<Vec<String>>>);
+
+impl rustyline::ExternalPrinter for RecordingPrinter {
+    fn print(&mut self, msg: String) -> rustyline::Result<()> {
+        self.0.lock().unwrap().push(msg.trim_end().to_string());
+        Ok(())
+    }
+}
+
+/// Route the controller's notifications into a list the test can read
+fn capture_console(app: &mut AppController) -> Arc<Mutex<Vec<String>>> {
+    let printed = Arc::new(Mutex::new(Vec::new()));
+    app.console = ConsoleOutput::with_printer(Box::new(RecordingPrinter(Arc::clone(&printed))));
+    printed
+}
+
+#[tokio::test]
+async fn test_play_file_queues_it_and_starts_playback() {
+    let (mut app, engine, dir) = controller();
+    let track = write_wav(&dir.path().join("one.wav"), 2);
+
+    engine.push_decoder_response(DecoderResponse::EndOfFile);
+    run(&mut app, &format!("play {}", track.display())).await.unwrap();
+
+    assert_eq!(app.queue_manager.lock().unwrap().len(), 1);
+    assert_eq!(engine.calls(), vec![EngineCall::StartPlayback { start: Duration::ZERO }]);
+    // Play polls the decoder once so a format change is picked up straight away
+    assert!(app.audio_engine.get_decoder_response().is_none());
+    assert_eq!(app.audio_engine.playback_state(), crate::audio::engine::PlaybackState::Playing);
+}
+
+#[tokio::test]
+async fn test_play_url_streams_it_and_queues_the_url() {
+    let (mut app, engine, dir) = controller();
+    let track = write_wav(&dir.path().join("one.wav"), 1);
+    let url = format!("{}/one.wav", serve_http(std::fs::read(&track).unwrap()));
+
+    run(&mut app, &format!("play {}", url)).await.unwrap();
+
+    let queue = app.queue_manager.lock().unwrap();
+    let entry = queue.current_track().unwrap();
+    assert_eq!(entry.path, PathBuf::from(&url));
+    assert_eq!(entry.display_name(), url);
+    // Known once the stream was opened
+    assert_eq!(entry.duration, Duration::from_secs(1));
+    assert!(queue.unreadable_entries().is_empty());
+    assert_eq!(engine.calls(), vec![EngineCall::StartPlayback { start: Duration::ZERO }]);
+}
+
+#[tokio::test]
+async fn test_play_unreachable_url_fails_with_the_reason() {
+    let (mut app, engine, _dir) = controller();
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+
+    let result = run(&mut app, &format!("play http://127.0.0.1:{}/live.mp3", port)).await;
+
+    match result {
+        Err(PlayerError::Audio(crate::error::AudioError::StreamError(message))) => {
+            assert!(message.contains("could not connect"), "{}", message)
+        }
+        other => panic!("expected a stream error, got {:?}", other),
+    }
+    assert!(engine.calls().is_empty());
+}
+
+#[tokio::test]
+async fn test_stream_title_updates_the_queue_and_is_announced_once() {
+    let (mut app, engine, _dir) = controller();
+    let url = "http://radio.example.com/live";
+    run(&mut app, &format!("queue add {}", url)).await.unwrap();
+    let printed = capture_console(&mut app);
+    let mut ticks = TickState::new();
+
+    for _ in 0..2 {
+        engine.push_decoder_response(DecoderResponse::StreamMetadata { path: PathBuf::from(url), title: "Artist - Song".to_string() });
+        app.poll_engine(&mut ticks).await;
+    }
+
+    let queue = app.queue_manager.lock().unwrap();
+    assert_eq!(queue.current_track().unwrap().display_name(), "Artist - Song");
+    assert_eq!(*printed.lock().unwrap(), vec!["Now playing: Artist - Song".to_string()]);
+}
+
+#[tokio::test]
+async fn test_play_directory_queues_every_track_and_plays_the_first() {
+    let (mut app, engine, dir) = controller();
+    let album = dir.path().join("album");
+    std::fs::create_dir(&album).unwrap();
+    write_wav(&album.join("01.wav"), 1);
+    write_wav(&album.join("02.wav"), 1);
+
+    run(&mut app, &format!("play {}", album.display())).await.unwrap();
+
+    let queue = app.queue_manager.lock().unwrap();
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue.current_track().unwrap().path, album.join("01.wav"));
+    assert_eq!(engine.calls(), vec![EngineCall::StartPlayback { start: Duration::ZERO }]);
+}
+
+#[tokio::test]
+async fn test_play_with_empty_queue_fails_without_touching_engine() {
+    let (mut app, engine, _dir) = controller();
+    let result = run(&mut app, "play").await;
+    assert!(matches!(result, Err(PlayerError::Queue(QueueError::EmptyQueue))));
+    assert!(engine.calls().is_empty());
+}
+
+#[tokio::test]
+async fn test_transport_commands_reach_engine() {
+    let (mut app, engine, _dir) = controller();
+    for input in ["pause", "resume", "stop"] {
+        run(&mut app, input).await.unwrap();
+    }
+    assert_eq!(engine.calls(), vec![EngineCall::Pause, EngineCall::Resume, EngineCall::Stop]);
+}
+
+#[tokio::test]
+async fn test_next_and_prev_load_neighbouring_tracks() {
+    let (mut app, engine, dir) = controller();
+    let first = write_wav(&dir.path().join("a.wav"), 1);
+    let second = write_wav(&dir.path().join("b.wav"), 1);
+    run(&mut app, &format!("queue add {}", first.display())).await.unwrap();
+    run(&mut app, &format!("queue add {}", second.display())).await.unwrap();
+
+    run(&mut app, "next").await.unwrap();
+    run(&mut app, "prev").await.unwrap();
+
+    assert_eq!(engine.calls(), vec![
+        EngineCall::LoadFile { path: second, start: Duration::ZERO },
+        EngineCall::LoadFile { path: first, start: Duration::ZERO },
+    ]);
+}
+
+#[tokio::test]
+async fn test_next_at_end_of_queue_wraps_to_first_track() {
+    let (mut app, engine, dir) = controller();
+
+    // Nothing queued: nothing to load
+    run(&mut app, "next").await.unwrap();
+    assert!(engine.calls().is_empty());
+
+    let only = write_wav(&dir.path().join("only.wav"), 1);
+    run(&mut app, &format!("queue add {}", only.display())).await.unwrap();
+    run(&mut app, "next").await.unwrap();
+    assert_eq!(engine.calls(), vec![EngineCall::LoadFile { path: only, start: Duration::ZERO }]);
+    assert_eq!(app.queue_manager.lock().unwrap().current_index(), 0);
+}
+
+#[tokio::test]
+async fn test_seek_is_checked_against_track_length() {
+    let (mut app, engine, dir) = controller();
+    let track = write_wav(&dir.path().join("short.wav"), 2);
+    run(&mut app, &format!("play {}", track.display())).await.unwrap();
+
+    run(&mut app, "seek 1").await.unwrap();
+    assert!(run(&mut app, "seek 30").await.is_err());
+
+    let seeks: Vec<EngineCall> = engine.calls().into_iter()
+        .filter(|call| matches!(call, EngineCall::Seek(_)))
+        .collect();
+    assert_eq!(seeks, vec![EngineCall::Seek(Duration::from_secs(1))]);
+}
+
+#[tokio::test]
+async fn test_seek_before_the_end_of_the_track() {
+    let (mut app, engine, dir) = controller();
+    let error = run(&mut app, "seek end-30").await.unwrap_err();
+    assert!(matches!(error, PlayerError::Queue(QueueError::EmptyQueue)));
+
+    let track = write_wav(&dir.path().join("three-minutes.wav"), 180);
+    run(&mut app, &format!("play {}", track.display())).await.unwrap();
+    run(&mut app, "seek end-30").await.unwrap();
+    assert_eq!(engine.calls().last(), Some(&EngineCall::Seek(Duration::from_secs(150))));
+}
+
+#[tokio::test]
+async fn test_loop_marks_use_engine_position() {
+    let (mut app, engine, dir) = controller();
+    let track = write_wav(&dir.path().join("loop.wav"), 3);
+
+    // No track: nothing to loop
+    run(&mut app, "loop a").await.unwrap();
+    assert!(engine.calls().is_empty());
+
+    run(&mut app, &format!("play {}", track.display())).await.unwrap();
+    engine.set_position(Duration::from_millis(500));
+    run(&mut app, "loop a").await.unwrap();
+    engine.set_position(Duration::from_millis(1500));
+    run(&mut app, "loop b").await.unwrap();
+    run(&mut app, "loop off").await.unwrap();
+
+    assert_eq!(&engine.calls()[1..], [
+        EngineCall::SetLoop { start: Duration::from_millis(500), end: Duration::from_millis(1500) },
+        EngineCall::ClearLoop,
+    ]);
+}
+
+#[tokio::test]
+async fn test_volume_is_applied_and_persisted_to_config() {
+    let (mut app, engine, dir) = controller();
+    run(&mut app, "volume 40").await.unwrap();
+
+    assert_eq!(engine.calls(), vec![EngineCall::SetVolume(0.4)]);
+    assert_eq!(saved_config(&dir).default_volume, 0.4);
+}
+
+#[tokio::test]
+async fn test_device_set_saves_preference_only_for_known_devices() {
+    let (mut app, engine, dir) = controller();
+    engine.set_devices(&["Mock Output", "USB DAC"]);
+
+    assert!(run(&mut app, "device set Missing").await.is_err());
+    assert_eq!(saved_config(&dir).preferred_device, None);
+
+    run(&mut app, "device set USB DAC").await.unwrap();
+    assert_eq!(app.audio_engine.current_device_name().unwrap().as_deref(), Some("USB DAC"));
+    assert_eq!(saved_config(&dir).preferred_device.as_deref(), Some("USB DAC"));
+
+    run(&mut app, "device list").await.unwrap();
+    assert_eq!(*app.device_names.lock().unwrap(), vec!["Mock Output", "USB DAC"]);
+}
+
+#[tokio::test]
+async fn test_device_exclusive_and_limiter_switches() {
+    let (mut app, engine, dir) = controller();
+    run(&mut app, "device exclusive on").await.unwrap();
+    run(&mut app, "limiter off").await.unwrap();
+
+    assert_eq!(engine.calls(), vec![EngineCall::SetExclusiveMode(true), EngineCall::SetLimiter(false)]);
+    assert!(saved_config(&dir).exclusive_mode);
+}
+
+#[tokio::test]
+async fn test_output_mode_and_balance() {
+    let (mut app, engine, dir) = controller();
+    run(&mut app, "output mono").await.unwrap();
+    run(&mut app, "output balance -20").await.unwrap();
+
+    let mono = OutputChannels { mode: ChannelMode::Mono, balance: 0 };
+    assert_eq!(engine.calls(), vec![
+        EngineCall::SetOutputChannels(mono),
+        EngineCall::SetOutputChannels(OutputChannels { balance: -20, ..mono }),
+    ]);
+    let saved = saved_config(&dir);
+    assert_eq!((saved.output_mode, saved.balance), (ChannelMode::Mono, -20));
+    assert_eq!(app.get_current_status().output_mode, OutputChannels { mode: ChannelMode::Mono, balance: -20 });
+}
+
+#[tokio::test]
+async fn test_crossfeed_switch_and_level() {
+    let (mut app, engine, dir) = controller();
+    run(&mut app, "crossfeed on 9").await.unwrap();
+    run(&mut app, "crossfeed off").await.unwrap();
+
+    let on = CrossfeedSettings { enabled: true, level_db: 9.0 };
+    assert_eq!(engine.calls(), vec![
+        EngineCall::SetCrossfeed(on),
+        EngineCall::SetCrossfeed(CrossfeedSettings { enabled: false, ..on }),
+    ]);
+    let saved = saved_config(&dir);
+    assert_eq!((saved.crossfeed, saved.crossfeed_level_db), (false, 9.0));
+
+    run(&mut app, "crossfeed on").await.unwrap();
+    assert_eq!(app.get_current_status().crossfeed, on);
+}
+
+#[tokio::test]
+async fn test_silence_skipping_switch_and_thresholds() {
+    let (mut app, engine, dir) = controller();
+    run(&mut app, "silence on").await.unwrap();
+    run(&mut app, "config set silence_threshold_db -50").await.unwrap();
+
+    let enabled = SilenceSettings { enabled: true, ..Default::default() };
+    assert_eq!(engine.calls(), vec![
+        EngineCall::SetSilenceSkipping(enabled),
+        EngineCall::SetSilenceSkipping(SilenceSettings { threshold_db: -50.0, ..enabled }),
+    ]);
+    assert!(saved_config(&dir).skip_silence);
+}
+
+#[tokio::test]
+async fn test_oversampling_factor_reaches_engine() {
+    let (mut app, engine, dir) = controller();
+    run(&mut app, "config set oversampling_factor 8").await.unwrap();
+    run(&mut app, "config set oversampling_factor off").await.unwrap();
+
+    assert_eq!(engine.calls(), vec![EngineCall::SetOversampling(Some(8)), EngineCall::SetOversampling(None)]);
+    assert_eq!(saved_config(&dir).oversampling_factor, None);
+    assert!(run(&mut app, "config set oversampling_factor 6").await.is_err());
+}
+
+#[tokio::test]
+async fn test_eq_bands_and_presets() {
+    let (mut app, engine, dir) = controller();
+    run(&mut app, "eq band peak 1000 3").await.unwrap();
+    run(&mut app, "eq save-preset mine").await.unwrap();
+    run(&mut app, "eq clear").await.unwrap();
+    assert!(app.audio_engine.eq_bands().is_empty());
+
+    run(&mut app, "eq load-preset mine").await.unwrap();
+    let bands = app.audio_engine.eq_bands();
+    assert_eq!(bands.len(), 1);
+    assert_eq!(bands[0].frequency_hz, 1000.0);
+    assert!(dir.path().join(crate::audio::eq_presets::EQ_PRESETS_FILE).exists());
+    assert_eq!(engine.calls().len(), 3);
+}
+
+#[tokio::test]
+async fn test_buffer_size_is_applied_and_persisted() {
+    let (mut app, engine, dir) = controller();
+    run(&mut app, "buffer 20ms").await.unwrap();
+
+    let frames = MockAudioEngine::SAMPLE_RATE / 50;
+    assert_eq!(engine.calls(), vec![EngineCall::SetOutputBufferFrames(frames)]);
+    assert_eq!(saved_config(&dir).buffer_size, frames as usize);
+}
+
+#[tokio::test]
+async fn test_config_set_applies_live_settings_only() {
+    let (mut app, engine, dir) = controller();
+    run(&mut app, "config set fade_out_ms 120").await.unwrap();
+    run(&mut app, "config set decoder_thread.priority normal").await.unwrap();
+
+    assert_eq!(engine.calls(), vec![EngineCall::SetFadeDurations {
+        fade_out: Duration::from_millis(120),
+        fade_in: Duration::ZERO,
+    }]);
+    let saved = saved_config(&dir);
+    assert_eq!(saved.fade_out_ms, 120);
+    assert_eq!(saved.decoder_thread.priority, crate::audio::priority::ThreadPriority::Normal);
+}
+
+#[tokio::test]
+async fn test_config_set_dither_reaches_the_engine() {
+    let (mut app, engine, dir) = controller();
+    run(&mut app, "config set dither on").await.unwrap();
+
+    assert_eq!(engine.calls(), vec![EngineCall::SetDither(true)]);
+    assert!(saved_config(&dir).dither);
+    assert!(app.get_current_status().dither);
+}
+
+#[tokio::test]
+async fn test_config_set_suspend_output_after_reaches_the_engine() {
+    let (mut app, engine, dir) = controller();
+    run(&mut app, "config set suspend_output_after_secs 30").await.unwrap();
+    assert!(run(&mut app, "config set suspend_output_after_secs 7200").await.is_err());
+
+    assert_eq!(engine.calls(), vec![EngineCall::SetSuspendOutputAfter(Duration::from_secs(30))]);
+    assert_eq!(saved_config(&dir).suspend_output_after_secs, 30);
+}
+
+#[tokio::test]
+async fn test_config_set_prefer_native_rate_switch_reaches_the_engine() {
+    let (mut app, engine, dir) = controller();
+    run(&mut app, "config set prefer_native_rate_switch on").await.unwrap();
+
+    assert_eq!(engine.calls(), vec![EngineCall::SetPreferNativeRateSwitch(true)]);
+    assert!(saved_config(&dir).prefer_native_rate_switch);
+}
+
+#[tokio::test]
+async fn test_config_set_checks_the_value_and_survives_a_reload() {
+    let (mut app, _engine, dir) = controller();
+    run(&mut app, "config set default_volume 0.6").await.unwrap();
+    assert!(run(&mut app, "config set volume 1.5").await.is_err());
+    run(&mut app, "config list").await.unwrap();
+
+    let saved = saved_config(&dir);
+    assert_eq!(saved.default_volume, 0.6);
+    let listed: crate::config::PlayerConfig = toml::from_str(&app.config_manager.to_toml().unwrap()).unwrap();
+    assert_eq!(listed.default_volume, 0.6);
+    assert_eq!(listed.playlist_directory, saved.playlist_directory);
+}
+
+#[tokio::test]
+async fn test_queue_changes_discard_preloaded_track() {
+    let (mut app, engine, dir) = controller();
+    let track = write_wav(&dir.path().join("dup.wav"), 1);
+    run(&mut app, &format!("queue add {}", track.display())).await.unwrap();
+    run(&mut app, &format!("queue add {}", track.display())).await.unwrap();
+
+    run(&mut app, "queue dedupe").await.unwrap();
+    run(&mut app, "queue sort title").await.unwrap();
+
+    assert_eq!(app.queue_manager.lock().unwrap().len(), 1);
+    assert_eq!(engine.calls(), vec![EngineCall::DiscardPreloadedTrack, EngineCall::DiscardPreloadedTrack]);
+}
+
+#[tokio::test]
+async fn test_rate_and_fav_need_a_current_track() {
+    let (mut app, _engine, dir) = controller();
+    let ratings_path = dir.path().join(crate::queue::ratings::RATINGS_FILE);
+
+    run(&mut app, "rate 4").await.unwrap();
+    assert!(!ratings_path.exists());
+
+    let track = write_wav(&dir.path().join("liked.wav"), 1);
+    run(&mut app, &format!("queue add {}", track.display())).await.unwrap();
+    run(&mut app, "rate 4").await.unwrap();
+    run(&mut app, "fav").await.unwrap();
+
+    assert!(ratings_path.exists());
+    assert_eq!(app.ratings.favorites().unwrap(), vec![track]);
+}
+
+#[tokio::test]
+async fn test_stream_errors_restart_playback_after_a_wait() {
+    let (mut app, engine, dir) = controller();
+    let track = write_wav(&dir.path().join("live.wav"), 10);
+    run(&mut app, &format!("play {}", track.display())).await.unwrap();
+    engine.set_position(Duration::from_secs(4));
+    let mut requests = app.api_requests.take().unwrap();
+
+    let error = PlayerError::Audio(crate::error::AudioError::StreamError("Device disconnected".to_string()));
+    app.handle_error_with_recovery(&error).await;
+    let request = tokio::time::timeout(Duration::from_secs(5), requests.recv()).await.unwrap().unwrap();
+    assert!(matches!(request.command, crate::api::ApiCommand::RetryPlayback));
+
+    app.handle_api_request(request).await;
+    assert_eq!(engine.calls().last(), Some(&EngineCall::LoadFile { path: track, start: Duration::from_secs(4) }));
+
+    // Nothing to restart once stopped
+    run(&mut app, "stop").await.unwrap();
+    let (reply, _) = tokio::sync::oneshot::channel();
+    app.handle_api_request(crate::api::ApiRequest { command: crate::api::ApiCommand::RetryPlayback, reply }).await;
+    assert_eq!(engine.calls().last(), Some(&EngineCall::Stop));
+}
+
+#[tokio::test]
+async fn test_missing_lyrics_are_not_an_error() {
+    let (mut app, _engine, dir) = controller();
+    run(&mut app, "lyrics").await.unwrap();
+
+    let track = write_wav(&dir.path().join("instrumental.wav"), 1);
+    run(&mut app, &format!("queue add {}", track.display())).await.unwrap();
+    run(&mut app, "lyrics").await.unwrap();
+
+    std::fs::write(dir.path().join("instrumental.lrc"), "[00:00.50]Hum").unwrap();
+    run(&mut app, "lyrics").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_art_falls_back_to_a_cover_file_and_opens_the_viewer() {
+    let (mut app, _engine, dir) = controller();
+    let album = dir.path().join("album");
+    std::fs::create_dir(&album).unwrap();
+    let track = write_wav(&album.join("01.wav"), 1);
+    let copied = dir.path().join("shown.jpg");
+    run(&mut app, &format!("config art_viewer cp {{}} {}", copied.display())).await.unwrap();
+
+    // Nothing to show yet: no track, then a track without embedded art or cover file
+    run(&mut app, "art").await.unwrap();
+    run(&mut app, &format!("queue add {}", track.display())).await.unwrap();
+    run(&mut app, "art").await.unwrap();
+    assert!(!copied.exists());
+
+    std::fs::write(album.join("folder.jpg"), b"folder art").unwrap();
+    run(&mut app, "art").await.unwrap();
+    assert_eq!(std::fs::read(&copied).unwrap(), b"folder art");
+
+    // A viewer that fails is reported
+    run(&mut app, "config art_viewer false").await.unwrap();
+    assert!(run(&mut app, "art").await.is_err());
+}
+
+#[tokio::test]
+async fn test_info_takes_a_queue_position_or_a_file() {
+    let (mut app, engine, dir) = controller();
+    let first = write_wav(&dir.path().join("first.wav"), 1);
+    let second = write_wav(&dir.path().join("second.wav"), 1);
+    for track in [&first, &second] {
+        run(&mut app, &format!("queue add {}", track.display())).await.unwrap();
+    }
+
+    run(&mut app, "info").await.unwrap();
+    run(&mut app, "info 2").await.unwrap();
+    run(&mut app, &format!("info --json {}", second.display())).await.unwrap();
+    // Out of range positions are reported, files that do not exist are errors
+    run(&mut app, "info 3").await.unwrap();
+    assert!(run(&mut app, &format!("info {}", dir.path().join("missing.wav").display())).await.is_err());
+    assert!(engine.calls().is_empty());
+}
+
+#[tokio::test]
+async fn test_verify_fails_for_truncated_files_when_run_once() {
+    let (mut app, engine, dir) = controller();
+    let library = dir.path().join("library");
+    std::fs::create_dir(&library).unwrap();
+    let intact = write_wav(&library.join("intact.wav"), 1);
+    let cut = write_wav(&library.join("cut.wav"), 3);
+
+    assert!(matches!(run(&mut app, "verify queue").await, Err(PlayerError::Queue(QueueError::EmptyQueue))));
+    run(&mut app, &format!("verify {}", library.display())).await.unwrap();
+    run(&mut app, "verify cancel").await.unwrap();
+
+    // Chop the file off a second in, as an interrupted copy would
+    let bytes = std::fs::read(&cut).unwrap();
+    std::fs::write(&cut, &bytes[..44 + 8000 * 4]).unwrap();
+    match run(&mut app, &format!("verify {}", library.display())).await {
+        Err(PlayerError::Queue(QueueError::VerificationFailed { failed, checked })) => assert_eq!((failed, checked), (1, 2)),
+        other => panic!("expected a failed verify, got {:?}", other),
+    }
+
+    // Queued files and saved playlists are checked by name
+    run(&mut app, &format!("queue add {}", intact.display())).await.unwrap();
+    run(&mut app, "verify queue").await.unwrap();
+    run(&mut app, "playlist save good").await.unwrap();
+    run(&mut app, "verify good").await.unwrap();
+    assert!(matches!(run(&mut app, "verify no such thing").await, Err(PlayerError::Queue(QueueError::FileNotFound { .. }))));
+    assert!(engine.calls().is_empty());
+}
+
+#[tokio::test]
+async fn test_export_and_status_run_without_engine_changes() {
+    let (mut app, engine, dir) = controller();
+    let output = dir.path().join("queue.csv");
+    run(&mut app, &format!("export csv {}", output.display())).await.unwrap();
+    assert!(!output.exists(), "an empty queue is not exported");
+
+    let track = write_wav(&dir.path().join("export.wav"), 1);
+    run(&mut app, &format!("queue add {}", track.display())).await.unwrap();
+    run(&mut app, &format!("export csv {}", output.display())).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&output).unwrap().lines().count(), 2);
+
+    run(&mut app, "status --json").await.unwrap();
+    run(&mut app, "history").await.unwrap();
+    assert!(engine.calls().is_empty());
+}
+
+#[tokio::test]
+async fn test_play_skips_unplayable_files_and_flags_them() {
+    let (mut app, engine, dir) = controller();
+    let printed = capture_console(&mut app);
+    let broken = write_garbage(&dir.path().join("01 broken.wav"));
+    let good = write_wav(&dir.path().join("02 good.wav"), 1);
+    for path in [&broken, &good] {
+        run(&mut app, &format!("queue add {}", path.display())).await.unwrap();
+    }
+
+    run(&mut app, "play").await.unwrap();
+
+    assert_eq!(engine.calls(), vec![EngineCall::StartPlayback { start: Duration::ZERO }]);
+    let queue = app.queue_manager.lock().unwrap();
+    assert_eq!(queue.current_track().unwrap().path, good);
+    let failed: Vec<bool> = queue.list().iter().map(|track| track.failed).collect();
+    assert_eq!(failed, vec![true, false]);
+    let lines = crate::cli::queue_view::QueueView::flat_lines(&queue.list().iter().cloned().collect::<Vec<_>>(), 1);
+    assert!(lines[0].ends_with("✗ unplayable"), "{}", lines[0]);
+    assert!(!lines[1].contains("unplayable"));
+    assert!(printed.lock().unwrap()[0].starts_with("Skipping unplayable track 01 broken:"));
+}
+
+#[tokio::test]
+async fn test_skipping_gives_up_after_max_consecutive_failures() {
+    let (mut app, engine, dir) = controller();
+    let printed = capture_console(&mut app);
+    run(&mut app, "config set max_consecutive_failures 2").await.unwrap();
+    for name in ["a.wav", "b.wav", "c.wav"] {
+        let path = write_garbage(&dir.path().join(name));
+        run(&mut app, &format!("queue add {}", path.display())).await.unwrap();
+    }
+    let good = write_wav(&dir.path().join("d.wav"), 1);
+    run(&mut app, &format!("queue add {}", good.display())).await.unwrap();
+
+    assert!(matches!(run(&mut app, "play").await, Err(PlayerError::Audio(_))));
+
+    assert!(engine.calls().is_empty());
+    let queue = app.queue_manager.lock().unwrap();
+    assert_eq!(queue.current_index(), 1, "stops on the track that reached the limit");
+    let failed: Vec<bool> = queue.list().iter().map(|track| track.failed).collect();
+    assert_eq!(failed, vec![true, true, false, false]);
+    assert_eq!(printed.lock().unwrap().last().unwrap(), "Stopped after 2 unplayable tracks in a row");
+}
+
+#[tokio::test]
+async fn test_decoder_failures_skip_to_next_track_and_are_summarized() {
+    let (mut app, engine, dir) = controller();
+    let printed = capture_console(&mut app);
+    let first = write_wav(&dir.path().join("1.wav"), 1);
+    let broken = write_garbage(&dir.path().join("2.wav"));
+    let also_broken = write_garbage(&dir.path().join("3.wav"));
+    let last = write_wav(&dir.path().join("4.wav"), 1);
+    for path in [&first, &broken, &also_broken, &last] {
+        run(&mut app, &format!("queue add {}", path.display())).await.unwrap();
+    }
+    run(&mut app, "play").await.unwrap();
+    let mut ticks = TickState::new();
+
+    // The end of the first track moved the queue on and the next file would not open
+    app.queue_manager.lock().unwrap().jump_to(1).unwrap();
+    let error = || crate::error::AudioError::InitializationFailed("WAV decoder error: bad header".to_string());
+    engine.push_decoder_response(DecoderResponse::TrackFailed { path: broken.clone(), error: error() });
+    app.poll_engine(&mut ticks).await;
+    engine.push_decoder_response(DecoderResponse::TrackFailed { path: also_broken.clone(), error: error() });
+    app.poll_engine(&mut ticks).await;
+    engine.push_decoder_response(DecoderResponse::FileLoaded { path: last.clone(), duration: Some(Duration::from_secs(1)), sample_rate: 8000, bit_depth: 16, channels: 2 });
+    app.poll_engine(&mut ticks).await;
+    assert_eq!(app.consecutive_failures, 0);
+    engine.push_decoder_response(DecoderResponse::EndOfFile);
+    app.poll_engine(&mut ticks).await;
+
+    assert_eq!(engine.calls()[1..], [
+        EngineCall::LoadFile { path: also_broken, start: Duration::ZERO },
+        EngineCall::LoadFile { path: last, start: Duration::ZERO },
+    ]);
+    let printed = printed.lock().unwrap();
+    assert_eq!(printed.iter().filter(|line| line.starts_with("Skipping unplayable track")).count(), 2);
+    assert_eq!(printed[printed.len() - 2..], ["Queue finished", "Skipped 2 unplayable tracks"]);
+}
+
+#[tokio::test]
+async fn test_spectrum_snapshot_analyses_latest_decoded_audio() {
+    let (mut app, engine, _dir) = controller();
+    let analyzer = crate::audio::spectrum::SpectrumAnalyzer::snapshot();
+    assert!(app.spectrum_snapshot(&analyzer).is_none());
+    run(&mut app, "spectrum").await.unwrap();
+
+    // Older audio ahead of the newest 2048 frames must not reach the analyzer
+    let rate = MockAudioEngine::SAMPLE_RATE;
+    let mut samples = vec![0.9f32; 4096 * 2];
+    for frame in 0..2048 {
+        let value = (2.0 * std::f32::consts::PI * 1_000.0 * frame as f32 / rate as f32).sin();
+        samples.extend([value, value]);
+    }
+    engine.set_latest_decoded(crate::models::AudioBuffer { frames: samples.len() / 2, samples, channels: 2, sample_rate: rate });
+
+    let (levels, sample_rate) = app.spectrum_snapshot(&analyzer).unwrap();
+    assert_eq!(sample_rate, rate);
+    let loudest = (0..levels.len()).max_by(|&a, &b| levels[a].total_cmp(&levels[b])).unwrap();
+    let edges = analyzer.band_edges(rate);
+    assert!(edges[loudest - 1] <= 1_000.0 && 1_000.0 < edges[loudest]);
+    run(&mut app, "spectrum").await.unwrap();
+    assert!(engine.calls().is_empty());
+}
+
+#[tokio::test]
+async fn test_missing_device_recovers_by_switching_to_default() {
+    let (mut app, engine, _dir) = controller();
+    engine.set_devices(&["Speakers", "USB DAC"]);
+
+    let error = run(&mut app, "device set Headphones").await.unwrap_err();
+    app.handle_error_with_recovery(&error).await;
+
+    assert_eq!(engine.calls(), vec![
+        EngineCall::SetDevice("Headphones".to_string()),
+        EngineCall::RefreshDevices,
+        EngineCall::SetDevice("Speakers".to_string()),
+    ]);
+    let stats = app.error_recovery.get_recovery_statistics();
+    assert_eq!(stats.attempts_by_category.get("audio device"), Some(&1));
+    assert_eq!(stats.successes, 1);
+    run(&mut app, "recovery stats").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tag_set_refreshes_the_queue_and_skips_the_playing_file() {
+    let (mut app, engine, dir) = controller();
+    let track = dir.path().join("song.flac");
+    std::fs::write(&track, crate::queue::tests::flac_with_comments(&["TITLE=Old", "COMMENT=rip"])).unwrap();
+    run(&mut app, &format!("queue add {}", track.display())).await.unwrap();
+
+    run(&mut app, &format!("tag set {} title=\"New Title\" tracknumber=3", track.display())).await.unwrap();
+    run(&mut app, &format!("tag clear {} comment", track.display())).await.unwrap();
+    assert_eq!(crate::queue::tags::read_comments(&track).unwrap(), vec![
+        ("TITLE".to_string(), "New Title".to_string()),
+        ("TRACKNUMBER".to_string(), "3".to_string()),
+    ]);
+    let entry = app.queue_manager.lock().unwrap().list()[0].clone();
+    assert_eq!(entry.metadata.title.as_deref(), Some("New Title"));
+    assert_eq!(entry.metadata.track_number, Some(3));
+    let wav = write_wav(&dir.path().join("song.wav"), 1);
+    assert!(run(&mut app, &format!("tag set {} title=New", wav.display())).await.is_err());
+
+    run(&mut app, "play").await.unwrap();
+    assert_eq!(engine.current_track_path(), Some(track.clone()));
+    let before = std::fs::read(&track).unwrap();
+    assert!(run(&mut app, &format!("tag set {} title=Other", track.display())).await.is_err());
+    assert_eq!(std::fs::read(&track).unwrap(), before);
+
+    run(&mut app, "stop").await.unwrap();
+    run(&mut app, &format!("tag set {} title=Other", track.display())).await.unwrap();
+    assert_eq!(app.queue_manager.lock().unwrap().list()[0].metadata.title.as_deref(), Some("Other"));
+}
+
+#[tokio::test]
+async fn test_watched_directory_files_join_and_leave_the_queue() {
+    use crate::queue::watch::DirectoryChange;
+    let (mut app, engine, dir) = controller();
+    let downloads = dir.path().join("downloads");
+    std::fs::create_dir(&downloads).unwrap();
+    let downloads = downloads.canonicalize().unwrap();
+
+    run(&mut app, &format!("watch-dir add {}", downloads.display())).await.unwrap();
+    run(&mut app, &format!("watch-dir add {}", downloads.display())).await.unwrap();
+    assert_eq!(saved_config(&dir).watch_dirs, vec![downloads.clone()]);
+    assert!(run(&mut app, &format!("watch-dir add {}", downloads.join("missing").display())).await.is_err());
+
+    let first = write_wav(&downloads.join("01.wav"), 1);
+    let second = write_wav(&downloads.join("02.wav"), 1);
+    app.apply_directory_changes(vec![
+        DirectoryChange::Added(first.clone()),
+        DirectoryChange::Added(second.clone()),
+        DirectoryChange::Added(downloads.join("gone.wav")),
+        // Rewritten files are refreshed, not queued again
+        DirectoryChange::Added(first.clone()),
+    ]);
+    let paths = |app: &AppController| app.queue_manager.lock().unwrap().list().iter().map(|track| track.path.clone()).collect::<Vec<_>>();
+    assert_eq!(paths(&app), vec![first.clone(), second.clone()]);
+
+    // The playing file stays queued when it is deleted
+    run(&mut app, "play").await.unwrap();
+    assert_eq!(engine.current_track_path(), Some(first.clone()));
+    app.apply_directory_changes(vec![DirectoryChange::Removed(first.clone()), DirectoryChange::Removed(second.clone())]);
+    assert_eq!(paths(&app), vec![first.clone()]);
+    run(&mut app, "stop").await.unwrap();
+    app.apply_directory_changes(vec![DirectoryChange::Removed(downloads.clone())]);
+    assert!(paths(&app).is_empty());
+
+    run(&mut app, &format!("watch-dir remove {}", downloads.display())).await.unwrap();
+    run(&mut app, "watch-dir list").await.unwrap();
+    assert!(saved_config(&dir).watch_dirs.is_empty());
+    run(&mut app, &format!("watch-dir remove {}", downloads.display())).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_device_default_clears_the_preference() {
+    let (mut app, engine, dir) = controller();
+    engine.set_devices(&["Speakers", "USB DAC"]);
+    run(&mut app, "device set USB DAC").await.unwrap();
+    assert_eq!(saved_config(&dir).preferred_device.as_deref(), Some("USB DAC"));
+
+    run(&mut app, "device default").await.unwrap();
+
+    assert_eq!(saved_config(&dir).preferred_device, None);
+    let default = app.audio_engine.default_device_name();
+    assert_eq!(default.as_deref(), Some("Speakers"));
+    assert_eq!(app.audio_engine.current_device_name().unwrap(), default);
+}
+
+#[tokio::test]
+async fn test_device_reset_restarts_the_current_device() {
+    let (mut app, engine, dir) = controller();
+    let track = write_wav(&dir.path().join("one.wav"), 1);
+    run(&mut app, &format!("play {}", track.display())).await.unwrap();
+    run(&mut app, "volume 40").await.unwrap();
+
+    run(&mut app, "device reset").await.unwrap();
+
+    assert_eq!(app.audio_engine.playback_state(), crate::audio::engine::PlaybackState::Stopped);
+    assert_eq!(app.audio_engine.current_track_path(), None);
+    assert_eq!(app.audio_engine.volume(), 0.4);
+    assert_eq!(engine.calls().last(), Some(&EngineCall::SetDevice("Mock Output".to_string())));
+}
+
+#[tokio::test]
+async fn test_device_reset_falls_back_to_the_default_device() {
+    let (mut app, engine, _dir) = controller();
+    engine.set_devices(&["Mock Output", "Headphones"]);
+    run(&mut app, "device set Headphones").await.unwrap();
+    engine.set_devices(&["USB DAC"]);
+
+    run(&mut app, "device reset").await.unwrap();
+
+    assert_eq!(app.audio_engine.current_device_name().unwrap().as_deref(), Some("USB DAC"));
+    assert!(run(&mut app, "device reset").await.is_ok());
+}
+
+#[tokio::test]
+async fn test_scan_loudness_stores_results_for_info() {
+    let (mut app, _engine, dir) = controller();
+    let track = write_wav(&dir.path().join("tone.wav"), 1);
+    let mut bytes = std::fs::read(&track).unwrap();
+    for (index, sample) in bytes[44..].chunks_exact_mut(2).enumerate() {
+        // 1 kHz square wave on both channels
+        let value: i16 = if index / 2 % 8 < 4 { 3000 } else { -3000 };
+        sample.copy_from_slice(&value.to_le_bytes());
+    }
+    std::fs::write(&track, bytes).unwrap();
+
+    run(&mut app, &format!("scan-loudness {}", track.display())).await.unwrap();
+
+    let loudness = app.loudness.get(&track).unwrap().unwrap();
+    assert!(loudness.integrated_lufs < -10.0 && loudness.integrated_lufs > -30.0, "{:?}", loudness);
+    assert!(dir.path().join(crate::queue::loudness::LOUDNESS_FILE).exists());
+    run(&mut app, &format!("info {}", track.display())).await.unwrap();
+    run(&mut app, "scan-loudness cancel").await.unwrap();
+    assert!(run(&mut app, "scan-loudness queue").await.is_err());
+}
+
+#[tokio::test]
+async fn test_export_renders_the_queue_to_a_file_without_playing() {
+    use crate::audio::decoders::FlacDecoder;
+    use crate::audio::AudioDecoder;
+    let (mut app, engine, dir) = controller();
+    let output = dir.path().join("album.flac");
+
+    run(&mut app, &format!("export {}", output.display())).await.unwrap();
+    assert!(!output.exists(), "nothing is loaded");
+    assert!(matches!(run(&mut app, &format!("export {} queue", output.display())).await, Err(PlayerError::Queue(QueueError::EmptyQueue))));
+
+    for (name, seconds) in [("one.wav", 1), ("two.wav", 2)] {
+        let track = write_wav(&dir.path().join(name), seconds);
+        run(&mut app, &format!("queue add {}", track.display())).await.unwrap();
+    }
+    run(&mut app, &format!("export {} queue --bits 24", output.display())).await.unwrap();
+
+    let mut decoder = FlacDecoder::new(&output).unwrap();
+    assert_eq!((decoder.sample_rate(), decoder.channels(), decoder.bit_depth()), (8000, 2, 24));
+    let mut frames = 0;
+    while let Some(buffer) = decoder.decode_next().unwrap() {
+        assert!(buffer.samples.iter().all(|&sample| sample == 0.0));
+        frames += buffer.frames;
+    }
+    assert_eq!(frames, 3 * 8000);
+
+    // A rate of its own, and FLAC can't hold 32 bits
+    let wav = dir.path().join("upsampled.wav");
+    run(&mut app, &format!("export {} queue --rate 16000", wav.display())).await.unwrap();
+    assert_eq!(crate::audio::decoders::WavDecoder::new(&wav).unwrap().sample_rate(), 16000);
+    assert!(run(&mut app, &format!("export {} queue --bits 32", output.display())).await.is_err());
+
+    run(&mut app, "export cancel").await.unwrap();
+    assert!(engine.calls().is_empty());
+}
+
+#[tokio::test]
+async fn test_finished_track_resets_recovery_attempts() {
+    let (mut app, engine, dir) = controller();
+    let track = write_wav(&dir.path().join("one.wav"), 1);
+    run(&mut app, &format!("queue add {}", track.display())).await.unwrap();
+    let mut ticks = TickState::new();
+    engine.push_decoder_response(DecoderResponse::FileLoaded { path: track, duration: Some(Duration::from_secs(1)), sample_rate: 8000, bit_depth: 16, channels: 2 });
+    app.poll_engine(&mut ticks).await;
+
+    let error = PlayerError::Audio(crate::error::AudioError::StreamError("device busy".to_string()));
+    app.handle_error_with_recovery(&error).await;
+    assert_eq!(app.error_recovery.get_recovery_statistics().total_errors_tracked, 1);
+
+    engine.push_decoder_response(DecoderResponse::EndOfFile);
+    app.poll_engine(&mut ticks).await;
+    let stats = app.error_recovery.get_recovery_statistics();
+    assert_eq!(stats.total_errors_tracked, 0);
+    assert_eq!(stats.retries, 1, "totals since startup are kept");
+}
+
+#[tokio::test]
+async fn test_status_reports_levels_only_while_playing() {
+    let (mut app, engine, dir) = controller();
+    let levels = crate::models::ChannelLevels { peak_left: 0.9, peak_right: 0.7, rms_left: 0.5, rms_right: 0.4 };
+    engine.set_output_levels(levels);
+    assert_eq!(app.get_current_status().levels, None);
+
+    let track = write_wav(&dir.path().join("one.wav"), 2);
+    run(&mut app, &format!("play {}", track.display())).await.unwrap();
+    assert_eq!(app.get_current_status().levels, Some(levels));
+    run(&mut app, "pause").await.unwrap();
+    assert_eq!(app.get_current_status().levels, None);
+}
+
+#[tokio::test]
+async fn test_status_warns_when_the_device_degrades_the_source() {
+    use crate::audio::DeviceCapabilities;
+    use crate::models::{AudioCodec, AudioFormat};
+    let (mut app, engine, dir) = controller();
+    let track = write_wav(&dir.path().join("master.wav"), 2);
+    run(&mut app, &format!("play {}", track.display())).await.unwrap();
+    engine.set_source_format(AudioFormat::new(192_000, 24, 2, AudioCodec::Flac));
+    assert_eq!(app.get_current_status().degradation_warning, None, "unknown capabilities give no warning");
+
+    let mut capabilities = DeviceCapabilities {
+        name: "Mock Output".to_string(),
+        supported_sample_rates: vec![44_100, 48_000],
+        supported_bit_depths: vec![16],
+        max_channels: 2,
+        supports_exclusive_mode: false,
+        default_config: cpal::SupportedStreamConfig::new(
+            2,
+            cpal::SampleRate(48_000),
+            cpal::SupportedBufferSize::Unknown,
+            cpal::SampleFormat::I16,
+        ),
+    };
+    engine.set_device_capabilities(capabilities.clone());
+    assert_eq!(
+        app.get_current_status().degradation_warning.as_deref(),
+        Some("Warning: device downsampling 192kHz→48kHz, truncating 24-bit→16-bit")
+    );
+
+    capabilities.supported_sample_rates.push(192_000);
+    capabilities.supported_bit_depths.push(32);
+    engine.set_device_capabilities(capabilities);
+    assert_eq!(app.get_current_status().degradation_warning, None);
+}
+
+#[tokio::test]
+async fn test_unseekable_files_are_reported_and_failed_seeks_announced() {
+    let (mut app, engine, dir) = controller();
+    let printed = capture_console(&mut app);
+    let track = write_wav(&dir.path().join("stream.wav"), 3);
+    run(&mut app, &format!("play {}", track.display())).await.unwrap();
+    assert!(app.get_current_status().seekable);
+
+    engine.push_decoder_response(DecoderResponse::SeekFailed {
+        requested: Duration::from_secs(2),
+        position: Duration::from_millis(1500),
+        reason: "Seek failed: stream is not seekable".to_string(),
+    });
+    app.poll_engine(&mut TickState::new()).await;
+    assert_eq!(
+        printed.lock().unwrap().last().unwrap(),
+        "Seek to 00:02 failed (Seek failed: stream is not seekable); playing on from 00:01"
+    );
+
+    engine.set_seekable(false);
+    assert!(!app.get_current_status().seekable);
+    assert!(run(&mut app, "seek 1").await.is_err());
+}
+
+#[tokio::test]
+async fn test_seek_keeps_the_playback_state() {
+    use crate::models::PlaybackState;
+    let (mut app, engine, dir) = controller();
+    let track = write_wav(&dir.path().join("three.wav"), 3);
+    run(&mut app, &format!("play {}", track.display())).await.unwrap();
+
+    run(&mut app, "seek 1").await.unwrap();
+    assert_eq!(app.get_current_status().position, Duration::from_secs(1));
+    assert_eq!(app.get_current_status().state, PlaybackState::Playing);
+
+    run(&mut app, "pause").await.unwrap();
+    run(&mut app, "seek 2").await.unwrap();
+    assert_eq!(app.get_current_status().position, Duration::from_secs(2));
+    assert_eq!(app.get_current_status().state, PlaybackState::Paused);
+
+    // A seek while stopped is where the next play starts
+    run(&mut app, "stop").await.unwrap();
+    run(&mut app, "seek 1").await.unwrap();
+    assert_eq!(app.get_current_status().state, PlaybackState::Stopped);
+    run(&mut app, "play").await.unwrap();
+    assert_eq!(app.get_current_status().position, Duration::from_secs(1));
+    assert_eq!(app.get_current_status().state, PlaybackState::Playing);
+
+    // The stopped track was still loaded, so play picked it up rather than reopening it
+    assert_eq!(engine.calls().last(), Some(&EngineCall::Resume));
+}
+
+#[tokio::test]
+async fn test_play_after_stop_restarts_the_loaded_track() {
+    use crate::models::PlaybackState;
+    let (mut app, engine, dir) = controller();
+    let track = write_wav(&dir.path().join("three.wav"), 3);
+    run(&mut app, &format!("play {}", track.display())).await.unwrap();
+    run(&mut app, "seek 2").await.unwrap();
+
+    // Stopped, the track stays loaded and shown, back at its start
+    run(&mut app, "stop").await.unwrap();
+    let status = app.get_current_status();
+    assert_eq!(status.state, PlaybackState::Stopped);
+    assert_eq!(status.position, Duration::ZERO);
+    assert_eq!(status.current_track.map(|track| track.path), Some(track.clone()));
+    assert_eq!(engine.current_track_path(), Some(track.clone()));
+
+    run(&mut app, "play").await.unwrap();
+    assert_eq!(engine.calls().last(), Some(&EngineCall::Resume));
+    assert_eq!(app.get_current_status().state, PlaybackState::Playing);
+    assert_eq!(app.get_current_status().position, Duration::ZERO);
+
+    // Unloading lets go of the track; play opens it again
+    run(&mut app, "unload").await.unwrap();
+    assert_eq!(engine.current_track_path(), None);
+    assert!(app.get_current_status().current_track.is_none());
+    run(&mut app, "play").await.unwrap();
+    assert_eq!(engine.calls().last(), Some(&EngineCall::StartPlayback { start: Duration::ZERO }));
+}
+
+#[tokio::test]
+async fn test_next_while_stopped_loads_the_track_for_play() {
+    use crate::models::PlaybackState;
+    let (mut app, engine, dir) = controller();
+    let first = write_wav(&dir.path().join("1.wav"), 1);
+    let second = write_wav(&dir.path().join("2.wav"), 1);
+    for path in [&first, &second] {
+        run(&mut app, &format!("queue add {}", path.display())).await.unwrap();
+    }
+    run(&mut app, "play").await.unwrap();
+    run(&mut app, "stop").await.unwrap();
+
+    run(&mut app, "next").await.unwrap();
+    let status = app.get_current_status();
+    assert_eq!(status.state, PlaybackState::Stopped);
+    assert_eq!(status.current_track.map(|track| track.path), Some(second.clone()));
+
+    run(&mut app, "play").await.unwrap();
+    assert_eq!(engine.calls()[2..], [
+        EngineCall::LoadFile { path: second, start: Duration::ZERO },
+        EngineCall::Resume,
+    ]);
+    assert_eq!(app.get_current_status().state, PlaybackState::Playing);
+}
+
+#[tokio::test]
+async fn test_slow_prebuffer_is_announced_once_then_stops_playback() {
+    let (mut app, engine, dir) = controller();
+    let printed = capture_console(&mut app);
+    let path = write_wav(&dir.path().join("slow.wav"), 1);
+    run(&mut app, &format!("play {}", path.display())).await.unwrap();
+    let mut ticks = TickState::new();
+
+    // A short wait is not worth mentioning
+    engine.set_buffering_for(Some(Duration::from_millis(100)));
+    app.poll_engine(&mut ticks).await;
+    assert!(printed.lock().unwrap().is_empty());
+
+    engine.set_buffering_for(Some(Duration::from_secs(2)));
+    app.poll_engine(&mut ticks).await;
+    app.poll_engine(&mut ticks).await;
+    assert_eq!(*printed.lock().unwrap(), vec!["Buffering…".to_string()]);
+    assert!(app.get_current_status().buffering);
+
+    engine.set_buffering_for(Some(Duration::from_secs(10)));
+    app.poll_engine(&mut ticks).await;
+
+    assert_eq!(engine.calls().last(), Some(&EngineCall::Stop));
+    assert!(!app.get_current_status().buffering);
+    let printed = printed.lock().unwrap();
+    assert_eq!(printed.len(), 2);
+    assert!(printed[1].starts_with("Playback stopped: The track could not be read fast enough"), "{}", printed[1]);
+}
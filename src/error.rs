@@ -1,4 +1,5 @@
 use thiserror::Error;
+use std::path::PathBuf;
 
 /// Main player error type
 #[derive(Debug, Error)]
@@ -166,6 +167,15 @@ pub enum AudioError {
 
     #[error("Invalid seek position: {position:.2}s exceeds track duration {duration:.2}s")]
     InvalidSeekPosition { position: f64, duration: f64 },
+
+    #[error("Invalid loop: end {end:.2}s is not after start {start:.2}s")]
+    InvalidLoopRegion { start: f64, end: f64 },
+
+    #[error("Seeking is not supported for this file")]
+    SeekUnsupported,
+
+    #[error("Buffering timed out after {waited_secs}s with {buffered_ms} of {target_ms} ms buffered")]
+    PrebufferTimeout { waited_secs: u64, buffered_ms: u64, target_ms: u64 },
 }
 
 impl AudioError {
@@ -192,6 +202,15 @@ impl AudioError {
             AudioError::InvalidSeekPosition { position, duration } => {
                 format!("Cannot seek to {:.1}s - track is only {:.1}s long", position, duration)
             }
+            AudioError::InvalidLoopRegion { start, end } => {
+                format!("Loop end {:.1}s must come after loop start {:.1}s", end, start)
+            }
+            AudioError::SeekUnsupported => {
+                "This file can't be seeked - playback continues from the current position".to_string()
+            }
+            AudioError::PrebufferTimeout { waited_secs, .. } => {
+                format!("The track could not be read fast enough to start playing within {}s", waited_secs)
+            }
         }
     }
 
@@ -233,6 +252,19 @@ impl AudioError {
                 format!("Use a position between 0 and {:.1} seconds", duration),
                 "Try seeking to an earlier position in the track".to_string(),
             ],
+            AudioError::InvalidLoopRegion { .. } => vec![
+                "Mark the loop start with 'loop a' before marking the end with 'loop b'".to_string(),
+                "Give the start time first, e.g. 'loop 1:00 1:30'".to_string(),
+            ],
+            AudioError::SeekUnsupported => vec![
+                "Use 'stop' then 'play' to start the track again from the beginning".to_string(),
+                "Convert the file to a seekable format such as FLAC".to_string(),
+            ],
+            AudioError::PrebufferTimeout { .. } => vec![
+                "Check that the drive or network share holding the file is reachable".to_string(),
+                "Copy the file to a local disk and play it from there".to_string(),
+                "Lower 'prebuffer_ms' in the configuration to start with less audio buffered".to_string(),
+            ],
         }
     }
 
@@ -245,6 +277,9 @@ impl AudioError {
             AudioError::BufferUnderrun => true,  // Usually recovers automatically
             AudioError::InitializationFailed(_) => true,  // Can retry initialization
             AudioError::InvalidSeekPosition { .. } => false, // Requires valid position
+            AudioError::InvalidLoopRegion { .. } => false, // Requires valid loop points
+            AudioError::SeekUnsupported => false, // The stream itself can't seek
+            AudioError::PrebufferTimeout { .. } => true, // The source may catch up on a retry
         }
     }
 }
@@ -263,6 +298,9 @@ pub enum DecodeError {
 
     #[error("Decode failed: {0}")]
     DecodeFailed(String),
+
+    #[error("Stream {url} failed: {reason}")]
+    StreamFailed { url: String, reason: String },
 }
 
 impl DecodeError {
@@ -280,6 +318,9 @@ impl DecodeError {
             DecodeError::DecodeFailed(msg) => {
                 format!("Failed to decode audio data: {}", msg)
             }
+            DecodeError::StreamFailed { url, reason } => {
+                format!("Could not play the stream {}: {}", url, reason)
+            }
         }
     }
 
@@ -306,6 +347,11 @@ impl DecodeError {
                 "Check if the file is completely downloaded".to_string(),
                 "Verify the file is not corrupted".to_string(),
             ],
+            DecodeError::StreamFailed { .. } => vec![
+                "Check the network connection".to_string(),
+                "Open the URL in a browser to check that the server is up".to_string(),
+                "Internet radio stations sometimes move; look up the station's current URL".to_string(),
+            ],
         }
     }
 
@@ -315,6 +361,7 @@ impl DecodeError {
             DecodeError::CorruptedFile(_) => false, // Requires file repair/replacement
             DecodeError::SeekError(_) => true, // Can continue without seeking
             DecodeError::DecodeFailed(_) => false, // Usually indicates file issues
+            DecodeError::StreamFailed { .. } => true, // The server may answer on another try
         }
     }
 }
@@ -333,6 +380,15 @@ pub enum ConfigError {
 
     #[error("Deserialization error: {0}")]
     DeserializationError(#[from] toml::de::Error),
+
+    #[error("Unknown configuration key: {key}")]
+    UnknownKey { key: String },
+
+    #[error("Invalid value '{value}' for {key}: {reason}")]
+    InvalidValue { key: String, value: String, reason: String },
+
+    #[error("Invalid setting {key} = '{value}' in config file: {reason}")]
+    InvalidSetting { key: String, value: String, reason: String },
 }
 
 impl ConfigError {
@@ -347,8 +403,17 @@ impl ConfigError {
             ConfigError::SerializationError(_) => {
                 "Failed to save configuration settings".to_string()
             }
-            ConfigError::DeserializationError(_) => {
-                "Configuration file is corrupted or has invalid format".to_string()
+            ConfigError::DeserializationError(err) => {
+                format!("Configuration file has an invalid format: {}", err.to_string().trim_end())
+            }
+            ConfigError::UnknownKey { key } => {
+                format!("'{}' is not a setting that can be changed", key)
+            }
+            ConfigError::InvalidValue { key, value, reason } => {
+                format!("Cannot set {} to '{}': {}", key, value, reason)
+            }
+            ConfigError::InvalidSetting { key, value, reason } => {
+                format!("{} = '{}' in config.toml is not valid: {}", key, value, reason)
             }
         }
     }
@@ -357,7 +422,7 @@ impl ConfigError {
         match self {
             ConfigError::ConfigDirNotFound => vec![
                 "Check that you have write permissions to your home directory".to_string(),
-                "Try creating the directory manually: ~/.config/hires-player/".to_string(),
+                "Try creating the directory manually: ~/.config/hires-player/ (%APPDATA%\\hires-player\\ on Windows)".to_string(),
             ],
             ConfigError::IoError(_) => vec![
                 "Check file permissions for the configuration directory".to_string(),
@@ -373,6 +438,18 @@ impl ConfigError {
                 "Check the configuration file format manually".to_string(),
                 "Backup and recreate the configuration file".to_string(),
             ],
+            ConfigError::UnknownKey { .. } => vec![
+                format!("Available keys: {}", crate::config::ConfigManager::SETTABLE_KEYS.join(", ")),
+                "Use 'config show' to see the current values".to_string(),
+            ],
+            ConfigError::InvalidValue { .. } => vec![
+                "Use 'help' to see the accepted values".to_string(),
+                "The previous setting is unchanged".to_string(),
+            ],
+            ConfigError::InvalidSetting { key, .. } => vec![
+                format!("Edit config.toml or use 'config set {} <value>'", key),
+                "The default value is used until it is fixed".to_string(),
+            ],
         }
     }
 
@@ -382,6 +459,9 @@ impl ConfigError {
             ConfigError::IoError(_) => true, // Can retry or use defaults
             ConfigError::SerializationError(_) => true, // Can use current settings
             ConfigError::DeserializationError(_) => true, // Can use defaults
+            ConfigError::UnknownKey { .. } => true, // Nothing was changed
+            ConfigError::InvalidValue { .. } => true, // Nothing was changed
+            ConfigError::InvalidSetting { .. } => true, // Can use the default value
         }
     }
 }
@@ -389,27 +469,33 @@ impl ConfigError {
 /// Queue management errors
 #[derive(Debug, Error)]
 pub enum QueueError {
-    #[error("File not found: {path}")]
-    FileNotFound { path: String },
+    #[error("File not found: {}", path.display())]
+    FileNotFound { path: PathBuf },
 
-    #[error("Invalid file format: {path}")]
-    InvalidFormat { path: String },
+    #[error("Invalid file format: {}", path.display())]
+    InvalidFormat { path: PathBuf },
 
     #[error("Queue is empty")]
     EmptyQueue,
 
     #[error("Invalid index: {index}")]
     InvalidIndex { index: usize },
+
+    #[error("Corrupted file {}: {reason}", path.display())]
+    CorruptedFile { path: PathBuf, reason: String },
+
+    #[error("{failed} of {checked} files failed verification")]
+    VerificationFailed { failed: usize, checked: usize },
 }
 
 impl QueueError {
     pub fn user_message(&self) -> String {
         match self {
             QueueError::FileNotFound { path } => {
-                format!("Cannot find audio file: {}", path)
+                format!("Cannot find audio file: {}", path.display())
             }
             QueueError::InvalidFormat { path } => {
-                format!("File '{}' is not a supported audio format", path)
+                format!("File '{}' is not a supported audio format", path.display())
             }
             QueueError::EmptyQueue => {
                 "No tracks in queue - add some files first".to_string()
@@ -417,6 +503,12 @@ impl QueueError {
             QueueError::InvalidIndex { index } => {
                 format!("Track number {} is not valid for current queue", index + 1)
             }
+            QueueError::CorruptedFile { path, reason } => {
+                format!("File '{}' failed verification: {}", path.display(), reason)
+            }
+            QueueError::VerificationFailed { failed, checked } => {
+                format!("{} of {} files failed verification", failed, checked)
+            }
         }
     }
 
@@ -441,6 +533,13 @@ impl QueueError {
                 "Use 'queue list' to see available tracks".to_string(),
                 "Track numbers start from 1".to_string(),
             ],
+            QueueError::CorruptedFile { .. } => vec![
+                "Try re-downloading or re-copying the file".to_string(),
+                "Check that the .sha256 file belongs to this version of the file".to_string(),
+            ],
+            QueueError::VerificationFailed { .. } => vec![
+                "Re-rip, re-download or re-copy the files listed above".to_string(),
+            ],
         }
     }
 
@@ -450,6 +549,8 @@ impl QueueError {
             QueueError::InvalidFormat { .. } => false, // Requires supported format
             QueueError::EmptyQueue => true, // Can add files
             QueueError::InvalidIndex { .. } => false, // Requires valid index
+            QueueError::CorruptedFile { .. } => false, // Requires an intact copy
+            QueueError::VerificationFailed { .. } => false, // Requires intact copies
         }
     }
 }
@@ -606,6 +707,15 @@ mod tests {
 
         let error = AudioError::InvalidSeekPosition { position: 200.5, duration: 180.0 };
         assert_eq!(format!("{}", error), "Invalid seek position: 200.50s exceeds track duration 180.00s");
+
+        let error = AudioError::InvalidLoopRegion { start: 90.0, end: 60.0 };
+        assert_eq!(format!("{}", error), "Invalid loop: end 60.00s is not after start 90.00s");
+
+        let error = AudioError::SeekUnsupported;
+        assert_eq!(format!("{}", error), "Seeking is not supported for this file");
+
+        let error = AudioError::PrebufferTimeout { waited_secs: 10, buffered_ms: 120, target_ms: 500 };
+        assert_eq!(format!("{}", error), "Buffering timed out after 10s with 120 of 500 ms buffered");
     }
 
     #[test]
@@ -638,12 +748,12 @@ mod tests {
     #[test]
     fn test_queue_error_display() {
         let error = QueueError::FileNotFound {
-            path: "/test/file.flac".to_string(),
+            path: PathBuf::from("/test/file.flac"),
         };
         assert_eq!(format!("{}", error), "File not found: /test/file.flac");
 
         let error = QueueError::InvalidFormat {
-            path: "/test/file.txt".to_string(),
+            path: PathBuf::from("/test/file.txt"),
         };
         assert_eq!(format!("{}", error), "Invalid file format: /test/file.txt");
 
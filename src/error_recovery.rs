@@ -1,68 +1,315 @@
+use crate::audio::buffer::BufferAdjustment;
+use crate::audio::engine::DECODE_TIMEOUT_MESSAGE;
 use crate::error::{PlayerError, AudioError, DecodeError, ConfigError, QueueError, PlaylistError};
 use crate::logging::AudioLogger;
 use log::{info, warn, error};
-use std::time::Duration;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
 use std::path::Path;
 
+/// How long a recovery attempt counts towards the attempt limit by default
+pub const DEFAULT_RECOVERY_COOLDOWN: Duration = Duration::from_secs(600);
+
+/// Failed recoveries in a row after which attempts for an error type are refused
+pub const CIRCUIT_BREAKER_THRESHOLD: u32 = 3;
+
+/// How long attempts are refused once the threshold is reached
+pub const CIRCUIT_BREAKER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Wait before the first retry of a stream error; doubled for each one after it
+pub const RETRY_INITIAL_DELAY: Duration = Duration::from_millis(100);
+
+/// Longest wait between retries
+pub const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Fixes the recovery manager carries out itself instead of suggesting them to the user
+///
+/// Implemented by the application controller; keeping the manager behind this trait lets
+/// it be tested without an audio engine.
+pub trait RecoveryActions {
+    /// Move playback to the system default output device and return its name
+    fn switch_to_default_device(&mut self) -> Result<String, PlayerError>;
+}
+
 /// Error recovery strategies and automatic error handling
 pub struct ErrorRecoveryManager {
     logger: AudioLogger,
-    recovery_attempts: std::collections::HashMap<String, u32>,
+    /// When each error was last tried, newest last; entries older than `cooldown` are dropped
+    recovery_attempts: HashMap<String, Vec<Instant>>,
     max_recovery_attempts: u32,
+    cooldown: Duration,
+    last_buffer_adjustment: Option<BufferAdjustment>,
+    totals: RecoveryTotals,
+    /// Circuit breakers by error category, for errors whose fix keeps failing the same way
+    circuits: HashMap<&'static str, CircuitBreakerState>,
+    retries: RetryScheduler,
+}
+
+/// Exponential backoff between retries, per error key
+///
+/// Each retry of an error waits twice as long as the one before, from `RETRY_INITIAL_DELAY`
+/// up to `RETRY_MAX_DELAY`.
+#[derive(Debug, Default)]
+pub struct RetryScheduler {
+    entries: HashMap<String, RetryEntry>,
+}
+
+#[derive(Debug)]
+struct RetryEntry {
+    /// Wait chosen for the last retry
+    delay: Duration,
+    next_attempt_at: Instant,
+    /// Set until the controller picks the retry up and schedules it
+    pending: bool,
+}
+
+impl RetryScheduler {
+    /// How long is left before `key` may be retried, if it is too early
+    pub fn remaining(&self, key: &str, now: Instant) -> Option<Duration> {
+        let entry = self.entries.get(key)?;
+        (now < entry.next_attempt_at).then(|| entry.next_attempt_at - now)
+    }
+
+    /// Schedule the next retry of `key` and return the wait before it
+    pub fn schedule(&mut self, key: &str, now: Instant) -> Duration {
+        let delay = self.entries.get(key)
+            .map_or(RETRY_INITIAL_DELAY, |entry| (entry.delay * 2).min(RETRY_MAX_DELAY));
+        self.entries.insert(key.to_string(), RetryEntry { delay, next_attempt_at: now + delay, pending: true });
+        delay
+    }
+
+    /// The wait of a retry scheduled for `key` that has not been picked up yet
+    pub fn take_pending(&mut self, key: &str) -> Option<Duration> {
+        let entry = self.entries.get_mut(key)?;
+        std::mem::take(&mut entry.pending).then_some(entry.delay)
+    }
+
+    pub fn clear(&mut self, key: &str) {
+        self.entries.remove(key);
+    }
+
+    pub fn reset(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Consecutive failed recoveries of one type of error
+///
+/// Once `CIRCUIT_BREAKER_THRESHOLD` recoveries in a row fail, the circuit opens and attempts
+/// fail straight away until `open_until`. The first attempt after that goes through
+/// (half-open): a success closes the circuit, another failure opens it again.
+#[derive(Debug, Clone, Default)]
+pub struct CircuitBreakerState {
+    pub open_until: Option<Instant>,
+    pub failure_count: u32,
+}
+
+/// Recovery outcomes counted since startup
+#[derive(Debug, Clone, Default)]
+struct RecoveryTotals {
+    attempts_by_category: BTreeMap<&'static str, u32>,
+    successes: u32,
+    retries: u32,
+    failures: u32,
 }
 
 impl ErrorRecoveryManager {
     pub fn new(logger: AudioLogger) -> Self {
         Self {
             logger,
-            recovery_attempts: std::collections::HashMap::new(),
+            recovery_attempts: HashMap::new(),
             max_recovery_attempts: 3,
+            cooldown: DEFAULT_RECOVERY_COOLDOWN,
+            last_buffer_adjustment: None,
+            totals: RecoveryTotals::default(),
+            circuits: HashMap::new(),
+            retries: RetryScheduler::default(),
         }
     }
 
-    /// Attempt to recover from an error automatically
-    pub async fn attempt_recovery(&mut self, error: &PlayerError) -> RecoveryResult {
+    /// Change how long an attempt counts towards the attempt limit
+    pub fn set_cooldown(&mut self, cooldown: Duration) {
+        self.cooldown = cooldown;
+    }
+
+    pub fn cooldown(&self) -> Duration {
+        self.cooldown
+    }
+
+    /// Record a buffer adjustment made by the engine so underrun recovery can report it
+    pub fn record_buffer_adjustment(&mut self, adjustment: BufferAdjustment) {
+        self.last_buffer_adjustment = Some(adjustment);
+    }
+
+    /// Attempt to recover from an error automatically, carrying out fixes through `actions`
+    pub async fn attempt_recovery(&mut self, error: &PlayerError, actions: &mut dyn RecoveryActions) -> RecoveryResult {
+        self.attempt_recovery_at(error, actions, Instant::now()).await
+    }
+
+    async fn attempt_recovery_at(
+        &mut self,
+        error: &PlayerError,
+        actions: &mut dyn RecoveryActions,
+        now: Instant,
+    ) -> RecoveryResult {
+        self.expire_attempts(now);
         let error_key = self.get_error_key(error);
-        let attempts = self.recovery_attempts.get(&error_key).unwrap_or(&0) + 1;
+        *self.totals.attempts_by_category.entry(Self::error_category(error)).or_insert(0) += 1;
+
+        let circuit_key = Self::circuit_key(error);
+        if let Some(circuit) = circuit_key.and_then(|key| self.circuits.get_mut(key)) {
+            match circuit.open_until {
+                Some(open_until) if now < open_until => {
+                    let wait = open_until.saturating_duration_since(now);
+                    warn!("Recovery skipped, {} failures in a row for error: {}", circuit.failure_count, error);
+                    self.logger.log_recovery_attempt(&error.to_string(), circuit.failure_count + 1, "skipped, circuit open");
+                    self.totals.failures += 1;
+                    return RecoveryResult::Failed(format!(
+                        "Recovery failed {} times in a row; not retrying for another {}s",
+                        circuit.failure_count,
+                        wait.as_secs().max(1)
+                    ));
+                }
+                // Half-open: let this attempt through
+                Some(_) => circuit.open_until = None,
+                None => {}
+            }
+        }
+
+        let attempts = self.recovery_attempts.get(&error_key).map_or(0, Vec::len) as u32 + 1;
         
         if attempts > self.max_recovery_attempts {
             warn!("Maximum recovery attempts ({}) exceeded for error: {}", 
                 self.max_recovery_attempts, error);
+            self.logger.log_recovery_attempt(&error.to_string(), attempts, "gave up, maximum attempts exceeded");
+            self.totals.failures += 1;
             return RecoveryResult::Failed("Maximum recovery attempts exceeded".to_string());
         }
         
-        self.recovery_attempts.insert(error_key.clone(), attempts);
+        self.recovery_attempts.entry(error_key.clone()).or_default().push(now);
         
         info!("Attempting recovery for error (attempt {}): {}", attempts, error);
         
-        let result = match error {
-            PlayerError::Audio(audio_err) => self.recover_audio_error(audio_err).await,
-            PlayerError::Decode(decode_err) => self.recover_decode_error(decode_err).await,
-            PlayerError::Config(config_err) => self.recover_config_error(config_err).await,
-            PlayerError::Queue(queue_err) => self.recover_queue_error(queue_err).await,
-            PlayerError::Playlist(playlist_err) => self.recover_playlist_error(playlist_err).await,
-            PlayerError::File(_) => RecoveryResult::Failed("File errors require manual intervention".to_string()),
-            PlayerError::Parse(_) => RecoveryResult::Failed("Parse errors require correct input".to_string()),
+        let pending_retry = self.retries.remaining(&error_key, now).filter(|_| Self::backs_off(error));
+        let result = if let Some(wait) = pending_retry {
+            // The retry already scheduled covers this one
+            RecoveryResult::Retry(format!("Stream error detected. A retry is already due in {}", Self::format_wait(wait)))
+        } else {
+            self.dispatch_recovery(error, &error_key, actions, now).await
         };
         
+        let outcome = match &result {
+            RecoveryResult::Success(msg) => format!("succeeded: {}", msg),
+            RecoveryResult::Retry(msg) => format!("retry: {}", msg),
+            RecoveryResult::Failed(msg) => format!("failed: {}", msg),
+        };
+        self.logger.log_recovery_attempt(&error.to_string(), attempts, &outcome);
+
         match &result {
             RecoveryResult::Success(msg) => {
                 info!("Recovery successful: {}", msg);
+                self.totals.successes += 1;
                 self.recovery_attempts.remove(&error_key);
+                self.retries.clear(&error_key);
             }
             RecoveryResult::Retry(msg) => {
                 info!("Recovery requires retry: {}", msg);
+                self.totals.retries += 1;
             }
             RecoveryResult::Failed(msg) => {
                 warn!("Recovery failed: {}", msg);
+                self.totals.failures += 1;
             }
         }
+
+        if let Some(key) = circuit_key {
+            self.record_circuit_outcome(key, result.is_failed(), now);
+        }
         
         result
     }
 
+    /// Errors retried after a growing wait: stream errors other than a decoder timeout,
+    /// which skips the track instead
+    fn backs_off(error: &PlayerError) -> bool {
+        matches!(error, PlayerError::Audio(AudioError::StreamError(msg)) if msg != DECODE_TIMEOUT_MESSAGE)
+    }
+
+    /// The wait before the retry the last recovery of `error` scheduled, once
+    ///
+    /// The caller restarts playback after it; attempts made before then only report the wait.
+    pub fn take_scheduled_retry(&mut self, error: &PlayerError) -> Option<Duration> {
+        let error_key = self.get_error_key(error);
+        self.retries.take_pending(&error_key)
+    }
+
+    fn format_wait(wait: Duration) -> String {
+        if wait < Duration::from_secs(1) {
+            format!("{}ms", wait.as_millis())
+        } else {
+            format!("{:.1}s", wait.as_secs_f64())
+        }
+    }
+
+    /// Error types that get a circuit breaker: retrying them straight away is pointless
+    /// while the cause persists, such as no usable output device at all
+    fn circuit_key(error: &PlayerError) -> Option<&'static str> {
+        matches!(error, PlayerError::Audio(AudioError::DeviceNotFound { .. }))
+            .then(|| Self::error_category(error))
+    }
+
+    /// Count a failed recovery towards opening the circuit, or close it after one that worked
+    fn record_circuit_outcome(&mut self, key: &'static str, failed: bool, now: Instant) {
+        if !failed {
+            self.circuits.remove(key);
+            return;
+        }
+        let circuit = self.circuits.entry(key).or_default();
+        circuit.failure_count += 1;
+        if circuit.failure_count >= CIRCUIT_BREAKER_THRESHOLD {
+            circuit.open_until = Some(now + CIRCUIT_BREAKER_TIMEOUT);
+        }
+    }
+
+    /// Run the recovery strategy for an error
+    async fn dispatch_recovery(
+        &mut self,
+        error: &PlayerError,
+        error_key: &str,
+        actions: &mut dyn RecoveryActions,
+        now: Instant,
+    ) -> RecoveryResult {
+        let result = match error {
+            PlayerError::Audio(audio_err) => self.recover_audio_error(audio_err, actions).await,
+            PlayerError::Decode(decode_err) => self.recover_decode_error(decode_err).await,
+            PlayerError::Config(config_err) => self.recover_config_error(config_err).await,
+            PlayerError::Queue(queue_err) => self.recover_queue_error(queue_err).await,
+            PlayerError::Playlist(playlist_err) => self.recover_playlist_error(playlist_err).await,
+            PlayerError::File(_) => RecoveryResult::Failed("File errors require manual intervention".to_string()),
+            PlayerError::Parse(_) => RecoveryResult::Failed("Parse errors require correct input".to_string()),
+        };
+
+        // The caller retries these after a wait that doubles each time
+        match result {
+            RecoveryResult::Retry(msg) if Self::backs_off(error) => {
+                let wait = self.retries.schedule(error_key, now);
+                RecoveryResult::Retry(format!("{} in {}", msg, Self::format_wait(wait)))
+            }
+            other => other,
+        }
+    }
+
+    /// Forget attempts made longer ago than the cooldown
+    fn expire_attempts(&mut self, now: Instant) {
+        let cooldown = self.cooldown;
+        self.recovery_attempts.retain(|_, times| {
+            times.retain(|&time| now.saturating_duration_since(time) < cooldown);
+            !times.is_empty()
+        });
+    }
+
     /// Recover from audio-related errors
-    async fn recover_audio_error(&mut self, error: &AudioError) -> RecoveryResult {
+    async fn recover_audio_error(&mut self, error: &AudioError, actions: &mut dyn RecoveryActions) -> RecoveryResult {
         match error {
             AudioError::DeviceNotFound { device } => {
                 self.logger.log_event(
@@ -72,10 +319,16 @@ impl ErrorRecoveryManager {
                 );
                 
                 // Strategy: Fall back to default device
-                RecoveryResult::Retry(format!(
-                    "Device '{}' not found. Try switching to default device with 'device set default'", 
-                    device
-                ))
+                match actions.switch_to_default_device() {
+                    Ok(default_device) => RecoveryResult::Success(format!(
+                        "Device '{}' not found. Switched to the default device '{}'",
+                        device, default_device
+                    )),
+                    Err(e) => RecoveryResult::Failed(format!(
+                        "Device '{}' not found and the default device could not be used: {}",
+                        device, e
+                    )),
+                }
             }
             
             AudioError::UnsupportedSampleRate { rate } => {
@@ -94,18 +347,32 @@ impl ErrorRecoveryManager {
                 ))
             }
             
+            AudioError::StreamError(msg) if msg == DECODE_TIMEOUT_MESSAGE => {
+                self.logger.log_stream_error(&error.to_string(), true);
+
+                // Strategy: the engine has already dropped the hung decoder; move on to the next track
+                RecoveryResult::Retry(
+                    "Decoding stopped responding; the file may be corrupted. Skipping to the next track".to_string()
+                )
+            }
+
             AudioError::StreamError(_) => {
                 self.logger.log_stream_error(&error.to_string(), true);
                 
-                // Strategy: Restart audio stream
-                RecoveryResult::Retry("Stream error detected. Try stopping and restarting playback".to_string())
+                // Strategy: restart playback where it stopped, once the retry wait is over
+                RecoveryResult::Retry("Stream error detected. Restarting playback".to_string())
             }
             
             AudioError::BufferUnderrun => {
                 self.logger.log_buffer_underrun(0.0, Duration::from_millis(100));
                 
-                // Strategy: Increase buffer size and continue
-                RecoveryResult::Success("Buffer underrun recovered automatically".to_string())
+                // Strategy: the engine grows the buffer after repeated underruns; report what it did
+                match self.last_buffer_adjustment.take() {
+                    Some(adjustment) => RecoveryResult::Success(adjustment.description()),
+                    None => RecoveryResult::Retry(
+                        "Buffer underrun logged. The buffer is enlarged automatically if underruns keep occurring".to_string()
+                    ),
+                }
             }
             
             AudioError::InitializationFailed(_) => {
@@ -117,6 +384,21 @@ impl ErrorRecoveryManager {
                 // Strategy: Reset to beginning of track
                 RecoveryResult::Retry("Invalid seek position. Resetting to beginning of track".to_string())
             }
+
+            AudioError::InvalidLoopRegion { .. } => {
+                RecoveryResult::Failed("Invalid loop region. Playback continues without a loop".to_string())
+            }
+
+            AudioError::SeekUnsupported => {
+                RecoveryResult::Failed("This file does not support seeking. Playback continues from the current position".to_string())
+            }
+
+            AudioError::PrebufferTimeout { .. } => {
+                self.logger.log_stream_error(&error.to_string(), true);
+
+                // Strategy: the engine has stopped; playing again gives a slow source another chance
+                RecoveryResult::Retry("The source was too slow to buffer. Retrying playback".to_string())
+            }
         }
     }
 
@@ -149,6 +431,13 @@ impl ErrorRecoveryManager {
                 // Strategy: Skip file and continue
                 RecoveryResult::Retry("Decode failed. Skipping file and continuing with next track".to_string())
             }
+
+            DecodeError::StreamFailed { url, reason } => {
+                self.logger.log_decode_error(url, reason);
+
+                // Strategy: Skip the stream, it may be back later
+                RecoveryResult::Retry("Stream unavailable. Skipping it and continuing with next track".to_string())
+            }
         }
     }
 
@@ -174,6 +463,11 @@ impl ErrorRecoveryManager {
                 // Strategy: Reset to default configuration
                 RecoveryResult::Success("Configuration file corrupted. Reset to default settings".to_string())
             }
+
+            ConfigError::UnknownKey { .. } | ConfigError::InvalidValue { .. } | ConfigError::InvalidSetting { .. } => {
+                // Strategy: Keep the current settings
+                RecoveryResult::Failed(error.user_message())
+            }
         }
     }
 
@@ -182,12 +476,12 @@ impl ErrorRecoveryManager {
         match error {
             QueueError::FileNotFound { path } => {
                 // Strategy: Remove file from queue and continue
-                RecoveryResult::Retry(format!("File '{}' not found. Removing from queue and continuing", path))
+                RecoveryResult::Retry(format!("File '{}' not found. Removing from queue and continuing", path.display()))
             }
             
             QueueError::InvalidFormat { path } => {
                 // Strategy: Skip unsupported file
-                RecoveryResult::Retry(format!("Skipping unsupported file '{}' and continuing", path))
+                RecoveryResult::Retry(format!("Skipping unsupported file '{}' and continuing", path.display()))
             }
             
             QueueError::EmptyQueue => {
@@ -199,6 +493,16 @@ impl ErrorRecoveryManager {
                 // Strategy: Reset to first track
                 RecoveryResult::Retry("Invalid track index. Resetting to first track in queue".to_string())
             }
+
+            QueueError::CorruptedFile { path, .. } => {
+                // Strategy: Leave the damaged file out
+                RecoveryResult::Failed(format!("'{}' failed verification and was not added. Replace it with an intact copy", path.display()))
+            }
+
+            QueueError::VerificationFailed { failed, .. } => {
+                // Strategy: Nothing to do but replace the files
+                RecoveryResult::Failed(format!("{} file(s) failed verification. Replace them with intact copies", failed))
+            }
         }
     }
 
@@ -249,6 +553,15 @@ impl ErrorRecoveryManager {
             PlayerError::Audio(AudioError::InvalidSeekPosition { position, duration }) => {
                 format!("audio_invalid_seek_{}_{}", position, duration)
             }
+            PlayerError::Audio(AudioError::InvalidLoopRegion { start, end }) => {
+                format!("audio_invalid_loop_{}_{}", start, end)
+            }
+            PlayerError::Audio(AudioError::SeekUnsupported) => {
+                "audio_seek_unsupported".to_string()
+            }
+            PlayerError::Audio(AudioError::PrebufferTimeout { .. }) => {
+                "audio_prebuffer_timeout".to_string()
+            }
             PlayerError::Decode(DecodeError::UnsupportedFormat { format }) => {
                 format!("decode_unsupported_{}", format)
             }
@@ -261,11 +574,14 @@ impl ErrorRecoveryManager {
             PlayerError::Decode(DecodeError::DecodeFailed(msg)) => {
                 format!("decode_failed_{}", msg)
             }
+            PlayerError::Decode(DecodeError::StreamFailed { url, .. }) => {
+                format!("decode_stream_failed_{}", url)
+            }
             PlayerError::Queue(QueueError::FileNotFound { path }) => {
-                format!("queue_file_not_found_{}", path)
+                format!("queue_file_not_found_{}", path.display())
             }
             PlayerError::Queue(QueueError::InvalidFormat { path }) => {
-                format!("queue_invalid_format_{}", path)
+                format!("queue_invalid_format_{}", path.display())
             }
             PlayerError::Queue(QueueError::EmptyQueue) => {
                 "queue_empty".to_string()
@@ -273,6 +589,12 @@ impl ErrorRecoveryManager {
             PlayerError::Queue(QueueError::InvalidIndex { index }) => {
                 format!("queue_invalid_index_{}", index)
             }
+            PlayerError::Queue(QueueError::CorruptedFile { path, .. }) => {
+                format!("queue_corrupted_{}", path.display())
+            }
+            PlayerError::Queue(QueueError::VerificationFailed { .. }) => {
+                "queue_verification_failed".to_string()
+            }
             PlayerError::Playlist(PlaylistError::PlaylistNotFound { name }) => {
                 format!("playlist_not_found_{}", name)
             }
@@ -290,28 +612,60 @@ impl ErrorRecoveryManager {
         }
     }
 
+    /// Coarse grouping of errors for the statistics
+    fn error_category(error: &PlayerError) -> &'static str {
+        match error {
+            PlayerError::Audio(AudioError::DeviceNotFound { .. }) => "audio device",
+            PlayerError::Audio(AudioError::UnsupportedSampleRate { .. } | AudioError::UnsupportedFormat { .. }) => "audio format",
+            PlayerError::Audio(AudioError::StreamError(msg)) if msg == DECODE_TIMEOUT_MESSAGE => "decode timeout",
+            PlayerError::Audio(AudioError::StreamError(_)) => "audio stream",
+            PlayerError::Audio(AudioError::BufferUnderrun) => "buffer underrun",
+            PlayerError::Audio(AudioError::PrebufferTimeout { .. }) => "buffering",
+            PlayerError::Audio(AudioError::InitializationFailed(_)) => "audio initialization",
+            PlayerError::Audio(AudioError::InvalidSeekPosition { .. } | AudioError::InvalidLoopRegion { .. } | AudioError::SeekUnsupported) => "seek",
+            PlayerError::Decode(_) => "decode",
+            PlayerError::Config(_) => "config",
+            PlayerError::Queue(_) => "queue",
+            PlayerError::Playlist(_) => "playlist",
+            PlayerError::File(_) => "file",
+            PlayerError::Parse(_) => "input",
+        }
+    }
+
     /// Reset recovery attempts for a specific error type
     pub fn reset_recovery_attempts(&mut self, error: &PlayerError) {
         let error_key = self.get_error_key(error);
         self.recovery_attempts.remove(&error_key);
+        self.retries.clear(&error_key);
+        if let Some(key) = Self::circuit_key(error) {
+            self.circuits.remove(key);
+        }
     }
 
-    /// Clear all recovery attempt counters
-    pub fn clear_recovery_attempts(&mut self) {
+    /// Clear all recovery attempt counters and circuit breakers, e.g. once a track has played through
+    ///
+    /// The totals since startup are kept.
+    pub fn reset(&mut self) {
         self.recovery_attempts.clear();
+        self.circuits.clear();
+        self.retries.reset();
     }
 
-    /// Get recovery statistics
+    /// Get recovery statistics: attempts within the cooldown, and totals since startup
     pub fn get_recovery_statistics(&self) -> RecoveryStatistics {
+        let now = Instant::now();
+        let recent: Vec<usize> = self.recovery_attempts.values()
+            .map(|times| times.iter().filter(|&&time| now.saturating_duration_since(time) < self.cooldown).count())
+            .filter(|&attempts| attempts > 0)
+            .collect();
         RecoveryStatistics {
-            total_errors_tracked: self.recovery_attempts.len(),
-            errors_with_multiple_attempts: self.recovery_attempts.values()
-                .filter(|&&attempts| attempts > 1)
-                .count(),
-            max_attempts_for_any_error: self.recovery_attempts.values()
-                .max()
-                .copied()
-                .unwrap_or(0),
+            total_errors_tracked: recent.len(),
+            errors_with_multiple_attempts: recent.iter().filter(|&&attempts| attempts > 1).count(),
+            max_attempts_for_any_error: recent.iter().max().copied().unwrap_or(0) as u32,
+            attempts_by_category: self.totals.attempts_by_category.clone(),
+            successes: self.totals.successes,
+            retries: self.totals.retries,
+            failures: self.totals.failures,
         }
     }
 }
@@ -352,9 +706,21 @@ impl RecoveryResult {
 /// Statistics about error recovery operations
 #[derive(Debug, Clone)]
 pub struct RecoveryStatistics {
+    /// Errors with attempts inside the cooldown
     pub total_errors_tracked: usize,
     pub errors_with_multiple_attempts: usize,
     pub max_attempts_for_any_error: u32,
+    /// Attempts since startup by error category, including ones refused at the limit
+    pub attempts_by_category: BTreeMap<&'static str, u32>,
+    pub successes: u32,
+    pub retries: u32,
+    pub failures: u32,
+}
+
+impl RecoveryStatistics {
+    pub fn total_attempts(&self) -> u32 {
+        self.attempts_by_category.values().sum()
+    }
 }
 
 /// Utility functions for error recovery
@@ -446,30 +812,57 @@ mod tests {
         ErrorRecoveryManager::new(logger)
     }
 
+    /// Counts device switches; without a default device the switch fails
+    #[derive(Default)]
+    struct RecordingActions {
+        default_device: Option<String>,
+        switches: u32,
+    }
+
+    impl RecoveryActions for RecordingActions {
+        fn switch_to_default_device(&mut self) -> Result<String, PlayerError> {
+            self.switches += 1;
+            self.default_device.clone().ok_or_else(|| {
+                PlayerError::Audio(AudioError::DeviceNotFound { device: "default".to_string() })
+            })
+        }
+    }
+
     #[tokio::test]
     async fn test_audio_error_recovery() {
         let mut manager = create_test_recovery_manager();
+        let mut actions = RecordingActions::default();
         
         let error = AudioError::DeviceNotFound {
             device: "Test Device".to_string(),
         };
         let player_error = PlayerError::Audio(error);
         
-        let result = manager.attempt_recovery(&player_error).await;
-        assert!(result.is_retry());
+        // No default device either: the switch is tried and reported as failed
+        let result = manager.attempt_recovery(&player_error, &mut actions).await;
+        assert!(result.is_failed());
         assert!(result.message().contains("Test Device"));
+        assert_eq!(actions.switches, 1);
+
+        actions.default_device = Some("Speakers".to_string());
+        let result = manager.attempt_recovery(&player_error, &mut actions).await;
+        assert!(result.is_success());
+        assert!(result.message().contains("Switched to the default device 'Speakers'"));
+        assert_eq!(actions.switches, 2);
+        assert!(manager.recovery_attempts.is_empty());
     }
 
     #[tokio::test]
     async fn test_decode_error_recovery() {
         let mut manager = create_test_recovery_manager();
+        let mut actions = RecordingActions::default();
         
         let error = DecodeError::UnsupportedFormat {
             format: "UNKNOWN".to_string(),
         };
         let player_error = PlayerError::Decode(error);
         
-        let result = manager.attempt_recovery(&player_error).await;
+        let result = manager.attempt_recovery(&player_error, &mut actions).await;
         assert!(result.is_retry());
         assert!(result.message().contains("UNKNOWN"));
     }
@@ -477,11 +870,12 @@ mod tests {
     #[tokio::test]
     async fn test_config_error_recovery() {
         let mut manager = create_test_recovery_manager();
+        let mut actions = RecordingActions::default();
         
         let error = ConfigError::ConfigDirNotFound;
         let player_error = PlayerError::Config(error);
         
-        let result = manager.attempt_recovery(&player_error).await;
+        let result = manager.attempt_recovery(&player_error, &mut actions).await;
         assert!(result.is_success());
         assert!(result.message().contains("default"));
     }
@@ -489,13 +883,14 @@ mod tests {
     #[tokio::test]
     async fn test_queue_error_recovery() {
         let mut manager = create_test_recovery_manager();
+        let mut actions = RecordingActions::default();
         
         let error = QueueError::FileNotFound {
-            path: "/test/file.flac".to_string(),
+            path: PathBuf::from("/test/file.flac"),
         };
         let player_error = PlayerError::Queue(error);
         
-        let result = manager.attempt_recovery(&player_error).await;
+        let result = manager.attempt_recovery(&player_error, &mut actions).await;
         assert!(result.is_retry());
         assert!(result.message().contains("/test/file.flac"));
     }
@@ -503,25 +898,59 @@ mod tests {
     #[tokio::test]
     async fn test_max_recovery_attempts() {
         let mut manager = create_test_recovery_manager();
+        let mut actions = RecordingActions::default();
         manager.max_recovery_attempts = 2;
         
         let error = AudioError::StreamError("Test error".to_string());
         let player_error = PlayerError::Audio(error);
         
         // First attempt should succeed
-        let result1 = manager.attempt_recovery(&player_error).await;
+        let result1 = manager.attempt_recovery(&player_error, &mut actions).await;
         assert!(result1.is_retry());
         
         // Second attempt should succeed
-        let result2 = manager.attempt_recovery(&player_error).await;
+        let result2 = manager.attempt_recovery(&player_error, &mut actions).await;
         assert!(result2.is_retry());
         
         // Third attempt should fail due to max attempts
-        let result3 = manager.attempt_recovery(&player_error).await;
+        let result3 = manager.attempt_recovery(&player_error, &mut actions).await;
         assert!(result3.is_failed());
         assert!(result3.message().contains("Maximum recovery attempts"));
     }
 
+    #[tokio::test]
+    async fn test_buffer_underrun_reports_adjustment() {
+        let mut manager = create_test_recovery_manager();
+        let mut actions = RecordingActions::default();
+        let player_error = PlayerError::Audio(AudioError::BufferUnderrun);
+
+        // Without an adjustment nothing has actually been fixed yet
+        let result = manager.attempt_recovery(&player_error, &mut actions).await;
+        assert!(result.is_retry());
+
+        manager.record_buffer_adjustment(BufferAdjustment {
+            underruns: 3,
+            window: Duration::from_secs(10),
+            old_target_ms: 300,
+            new_target_ms: 450,
+            pause_ms: 200,
+        });
+        let result = manager.attempt_recovery(&player_error, &mut actions).await;
+        assert!(result.is_success());
+        assert!(result.message().contains("from 300 ms to 450 ms"));
+    }
+
+    #[tokio::test]
+    async fn test_decode_timeout_recovery() {
+        let mut manager = create_test_recovery_manager();
+        let mut actions = RecordingActions::default();
+        let player_error = PlayerError::Audio(AudioError::StreamError(DECODE_TIMEOUT_MESSAGE.to_string()));
+
+        let result = manager.attempt_recovery(&player_error, &mut actions).await;
+        assert!(result.is_retry());
+        assert!(result.message().contains("corrupted"));
+    }
+
     #[test]
     fn test_recovery_result_methods() {
         let success = RecoveryResult::Success("Success message".to_string());
@@ -635,33 +1064,171 @@ mod tests {
         assert_ne!(stream_key, buffer_key);
     }
 
+    #[tokio::test]
+    async fn test_attempts_expire_after_cooldown() {
+        let mut manager = create_test_recovery_manager();
+        let mut actions = RecordingActions::default();
+        manager.max_recovery_attempts = 2;
+        let player_error = PlayerError::Audio(AudioError::StreamError("Test error".to_string()));
+
+        let start = Instant::now();
+        assert!(manager.attempt_recovery_at(&player_error, &mut actions, start).await.is_retry());
+        let later = start + Duration::from_secs(300);
+        assert!(manager.attempt_recovery_at(&player_error, &mut actions, later).await.is_retry());
+        assert!(manager.attempt_recovery_at(&player_error, &mut actions, later).await.is_failed());
+
+        // Ten minutes after the first attempt only the second one still counts
+        let after_cooldown = start + DEFAULT_RECOVERY_COOLDOWN;
+        assert!(manager.attempt_recovery_at(&player_error, &mut actions, after_cooldown).await.is_retry());
+        assert!(manager.attempt_recovery_at(&player_error, &mut actions, after_cooldown).await.is_failed());
+
+        manager.set_cooldown(Duration::from_secs(60));
+        let minute_later = after_cooldown + Duration::from_secs(60);
+        assert!(manager.attempt_recovery_at(&player_error, &mut actions, minute_later).await.is_retry());
+    }
+
+    #[tokio::test]
+    async fn test_stream_error_retries_back_off() {
+        let mut manager = create_test_recovery_manager();
+        let mut actions = RecordingActions::default();
+        manager.max_recovery_attempts = 4;
+        let player_error = PlayerError::Audio(AudioError::StreamError("Device disconnected".to_string()));
+
+        let start = Instant::now();
+        let mut waits = Vec::new();
+        for offset_ms in [0, 100, 300] {
+            let result = manager.attempt_recovery_at(&player_error, &mut actions, start + Duration::from_millis(offset_ms)).await;
+            assert!(result.is_retry());
+            waits.push(manager.take_scheduled_retry(&player_error).unwrap());
+            assert!(result.message().ends_with(&format!("in {}ms", waits.last().unwrap().as_millis())));
+        }
+        assert_eq!(waits, [100, 200, 400].map(Duration::from_millis));
+
+        // Before the retry is due: the wait left is reported and nothing new is scheduled
+        let result = manager.attempt_recovery_at(&player_error, &mut actions, start + Duration::from_millis(350)).await;
+        assert!(result.is_retry());
+        assert!(result.message().contains("already due in 350ms"));
+        assert_eq!(manager.take_scheduled_retry(&player_error), None);
+
+        // A decoder timeout skips the track instead of waiting
+        let timeout = PlayerError::Audio(AudioError::StreamError(DECODE_TIMEOUT_MESSAGE.to_string()));
+        assert!(manager.attempt_recovery_at(&timeout, &mut actions, start).await.is_retry());
+        assert_eq!(manager.take_scheduled_retry(&timeout), None);
+    }
+
+    #[test]
+    fn test_retry_wait_is_capped() {
+        let mut scheduler = RetryScheduler::default();
+        let now = Instant::now();
+        let waits: Vec<Duration> = (0..12).map(|_| scheduler.schedule("stream", now)).collect();
+        assert_eq!(waits[1], Duration::from_millis(200));
+        assert_eq!(waits[8], Duration::from_millis(25_600));
+        assert_eq!(waits[9], RETRY_MAX_DELAY);
+        assert_eq!(waits[11], RETRY_MAX_DELAY);
+        assert_eq!(scheduler.remaining("stream", now + Duration::from_secs(10)), Some(Duration::from_secs(20)));
+        assert_eq!(scheduler.remaining("other", now), None);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_after_repeated_device_failures() {
+        let mut manager = create_test_recovery_manager();
+        let mut actions = RecordingActions::default();
+        // Different devices, so the per-error attempt limit never applies
+        let device_error = |n: u32| PlayerError::Audio(AudioError::DeviceNotFound { device: format!("DAC {}", n) });
+
+        let start = Instant::now();
+        for n in 1..=3 {
+            assert!(manager.attempt_recovery_at(&device_error(n), &mut actions, start).await.is_failed());
+        }
+        assert_eq!(actions.switches, 3);
+        let circuit = &manager.circuits["audio device"];
+        assert_eq!(circuit.failure_count, 3);
+        assert_eq!(circuit.open_until, Some(start + CIRCUIT_BREAKER_TIMEOUT));
+
+        // The fourth fails without trying to switch devices
+        let result = manager.attempt_recovery_at(&device_error(4), &mut actions, start + Duration::from_secs(29)).await;
+        assert!(result.is_failed());
+        assert!(result.message().contains("3 times in a row"));
+        assert_eq!(actions.switches, 3);
+        assert_eq!(manager.get_recovery_statistics().failures, 4);
+
+        // Other errors are unaffected
+        let stream_error = PlayerError::Audio(AudioError::StreamError("Test".to_string()));
+        assert!(manager.attempt_recovery_at(&stream_error, &mut actions, start).await.is_retry());
+
+        // Half-open after the timeout: one attempt goes through, and failing reopens the circuit
+        let reopened = start + CIRCUIT_BREAKER_TIMEOUT;
+        assert!(manager.attempt_recovery_at(&device_error(5), &mut actions, reopened).await.is_failed());
+        assert_eq!(actions.switches, 4);
+        assert!(manager.attempt_recovery_at(&device_error(6), &mut actions, reopened).await.is_failed());
+        assert_eq!(actions.switches, 4);
+
+        // A success closes it
+        actions.default_device = Some("Speakers".to_string());
+        let closed = reopened + CIRCUIT_BREAKER_TIMEOUT;
+        assert!(manager.attempt_recovery_at(&device_error(7), &mut actions, closed).await.is_success());
+        assert!(manager.circuits.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_statistics_count_attempts_since_startup() {
+        let mut manager = create_test_recovery_manager();
+        let mut actions = RecordingActions { default_device: Some("Speakers".to_string()), switches: 0 };
+        manager.max_recovery_attempts = 1;
+
+        let stream_error = PlayerError::Audio(AudioError::StreamError("Test".to_string()));
+        let device_error = PlayerError::Audio(AudioError::DeviceNotFound { device: "USB DAC".to_string() });
+        let queue_error = PlayerError::Queue(QueueError::EmptyQueue);
+        let _ = manager.attempt_recovery(&stream_error, &mut actions).await;
+        let _ = manager.attempt_recovery(&stream_error, &mut actions).await;
+        let _ = manager.attempt_recovery(&device_error, &mut actions).await;
+        let _ = manager.attempt_recovery(&queue_error, &mut actions).await;
+
+        let stats = manager.get_recovery_statistics();
+        assert_eq!(stats.attempts_by_category.get("audio stream"), Some(&2));
+        assert_eq!(stats.attempts_by_category.get("audio device"), Some(&1));
+        assert_eq!(stats.attempts_by_category.get("queue"), Some(&1));
+        assert_eq!(stats.total_attempts(), 4);
+        assert_eq!((stats.successes, stats.retries, stats.failures), (1, 1, 2));
+        assert_eq!(stats.total_errors_tracked, 2);
+
+        // Resetting forgets the attempts but not the totals
+        manager.reset();
+        let stats = manager.get_recovery_statistics();
+        assert_eq!(stats.total_errors_tracked, 0);
+        assert_eq!(stats.total_attempts(), 4);
+        assert!(manager.attempt_recovery(&stream_error, &mut actions).await.is_retry());
+    }
+
     #[tokio::test]
     async fn test_clear_recovery_attempts() {
         let mut manager = create_test_recovery_manager();
+        let mut actions = RecordingActions::default();
         
         let error = AudioError::StreamError("Test".to_string());
         let player_error = PlayerError::Audio(error);
         
         // Make an attempt to add to tracking
-        let _result = manager.attempt_recovery(&player_error).await;
+        let _result = manager.attempt_recovery(&player_error, &mut actions).await;
         assert!(!manager.recovery_attempts.is_empty());
         
         // Clear attempts
-        manager.clear_recovery_attempts();
+        manager.reset();
         assert!(manager.recovery_attempts.is_empty());
     }
 
     #[tokio::test]
     async fn test_reset_specific_recovery_attempts() {
         let mut manager = create_test_recovery_manager();
+        let mut actions = RecordingActions::default();
         
-        // Use two errors that both result in Retry so they stay in the map
+        // Use two errors that don't recover (no default device to switch to) so they stay in the map
         let error1 = PlayerError::Audio(AudioError::StreamError("Test1".to_string()));
         let error2 = PlayerError::Audio(AudioError::DeviceNotFound { device: "TestDevice".to_string() });
         
         // Make attempts for both errors
-        let _result1 = manager.attempt_recovery(&error1).await;
-        let _result2 = manager.attempt_recovery(&error2).await;
+        let _result1 = manager.attempt_recovery(&error1, &mut actions).await;
+        let _result2 = manager.attempt_recovery(&error2, &mut actions).await;
         
         assert_eq!(manager.recovery_attempts.len(), 2);
         
@@ -86,18 +86,18 @@ mod integration_tests {
         
         // Test adding file to queue
         let command = Commands::Queue {
-            action: QueueAction::Add { path: test_file.clone() }
+            action: QueueAction::Add { path: test_file.clone(), filters: Default::default() }
         };
         let result = app.execute_command(command).await;
         assert!(result.is_ok(), "Queue add command should succeed");
         
         // Verify queue has the file
-        assert_eq!(app.queue_manager.len(), 1);
-        assert!(!app.queue_manager.is_empty());
+        assert_eq!(app.queue_manager.lock().unwrap().len(), 1);
+        assert!(!app.queue_manager.lock().unwrap().is_empty());
         
         // Test queue list command
         let command = Commands::Queue {
-            action: QueueAction::List
+            action: QueueAction::List { compact: false, flat: false }
         };
         let result = app.execute_command(command).await;
         assert!(result.is_ok(), "Queue list command should succeed");
@@ -110,8 +110,8 @@ mod integration_tests {
         assert!(result.is_ok(), "Queue clear command should succeed");
         
         // Verify queue is empty
-        assert_eq!(app.queue_manager.len(), 0);
-        assert!(app.queue_manager.is_empty());
+        assert_eq!(app.queue_manager.lock().unwrap().len(), 0);
+        assert!(app.queue_manager.lock().unwrap().is_empty());
     }
 
     #[tokio::test]
@@ -123,13 +123,13 @@ mod integration_tests {
         
         // Test adding directory to queue
         let command = Commands::Queue {
-            action: QueueAction::Add { path: temp_dir.path().to_path_buf() }
+            action: QueueAction::Add { path: temp_dir.path().to_path_buf(), filters: Default::default() }
         };
         let result = app.execute_command(command).await;
         assert!(result.is_ok(), "Queue add directory command should succeed");
         
         // Verify queue has multiple files (should find 5 audio files)
-        assert_eq!(app.queue_manager.len(), 5);
+        assert_eq!(app.queue_manager.lock().unwrap().len(), 5);
         
         // Test queue position command
         let command = Commands::Queue {
@@ -150,18 +150,18 @@ mod integration_tests {
         
         // Add files to queue
         let command = Commands::Queue {
-            action: QueueAction::Add { path: test_file1 }
+            action: QueueAction::Add { path: test_file1, filters: Default::default() }
         };
         app.execute_command(command).await.expect("Failed to add file 1");
         
         let command = Commands::Queue {
-            action: QueueAction::Add { path: test_file2 }
+            action: QueueAction::Add { path: test_file2, filters: Default::default() }
         };
         app.execute_command(command).await.expect("Failed to add file 2");
         
         // Test saving playlist
         let command = Commands::Playlist {
-            action: PlaylistAction::Save { name: "test_playlist".to_string() }
+            action: PlaylistAction::Save { name: "test_playlist".to_string(), relative: false }
         };
         let result = app.execute_command(command).await;
         assert!(result.is_ok(), "Playlist save command should succeed");
@@ -174,8 +174,8 @@ mod integration_tests {
         assert!(result.is_ok(), "Playlist list command should succeed");
         
         // Clear queue and load playlist
-        app.queue_manager.clear();
-        assert!(app.queue_manager.is_empty());
+        app.queue_manager.lock().unwrap().clear();
+        assert!(app.queue_manager.lock().unwrap().is_empty());
         
         let command = Commands::Playlist {
             action: PlaylistAction::Load { name: "test_playlist".to_string() }
@@ -184,7 +184,7 @@ mod integration_tests {
         assert!(result.is_ok(), "Playlist load command should succeed");
         
         // Verify queue has the loaded tracks
-        assert_eq!(app.queue_manager.len(), 2);
+        assert_eq!(app.queue_manager.lock().unwrap().len(), 2);
         
         // Test deleting playlist
         let command = Commands::Playlist {
@@ -207,7 +207,7 @@ mod integration_tests {
         assert!(result.is_ok(), "Device list command should succeed");
         
         // Get available devices
-        let devices = app.audio_engine.device_manager().list_devices();
+        let devices = app.audio_engine.list_devices();
         
         if !devices.is_empty() {
             // Test setting device to first available device
@@ -219,7 +219,7 @@ mod integration_tests {
             assert!(result.is_ok(), "Device set command should succeed");
             
             // Verify device was set
-            let current_device = app.audio_engine.device_manager().current_device_name()
+            let current_device = app.audio_engine.current_device_name()
                 .unwrap_or(None);
             assert_eq!(current_device, Some(first_device));
         }
@@ -235,7 +235,7 @@ mod integration_tests {
         
         // Add file to queue first
         let command = Commands::Queue {
-            action: QueueAction::Add { path: test_file }
+            action: QueueAction::Add { path: test_file, filters: Default::default() }
         };
         app.execute_command(command).await.expect("Failed to add file to queue");
         
@@ -274,7 +274,7 @@ mod integration_tests {
         
         for file in [test_file1, test_file2, test_file3] {
             let command = Commands::Queue {
-                action: QueueAction::Add { path: file }
+                action: QueueAction::Add { path: file, filters: Default::default() }
             };
             app.execute_command(command).await.expect("Failed to add file to queue");
         }
@@ -285,7 +285,7 @@ mod integration_tests {
         assert!(result.is_ok(), "Next command should succeed");
         
         // Verify we moved to next track
-        assert_eq!(app.queue_manager.current_index(), 1);
+        assert_eq!(app.queue_manager.lock().unwrap().current_index(), 1);
         
         // Test previous command
         let command = Commands::Prev;
@@ -293,7 +293,7 @@ mod integration_tests {
         assert!(result.is_ok(), "Previous command should succeed");
         
         // Verify we moved back
-        assert_eq!(app.queue_manager.current_index(), 0);
+        assert_eq!(app.queue_manager.lock().unwrap().current_index(), 0);
     }
 
     #[tokio::test]
@@ -326,7 +326,7 @@ mod integration_tests {
         app.initialize().expect("Failed to initialize");
         
         // Test status command
-        let command = Commands::Status;
+        let command = Commands::Status { json: false };
         let result = app.execute_command(command).await;
         assert!(result.is_ok(), "Status command should succeed");
         
@@ -349,7 +349,7 @@ mod integration_tests {
         assert!(result.is_ok(), "Play command with file path should succeed");
         
         // Verify file was added to queue
-        assert_eq!(app.queue_manager.len(), 1);
+        assert_eq!(app.queue_manager.lock().unwrap().len(), 1);
     }
 
     #[tokio::test]
@@ -365,7 +365,7 @@ mod integration_tests {
         assert!(result.is_ok(), "Play command with directory path should succeed");
         
         // Verify files were added to queue (should find 5 audio files)
-        assert_eq!(app.queue_manager.len(), 5);
+        assert_eq!(app.queue_manager.lock().unwrap().len(), 5);
     }
 
     #[tokio::test]
@@ -424,7 +424,7 @@ mod integration_tests {
         
         // Test adding nonexistent file to queue
         let command = Commands::Queue {
-            action: QueueAction::Add { path: nonexistent_file }
+            action: QueueAction::Add { path: nonexistent_file, filters: Default::default() }
         };
         let result = app.execute_command(command).await;
         assert!(result.is_err(), "Adding nonexistent file should fail");
@@ -452,7 +452,7 @@ mod integration_tests {
         
         // Step 1: Add directory to queue
         let command = Commands::Queue {
-            action: QueueAction::Add { path: temp_dir.path().to_path_buf() }
+            action: QueueAction::Add { path: temp_dir.path().to_path_buf(), filters: Default::default() }
         };
         app.execute_command(command).await.expect("Failed to add directory");
         
@@ -473,12 +473,12 @@ mod integration_tests {
         
         // Step 5: Save playlist
         let command = Commands::Playlist {
-            action: PlaylistAction::Save { name: "workflow_test".to_string() }
+            action: PlaylistAction::Save { name: "workflow_test".to_string(), relative: false }
         };
         app.execute_command(command).await.expect("Failed to save playlist");
         
         // Step 6: Check status
-        let command = Commands::Status;
+        let command = Commands::Status { json: false };
         app.execute_command(command).await.expect("Failed to get status");
         
         // Step 7: Pause and resume
@@ -498,7 +498,7 @@ mod integration_tests {
         // Verify final state
         let status = app.get_current_status();
         assert_eq!(status.volume, 0.8);
-        assert_eq!(app.queue_manager.len(), 5);
+        assert_eq!(app.queue_manager.lock().unwrap().len(), 5);
     }
 
     #[tokio::test]
@@ -511,7 +511,7 @@ mod integration_tests {
         
         // Add file to queue
         let command = Commands::Queue {
-            action: QueueAction::Add { path: test_file }
+            action: QueueAction::Add { path: test_file, filters: Default::default() }
         };
         app.execute_command(command).await.expect("Failed to add file");
         
@@ -555,12 +555,12 @@ mod integration_tests {
         let test_file = create_test_audio_file(temp_dir.path(), "single", "flac");
         
         let command = Commands::Queue {
-            action: QueueAction::Add { path: test_file }
+            action: QueueAction::Add { path: test_file, filters: Default::default() }
         };
         app.execute_command(command).await.expect("Failed to add file");
         
         // Test navigation with single track (should wrap around)
-        let initial_index = app.queue_manager.current_index();
+        let initial_index = app.queue_manager.lock().unwrap().current_index();
         
         let command = Commands::Next;
         app.execute_command(command).await.expect("Failed to go to next");
@@ -569,7 +569,7 @@ mod integration_tests {
         app.execute_command(command).await.expect("Failed to go to previous");
         
         // Should be back at the same position
-        assert_eq!(app.queue_manager.current_index(), initial_index);
+        assert_eq!(app.queue_manager.lock().unwrap().current_index(), initial_index);
     }
 
     #[tokio::test]
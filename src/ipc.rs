@@ -0,0 +1,254 @@
+use crate::api::{ApiCommand, ApiRequest, ApiSender};
+use crate::cli::{Commands, PerfAction, PlaylistAction, QueueAction};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// How long the client waits for the running player to answer
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One line sent by the client
+#[derive(Debug, Serialize, Deserialize)]
+struct IpcRequest {
+    command: Commands,
+}
+
+/// One line sent back by the running player
+#[derive(Debug, Serialize, Deserialize)]
+struct IpcResponse {
+    ok: bool,
+    message: String,
+}
+
+/// Socket the running player listens on: `player.sock` in the app directory
+pub fn default_socket_path() -> PathBuf {
+    crate::config::app_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("player.sock")
+}
+
+/// Connection to a running player
+pub struct IpcClient {
+    stream: std::os::unix::net::UnixStream,
+}
+
+impl IpcClient {
+    /// Connect to the player listening on the default socket
+    pub fn connect() -> io::Result<Self> {
+        Self::connect_to(&default_socket_path())
+    }
+
+    /// Connect to the player listening on `path`; fails when none is running
+    pub fn connect_to(path: &Path) -> io::Result<Self> {
+        let stream = std::os::unix::net::UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+        Ok(Self { stream })
+    }
+
+    /// Have the running player execute `command`; returns its reply
+    ///
+    /// Relative paths are resolved against this process's working directory first.
+    pub fn send_command(&mut self, command: &Commands) -> io::Result<String> {
+        let request = IpcRequest { command: with_absolute_paths(command.clone()) };
+        let mut line = serde_json::to_string(&request).map_err(io::Error::from)?;
+        line.push('\n');
+        self.stream.write_all(line.as_bytes())?;
+
+        let mut reply = String::new();
+        BufReader::new(&self.stream).read_line(&mut reply)?;
+        if reply.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "running player closed the connection"));
+        }
+        let response: IpcResponse = serde_json::from_str(&reply).map_err(io::Error::from)?;
+        if response.ok {
+            Ok(response.message)
+        } else {
+            Err(io::Error::other(response.message))
+        }
+    }
+}
+
+/// Accepts commands from other invocations and passes them to the main loop
+pub struct IpcServer {
+    path: PathBuf,
+    task: JoinHandle<()>,
+}
+
+impl IpcServer {
+    /// Listen on `path`, replacing a socket left behind by a player that was killed
+    pub fn bind(path: &Path, requests: ApiSender) -> io::Result<Self> {
+        if path.exists() {
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, "another player is already listening"));
+            }
+            std::fs::remove_file(path)?;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let listener = tokio::net::UnixListener::bind(path)?;
+        let task = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(serve_connection(stream, requests.clone()));
+                    }
+                    Err(e) => {
+                        log::warn!("IPC accept failed: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Self { path: path.to_path_buf(), task })
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        self.task.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+async fn serve_connection(stream: tokio::net::UnixStream, requests: ApiSender) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let response = match serde_json::from_str::<IpcRequest>(&line) {
+            Ok(request) => execute(request.command, &requests).await,
+            Err(e) => IpcResponse { ok: false, message: format!("Invalid request: {}", e) },
+        };
+        let Ok(mut reply) = serde_json::to_string(&response) else { break };
+        reply.push('\n');
+        if writer.write_all(reply.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Run a forwarded command in the main loop and describe the outcome
+async fn execute(command: Commands, requests: &ApiSender) -> IpcResponse {
+    // Live views would take over the running player's terminal
+    if matches!(command, Commands::Watch { .. } | Commands::Perf { action: Some(PerfAction::Watch), .. }) {
+        return IpcResponse {
+            ok: false,
+            message: "Live views only run in the player's own terminal".to_string(),
+        };
+    }
+
+    // `status --json` is answered here so scripts can read it
+    let status_json = matches!(command, Commands::Status { json: true });
+    let api_command = if status_json { ApiCommand::Status } else { ApiCommand::Run(command) };
+
+    let (reply, answer) = oneshot::channel();
+    if requests.send(ApiRequest { command: api_command, reply }).is_err() {
+        return IpcResponse { ok: false, message: "Player is shutting down".to_string() };
+    }
+    match answer.await {
+        Ok(Ok(status)) if status_json => IpcResponse {
+            ok: true,
+            message: serde_json::to_string_pretty(&status).unwrap_or_default(),
+        },
+        Ok(Ok(_)) => IpcResponse { ok: true, message: "OK: Sent to the running player".to_string() },
+        Ok(Err(e)) => IpcResponse { ok: false, message: e.user_message() },
+        Err(_) => IpcResponse { ok: false, message: "Player is shutting down".to_string() },
+    }
+}
+
+/// Resolve relative paths that exist here, leaving keywords such as `favorites` alone
+fn with_absolute_paths(mut command: Commands) -> Commands {
+    let Ok(cwd) = std::env::current_dir() else {
+        return command;
+    };
+    let absolute = |path: &mut PathBuf| {
+        if path.is_relative() && path.exists() {
+            *path = cwd.join(&*path);
+        }
+    };
+    match &mut command {
+        Commands::Play { path: Some(path) } | Commands::Info { path: Some(path), .. } => absolute(path),
+        Commands::Queue {
+            action: QueueAction::Add { path, .. } | QueueAction::AddNext { path } | QueueAction::Insert { path, .. },
+        } => absolute(path),
+        Commands::Playlist { action: PlaylistAction::LoadSmart { library_path: Some(path), .. } } => absolute(path),
+        _ => {}
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use tempfile::TempDir;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_forwarded_volume_command_is_executed() {
+        let dir = TempDir::new().unwrap();
+        let socket = dir.path().join("player.sock");
+        let (sender, mut receiver) = crate::api::channel();
+        let _server = IpcServer::bind(&socket, sender).unwrap();
+
+        let client_socket = socket.clone();
+        let client = std::thread::spawn(move || {
+            let mut client = IpcClient::connect_to(&client_socket).unwrap();
+            client.send_command(&Commands::Volume { level: 35 })
+        });
+
+        let request = receiver.recv().await.unwrap();
+        assert!(matches!(request.command, ApiCommand::Run(Commands::Volume { level: 35 })));
+        request.reply.send(Ok(Value::Null)).unwrap();
+
+        let reply = tokio::task::spawn_blocking(move || client.join().unwrap()).await.unwrap();
+        assert!(reply.unwrap().starts_with("OK"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_errors_and_live_views_are_reported() {
+        let dir = TempDir::new().unwrap();
+        let socket = dir.path().join("player.sock");
+        let (sender, mut receiver) = crate::api::channel();
+        let _server = IpcServer::bind(&socket, sender).unwrap();
+
+        tokio::spawn(async move {
+            while let Some(request) = receiver.recv().await {
+                let _ = request.reply.send(Err(crate::error::QueueError::EmptyQueue.into()));
+            }
+        });
+
+        let client_socket = socket.clone();
+        let replies = tokio::task::spawn_blocking(move || {
+            let mut client = IpcClient::connect_to(&client_socket).unwrap();
+            (
+                client.send_command(&Commands::Play { path: None }),
+                client.send_command(&Commands::Watch { spectrum: false }),
+            )
+        })
+        .await
+        .unwrap();
+
+        assert!(replies.0.unwrap_err().to_string().contains("No tracks in queue"));
+        assert!(replies.1.unwrap_err().to_string().contains("own terminal"));
+    }
+
+    #[tokio::test]
+    async fn test_stale_socket_is_replaced() {
+        let dir = TempDir::new().unwrap();
+        let socket = dir.path().join("player.sock");
+        std::fs::write(&socket, "").unwrap();
+
+        assert!(IpcClient::connect_to(&socket).is_err());
+        let (sender, _receiver) = crate::api::channel();
+        let server = IpcServer::bind(&socket, sender).unwrap();
+        assert!(IpcClient::connect_to(&socket).is_ok());
+
+        drop(server);
+        assert!(!socket.exists());
+    }
+}
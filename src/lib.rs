@@ -1,3 +1,4 @@
+pub mod api;
 pub mod cli;
 pub mod audio;
 pub mod queue;
@@ -6,6 +7,8 @@ pub mod error;
 pub mod models;
 pub mod logging;
 pub mod error_recovery;
+#[cfg(unix)]
+pub mod ipc;
 
 pub use error::*;
 pub use models::*;
\ No newline at end of file
@@ -1,8 +1,13 @@
 use log::{info, warn, error, debug, trace};
 use std::time::{Duration, Instant};
-use std::sync::{Arc, Mutex};
+use std::sync::{mpsc, Arc, Mutex};
 use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
 /// Performance metrics collector for audio operations
 #[derive(Debug, Clone)]
@@ -35,18 +40,324 @@ pub struct AudioEvent {
     pub details: String,
 }
 
+impl AudioEvent {
+    /// Format the event as a single log file line
+    pub fn to_log_line(&self, format: LogFormat) -> String {
+        match format {
+            LogFormat::Text => {
+                let mut line = format!(
+                    "{} [{}] {}",
+                    self.timestamp.format("%Y-%m-%d %H:%M:%S%.3f"),
+                    self.event_type.as_str(),
+                    self.details
+                );
+                if let Some(duration) = self.duration {
+                    line.push_str(&format!(" ({:.2}ms)", duration.as_secs_f64() * 1000.0));
+                }
+                line
+            }
+            LogFormat::Json => {
+                let mut value = serde_json::json!({
+                    "timestamp": self.timestamp.to_rfc3339(),
+                    "event": self.event_type.as_str(),
+                    "details": self.details,
+                });
+                if let Some(duration) = self.duration {
+                    value["duration_ms"] = serde_json::json!(duration.as_secs_f64() * 1000.0);
+                }
+                value.to_string()
+            }
+        }
+    }
+}
+
+/// Line format of the persistent event log and the diagnostic log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Appends event lines to a file from a background thread so logging never blocks the caller
+struct EventLogWriter {
+    format: LogFormat,
+    sender: Option<mpsc::Sender<String>>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl EventLogWriter {
+    fn spawn(path: &Path, format: LogFormat, max_bytes: u64) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = open_append(path)?;
+        let mut size = file.metadata()?.len();
+        let path = path.to_path_buf();
+        let (sender, receiver) = mpsc::channel::<String>();
+
+        let handle = thread::Builder::new()
+            .name("event-log".to_string())
+            .spawn(move || {
+                let mut writer = BufWriter::new(file);
+                for line in receiver {
+                    let line_len = line.len() as u64 + 1;
+                    if max_bytes > 0 && size > 0 && size + line_len > max_bytes {
+                        let _ = writer.flush();
+                        match rotate_event_log(&path) {
+                            Ok(file) => {
+                                writer = BufWriter::new(file);
+                                size = 0;
+                            }
+                            Err(e) => warn!("Could not rotate event log '{}': {}", path.display(), e),
+                        }
+                    }
+                    if writeln!(writer, "{}", line).and_then(|_| writer.flush()).is_ok() {
+                        size += line_len;
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            format,
+            sender: Some(sender),
+            handle: Some(handle),
+        })
+    }
+
+    fn write(&self, event: &AudioEvent) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(event.to_log_line(self.format));
+        }
+    }
+}
+
+impl Drop for EventLogWriter {
+    fn drop(&mut self) {
+        // Closing the channel lets the writer drain what is queued and exit
+        self.sender.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Path the event log is moved to when it is rotated (`player.log` -> `player.log.1`)
+pub fn rotated_log_path(path: &Path) -> PathBuf {
+    numbered_log_path(path, 1)
+}
+
+/// `n`th rotated copy of a log file (`player.log.n`)
+pub fn numbered_log_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", n));
+    path.with_file_name(name)
+}
+
+fn rotate_event_log(path: &Path) -> io::Result<File> {
+    fs::rename(path, rotated_log_path(path))?;
+    open_append(path)
+}
+
+/// Where the diagnostic log goes and how much of it to keep
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogConfig {
+    pub path: PathBuf,
+    /// Rotate once a line would take the file past this size (0 = never)
+    pub max_bytes: u64,
+    /// Rotated files kept, from `player.log.1` (newest) to `player.log.N`
+    pub max_files: usize,
+    /// Line format, shared with stderr
+    pub format: LogFormat,
+}
+
+/// Writes `log` records to a file, rotating it through numbered copies and deleting the oldest
+pub struct FileLogger {
+    config: LogConfig,
+    file: Mutex<File>,
+}
+
+impl FileLogger {
+    /// Open the log for appending, creating its directory if needed
+    pub fn open(config: &LogConfig) -> io::Result<Self> {
+        if let Some(parent) = config.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(Self {
+            config: config.clone(),
+            file: Mutex::new(open_append(&config.path)?),
+        })
+    }
+
+    /// Append a line, rotating first when it would not fit under `max_bytes`
+    pub fn write_line(&self, line: &str) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let line_len = line.len() as u64 + 1;
+        let size = file.metadata()?.len();
+        if self.config.max_bytes > 0 && size > 0 && size + line_len > self.config.max_bytes {
+            *file = self.rotate()?;
+        }
+        writeln!(file, "{}", line)
+    }
+
+    /// Shift `player.log.N` up by one, dropping those past `max_files`, and start a new file
+    fn rotate(&self) -> io::Result<File> {
+        let path = &self.config.path;
+        if self.config.max_files == 0 {
+            return File::create(path);
+        }
+        // Also catches copies left over from a larger `max_log_files`
+        let mut oldest = self.config.max_files;
+        while numbered_log_path(path, oldest).exists() {
+            fs::remove_file(numbered_log_path(path, oldest))?;
+            oldest += 1;
+        }
+        for n in (1..self.config.max_files).rev() {
+            let from = numbered_log_path(path, n);
+            if from.exists() {
+                fs::rename(&from, numbered_log_path(path, n + 1))?;
+            }
+        }
+        fs::rename(path, numbered_log_path(path, 1))?;
+        open_append(path)
+    }
+}
+
+impl log::Log for FileLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        // Nowhere left to report a failure to write the log
+        let _ = self.write_line(&format_record(record, self.config.format));
+    }
+
+    fn flush(&self) {
+        let _ = self.file.lock().unwrap_or_else(|e| e.into_inner()).flush();
+    }
+}
+
+/// Sends records to stderr as before and, at the same level, to the diagnostic log file
+struct TeeLogger {
+    console: env_logger::Logger,
+    file: FileLogger,
+}
+
+impl log::Log for TeeLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.console.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.console.matches(record) {
+            self.console.log(record);
+            self.file.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.console.flush();
+        self.file.flush();
+    }
+}
+
+/// One log line: time, level, source location and message, or the same as a JSON object
+fn format_record(record: &log::Record, format: LogFormat) -> String {
+    match format {
+        LogFormat::Text => format!(
+            "{} [{}] [{}:{}] {}",
+            chrono::Utc::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            record.level(),
+            record.file().unwrap_or("unknown"),
+            record.line().unwrap_or(0),
+            record.args()
+        ),
+        LogFormat::Json => {
+            let module = record.module_path().unwrap_or(record.target());
+            let mut extra = JsonFields(serde_json::Map::new());
+            // Collecting into a map cannot fail
+            let _ = record.key_values().visit(&mut extra);
+            let line = JsonLogLine {
+                timestamp: Utc::now().to_rfc3339(),
+                level: record.level().as_str(),
+                // Our own modules without the crate name, e.g. `audio::engine`
+                module: module.strip_prefix(concat!(env!("CARGO_CRATE_NAME"), "::")).unwrap_or(module),
+                message: record.args().to_string(),
+                extra: extra.0,
+            };
+            serde_json::to_string(&line).unwrap_or_default()
+        }
+    }
+}
+
+/// A diagnostic log line in the JSON format
+#[derive(Serialize)]
+struct JsonLogLine<'a> {
+    timestamp: String,
+    level: &'a str,
+    module: &'a str,
+    message: String,
+    /// Key-value pairs given with the record, such as `sample_rate` or `path`
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Collects a record's key-value pairs as JSON numbers, booleans and strings
+struct JsonFields(serde_json::Map<String, serde_json::Value>);
+
+impl<'kvs> log::kv::VisitSource<'kvs> for JsonFields {
+    fn visit_pair(&mut self, key: log::kv::Key<'kvs>, value: log::kv::Value<'kvs>) -> Result<(), log::kv::Error> {
+        let json = if let Some(number) = value.to_u64() {
+            number.into()
+        } else if let Some(number) = value.to_i64() {
+            number.into()
+        } else if let Some(number) = value.to_f64() {
+            number.into()
+        } else if let Some(flag) = value.to_bool() {
+            flag.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.as_str().to_string(), json);
+        Ok(())
+    }
+}
+
+/// Read the last `count` lines of the event log, including the rotated file
+pub fn read_event_log(path: &Path, count: usize) -> io::Result<Vec<String>> {
+    let mut lines = Vec::new();
+    for file in [rotated_log_path(path), path.to_path_buf()] {
+        match fs::read_to_string(&file) {
+            Ok(content) => lines.extend(content.lines().map(str::to_string)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    let skip = lines.len().saturating_sub(count);
+    Ok(lines.split_off(skip))
+}
+
 #[derive(Debug, Clone)]
 pub enum AudioEventType {
     PlaybackStarted,
     PlaybackPaused,
     PlaybackStopped,
     TrackChanged,
+    TrackFinished,
     DeviceChanged,
     BufferUnderrun,
     SeekOperation,
     DecodeError,
     StreamError,
     PerformanceWarning,
+    Clipping,
+    BufferAdjusted,
+    RecoveryAttempt,
 }
 
 impl AudioEventType {
@@ -56,12 +367,16 @@ impl AudioEventType {
             AudioEventType::PlaybackPaused => "PLAYBACK_PAUSED",
             AudioEventType::PlaybackStopped => "PLAYBACK_STOPPED",
             AudioEventType::TrackChanged => "TRACK_CHANGED",
+            AudioEventType::TrackFinished => "TRACK_FINISHED",
             AudioEventType::DeviceChanged => "DEVICE_CHANGED",
             AudioEventType::BufferUnderrun => "BUFFER_UNDERRUN",
             AudioEventType::SeekOperation => "SEEK_OPERATION",
             AudioEventType::DecodeError => "DECODE_ERROR",
             AudioEventType::StreamError => "STREAM_ERROR",
             AudioEventType::PerformanceWarning => "PERFORMANCE_WARNING",
+            AudioEventType::Clipping => "CLIPPING",
+            AudioEventType::BufferAdjusted => "BUFFER_ADJUSTED",
+            AudioEventType::RecoveryAttempt => "RECOVERY_ATTEMPT",
         }
     }
 }
@@ -72,6 +387,7 @@ pub struct AudioLogger {
     events: Arc<Mutex<VecDeque<AudioEvent>>>,
     max_events: usize,
     performance_metrics: Arc<Mutex<PerformanceMetrics>>,
+    event_log: Option<Arc<EventLogWriter>>,
 }
 
 impl AudioLogger {
@@ -80,11 +396,43 @@ impl AudioLogger {
             events: Arc::new(Mutex::new(VecDeque::new())),
             max_events: 1000, // Keep last 1000 events
             performance_metrics: Arc::new(Mutex::new(PerformanceMetrics::new())),
+            event_log: None,
         }
     }
 
-    /// Initialize logging system with appropriate log level
-    pub fn init() -> Result<(), Box<dyn std::error::Error>> {
+    /// Create a logger that also appends every event to a file, rotating it once it exceeds `max_bytes` (0 = never)
+    pub fn with_event_log(path: &Path, format: LogFormat, max_bytes: u64) -> io::Result<Self> {
+        let mut logger = Self::new();
+        logger.event_log = Some(Arc::new(EventLogWriter::spawn(path, format, max_bytes)?));
+        Ok(logger)
+    }
+
+    /// Initialize logging to stderr at the level in `HIRES_PLAYER_LOG_LEVEL`, with lines in `format`
+    pub fn init(format: LogFormat) -> Result<(), Box<dyn std::error::Error>> {
+        let (mut builder, log_level) = Self::console_builder(format);
+        builder.try_init()?;
+        
+        info!("Audio player logging initialized with level: {}", log_level);
+        Ok(())
+    }
+
+    /// Initialize logging to stderr and to the rotating log file described by `config`
+    ///
+    /// Fails without installing anything when the file cannot be opened.
+    pub fn init_file_logging(config: &LogConfig) -> Result<(), Box<dyn std::error::Error>> {
+        let file = FileLogger::open(config)?;
+        let (mut builder, log_level) = Self::console_builder(config.format);
+        let console = builder.build();
+        let max_level = console.filter();
+        log::set_boxed_logger(Box::new(TeeLogger { console, file }))?;
+        log::set_max_level(max_level);
+
+        info!("Audio player logging initialized with level: {}, writing to {}", log_level, config.path.display());
+        Ok(())
+    }
+
+    /// Stderr logger at the level in `HIRES_PLAYER_LOG_LEVEL`, and that level
+    fn console_builder(format: LogFormat) -> (env_logger::Builder, String) {
         // Set log level based on environment variable or default to Info
         let log_level = std::env::var("HIRES_PLAYER_LOG_LEVEL")
             .unwrap_or_else(|_| "info".to_string());
@@ -92,18 +440,7 @@ impl AudioLogger {
         let mut builder = env_logger::Builder::new();
         
         // Set custom format for better readability
-        builder.format(|buf, record| {
-            use std::io::Write;
-            writeln!(
-                buf,
-                "{} [{}] [{}:{}] {}",
-                chrono::Utc::now().format("%Y-%m-%d %H:%M:%S%.3f"),
-                record.level(),
-                record.file().unwrap_or("unknown"),
-                record.line().unwrap_or(0),
-                record.args()
-            )
-        });
+        builder.format(move |buf, record| writeln!(buf, "{}", format_record(record, format)));
 
         // Parse and set log level
         match log_level.to_lowercase().as_str() {
@@ -114,11 +451,7 @@ impl AudioLogger {
             "error" => builder.filter_level(log::LevelFilter::Error),
             _ => builder.filter_level(log::LevelFilter::Info),
         };
-
-        builder.try_init()?;
-        
-        info!("Audio player logging initialized with level: {}", log_level);
-        Ok(())
+        (builder, log_level)
     }
 
     /// Log an audio event
@@ -130,6 +463,10 @@ impl AudioLogger {
             details: details.clone(),
         };
 
+        if let Some(event_log) = &self.event_log {
+            event_log.write(&event);
+        }
+
         // Add to event history
         {
             let mut events = self.events.lock().unwrap();
@@ -141,29 +478,31 @@ impl AudioLogger {
             }
         }
 
-        // Log to standard logger based on event type
+        // Log to standard logger based on event type, tagged with the event for structured output
         match event_type {
             AudioEventType::PlaybackStarted | 
             AudioEventType::PlaybackPaused | 
             AudioEventType::PlaybackStopped |
-            AudioEventType::TrackChanged => {
-                info!("[{}] {}", event_type.as_str(), details);
+            AudioEventType::TrackChanged |
+            AudioEventType::TrackFinished |
+            AudioEventType::RecoveryAttempt => {
+                info!(event = event_type.as_str(); "[{}] {}", event_type.as_str(), details);
             }
             AudioEventType::DeviceChanged => {
-                info!("[{}] {}", event_type.as_str(), details);
+                info!(event = event_type.as_str(); "[{}] {}", event_type.as_str(), details);
             }
             AudioEventType::SeekOperation => {
-                debug!("[{}] {} (took: {:?})", event_type.as_str(), details, duration);
+                debug!(event = event_type.as_str(); "[{}] {} (took: {:?})", event_type.as_str(), details, duration);
             }
-            AudioEventType::BufferUnderrun => {
-                warn!("[{}] {}", event_type.as_str(), details);
+            AudioEventType::BufferUnderrun | AudioEventType::Clipping | AudioEventType::BufferAdjusted => {
+                warn!(event = event_type.as_str(); "[{}] {}", event_type.as_str(), details);
             }
             AudioEventType::DecodeError | 
             AudioEventType::StreamError => {
-                error!("[{}] {}", event_type.as_str(), details);
+                error!(event = event_type.as_str(); "[{}] {}", event_type.as_str(), details);
             }
             AudioEventType::PerformanceWarning => {
-                warn!("[{}] {} (duration: {:?})", event_type.as_str(), details, duration);
+                warn!(event = event_type.as_str(); "[{}] {} (duration: {:?})", event_type.as_str(), details, duration);
             }
         }
     }
@@ -204,6 +543,15 @@ impl AudioLogger {
         self.log_event(AudioEventType::TrackChanged, details, None);
     }
 
+    /// Log a track that played through to its end
+    pub fn log_track_finished(&self, track_path: &str) {
+        self.log_event(
+            AudioEventType::TrackFinished,
+            format!("Finished playing: {}", track_path),
+            None,
+        );
+    }
+
     /// Log device change event
     pub fn log_device_changed(&self, from_device: Option<&str>, to_device: &str, switch_time: Duration) {
         let details = match from_device {
@@ -229,6 +577,34 @@ impl AudioLogger {
         );
     }
 
+    /// Log underruns noticed by the output callback since the last check
+    pub fn log_underruns_detected(&self, new_underruns: u64, buffer_fill: f32) {
+        self.log_event(
+            AudioEventType::BufferUnderrun,
+            format!("{} buffer underrun(s) detected (buffer fill: {:.1}%)",
+                new_underruns, buffer_fill * 100.0),
+            None,
+        );
+    }
+
+    /// Log an automatic buffer adjustment made after repeated underruns
+    pub fn log_buffer_adjustment(&self, description: &str) {
+        self.log_event(
+            AudioEventType::BufferAdjusted,
+            format!("Adaptive buffering: {}", description),
+            None,
+        );
+    }
+
+    /// Log first clipping occurrence on a track
+    pub fn log_clipping(&self, track_path: &str, clipped_samples: u64) {
+        self.log_event(
+            AudioEventType::Clipping,
+            format!("Output clipping on '{}' ({} samples so far)", track_path, clipped_samples),
+            None,
+        );
+    }
+
     /// Log seek operation
     pub fn log_seek_operation(&self, from_position: Duration, to_position: Duration, seek_time: Duration) {
         self.log_event(
@@ -263,6 +639,15 @@ impl AudioLogger {
         );
     }
 
+    /// Log an automatic recovery attempt and its outcome
+    pub fn log_recovery_attempt(&self, error: &str, attempt: u32, outcome: &str) {
+        self.log_event(
+            AudioEventType::RecoveryAttempt,
+            format!("Recovery attempt {} for '{}': {}", attempt, error, outcome),
+            None,
+        );
+    }
+
     /// Log performance warning
     pub fn log_performance_warning(&self, operation: &str, duration: Duration, threshold: Duration) {
         self.log_event(
@@ -599,4 +984,138 @@ mod tests {
         assert!(event_types.contains(&"STREAM_ERROR"));
         assert!(event_types.contains(&"PERFORMANCE_WARNING"));
     }
+
+    #[test]
+    fn test_event_log_line_formats() {
+        let event = AudioEvent {
+            timestamp: Utc::now(),
+            event_type: AudioEventType::DeviceChanged,
+            duration: Some(Duration::from_millis(12)),
+            details: "Audio device set to 'DAC'".to_string(),
+        };
+
+        let text = event.to_log_line(LogFormat::Text);
+        assert!(text.contains("[DEVICE_CHANGED] Audio device set to 'DAC' (12.00ms)"));
+
+        let json: serde_json::Value = serde_json::from_str(&event.to_log_line(LogFormat::Json)).unwrap();
+        assert_eq!(json["event"], "DEVICE_CHANGED");
+        assert_eq!(json["details"], "Audio device set to 'DAC'");
+        assert_eq!(json["duration_ms"], 12.0);
+        assert!(json["timestamp"].is_string());
+    }
+
+    #[test]
+    fn test_event_log_file_and_rotation() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("logs").join("player.log");
+
+        {
+            let logger = AudioLogger::with_event_log(&path, LogFormat::Text, 200).unwrap();
+            for i in 0..6 {
+                logger.log_track_finished(&format!("/music/track{}.flac", i));
+            }
+            logger.log_recovery_attempt("Buffer underrun", 1, "retry");
+            // Dropping the last handle flushes the writer thread
+        }
+
+        assert!(rotated_log_path(&path).exists());
+        assert!(fs::metadata(&path).unwrap().len() <= 200);
+
+        let lines = read_event_log(&path, 3).unwrap();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("[TRACK_FINISHED] Finished playing: /music/track4.flac"));
+        assert!(lines[2].contains("[RECOVERY_ATTEMPT] Recovery attempt 1 for 'Buffer underrun': retry"));
+
+        assert!(read_event_log(&temp_dir.path().join("missing.log"), 5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_file_logger_rotation_and_retention() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("logs").join("player.log");
+        let config = LogConfig { path: path.clone(), max_bytes: 100, max_files: 2, format: LogFormat::Text };
+        let logger = FileLogger::open(&config).unwrap();
+
+        // Two 40-byte lines fit; the third starts a new file
+        let line = |n: usize| format!("{:0>39}", n);
+        for n in 0..3 {
+            logger.write_line(&line(n)).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("{}\n", line(2)));
+        assert_eq!(fs::read_to_string(numbered_log_path(&path, 1)).unwrap(), format!("{}\n{}\n", line(0), line(1)));
+
+        // Older files move up one place and the oldest past the limit is deleted
+        for n in 3..7 {
+            logger.write_line(&line(n)).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("{}\n", line(6)));
+        assert_eq!(fs::read_to_string(numbered_log_path(&path, 1)).unwrap(), format!("{}\n{}\n", line(4), line(5)));
+        assert_eq!(fs::read_to_string(numbered_log_path(&path, 2)).unwrap(), format!("{}\n{}\n", line(2), line(3)));
+        assert!(!numbered_log_path(&path, 3).exists());
+
+        // Records go through the `log` interface as formatted lines
+        log::Log::log(&logger, &log::Record::builder()
+            .args(format_args!("Device lost"))
+            .level(log::Level::Warn)
+            .file(Some("src/audio/engine.rs"))
+            .line(Some(42))
+            .build());
+        let last = fs::read_to_string(&path).unwrap();
+        assert!(last.lines().last().unwrap().ends_with("[WARN] [src/audio/engine.rs:42] Device lost"));
+    }
+
+    /// Keeps what the console logger prints
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_log_lines() {
+        let output = Captured::default();
+        let (mut builder, _) = AudioLogger::console_builder(LogFormat::Json);
+        let console = builder
+            .filter_level(log::LevelFilter::Trace)
+            .target(env_logger::Target::Pipe(Box::new(output.clone())))
+            .build();
+
+        let path = Path::new("/music/album/01 track.flac");
+        let display = path.display();
+        let fields: [(&str, log::kv::Value); 3] = [
+            ("sample_rate", 96_000u32.into()),
+            ("bit_depth", 24u16.into()),
+            ("path", log::kv::Value::from_display(&display)),
+        ];
+        log::Log::log(&console, &log::Record::builder()
+            .args(format_args!("Decoding '{}'", path.display()))
+            .level(log::Level::Info)
+            .module_path(Some(concat!(env!("CARGO_CRATE_NAME"), "::audio::engine")))
+            .key_values(&fields)
+            .build());
+
+        let printed = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(printed.lines().count(), 1);
+        let line: serde_json::Value = serde_json::from_str(&printed).unwrap();
+        for key in ["timestamp", "level", "module", "message", "extra"] {
+            assert!(line.get(key).is_some(), "missing {}", key);
+        }
+        assert!(DateTime::parse_from_rfc3339(line["timestamp"].as_str().unwrap()).is_ok());
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["module"], "audio::engine");
+        assert_eq!(line["message"], "Decoding '/music/album/01 track.flac'");
+        assert_eq!(line["extra"], serde_json::json!({
+            "sample_rate": 96000,
+            "bit_depth": 24,
+            "path": "/music/album/01 track.flac",
+        }));
+    }
 }
\ No newline at end of file
@@ -1,3 +1,4 @@
+mod api;
 mod cli;
 mod audio;
 mod queue;
@@ -6,28 +7,109 @@ mod error;
 mod models;
 mod logging;
 mod error_recovery;
+#[cfg(unix)]
+mod ipc;
 
 #[cfg(test)]
 mod integration_tests;
+#[cfg(test)]
+mod controller_tests;
 
-use cli::{CliApp, Commands, ParseError};
+use cli::{CliApp, Commands, ParseError, SeekOffset};
 use error::{PlayerError, AudioError};
 use models::PlayerStatus;
 use queue::QueueManager;
-use audio::AudioEngine;
+use audio::AudioEngineControl;
+use audio::channels::ChannelMode;
 use logging::AudioLogger;
-use error_recovery::{ErrorRecoveryManager, RecoveryResult};
+use error_recovery::{ErrorRecoveryManager, RecoveryActions, RecoveryResult};
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use log::{info, warn, error};
 
 /// Main application controller that coordinates all components
 pub struct AppController {
-    audio_engine: audio::engine::AudioEngineImpl,
+    audio_engine: Box<dyn AudioEngineControl>,
     queue_manager: std::sync::Arc<std::sync::Mutex<queue::QueueManagerImpl>>,
     config_manager: config::ConfigManager,
     cli_app: CliApp,
     logger: AudioLogger,
     error_recovery: ErrorRecoveryManager,
+    device_monitor: Option<audio::DeviceMonitor>,
+    config_watcher: Option<config::watch::ConfigWatcher>,
+    /// Started by the first `watch-dir add`, or at startup for saved `watch_dirs`
+    directory_watcher: Option<queue::watch::DirectoryWatcher>,
+    /// A reload of `config.toml` failed and has been reported; cleared by the next good reload
+    config_reload_failed: bool,
+    /// Underruns already written to the event log
+    logged_underruns: u64,
+    /// `loop a` mark: file and position within it, waiting for `loop b`
+    loop_start: Option<(std::path::PathBuf, std::time::Duration)>,
+    /// Star ratings and favorites, read on first use
+    ratings: queue::ratings::RatingStore,
+    /// Loudness measured by `scan-loudness`
+    loudness: queue::loudness::LoudnessStore,
+    eq_presets: audio::eq_presets::EqPresetStore,
+    /// Played tracks and the resumable session
+    history: queue::history::PlayHistory,
+    /// How long the current track has been playing
+    play_tracker: queue::history::PlayTracker,
+    /// Every track started since the player was launched
+    session_history: models::SessionHistory,
+    /// Output device names offered by tab completion
+    device_names: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    /// Handed to the REST API server to forward requests to the main loop
+    api_sender: api::ApiSender,
+    /// Taken by whichever loop is running
+    api_requests: Option<api::ApiReceiver>,
+    api_server: Option<api::RestApiServer>,
+    /// Status snapshots for WebSocket clients
+    status_events: tokio::sync::broadcast::Sender<PlayerStatus>,
+    /// Commands forwarded by later invocations of the player
+    #[cfg(unix)]
+    ipc_server: Option<ipc::IpcServer>,
+    /// Where notifications from the engine poll are printed
+    console: cli::ConsoleOutput,
+    /// Unplayable tracks skipped in a row, and since the queue last finished
+    consecutive_failures: u32,
+    skipped_tracks: usize,
+    /// `verify` running in the background, and the flag that cancels it
+    verify_job: Option<(std::sync::Arc<std::sync::atomic::AtomicBool>, tokio::task::JoinHandle<()>)>,
+    /// `scan-loudness` running in the background, and the flag that cancels it
+    loudness_job: Option<(std::sync::Arc<std::sync::atomic::AtomicBool>, tokio::task::JoinHandle<()>)>,
+    /// `export` to a WAV or FLAC file running in the background, and the flag that cancels it
+    export_job: Option<(std::sync::Arc<std::sync::atomic::AtomicBool>, tokio::task::JoinHandle<()>)>,
+}
+
+/// Waiting this long for the pre-buffer is announced, so a slow source isn't mistaken for a hang
+const BUFFERING_NOTICE_AFTER: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Bookkeeping carried between engine polls
+struct TickState {
+    announced_queue_finished: bool,
+    /// Said "Buffering…" for the current wait on the pre-buffer
+    announced_buffering: bool,
+    /// Refresh CPU/memory figures about once a second so `perf` is never stale
+    last_perf_update: std::time::Instant,
+    /// Track that is playing according to the decoder, for the event log
+    playing_path: Option<String>,
+    /// Count plays for the history; keep the session saved in case the player is killed
+    last_tick: std::time::Instant,
+    last_session_save: std::time::Instant,
+}
+
+impl TickState {
+    fn new() -> Self {
+        let now = std::time::Instant::now();
+        Self {
+            announced_queue_finished: false,
+            announced_buffering: false,
+            last_perf_update: now,
+            playing_path: None,
+            last_tick: now,
+            last_session_save: now,
+        }
+    }
 }
 
 impl AppController {
@@ -40,16 +122,47 @@ impl AppController {
         if std::env::var("RUST_LOG").is_err() {
             std::env::set_var("RUST_LOG", "warn");
         }
-        if let Err(e) = AudioLogger::init() {
+        // The config says where the log file goes; it reports its own problems on stderr
+        let config_manager = config::ConfigManager::new()?;
+        let log_format = config_manager.get_config().log_format;
+        let logging = match config_manager.get_config().diagnostic_log() {
+            Some(log_config) => AudioLogger::init_file_logging(&log_config).or_else(|e| {
+                eprintln!("Warning: Not writing a log file to '{}': {}", log_config.path.display(), e);
+                AudioLogger::init(log_format)
+            }),
+            None => AudioLogger::init(log_format),
+        };
+        if let Err(e) = logging {
             eprintln!("Warning: Failed to initialize logging: {}", e);
         }
 
-        let audio_engine = audio::engine::AudioEngineImpl::new()?;
-        let queue_manager = std::sync::Arc::new(std::sync::Mutex::new(queue::QueueManagerImpl::new()));
-        let config_manager = config::ConfigManager::new()?;
+        let audio_engine = audio::engine::AudioEngineImpl::new_with_config(config_manager.get_config())?;
+        let state_dir = config::app_dir().unwrap_or_default();
+        Self::with_engine(Box::new(audio_engine), config_manager, &state_dir)
+    }
+
+    /// Controller around an existing engine, keeping ratings, EQ presets and history in `state_dir`
+    pub fn with_engine(
+        audio_engine: Box<dyn AudioEngineControl>,
+        config_manager: config::ConfigManager,
+        state_dir: &Path,
+    ) -> Result<Self, PlayerError> {
+        let config = config_manager.get_config();
+        let queue_manager = queue::QueueManagerImpl::with_playlist_directory(config.playlist_directory.clone())?;
+        let queue_manager = std::sync::Arc::new(std::sync::Mutex::new(queue_manager));
         let cli_app = CliApp::new()?;
-        let logger = AudioLogger::new();
-        let error_recovery = ErrorRecoveryManager::new(logger.clone());
+        let logger = match config.event_log_path() {
+            Some(path) => AudioLogger::with_event_log(path, config.log_format, config.log_max_bytes)
+                .unwrap_or_else(|e| {
+                    eprintln!("Warning: Could not open event log '{}': {}", path.display(), e);
+                    AudioLogger::new()
+                }),
+            None => AudioLogger::new(),
+        };
+        let mut error_recovery = ErrorRecoveryManager::new(logger.clone());
+        error_recovery.set_cooldown(std::time::Duration::from_secs(config.recovery_cooldown_secs));
+        let history = queue::history::PlayHistory::open(state_dir, config.history_size);
+        let (api_sender, api_requests) = api::channel();
 
         info!("Application controller initialized successfully");
 
@@ -60,6 +173,31 @@ impl AppController {
             cli_app,
             logger,
             error_recovery,
+            device_monitor: None,
+            config_watcher: None,
+            directory_watcher: None,
+            config_reload_failed: false,
+            logged_underruns: 0,
+            loop_start: None,
+            ratings: queue::ratings::RatingStore::new(state_dir.join(queue::ratings::RATINGS_FILE)),
+            loudness: queue::loudness::LoudnessStore::new(state_dir.join(queue::loudness::LOUDNESS_FILE)),
+            eq_presets: audio::eq_presets::EqPresetStore::new(state_dir.join(audio::eq_presets::EQ_PRESETS_FILE)),
+            history,
+            play_tracker: queue::history::PlayTracker::new(),
+            session_history: models::SessionHistory::new(),
+            device_names: std::sync::Arc::new(std::sync::Mutex::new(Vec::new())),
+            api_sender,
+            api_requests: Some(api_requests),
+            api_server: None,
+            status_events: api::status_channel(),
+            #[cfg(unix)]
+            ipc_server: None,
+            console: cli::ConsoleOutput::stdout(),
+            consecutive_failures: 0,
+            skipped_tracks: 0,
+            verify_job: None,
+            loudness_job: None,
+            export_job: None,
         })
     }
 
@@ -80,6 +218,9 @@ impl AppController {
 
         // Set gapless playback preference
         self.audio_engine.set_gapless_enabled(config.enable_gapless);
+        self.queue_manager.lock().unwrap().set_skip_duplicates(config.skip_duplicates);
+        self.queue_manager.lock().unwrap().set_follow_symlinks(config.follow_symlinks);
+        self.queue_manager.lock().unwrap().set_nomusic_files(config.nomusic_files.clone());
 
         Ok(())
     }
@@ -90,59 +231,57 @@ impl AppController {
             Commands::Play { path } => {
                 if let Some(path) = path {
                     // Add file/directory to queue and start playback
-                    if path.is_dir() {
-                        self.queue_manager.lock().unwrap().add_directory(&path)?;
+                    if Self::is_keyword(&path, "favorites") {
+                        self.enqueue_favorites()?;
+                    } else if Self::is_keyword(&path, "last") {
+                        let Some(last) = self.history.last().cloned() else {
+                            println!("No tracks played yet");
+                            return Ok(());
+                        };
+                        self.enqueue_and_select(&last.path, last.start_offset)?;
+                    } else if path.is_dir() {
+                        self.queue_manager.lock().unwrap().add_directory(&path, &queue::DirectoryAddOptions::default())?;
                     } else {
                         self.queue_manager.lock().unwrap().add_file(&path)?;
                     }
                 }
 
                 // Start playback of current track
-                if let Some(track) = self.queue_manager.lock().unwrap().current_track().cloned() {
-                    // Create decoder for the current track
-                    use crate::audio::decoders::flac::FlacDecoder;
-                    use crate::audio::decoders::wav::WavDecoder;
-                    use crate::audio::decoders::mp3::Mp3Decoder;
-                    use crate::audio::decoders::ogg::OggDecoder;
-                    use crate::audio::decoders::m4a::M4aDecoder;
-
-                    let extension = track.path.extension()
-                        .and_then(|ext| ext.to_str())
-                        .map(|s| s.to_lowercase())
-                        .ok_or_else(|| PlayerError::Audio(AudioError::UnsupportedFormat {
-                            format: "No file extension".to_string(),
-                        }))?;
-
-                    let decoder: Box<dyn crate::audio::AudioDecoder> = match extension.as_str() {
-                        "flac" => {
-                            Box::new(FlacDecoder::new(&track.path)
-                                .map_err(|e| PlayerError::Audio(AudioError::InitializationFailed(format!("FLAC decoder error: {}", e))))?)
-                        }
-                        "wav" => {
-                            Box::new(WavDecoder::new(&track.path)
-                                .map_err(|e| PlayerError::Audio(AudioError::InitializationFailed(format!("WAV decoder error: {}", e))))?)
-                        }
-                        "mp3" => {
-                            Box::new(Mp3Decoder::new(&track.path)
-                                .map_err(|e| PlayerError::Audio(AudioError::InitializationFailed(format!("MP3 decoder error: {}", e))))?)
-                        }
-                        "ogg" | "oga" => {
-                            Box::new(OggDecoder::new(&track.path)
-                                .map_err(|e| PlayerError::Audio(AudioError::InitializationFailed(format!("OGG decoder error: {}", e))))?)
-                        }
-                        "m4a" | "mp4" | "m4b" => {
-                            Box::new(M4aDecoder::new(&track.path)
-                                .map_err(|e| PlayerError::Audio(AudioError::InitializationFailed(format!("M4A/MP4 decoder error: {}", e))))?)
-                        }
-                        _ => {
-                            return Err(PlayerError::Audio(AudioError::UnsupportedFormat {
-                                format: format!("Unsupported file extension: {}", extension),
-                            }));
+                let current = self.queue_manager.lock().unwrap().current_track().cloned();
+                let stopped_track = self.audio_engine.current_track_path()
+                    .filter(|_| self.audio_engine.playback_state() == audio::engine::PlaybackState::Stopped);
+                if let Some(track) = current.as_ref().filter(|track| stopped_track.as_ref() == Some(&track.path)) {
+                    // Stop kept the track loaded and rewound; play it from there
+                    self.track_played(&track.path);
+                    self.audio_engine.resume()?;
+                    println!("Playing: {} - {}", track.display_name(), track.artist_name());
+                } else if let Some(mut track) = current {
+                    // Open the current track, skipping ahead past files that cannot be opened
+                    let decoder = loop {
+                        match audio::engine::AudioEngineImpl::open_decoder(&track.path) {
+                            Ok(decoder) => break decoder,
+                            Err(e) => {
+                                self.logger.log_decode_error(&track.path.display().to_string(), &e.to_string());
+                                match self.skip_unplayable(&track.path, &e.to_string()) {
+                                    Some(next) => track = next,
+                                    None => return Err(PlayerError::Audio(e)),
+                                }
+                            }
                         }
                     };
+                    self.track_played(&track.path);
+                    if audio::source::stream_url(&track.path).is_some() {
+                        // Streams are queued by URL alone; opening one reads its tags and length
+                        let (metadata, duration) = (decoder.metadata().clone(), decoder.duration().unwrap_or_default());
+                        self.queue_manager.lock().unwrap().update_stream(&track.path, |entry| {
+                            entry.metadata = metadata.clone();
+                            entry.duration = duration;
+                        });
+                        track.metadata = metadata;
+                    }
 
-                    // Start playback with the decoder
-                    self.audio_engine.start_playback(decoder)?;
+                    // Start playback with the decoder, at the track's start for CUE sheet tracks
+                    self.audio_engine.start_playback_at(track.path.clone(), decoder, track.start_offset)?;
 
                     // Poll decoder responses to trigger any auto-reconfiguration
                     let _ = self.audio_engine.get_decoder_response();
@@ -163,10 +302,14 @@ impl AppController {
                 self.audio_engine.stop()?;
                 println!("OK: Stopped");
             }
+            Commands::Unload => {
+                self.audio_engine.unload()?;
+                println!("OK: Unloaded");
+            }
             Commands::Next => {
                 if let Some(track) = self.queue_manager.lock().unwrap().next_track().cloned() {
                     // Load and play the next track without any preloading to avoid lock contention
-                    self.audio_engine.load_file(track.path.clone())?;
+                    self.audio_engine.load_file_at(track.path.clone(), track.start_offset)?;
                     println!("OK: Next - {}", track.display_name());
                 } else {
                     println!("Queue finished");
@@ -174,7 +317,7 @@ impl AppController {
             }
             Commands::Prev => {
                 if let Some(track) = self.queue_manager.lock().unwrap().previous_track().cloned() {
-                    self.audio_engine.load_file(track.path.clone())?;
+                    self.audio_engine.load_file_at(track.path.clone(), track.start_offset)?;
                     let _ = self.audio_engine.get_decoder_response();
                     println!("OK: Previous - {}", track.display_name());
                 } else {
@@ -182,20 +325,373 @@ impl AppController {
                 }
             }
             Commands::Seek { position } => {
-                let duration = CliApp::parse_time(&position)?;
-                let validated_duration = self.audio_engine.validate_seek_position(duration)?;
+                let offset = CliApp::parse_seek_offset(&position)?;
+                // Positions are relative to the track, which may start partway into its file
+                let track = self.queue_manager.lock().unwrap().current_track().cloned();
+                let start_offset = track.as_ref().map(|track| track.start_offset).unwrap_or_default();
+                let duration = match offset {
+                    SeekOffset::FromStart(duration) => duration,
+                    SeekOffset::BeforeEnd(_) => {
+                        let file_duration = self.audio_engine.current_duration()
+                            .ok_or(PlayerError::Queue(error::QueueError::EmptyQueue))?;
+                        // A CUE sheet track ends where the next one starts, not at the end of the file
+                        let mut length = file_duration.saturating_sub(start_offset);
+                        if let Some(track_length) = track.map(|track| track.duration).filter(|duration| !duration.is_zero()) {
+                            length = length.min(track_length);
+                        }
+                        offset.resolve(length)
+                    }
+                };
+                let validated_duration = self.audio_engine.validate_seek_position(start_offset + duration)?;
                 self.audio_engine.seek(validated_duration)?;
-                println!("Seeked to: {}", CliApp::format_duration(validated_duration));
+                println!("Seeked to: {}", CliApp::format_duration(validated_duration.saturating_sub(start_offset)));
             }
-            Commands::Status => {
-                use crate::cli::status::StatusDisplay;
+            Commands::Loop { point, end } => {
+                let Some(track) = self.queue_manager.lock().unwrap().current_track().cloned() else {
+                    println!("No track loaded");
+                    return Ok(());
+                };
+                let position = self.audio_engine.current_position();
+                match (point.to_lowercase().as_str(), end) {
+                    ("off", _) => {
+                        self.loop_start = None;
+                        self.audio_engine.clear_loop()?;
+                        println!("OK: Loop off");
+                    }
+                    ("a", _) => {
+                        self.loop_start = Some((track.path.clone(), position));
+                        println!("OK: Loop start at {}", CliApp::format_duration(position.saturating_sub(track.start_offset)));
+                    }
+                    ("b", _) => {
+                        // A mark belongs to the file it was set in; a track change drops it
+                        let start = match &self.loop_start {
+                            Some((path, start)) if *path == track.path => *start,
+                            _ => {
+                                println!("Warning: Mark the loop start with 'loop a' first");
+                                return Ok(());
+                            }
+                        };
+                        let region = self.audio_engine.set_loop(start, position)?;
+                        self.loop_start = None;
+                        Self::print_loop(&track, region);
+                    }
+                    (start, Some(end)) => {
+                        let start = CliApp::parse_time(start)?;
+                        let end = CliApp::validate_seek_time(CliApp::parse_time(&end)?, Some(track.duration))?;
+                        // Times are relative to the track, which may start partway into its file
+                        let region = self.audio_engine.set_loop(track.start_offset + start, track.start_offset + end)?;
+                        self.loop_start = None;
+                        Self::print_loop(&track, region);
+                    }
+                    (_, None) => {
+                        return Err(ParseError::MissingArgument {
+                            command: "loop".to_string(),
+                            argument: "end".to_string(),
+                        }.into());
+                    }
+                }
+            }
+            Commands::Status { json } => {
                 // One-shot snapshot
                 let status = self.get_current_status();
-                self.cli_app.display_status(&status);
+                if json {
+                    println!("{}", status.to_json().map_err(io::Error::from)?);
+                } else {
+                    self.cli_app.display_status(&status);
+                }
+            }
+            Commands::Info { path, json } => {
+                use crate::audio::metadata::MetadataExtractor;
+                use crate::cli::status::StatusDisplay;
+                // A bare number is a queue position, unless a file by that name exists
+                let position = path.as_ref()
+                    .filter(|path| !path.exists())
+                    .and_then(|path| path.to_str()?.parse::<usize>().ok());
+                let queued = {
+                    let queue = self.queue_manager.lock().unwrap();
+                    match (&path, position) {
+                        (_, Some(position)) => match position.checked_sub(1).and_then(|index| queue.list().get(index)) {
+                            Some(track) => Some((track.clone(), position - 1 == queue.current_index())),
+                            None => {
+                                println!("No track at queue position {}", position);
+                                return Ok(());
+                            }
+                        },
+                        (Some(path), None) => {
+                            let is_current = queue.current_track().is_some_and(|track| &track.path == path);
+                            drop(queue);
+                            Some((queue::QueueManagerImpl::create_track_info(path)?, is_current))
+                        }
+                        (None, None) => queue.current_track().cloned().map(|track| (track, true)),
+                    }
+                };
+                let Some((mut track, is_current)) = queued else {
+                    println!("No track loaded");
+                    return Ok(());
+                };
+                if let Err(e) = self.ratings.annotate(&mut track) {
+                    warn!("Could not read ratings: {}", e);
+                }
+                let mut report = MetadataExtractor::full_report(&track.path)
+                    .map_err(|e| warn!("Could not read file details of {}: {}", track.path.display(), e))
+                    .ok();
+                if let Some(report) = &mut report {
+                    track.metadata.extra_tags = report.metadata.extra_tags.clone();
+                    report.loudness = self.loudness.get(&track.path)
+                        .unwrap_or_else(|e| {
+                            warn!("Could not read loudness results: {}", e);
+                            None
+                        });
+                }
+                if json {
+                    println!("{}", track.to_json().map_err(io::Error::from)?);
+                } else {
+                    StatusDisplay::display_track_metadata(&track);
+                    if let Some(report) = &report {
+                        // The output side only means something while the track is loaded
+                        let status = is_current.then(|| self.get_current_status())
+                            .filter(|status| status.state != models::PlaybackState::Stopped);
+                        StatusDisplay::display_file_report(report, status.as_ref());
+                    }
+                }
+            }
+            Commands::Lyrics => {
+                use crate::cli::status::StatusDisplay;
+                use crate::queue::lyrics::Lyrics;
+                let Some(track) = self.queue_manager.lock().unwrap().current_track().cloned() else {
+                    println!("No track loaded");
+                    return Ok(());
+                };
+                match Lyrics::for_track(&track) {
+                    Some(lyrics) => StatusDisplay::display_lyrics(&lyrics),
+                    None => println!("No lyrics for {}", track.display_name()),
+                }
+            }
+            Commands::Art => {
+                use crate::audio::metadata::MetadataExtractor;
+                let Some(mut track) = self.queue_manager.lock().unwrap().current_track().cloned() else {
+                    println!("No track loaded");
+                    return Ok(());
+                };
+                // Read now rather than kept with the queue, so only this track's picture is in memory
+                track.metadata.artwork = MetadataExtractor::extract_artwork(&track.path).unwrap_or_else(|e| {
+                    warn!("Could not read cover art of {}: {}", track.path.display(), e);
+                    None
+                });
+                let image = match &track.metadata.artwork {
+                    Some(artwork) => {
+                        // A single file, overwritten for each track
+                        let image = std::env::temp_dir().join(format!("rmusic-cover.{}", artwork.extension()));
+                        std::fs::write(&image, &artwork.data)?;
+                        image
+                    }
+                    None => match MetadataExtractor::find_cover_file(&track.path) {
+                        Some(cover) => cover,
+                        None => {
+                            println!("No cover art for {}", track.display_name());
+                            return Ok(());
+                        }
+                    },
+                };
+                match self.config_manager.get_config().art_viewer.clone() {
+                    Some(viewer) => Self::open_in_viewer(&viewer, &image)?,
+                    None => println!("{}", image.display()),
+                }
+            }
+            Commands::Verify { target } => {
+                use crate::cli::status::StatusDisplay;
+                use crate::queue::verify;
+                use std::sync::atomic::{AtomicBool, Ordering};
+                let running = self.verify_job.take().filter(|(_, job)| !job.is_finished());
+                if target.eq_ignore_ascii_case("cancel") {
+                    match running {
+                        Some((cancel, _)) => {
+                            cancel.store(true, Ordering::Relaxed);
+                            println!("Cancelling verify after the current file");
+                        }
+                        None => println!("No verify is running"),
+                    }
+                    return Ok(());
+                }
+                if running.is_some() {
+                    self.verify_job = running;
+                    println!("A verify is already running; 'verify cancel' stops it");
+                    return Ok(());
+                }
+
+                let files = self.verify_targets(&target)?;
+                let total = files.len();
+                let progress = move |done: usize, check: &verify::FileCheck| match &check.problem {
+                    None => format!("[{}/{}] ✓ {}", done, total, check.path.display()),
+                    Some(problem) => format!("[{}/{}] ✗ {}: {}", done, total, check.path.display(), problem),
+                };
+                let cancel = std::sync::Arc::new(AtomicBool::new(false));
+
+                if self.api_requests.is_some() {
+                    // No loop to report back to, as for a one-shot command: check here and fail on bad files
+                    println!("Verifying {} file(s)...", total);
+                    let summary = verify::run(&files, &cancel, |done, check| println!("{}", progress(done, check)));
+                    for line in StatusDisplay::verify_summary_lines(&summary) {
+                        println!("{}", line);
+                    }
+                    let failed = summary.failed();
+                    if failed > 0 {
+                        return Err(error::QueueError::VerificationFailed { failed, checked: summary.checks.len() }.into());
+                    }
+                } else {
+                    println!("Verifying {} file(s) in the background; 'verify cancel' stops it", total);
+                    let console = self.console.clone();
+                    let flag = cancel.clone();
+                    let job = tokio::task::spawn_blocking(move || {
+                        let summary = verify::run(&files, &flag, |done, check| console.print_async(&progress(done, check)));
+                        console.print_async(&StatusDisplay::verify_summary_lines(&summary).join("\n"));
+                    });
+                    self.verify_job = Some((cancel, job));
+                }
+            }
+            Commands::ScanLoudness { target, write_tags } => {
+                use crate::cli::status::StatusDisplay;
+                use crate::queue::loudness;
+                use std::sync::atomic::{AtomicBool, Ordering};
+                let running = self.loudness_job.take().filter(|(_, job)| !job.is_finished());
+                if target.eq_ignore_ascii_case("cancel") {
+                    match running {
+                        Some((cancel, _)) => {
+                            cancel.store(true, Ordering::Relaxed);
+                            println!("Cancelling the loudness scan after the current file");
+                        }
+                        None => println!("No loudness scan is running"),
+                    }
+                    return Ok(());
+                }
+                if running.is_some() {
+                    self.loudness_job = running;
+                    println!("A loudness scan is already running; 'scan-loudness cancel' stops it");
+                    return Ok(());
+                }
+
+                let files = self.verify_targets(&target)?;
+                let total = files.len();
+                let progress = move |done: usize, scan: &loudness::FileScan| match (&scan.loudness, &scan.problem) {
+                    (Some(loudness), None) => format!("[{}/{}] {:.1} LUFS, {:.1} dBTP{} {}",
+                        done, total, loudness.integrated_lufs, loudness.true_peak_dbtp(),
+                        if scan.tagged { ", tagged" } else { "" }, scan.path.display()),
+                    (_, problem) => format!("[{}/{}] ✗ {}: {}", done, total, scan.path.display(), problem.as_deref().unwrap_or_default()),
+                };
+                let cancel = std::sync::Arc::new(AtomicBool::new(false));
+                let store = self.loudness.clone();
+
+                if self.api_requests.is_some() {
+                    // No loop to report back to, as for a one-shot command: scan here
+                    println!("Scanning {} file(s)...", total);
+                    let summary = loudness::run(&files, &store, write_tags, &cancel, |done, scan| println!("{}", progress(done, scan)));
+                    for line in StatusDisplay::loudness_summary_lines(&summary) {
+                        println!("{}", line);
+                    }
+                } else {
+                    println!("Scanning {} file(s) in the background; 'scan-loudness cancel' stops it", total);
+                    let console = self.console.clone();
+                    let flag = cancel.clone();
+                    let job = tokio::task::spawn_blocking(move || {
+                        let summary = loudness::run(&files, &store, write_tags, &flag, |done, scan| console.print_async(&progress(done, scan)));
+                        console.print_async(&StatusDisplay::loudness_summary_lines(&summary).join("\n"));
+                    });
+                    self.loudness_job = Some((cancel, job));
+                }
+            }
+            Commands::Tag { action } => {
+                use cli::TagAction;
+                use crate::queue::tags;
+                match action {
+                    TagAction::Show { path } => {
+                        let comments = tags::read_comments(&path)?;
+                        if comments.is_empty() {
+                            println!("No tags in {}", path.display());
+                        }
+                        for (key, value) in comments {
+                            println!("{}={}", key, value);
+                        }
+                    }
+                    TagAction::Set { path, fields } => {
+                        let mut changes = Vec::new();
+                        for field in &fields {
+                            let (key, value) = field.split_once('=')
+                                .filter(|(key, _)| tags::valid_key(key))
+                                .ok_or_else(|| ParseError::InvalidArgument {
+                                    argument: "tag".to_string(),
+                                    value: field.clone(),
+                                    expected: "FIELD=value".to_string(),
+                                })?;
+                            changes.push((key.to_uppercase(), value.to_string()));
+                        }
+                        self.ensure_not_playing(&path)?;
+                        let changes: Vec<(&str, String)> = changes.iter().map(|(key, value)| (key.as_str(), value.clone())).collect();
+                        tags::write_comments(&path, &changes)?;
+                        self.refresh_queued_file(&path);
+                        println!("OK: Tagged {}", path.display());
+                    }
+                    TagAction::Clear { path, field } => {
+                        let key = field.to_uppercase();
+                        if !tags::read_comments(&path)?.iter().any(|(existing, _)| existing.eq_ignore_ascii_case(&key)) {
+                            println!("{} has no {} tag", path.display(), key);
+                            return Ok(());
+                        }
+                        self.ensure_not_playing(&path)?;
+                        tags::remove_comments(&path, &[&key])?;
+                        self.refresh_queued_file(&path);
+                        println!("OK: Removed {} from {}", key, path.display());
+                    }
+                }
+            }
+            Commands::WatchDir { action } => {
+                use cli::WatchDirAction;
+                match action {
+                    WatchDirAction::Add { path } => {
+                        if !path.is_dir() {
+                            return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is not a directory", path.display())).into());
+                        }
+                        // Events name files by the watched path, so keep one spelling of it
+                        let path = std::fs::canonicalize(&path)?;
+                        if !self.watch_directory(&path)? {
+                            println!("Already watching {}", path.display());
+                            return Ok(());
+                        }
+                        self.save_watch_dirs()?;
+                        println!("OK: Watching {} for new audio files", path.display());
+                    }
+                    WatchDirAction::Remove { path } => {
+                        let path = std::fs::canonicalize(&path).unwrap_or(path);
+                        if !self.directory_watcher.as_mut().is_some_and(|watcher| watcher.remove(&path)) {
+                            println!("Not watching {}", path.display());
+                            return Ok(());
+                        }
+                        self.save_watch_dirs()?;
+                        println!("OK: Stopped watching {}", path.display());
+                    }
+                    WatchDirAction::List => {
+                        let directories = self.directory_watcher.as_ref().map(|watcher| watcher.directories()).unwrap_or_default();
+                        if directories.is_empty() {
+                            println!("No watched directories");
+                        }
+                        for directory in directories {
+                            println!("{}", directory.display());
+                        }
+                    }
+                }
             }
-            Commands::Watch => {
+            Commands::Watch { spectrum } => {
                 use crate::cli::status::StatusDisplay;
+                use crate::audio::spectrum::{SpectrumAnalyzer, DEFAULT_BANDS};
                 println!("Watching status (updates every 100ms). Press Ctrl-C to stop.");
+
+                // The FFT costs CPU, so only tap decoded audio when asked to
+                let show_spectrum = spectrum || self.config_manager.get_config().show_spectrum;
+                let tap = self.audio_engine.spectrum_tap();
+                tap.set_enabled(show_spectrum);
+                let analyzer = SpectrumAnalyzer::new(DEFAULT_BANDS);
+                let mut window = Vec::new();
+                let mut lyrics = crate::queue::lyrics::LyricsSync::default();
+
                 loop {
                     // Poll decoder responses and render snapshot
                     let _ = self.audio_engine.get_decoder_response();
@@ -203,10 +699,31 @@ impl AppController {
                     // Clear screen and print snapshot
                     print!("\x1B[2J\x1B[H");
                     self.cli_app.display_status(&status);
+                    lyrics.follow(status.current_track.as_ref());
+                    if !lyrics.lines().is_empty() {
+                        StatusDisplay::display_synced_lyrics(lyrics.lines(), lyrics.current_line(status.position));
+                    }
+                    if show_spectrum {
+                        tap.drain_into(&mut window);
+                        let sample_rate = tap.sample_rate();
+                        let levels = analyzer.analyze(&window, sample_rate);
+                        StatusDisplay::display_spectrum(&levels, sample_rate);
+                    }
                     tokio::time::sleep(std::time::Duration::from_millis(100)).await;
                 }
             }
 
+            Commands::Spectrum => {
+                let analyzer = audio::spectrum::SpectrumAnalyzer::snapshot();
+                let Some((levels, sample_rate)) = self.spectrum_snapshot(&analyzer) else {
+                    println!("Nothing to analyse: no audio has been decoded yet, or it is silent");
+                    return Ok(());
+                };
+                let centers = analyzer.band_centers(sample_rate);
+                for line in cli::status::StatusDisplay::spectrum_snapshot_lines(&levels, &centers) {
+                    println!("{}", line);
+                }
+            }
             Commands::Volume { level } => {
                 let volume = (level as f32) / 100.0;
                 self.audio_engine.set_volume(volume)?;
@@ -219,33 +736,82 @@ impl AppController {
             Commands::Queue { action } => {
                 use cli::QueueAction;
                 match action {
-                    QueueAction::Add { path } => {
-                        if path.is_dir() {
-                            self.queue_manager.lock().unwrap().add_directory(&path)?;
+                    QueueAction::Add { path, filters } => {
+                        if Self::is_keyword(&path, "favorites") {
+                            let added = self.enqueue_favorites()?;
+                            println!("OK: Added {} favorite track(s)", added);
+                        } else if path.is_dir() {
+                            self.queue_manager.lock().unwrap().add_directory(&path, &filters.options())?;
                             println!("OK: Added directory {}", path.display());
+                        } else if filters.verify {
+                            self.queue_manager.lock().unwrap().add_verified_file(&path)?;
+                            println!("OK: Added file {} (verified)", path.display());
                         } else {
                             self.queue_manager.lock().unwrap().add_file(&path)?;
                             println!("OK: Added file {}", path.display());
                         }
                     }
-                    QueueAction::List => {
+                    QueueAction::List { compact, flat } => {
+                        use cli::queue_view::QueueView;
                         let qm = self.queue_manager.lock().unwrap();
-                        let queue = qm.list();
+                        let mut queue: Vec<_> = qm.list().iter().cloned().collect();
                         if queue.is_empty() {
                             println!("Queue is empty");
                         } else {
+                            for track in queue.iter_mut() {
+                                if let Err(e) = self.ratings.annotate(track) {
+                                    warn!("Could not read ratings: {}", e);
+                                }
+                            }
                             println!("Queue ({} tracks):", queue.len());
-                            for (i, track) in queue.iter().enumerate() {
-                                let marker = if i == qm.current_index() { ">" } else { " " };
-                                println!("{} {}: {} - {}",
-                                    marker,
-                                    i + 1,
-                                    track.artist_name(),
-                                    track.display_name()
-                                );
+                            let lines = if flat {
+                                QueueView::flat_lines(&queue, qm.current_index())
+                            } else if compact {
+                                QueueView::compact_lines(&queue, qm.current_index())
+                            } else {
+                                QueueView::grouped_lines(&queue, qm.current_index())
+                            };
+                            for line in lines {
+                                println!("{}", line);
+                            }
+                            if !flat {
+                                println!("{}", QueueView::total_line(&queue));
                             }
                         }
                     }
+                    QueueAction::AddNext { path } => {
+                        let inserted = self.insert_into_queue(&path, None)?;
+                        println!("OK: {} track(s) will play next", inserted);
+                    }
+                    QueueAction::Insert { index, path } => {
+                        let inserted = self.insert_into_queue(&path, Some(index - 1))?;
+                        println!("OK: Inserted {} track(s) at position {}", inserted, index);
+                    }
+                    QueueAction::Dedupe { metadata } => {
+                        let removed = self.queue_manager.lock().unwrap().dedupe(metadata);
+                        if removed > 0 {
+                            self.audio_engine.discard_preloaded_track()?;
+                        }
+                        println!("OK: Removed {} duplicate(s)", removed);
+                    }
+                    QueueAction::Validate { prune } => {
+                        let mut qm = self.queue_manager.lock().unwrap();
+                        let unreadable = qm.unreadable_entries();
+                        for &index in &unreadable {
+                            let track = &qm.list()[index];
+                            println!("Missing: {}: {} ({})", index + 1, track.display_name(), track.path.display());
+                        }
+                        if unreadable.is_empty() {
+                            println!("OK: All {} track(s) are readable", qm.len());
+                        } else if prune {
+                            let removed = qm.remove_indices(&unreadable).len();
+                            drop(qm);
+                            self.audio_engine.discard_preloaded_track()?;
+                            println!("OK: Removed {} missing track(s)", removed);
+                        } else {
+                            println!("{} missing track(s); run 'queue validate --prune' to remove them", unreadable.len());
+                        }
+                    }
                     QueueAction::Clear => {
                         self.queue_manager.lock().unwrap().clear();
                         println!("OK: Queue cleared");
@@ -263,13 +829,29 @@ impl AppController {
                             println!("No current track");
                         }
                     }
+                    QueueAction::Sort { key } => {
+                        self.queue_manager.lock().unwrap().sort_by(key);
+                        // The preloaded next track is probably no longer next
+                        self.audio_engine.discard_preloaded_track()?;
+                        println!("OK: Queue sorted by {}", key.as_str());
+                    }
+                    QueueAction::Shuffle { seed } => {
+                        let seed = seed.unwrap_or_else(rand::random);
+                        self.queue_manager.lock().unwrap().shuffle_tracks(seed);
+                        self.audio_engine.discard_preloaded_track()?;
+                        println!("OK: Queue shuffled (seed {})", seed);
+                    }
                 }
             }
             Commands::Playlist { action } => {
                 use cli::PlaylistAction;
                 match action {
-                    PlaylistAction::Save { name } => {
-                        self.queue_manager.lock().unwrap().save_playlist(&name, queue::playlist::PlaylistFormat::M3u)?;
+                    PlaylistAction::Save { name, relative } => {
+                        let options = queue::playlist::PlaylistSaveOptions {
+                            use_relative_paths: relative,
+                            ..Default::default()
+                        };
+                        self.queue_manager.lock().unwrap().save_playlist(&name, queue::playlist::PlaylistFormat::M3u, &options)?;
                         println!("Playlist saved: {}", name);
                     }
                     PlaylistAction::Load { name } => {
@@ -291,18 +873,45 @@ impl AppController {
                         self.queue_manager.lock().unwrap().delete_playlist(&name)?;
                         println!("Playlist deleted: {}", name);
                     }
+                    PlaylistAction::Rename { old_name, new_name } => {
+                        self.queue_manager.lock().unwrap().rename_playlist(&old_name, &new_name)?;
+                        println!("Playlist renamed: {} → {}", old_name, new_name);
+                    }
+                    PlaylistAction::Merge { names, output } => {
+                        let tracks = self.queue_manager.lock().unwrap().merge_playlists(&names, &output)?;
+                        println!("Playlist merged: {} → {} ({} tracks)", names.join(", "), output, tracks);
+                    }
+                    PlaylistAction::CreateSmart { name, criteria_json } => {
+                        let criteria = queue::playlist::SmartPlaylistCriteria::from_json(&criteria_json)?;
+                        self.queue_manager.lock().unwrap().save_smart_playlist(&name, &criteria)?;
+                        println!("Smart playlist saved: {}", name);
+                    }
+                    PlaylistAction::LoadSmart { name, library_path } => {
+                        let library = library_path
+                            .or_else(|| self.config_manager.get_config().library_path.as_ref()
+                                .map(|path| CliApp::expand_path(&path.to_string_lossy())))
+                            .ok_or_else(|| ParseError::MissingArgument {
+                                command: "playlist load-smart".to_string(),
+                                argument: "library path (or set one with 'config library_path <dir>')".to_string(),
+                            })?;
+                        let mut qm = self.queue_manager.lock().unwrap();
+                        let criteria = qm.smart_playlist(&name)?;
+                        let loaded = qm.load_matching(&library, &criteria)?;
+                        println!("Smart playlist loaded: {} ({} matching tracks)", name, loaded);
+                    }
                 }
             }
             Commands::Device { action } => {
                 use cli::DeviceAction;
                 match action {
                     DeviceAction::List => {
-                        let devices = self.audio_engine.device_manager().list_devices();
+                        let devices = self.audio_engine.list_devices();
+                        *self.device_names.lock().unwrap() = devices.clone();
                         if devices.is_empty() {
                             println!("No audio devices found");
                         } else {
                             println!("Available audio devices:");
-                            let current_device = self.audio_engine.device_manager().current_device_name()
+                            let current_device = self.audio_engine.current_device_name()
                                 .unwrap_or(None);
 
                             for device in devices {
@@ -312,194 +921,1293 @@ impl AppController {
                         }
                     }
                     DeviceAction::Set { device } => {
+                        let started = std::time::Instant::now();
+                        let previous = self.audio_engine.current_device_name().unwrap_or(None);
                         self.audio_engine.set_device(&device)?;
+                        self.logger.log_device_changed(previous.as_deref(), &device, started.elapsed());
 
                         // Save device preference to config
                         self.config_manager.set_preferred_device(Some(device.clone()))?;
 
                         println!("Audio device set to: {}", device);
                     }
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    /// Get current player status
-    fn get_current_status(&self) -> PlayerStatus {
-        let mut status = PlayerStatus::new();
-
-        // Get playback state from audio engine and convert to models::PlaybackState
-        let engine_state = self.audio_engine.playback_state();
-        status.state = match engine_state {
-            audio::engine::PlaybackState::Stopped => models::PlaybackState::Stopped,
-            audio::engine::PlaybackState::Playing => models::PlaybackState::Playing,
-            audio::engine::PlaybackState::Paused => models::PlaybackState::Paused,
-        };
+                    DeviceAction::SetExclusive { enable } => {
+                        let acquired = self.audio_engine.set_exclusive_mode(enable)?;
+                        self.config_manager.update_config(|config| config.exclusive_mode = enable)?;
 
-        status.position = self.audio_engine.current_position();
-        status.volume = self.audio_engine.volume();
+                        if !enable {
+                            println!("OK: Exclusive mode off");
+                        } else if acquired {
+                            println!("OK: Exclusive mode on");
+                        } else {
+                            println!("Warning: Exclusive mode is not available on this device; using shared mode");
+                        }
+                    }
+                    DeviceAction::SetDefault => {
+                        let started = std::time::Instant::now();
+                        // Forget the preference first, so a failed switch still starts on the default next time
+                        self.config_manager.set_preferred_device(None)?;
+                        let previous = self.audio_engine.current_device_name().unwrap_or(None);
+                        let device = self.system_default_device()?;
+                        self.audio_engine.set_device(&device)?;
+                        self.logger.log_device_changed(previous.as_deref(), &device, started.elapsed());
+                        println!("Audio device set to system default: {}", device);
+                    }
+                    DeviceAction::Reset => {
+                        let started = std::time::Instant::now();
+                        self.audio_engine.refresh_devices()?;
+                        let previous = self.audio_engine.current_device_name().unwrap_or(None);
+                        let device = match previous.clone().filter(|device| self.audio_engine.list_devices().contains(device)) {
+                            Some(device) => device,
+                            None => self.system_default_device()?,
+                        };
+                        self.audio_engine.set_device(&device)?;
+                        if previous.as_deref() != Some(device.as_str()) {
+                            self.logger.log_device_changed(previous.as_deref(), &device, started.elapsed());
+                            println!("Device {} is gone; using {}", previous.as_deref().unwrap_or("(none)"), device);
+                        }
+                        println!("OK: Audio backend restarted");
+                    }
+                    DeviceAction::Capabilities { device, json } => {
+                        let capabilities = self.audio_engine.device_capabilities(&device)
+                            .ok_or_else(|| AudioError::DeviceNotFound { device: device.clone() })?;
 
-        // Only show track info if playing or paused; otherwise show basic device/volume only
-        match status.state {
-            models::PlaybackState::Playing | models::PlaybackState::Paused => {
-                status.current_track = self.queue_manager.lock().unwrap().current_track().cloned();
-                if let Some(_track) = &status.current_track {
-                    status.audio_format = Some(models::AudioFormat::new(
-                        self.audio_engine.sample_rate(),
-                        self.audio_engine.bit_depth(),
-                        self.audio_engine.channels(),
-                        models::AudioCodec::Flac, // Default for now
-                    ));
+                        if json {
+                            println!("{}", capabilities.to_json().map_err(io::Error::from)?);
+                        } else {
+                            crate::cli::status::StatusDisplay::display_device_capabilities(&capabilities);
+                        }
+                    }
                 }
             }
-            models::PlaybackState::Stopped => {
-                status.current_track = None;
-                status.audio_format = None;
+            Commands::Limiter { state } => {
+                self.audio_engine.set_limiter_enabled(state.is_on());
+                println!("OK: Limiter {}", state.as_str());
             }
-        }
-
-        // Get current device name
-        status.output_device = self.audio_engine.device_manager().current_device_name()
-            .unwrap_or(None);
-
-        status
-    }
-
-    /// Run interactive mode
-    pub async fn run_interactive_mode(&mut self) -> Result<(), PlayerError> {
-        println!("High-Resolution Audio Player v0.1.0");
-        println!("Type 'help' for available commands, 'exit' or 'quit' to quit.");
-        println!();
-
-        // Set up graceful shutdown handling
-        let shutdown_flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
-        let shutdown_flag_clone = shutdown_flag.clone();
-
-        ctrlc::set_handler(move || {
-            println!("\nReceived interrupt signal. Shutting down gracefully...");
-            shutdown_flag_clone.store(true, std::sync::atomic::Ordering::Relaxed);
-        }).expect("Error setting Ctrl-C handler");
-
-        // Non-blocking input with 100ms polling using a dedicated stdin thread
-        let mut interval = tokio::time::interval(std::time::Duration::from_millis(100));
-        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
-        std::thread::spawn(move || {
-            let stdin = std::io::stdin();
-            let mut line = String::new();
-            loop {
-                line.clear();
-                match stdin.read_line(&mut line) {
-                    Ok(0) => {
-                        // EOF
-                        let _ = tx.send(String::new());
-                        break;
+            Commands::Output { action } => {
+                use cli::OutputAction;
+                let mut output = self.config_manager.get_config().output_channels();
+                match action {
+                    None => {
+                        let output = self.audio_engine.output_channels();
+                        println!("Output: {}, balance {}", output.mode.as_str(), output.balance_label());
+                        return Ok(());
+                    }
+                    Some(OutputAction::Stereo) => output.mode = ChannelMode::Stereo,
+                    Some(OutputAction::Mono) => output.mode = ChannelMode::Mono,
+                    Some(OutputAction::Swap) => output.mode = ChannelMode::Swap,
+                    Some(OutputAction::Balance { balance }) => output.balance = balance,
+                }
+                self.config_manager.update_config(|config| {
+                    config.output_mode = output.mode;
+                    config.balance = output.balance;
+                })?;
+                self.apply_setting("output_mode")?;
+                println!("OK: Output {}, balance {}", output.mode.as_str(), output.balance_label());
+            }
+            Commands::Crossfeed { state, level } => {
+                let Some(state) = state else {
+                    println!("Crossfeed: {}", self.audio_engine.crossfeed());
+                    return Ok(());
+                };
+                self.config_manager.update_config(|config| {
+                    config.crossfeed = state.is_on();
+                    if let Some(level) = level {
+                        config.crossfeed_level_db = level;
+                    }
+                })?;
+                self.apply_setting("crossfeed")?;
+                println!("OK: Crossfeed {}", self.audio_engine.crossfeed());
+            }
+            Commands::Silence { state } => {
+                self.config_manager.update_config(|config| config.skip_silence = state.is_on())?;
+                self.apply_setting("skip_silence")?;
+                println!("OK: Silence skipping {}", state.as_str());
+            }
+            Commands::Eq { action } => {
+                use audio::eq::{EqBand, MAX_BANDS};
+                use cli::EqAction;
+                match action.unwrap_or(EqAction::Show) {
+                    EqAction::Show => {
+                        let bands = self.audio_engine.eq_bands();
+                        if bands.is_empty() {
+                            println!("EQ is flat (no bands)");
+                        }
+                        for (index, band) in bands.iter().enumerate() {
+                            println!("{:2}. {}", index + 1, band.describe());
+                        }
                     }
-                    Ok(_) => {
-                        let s = line.trim().to_string();
-                        let _ = tx.send(s);
+                    EqAction::Band { filter, frequency, gain, q } => {
+                        let band = EqBand::new(filter, frequency, gain, q.unwrap_or(EqBand::DEFAULT_Q));
+                        band.validate().map_err(|reason| io::Error::new(io::ErrorKind::InvalidInput, reason))?;
+                        let mut bands = self.audio_engine.eq_bands();
+                        if bands.len() >= MAX_BANDS {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                format!("the EQ already has {} bands; use 'eq clear' to start over", MAX_BANDS),
+                            ).into());
+                        }
+                        bands.push(band);
+                        self.audio_engine.set_eq_bands(bands);
+                        println!("OK: Added {}", band.describe());
                     }
-                    Err(_) => {
-                        let _ = tx.send(String::new());
-                        break;
+                    EqAction::Clear => {
+                        self.audio_engine.set_eq_bands(Vec::new());
+                        println!("OK: EQ cleared");
+                    }
+                    EqAction::SavePreset { name } => {
+                        let bands = self.audio_engine.eq_bands();
+                        self.eq_presets.save(&name, &bands)?;
+                        println!("OK: Saved EQ preset '{}' ({} bands)", name.trim(), bands.len());
+                    }
+                    EqAction::LoadPreset { name } => {
+                        let preset = self.eq_presets.load(&name)?;
+                        let count = preset.bands.len();
+                        self.audio_engine.set_eq_bands(preset.bands);
+                        println!("OK: Loaded EQ preset '{}' ({} bands)", preset.name, count);
+                    }
+                    EqAction::ListPresets => {
+                        for preset in self.eq_presets.list()? {
+                            let bands: Vec<String> = preset.bands.iter().map(EqBand::describe).collect();
+                            println!("{}{}: {}",
+                                preset.name,
+                                if preset.builtin { " (built-in)" } else { "" },
+                                if bands.is_empty() { "flat".to_string() } else { bands.join(", ") }
+                            );
+                        }
+                    }
+                    EqAction::DeletePreset { name } => {
+                        self.eq_presets.delete(&name)?;
+                        println!("OK: Deleted EQ preset '{}'", name.trim());
                     }
                 }
             }
-        });
-        let mut awaiting_input = false;
-        let mut announced_queue_finished = false;
-
-        loop {
-            // Check for shutdown signal
-            if shutdown_flag.load(std::sync::atomic::Ordering::Relaxed) {
-                break;
-            }
-
+            Commands::Buffer { size } => {
+                let requested = size.to_frames(self.audio_engine.sample_rate());
+                let effective = self.audio_engine.set_output_buffer_frames(requested)?;
+                // An explicit buffer replaces any fixed output_buffer_frames
+                self.config_manager.update_config(|config| {
+                    config.buffer_size = effective as usize;
+                    config.output_buffer_frames = None;
+                })?;
 
-            // Print prompt only when not already awaiting input
-            if !awaiting_input {
-                print!("> ");
-                let _ = std::io::Write::flush(&mut std::io::stdout());
-                awaiting_input = true;
+                if effective != requested {
+                    println!("Warning: {} frames is outside the device's supported range; clamped", requested);
+                }
+                if effective == 0 {
+                    println!("OK: Output buffer set to device default");
+                } else {
+                    println!("OK: Output buffer {} frames ({:.1} ms)", effective, self.audio_engine.output_latency_ms());
+                }
             }
-
-            tokio::select! {
-                biased;
-
-                // Handle user input when it arrives (from stdin thread)
-                line = rx.recv() => {
-                    awaiting_input = false;
-                    match line {
-                        Some(line) => {
-                            let line = line.trim().to_string();
-                            if line.is_empty() {
-                                continue;
-                            }
-                            if line == "exit" || line == "quit" {
-                                println!("Goodbye!");
-                                break;
-                            }
-                            match CliApp::parse_command(&line) {
-                                Ok(command) => {
-                                    if let Err(e) = self.execute_command(command).await {
-                                        self.handle_error_with_recovery(&e).await;
-                                    }
-                                }
-                                Err(ParseError::HelpRequested) => {
-                                    CliApp::display_help();
-                                }
-                                Err(e) => {
-                                    eprintln!("Error: {}", e);
-                                    println!("Type 'help' for available commands.");
-                                }
-                            }
-                        }
-                        None => {
-                            // Channel closed / EOF
-                            println!();
+            Commands::Perf { action, json, output } => {
+                use cli::PerfAction;
+                let watch = action == Some(PerfAction::Watch);
+                if watch {
+                    println!("Watching performance (updates every second). Press Ctrl-C to stop.");
+                }
+                loop {
+                    self.audio_engine.update_performance_monitoring();
+                    let report = self.audio_engine.get_performance_report();
+                    if let Some(output) = &output {
+                        report.write_to(output)?;
+                        if !watch {
+                            println!("OK: Performance report written to {}", output.display());
                             break;
                         }
                     }
+                    if json {
+                        println!("{}", report.to_json().map_err(io::Error::from)?);
+                    } else {
+                        if watch {
+                            print!("\x1B[2J\x1B[H");
+                        }
+                        print!("{}", report.format_report());
+                    }
+                    if !watch {
+                        break;
+                    }
+                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
                 }
-
-                // 100ms poll: process engine events and keep prompt responsive
-                _ = interval.tick() => {
-                    // Poll decoder responses to trigger any auto-reconfiguration and keep next track preloaded
-                    if let Some(resp) = self.audio_engine.get_decoder_response() {
-                        use crate::audio::engine::DecoderResponse;
-                        match resp {
-                            DecoderResponse::FileLoaded { .. } | DecoderResponse::TrackTransitioned => {
-                                // Announce the new track title and reset completion flag
-                                if let Some(track) = self.queue_manager.lock().unwrap().current_track() {
-                                    println!("Now playing: {} - {}", track.display_name(), track.artist_name());
-                                }
-                                announced_queue_finished = false;
-                            }
-                            DecoderResponse::EndOfFile => {
-                                if !announced_queue_finished {
-                                    println!("\nQueue finished");
-                                    announced_queue_finished = true;
-                                }
+            }
+            Commands::Log { action } => {
+                use cli::LogAction;
+                match action {
+                    LogAction::Show { count } => {
+                        let Some(path) = self.config_manager.get_config().event_log_path().map(Path::to_path_buf) else {
+                            println!("Event log is off (set log_file in the config to enable it)");
+                            return Ok(());
+                        };
+                        let lines = logging::read_event_log(&path, count.unwrap_or(20))?;
+                        if lines.is_empty() {
+                            println!("No events logged yet");
+                        } else {
+                            for line in lines {
+                                println!("{}", line);
                             }
-                            _ => {}
                         }
                     }
                 }
             }
-
-        }
-
-        // Perform graceful shutdown
-        self.shutdown().await?;
-
-        Ok(())
+            Commands::Recovery { action: cli::RecoveryAction::Stats } => {
+                let stats = self.error_recovery.get_recovery_statistics();
+                if stats.total_attempts() == 0 {
+                    println!("No recovery attempts since startup");
+                    return Ok(());
+                }
+                println!("Recovery attempts since startup: {}", stats.total_attempts());
+                for (category, attempts) in &stats.attempts_by_category {
+                    println!("  {:<22}{}", category, attempts);
+                }
+                println!("Succeeded: {}  Retry suggested: {}  Failed: {}", stats.successes, stats.retries, stats.failures);
+                println!(
+                    "Errors within the {} s cooldown: {} ({} tried more than once, at most {} times)",
+                    self.error_recovery.cooldown().as_secs(),
+                    stats.total_errors_tracked,
+                    stats.errors_with_multiple_attempts,
+                    stats.max_attempts_for_any_error
+                );
+            }
+            Commands::History { action: Some(cli::HistoryAction::Clear), .. } => {
+                self.session_history.clear();
+                println!("OK: Session history cleared");
+            }
+            Commands::History { action: None, limit, all: false } => {
+                let limit = limit.unwrap_or(models::SessionHistory::DEFAULT_LIMIT);
+                if self.session_history.is_empty() {
+                    println!("No tracks played this session");
+                } else {
+                    for (track, started_at) in self.session_history.recent(limit) {
+                        println!("{}  {} - {}",
+                            chrono::DateTime::<chrono::Local>::from(*started_at).format("%H:%M:%S"),
+                            track.artist_name(),
+                            track.display_name()
+                        );
+                    }
+                }
+            }
+            Commands::History { action: None, limit, all: true } => {
+                let plays: Vec<_> = self.history.recent(limit.unwrap_or(20)).collect();
+                if plays.is_empty() {
+                    println!("No tracks played yet");
+                } else {
+                    for (i, play) in plays.iter().enumerate() {
+                        println!("{:>3}. {}  {} ({})",
+                            i + 1,
+                            play.played_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                            play.display_name(),
+                            CliApp::format_duration(play.played_for)
+                        );
+                    }
+                }
+            }
+            Commands::Config { action } => {
+                use cli::ConfigAction;
+                use config::ConfigManager;
+                match action {
+                    ConfigAction::Show => {
+                        let width = ConfigManager::SETTABLE_KEYS.iter().map(|key| key.len()).max().unwrap_or(0);
+                        for key in ConfigManager::SETTABLE_KEYS {
+                            println!("{:<width$}  {}", key, self.config_manager.get_value(key)?, width = width);
+                        }
+                    }
+                    ConfigAction::List => {
+                        print!("{}", self.config_manager.to_toml()?);
+                    }
+                    ConfigAction::Get { key } => {
+                        println!("{}", self.config_manager.get_value(&key)?);
+                    }
+                    ConfigAction::Set { key, value, move_playlists } => {
+                        if ConfigManager::canonical_key(&key)? == "playlist_directory" {
+                            return self.change_playlist_directory(CliApp::expand_path(&value), move_playlists);
+                        }
+                        if move_playlists {
+                            println!("Warning: --move only applies to playlist_directory");
+                        }
+                        let key = self.config_manager.set_value(&key, &value)?;
+                        let applied = self.apply_setting(key)?;
+                        println!("OK: {} = {}", key, self.config_manager.get_value(key)?);
+                        if !applied {
+                            println!("Note: {} takes effect the next time the player starts", key);
+                        }
+                    }
+                    ConfigAction::Reset => {
+                        self.config_manager.reset_to_defaults()?;
+                        for key in ConfigManager::SETTABLE_KEYS {
+                            self.apply_setting(key)?;
+                        }
+                        println!("OK: Settings restored to defaults");
+                    }
+                    ConfigAction::Validate => {
+                        let problems = self.config_manager.validate_file();
+                        if problems.is_empty() {
+                            println!("OK: {} is valid", self.config_manager.config_path().display());
+                        }
+                        for problem in problems {
+                            println!("Error: {}", problem.user_message());
+                        }
+                    }
+                }
+            }
+            Commands::Rate { stars } => {
+                let Some(mut track) = self.queue_manager.lock().unwrap().current_track().cloned() else {
+                    println!("No track loaded");
+                    return Ok(());
+                };
+                let rating = (stars > 0).then_some(stars);
+                self.ratings.set_rating(&track.path, track.start_offset, rating)?;
+                self.ratings.annotate(&mut track)?;
+                match rating {
+                    Some(_) => println!("OK: Rated {} {}", track.display_name(), track.rating_label()),
+                    None => println!("OK: Cleared rating of {}", track.display_name()),
+                }
+            }
+            Commands::Fav { state } => {
+                let Some(mut track) = self.queue_manager.lock().unwrap().current_track().cloned() else {
+                    println!("No track loaded");
+                    return Ok(());
+                };
+                self.ratings.annotate(&mut track)?;
+                let favorite = state.map_or(!track.favorite, |state| state.is_on());
+                self.ratings.set_favorite(&track.path, track.start_offset, favorite)?;
+                if favorite {
+                    println!("OK: {} added to favorites ♥", track.display_name());
+                } else {
+                    println!("OK: {} removed from favorites", track.display_name());
+                }
+            }
+            Commands::Export { format, output } => {
+                let format: queue::export::ExportFormat = format.parse()
+                    .map_err(|reason: String| io::Error::new(io::ErrorKind::InvalidInput, reason))?;
+                let queue = self.queue_manager.lock().unwrap();
+                if queue.is_empty() {
+                    println!("Queue is empty, nothing to export");
+                    return Ok(());
+                }
+                queue::export::write_export(queue.list(), format, &output)?;
+                println!("OK: Exported {} tracks to {}", queue.len(), output.display());
+            }
+            Commands::ExportAudio { output, target, rate, bits } => {
+                use crate::audio::render::{self, RenderOptions};
+                use crate::cli::status::StatusDisplay;
+                use std::sync::atomic::{AtomicBool, Ordering};
+                let running = self.export_job.take().filter(|(_, job)| !job.is_finished());
+                if Self::is_keyword(&output, "cancel") {
+                    match running {
+                        Some((cancel, _)) => {
+                            cancel.store(true, Ordering::Relaxed);
+                            println!("Cancelling the export");
+                        }
+                        None => println!("No export is running"),
+                    }
+                    return Ok(());
+                }
+                if running.is_some() {
+                    self.export_job = running;
+                    println!("An export is already running; 'export cancel' stops it");
+                    return Ok(());
+                }
+
+                let files = if target == "queue" {
+                    self.verify_targets("queue")?
+                } else {
+                    match self.queue_manager.lock().unwrap().current_track() {
+                        Some(track) => vec![track.path.clone()],
+                        None => {
+                            println!("No track loaded");
+                            return Ok(());
+                        }
+                    }
+                };
+                if let Some(stream) = files.iter().find(|path| audio::source::stream_url(path).is_some()) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("can't export {}: a stream has no end", stream.display()),
+                    ).into());
+                }
+                let options = RenderOptions {
+                    sample_rate: rate,
+                    bit_depth: bits,
+                    oversampling: self.config_manager.get_config().oversampling_factor,
+                };
+                let controls = self.audio_engine.chain_controls();
+                let progress = |percent: u32| format!("Exporting: {}%", percent);
+                let cancel = std::sync::Arc::new(AtomicBool::new(false));
+
+                if self.api_requests.is_some() {
+                    // No loop to report back to, as for a one-shot command: render here
+                    println!("Exporting {} file(s) to {}...", files.len(), output.display());
+                    let summary = render::run(&files, &output, options, &controls, &cancel, |percent| println!("{}", progress(percent)))?;
+                    for line in StatusDisplay::export_summary_lines(&summary) {
+                        println!("{}", line);
+                    }
+                } else {
+                    println!("Exporting {} file(s) to {} in the background; 'export cancel' stops it", files.len(), output.display());
+                    let console = self.console.clone();
+                    let flag = cancel.clone();
+                    let job = tokio::task::spawn_blocking(move || {
+                        let lines = match render::run(&files, &output, options, &controls, &flag, |percent| console.print_async(&progress(percent))) {
+                            Ok(summary) => StatusDisplay::export_summary_lines(&summary),
+                            Err(e) => vec![format!("Export to {} failed: {}", output.display(), e)],
+                        };
+                        console.print_async(&lines.join("\n"));
+                    });
+                    self.export_job = Some((cancel, job));
+                }
+            }
+            Commands::Serve => {
+                self.start_api_server().await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Run the `art_viewer` command on an image and wait for it to exit
+    ///
+    /// `{}` in the command is replaced with the image path, which is appended when there is none.
+    fn open_in_viewer(viewer: &str, image: &Path) -> io::Result<()> {
+        let mut words = viewer.split_whitespace();
+        let program = words.next().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "art_viewer is empty"))?;
+        let mut args: Vec<std::ffi::OsString> = words
+            .map(|word| if word == "{}" { image.as_os_str().to_owned() } else { word.into() })
+            .collect();
+        if !viewer.split_whitespace().any(|word| word == "{}") {
+            args.push(image.as_os_str().to_owned());
+        }
+        let status = std::process::Command::new(program).args(&args).status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("{} exited with {}", program, status)));
+        }
+        Ok(())
+    }
+
+    /// Files for `verify`: the queue, a file, the audio files under a directory, or a saved playlist
+    fn verify_targets(&self, target: &str) -> Result<Vec<PathBuf>, PlayerError> {
+        let queue_manager = self.queue_manager.lock().unwrap();
+        if Self::is_keyword(Path::new(target), "queue") {
+            if queue_manager.is_empty() {
+                return Err(error::QueueError::EmptyQueue.into());
+            }
+            return Ok(queue::verify::unique_files(queue_manager.list().iter().map(|track| track.path.clone())));
+        }
+        let path = CliApp::expand_path(target);
+        if path.is_dir() {
+            return Ok(queue_manager.audio_files_in(&path)?);
+        }
+        if path.is_file() {
+            return Ok(vec![path]);
+        }
+        match queue_manager.playlist_files(target) {
+            Err(error::PlaylistError::PlaylistNotFound { .. }) => Err(error::QueueError::FileNotFound { path }.into()),
+            result => Ok(result?),
+        }
+    }
+
+    /// Refuse to rewrite the file the output is playing or paused on
+    fn ensure_not_playing(&self, path: &Path) -> Result<(), PlayerError> {
+        let loaded = self.audio_engine.current_track_path()
+            .filter(|_| self.audio_engine.playback_state() != audio::engine::PlaybackState::Stopped);
+        let Some(loaded) = loaded else {
+            return Ok(());
+        };
+        let same_file = loaded == path
+            || matches!((std::fs::canonicalize(&loaded), std::fs::canonicalize(path)), (Ok(a), Ok(b)) if a == b);
+        if same_file {
+            return Err(io::Error::new(
+                io::ErrorKind::ResourceBusy,
+                format!("{} is playing; stop it before editing its tags", path.display()),
+            ).into());
+        }
+        Ok(())
+    }
+
+    /// Show the edited tags of a queued file in `queue list`
+    fn refresh_queued_file(&self, path: &Path) {
+        if let Err(e) = self.queue_manager.lock().unwrap().refresh_file(path) {
+            warn!("Could not re-read the tags of {}: {}", path.display(), e);
+        }
+    }
+
+    /// The system's default output device
+    fn system_default_device(&self) -> Result<String, PlayerError> {
+        self.audio_engine.default_device_name()
+            .ok_or_else(|| AudioError::DeviceNotFound { device: "default".to_string() }.into())
+    }
+
+    /// `favorites` and `last` stand for tracks unless such a file exists
+    fn is_keyword(path: &Path, keyword: &str) -> bool {
+        path.as_os_str() == keyword && !path.exists()
+    }
+
+    /// Add a file to the queue and make its track at `start_offset` current
+    fn enqueue_and_select(&mut self, path: &Path, start_offset: std::time::Duration) -> Result<(), PlayerError> {
+        let mut queue = self.queue_manager.lock().unwrap();
+        let first = queue.len();
+        queue.add_file(path)?;
+        let index = (first..queue.len())
+            .find(|&index| queue.list()[index].start_offset == start_offset)
+            .unwrap_or(first);
+        queue.jump_to(index)?;
+        Ok(())
+    }
+
+    /// Offer to resume the track a killed player left behind
+    async fn offer_resume(&mut self) {
+        if !self.config_manager.get_config().restore_session {
+            return;
+        }
+        let Some(session) = self.history.interrupted_session() else {
+            return;
+        };
+        self.history.save_session(None);
+        if !session.path.is_file() {
+            return;
+        }
+
+        let name = session.path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        print!("Resume {} at {}? [Y/n] ", name, CliApp::format_duration(session.track_position()));
+        let _ = io::stdout().flush();
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).is_err() {
+            return;
+        }
+        let answer = answer.trim().to_lowercase();
+        if !(answer.is_empty() || answer.starts_with('y')) {
+            return;
+        }
+
+        match self.resume_session(&session).await {
+            Ok(()) => println!("OK: Resumed at {}", CliApp::format_duration(session.track_position())),
+            Err(e) => self.handle_error_with_recovery(&e).await,
+        }
+    }
+
+    /// Queue the session's track and continue where it left off
+    async fn resume_session(&mut self, session: &queue::history::SessionState) -> Result<(), PlayerError> {
+        self.enqueue_and_select(&session.path, session.start_offset)?;
+        self.execute_command(Commands::Play { path: None }).await?;
+        let position = self.audio_engine.validate_seek_position(session.position)?;
+        self.audio_engine.seek(position)?;
+        Ok(())
+    }
+
+    /// Queue every favorited track that still exists
+    fn enqueue_favorites(&mut self) -> Result<usize, PlayerError> {
+        let favorites = self.ratings.favorites()?;
+        if favorites.is_empty() {
+            println!("No favorites yet (use 'fav' while a track is playing)");
+        }
+        let mut queue = self.queue_manager.lock().unwrap();
+        for path in &favorites {
+            queue.add_file(path)?;
+        }
+        Ok(favorites.len())
+    }
+
+    /// Insert a file or directory before queue `index`, or right after the current track
+    fn insert_into_queue(&mut self, path: &Path, index: Option<usize>) -> Result<usize, PlayerError> {
+        let (inserted, changes_next) = {
+            let mut queue = self.queue_manager.lock().unwrap();
+            let next = queue.play_next_index();
+            let index = index.unwrap_or(next);
+            let inserted = if path.is_dir() {
+                queue.insert_directory(path, index)?
+            } else {
+                queue.insert_file(path, index)?
+            };
+            (inserted, index <= next)
+        };
+        // A track preloaded for gapless playback would skip what was inserted ahead of it
+        if changes_next && inserted > 0 {
+            self.audio_engine.discard_preloaded_track()?;
+        }
+        Ok(inserted)
+    }
+
+    /// Confirm an active loop in track time
+    fn print_loop(track: &models::TrackInfo, region: models::LoopRegion) {
+        let region = region.relative_to(track.start_offset);
+        println!("OK: Looping {} - {}", CliApp::format_duration(region.start), CliApp::format_duration(region.end));
+    }
+
+    /// Band levels of the audio decoded last, and the sample rate it was analysed at
+    ///
+    /// Takes the newest `analyzer.fft_size()` frames from the ring buffer, mixed to mono;
+    /// `None` before anything has been decoded, or when it was all digital silence.
+    pub fn spectrum_snapshot(&self, analyzer: &audio::spectrum::SpectrumAnalyzer) -> Option<(Vec<f32>, u32)> {
+        let decoded = self.audio_engine.latest_decoded(analyzer.fft_size());
+        if decoded.sample_rate == 0 || decoded.samples.iter().all(|&sample| sample == 0.0) {
+            return None;
+        }
+        Some((analyzer.analyze(&decoded.mono(), decoded.sample_rate), decoded.sample_rate))
+    }
+
+    /// Get current player status
+    fn get_current_status(&mut self) -> PlayerStatus {
+        let mut status = PlayerStatus::new();
+
+        // Get playback state from audio engine and convert to models::PlaybackState
+        let engine_state = self.audio_engine.playback_state();
+        status.state = match engine_state {
+            audio::engine::PlaybackState::Stopped => models::PlaybackState::Stopped,
+            audio::engine::PlaybackState::Playing => models::PlaybackState::Playing,
+            audio::engine::PlaybackState::Paused => models::PlaybackState::Paused,
+        };
+
+        status.position = self.audio_engine.current_position();
+        status.volume = self.audio_engine.volume();
+        status.clipped_samples = self.audio_engine.clipped_samples();
+        status.limiter_enabled = self.audio_engine.is_limiter_enabled();
+        status.dither = self.audio_engine.is_dither_enabled();
+        status.exclusive_mode = self.audio_engine.is_exclusive_active();
+        status.output_buffer_frames = self.audio_engine.effective_output_buffer_frames();
+        status.output_latency_ms = self.audio_engine.output_latency_ms();
+        status.ring_buffer_ms = self.audio_engine.buffer_settings().ring_buffer_ms;
+        status.output_sample_rate = self.audio_engine.sample_rate();
+        status.output_bit_depth = self.audio_engine.bit_depth();
+        status.output_channels = self.audio_engine.channels();
+        status.output_mode = self.audio_engine.output_channels();
+        status.crossfeed = self.audio_engine.crossfeed();
+
+        // Merge what the audio and decoder threads last reported
+        let thread_status = self.audio_engine.get_status().clone();
+        status.buffer_fill = thread_status.buffer_fill;
+
+        // Show track info while a track is loaded, stopped included; otherwise basic device/volume only
+        let track_loaded = status.state != models::PlaybackState::Stopped
+            || self.audio_engine.current_track_path().is_some();
+        if track_loaded {
+            status.current_track = self.queue_manager.lock().unwrap().current_track().cloned();
+            if let Some(track) = status.current_track.as_mut() {
+                if let Err(e) = self.ratings.annotate(track) {
+                    warn!("Could not read ratings: {}", e);
+                }
+            }
+            if let Some(track) = status.current_track.as_mut() {
+                status.position = status.position.saturating_sub(track.start_offset);
+                status.loop_region = self.audio_engine.loop_region()
+                    .map(|region| region.relative_to(track.start_offset));
+                status.audio_format = thread_status.source_format.clone();
+                status.resampling = thread_status.resampling;
+                match &status.audio_format {
+                    // The decoder knows the real codec; the queue only guessed from the extension
+                    Some(format) => track.codec = Some(format.codec),
+                    None => status.audio_format = track.codec.map(|codec| models::AudioFormat::new(
+                        status.output_sample_rate,
+                        status.output_bit_depth,
+                        status.output_channels,
+                        codec,
+                    )),
+                }
+            }
+        } else {
+            status.current_track = None;
+            status.audio_format = None;
+        }
+
+        // Get current device name
+        status.output_device = self.audio_engine.current_device_name()
+            .unwrap_or(None);
+        status.oversampling = self.config_manager.get_config().oversampling_factor.filter(|factor| {
+            status.audio_format.as_ref().is_some_and(|format| format.sample_rate * factor == status.output_sample_rate)
+        });
+        status.degradation_warning = status.audio_format.as_ref()
+            .zip(status.output_device.as_deref())
+            .and_then(|(format, device)| {
+                let capabilities = self.audio_engine.device_capabilities(device)?;
+                format.degradation_warning(capabilities.max_sample_rate()?, capabilities.max_bit_depth()?)
+            });
+        status.recent_history = self.session_history.summary(models::SessionHistory::DEFAULT_LIMIT);
+        if status.state == models::PlaybackState::Playing {
+            status.levels = Some(self.audio_engine.output_levels());
+        }
+        status.seekable = self.audio_engine.supports_seek();
+        status.buffering = self.audio_engine.buffering_for().is_some();
+        status.stream = status.current_track.as_ref()
+            .and_then(|track| audio::source::stream_url(&track.path))
+            .and_then(audio::source::stream_status);
+
+        status
+    }
+
+    /// Run interactive mode
+    pub async fn run_interactive_mode(&mut self) -> Result<(), PlayerError> {
+        println!("High-Resolution Audio Player v0.1.0");
+        println!("Type 'help' for available commands, 'exit' or 'quit' to quit.");
+        println!();
+
+        let shutdown_flag = Self::install_shutdown_handler();
+
+        self.offer_resume().await;
+
+        // Non-blocking input with 100ms polling; the line editor runs on its own thread
+        let mut interval = tokio::time::interval(std::time::Duration::from_millis(100));
+        self.refresh_device_names();
+        let completer = cli::CommandCompleter::new(self.queue_manager.clone(), self.device_names.clone());
+        let mut input = cli::LineReader::spawn(completer, cli::LineReader::default_history_path());
+        self.console = input.console();
+        let mut api_requests = self.api_requests.take().expect("API requests are only received by one loop");
+        let mut ticks = TickState::new();
+        self.start_ipc_server();
+
+        // Watch for output devices being connected or disconnected
+        self.device_monitor = Some(audio::DeviceMonitor::start());
+        self.start_config_watcher();
+        self.start_directory_watcher();
+
+        loop {
+            // Check for shutdown signal
+            if shutdown_flag.load(std::sync::atomic::Ordering::Relaxed) {
+                break;
+            }
+
+            tokio::select! {
+                biased;
+
+                // Handle user input when it arrives (from the editor thread)
+                line = input.next_line() => {
+                    match line {
+                        Some(line) => {
+                            let line = line.trim().to_string();
+                            if line.is_empty() {
+                                input.prompt();
+                                continue;
+                            }
+                            if line == "exit" || line == "quit" {
+                                println!("Goodbye!");
+                                break;
+                            }
+                            match CliApp::parse_command(&line) {
+                                Ok(command) => {
+                                    if let Err(e) = self.execute_command(command).await {
+                                        self.handle_error_with_recovery(&e).await;
+                                    }
+                                }
+                                Err(ParseError::HelpRequested) => {
+                                    CliApp::display_help();
+                                }
+                                Err(e) => {
+                                    eprintln!("Error: {}", e);
+                                    println!("Type 'help' for available commands.");
+                                }
+                            }
+                            input.prompt();
+                        }
+                        None => {
+                            // Channel closed / EOF
+                            println!();
+                            break;
+                        }
+                    }
+                }
+
+                // Commands from the REST API, once `serve` has started it
+                Some(request) = api_requests.recv() => {
+                    self.handle_api_request(request).await;
+                }
+
+                // 100ms poll: process engine events and keep prompt responsive
+                _ = interval.tick() => {
+                    self.poll_engine(&mut ticks).await;
+                }
+            }
+
+        }
+
+        self.api_requests = Some(api_requests);
+        self.console = cli::ConsoleOutput::stdout();
+
+        // Perform graceful shutdown
+        self.shutdown().await?;
+
+        Ok(())
+    }
+
+    /// Run the REST API without a prompt until interrupted
+    pub async fn run_server_mode(&mut self) -> Result<(), PlayerError> {
+        let shutdown_flag = Self::install_shutdown_handler();
+        self.start_api_server().await?;
+
+        let mut interval = tokio::time::interval(std::time::Duration::from_millis(100));
+        let mut api_requests = self.api_requests.take().expect("API requests are only received by one loop");
+        let mut ticks = TickState::new();
+        self.start_ipc_server();
+        self.device_monitor = Some(audio::DeviceMonitor::start());
+        self.start_config_watcher();
+        self.start_directory_watcher();
+
+        while !shutdown_flag.load(std::sync::atomic::Ordering::Relaxed) {
+            tokio::select! {
+                biased;
+
+                Some(request) = api_requests.recv() => {
+                    self.handle_api_request(request).await;
+                }
+
+                _ = interval.tick() => {
+                    self.poll_engine(&mut ticks).await;
+                }
+            }
+        }
+
+        self.api_requests = Some(api_requests);
+        self.shutdown().await
+    }
+
+    /// Set up graceful shutdown on Ctrl-C
+    fn install_shutdown_handler() -> std::sync::Arc<std::sync::atomic::AtomicBool> {
+        let shutdown_flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let shutdown_flag_clone = shutdown_flag.clone();
+
+        ctrlc::set_handler(move || {
+            println!("\nReceived interrupt signal. Shutting down gracefully...");
+            shutdown_flag_clone.store(true, std::sync::atomic::Ordering::Relaxed);
+        }).expect("Error setting Ctrl-C handler");
+
+        shutdown_flag
+    }
+
+    /// Accept commands from later invocations, e.g. `rmusic play file.flac` in another terminal
+    fn start_ipc_server(&mut self) {
+        #[cfg(unix)]
+        match ipc::IpcServer::bind(&ipc::default_socket_path(), self.api_sender.clone()) {
+            Ok(server) => self.ipc_server = Some(server),
+            Err(e) => warn!("Commands from other invocations will not reach this player: {}", e),
+        }
+    }
+
+    /// Start the REST API on the configured address
+    async fn start_api_server(&mut self) -> Result<(), PlayerError> {
+        if let Some(server) = &self.api_server {
+            println!("REST API already listening on http://{}", server.local_addr());
+            return Ok(());
+        }
+
+        let config = self.config_manager.get_config();
+        let server = api::RestApiServer::bind(
+            &config.api_address,
+            config.api_port,
+            self.api_sender.clone(),
+            self.status_events.clone(),
+        ).await?;
+        println!("OK: REST API listening on http://{}", server.local_addr());
+        self.api_server = Some(server);
+        Ok(())
+    }
+
+    /// Answer a REST API request
+    async fn handle_api_request(&mut self, request: api::ApiRequest) {
+        let result = match request.command {
+            api::ApiCommand::Run(command) => self.execute_command(command).await.map(|()| serde_json::Value::Null),
+            api::ApiCommand::RetryPlayback => {
+                if let Err(e) = self.retry_playback() {
+                    self.handle_error_with_recovery(&e).await;
+                }
+                Ok(serde_json::Value::Null)
+            }
+            api::ApiCommand::Status => serde_json::to_value(self.get_current_status())
+                .map_err(|e| PlayerError::from(io::Error::from(e))),
+            api::ApiCommand::Queue => {
+                let snapshot = {
+                    let qm = self.queue_manager.lock().unwrap();
+                    api::QueueSnapshot {
+                        current_index: (!qm.is_empty()).then(|| qm.current_index()),
+                        tracks: qm.list().iter().cloned().collect(),
+                    }
+                };
+                serde_json::to_value(snapshot).map_err(|e| PlayerError::from(io::Error::from(e)))
+            }
+        };
+        let _ = request.reply.send(result);
+        self.publish_status();
+    }
+
+    /// Send the current status to WebSocket clients, if any are connected
+    fn publish_status(&mut self) {
+        if self.status_events.receiver_count() > 0 {
+            let status = self.get_current_status();
+            let _ = self.status_events.send(status);
+        }
+    }
+
+    /// Process engine events; called every 100ms by the interactive and server loops
+    async fn poll_engine(&mut self, ticks: &mut TickState) {
+        // Poll decoder responses to trigger any auto-reconfiguration and keep next track preloaded
+        if let Some(resp) = self.audio_engine.get_decoder_response() {
+            use crate::audio::engine::DecoderResponse;
+            match resp {
+                DecoderResponse::FileLoaded { .. } | DecoderResponse::TrackTransitioned => {
+                    if matches!(resp, DecoderResponse::TrackTransitioned) {
+                        if let Some(path) = ticks.playing_path.take() {
+                            self.track_finished(&path);
+                        }
+                    }
+                    // A stream's length and format are known once it is open
+                    if let DecoderResponse::FileLoaded { path, duration, sample_rate, bit_depth, channels } = &resp {
+                        if audio::source::stream_url(path).is_some() {
+                            self.queue_manager.lock().unwrap().update_stream(path, |track| {
+                                track.duration = duration.unwrap_or_default();
+                                track.sample_rate = Some(*sample_rate);
+                                track.bit_depth = Some(*bit_depth);
+                                track.channels = Some(*channels);
+                            });
+                        }
+                    }
+                    // Announce the new track title and reset completion flag
+                    let track = self.queue_manager.lock().unwrap().current_track().cloned();
+                    if let Some(track) = track {
+                        self.console.print_async(&format!("Now playing: {} - {}", track.display_name(), track.artist_name()));
+                        self.session_history.push(track.clone());
+                        let format_info = match resp {
+                            DecoderResponse::FileLoaded { sample_rate, bit_depth, channels, .. } => {
+                                format!("{}-bit/{:.1}kHz, {} channels", bit_depth, sample_rate as f32 / 1000.0, channels)
+                            }
+                            _ => self.audio_engine.get_status().source_format.as_ref()
+                                .map(|format| format.format_description())
+                                .unwrap_or_else(|| "format unknown".to_string()),
+                        };
+                        let path = track.path.display().to_string();
+                        self.logger.log_playback_started(&path, &format_info);
+                        ticks.playing_path = Some(path);
+                        self.track_played(&track.path);
+                    }
+                    ticks.announced_queue_finished = false;
+                }
+                DecoderResponse::EndOfFile => {
+                    if let Some(path) = ticks.playing_path.take() {
+                        self.track_finished(&path);
+                    }
+                    if !ticks.announced_queue_finished {
+                        self.announce_queue_finished();
+                        ticks.announced_queue_finished = true;
+                    }
+                }
+                DecoderResponse::TrackFailed { path, error } => {
+                    ticks.playing_path = None;
+                    self.skip_failed_track(&path, error).await;
+                }
+                DecoderResponse::Error(e) => {
+                    let path = ticks.playing_path.clone().unwrap_or_else(|| "unknown".to_string());
+                    self.logger.log_decode_error(&path, &e.to_string());
+                }
+                DecoderResponse::SeekFailed { requested, position, reason } => {
+                    let start_offset = self.queue_manager.lock().unwrap().current_track()
+                        .map(|track| track.start_offset)
+                        .unwrap_or_default();
+                    let requested = CliApp::format_duration(requested.saturating_sub(start_offset));
+                    self.console.print_async(&format!(
+                        "Seek to {} failed ({}); playing on from {}",
+                        requested,
+                        reason,
+                        CliApp::format_duration(position.saturating_sub(start_offset))
+                    ));
+                    let path = ticks.playing_path.clone().unwrap_or_else(|| "unknown".to_string());
+                    self.logger.log_decode_error(&path, &format!("Seek to {} failed: {}", requested, reason));
+                }
+                DecoderResponse::StreamMetadata { path, title } => {
+                    self.stream_title_changed(&path, title);
+                }
+                _ => {}
+            }
+        }
+
+        // CUE sheet tracks share a file; follow the playback position through them
+        let position = self.audio_engine.current_position();
+        let previous = self.queue_manager.lock().unwrap().current_track().map(|track| track.display_name());
+        let advanced = self.queue_manager.lock().unwrap().advance_within_file(position).cloned();
+        if let Some(track) = advanced {
+            self.console.print_async(&format!("Now playing: {} - {}", track.display_name(), track.artist_name()));
+            self.logger.log_track_changed(previous.as_deref(), &track.display_name());
+            self.session_history.push(track);
+        }
+
+        self.update_history(ticks.last_tick.elapsed(), &mut ticks.last_session_save);
+        ticks.last_tick = std::time::Instant::now();
+
+        self.handle_device_events();
+        self.reload_config_if_changed();
+        self.handle_directory_changes();
+
+        if ticks.last_perf_update.elapsed() >= std::time::Duration::from_secs(1) {
+            self.audio_engine.update_performance_monitoring();
+            ticks.last_perf_update = std::time::Instant::now();
+        }
+
+        // Report clipping once per track
+        if let Some(clipped) = self.audio_engine.take_first_clip() {
+            let track_path = self.queue_manager.lock().unwrap().current_track()
+                .map(|track| track.path.display().to_string())
+                .unwrap_or_default();
+            self.logger.log_clipping(&track_path, clipped);
+        }
+
+        // Log new underruns with the buffer level they happened at
+        let underruns = self.audio_engine.underrun_count();
+        if underruns > self.logged_underruns {
+            let buffer_fill = self.audio_engine.get_status().buffer_fill;
+            self.logger.log_underruns_detected(underruns - self.logged_underruns, buffer_fill);
+        }
+        self.logged_underruns = underruns;
+
+        // Grow the buffer if underruns keep recurring
+        if let Some(adjustment) = self.audio_engine.check_underruns() {
+            self.logger.log_buffer_adjustment(&adjustment.description());
+            self.error_recovery.record_buffer_adjustment(adjustment);
+        }
+
+        // A slow source keeps the output silent; say so, and give up rather than wait forever
+        match self.audio_engine.buffering_for() {
+            Some(waited) if waited >= BUFFERING_NOTICE_AFTER && !ticks.announced_buffering => {
+                self.console.print_async("Buffering…");
+                ticks.announced_buffering = true;
+            }
+            None => ticks.announced_buffering = false,
+            _ => {}
+        }
+        if let Err(e) = self.audio_engine.check_prebuffer() {
+            ticks.announced_buffering = false;
+            self.logger.log_playback_stopped(&e.to_string());
+            self.console.print_async(&format!("Playback stopped: {}", e.user_message()));
+        }
+
+        self.publish_status();
+    }
+
+    /// Show an internet radio station's new `StreamTitle` in the queue and announce it
+    fn stream_title_changed(&mut self, path: &Path, title: String) {
+        let mut changed = false;
+        self.queue_manager.lock().unwrap().update_stream(path, |track| {
+            changed |= track.metadata.title.as_ref() != Some(&title);
+            track.metadata.title = Some(title.clone());
+        });
+        if changed {
+            self.console.print_async(&format!("Now playing: {}", title));
+        }
+    }
+
+    /// Record finished plays and save the session every few seconds
+    fn update_history(&mut self, elapsed: std::time::Duration, last_session_save: &mut std::time::Instant) {
+        let state = self.audio_engine.playback_state();
+        let track = match state {
+            audio::engine::PlaybackState::Stopped => None,
+            _ => self.queue_manager.lock().unwrap().current_track().cloned(),
+        };
+        let playing = state == audio::engine::PlaybackState::Playing;
+        if let Some(play) = self.play_tracker.tick(track.as_ref(), playing, elapsed) {
+            self.history.record(play);
+        }
+
+        if self.config_manager.get_config().restore_session
+            && last_session_save.elapsed() >= std::time::Duration::from_secs(5)
+        {
+            let position = self.audio_engine.current_position();
+            self.history.save_session(track.map(|track| queue::history::SessionState::new(&track, position)));
+            *last_session_save = std::time::Instant::now();
+        }
+    }
+
+    /// Update the device names offered by tab completion
+    fn refresh_device_names(&mut self) {
+        *self.device_names.lock().unwrap() = self.audio_engine.list_devices();
+    }
+
+    /// Reload settings when `config.toml` is edited while the player runs
+    fn start_config_watcher(&mut self) {
+        match config::watch::ConfigWatcher::start(self.config_manager.config_path()) {
+            Ok(watcher) => self.config_watcher = Some(watcher),
+            Err(e) => self.console.print_async(&format!("Warning: Not watching config file for changes: {}", e)),
+        }
+    }
+
+    /// Resume watching the directories saved by `watch-dir add`
+    fn start_directory_watcher(&mut self) {
+        for directory in self.config_manager.get_config().watch_dirs.clone() {
+            if let Err(e) = self.watch_directory(&directory) {
+                self.console.print_async(&format!("Warning: Not watching {}: {}", directory.display(), e));
+            }
+        }
+    }
+
+    /// Add `directory` to the directory watcher, starting it if needed; false if already watched
+    fn watch_directory(&mut self, directory: &Path) -> io::Result<bool> {
+        let watcher = match &mut self.directory_watcher {
+            Some(watcher) => watcher,
+            None => self.directory_watcher.insert(
+                queue::watch::DirectoryWatcher::new(queue::watch::DirectoryWatcher::SETTLE_PERIOD).map_err(io::Error::other)?,
+            ),
+        };
+        watcher.add(directory).map_err(io::Error::other)
+    }
+
+    fn save_watch_dirs(&mut self) -> Result<(), PlayerError> {
+        let directories = self.directory_watcher.as_ref().map(|watcher| watcher.directories().to_vec()).unwrap_or_default();
+        Ok(self.config_manager.set_watch_dirs(directories)?)
+    }
+
+    fn handle_directory_changes(&mut self) {
+        let changes = match &mut self.directory_watcher {
+            Some(watcher) => watcher.poll(),
+            None => return,
+        };
+        self.apply_directory_changes(changes);
+    }
+
+    /// Queue new files from watched directories and drop deleted ones
+    ///
+    /// Files are read before the queue is locked, so playback is not held up by a slow disk.
+    fn apply_directory_changes(&mut self, changes: Vec<queue::watch::DirectoryChange>) {
+        use queue::watch::DirectoryChange;
+        for change in changes {
+            match change {
+                DirectoryChange::Added(path) => {
+                    let queued = self.queue_manager.lock().unwrap().list().iter().any(|track| track.path == path);
+                    if queued {
+                        // Rewritten in place, e.g. by a tag editor
+                        match queue::QueueManagerImpl::create_track_info(&path) {
+                            Ok(fresh) => {
+                                self.queue_manager.lock().unwrap().update_file(&path, &fresh);
+                            }
+                            Err(e) => warn!("Could not re-read the tags of {}: {}", path.display(), e),
+                        }
+                        continue;
+                    }
+                    match queue::QueueManagerImpl::tracks_for_file(&path) {
+                        Ok(tracks) => {
+                            self.queue_manager.lock().unwrap().append_tracks(tracks);
+                            info!("Queued {} from a watched directory", path.display());
+                            self.console.print_async(&format!("Queued {}", path.display()));
+                        }
+                        // A file that is still incomplete is reported again by its next write
+                        Err(e) => self.console.print_async(&format!("Warning: Not queueing {}: {}", path.display(), e.user_message())),
+                    }
+                }
+                DirectoryChange::Removed(path) => {
+                    let playing = self.audio_engine.playback_state() != audio::engine::PlaybackState::Stopped;
+                    let removed = self.queue_manager.lock().unwrap().remove_under(&path, playing);
+                    if !removed.is_empty() {
+                        info!("Removed {} deleted tracks under {} from the queue", removed.len(), path.display());
+                        self.console.print_async(&format!(
+                            "Removed {} from the queue ({} track{})",
+                            path.display(),
+                            removed.len(),
+                            if removed.len() == 1 { "" } else { "s" }
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply edits to `config.toml`; an invalid file keeps the current settings
+    fn reload_config_if_changed(&mut self) {
+        if !self.config_watcher.as_mut().is_some_and(|watcher| watcher.poll_changed()) {
+            return;
+        }
+        let changed = match self.config_manager.reload() {
+            Ok(changed) => changed,
+            Err(e) => {
+                // Warn once, not on every save while the file is being edited
+                if !self.config_reload_failed {
+                    self.console.print_async(&format!(
+                        "Warning: Config file not reloaded, keeping current settings: {}",
+                        e.user_message()
+                    ));
+                    self.config_reload_failed = true;
+                }
+                return;
+            }
+        };
+        self.config_reload_failed = false;
+        if changed.is_empty() {
+            return;
+        }
+
+        let mut needs_restart = Vec::new();
+        for key in &changed {
+            match self.apply_setting(key) {
+                Ok(true) => {}
+                Ok(false) => needs_restart.push(*key),
+                Err(e) => self.console.print_async(&format!("Warning: Could not apply {}: {}", key, e.user_message())),
+            }
+        }
+        self.console.print_async(&format!("Config reloaded: {} changed", changed.join(", ")));
+        if !needs_restart.is_empty() {
+            self.console.print_async(&format!(
+                "Note: {} take effect the next time the player starts",
+                needs_restart.join(", ")
+            ));
+        }
+    }
+
+    /// React to hot-plug notifications from the device monitor
+    fn handle_device_events(&mut self) {
+        let events: Vec<audio::DeviceEvent> = match &self.device_monitor {
+            Some(monitor) => std::iter::from_fn(|| monitor.try_recv()).collect(),
+            None => return,
+        };
+        if !events.is_empty() {
+            let _ = self.audio_engine.refresh_devices();
+            self.refresh_device_names();
+        }
+
+        for event in events {
+            match event {
+                audio::DeviceEvent::Added(name) => {
+                    self.console.print_async(&format!("Device connected: {}", name));
+                    if self.config_manager.get_config().auto_switch_to_new_device {
+                        self.switch_output_device(&name);
+                    }
+                }
+                audio::DeviceEvent::Removed(name) => {
+                    self.console.print_async(&format!("Device disconnected: {}", name));
+                    // A device that can no longer report its name is gone as well
+                    let current_removed = match self.audio_engine.current_device_name() {
+                        Ok(Some(current)) => audio::device::device_names_match(&current, &name),
+                        Ok(None) => false,
+                        Err(_) => true,
+                    };
+                    if current_removed {
+                        let _ = self.audio_engine.refresh_devices();
+                        match self.audio_engine.default_device_name() {
+                            Some(default_device) => self.switch_output_device(&default_device),
+                            None => self.console.print_async("Warning: No default output device available"),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Switch playback to another output device after a hot-plug event
+    fn switch_output_device(&mut self, device_name: &str) {
+        let started = std::time::Instant::now();
+        let previous = self.audio_engine.current_device_name().unwrap_or(None);
+
+        if let Err(e) = self.audio_engine.refresh_devices() {
+            self.console.print_async(&format!("Warning: Could not refresh audio devices: {}", e));
+            return;
+        }
+
+        match self.audio_engine.set_device(device_name) {
+            Ok(()) => {
+                self.logger.log_device_changed(previous.as_deref(), device_name, started.elapsed());
+                self.console.print_async(&format!("Audio device set to: {}", device_name));
+            }
+            Err(e) => self.console.print_async(&format!("Warning: Could not switch to '{}': {}", device_name, e)),
+        }
     }
 
     /// Perform graceful shutdown with resource cleanup and configuration saving
     pub async fn shutdown(&mut self) -> Result<(), PlayerError> {
         println!("Shutting down...");
 
+        // A clean exit has nothing to resume
+        if let Some(play) = self.play_tracker.finish() {
+            self.history.record(play);
+        }
+        self.history.save_session(None);
+
         // Stop audio playback
         if let Err(e) = self.audio_engine.stop() {
             eprintln!("Warning: Error stopping audio engine: {}", e);
@@ -514,6 +2222,206 @@ impl AppController {
         Ok(())
     }
 
+    /// Save and load playlists in `directory` from now on; `move_existing` brings the saved ones along
+    fn change_playlist_directory(&mut self, directory: PathBuf, move_existing: bool) -> Result<(), PlayerError> {
+        let mut qm = self.queue_manager.lock().unwrap();
+        let old_directory = qm.playlist_directory().to_path_buf();
+        if move_existing {
+            let outcome = qm.move_playlist_directory(directory.clone())?;
+            println!("Moved {} playlist file(s) from {}", outcome.moved, old_directory.display());
+            for skipped in &outcome.skipped {
+                println!("Warning: Kept {}: the new directory already has a file with that name", skipped.display());
+            }
+        } else {
+            let left_behind = qm.list_playlists().unwrap_or_default().len();
+            qm.set_playlist_directory(directory.clone())?;
+            if left_behind > 0 && qm.playlist_directory() != old_directory {
+                println!(
+                    "Note: {} playlist(s) remain in {}; add --move to bring them along",
+                    left_behind,
+                    old_directory.display()
+                );
+            }
+        }
+        drop(qm);
+
+        self.config_manager.set_playlist_directory(directory.clone())?;
+        println!("OK: playlist_directory = {}", directory.display());
+        Ok(())
+    }
+
+    /// Apply a saved setting to the running player; false when it is only read at startup
+    fn apply_setting(&mut self, key: &str) -> Result<bool, PlayerError> {
+        let config = self.config_manager.get_config().clone();
+        match key {
+            "default_volume" => self.audio_engine.set_volume(config.default_volume)?,
+            "preferred_device" => match &config.preferred_device {
+                Some(device) => {
+                    if let Err(e) = self.audio_engine.set_device(device) {
+                        println!("Warning: Could not switch to '{}': {}", device, e);
+                    }
+                }
+                None => return Ok(false),
+            },
+            "buffer_size" | "output_buffer_frames" => {
+                let requested = config.device_buffer_frames();
+                let effective = self.audio_engine.set_output_buffer_frames(requested)?;
+                if effective != requested {
+                    println!("Warning: {} frames is outside the device's supported range; clamped to {}", requested, effective);
+                }
+            }
+            "enable_gapless" => self.audio_engine.set_gapless_enabled(config.enable_gapless),
+            "prefer_native_rate_switch" => self.audio_engine.set_prefer_native_rate_switch(config.prefer_native_rate_switch),
+            "fade_out_ms" | "fade_in_ms" => self.audio_engine.set_fade_durations(
+                std::time::Duration::from_millis(config.fade_out_ms),
+                std::time::Duration::from_millis(config.fade_in_ms),
+            ),
+            "output_mode" | "balance" => self.audio_engine.set_output_channels(config.output_channels()),
+            "channel_layout" => self.audio_engine.set_channel_layout(config.channel_layout.clone()),
+            "oversampling_factor" => self.audio_engine.set_oversampling(config.oversampling_factor),
+            "crossfeed" | "crossfeed_level_db" => self.audio_engine.set_crossfeed(config.crossfeed_settings()),
+            "dither" => self.audio_engine.set_dither(config.dither),
+            "suspend_output_after_secs" => self.audio_engine.set_suspend_output_after(
+                std::time::Duration::from_secs(config.suspend_output_after_secs),
+            ),
+            "prebuffer_ms" => self.audio_engine.set_prebuffer(std::time::Duration::from_millis(config.prebuffer_ms)),
+            "skip_silence" | "silence_threshold_db" | "silence_leading_ms" | "silence_trailing_ms" => {
+                self.audio_engine.set_silence_skipping(config.silence_settings());
+            }
+            "exclusive_mode" => {
+                let acquired = self.audio_engine.set_exclusive_mode(config.exclusive_mode)?;
+                if config.exclusive_mode && !acquired {
+                    println!("Warning: Exclusive mode is not available on this device; using shared mode");
+                }
+            }
+            "playlist_directory" => self.queue_manager.lock().unwrap().set_playlist_directory(config.playlist_directory)?,
+            "skip_duplicates" => self.queue_manager.lock().unwrap().set_skip_duplicates(config.skip_duplicates),
+            "follow_symlinks" => self.queue_manager.lock().unwrap().set_follow_symlinks(config.follow_symlinks),
+            "nomusic_files" => self.queue_manager.lock().unwrap().set_nomusic_files(config.nomusic_files),
+            "recovery_cooldown_secs" => {
+                self.error_recovery.set_cooldown(std::time::Duration::from_secs(config.recovery_cooldown_secs));
+            }
+            // Read each time they are used
+            "show_spectrum" | "auto_switch_to_new_device" | "library_path" | "api_address" | "api_port"
+            | "max_consecutive_failures" | "art_viewer" => {}
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Flag a track the decoder gave up on and load the one after it
+    async fn skip_failed_track(&mut self, path: &Path, error: AudioError) {
+        let reason = error.to_string();
+        self.logger.log_decode_error(&path.display().to_string(), &reason);
+        if matches!(&error, AudioError::StreamError(msg) if msg == audio::engine::DECODE_TIMEOUT_MESSAGE) {
+            let result = self.attempt_recovery(&PlayerError::Audio(error)).await;
+            if result.is_retry() {
+                self.console.print_async(&format!("🔄 Recovery suggestion: {}", result.message()));
+            }
+        }
+        match self.skip_unplayable(path, &reason) {
+            Some(track) => {
+                if let Err(e) = self.audio_engine.load_file_at(track.path.clone(), track.start_offset) {
+                    self.console.print_async(&format!("Error: {}", e));
+                }
+            }
+            None => {
+                let _ = self.audio_engine.stop();
+            }
+        }
+    }
+
+    /// Mark the queue entries of `path` as unplayable and move the queue to the next file
+    ///
+    /// Returns the track to play next, or `None` at the end of the queue or once
+    /// `max_consecutive_failures` tracks in a row have failed.
+    fn skip_unplayable(&mut self, path: &Path, reason: &str) -> Option<models::TrackInfo> {
+        let name = {
+            let mut queue = self.queue_manager.lock().unwrap();
+            queue.set_failed(path, true);
+            queue.current_track().filter(|track| track.path == path).map(|track| track.display_name())
+        };
+        let name = name.unwrap_or_else(|| path.display().to_string());
+        self.consecutive_failures += 1;
+        self.skipped_tracks += 1;
+
+        let limit = self.config_manager.get_config().max_consecutive_failures;
+        if self.consecutive_failures >= limit {
+            self.console.print_async(&format!("Cannot play {}: {}", name, reason));
+            self.console.print_async(&format!("Stopped after {} unplayable tracks in a row", self.consecutive_failures));
+            self.consecutive_failures = 0;
+            self.skipped_tracks = 0;
+            return None;
+        }
+        self.console.print_async(&format!("Skipping unplayable track {}: {}", name, reason));
+
+        let next = {
+            let mut queue = self.queue_manager.lock().unwrap();
+            queue.next_file_index().and_then(|index| queue.jump_to(index).ok().cloned())
+        };
+        if next.is_none() {
+            self.announce_queue_finished();
+        }
+        next
+    }
+
+    /// A track opened after all; clear its flag and restart the count of failures in a row
+    fn track_played(&mut self, path: &Path) {
+        self.consecutive_failures = 0;
+        self.queue_manager.lock().unwrap().set_failed(path, false);
+    }
+
+    /// A track played through to its end, so earlier recovery attempts no longer count
+    fn track_finished(&mut self, path: &str) {
+        self.logger.log_track_finished(path);
+        self.error_recovery.reset();
+    }
+
+    /// Report the end of the queue and how many tracks were skipped on the way
+    fn announce_queue_finished(&mut self) {
+        self.console.print_async("Queue finished");
+        match self.skipped_tracks {
+            0 => {}
+            1 => self.console.print_async("Skipped 1 unplayable track"),
+            skipped => self.console.print_async(&format!("Skipped {} unplayable tracks", skipped)),
+        }
+        self.consecutive_failures = 0;
+        self.skipped_tracks = 0;
+    }
+
+    /// Run the recovery manager, which calls back into the controller to carry out fixes
+    async fn attempt_recovery(&mut self, error: &PlayerError) -> RecoveryResult {
+        let placeholder = ErrorRecoveryManager::new(self.logger.clone());
+        let mut recovery = std::mem::replace(&mut self.error_recovery, placeholder);
+        let result = recovery.attempt_recovery(error, self).await;
+        self.error_recovery = recovery;
+        result
+    }
+
+    /// Ask the main loop to restart playback once `wait` has passed
+    fn schedule_playback_retry(&self, wait: std::time::Duration) {
+        let requests = self.api_sender.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(wait).await;
+            let (reply, _) = tokio::sync::oneshot::channel();
+            let _ = requests.send(api::ApiRequest { command: api::ApiCommand::RetryPlayback, reply });
+        });
+    }
+
+    /// Reopen the current track where playback stopped, unless it was stopped since
+    fn retry_playback(&mut self) -> Result<(), PlayerError> {
+        if self.audio_engine.playback_state() == audio::engine::PlaybackState::Stopped {
+            return Ok(());
+        }
+        let Some(track) = self.queue_manager.lock().unwrap().current_track().cloned() else {
+            return Ok(());
+        };
+        let position = self.audio_engine.current_position().max(track.start_offset);
+        self.audio_engine.load_file_at(track.path.clone(), position)?;
+        self.console.print_async(&format!("✓ Restarted {}", track.display_name()));
+        Ok(())
+    }
+
     /// Handle error with automatic recovery attempts
     async fn handle_error_with_recovery(&mut self, error: &PlayerError) {
         error!("Error occurred: {}", error);
@@ -530,7 +2438,7 @@ impl AppController {
 
         // Attempt automatic recovery if the error is recoverable
         if error.is_recoverable() {
-            match self.error_recovery.attempt_recovery(error).await {
+            match self.attempt_recovery(error).await {
                 RecoveryResult::Success(msg) => {
                     info!("Recovery successful: {}", msg);
                     println!("✓ Recovered: {}", msg);
@@ -539,6 +2447,10 @@ impl AppController {
                 RecoveryResult::Retry(msg) => {
                     info!("Recovery suggests retry: {}", msg);
                     println!("🔄 Recovery suggestion: {}", msg);
+                    if let Some(wait) = self.error_recovery.take_scheduled_retry(error) {
+                        self.schedule_playback_retry(wait);
+                        return;
+                    }
                 }
                 RecoveryResult::Failed(msg) => {
                     warn!("Recovery failed: {}", msg);
@@ -560,7 +2472,7 @@ impl AppController {
             config.enable_gapless = self.audio_engine.is_gapless_enabled();
 
             // Save current device if available
-            if let Ok(Some(device_name)) = self.audio_engine.device_manager().current_device_name() {
+            if let Ok(Some(device_name)) = self.audio_engine.current_device_name() {
                 config.preferred_device = Some(device_name);
             }
         })?;
@@ -569,8 +2481,67 @@ impl AppController {
     }
 }
 
+impl RecoveryActions for AppController {
+    fn switch_to_default_device(&mut self) -> Result<String, PlayerError> {
+        let started = std::time::Instant::now();
+        let previous = self.audio_engine.current_device_name().unwrap_or(None);
+        self.audio_engine.refresh_devices()?;
+        let device = self.audio_engine.default_device_name().ok_or_else(|| AudioError::DeviceNotFound {
+            device: "default".to_string(),
+        })?;
+        self.audio_engine.set_device(&device)?;
+        self.logger.log_device_changed(previous.as_deref(), &device, started.elapsed());
+        self.refresh_device_names();
+        Ok(device)
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), PlayerError> {
+    // Parse command line arguments
+    let cli = CliApp::parse();
+
+    let batch_script = match &cli.batch {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(script) => Some(script),
+            Err(e) => {
+                eprintln!("Error: Could not read batch file '{}': {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // Hand the command to a player that is already running, if there is one; `verify` decodes
+    // here instead so the exit code tells whether files failed
+    #[cfg(unix)]
+    if let Some(command) = cli.command.as_ref()
+        .filter(|command| !matches!(command, Commands::Verify { target } if !target.eq_ignore_ascii_case("cancel")))
+    {
+        if let Ok(mut client) = ipc::IpcClient::connect() {
+            match client.send_command(command) {
+                Ok(reply) => {
+                    println!("{}", reply);
+                    return Ok(());
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+    } else if let Some(script) = &batch_script {
+        if let Ok(mut client) = ipc::IpcClient::connect() {
+            let report = cli::batch::run_batch(script, cli.batch_abort_on_error, async |command| {
+                let reply = client.send_command(&command)?;
+                println!("{}", reply);
+                Ok(())
+            })
+            .await;
+            std::process::exit(if report.failed > 0 { 1 } else { 0 });
+        }
+    }
+
     // Create and initialize application controller
     let mut app = match AppController::new() {
         Ok(app) => app,
@@ -590,19 +2561,10 @@ async fn main() -> Result<(), PlayerError> {
     impl crate::audio::engine::NextTrackProvider for QueueNextTrackProvider {
         fn request_next(&self) -> Option<std::path::PathBuf> {
             let mut qm = self.qm.lock().unwrap();
-            let len = qm.len();
-            if len == 0 {
-                return None;
-            }
-            let cur = qm.current_index();
-            // Advance to the next track without wrapping. If at the end, signal completion.
-            if cur + 1 < len {
-                let _ = qm.jump_to(cur + 1);
-                if let Some(track) = qm.current_track() {
-                    return Some(track.path.clone());
-                }
-            }
-            None
+            // Advance to the next file without wrapping. If at the end, signal completion.
+            // CUE sheet tracks after the current one were part of the file that just ended.
+            let next = qm.next_file_index()?;
+            qm.jump_to(next).ok().map(|track| track.path.clone())
         }
     }
     let provider = std::sync::Arc::new(QueueNextTrackProvider {
@@ -616,10 +2578,13 @@ async fn main() -> Result<(), PlayerError> {
         std::process::exit(1);
     }
 
-    // Parse command line arguments
-    let cli = CliApp::parse();
-
     match cli.command {
+        Some(Commands::Serve) => {
+            if let Err(e) = app.run_server_mode().await {
+                app.handle_error_with_recovery(&e).await;
+                std::process::exit(1);
+            }
+        }
         Some(command) => {
             // Single command mode
             if let Err(e) = app.execute_command(command).await {
@@ -628,6 +2593,18 @@ async fn main() -> Result<(), PlayerError> {
             }
         }
         None => {
+            if let Some(script) = &batch_script {
+                let report = cli::batch::run_batch(script, cli.batch_abort_on_error, async |command| {
+                    app.execute_command(command).await
+                })
+                .await;
+                println!("Batch finished: {} command(s) run, {} failed", report.executed, report.failed);
+                if report.stopped {
+                    app.shutdown().await?;
+                    std::process::exit(if report.failed > 0 { 1 } else { 0 });
+                }
+            }
+
             // Interactive mode
             if let Err(e) = app.run_interactive_mode().await {
                 app.handle_error_with_recovery(&e).await;
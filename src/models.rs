@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
+use crate::audio::channels::OutputChannels;
+use crate::audio::crossfeed::CrossfeedSettings;
+use crate::audio::source::StreamStatus;
 
 /// Information about an audio track
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -9,6 +13,36 @@ pub struct TrackInfo {
     pub metadata: AudioMetadata,
     pub duration: Duration,
     pub file_size: u64,
+    /// Codec of the file, once known
+    #[serde(default)]
+    pub codec: Option<AudioCodec>,
+    /// Sample rate of the stream in Hz, when the probe reported it
+    #[serde(default)]
+    pub sample_rate: Option<u32>,
+    /// Bits per sample, for codecs that have one
+    #[serde(default)]
+    pub bit_depth: Option<u16>,
+    /// Channel count of the stream, when the probe reported it
+    #[serde(default)]
+    pub channels: Option<u16>,
+    /// Where the track starts within its file (CUE sheet tracks)
+    #[serde(default)]
+    pub start_offset: Duration,
+    /// Where the track ends within its file, if before the end of the file
+    #[serde(default)]
+    pub end_offset: Option<Duration>,
+    /// Star rating (1-5) from the ratings store
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    /// Favorite flag from the ratings store
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub favorite: bool,
+    /// SHA-256 of the file, when it was added with `--verify`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<[u8; 32]>,
+    /// The file could not be opened or decoded and playback skipped it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub failed: bool,
 }
 
 impl TrackInfo {
@@ -18,15 +52,34 @@ impl TrackInfo {
             metadata,
             duration,
             file_size,
+            codec: None,
+            sample_rate: None,
+            bit_depth: None,
+            channels: None,
+            start_offset: Duration::ZERO,
+            end_offset: None,
+            rating: None,
+            favorite: false,
+            content_hash: None,
+            failed: false,
         }
     }
 
+    /// Whether this entry is the part of its file that directly follows `previous`
+    pub fn continues(&self, previous: &TrackInfo) -> bool {
+        self.path == previous.path && previous.end_offset == Some(self.start_offset)
+    }
+
     /// Get the display name for this track (title or filename)
     pub fn display_name(&self) -> String {
         self.metadata
             .title
             .clone()
             .unwrap_or_else(|| {
+                // A stream without a title is known by its whole URL
+                if let Some(url) = crate::audio::source::stream_url(&self.path) {
+                    return url.to_string();
+                }
                 self.path
                     .file_stem()
                     .and_then(|s| s.to_str())
@@ -50,6 +103,120 @@ impl TrackInfo {
             .clone()
             .unwrap_or_else(|| "Unknown Album".to_string())
     }
+
+    /// Short stream format such as "24/96 FLAC" or "44.1kHz MP3"; None when nothing is known
+    pub fn format_label(&self) -> Option<String> {
+        let rate = self.sample_rate.map(khz_label);
+        let stream = match (self.bit_depth, rate) {
+            (Some(bits), Some(rate)) => Some(format!("{}/{}", bits, rate)),
+            (None, Some(rate)) => Some(format!("{}kHz", rate)),
+            _ => None,
+        };
+        let codec = self.codec.as_ref().map(|codec| codec.name().to_string());
+        match (stream, codec) {
+            (Some(stream), Some(codec)) => Some(format!("{} {}", stream, codec)),
+            (stream, codec) => stream.or(codec),
+        }
+    }
+
+    /// Stars and favorite marker, e.g. "★★★★☆ ♥"; empty when unrated
+    pub fn rating_label(&self) -> String {
+        let mut label = match self.rating {
+            Some(stars) => {
+                let stars = stars.min(5) as usize;
+                format!("{}{}", "★".repeat(stars), "☆".repeat(5 - stars))
+            }
+            None => String::new(),
+        };
+        if self.favorite {
+            if !label.is_empty() {
+                label.push(' ');
+            }
+            label.push('♥');
+        }
+        label
+    }
+
+    /// Quality tier of the stream: "Studio Master" from 24-bit/176.4 kHz, "Hi-Res" above
+    /// 16-bit/48 kHz, "CD Quality" at or below it; lossy codecs and unknown formats are named as such
+    pub fn quality_label(&self) -> &'static str {
+        if self.codec.as_ref().is_some_and(|codec| !codec.is_lossless()) {
+            return "Lossy";
+        }
+        let bits = self.bit_depth.unwrap_or(0);
+        match self.sample_rate {
+            None if self.bit_depth.is_none() => "Unknown",
+            Some(rate) if rate >= 176_400 && bits >= 24 => "Studio Master",
+            rate if rate.unwrap_or(0) > 48_000 || bits >= 24 => "Hi-Res",
+            _ => "CD Quality",
+        }
+    }
+
+    /// All tagged metadata as "Field: value" lines, followed by the lyrics when present
+    pub fn display_full_metadata(&self) -> String {
+        use crate::cli::status::StatusDisplay;
+        let metadata = &self.metadata;
+        let mut lines = vec![
+            format!("Title: {}", self.display_name()),
+            format!("Artist: {}", self.artist_name()),
+            format!("Album: {}", self.album_name()),
+        ];
+        let numbered = |number: u32, total: Option<u32>| match total {
+            Some(total) => format!("{}/{}", number, total),
+            None => number.to_string(),
+        };
+        if let Some(track_number) = metadata.track_number {
+            lines.push(format!("Track: {}", numbered(track_number, metadata.total_tracks)));
+        }
+        if let Some(disc) = metadata.disc_number {
+            lines.push(format!("Disc: {}", numbered(disc, metadata.total_discs)));
+        }
+        if let Some(year) = metadata.year {
+            lines.push(format!("Year: {}", year));
+        }
+        if let Some(genre) = &metadata.genre {
+            lines.push(format!("Genre: {}", genre));
+        }
+        if let Some(composer) = &metadata.composer {
+            lines.push(format!("Composer: {}", composer));
+        }
+        if let Some(bpm) = metadata.bpm {
+            lines.push(format!("BPM: {}", bpm));
+        }
+        let rating = self.rating_label();
+        if !rating.is_empty() {
+            lines.push(format!("Rating: {}", rating));
+        }
+        if metadata.compilation {
+            lines.push("Compilation: Yes".to_string());
+        }
+        if let Some(comment) = &metadata.comment {
+            lines.push(format!("Comment: {}", comment));
+        }
+        lines.push(format!("Duration: {}", StatusDisplay::format_duration(self.duration)));
+        lines.push(format!("File Size: {}", StatusDisplay::format_file_size(self.file_size)));
+        match self.format_label() {
+            Some(format) => lines.push(format!("Quality: {} ({})", self.quality_label(), format)),
+            None => lines.push(format!("Quality: {}", self.quality_label())),
+        }
+        lines.push(format!("Path: {}", self.path.display()));
+        if let Some(lyrics) = &metadata.lyrics {
+            lines.push("Lyrics:".to_string());
+            lines.extend(lyrics.lines().map(|line| format!("  {}", line)));
+        }
+        lines.join("\n")
+    }
+
+    /// Serialize the track and its metadata as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl std::fmt::Display for TrackInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.display_full_metadata())
+    }
 }
 
 /// Audio metadata extracted from files
@@ -58,12 +225,44 @@ pub struct AudioMetadata {
     pub title: Option<String>,
     pub artist: Option<String>,
     pub album: Option<String>,
+    /// Release-level artist, when it differs from the track artist
+    #[serde(default)]
+    pub album_artist: Option<String>,
     pub track_number: Option<u32>,
     pub year: Option<u32>,
     pub genre: Option<String>,
+    /// Total tracks on the release
+    #[serde(default)]
+    pub total_tracks: Option<u32>,
+    /// Disc number within a multi-disc release
+    #[serde(default)]
+    pub disc_number: Option<u32>,
+    /// Total discs in a multi-disc release
+    #[serde(default)]
+    pub total_discs: Option<u32>,
+    #[serde(default)]
+    pub composer: Option<String>,
+    /// Tempo in beats per minute
+    #[serde(default)]
+    pub bpm: Option<f32>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// Unsynchronized lyrics
+    #[serde(default)]
+    pub lyrics: Option<String>,
+    /// Part of a various-artists compilation
+    #[serde(default)]
+    pub compilation: bool,
+    /// Every other text tag as (key, value), in file order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_tags: Vec<(String, String)>,
+    /// Embedded cover picture; only loaded on request for the current track, never saved
+    #[serde(skip)]
+    pub artwork: Option<Artwork>,
 }
 
 impl AudioMetadata {
+    /// Metadata with every field unset
     pub fn new() -> Self {
         Self::default()
     }
@@ -73,9 +272,20 @@ impl AudioMetadata {
         self.title.is_none()
             && self.artist.is_none()
             && self.album.is_none()
+            && self.album_artist.is_none()
             && self.track_number.is_none()
             && self.year.is_none()
             && self.genre.is_none()
+            && self.total_tracks.is_none()
+            && self.disc_number.is_none()
+            && self.total_discs.is_none()
+            && self.composer.is_none()
+            && self.bpm.is_none()
+            && self.comment.is_none()
+            && self.lyrics.is_none()
+            && !self.compilation
+            && self.extra_tags.is_empty()
+            && self.artwork.is_none()
     }
 
     /// Create metadata with basic information
@@ -88,6 +298,27 @@ impl AudioMetadata {
     }
 }
 
+/// Cover picture embedded in a file (FLAC picture block, ID3 APIC frame, MP4 covr atom)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Artwork {
+    pub data: Vec<u8>,
+    /// Media type as tagged, e.g. `image/jpeg`
+    pub mime: String,
+}
+
+impl Artwork {
+    /// File extension matching the media type, JPEG when it is unknown
+    pub fn extension(&self) -> &'static str {
+        match self.mime.to_ascii_lowercase().as_str() {
+            "image/png" => "png",
+            "image/gif" => "gif",
+            "image/bmp" => "bmp",
+            "image/webp" => "webp",
+            _ => "jpg",
+        }
+    }
+}
+
 /// Audio format information
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct AudioFormat {
@@ -124,6 +355,32 @@ impl AudioFormat {
         self.bit_depth >= 24 || self.sample_rate >= 96000
     }
 
+    /// Score for comparing formats: sample rate times bit depth
+    pub fn quality_rank(&self) -> u32 {
+        self.sample_rate.saturating_mul(self.bit_depth as u32)
+    }
+
+    /// Warning when a device that goes up to `max_sample_rate` and `max_bit_depth` can't play
+    /// this format as it is, e.g. "Warning: device downsampling 192kHz→48kHz"
+    pub fn degradation_warning(&self, max_sample_rate: u32, max_bit_depth: u16) -> Option<String> {
+        let best = AudioFormat {
+            sample_rate: self.sample_rate.min(max_sample_rate),
+            bit_depth: self.bit_depth.min(max_bit_depth),
+            ..self.clone()
+        };
+        if best.quality_rank() >= self.quality_rank() {
+            return None;
+        }
+        let mut losses = Vec::new();
+        if best.sample_rate < self.sample_rate {
+            losses.push(format!("downsampling {}kHz→{}kHz", khz_label(self.sample_rate), khz_label(best.sample_rate)));
+        }
+        if best.bit_depth < self.bit_depth {
+            losses.push(format!("truncating {}-bit→{}-bit", self.bit_depth, best.bit_depth));
+        }
+        Some(format!("Warning: device {}", losses.join(", ")))
+    }
+
     /// Calculate approximate bitrate for uncompressed formats
     pub fn bitrate(&self) -> Option<u32> {
         match self.codec {
@@ -135,14 +392,25 @@ impl AudioFormat {
     }
 }
 
+/// Sample rate in kHz without trailing zeros, e.g. "96" or "44.1"
+fn khz_label(rate: u32) -> String {
+    if rate.is_multiple_of(1000) {
+        format!("{}", rate / 1000)
+    } else {
+        format!("{:.1}", rate as f64 / 1000.0)
+    }
+}
+
 /// Supported audio codecs
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum AudioCodec {
     Flac,
     Wav,
     Alac,
     Mp3,
     OggVorbis,
+    Aac,
+    WavPack,
 }
 
 impl AudioCodec {
@@ -154,12 +422,26 @@ impl AudioCodec {
             AudioCodec::Alac => "ALAC",
             AudioCodec::Mp3 => "MP3",
             AudioCodec::OggVorbis => "OGG Vorbis",
+            AudioCodec::Aac => "AAC",
+            AudioCodec::WavPack => "WavPack",
+        }
+    }
+
+    /// Name of the file format the codec usually comes in
+    pub fn container_name(&self) -> &'static str {
+        match self {
+            AudioCodec::Flac => "FLAC",
+            AudioCodec::Wav => "RIFF WAVE",
+            AudioCodec::Alac | AudioCodec::Aac => "MPEG-4",
+            AudioCodec::Mp3 => "MPEG audio",
+            AudioCodec::OggVorbis => "Ogg",
+            AudioCodec::WavPack => "WavPack",
         }
     }
 
     /// Check if the codec is lossless
     pub fn is_lossless(&self) -> bool {
-        matches!(self, AudioCodec::Flac | AudioCodec::Wav | AudioCodec::Alac)
+        matches!(self, AudioCodec::Flac | AudioCodec::Wav | AudioCodec::Alac | AudioCodec::WavPack)
     }
 
     /// Get file extensions associated with this codec
@@ -170,6 +452,49 @@ impl AudioCodec {
             AudioCodec::Alac => &["m4a", "alac"],
             AudioCodec::Mp3 => &["mp3"],
             AudioCodec::OggVorbis => &["ogg", "oga"],
+            AudioCodec::Aac => &["m4a", "mp4"],
+            AudioCodec::WavPack => &["wv"],
+        }
+    }
+}
+
+/// A-B loop region within a track
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoopRegion {
+    pub start: Duration,
+    pub end: Duration,
+}
+
+impl LoopRegion {
+    /// Create a region; `None` unless `end` is after `start`
+    pub fn new(start: Duration, end: Duration) -> Option<Self> {
+        (end > start).then_some(Self { start, end })
+    }
+
+    /// Length of one pass through the loop
+    pub fn length(&self) -> Duration {
+        self.end - self.start
+    }
+
+    /// Check if a position lies inside the loop
+    pub fn contains(&self, position: Duration) -> bool {
+        position >= self.start && position < self.end
+    }
+
+    /// Map a position that ran past the end back into the loop
+    pub fn wrap(&self, position: Duration) -> Duration {
+        if position < self.end {
+            return position;
+        }
+        let into_loop = (position - self.start).as_nanos() % self.length().as_nanos();
+        self.start + Duration::from_nanos(into_loop as u64)
+    }
+
+    /// Shift both ends back by `offset` (file position -> track position)
+    pub fn relative_to(&self, offset: Duration) -> Self {
+        Self {
+            start: self.start.saturating_sub(offset),
+            end: self.end.saturating_sub(offset),
         }
     }
 }
@@ -183,6 +508,79 @@ pub struct PlayerStatus {
     pub volume: f32,
     pub audio_format: Option<AudioFormat>,
     pub output_device: Option<String>,
+    #[serde(default)]
+    pub clipped_samples: u64,
+    #[serde(default)]
+    pub limiter_enabled: bool,
+    /// Whether narrower output streams are dithered
+    #[serde(default)]
+    pub dither: bool,
+    /// Whether exclusive device access is held
+    #[serde(default)]
+    pub exclusive_mode: bool,
+    /// Effective device buffer size in frames (0 = device default)
+    #[serde(default)]
+    pub output_buffer_frames: u32,
+    #[serde(default)]
+    pub output_latency_ms: f32,
+    #[serde(default)]
+    pub ring_buffer_ms: u64,
+    /// Ring buffer fill level (0.0 to 1.0)
+    #[serde(default)]
+    pub buffer_fill: f32,
+    /// Sample rate of the output stream
+    #[serde(default)]
+    pub output_sample_rate: u32,
+    #[serde(default)]
+    pub output_bit_depth: u16,
+    #[serde(default)]
+    pub output_channels: u16,
+    /// Mono/swap and balance applied to the output
+    #[serde(default)]
+    pub output_mode: OutputChannels,
+    /// Headphone crossfeed on stereo output
+    #[serde(default)]
+    pub crossfeed: CrossfeedSettings,
+    /// Whether the source is resampled to the output rate
+    #[serde(default)]
+    pub resampling: bool,
+    /// Active A-B loop, relative to the track
+    #[serde(default)]
+    pub loop_region: Option<LoopRegion>,
+    /// Tracks started this session as "Artist - Title", most recent first
+    #[serde(default)]
+    pub recent_history: Vec<(String, SystemTime)>,
+    /// Output levels over the last 100 ms, while playing
+    #[serde(default)]
+    pub levels: Option<ChannelLevels>,
+    /// False when the current file can't be seeked
+    #[serde(default = "default_seekable")]
+    pub seekable: bool,
+    /// Set when the output device can't reach the source's sample rate or bit depth
+    #[serde(default)]
+    pub degradation_warning: Option<String>,
+    /// Buffering and station title while an HTTP(S) stream plays
+    #[serde(default)]
+    pub stream: Option<StreamStatus>,
+    /// Whole factor the source is upsampled by to reach the output rate
+    #[serde(default)]
+    pub oversampling: Option<u32>,
+    /// Playing, but silent until the pre-buffer has filled
+    #[serde(default)]
+    pub buffering: bool,
+}
+
+fn default_seekable() -> bool {
+    true
+}
+
+/// Peak and RMS levels of the left and right channels, as linear amplitudes (1.0 = full scale)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChannelLevels {
+    pub peak_left: f32,
+    pub peak_right: f32,
+    pub rms_left: f32,
+    pub rms_right: f32,
 }
 
 impl PlayerStatus {
@@ -194,6 +592,28 @@ impl PlayerStatus {
             volume: 1.0,
             audio_format: None,
             output_device: None,
+            clipped_samples: 0,
+            limiter_enabled: false,
+            dither: false,
+            exclusive_mode: false,
+            output_buffer_frames: 0,
+            output_latency_ms: 0.0,
+            ring_buffer_ms: 0,
+            buffer_fill: 0.0,
+            output_sample_rate: 0,
+            output_bit_depth: 0,
+            output_channels: 0,
+            output_mode: OutputChannels::default(),
+            crossfeed: CrossfeedSettings::default(),
+            resampling: false,
+            loop_region: None,
+            recent_history: Vec::new(),
+            levels: None,
+            seekable: true,
+            degradation_warning: None,
+            stream: None,
+            oversampling: None,
+            buffering: false,
         }
     }
 
@@ -209,8 +629,7 @@ impl PlayerStatus {
             current_track: Some(track),
             position,
             volume,
-            audio_format: None,
-            output_device: None,
+            ..Self::new()
         }
     }
 
@@ -221,12 +640,33 @@ impl PlayerStatus {
             current_track: Some(track),
             position,
             volume,
-            audio_format: None,
-            output_device: None,
+            ..Self::new()
         }
     }
 
     /// Check if currently playing
+    /// Whether source samples reach the device unaltered: same rate and channels, no volume
+    /// scaling, and an output word at least as wide as the source
+    pub fn is_bit_perfect(&self) -> bool {
+        match &self.audio_format {
+            Some(format) => {
+                !self.resampling
+                    && format.sample_rate == self.output_sample_rate
+                    && format.channels == self.output_channels
+                    && format.bit_depth <= self.output_bit_depth
+                    && self.volume == 1.0
+                    && self.output_mode.is_neutral()
+                    && (!self.crossfeed.enabled || format.channels != 2)
+            }
+            None => false,
+        }
+    }
+
+    /// Serialize the status snapshot as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
     pub fn is_playing(&self) -> bool {
         matches!(self.state, PlaybackState::Playing)
     }
@@ -306,6 +746,60 @@ impl std::fmt::Display for PlaybackState {
     }
 }
 
+/// Tracks started during this session, with the time each one started
+///
+/// Unlike the persistent play history, every track counts as soon as it
+/// starts, and nothing is kept once the player exits.
+#[derive(Debug, Clone, Default)]
+pub struct SessionHistory {
+    entries: VecDeque<(TrackInfo, SystemTime)>,
+}
+
+impl SessionHistory {
+    /// Oldest entries are dropped beyond this
+    pub const MAX_ENTRIES: usize = 100;
+
+    /// Entries shown by `history` and included in the status
+    pub const DEFAULT_LIMIT: usize = 20;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that a track started now
+    pub fn push(&mut self, track: TrackInfo) {
+        self.push_at(track, SystemTime::now());
+    }
+
+    /// Record that a track started at `started_at`
+    pub fn push_at(&mut self, track: TrackInfo, started_at: SystemTime) {
+        self.entries.push_back((track, started_at));
+        while self.entries.len() > Self::MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+    }
+
+    /// The last `limit` entries, most recent first
+    pub fn recent(&self, limit: usize) -> impl Iterator<Item = &(TrackInfo, SystemTime)> {
+        self.entries.iter().rev().take(limit)
+    }
+
+    /// The last `limit` entries as "Artist - Title", most recent first
+    pub fn summary(&self, limit: usize) -> Vec<(String, SystemTime)> {
+        self.recent(limit)
+            .map(|(track, started_at)| (format!("{} - {}", track.artist_name(), track.display_name()), *started_at))
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -327,6 +821,62 @@ mod tests {
         assert_eq!(track.file_size, file_size);
     }
 
+    #[test]
+    fn test_track_info_display() {
+        let mut metadata = AudioMetadata::with_title_artist("Test Song".to_string(), "Test Artist".to_string());
+        metadata.album = Some("Test Album".to_string());
+        metadata.track_number = Some(3);
+        metadata.total_tracks = Some(12);
+        metadata.year = Some(2023);
+        metadata.genre = Some("Jazz".to_string());
+        metadata.lyrics = Some("First line\nSecond line".to_string());
+        let mut track = TrackInfo::new(PathBuf::from("/music/song.flac"), metadata, Duration::from_secs(200), 1024);
+        track.codec = Some(AudioCodec::Flac);
+        track.sample_rate = Some(96_000);
+        track.bit_depth = Some(24);
+
+        let shown = track.to_string();
+        assert_eq!(shown, track.display_full_metadata());
+        let lines: Vec<&str> = shown.lines().collect();
+        assert_eq!(&lines[..4], ["Title: Test Song", "Artist: Test Artist", "Album: Test Album", "Track: 3/12"]);
+        assert!(lines.contains(&"Genre: Jazz"));
+        assert!(lines.contains(&"Quality: Hi-Res (24/96 FLAC)"));
+        assert_eq!(&lines[lines.len() - 3..], ["Lyrics:", "  First line", "  Second line"]);
+        assert!(!shown.contains("Composer") && !shown.contains("Rating"));
+    }
+
+    #[test]
+    fn test_track_info_quality_label() {
+        let quality = |codec, sample_rate, bit_depth| {
+            let mut track = TrackInfo::new(PathBuf::from("/music/song"), AudioMetadata::new(), Duration::ZERO, 0);
+            (track.codec, track.sample_rate, track.bit_depth) = (codec, sample_rate, bit_depth);
+            track.quality_label()
+        };
+        assert_eq!(quality(Some(AudioCodec::Flac), Some(44_100), Some(16)), "CD Quality");
+        assert_eq!(quality(Some(AudioCodec::Wav), Some(48_000), None), "CD Quality");
+        assert_eq!(quality(Some(AudioCodec::Flac), Some(48_000), Some(24)), "Hi-Res");
+        assert_eq!(quality(None, Some(88_200), Some(16)), "Hi-Res");
+        assert_eq!(quality(Some(AudioCodec::Flac), Some(192_000), Some(24)), "Studio Master");
+        assert_eq!(quality(Some(AudioCodec::Mp3), Some(44_100), None), "Lossy");
+        assert_eq!(quality(None, None, None), "Unknown");
+    }
+
+    #[test]
+    fn test_audio_format_degradation_warning() {
+        let source = AudioFormat::new(192_000, 24, 2, AudioCodec::Flac);
+        assert!(source.quality_rank() > AudioFormat::new(48_000, 16, 2, AudioCodec::Flac).quality_rank());
+        assert_eq!(
+            source.degradation_warning(48_000, 16).as_deref(),
+            Some("Warning: device downsampling 192kHz→48kHz, truncating 24-bit→16-bit")
+        );
+        assert_eq!(
+            source.degradation_warning(44_100, 32).as_deref(),
+            Some("Warning: device downsampling 192kHz→44.1kHz")
+        );
+        assert_eq!(source.degradation_warning(384_000, 32), None);
+        assert_eq!(source.degradation_warning(192_000, 24), None);
+    }
+
     #[test]
     fn test_track_info_display_name() {
         let metadata = AudioMetadata::with_title_artist("Test Song".to_string(), "Test Artist".to_string());
@@ -376,6 +926,43 @@ mod tests {
         assert_eq!(track.album_name(), "Unknown Album");
     }
 
+    #[test]
+    fn test_track_info_rating_label() {
+        let path = PathBuf::from("/test/path/song.flac");
+        let mut track = TrackInfo::new(path, AudioMetadata::new(), Duration::from_secs(180), 1024);
+        assert_eq!(track.rating_label(), "");
+
+        track.rating = Some(4);
+        assert_eq!(track.rating_label(), "★★★★☆");
+
+        track.favorite = true;
+        assert_eq!(track.rating_label(), "★★★★☆ ♥");
+
+        track.rating = None;
+        assert_eq!(track.rating_label(), "♥");
+        // Unrated, non-favorite tracks keep their serialized form unchanged
+        assert!(!serde_json::to_string(&TrackInfo::new(PathBuf::from("a.flac"), AudioMetadata::new(), Duration::ZERO, 0))
+            .unwrap().contains("favorite"));
+    }
+
+    #[test]
+    fn test_track_info_format_label() {
+        let mut track = TrackInfo::new(PathBuf::from("/test/song.flac"), AudioMetadata::new(), Duration::ZERO, 0);
+        assert_eq!(track.format_label(), None);
+
+        track.codec = Some(AudioCodec::Flac);
+        assert_eq!(track.format_label().as_deref(), Some("FLAC"));
+
+        track.sample_rate = Some(96000);
+        track.bit_depth = Some(24);
+        assert_eq!(track.format_label().as_deref(), Some("24/96 FLAC"));
+
+        track.sample_rate = Some(44100);
+        track.bit_depth = None;
+        track.codec = Some(AudioCodec::Mp3);
+        assert_eq!(track.format_label().as_deref(), Some("44.1kHz MP3"));
+    }
+
     #[test]
     fn test_audio_metadata_creation() {
         let metadata = AudioMetadata::new();
@@ -464,6 +1051,37 @@ mod tests {
         assert_eq!(AudioCodec::OggVorbis.name(), "OGG Vorbis");
         assert!(!AudioCodec::OggVorbis.is_lossless());
         assert_eq!(AudioCodec::OggVorbis.extensions(), &["ogg", "oga"]);
+
+        assert_eq!(AudioCodec::Aac.name(), "AAC");
+        assert!(!AudioCodec::Aac.is_lossless());
+
+        assert_eq!(AudioCodec::WavPack.name(), "WavPack");
+        assert!(AudioCodec::WavPack.is_lossless());
+        assert_eq!(AudioCodec::WavPack.extensions(), &["wv"]);
+    }
+
+    #[test]
+    fn test_bit_perfect_detection() {
+        let mut status = PlayerStatus::new();
+        assert!(!status.is_bit_perfect());
+
+        status.audio_format = Some(AudioFormat::new(192000, 24, 2, AudioCodec::Flac));
+        status.output_sample_rate = 192000;
+        status.output_bit_depth = 32;
+        status.output_channels = 2;
+        assert!(status.is_bit_perfect());
+
+        status.volume = 0.5;
+        assert!(!status.is_bit_perfect());
+        status.volume = 1.0;
+
+        status.output_bit_depth = 16;
+        assert!(!status.is_bit_perfect());
+        status.output_bit_depth = 24;
+
+        status.output_sample_rate = 48000;
+        status.resampling = true;
+        assert!(!status.is_bit_perfect());
     }
 
     #[test]
@@ -603,6 +1221,25 @@ mod tests {
         assert_eq!(empty_buffer.duration(), Duration::from_secs(0));
     }
 
+    #[test]
+    fn test_loop_region() {
+        assert!(LoopRegion::new(Duration::from_secs(90), Duration::from_secs(60)).is_none());
+        assert!(LoopRegion::new(Duration::from_secs(60), Duration::from_secs(60)).is_none());
+
+        let region = LoopRegion::new(Duration::from_secs(60), Duration::from_secs(90)).unwrap();
+        assert!(region.contains(Duration::from_secs(60)));
+        assert!(!region.contains(Duration::from_secs(90)));
+        assert_eq!(region.wrap(Duration::from_secs(75)), Duration::from_secs(75));
+        assert_eq!(region.wrap(Duration::from_secs(95)), Duration::from_secs(65));
+        assert_eq!(region.wrap(Duration::from_secs(125)), Duration::from_secs(65));
+        assert_eq!(region.relative_to(Duration::from_secs(30)).start, Duration::from_secs(30));
+
+        let mut buffer = AudioBuffer::new(2, 48000, 480);
+        buffer.truncate(100);
+        assert_eq!(buffer.frames(), 100);
+        assert_eq!(buffer.total_samples(), 200);
+    }
+
     #[test]
     fn test_serialization_deserialization() {
         // Test TrackInfo serialization
@@ -632,6 +1269,66 @@ mod tests {
         assert_eq!(status.position, deserialized.position);
         assert_eq!(status.volume, deserialized.volume);
     }
+
+    #[test]
+    fn test_extended_tags_in_status_json() {
+        let mut metadata = AudioMetadata::with_title_artist("Song".to_string(), "Artist".to_string());
+        metadata.composer = Some("Composer".to_string());
+        metadata.disc_number = Some(2);
+        metadata.lyrics = Some("La la".to_string());
+        metadata.compilation = true;
+        let track = TrackInfo::new(PathBuf::from("/test/song.mp3"), metadata, Duration::from_secs(180), 1024);
+
+        let json = PlayerStatus::playing(track, Duration::from_secs(1), 1.0).to_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let tags = &value["current_track"]["metadata"];
+        assert_eq!(tags["composer"], "Composer");
+        assert_eq!(tags["disc_number"], 2);
+        assert_eq!(tags["lyrics"], "La la");
+        assert_eq!(tags["compilation"], true);
+        assert!(tags["comment"].is_null());
+
+        // Metadata saved before the extended tags existed still loads
+        let legacy = r#"{"title":"Old","artist":null,"album":null,"track_number":1,"year":null,"genre":null}"#;
+        let metadata: AudioMetadata = serde_json::from_str(legacy).unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Old"));
+        assert!(!metadata.compilation);
+    }
+
+    #[test]
+    fn test_session_history_most_recent_first() {
+        let mut history = SessionHistory::new();
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        for (i, title) in ["First", "Second", "Third"].iter().enumerate() {
+            let metadata = AudioMetadata::with_title_artist(title.to_string(), "Artist".to_string());
+            let track = TrackInfo::new(PathBuf::from(format!("/test/{}.flac", i)), metadata, Duration::from_secs(180), 1024);
+            history.push_at(track, start + Duration::from_secs(180 * i as u64));
+        }
+
+        assert_eq!(history.recent(usize::MAX).count(), 3);
+        let titles: Vec<String> = history.recent(10).map(|(track, _)| track.display_name()).collect();
+        assert_eq!(titles, vec!["Third", "Second", "First"]);
+        assert_eq!(history.summary(2), vec![
+            ("Artist - Third".to_string(), start + Duration::from_secs(360)),
+            ("Artist - Second".to_string(), start + Duration::from_secs(180)),
+        ]);
+
+        history.clear();
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn test_session_history_is_capped() {
+        let mut history = SessionHistory::new();
+        for i in 0..SessionHistory::MAX_ENTRIES + 5 {
+            let track = TrackInfo::new(PathBuf::from(format!("/test/{}.flac", i)), AudioMetadata::new(), Duration::ZERO, 0);
+            history.push(track);
+        }
+
+        assert_eq!(history.recent(usize::MAX).count(), SessionHistory::MAX_ENTRIES);
+        let (newest, _) = history.recent(1).next().unwrap();
+        assert_eq!(newest.display_name(), format!("{}", SessionHistory::MAX_ENTRIES + 4));
+    }
 }
 
 /// Audio buffer for sample data
@@ -679,6 +1376,23 @@ impl AudioBuffer {
         self.samples.is_empty()
     }
 
+    /// Average of the channels, one sample per frame
+    pub fn mono(&self) -> Vec<f32> {
+        let channels = self.channels.max(1) as usize;
+        self.samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect()
+    }
+
+    /// Keep only the first `frames` frames
+    pub fn truncate(&mut self, frames: usize) {
+        if frames < self.frames {
+            self.samples.truncate(frames * self.channels as usize);
+            self.frames = frames;
+        }
+    }
+
     /// Get duration of this buffer
     pub fn duration(&self) -> Duration {
         if self.sample_rate > 0 {
@@ -0,0 +1,259 @@
+/*!
+CUE sheet sidecar parsing
+
+Ripped albums are often a single `album.flac` next to an `album.cue` that
+describes where each track starts. `CueSheet` reads the `FILE`, `TRACK`,
+`INDEX 01`, `PERFORMER` and `TITLE` directives and splits the album file into
+virtual `TrackInfo` entries with start and end offsets.
+*/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::error::PlaylistError;
+use crate::models::TrackInfo;
+
+/// CUE time frames per second (`MM:SS:FF`)
+const FRAMES_PER_SECOND: u64 = 75;
+
+/// One `TRACK` entry of a CUE sheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    /// `INDEX 01` position within the file
+    pub start: Duration,
+    /// `FILE` the track belongs to
+    pub file: Option<String>,
+}
+
+/// Parsed CUE sheet
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueSheet {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub files: Vec<String>,
+    pub tracks: Vec<CueTrack>,
+}
+
+impl CueSheet {
+    /// Parse CUE sheet text
+    pub fn parse(content: &str) -> Result<Self, PlaylistError> {
+        let mut sheet = CueSheet::default();
+        let mut current_file: Option<String> = None;
+
+        for (line_number, line) in content.lines().enumerate() {
+            let line = line.trim().trim_start_matches('\u{feff}');
+            let (keyword, rest) = match line.split_once(char::is_whitespace) {
+                Some((keyword, rest)) => (keyword, rest.trim()),
+                None => (line, ""),
+            };
+            let invalid = |reason: &str| {
+                PlaylistError::InvalidFormat(format!("CUE line {}: {}", line_number + 1, reason))
+            };
+
+            match keyword.to_ascii_uppercase().as_str() {
+                "FILE" => {
+                    let name = Self::file_name(rest);
+                    sheet.files.push(name.clone());
+                    current_file = Some(name);
+                }
+                "TRACK" => {
+                    let number = rest.split_whitespace().next()
+                        .and_then(|number| number.parse().ok())
+                        .ok_or_else(|| invalid("TRACK without a number"))?;
+                    sheet.tracks.push(CueTrack {
+                        number,
+                        title: None,
+                        performer: None,
+                        start: Duration::ZERO,
+                        file: current_file.clone(),
+                    });
+                }
+                "TITLE" | "PERFORMER" => {
+                    let value = Some(Self::unquote(rest));
+                    let is_title = keyword.eq_ignore_ascii_case("TITLE");
+                    // Before the first TRACK these describe the whole album
+                    match sheet.tracks.last_mut() {
+                        Some(track) if is_title => track.title = value,
+                        Some(track) => track.performer = value,
+                        None if is_title => sheet.title = value,
+                        None => sheet.performer = value,
+                    }
+                }
+                "INDEX" => {
+                    let mut parts = rest.split_whitespace();
+                    let index = parts.next().and_then(|index| index.parse::<u32>().ok());
+                    if index != Some(1) {
+                        continue;
+                    }
+                    let start = parts.next()
+                        .and_then(Self::parse_time)
+                        .ok_or_else(|| invalid("INDEX 01 needs an MM:SS:FF time"))?;
+                    let track = sheet.tracks.last_mut()
+                        .ok_or_else(|| invalid("INDEX before any TRACK"))?;
+                    track.start = start;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(sheet)
+    }
+
+    /// Read and parse a CUE file; non-UTF-8 sheets are read as Latin-1
+    pub fn load(path: &Path) -> Result<Self, PlaylistError> {
+        let bytes = fs::read(path)?;
+        let content = match String::from_utf8(bytes) {
+            Ok(content) => content,
+            Err(e) => e.into_bytes().iter().map(|&byte| byte as char).collect(),
+        };
+        Self::parse(&content)
+    }
+
+    /// Find the sidecar for an audio file: `album.cue` or `album.flac.cue`
+    pub fn find_for(audio_path: &Path) -> Option<PathBuf> {
+        let mut with_suffix = audio_path.as_os_str().to_os_string();
+        with_suffix.push(".cue");
+        [audio_path.with_extension("cue"), PathBuf::from(with_suffix)]
+            .into_iter()
+            .find(|candidate| candidate.is_file())
+    }
+
+    /// Parse an `MM:SS:FF` CUE time (75 frames per second)
+    pub fn parse_time(value: &str) -> Option<Duration> {
+        let mut parts = value.split(':').map(|part| part.parse::<u64>().ok());
+        let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+        if parts.next().is_some() || seconds >= 60 || frames >= FRAMES_PER_SECOND {
+            return None;
+        }
+        let total_frames = (minutes * 60 + seconds) * FRAMES_PER_SECOND + frames;
+        Some(Duration::from_nanos(total_frames * 1_000_000_000 / FRAMES_PER_SECOND))
+    }
+
+    /// Split a whole-file track into one entry per CUE track that refers to it
+    pub fn split(&self, album: &TrackInfo) -> Vec<TrackInfo> {
+        let file_name = album.path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+        // Rips are often renamed after the sheet was written; trust a single-file sheet
+        let tracks: Vec<&CueTrack> = self.tracks.iter()
+            .filter(|track| self.files.len() <= 1 || track.file.as_deref()
+                .is_some_and(|file| file.eq_ignore_ascii_case(file_name)))
+            .collect();
+
+        tracks.iter().enumerate().map(|(i, cue_track)| {
+            let end = tracks.get(i + 1).map(|next| next.start);
+            let mut track = album.clone();
+            track.start_offset = cue_track.start;
+            track.end_offset = end;
+            track.duration = end.unwrap_or(album.duration).saturating_sub(cue_track.start);
+            track.metadata.title = cue_track.title.clone().or_else(|| Some(format!("Track {:02}", cue_track.number)));
+            if let Some(performer) = cue_track.performer.as_ref().or(self.performer.as_ref()) {
+                track.metadata.artist = Some(performer.clone());
+            }
+            if let Some(album_title) = &self.title {
+                track.metadata.album = Some(album_title.clone());
+            }
+            track.metadata.track_number = Some(cue_track.number);
+            track.metadata.total_tracks = Some(tracks.len() as u32);
+            track
+        }).collect()
+    }
+
+    /// `FILE "name.flac" WAVE` -> `name.flac`
+    fn file_name(rest: &str) -> String {
+        if rest.starts_with('"') {
+            return Self::unquote(rest);
+        }
+        // Unquoted names end before the file type
+        match rest.rsplit_once(char::is_whitespace) {
+            Some((name, _file_type)) => name.trim().to_string(),
+            None => rest.to_string(),
+        }
+    }
+
+    /// Strip surrounding quotes; text after the closing quote is ignored
+    fn unquote(value: &str) -> String {
+        match value.strip_prefix('"') {
+            Some(inner) => inner.split('"').next().unwrap_or("").to_string(),
+            None => value.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AudioMetadata;
+
+    const SHEET: &str = "REM GENRE Electronic\r
+PERFORMER \"Test Artist\"\r
+TITLE \"Test Album\"\r
+FILE \"album.flac\" WAVE\r
+  TRACK 01 AUDIO\r
+    TITLE \"Opening\"\r
+    INDEX 01 00:00:00\r
+  TRACK 02 AUDIO\r
+    TITLE \"Middle\"\r
+    PERFORMER \"Guest Artist\"\r
+    INDEX 00 03:58:50\r
+    INDEX 01 04:00:37\r
+  TRACK 03 AUDIO\r
+    TITLE \"Closing\"\r
+    INDEX 01 09:12:00\r
+";
+
+    #[test]
+    fn test_parse_cue_sheet() {
+        let sheet = CueSheet::parse(SHEET).unwrap();
+
+        assert_eq!(sheet.title.as_deref(), Some("Test Album"));
+        assert_eq!(sheet.performer.as_deref(), Some("Test Artist"));
+        assert_eq!(sheet.files, vec!["album.flac".to_string()]);
+        assert_eq!(sheet.tracks.len(), 3);
+
+        let titles: Vec<_> = sheet.tracks.iter().map(|track| track.title.as_deref().unwrap()).collect();
+        assert_eq!(titles, vec!["Opening", "Middle", "Closing"]);
+        assert_eq!(sheet.tracks[1].performer.as_deref(), Some("Guest Artist"));
+        assert_eq!(sheet.tracks[0].start, Duration::ZERO);
+        // INDEX 01, not the INDEX 00 pregap
+        assert_eq!(sheet.tracks[1].start, Duration::from_millis(240_493) + Duration::from_nanos(333_333));
+        assert_eq!(sheet.tracks[2].start, Duration::from_secs(552));
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(CueSheet::parse_time("01:02:00"), Some(Duration::from_secs(62)));
+        assert_eq!(CueSheet::parse_time("00:00:75"), None);
+        assert_eq!(CueSheet::parse_time("00:60:00"), None);
+        assert_eq!(CueSheet::parse_time("1:2"), None);
+        assert!(CueSheet::parse("TRACK 01 AUDIO\nINDEX 01 nonsense").is_err());
+    }
+
+    #[test]
+    fn test_split_into_virtual_tracks() {
+        let sheet = CueSheet::parse(SHEET).unwrap();
+        let album = TrackInfo::new(
+            PathBuf::from("/music/album.flac"),
+            AudioMetadata::new(),
+            Duration::from_secs(600),
+            1024,
+        );
+
+        let tracks = sheet.split(&album);
+        assert_eq!(tracks.len(), 3);
+        assert_eq!(tracks[0].display_name(), "Opening");
+        assert_eq!(tracks[0].artist_name(), "Test Artist");
+        assert_eq!(tracks[1].artist_name(), "Guest Artist");
+        assert_eq!(tracks[2].metadata.album.as_deref(), Some("Test Album"));
+        assert_eq!(tracks[2].metadata.track_number, Some(3));
+        assert_eq!(tracks[2].metadata.total_tracks, Some(3));
+
+        assert_eq!(tracks[0].start_offset, Duration::ZERO);
+        assert_eq!(tracks[0].end_offset, Some(tracks[1].start_offset));
+        assert_eq!(tracks[2].start_offset, Duration::from_secs(552));
+        assert_eq!(tracks[2].end_offset, None);
+        assert_eq!(tracks[2].duration, Duration::from_secs(48));
+        assert!(tracks.iter().all(|track| track.path == album.path));
+    }
+}
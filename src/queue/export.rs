@@ -0,0 +1,203 @@
+/*!
+Exporting the queue's track metadata for other tools
+
+`export json <file>` writes an array with one object per queue entry and
+`export csv <file>` a header row followed by one row per entry. Both carry
+the same columns; tags or stream details that are unknown are left empty
+(`null` in JSON).
+*/
+
+use std::io;
+use std::path::Path;
+use serde::Serialize;
+use crate::models::TrackInfo;
+
+/// Output format of `export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(format!("unknown export format '{}' (expected json or csv)", value)),
+        }
+    }
+}
+
+/// One exported queue entry; field order is the CSV column order
+#[derive(Debug, Serialize)]
+struct ExportRow {
+    /// Position in the queue, starting at 1 as in `queue list`
+    index: usize,
+    path: String,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    track_number: Option<u32>,
+    year: Option<u32>,
+    genre: Option<String>,
+    duration_secs: f64,
+    sample_rate: Option<u32>,
+    bit_depth: Option<u16>,
+    channels: Option<u16>,
+    codec: Option<&'static str>,
+}
+
+const CSV_HEADER: [&str; 13] = [
+    "index", "path", "title", "artist", "album", "track_number", "year", "genre",
+    "duration_secs", "sample_rate", "bit_depth", "channels", "codec",
+];
+
+impl ExportRow {
+    fn new(index: usize, track: &TrackInfo) -> Self {
+        let metadata = &track.metadata;
+        Self {
+            index: index + 1,
+            path: track.path.display().to_string(),
+            title: metadata.title.clone(),
+            artist: metadata.artist.clone(),
+            album: metadata.album.clone(),
+            track_number: metadata.track_number,
+            year: metadata.year,
+            genre: metadata.genre.clone(),
+            duration_secs: (track.duration.as_secs_f64() * 1000.0).round() / 1000.0,
+            sample_rate: track.sample_rate,
+            bit_depth: track.bit_depth,
+            channels: track.channels,
+            codec: track.codec.as_ref().map(|codec| codec.name()),
+        }
+    }
+
+    fn csv_fields(&self) -> [String; 13] {
+        fn optional<T: ToString>(value: &Option<T>) -> String {
+            value.as_ref().map(ToString::to_string).unwrap_or_default()
+        }
+        [
+            self.index.to_string(),
+            self.path.clone(),
+            optional(&self.title),
+            optional(&self.artist),
+            optional(&self.album),
+            optional(&self.track_number),
+            optional(&self.year),
+            optional(&self.genre),
+            format!("{:.3}", self.duration_secs),
+            optional(&self.sample_rate),
+            optional(&self.bit_depth),
+            optional(&self.channels),
+            optional(&self.codec),
+        ]
+    }
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Render the metadata of `tracks`, in queue order
+pub fn export_tracks<'a>(tracks: impl IntoIterator<Item = &'a TrackInfo>, format: ExportFormat) -> io::Result<String> {
+    let rows: Vec<ExportRow> = tracks.into_iter().enumerate().map(|(index, track)| ExportRow::new(index, track)).collect();
+    match format {
+        ExportFormat::Json => Ok(serde_json::to_string_pretty(&rows).map_err(io::Error::from)? + "\n"),
+        ExportFormat::Csv => {
+            let mut csv = CSV_HEADER.join(",") + "\n";
+            for row in &rows {
+                let fields: Vec<String> = row.csv_fields().iter().map(|field| csv_field(field)).collect();
+                csv.push_str(&fields.join(","));
+                csv.push('\n');
+            }
+            Ok(csv)
+        }
+    }
+}
+
+/// Write the metadata of `tracks` to `path`
+pub fn write_export<'a>(tracks: impl IntoIterator<Item = &'a TrackInfo>, format: ExportFormat, path: &Path) -> io::Result<()> {
+    std::fs::write(path, export_tracks(tracks, format)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queue::{QueueManager, QueueManagerImpl};
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Split a CSV line, honouring quoted fields
+    fn parse_csv_line(line: &str) -> Vec<String> {
+        let mut fields = vec![String::new()];
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    fields.last_mut().unwrap().push('"');
+                }
+                '"' => quoted = !quoted,
+                ',' if !quoted => fields.push(String::new()),
+                c => fields.last_mut().unwrap().push(c),
+            }
+        }
+        fields
+    }
+
+    fn queue_with_stub_tracks(dir: &TempDir) -> (QueueManagerImpl, Vec<String>) {
+        let mut queue = QueueManagerImpl::with_playlist_directory(dir.path().join("playlists")).unwrap();
+        let mut paths = Vec::new();
+        for name in ["one.flac", "two, with comma.mp3", "three.wav"] {
+            let path = dir.path().join(name);
+            fs::write(&path, b"dummy audio data").unwrap();
+            queue.add_file(&path).unwrap();
+            paths.push(path.display().to_string());
+        }
+        (queue, paths)
+    }
+
+    #[test]
+    fn test_csv_export_has_header_and_a_row_per_track() {
+        let dir = TempDir::new().unwrap();
+        let (queue, paths) = queue_with_stub_tracks(&dir);
+        let output = dir.path().join("queue.csv");
+        write_export(queue.list(), ExportFormat::Csv, &output).unwrap();
+
+        let csv = fs::read_to_string(&output).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(parse_csv_line(lines.next().unwrap()), CSV_HEADER);
+        let rows: Vec<Vec<String>> = lines.map(parse_csv_line).collect();
+        assert_eq!(rows.len(), 3);
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(row.len(), CSV_HEADER.len());
+            assert_eq!(row[0], (i + 1).to_string());
+            assert_eq!(row[1], paths[i]);
+        }
+        assert_eq!(rows[1][2], "two, with comma");
+    }
+
+    #[test]
+    fn test_json_export_lists_tracks_in_queue_order() {
+        let dir = TempDir::new().unwrap();
+        let (queue, paths) = queue_with_stub_tracks(&dir);
+
+        let json = export_tracks(queue.list(), ExportFormat::Json).unwrap();
+        let rows: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        let exported: Vec<&str> = rows.iter().map(|row| row["path"].as_str().unwrap()).collect();
+        assert_eq!(exported, paths);
+        assert_eq!(rows[0]["codec"], "FLAC");
+        assert!(rows[0]["sample_rate"].is_null());
+        assert_eq!("CSV".parse::<ExportFormat>(), Ok(ExportFormat::Csv));
+        assert!("xml".parse::<ExportFormat>().is_err());
+    }
+}
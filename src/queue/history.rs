@@ -0,0 +1,445 @@
+/*!
+Playback history and session resume
+
+Every track that plays for at least `MIN_PLAY_TIME` is appended to
+`history.jsonl` (one JSON object per line) in the config directory, capped at
+a configured number of entries. While a track plays, its position is saved to
+`session.json` so that a player killed mid-track can offer to resume it. Both
+files are written by a background thread; unreadable history lines and a
+corrupt session file are skipped with a warning.
+*/
+
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use crate::config::store::replace_file;
+use crate::models::TrackInfo;
+
+/// History log file name inside the config directory
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// Last playing track and position, removed on a clean exit
+pub const SESSION_FILE: &str = "session.json";
+
+/// How long a track has to play before it counts as played
+pub const MIN_PLAY_TIME: Duration = Duration::from_secs(30);
+
+/// One play of a track
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub path: PathBuf,
+    /// Where the track starts within its file (CUE sheet tracks)
+    #[serde(default)]
+    pub start_offset: Duration,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub artist: Option<String>,
+    pub played_at: DateTime<Utc>,
+    /// Time the track was actually playing
+    pub played_for: Duration,
+}
+
+impl HistoryEntry {
+    /// "Artist - Title", falling back to the file name
+    pub fn display_name(&self) -> String {
+        let title = self.title.clone().unwrap_or_else(|| {
+            self.path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("Unknown")
+                .to_string()
+        });
+        match &self.artist {
+            Some(artist) => format!("{} - {}", artist, title),
+            None => title,
+        }
+    }
+}
+
+/// Track and position that was playing when the session was last saved
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionState {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub start_offset: Duration,
+    /// Playback position within the file
+    pub position: Duration,
+    pub saved_at: DateTime<Utc>,
+}
+
+impl SessionState {
+    pub fn new(track: &TrackInfo, position: Duration) -> Self {
+        Self {
+            path: track.path.clone(),
+            start_offset: track.start_offset,
+            position,
+            saved_at: Utc::now(),
+        }
+    }
+
+    /// Position relative to the start of the track
+    pub fn track_position(&self) -> Duration {
+        self.position.saturating_sub(self.start_offset)
+    }
+}
+
+/// Work for the background writer
+enum HistoryWrite {
+    Append(HistoryEntry),
+    Session(Option<SessionState>),
+}
+
+/// Playback history with a background writer thread
+pub struct PlayHistory {
+    dir: PathBuf,
+    entries: VecDeque<HistoryEntry>,
+    max_entries: usize,
+    session_saved: bool,
+    sender: Option<mpsc::Sender<HistoryWrite>>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl PlayHistory {
+    /// Load the history from `dir` and start the writer; `max_entries` of 0 turns history off
+    pub fn open(dir: &Path, max_entries: usize) -> Self {
+        let entries = match read_history(&dir.join(HISTORY_FILE), max_entries) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Ignoring unreadable play history: {}", e);
+                VecDeque::new()
+            }
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        let writer_dir = dir.to_path_buf();
+        let writer_entries = entries.clone();
+        let handle = thread::Builder::new()
+            .name("play-history".to_string())
+            .spawn(move || write_history(&writer_dir, writer_entries, max_entries, receiver));
+
+        let (sender, handle) = match handle {
+            Ok(handle) => (Some(sender), Some(handle)),
+            Err(e) => {
+                warn!("Play history will not be saved: {}", e);
+                (None, None)
+            }
+        };
+
+        Self {
+            dir: dir.to_path_buf(),
+            entries,
+            max_entries,
+            session_saved: dir.join(SESSION_FILE).exists(),
+            sender,
+            handle,
+        }
+    }
+
+    /// Add a play; written in the background
+    pub fn record(&mut self, entry: HistoryEntry) {
+        if self.max_entries == 0 {
+            return;
+        }
+        self.entries.push_back(entry.clone());
+        while self.entries.len() > self.max_entries {
+            self.entries.pop_front();
+        }
+        self.send(HistoryWrite::Append(entry));
+    }
+
+    /// The last `count` plays, most recent first
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.iter().rev().take(count)
+    }
+
+    /// Most recent play
+    pub fn last(&self) -> Option<&HistoryEntry> {
+        self.entries.back()
+    }
+
+    /// Save (or with `None` remove) the resumable session
+    pub fn save_session(&mut self, session: Option<SessionState>) {
+        if session.is_none() && !self.session_saved {
+            return;
+        }
+        self.session_saved = session.is_some();
+        self.send(HistoryWrite::Session(session));
+    }
+
+    /// Session left behind by a player that did not exit cleanly
+    pub fn interrupted_session(&self) -> Option<SessionState> {
+        let path = self.dir.join(SESSION_FILE);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!("Could not read '{}': {}", path.display(), e);
+                return None;
+            }
+        };
+        match serde_json::from_str(&content) {
+            Ok(session) => Some(session),
+            Err(e) => {
+                warn!("Ignoring corrupt session file '{}': {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn send(&self, write: HistoryWrite) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(write);
+        }
+    }
+}
+
+impl Drop for PlayHistory {
+    fn drop(&mut self) {
+        // Closing the channel lets the writer finish what is queued
+        self.sender.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Read the last `max_entries` plays, skipping lines that do not parse
+fn read_history(path: &Path, max_entries: usize) -> io::Result<VecDeque<HistoryEntry>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(VecDeque::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut entries = VecDeque::new();
+    let mut unreadable = 0;
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push_back(entry),
+            Err(_) => unreadable += 1,
+        }
+    }
+    if unreadable > 0 {
+        warn!("Ignoring {} unreadable line(s) in '{}'", unreadable, path.display());
+    }
+    while entries.len() > max_entries {
+        entries.pop_front();
+    }
+    Ok(entries)
+}
+
+/// Writer thread: append plays, compact the log once it outgrows the cap, keep the session file
+fn write_history(
+    dir: &Path,
+    mut entries: VecDeque<HistoryEntry>,
+    max_entries: usize,
+    receiver: mpsc::Receiver<HistoryWrite>,
+) {
+    let history_path = dir.join(HISTORY_FILE);
+    let session_path = dir.join(SESSION_FILE);
+    // Rewriting the whole file on every play would be wasteful; let it grow a little first
+    let mut lines_on_disk = count_lines(&history_path);
+
+    for write in receiver {
+        let result = match write {
+            HistoryWrite::Append(entry) => {
+                entries.push_back(entry.clone());
+                while entries.len() > max_entries {
+                    entries.pop_front();
+                }
+                if lines_on_disk + 1 > max_entries + max_entries / 4 {
+                    lines_on_disk = entries.len();
+                    replace_file(&history_path, history_lines(&entries).as_bytes())
+                } else {
+                    lines_on_disk += 1;
+                    append_line(&history_path, &entry)
+                }
+            }
+            HistoryWrite::Session(Some(session)) => serde_json::to_string(&session)
+                .map_err(io::Error::from)
+                .and_then(|json| replace_file(&session_path, json.as_bytes())),
+            HistoryWrite::Session(None) => match fs::remove_file(&session_path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            },
+        };
+        if let Err(e) = result {
+            warn!("Could not save play history: {}", e);
+        }
+    }
+}
+
+fn count_lines(path: &Path) -> usize {
+    fs::read_to_string(path).map(|content| content.lines().count()).unwrap_or(0)
+}
+
+fn history_lines(entries: &VecDeque<HistoryEntry>) -> String {
+    entries.iter()
+        .filter_map(|entry| serde_json::to_string(entry).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+fn append_line(path: &Path, entry: &HistoryEntry) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = BufWriter::new(file);
+    writeln!(writer, "{}", serde_json::to_string(entry)?)?;
+    writer.flush()
+}
+
+/// Accumulates how long the current track has been playing and reports
+/// finished plays that count for the history
+#[derive(Debug, Default)]
+pub struct PlayTracker {
+    current: Option<(TrackInfo, Duration)>,
+}
+
+impl PlayTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Advance by `elapsed`; returns the previous track's play once a different track (or nothing) is playing
+    pub fn tick(&mut self, track: Option<&TrackInfo>, playing: bool, elapsed: Duration) -> Option<HistoryEntry> {
+        let same_track = match (&self.current, track) {
+            (Some((current, _)), Some(track)) => {
+                current.path == track.path && current.start_offset == track.start_offset
+            }
+            _ => false,
+        };
+        let finished = if same_track { None } else { self.finish() };
+
+        if let Some(track) = track {
+            let (_, played) = self.current.get_or_insert_with(|| (track.clone(), Duration::ZERO));
+            if playing {
+                *played += elapsed;
+            }
+        }
+        finished
+    }
+
+    /// End the current play, e.g. on exit
+    pub fn finish(&mut self) -> Option<HistoryEntry> {
+        let (track, played) = self.current.take()?;
+        (played >= MIN_PLAY_TIME).then(|| HistoryEntry {
+            path: track.path.clone(),
+            start_offset: track.start_offset,
+            title: track.metadata.title.clone(),
+            artist: track.metadata.artist.clone(),
+            played_at: Utc::now(),
+            played_for: played,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AudioMetadata;
+    use tempfile::TempDir;
+
+    fn track(path: &str) -> TrackInfo {
+        TrackInfo::new(PathBuf::from(path), AudioMetadata::new(), Duration::from_secs(240), 1024)
+    }
+
+    fn entry(path: &str) -> HistoryEntry {
+        HistoryEntry {
+            path: PathBuf::from(path),
+            start_offset: Duration::ZERO,
+            title: None,
+            artist: None,
+            played_at: Utc::now(),
+            played_for: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn test_tracker_counts_only_long_plays() {
+        let mut tracker = PlayTracker::new();
+        let a = track("/music/a.flac");
+        let b = track("/music/b.flac");
+
+        for _ in 0..20 {
+            assert!(tracker.tick(Some(&a), true, Duration::from_secs(1)).is_none());
+        }
+        // Skipped after 20 seconds: not a play
+        assert!(tracker.tick(Some(&b), true, Duration::from_secs(1)).is_none());
+
+        // Paused time does not count
+        for _ in 0..40 {
+            tracker.tick(Some(&b), false, Duration::from_secs(1));
+        }
+        for _ in 0..30 {
+            tracker.tick(Some(&b), true, Duration::from_secs(1));
+        }
+        let played = tracker.tick(None, false, Duration::from_secs(1)).unwrap();
+        assert_eq!(played.path, b.path);
+        assert_eq!(played.played_for, Duration::from_secs(31));
+        assert!(tracker.finish().is_none());
+    }
+
+    #[test]
+    fn test_history_persists_and_is_capped() {
+        let dir = TempDir::new().unwrap();
+        {
+            let mut history = PlayHistory::open(dir.path(), 3);
+            for i in 0..10 {
+                history.record(entry(&format!("/music/{}.flac", i)));
+            }
+            let recent: Vec<_> = history.recent(2).map(|entry| entry.path.clone()).collect();
+            assert_eq!(recent, vec![PathBuf::from("/music/9.flac"), PathBuf::from("/music/8.flac")]);
+        }
+
+        let history = PlayHistory::open(dir.path(), 3);
+        let paths: Vec<_> = history.recent(10).map(|entry| entry.path.clone()).collect();
+        assert_eq!(paths.len(), 3);
+        assert_eq!(history.last().unwrap().path, PathBuf::from("/music/9.flac"));
+        // The file itself is compacted, never far beyond the cap
+        assert!(count_lines(&dir.path().join(HISTORY_FILE)) <= 4);
+    }
+
+    #[test]
+    fn test_corrupt_history_lines_are_skipped() {
+        let dir = TempDir::new().unwrap();
+        let good = serde_json::to_string(&entry("/music/good.flac")).unwrap();
+        fs::write(dir.path().join(HISTORY_FILE), format!("{}\n{{ truncated\nnot json\n", good)).unwrap();
+
+        let history = PlayHistory::open(dir.path(), 100);
+        assert_eq!(history.recent(10).count(), 1);
+        assert_eq!(history.last().unwrap().path, PathBuf::from("/music/good.flac"));
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let mut album_track = track("/music/album.flac");
+        album_track.start_offset = Duration::from_secs(300);
+        {
+            let mut history = PlayHistory::open(dir.path(), 10);
+            assert!(history.interrupted_session().is_none());
+            history.save_session(Some(SessionState::new(&album_track, Duration::from_secs(390))));
+        }
+
+        let mut history = PlayHistory::open(dir.path(), 10);
+        let session = history.interrupted_session().unwrap();
+        assert_eq!(session.path, album_track.path);
+        assert_eq!(session.track_position(), Duration::from_secs(90));
+
+        history.save_session(None);
+        drop(history);
+        assert!(!dir.path().join(SESSION_FILE).exists());
+
+        fs::write(dir.path().join(SESSION_FILE), "garbage").unwrap();
+        assert!(PlayHistory::open(dir.path(), 10).interrupted_session().is_none());
+    }
+}
@@ -0,0 +1,204 @@
+/*!
+File integrity checks for `queue add --verify`
+
+Every verified file gets a SHA-256 content hash. When a `.sha256` sidecar sits
+next to the file (`song.flac.sha256` or `song.sha256`, in `sha256sum` format)
+the hash must match it. FLAC files are also fully decoded so the MD5 of the
+audio can be compared with the signature in their STREAMINFO block.
+*/
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::probe::Hint;
+use symphonia::default::{get_codecs, get_probe};
+use crate::error::QueueError;
+
+/// SHA-256 digest of a file's contents
+pub type ContentHash = [u8; 32];
+
+/// Hash `path` and check it against its sidecar and, for FLAC, its embedded MD5
+pub fn verify_file(path: &Path) -> Result<ContentHash, QueueError> {
+    let corrupted = |reason: String| QueueError::CorruptedFile {
+        path: path.to_path_buf(),
+        reason,
+    };
+
+    let hash = sha256_file(path).map_err(|e| corrupted(format!("cannot read file: {}", e)))?;
+    if let Some((sidecar, expected)) = read_sidecar(path) {
+        if expected != hash {
+            return Err(corrupted(format!(
+                "SHA-256 {} does not match {} from '{}'",
+                to_hex(&hash),
+                to_hex(&expected),
+                sidecar.display()
+            )));
+        }
+    }
+
+    let is_flac = path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ext.eq_ignore_ascii_case("flac"));
+    if is_flac && !flac_md5_matches(path).map_err(corrupted)? {
+        return Err(corrupted("decoded audio does not match the FLAC MD5 signature".to_string()));
+    }
+
+    Ok(hash)
+}
+
+/// SHA-256 of the file at `path`
+pub fn sha256_file(path: &Path) -> io::Result<ContentHash> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut chunk = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&chunk[..read]);
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// Lowercase hex form of a hash, as `sha256sum` prints it
+pub fn to_hex(hash: &[u8]) -> String {
+    hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Expected hash from the sidecar next to `path`, with the sidecar's path
+fn read_sidecar(path: &Path) -> Option<(PathBuf, ContentHash)> {
+    let mut with_suffix = path.as_os_str().to_owned();
+    with_suffix.push(".sha256");
+
+    [PathBuf::from(with_suffix), path.with_extension("sha256")]
+        .into_iter()
+        .find_map(|sidecar| {
+            let contents = fs::read_to_string(&sidecar).ok()?;
+            match parse_hex(contents.split_whitespace().next()?) {
+                Some(hash) => Some((sidecar, hash)),
+                None => {
+                    log::warn!("Ignoring malformed checksum file '{}'", sidecar.display());
+                    None
+                }
+            }
+        })
+}
+
+fn parse_hex(text: &str) -> Option<ContentHash> {
+    if text.len() != 64 || !text.is_ascii() {
+        return None;
+    }
+    let mut hash = [0u8; 32];
+    for (byte, pair) in hash.iter_mut().zip(text.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(hash)
+}
+
+/// Decode the whole FLAC stream and compare it with the STREAMINFO MD5; files without a signature pass
+pub fn flac_md5_matches(path: &Path) -> Result<bool, String> {
+    let file = File::open(path).map_err(|e| format!("cannot read file: {}", e))?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension("flac");
+
+    let probed = get_probe()
+        .format(&hint, mss, &Default::default(), &Default::default())
+        .map_err(|e| format!("cannot read FLAC stream: {}", e))?;
+    let mut format = probed.format;
+    let track = format.default_track().ok_or("no audio stream")?;
+    // An all-zero signature means the encoder did not compute one
+    if track.codec_params.verification_check.is_none() {
+        return Ok(true);
+    }
+    let track_id = track.id;
+    let mut decoder = get_codecs()
+        .make(&track.codec_params, &DecoderOptions { verify: true })
+        .map_err(|e| format!("cannot decode FLAC stream: {}", e))?;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(format!("cannot read FLAC stream: {}", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        decoder.decode(&packet).map_err(|e| format!("decode failed: {}", e))?;
+    }
+
+    Ok(decoder.finalize().verify_ok != Some(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::test_files;
+    use tempfile::TempDir;
+
+    const SAMPLE_BYTES: &[u8] = b"RIFF-not-really-audio-but-bytes-are-bytes";
+
+    fn write_sidecar(path: &Path, hash: &ContentHash) {
+        let mut sidecar = path.as_os_str().to_owned();
+        sidecar.push(".sha256");
+        let name = path.file_name().unwrap().to_string_lossy();
+        fs::write(PathBuf::from(sidecar), format!("{}  {}\n", to_hex(hash), name)).unwrap();
+    }
+
+    /// Mono 16-bit 44.1 kHz FLAC holding one frame of 256 silent samples, with the given MD5 signature
+    fn write_flac(path: &Path, md5: [u8; 16]) {
+        let mut bytes = b"fLaC".to_vec();
+        bytes.extend(test_files::flac_block(0, true, &test_files::flac_streaminfo(256, 44_100, 1, 16, 256, md5)));
+        // Frame 0: 256-sample block, 44.1 kHz, mono, 16-bit, then a CONSTANT subframe of zero
+        bytes.extend(test_files::flac_frame(&[0xFF, 0xF8, 0x89, 0x08, 0x00], &[0x00, 0x00, 0x00]));
+        fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn test_hash_is_returned_without_sidecar() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("song.wav");
+        fs::write(&path, SAMPLE_BYTES).unwrap();
+
+        let hash = verify_file(&path).unwrap();
+        assert_eq!(hash, <[u8; 32]>::from(Sha256::digest(SAMPLE_BYTES)));
+    }
+
+    #[test]
+    fn test_corrupted_byte_fails_sidecar_check() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("song.wav");
+        fs::write(&path, SAMPLE_BYTES).unwrap();
+        write_sidecar(&path, &sha256_file(&path).unwrap());
+        assert!(verify_file(&path).is_ok());
+
+        let mut corrupted = SAMPLE_BYTES.to_vec();
+        corrupted[10] ^= 0x01;
+        fs::write(&path, corrupted).unwrap();
+
+        match verify_file(&path) {
+            Err(QueueError::CorruptedFile { reason, .. }) => assert!(reason.contains("does not match")),
+            other => panic!("expected CorruptedFile, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_flac_md5_signature_is_checked() {
+        let temp_dir = TempDir::new().unwrap();
+        let unsigned = temp_dir.path().join("unsigned.flac");
+        write_flac(&unsigned, [0; 16]);
+        assert!(verify_file(&unsigned).is_ok());
+
+        // Silence never hashes to this signature
+        let mismatched = temp_dir.path().join("mismatched.flac");
+        write_flac(&mismatched, [0xAA; 16]);
+        match verify_file(&mismatched) {
+            Err(QueueError::CorruptedFile { reason, .. }) => assert!(reason.contains("MD5 signature")),
+            other => panic!("expected CorruptedFile, got {:?}", other),
+        }
+    }
+}
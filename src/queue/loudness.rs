@@ -0,0 +1,269 @@
+/*!
+Loudness scanning for `scan-loudness`
+
+Each file is decoded with the same decoder playback would use and measured
+with `audio::loudness`. Results are kept in `loudness.json` next to
+`config.toml`, keyed by path and carrying a fingerprint of the file, so a
+result stops counting once the file changes; files without ReplayGain tags
+still have a gain to show. The store is read and replaced atomically on every
+call, which lets a background scan write to it while the player reads it.
+
+With `--write-tags`, FLAC and Ogg Vorbis files also get `REPLAYGAIN_TRACK_GAIN`
+and `REPLAYGAIN_TRACK_PEAK` comments; the fingerprint is taken after the tags
+are written.
+*/
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::audio::engine::AudioEngineImpl;
+use crate::audio::loudness::{self, Loudness};
+use crate::config::store;
+use super::ratings::{fingerprint, path_key};
+use super::tags;
+
+/// Scan results, in the config directory
+pub const LOUDNESS_FILE: &str = "loudness.json";
+
+/// Format version written by this build
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredLoudness {
+    #[serde(flatten)]
+    loudness: Loudness,
+    /// Content fingerprint of the file that was measured
+    fingerprint: String,
+}
+
+/// On-disk layout of `loudness.json`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LoudnessFile {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    tracks: BTreeMap<String, StoredLoudness>,
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+}
+
+/// Measured loudness by file, stored in `loudness.json`
+#[derive(Debug, Clone)]
+pub struct LoudnessStore {
+    path: PathBuf,
+}
+
+impl LoudnessStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Stored loudness of `path`, unless the file changed after it was measured
+    pub fn get(&self, path: &Path) -> io::Result<Option<Loudness>> {
+        let file = self.read()?;
+        let Some(entry) = file.tracks.get(&path_key(path)) else {
+            return Ok(None);
+        };
+        Ok((fingerprint(path).ok().as_deref() == Some(entry.fingerprint.as_str())).then_some(entry.loudness))
+    }
+
+    /// Keep `loudness` for the file as it is now
+    pub fn insert(&self, path: &Path, loudness: Loudness) -> io::Result<()> {
+        let mut file = self.read()?;
+        file.tracks.insert(path_key(path), StoredLoudness { loudness, fingerprint: fingerprint(path)? });
+        file.version = file.version.max(FORMAT_VERSION);
+        self.write(&file)
+    }
+
+    fn read(&self) -> io::Result<LoudnessFile> {
+        store::load_json(&self.path)
+    }
+
+    fn write(&self, file: &LoudnessFile) -> io::Result<()> {
+        store::save_json(&self.path, file)
+    }
+}
+
+/// Outcome of scanning one file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileScan {
+    pub path: PathBuf,
+    pub loudness: Option<Loudness>,
+    /// Why the file has no loudness, or why its result or tags were not saved
+    pub problem: Option<String>,
+    /// Whether ReplayGain tags were written to the file
+    pub tagged: bool,
+}
+
+/// Outcome of a `scan-loudness` run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScanSummary {
+    /// Scanned files, in the order they were scanned
+    pub scans: Vec<FileScan>,
+    /// Files left unscanned because the run was cancelled
+    pub skipped: usize,
+}
+
+impl ScanSummary {
+    pub fn failures(&self) -> impl Iterator<Item = &FileScan> {
+        self.scans.iter().filter(|scan| scan.problem.is_some())
+    }
+
+    pub fn failed(&self) -> usize {
+        self.failures().count()
+    }
+
+    pub fn tagged(&self) -> usize {
+        self.scans.iter().filter(|scan| scan.tagged).count()
+    }
+}
+
+/// Scan `files` one after another into `store`, calling `progress` after each
+///
+/// Setting `cancel` stops the run after the file being decoded; the rest count as skipped.
+pub fn run(
+    files: &[PathBuf],
+    store: &LoudnessStore,
+    write_tags: bool,
+    cancel: &AtomicBool,
+    mut progress: impl FnMut(usize, &FileScan),
+) -> ScanSummary {
+    let mut summary = ScanSummary::default();
+    for (index, path) in files.iter().enumerate() {
+        let scan = scan_file(path, store, write_tags, cancel);
+        if cancel.load(Ordering::Relaxed) {
+            summary.skipped = files.len() - index;
+            break;
+        }
+        progress(index + 1, &scan);
+        summary.scans.push(scan);
+    }
+    summary
+}
+
+/// Measure `path`, tag it when asked and the format allows, and store the result
+pub fn scan_file(path: &Path, store: &LoudnessStore, write_tags: bool, cancel: &AtomicBool) -> FileScan {
+    let mut scan = FileScan { path: path.to_path_buf(), loudness: None, problem: None, tagged: false };
+    let measured = AudioEngineImpl::open_decoder(path)
+        .map_err(|e| e.to_string())
+        .and_then(|mut decoder| loudness::measure(decoder.as_mut(), cancel).map_err(|e| e.to_string()));
+    let loudness = match measured {
+        Ok(Some(loudness)) => loudness,
+        Ok(None) => {
+            scan.problem = Some("silent, or shorter than 400 ms".to_string());
+            return scan;
+        }
+        Err(reason) => {
+            scan.problem = Some(reason);
+            return scan;
+        }
+    };
+    scan.loudness = Some(loudness);
+    if cancel.load(Ordering::Relaxed) {
+        return scan;
+    }
+
+    if write_tags && tags::can_write(path) {
+        let replaygain = [
+            ("REPLAYGAIN_TRACK_GAIN", format!("{:.2} dB", loudness.track_gain_db())),
+            ("REPLAYGAIN_TRACK_PEAK", format!("{:.6}", loudness.true_peak)),
+        ];
+        match tags::write_comments(path, &replaygain) {
+            Ok(()) => scan.tagged = true,
+            Err(e) => scan.problem = Some(format!("could not write tags: {}", e)),
+        }
+    }
+    if let Err(e) = store.insert(path, loudness) {
+        scan.problem = Some(format!("could not save the result: {}", e));
+    }
+    scan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::metadata::MetadataExtractor;
+    use crate::audio::test_files;
+    use std::f64::consts::PI;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// 16-bit mono samples of a 1 kHz sine at -20 dBFS, 44.1 kHz
+    fn sine_samples(frames: usize) -> Vec<i16> {
+        (0..frames)
+            .map(|n| (0.1 * (2.0 * PI * 1000.0 * n as f64 / 44_100.0).sin() * 32767.0).round() as i16)
+            .collect()
+    }
+
+    fn write_wav(dir: &TempDir, name: &str, samples: &[i16]) -> PathBuf {
+        let path = dir.path().join(name);
+        test_files::write_wav(&path, 44_100, 1, 16, &test_files::pcm16(samples));
+        path
+    }
+
+    /// Mono 16-bit 44.1 kHz FLAC of `samples` in 4096-sample VERBATIM frames, without an MD5
+    fn write_flac(dir: &TempDir, name: &str, samples: &[i16]) -> PathBuf {
+        const BLOCK: usize = 4096;
+        let streaminfo = test_files::flac_streaminfo(BLOCK as u16, 44_100, 1, 16, samples.len() as u64, [0; 16]);
+        let mut bytes = b"fLaC".to_vec();
+        bytes.extend(test_files::flac_block(0, true, &streaminfo));
+        for (index, block) in samples.chunks_exact(BLOCK).enumerate() {
+            // 4096-sample block, 44.1 kHz, mono, 16-bit, frame number, then a VERBATIM subframe
+            let mut subframe = vec![0x02];
+            subframe.extend(block.iter().flat_map(|sample| sample.to_be_bytes()));
+            bytes.extend(test_files::flac_frame(&[0xFF, 0xF8, 0xC9, 0x08, index as u8], &subframe));
+        }
+        let path = dir.path().join(name);
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_scan_stores_loudness_until_the_file_changes() {
+        let dir = TempDir::new().unwrap();
+        let track = write_wav(&dir, "tone.wav", &sine_samples(44_100 * 2));
+        let silent = write_wav(&dir, "silent.wav", &vec![0; 44_100]);
+        let store = LoudnessStore::new(dir.path().join(LOUDNESS_FILE));
+
+        let mut seen = Vec::new();
+        let summary = run(&[track.clone(), silent.clone()], &store, true, &AtomicBool::new(false), |done, scan| {
+            seen.push((done, scan.path.clone()))
+        });
+        assert_eq!(seen, vec![(1, track.clone()), (2, silent.clone())]);
+        assert_eq!((summary.scans.len(), summary.failed(), summary.tagged(), summary.skipped), (2, 1, 0, 0));
+
+        // One channel at -20 dBFS reads -23 LUFS
+        let loudness = store.get(&track).unwrap().unwrap();
+        assert!((loudness.integrated_lufs + 23.0).abs() < 0.1);
+        assert!((loudness.track_gain_db() - 5.0).abs() < 0.1);
+        assert_eq!(Some(loudness), summary.scans[0].loudness);
+        assert_eq!(store.get(&silent).unwrap(), None);
+
+        write_wav(&dir, "tone.wav", &sine_samples(44_100));
+        assert_eq!(store.get(&track).unwrap(), None);
+
+        let summary = run(&[track], &store, false, &AtomicBool::new(true), |_, _| panic!("nothing is scanned once cancelled"));
+        assert_eq!((summary.scans.len(), summary.skipped), (0, 1));
+    }
+
+    #[test]
+    fn test_scan_writes_replaygain_tags_to_flac() {
+        let dir = TempDir::new().unwrap();
+        let track = write_flac(&dir, "tone.flac", &sine_samples(4096 * 16));
+        let store = LoudnessStore::new(dir.path().join(LOUDNESS_FILE));
+
+        let scan = scan_file(&track, &store, true, &AtomicBool::new(false));
+        assert_eq!(scan.problem, None);
+        assert!(scan.tagged);
+
+        let tags = MetadataExtractor::full_report(&track).unwrap().metadata.extra_tags;
+        let gain = tags.iter().find(|(key, _)| key == "REPLAYGAIN_TRACK_GAIN").map(|(_, value)| value.as_str());
+        assert_eq!(gain, Some(format!("{:.2} dB", scan.loudness.unwrap().track_gain_db()).as_str()));
+        assert!(tags.iter().any(|(key, _)| key == "REPLAYGAIN_TRACK_PEAK"));
+        // The stored result belongs to the tagged file
+        assert_eq!(store.get(&track).unwrap(), scan.loudness);
+    }
+}
@@ -0,0 +1,253 @@
+/*!
+Lyrics from tags and sidecar files
+
+A track's lyrics come from its `LYRICS`/`USLT` tag, or else from `song.lrc` or
+`song.txt` next to `song.flac`. Text with LRC timestamps (`[01:23.45]`) is
+parsed into timed lines, which `LyricsSync` follows during `watch`.
+*/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::models::TrackInfo;
+
+/// Sidecar extensions looked for next to the audio file, in order
+const SIDECAR_EXTENSIONS: &[&str] = &["lrc", "txt"];
+
+/// One timed line of LRC lyrics
+#[derive(Debug, Clone, PartialEq)]
+pub struct LyricLine {
+    pub time: Duration,
+    pub text: String,
+}
+
+/// Lyrics of a track, timed when they came as LRC
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lyrics {
+    Plain(String),
+    /// Lines sorted by time
+    Synced(Vec<LyricLine>),
+}
+
+impl Lyrics {
+    /// Parse lyrics text, as LRC when any line carries a timestamp
+    pub fn parse(content: &str) -> Self {
+        let content = content.trim_start_matches('\u{feff}');
+        let mut lines = Vec::new();
+        let mut offset_ms: i64 = 0;
+
+        for line in content.lines() {
+            let mut rest = line.trim();
+            let mut times = Vec::new();
+            while let Some((tag, after)) = rest.strip_prefix('[').and_then(|tag| tag.split_once(']')) {
+                match Self::parse_timestamp(tag) {
+                    Some(time) => times.push(time),
+                    None => {
+                        // ID tags such as [ar:Artist]; only the offset changes timing
+                        if let Some((key, value)) = tag.split_once(':') {
+                            if key.trim().eq_ignore_ascii_case("offset") {
+                                offset_ms = value.trim().trim_start_matches('+').parse().unwrap_or(0);
+                            }
+                        }
+                        break;
+                    }
+                }
+                rest = after;
+            }
+            // One line may be sung several times: [00:12.00][01:15.30]Chorus
+            for time in times {
+                lines.push(LyricLine { time, text: rest.trim().to_string() });
+            }
+        }
+
+        if lines.is_empty() {
+            return Lyrics::Plain(content.trim().to_string());
+        }
+
+        // A positive offset shows lines sooner
+        let offset = Duration::from_millis(offset_ms.unsigned_abs());
+        for line in &mut lines {
+            line.time = if offset_ms >= 0 {
+                line.time.saturating_sub(offset)
+            } else {
+                line.time + offset
+            };
+        }
+        lines.sort_by_key(|line| line.time);
+        Lyrics::Synced(lines)
+    }
+
+    /// Parse an LRC timestamp: `mm:ss`, `mm:ss.xx` or `mm:ss.xxx`
+    pub fn parse_timestamp(tag: &str) -> Option<Duration> {
+        let (minutes, seconds) = tag.trim().split_once(':')?;
+        let minutes: u64 = minutes.parse().ok()?;
+        let (seconds, fraction) = match seconds.split_once(['.', ':']) {
+            Some((seconds, fraction)) => (seconds, fraction),
+            None => (seconds, ""),
+        };
+        let seconds: u64 = seconds.parse().ok()?;
+        if seconds >= 60 || fraction.len() > 3 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        // Hundredths are the usual precision; pad to milliseconds
+        let millis = if fraction.is_empty() { 0 } else { format!("{:0<3}", fraction).parse::<u64>().ok()? };
+        Some(Duration::from_millis((minutes * 60 + seconds) * 1000 + millis))
+    }
+
+    /// Lyrics for a track: its lyrics tag, or a sidecar file
+    ///
+    /// Returns `None` when there are none or the sidecar cannot be read.
+    pub fn for_track(track: &TrackInfo) -> Option<Self> {
+        if let Some(lyrics) = track.metadata.lyrics.as_deref().filter(|lyrics| !lyrics.trim().is_empty()) {
+            return Some(Self::parse(lyrics));
+        }
+        let content = Self::find_for(&track.path).and_then(|path| Self::read(&path))?;
+        (!content.trim().is_empty()).then(|| Self::parse(&content))
+    }
+
+    /// Find the sidecar for an audio file: `song.lrc`, then `song.txt`
+    pub fn find_for(audio_path: &Path) -> Option<PathBuf> {
+        SIDECAR_EXTENSIONS.iter()
+            .map(|extension| audio_path.with_extension(extension))
+            .find(|candidate| candidate.is_file())
+    }
+
+    /// Read a lyrics file; non-UTF-8 files are read as Latin-1
+    fn read(path: &Path) -> Option<String> {
+        let bytes = fs::read(path).ok()?;
+        Some(match String::from_utf8(bytes) {
+            Ok(content) => content,
+            Err(e) => e.into_bytes().iter().map(|&byte| byte as char).collect(),
+        })
+    }
+}
+
+/// Follows the current line of the playing track's timed lyrics
+///
+/// The line is looked up from the position on every call, so seeks in either direction
+/// are picked up straight away. Lyrics are reloaded when the track changes.
+#[derive(Debug, Default)]
+pub struct LyricsSync {
+    /// Track the lines belong to: its path and start within the file
+    track: Option<(PathBuf, Duration)>,
+    lines: Vec<LyricLine>,
+}
+
+impl LyricsSync {
+    /// Load the timed lyrics of `track` unless they are already loaded
+    pub fn follow(&mut self, track: Option<&TrackInfo>) {
+        let key = track.map(|track| (track.path.clone(), track.start_offset));
+        if key == self.track {
+            return;
+        }
+        self.lines = match track.and_then(Lyrics::for_track) {
+            Some(Lyrics::Synced(lines)) => lines,
+            _ => Vec::new(),
+        };
+        self.track = key;
+    }
+
+    pub fn lines(&self) -> &[LyricLine] {
+        &self.lines
+    }
+
+    /// Index of the line being sung at `position`, `None` before the first
+    pub fn current_line(&self, position: Duration) -> Option<usize> {
+        self.lines.partition_point(|line| line.time <= position).checked_sub(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn synced(lyrics: Lyrics) -> Vec<(u64, String)> {
+        match lyrics {
+            Lyrics::Synced(lines) => lines.into_iter().map(|line| (line.time.as_millis() as u64, line.text)).collect(),
+            Lyrics::Plain(text) => panic!("expected timed lyrics, got {:?}", text),
+        }
+    }
+
+    fn track(path: PathBuf) -> TrackInfo {
+        TrackInfo::new(path, Default::default(), Duration::from_secs(200), 0)
+    }
+
+    #[test]
+    fn test_parse_timestamps() {
+        assert_eq!(Lyrics::parse_timestamp("01:23.45"), Some(Duration::from_millis(83_450)));
+        assert_eq!(Lyrics::parse_timestamp("01:23.456"), Some(Duration::from_millis(83_456)));
+        assert_eq!(Lyrics::parse_timestamp("01:23"), Some(Duration::from_secs(83)));
+        assert_eq!(Lyrics::parse_timestamp("1:02:50"), Some(Duration::from_millis(62_500)));
+        assert_eq!(Lyrics::parse_timestamp("ar:Someone"), None);
+        assert_eq!(Lyrics::parse_timestamp("00:75.00"), None);
+    }
+
+    #[test]
+    fn test_parse_lrc_with_metadata_and_repeated_timestamps() {
+        let lrc = "[ti:Song]\n[ar:Someone]\n[length: 03:20]\n\n[00:12.00][01:15.30]Chorus line\n[00:05.50]First line\n[00:20.00]\n";
+        assert_eq!(synced(Lyrics::parse(lrc)), vec![
+            (5_500, "First line".to_string()),
+            (12_000, "Chorus line".to_string()),
+            // An empty timed line clears the previous one
+            (20_000, String::new()),
+            (75_300, "Chorus line".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_lrc_offset() {
+        // Positive offsets show lines sooner, negative ones later
+        assert_eq!(synced(Lyrics::parse("[offset:+500]\n[00:10.00]Early\n[00:00.20]Start")), vec![
+            (0, "Start".to_string()),
+            (9_500, "Early".to_string()),
+        ]);
+        assert_eq!(synced(Lyrics::parse("[offset:-250]\n[00:10.00]Late")), vec![(10_250, "Late".to_string())]);
+    }
+
+    #[test]
+    fn test_untimed_text_is_plain() {
+        assert_eq!(Lyrics::parse("First line\nSecond [bridge]\n"), Lyrics::Plain("First line\nSecond [bridge]".to_string()));
+    }
+
+    #[test]
+    fn test_tag_is_preferred_over_sidecar_files() {
+        let dir = TempDir::new().unwrap();
+        let audio = dir.path().join("song.flac");
+        let mut song = track(audio.clone());
+        assert_eq!(Lyrics::for_track(&song), None);
+
+        fs::write(dir.path().join("song.txt"), "From the text file").unwrap();
+        assert_eq!(Lyrics::for_track(&song), Some(Lyrics::Plain("From the text file".to_string())));
+
+        fs::write(dir.path().join("song.lrc"), "[00:01.00]From the LRC file").unwrap();
+        assert_eq!(Lyrics::find_for(&audio), Some(dir.path().join("song.lrc")));
+        assert!(matches!(Lyrics::for_track(&song), Some(Lyrics::Synced(_))));
+
+        song.metadata.lyrics = Some("From the tag".to_string());
+        assert_eq!(Lyrics::for_track(&song), Some(Lyrics::Plain("From the tag".to_string())));
+    }
+
+    #[test]
+    fn test_sync_follows_seeks_and_track_changes() {
+        let dir = TempDir::new().unwrap();
+        let mut song = track(dir.path().join("song.flac"));
+        song.metadata.lyrics = Some("[00:01.00]One\n[00:05.00]Two\n[00:09.00]Three".to_string());
+
+        let mut sync = LyricsSync::default();
+        sync.follow(Some(&song));
+        assert_eq!(sync.lines().len(), 3);
+        assert_eq!(sync.current_line(Duration::ZERO), None);
+        assert_eq!(sync.current_line(Duration::from_secs(6)), Some(1));
+        // Seeking back and forward
+        assert_eq!(sync.current_line(Duration::from_secs(2)), Some(0));
+        assert_eq!(sync.current_line(Duration::from_secs(120)), Some(2));
+
+        // Untimed lyrics give nothing to follow
+        let mut other = track(dir.path().join("other.flac"));
+        other.metadata.lyrics = Some("Just text".to_string());
+        sync.follow(Some(&other));
+        assert!(sync.lines().is_empty());
+        assert_eq!(sync.current_line(Duration::from_secs(6)), None);
+    }
+}
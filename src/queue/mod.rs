@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
 use std::fs;
 use std::time::Duration;
@@ -6,17 +6,27 @@ use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
 use symphonia::default::get_probe;
+use crate::audio::decoders::{Mp3Decoder, WavPackDecoder};
+use crate::audio::MetadataExtractor;
+use crate::audio::AudioDecoder;
+use crate::audio::format_detection::detect_audio_format;
+use crate::audio::source::{stream_extension, stream_url};
 use crate::error::{QueueError, PlaylistError};
 use crate::models::{TrackInfo, AudioMetadata, AudioCodec};
-use crate::queue::playlist::{PlaylistManager, PlaylistFormat};
+use crate::queue::cuesheet::CueSheet;
+use crate::queue::playlist::{PlaylistManager, PlaylistMove, PlaylistFormat, PlaylistSaveOptions, SmartPlaylistCriteria};
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
 
 /// Core trait for queue management functionality
 pub trait QueueManager: Send {
     /// Add a file to the queue
     fn add_file(&mut self, path: &Path) -> Result<(), QueueError>;
     
-    /// Add all audio files from a directory recursively
-    fn add_directory(&mut self, path: &Path) -> Result<(), QueueError>;
+    /// Add the audio files of a directory that pass `options`
+    fn add_directory(&mut self, path: &Path, options: &DirectoryAddOptions) -> Result<(), QueueError>;
     
     /// Get the next track in the queue
     fn next_track(&mut self) -> Option<&TrackInfo>;
@@ -49,7 +59,7 @@ pub trait QueueManager: Send {
     fn jump_to(&mut self, index: usize) -> Result<&TrackInfo, QueueError>;
     
     /// Save the current queue as a playlist
-    fn save_playlist(&self, name: &str, format: PlaylistFormat) -> Result<(), PlaylistError>;
+    fn save_playlist(&self, name: &str, format: PlaylistFormat, options: &PlaylistSaveOptions) -> Result<(), PlaylistError>;
     
     /// Load a playlist into the current queue
     fn load_playlist(&mut self, name: &str) -> Result<(), PlaylistError>;
@@ -59,32 +69,190 @@ pub trait QueueManager: Send {
     
     /// Delete a playlist
     fn delete_playlist(&self, name: &str) -> Result<(), PlaylistError>;
+
+    /// Rename a playlist
+    fn rename_playlist(&self, old_name: &str, new_name: &str) -> Result<(), PlaylistError>;
+
+    /// Combine playlists into a new one, returning its track count
+    fn merge_playlists(&self, names: &[String], output: &str) -> Result<usize, PlaylistError>;
+
+    /// Shuffle the queue in an order fixed by `seed`; the current track keeps playing
+    fn shuffle_tracks(&mut self, seed: u64);
 }
 
+pub mod cuesheet;
+pub mod export;
+pub mod history;
+pub mod integrity;
+pub mod loudness;
+pub mod lyrics;
 pub mod playlist;
+pub mod ratings;
+pub mod tags;
+pub mod verify;
+pub mod watch;
+
+/// Key for `queue sort`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortKey {
+    /// Artist, then title
+    Artist,
+    /// Album artist, album, disc and track number; untagged tracks by file name
+    Album,
+    /// Title, or file name when untagged
+    Title,
+    /// Track number; untagged tracks last
+    Track,
+    /// Full file path
+    Path,
+    /// Length, shortest first
+    Duration,
+    /// One-shot shuffle of the stored order
+    Random,
+    /// Disc number, then track number
+    Disc,
+    /// Tempo, slowest first; untagged tracks last
+    Bpm,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 9] = [
+        SortKey::Artist,
+        SortKey::Album,
+        SortKey::Title,
+        SortKey::Track,
+        SortKey::Path,
+        SortKey::Duration,
+        SortKey::Random,
+        SortKey::Disc,
+        SortKey::Bpm,
+    ];
+
+    /// Display name
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortKey::Artist => "artist",
+            SortKey::Album => "album",
+            SortKey::Title => "title",
+            SortKey::Track => "track",
+            SortKey::Path => "path",
+            SortKey::Duration => "duration",
+            SortKey::Random => "random",
+            SortKey::Disc => "disc",
+            SortKey::Bpm => "bpm",
+        }
+    }
+}
+
+impl std::str::FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value_lower = value.trim().to_lowercase();
+        SortKey::ALL
+            .into_iter()
+            .find(|key| key.as_str() == value_lower)
+            .ok_or_else(|| format!("unknown sort key '{}'", value))
+    }
+}
+
+/// Case-insensitive sort text for a tag; missing tags sort after every tagged value
+fn tag_sort_key(tag: Option<&str>) -> (bool, String) {
+    (tag.is_none(), tag.map(str::to_lowercase).unwrap_or_default())
+}
+
+/// Case-insensitive file name, the tie-breaker when tags are missing or equal
+fn file_name_sort_key(track: &TrackInfo) -> String {
+    track.path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default()
+}
+
+/// Which files adding a directory picks up; the default takes every supported file at any depth
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DirectoryAddOptions {
+    /// Subdirectory levels to descend into (0 = the directory itself only); None = no limit
+    pub max_depth: Option<usize>,
+    /// Extensions to take, without the dot; empty = every supported format
+    pub extensions: Vec<String>,
+    /// Leave out tracks shorter than this, including files whose duration could not be probed
+    pub min_duration: Option<Duration>,
+    /// Hash each file and check it against its sidecar checksum and embedded MD5; a failure aborts the add
+    pub verify: bool,
+}
+
+impl DirectoryAddOptions {
+    fn accepts_extension(&self, extension: &str) -> bool {
+        self.extensions.is_empty()
+            || self.extensions.iter().any(|allowed| allowed.trim_start_matches('.').eq_ignore_ascii_case(extension))
+    }
+
+    fn accepts_track(&self, track: &TrackInfo) -> bool {
+        self.min_duration.is_none_or(|min| track.duration >= min)
+    }
+}
+
+/// What probing a file's container reveals about it
+#[derive(Default)]
+struct ProbedFile {
+    metadata: AudioMetadata,
+    duration: Duration,
+    sample_rate: Option<u32>,
+    bit_depth: Option<u16>,
+    channels: Option<u16>,
+}
+
+/// What makes two queue entries duplicates of each other
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum DuplicateKey {
+    /// The same file, however its path was written, and the same CUE track within it
+    File(PathBuf, Duration),
+    /// Same artist and title (ignoring case) and the same length to the second
+    Recording(String, String, u64),
+}
+
+impl DuplicateKey {
+    /// Keys `track` is known by; the recording key only when asked for and both tags are present
+    fn for_track(track: &TrackInfo, by_metadata: bool) -> Vec<DuplicateKey> {
+        let path = fs::canonicalize(&track.path).unwrap_or_else(|_| track.path.clone());
+        let mut keys = vec![DuplicateKey::File(path, track.start_offset)];
+        if by_metadata {
+            if let (Some(artist), Some(title)) = (&track.metadata.artist, &track.metadata.title) {
+                keys.push(DuplicateKey::Recording(
+                    artist.trim().to_lowercase(),
+                    title.trim().to_lowercase(),
+                    track.duration.as_secs(),
+                ));
+            }
+        }
+        keys
+    }
+}
 
 /// Queue manager implementation with VecDeque for efficient queue operations
 pub struct QueueManagerImpl {
     current_queue: VecDeque<TrackInfo>,
     current_index: usize,
     playlist_manager: PlaylistManager,
+    /// Leave out tracks already queued when adding a directory
+    skip_duplicates: bool,
+    /// Descend into symlinked directories and take symlinked files when scanning
+    follow_symlinks: bool,
+    /// Names of marker files that keep their directory out of scans
+    nomusic_files: Vec<String>,
 }
 
 impl QueueManagerImpl {
     pub fn new() -> Self {
-        // Use default playlist directory in user's home config
-        let playlist_dir = dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("hires-player")
-            .join("playlists");
-        
-        let playlist_manager = PlaylistManager::new(playlist_dir)
+        // Same default playlist directory as the config
+        let playlist_manager = PlaylistManager::new(crate::config::default_playlist_directory())
             .expect("Failed to create playlist manager");
         
         Self {
             current_queue: VecDeque::new(),
             current_index: 0,
             playlist_manager,
+            skip_duplicates: false,
+            follow_symlinks: true,
+            nomusic_files: crate::config::default_nomusic_files(),
         }
     }
     
@@ -95,15 +263,18 @@ impl QueueManagerImpl {
             current_queue: VecDeque::new(),
             current_index: 0,
             playlist_manager,
+            skip_duplicates: false,
+            follow_symlinks: true,
+            nomusic_files: crate::config::default_nomusic_files(),
         })
     }
 
     /// Check if a file extension is supported
-    fn is_supported_format(extension: &str) -> bool {
+    pub(crate) fn is_supported_format(extension: &str) -> bool {
         let ext = extension.to_lowercase();
         matches!(
             ext.as_str(),
-            "flac" | "wav" | "wave" | "m4a" | "alac" | "mp3" | "ogg" | "oga"
+            "flac" | "wav" | "wave" | "m4a" | "alac" | "mp3" | "ogg" | "oga" | "wv"
         )
     }
 
@@ -116,17 +287,23 @@ impl QueueManagerImpl {
             "m4a" | "alac" => Some(AudioCodec::Alac),
             "mp3" => Some(AudioCodec::Mp3),
             "ogg" | "oga" => Some(AudioCodec::OggVorbis),
+            "wv" => Some(AudioCodec::WavPack),
             _ => None,
         }
     }
 
     /// Extract metadata and create TrackInfo from a file path
     pub fn create_track_info(path: &Path) -> Result<TrackInfo, QueueError> {
+        // A stream's tags and length are only known once it plays
+        if let Some(url) = stream_url(path) {
+            let mut track = TrackInfo::new(path.to_path_buf(), AudioMetadata::new(), Duration::ZERO, 0);
+            track.codec = stream_extension(url, None).and_then(|extension| Self::codec_from_extension(&extension));
+            return Ok(track);
+        }
+
         // Check if file exists
         if !path.exists() {
-            return Err(QueueError::FileNotFound {
-                path: path.to_string_lossy().to_string(),
-            });
+            return Err(QueueError::FileNotFound { path: path.to_path_buf() });
         }
 
         // Check if it's a supported format
@@ -135,35 +312,425 @@ impl QueueManagerImpl {
             .and_then(|ext| ext.to_str())
             .unwrap_or("");
 
-        if !Self::is_supported_format(extension) {
-            return Err(QueueError::InvalidFormat {
-                path: path.to_string_lossy().to_string(),
-            });
-        }
+        // Files with a missing or unfamiliar extension are recognized by their magic bytes
+        let codec = if Self::is_supported_format(extension) {
+            Self::codec_from_extension(extension)
+        } else {
+            Some(detect_audio_format(path).ok_or_else(|| QueueError::InvalidFormat { path: path.to_path_buf() })?)
+        };
 
         // Get file size
         let file_size = fs::metadata(path)
-            .map_err(|_| QueueError::FileNotFound {
-                path: path.to_string_lossy().to_string(),
-            })?
+            .map_err(|_| QueueError::FileNotFound { path: path.to_path_buf() })?
             .len();
 
         // Try to extract metadata using symphonia
-        let (metadata, duration) = Self::extract_metadata_and_duration(path)
+        let probed = Self::probe_file(path)
             .unwrap_or_else(|_| {
                 // Fallback to basic metadata if extraction fails
                 let mut basic_metadata = AudioMetadata::new();
                 if let Some(filename) = path.file_stem().and_then(|s| s.to_str()) {
                     basic_metadata.title = Some(filename.to_string());
                 }
-                (basic_metadata, Duration::from_secs(0))
+                ProbedFile { metadata: basic_metadata, ..Default::default() }
             });
 
-        Ok(TrackInfo::new(path.to_path_buf(), metadata, duration, file_size))
+        let mut track = TrackInfo::new(path.to_path_buf(), probed.metadata, probed.duration, file_size);
+        track.codec = codec;
+        track.sample_rate = probed.sample_rate;
+        track.bit_depth = probed.bit_depth;
+        track.channels = probed.channels;
+        Ok(track)
+    }
+
+    /// Queue entries for a file: one per track of a sidecar CUE sheet, otherwise just the file
+    pub fn tracks_for_file(path: &Path) -> Result<Vec<TrackInfo>, QueueError> {
+        let track = Self::create_track_info(path)?;
+
+        if let Some(cue_path) = CueSheet::find_for(path) {
+            match CueSheet::load(&cue_path) {
+                Ok(sheet) => {
+                    let tracks = sheet.split(&track);
+                    if !tracks.is_empty() {
+                        return Ok(tracks);
+                    }
+                }
+                Err(e) => log::warn!("Ignoring CUE sheet '{}': {}", cue_path.display(), e),
+            }
+        }
+
+        Ok(vec![track])
+    }
+
+    /// Move to the following CUE track once playback of a shared file reaches its start
+    ///
+    /// `position` is the playback position within the current file. Returns the new
+    /// current track when the index moved.
+    pub fn advance_within_file(&mut self, position: Duration) -> Option<&TrackInfo> {
+        let start_index = self.current_index;
+        loop {
+            let current = self.current_queue.get(self.current_index)?;
+            let next = self.current_queue.get(self.current_index + 1);
+            match (current.end_offset, next) {
+                (Some(end), Some(next)) if position >= end && next.continues(current) => {
+                    self.current_index += 1;
+                }
+                _ => break,
+            }
+        }
+        if self.current_index == start_index {
+            return None;
+        }
+        self.current_queue.get(self.current_index)
+    }
+
+    /// Index of the next entry that needs its file opened, skipping CUE tracks that
+    /// play on from the current entry's file
+    pub fn next_file_index(&self) -> Option<usize> {
+        let mut index = self.current_index;
+        loop {
+            let current = self.current_queue.get(index)?;
+            let next = self.current_queue.get(index + 1)?;
+            if !next.continues(current) {
+                return Some(index + 1);
+            }
+            index += 1;
+        }
+    }
+
+    /// Flag every entry playing `path` as unplayable, or clear the flag; returns how many changed
+    pub fn set_failed(&mut self, path: &Path, failed: bool) -> usize {
+        let mut changed = 0;
+        for track in self.current_queue.iter_mut().filter(|track| track.path == path && track.failed != failed) {
+            track.failed = failed;
+            changed += 1;
+        }
+        changed
+    }
+
+    /// Fill in what playing a stream revealed, such as its length or station title, for every entry of `url`
+    pub fn update_stream(&mut self, url: &Path, update: impl FnMut(&mut TrackInfo)) {
+        self.current_queue.iter_mut().filter(|track| track.path == url).for_each(update);
+    }
+
+    /// Read the tags of `path` again for every entry playing it; returns how many were updated
+    ///
+    /// CUE sheet tracks keep the titles from their sheet. The content hash of a `--verify`
+    /// entry no longer matches an edited file and is dropped.
+    pub fn refresh_file(&mut self, path: &Path) -> Result<usize, QueueError> {
+        let fresh = Self::create_track_info(path)?;
+        Ok(self.update_file(path, &fresh))
+    }
+
+    /// `refresh_file` with tags already read into `fresh`, so callers can probe without holding the queue
+    pub fn update_file(&mut self, path: &Path, fresh: &TrackInfo) -> usize {
+        let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let mut updated = 0;
+        for track in self.current_queue.iter_mut() {
+            let same_file = track.path == path
+                || (track.path.file_name() == path.file_name() && fs::canonicalize(&track.path).is_ok_and(|other| other == target));
+            if !same_file {
+                continue;
+            }
+            if track.start_offset.is_zero() && track.end_offset.is_none() {
+                track.metadata = fresh.metadata.clone();
+            }
+            track.file_size = fresh.file_size;
+            track.content_hash = None;
+            updated += 1;
+        }
+        updated
+    }
+
+    /// Queue tracks that were read beforehand, e.g. by `tracks_for_file`
+    pub fn append_tracks(&mut self, tracks: Vec<TrackInfo>) {
+        self.current_queue.extend(tracks);
+    }
+
+    /// Remove the entries of `path` and of files below it; the current entry stays when `keep_current`
+    pub fn remove_under(&mut self, path: &Path, keep_current: bool) -> Vec<TrackInfo> {
+        let indices: Vec<usize> = self.current_queue.iter().enumerate()
+            .filter(|(index, track)| track.path.starts_with(path) && !(keep_current && *index == self.current_index))
+            .map(|(index, _)| index)
+            .collect();
+        self.remove_indices(&indices)
+    }
+
+    /// Stable-sort the queue; the current track keeps playing and its index follows it
+    ///
+    /// Tracks without a disc number sort with disc 1, and tracks without a track
+    /// number go after the numbered ones on their disc. Tag-based keys put
+    /// untagged tracks last, ordered by file name.
+    pub fn sort_by(&mut self, key: SortKey) {
+        let mut entries: Vec<(usize, TrackInfo)> = self.current_queue.drain(..).enumerate().collect();
+        match key {
+            SortKey::Artist => entries.sort_by_cached_key(|(_, track)| (
+                tag_sort_key(track.metadata.artist.as_deref()),
+                track.display_name().to_lowercase(),
+            )),
+            SortKey::Album => entries.sort_by_cached_key(|(_, track)| {
+                let metadata = &track.metadata;
+                (
+                    tag_sort_key(metadata.album_artist.as_deref().or(metadata.artist.as_deref())),
+                    tag_sort_key(metadata.album.as_deref()),
+                    metadata.disc_number.unwrap_or(1),
+                    metadata.track_number.unwrap_or(u32::MAX),
+                    file_name_sort_key(track),
+                )
+            }),
+            SortKey::Title => entries.sort_by_cached_key(|(_, track)| track.display_name().to_lowercase()),
+            SortKey::Track => entries.sort_by_cached_key(|(_, track)| (
+                track.metadata.track_number.unwrap_or(u32::MAX),
+                file_name_sort_key(track),
+            )),
+            SortKey::Path => entries.sort_by(|(_, a), (_, b)| a.path.cmp(&b.path)),
+            SortKey::Duration => entries.sort_by_key(|(_, track)| track.duration),
+            SortKey::Random => entries.shuffle(&mut SmallRng::seed_from_u64(rand::random())),
+            SortKey::Disc => entries.sort_by_key(|(_, track)| (
+                track.metadata.disc_number.unwrap_or(1),
+                track.metadata.track_number.unwrap_or(u32::MAX),
+            )),
+            SortKey::Bpm => entries.sort_by(|(_, a), (_, b)| match (a.metadata.bpm, b.metadata.bpm) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                (a, b) => a.is_none().cmp(&b.is_none()),
+            }),
+        }
+        self.restore_order(entries);
+    }
+
+    /// Put reordered `(original index, track)` entries back, keeping the current track current
+    fn restore_order(&mut self, entries: Vec<(usize, TrackInfo)>) {
+        let current = self.current_index;
+        self.current_index = entries.iter().position(|(index, _)| *index == current).unwrap_or(0);
+        self.current_queue = entries.into_iter().map(|(_, track)| track).collect();
+    }
+
+    /// Insert a file's tracks before `index` (0-based, `len()` appends); returns how many were added
+    pub fn insert_file(&mut self, path: &Path, index: usize) -> Result<usize, QueueError> {
+        self.check_insert_index(index)?;
+        let tracks = Self::tracks_for_file(path)?;
+        Ok(self.insert_tracks(index, tracks))
+    }
+
+    /// Insert the audio files of a directory before `index`, skipping files that fail to load
+    pub fn insert_directory(&mut self, path: &Path, index: usize) -> Result<usize, QueueError> {
+        self.check_insert_index(index)?;
+        let tracks = self.directory_tracks(path, &DirectoryAddOptions::default())?;
+        Ok(self.insert_tracks(index, tracks))
+    }
+
+    /// Leave out files that are already queued when adding a directory
+    pub fn set_skip_duplicates(&mut self, skip: bool) {
+        self.skip_duplicates = skip;
+    }
+
+    /// Follow symbolic links when scanning directories
+    pub fn set_follow_symlinks(&mut self, follow: bool) {
+        self.follow_symlinks = follow;
+    }
+
+    /// Skip subdirectories containing a file with one of these names when scanning
+    pub fn set_nomusic_files(&mut self, names: Vec<String>) {
+        self.nomusic_files = names;
+    }
+
+    /// Remove repeated entries, keeping the first of each; returns how many were removed
+    ///
+    /// Entries are repeats when their paths lead to the same file; CUE sheet tracks of one file
+    /// are told apart by where they start. With `by_metadata`, tracks with the same artist, title
+    /// and length count as repeats too, even in different files. When the current entry is a
+    /// repeat, the first occurrence becomes current.
+    pub fn dedupe(&mut self, by_metadata: bool) -> usize {
+        let mut first_seen: HashMap<DuplicateKey, usize> = HashMap::new();
+        let mut repeats = Vec::new();
+        let mut current_to = None;
+        for (index, track) in self.current_queue.iter().enumerate() {
+            let keys = DuplicateKey::for_track(track, by_metadata);
+            match keys.iter().find_map(|key| first_seen.get(key)) {
+                Some(&first) => {
+                    if index == self.current_index {
+                        current_to = Some(first);
+                    }
+                    repeats.push(index);
+                }
+                None => {
+                    for key in keys {
+                        first_seen.insert(key, index);
+                    }
+                }
+            }
+        }
+        self.remove_entries(&repeats, current_to).len()
+    }
+
+    /// Indices of entries whose file is gone or can no longer be opened; streams are left alone
+    pub fn unreadable_entries(&self) -> Vec<usize> {
+        self.current_queue
+            .iter()
+            .enumerate()
+            .filter(|(_, track)| stream_url(&track.path).is_none() && fs::File::open(&track.path).is_err())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Remove the entries at `indices`; the current track stays selected, or the one after it
+    pub fn remove_indices(&mut self, indices: &[usize]) -> Vec<TrackInfo> {
+        self.remove_entries(indices, None)
+    }
+
+    /// Drop entries, selecting `current_to` (an index before removal) if the current one goes
+    fn remove_entries(&mut self, indices: &[usize], current_to: Option<usize>) -> Vec<TrackInfo> {
+        let doomed: HashSet<usize> = indices.iter().copied().collect();
+        if doomed.is_empty() {
+            return Vec::new();
+        }
+        let target = match current_to {
+            Some(index) if doomed.contains(&self.current_index) => index,
+            _ => self.current_index,
+        };
+        // A removed target hands over to the entry that followed it
+        let new_current = (0..target).filter(|index| !doomed.contains(index)).count();
+
+        let mut removed = Vec::new();
+        for (index, track) in std::mem::take(&mut self.current_queue).into_iter().enumerate() {
+            if doomed.contains(&index) {
+                removed.push(track);
+            } else {
+                self.current_queue.push_back(track);
+            }
+        }
+        self.current_index = new_current.min(self.current_queue.len().saturating_sub(1));
+        removed
+    }
+
+    /// Tracks under `path` that pass `options`, without ones already queued when skipping duplicates
+    fn directory_tracks(&self, path: &Path, options: &DirectoryAddOptions) -> Result<Vec<TrackInfo>, QueueError> {
+        let audio_files = Self::scan_directory(path, options, self.follow_symlinks, &self.nomusic_files)?;
+        let queued: HashSet<&Path> = if self.skip_duplicates {
+            self.current_queue.iter().map(|track| track.path.as_path()).collect()
+        } else {
+            HashSet::new()
+        };
+
+        // Try to add each file, but don't fail the entire operation if one file fails
+        let mut tracks = Vec::new();
+        for file_path in audio_files.iter().filter(|file_path| !queued.contains(file_path.as_path())) {
+            let Ok(file_tracks) = Self::tracks_for_file(file_path) else {
+                continue;
+            };
+            let file_tracks = if options.verify { Self::verified(file_path, file_tracks)? } else { file_tracks };
+            tracks.extend(file_tracks.into_iter().filter(|track| options.accepts_track(track)));
+        }
+        Ok(tracks)
+    }
+
+    /// Add a file after checking its integrity; see `integrity::verify_file`
+    pub fn add_verified_file(&mut self, path: &Path) -> Result<(), QueueError> {
+        let tracks = Self::verified(path, Self::tracks_for_file(path)?)?;
+        self.current_queue.extend(tracks);
+        Ok(())
+    }
+
+    /// Verify `path` and record its hash on the file's tracks
+    fn verified(path: &Path, mut tracks: Vec<TrackInfo>) -> Result<Vec<TrackInfo>, QueueError> {
+        let hash = integrity::verify_file(path)?;
+        for track in &mut tracks {
+            track.content_hash = Some(hash);
+        }
+        Ok(tracks)
+    }
+
+    /// Directory playlists are saved to and loaded from
+    pub fn playlist_directory(&self) -> &Path {
+        self.playlist_manager.directory()
+    }
+
+    /// Save and load playlists in `directory` from now on, creating it if needed
+    pub fn set_playlist_directory(&mut self, directory: PathBuf) -> Result<(), PlaylistError> {
+        self.playlist_manager = PlaylistManager::new(directory)?;
+        Ok(())
+    }
+
+    /// Move the saved playlists into `directory` and use it from now on
+    pub fn move_playlist_directory(&mut self, directory: PathBuf) -> Result<PlaylistMove, PlaylistError> {
+        let target = PlaylistManager::new(directory)?;
+        let outcome = self.playlist_manager.move_playlists(target.directory())?;
+        self.playlist_manager = target;
+        Ok(outcome)
+    }
+
+    /// Save the criteria of smart playlist `name`
+    pub fn save_smart_playlist(&self, name: &str, criteria: &SmartPlaylistCriteria) -> Result<(), PlaylistError> {
+        self.playlist_manager.save_smart_playlist(name, criteria)
+    }
+
+    /// Criteria saved for smart playlist `name`
+    pub fn smart_playlist(&self, name: &str) -> Result<SmartPlaylistCriteria, PlaylistError> {
+        self.playlist_manager.load_smart_playlist(name)
+    }
+
+    /// Audio files under `dir`, found the way `add` finds them
+    pub fn audio_files_in(&self, dir: &Path) -> Result<Vec<PathBuf>, QueueError> {
+        Self::scan_directory(dir, &DirectoryAddOptions::default(), self.follow_symlinks, &self.nomusic_files)
+    }
+
+    /// Files in saved playlist `name`, without loading it into the queue
+    pub fn playlist_files(&self, name: &str) -> Result<Vec<PathBuf>, PlaylistError> {
+        let tracks = self.playlist_manager.load_playlist(name)?;
+        Ok(verify::unique_files(tracks.into_iter().map(|track| track.path)))
+    }
+
+    /// Replace the queue with the tracks under `library` that meet `criteria`; returns how many
+    pub fn load_matching(&mut self, library: &Path, criteria: &SmartPlaylistCriteria) -> Result<usize, QueueError> {
+        let matching: VecDeque<TrackInfo> = Self::scan_directory(library, &DirectoryAddOptions::default(), self.follow_symlinks, &self.nomusic_files)?
+            .iter()
+            .filter_map(|file_path| Self::tracks_for_file(file_path).ok())
+            .flatten()
+            .filter(|track| criteria.matches(track))
+            .collect();
+        self.current_queue = matching;
+        self.current_index = 0;
+        Ok(self.current_queue.len())
+    }
+
+    /// Where tracks go to play right after the current one
+    pub fn play_next_index(&self) -> usize {
+        if self.current_queue.is_empty() { 0 } else { self.current_index + 1 }
+    }
+
+    fn check_insert_index(&self, index: usize) -> Result<(), QueueError> {
+        if index > self.current_queue.len() {
+            return Err(QueueError::InvalidIndex { index });
+        }
+        Ok(())
+    }
+
+    fn insert_tracks(&mut self, index: usize, tracks: Vec<TrackInfo>) -> usize {
+        let count = tracks.len();
+        // Tracks placed at or before the current one push it back
+        if !self.current_queue.is_empty() && index <= self.current_index {
+            self.current_index += count;
+        }
+        let mut tail = self.current_queue.split_off(index);
+        self.current_queue.extend(tracks);
+        self.current_queue.append(&mut tail);
+        count
     }
 
     /// Extract metadata and duration using symphonia
-    fn extract_metadata_and_duration(path: &Path) -> Result<(AudioMetadata, Duration), Box<dyn std::error::Error>> {
+    fn probe_file(path: &Path) -> Result<ProbedFile, Box<dyn std::error::Error>> {
+        // Symphonia has no WavPack reader; the decoder indexes blocks and reads the APEv2 tag
+        if path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ext.eq_ignore_ascii_case("wv")) {
+            let decoder = WavPackDecoder::new(path)?;
+            return Ok(ProbedFile {
+                metadata: decoder.metadata().clone(),
+                duration: decoder.duration().unwrap_or_default(),
+                sample_rate: Some(decoder.sample_rate()),
+                bit_depth: Some(decoder.bit_depth()),
+                channels: Some(decoder.channels()),
+            });
+        }
+
         let file = std::fs::File::open(path)?;
         let mss = MediaSourceStream::new(Box::new(file), Default::default());
 
@@ -181,18 +748,29 @@ impl QueueManagerImpl {
         let mut metadata = AudioMetadata::new();
         let mut duration = Duration::from_secs(0);
 
+        let is_mp3 = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("mp3"));
+
+        // MP3 keeps its ID3v2 tag ahead of the stream; the decoder parses the full tag
+        if is_mp3 {
+            if let Ok(decoder) = Mp3Decoder::new(path) {
+                metadata = decoder.metadata().clone();
+            }
+        }
+
         // Extract metadata from the format
-        if let Some(metadata_rev) = format.metadata().current() {
+        if let Some(metadata_rev) = format.metadata().current().filter(|_| metadata.is_empty()) {
             for tag in metadata_rev.tags() {
+                // Track/disc numbers and totals arrive under several container-specific keys
+                if let Some(std_key) = tag.std_key {
+                    MetadataExtractor::apply_common_tag(std_key, &tag.value, &mut metadata);
+                }
                 match tag.key.as_str() {
                     "TITLE" | "TIT2" => metadata.title = Some(tag.value.to_string()),
                     "ARTIST" | "TPE1" => metadata.artist = Some(tag.value.to_string()),
                     "ALBUM" | "TALB" => metadata.album = Some(tag.value.to_string()),
-                    "TRACKNUMBER" | "TRCK" => {
-                        if let Ok(track_num) = tag.value.to_string().parse::<u32>() {
-                            metadata.track_number = Some(track_num);
-                        }
-                    }
                     "DATE" | "YEAR" | "TYER" => {
                         if let Ok(year) = tag.value.to_string().parse::<u32>() {
                             metadata.year = Some(year);
@@ -204,7 +782,10 @@ impl QueueManagerImpl {
             }
         }
 
-        // Try to get duration from the first track
+        // Try to get duration and stream format from the first track
+        let mut sample_rate = None;
+        let mut bit_depth = None;
+        let mut channels = None;
         if let Some(track) = format.tracks().first() {
             if let Some(time_base) = track.codec_params.time_base {
                 if let Some(n_frames) = track.codec_params.n_frames {
@@ -212,49 +793,122 @@ impl QueueManagerImpl {
                     duration = Duration::from_secs_f64(seconds);
                 }
             }
+            sample_rate = track.codec_params.sample_rate;
+            bit_depth = track.codec_params.bits_per_sample.and_then(|bits| u16::try_from(bits).ok());
+            channels = track.codec_params.channels.map(|channels| channels.count() as u16);
         }
 
-        Ok((metadata, duration))
+        Ok(ProbedFile { metadata, duration, sample_rate, bit_depth, channels })
     }
 
-    /// Recursively scan directory for audio files
-    fn scan_directory(dir: &Path) -> Result<Vec<PathBuf>, QueueError> {
-        let mut audio_files = Vec::new();
-
+    /// Scan a directory for audio files, descending as deep as `options` allows
+    ///
+    /// Subdirectories holding one of the `nomusic_files` are skipped along with everything below them.
+    fn scan_directory(
+        dir: &Path,
+        options: &DirectoryAddOptions,
+        follow_symlinks: bool,
+        nomusic_files: &[String],
+    ) -> Result<Vec<PathBuf>, QueueError> {
         if !dir.is_dir() {
-            return Err(QueueError::FileNotFound {
-                path: dir.to_string_lossy().to_string(),
-            });
+            return Err(QueueError::FileNotFound { path: dir.to_path_buf() });
         }
+        let entries = fs::read_dir(dir).map_err(|_| QueueError::FileNotFound { path: dir.to_path_buf() })?;
+
+        let mut scan = DirectoryScan {
+            options,
+            follow_symlinks,
+            nomusic_files,
+            visited: HashSet::new(),
+            audio_files: Vec::new(),
+        };
+        if let Ok(canonical) = dir.canonicalize() {
+            scan.visited.insert(canonical);
+        }
+        scan.scan_entries(entries, 0);
 
-        let entries = fs::read_dir(dir).map_err(|_| QueueError::FileNotFound {
-            path: dir.to_string_lossy().to_string(),
-        })?;
+        // Sort files for consistent ordering
+        scan.audio_files.sort();
+        Ok(scan.audio_files)
+    }
+}
 
-        for entry in entries {
-            let entry = entry.map_err(|_| QueueError::FileNotFound {
-                path: dir.to_string_lossy().to_string(),
-            })?;
+/// One directory scan: the files found so far and the directories already walked
+struct DirectoryScan<'a> {
+    options: &'a DirectoryAddOptions,
+    follow_symlinks: bool,
+    nomusic_files: &'a [String],
+    /// Canonical paths of scanned directories, so a link back up the tree is not walked again
+    visited: HashSet<PathBuf>,
+    audio_files: Vec<PathBuf>,
+}
 
+impl DirectoryScan<'_> {
+    fn scan_entries(&mut self, entries: fs::ReadDir, depth: usize) {
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    log::warn!("Skipping unreadable directory entry: {}", e);
+                    continue;
+                }
+            };
             let path = entry.path();
 
-            if path.is_dir() {
-                // Recursively scan subdirectories
-                let mut sub_files = Self::scan_directory(&path)?;
-                audio_files.append(&mut sub_files);
-            } else if path.is_file() {
+            let Ok(mut file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_symlink() {
+                if !self.follow_symlinks {
+                    continue;
+                }
+                match fs::metadata(&path) {
+                    Ok(target) => file_type = target.file_type(),
+                    Err(e) => {
+                        log::debug!("Skipping broken symlink '{}': {}", path.display(), e);
+                        continue;
+                    }
+                }
+            }
+
+            if file_type.is_dir() {
+                if self.options.max_depth.is_none_or(|max_depth| depth < max_depth) {
+                    self.scan_subdirectory(&path, depth + 1);
+                }
+            } else if file_type.is_file() {
                 // Check if it's a supported audio file
                 if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
-                    if Self::is_supported_format(extension) {
-                        audio_files.push(path);
+                    if QueueManagerImpl::is_supported_format(extension) && self.options.accepts_extension(extension) {
+                        self.audio_files.push(path);
                     }
                 }
             }
         }
+    }
 
-        // Sort files for consistent ordering
-        audio_files.sort();
-        Ok(audio_files)
+    /// Scan a subdirectory; unreadable ones are skipped rather than failing the whole scan
+    fn scan_subdirectory(&mut self, dir: &Path, depth: usize) {
+        if let Some(marker) = self.nomusic_files.iter().find(|name| dir.join(name).exists()) {
+            log::debug!("Skipping '{}': it contains {}", dir.display(), marker);
+            return;
+        }
+        match dir.canonicalize() {
+            Ok(canonical) => {
+                if !self.visited.insert(canonical) {
+                    log::debug!("Skipping '{}': directory already scanned", dir.display());
+                    return;
+                }
+            }
+            Err(e) => {
+                log::warn!("Skipping '{}': {}", dir.display(), e);
+                return;
+            }
+        }
+
+        match fs::read_dir(dir) {
+            Ok(entries) => self.scan_entries(entries, depth),
+            Err(e) => log::warn!("Skipping '{}': {}", dir.display(), e),
+        }
     }
 }
 
@@ -266,21 +920,14 @@ impl Default for QueueManagerImpl {
 
 impl QueueManager for QueueManagerImpl {
     fn add_file(&mut self, path: &Path) -> Result<(), QueueError> {
-        let track_info = Self::create_track_info(path)?;
-        self.current_queue.push_back(track_info);
+        let tracks = Self::tracks_for_file(path)?;
+        self.current_queue.extend(tracks);
         Ok(())
     }
 
-    fn add_directory(&mut self, path: &Path) -> Result<(), QueueError> {
-        let audio_files = Self::scan_directory(path)?;
-        
-        for file_path in audio_files {
-            // Try to add each file, but don't fail the entire operation if one file fails
-            if let Ok(track_info) = Self::create_track_info(&file_path) {
-                self.current_queue.push_back(track_info);
-            }
-        }
-        
+    fn add_directory(&mut self, path: &Path, options: &DirectoryAddOptions) -> Result<(), QueueError> {
+        let tracks = self.directory_tracks(path, options)?;
+        self.current_queue.extend(tracks);
         Ok(())
     }
 
@@ -371,8 +1018,8 @@ impl QueueManager for QueueManagerImpl {
         Ok(self.current_queue.get(self.current_index).unwrap())
     }
     
-    fn save_playlist(&self, name: &str, format: PlaylistFormat) -> Result<(), PlaylistError> {
-        self.playlist_manager.save_playlist(name, &self.current_queue, format)
+    fn save_playlist(&self, name: &str, format: PlaylistFormat, options: &PlaylistSaveOptions) -> Result<(), PlaylistError> {
+        self.playlist_manager.save_playlist(name, &self.current_queue, format, options)
     }
     
     fn load_playlist(&mut self, name: &str) -> Result<(), PlaylistError> {
@@ -389,10 +1036,24 @@ impl QueueManager for QueueManagerImpl {
     fn delete_playlist(&self, name: &str) -> Result<(), PlaylistError> {
         self.playlist_manager.delete_playlist(name)
     }
+
+    fn rename_playlist(&self, old_name: &str, new_name: &str) -> Result<(), PlaylistError> {
+        self.playlist_manager.rename_playlist(old_name, new_name)
+    }
+
+    fn merge_playlists(&self, names: &[String], output: &str) -> Result<usize, PlaylistError> {
+        self.playlist_manager.merge_playlists(names, output)
+    }
+
+    fn shuffle_tracks(&mut self, seed: u64) {
+        let mut entries: Vec<(usize, TrackInfo)> = self.current_queue.drain(..).enumerate().collect();
+        entries.shuffle(&mut SmallRng::seed_from_u64(seed));
+        self.restore_order(entries);
+    }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use std::fs;
     use tempfile::TempDir;
@@ -446,6 +1107,7 @@ mod tests {
         assert!(QueueManagerImpl::is_supported_format("alac"));
         assert!(QueueManagerImpl::is_supported_format("ogg"));
         assert!(QueueManagerImpl::is_supported_format("oga"));
+        assert!(QueueManagerImpl::is_supported_format("wv"));
 
         assert!(!QueueManagerImpl::is_supported_format("txt"));
         assert!(!QueueManagerImpl::is_supported_format("pdf"));
@@ -464,6 +1126,7 @@ mod tests {
         assert_eq!(QueueManagerImpl::codec_from_extension("alac"), Some(AudioCodec::Alac));
         assert_eq!(QueueManagerImpl::codec_from_extension("ogg"), Some(AudioCodec::OggVorbis));
         assert_eq!(QueueManagerImpl::codec_from_extension("oga"), Some(AudioCodec::OggVorbis));
+        assert_eq!(QueueManagerImpl::codec_from_extension("WV"), Some(AudioCodec::WavPack));
 
         assert_eq!(QueueManagerImpl::codec_from_extension("txt"), None);
         assert_eq!(QueueManagerImpl::codec_from_extension(""), None);
@@ -478,7 +1141,7 @@ mod tests {
         assert!(result.is_err());
         match result.unwrap_err() {
             QueueError::FileNotFound { path } => {
-                assert!(path.contains("nonexistent"));
+                assert!(path.starts_with("/nonexistent"));
             }
             _ => panic!("Expected FileNotFound error"),
         }
@@ -496,7 +1159,7 @@ mod tests {
         assert!(result.is_err());
         match result.unwrap_err() {
             QueueError::InvalidFormat { path } => {
-                assert!(path.contains("test.txt"));
+                assert_eq!(path, txt_file);
             }
             _ => panic!("Expected InvalidFormat error"),
         }
@@ -518,12 +1181,163 @@ mod tests {
         assert_eq!(track.path, audio_file);
     }
 
+    #[test]
+    fn test_set_and_move_playlist_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let audio_file = create_test_audio_file(temp_dir.path(), "a", "flac");
+        let first = temp_dir.path().join("first");
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(first.clone()).unwrap();
+        queue_manager.add_file(&audio_file).unwrap();
+        queue_manager.save_playlist("mix", PlaylistFormat::M3u, &PlaylistSaveOptions::default()).unwrap();
+
+        queue_manager.set_playlist_directory(temp_dir.path().join("second")).unwrap();
+        assert_eq!(queue_manager.playlist_directory(), temp_dir.path().join("second"));
+        assert!(queue_manager.list_playlists().unwrap().is_empty());
+
+        queue_manager.set_playlist_directory(first.clone()).unwrap();
+        let outcome = queue_manager.move_playlist_directory(temp_dir.path().join("third")).unwrap();
+        assert_eq!(outcome.moved, 1);
+        assert_eq!(queue_manager.list_playlists().unwrap(), vec!["mix".to_string()]);
+        assert!(!first.join("mix.m3u").exists());
+        queue_manager.load_playlist("mix").unwrap();
+        assert_eq!(queue_manager.len(), 1);
+    }
+
+    #[test]
+    fn test_add_file_detects_format_from_contents() {
+        let temp_dir = TempDir::new().unwrap();
+        let renamed = temp_dir.path().join("track.bin");
+        fs::write(&renamed, flac_with_comments(&["TITLE=Renamed"])).unwrap();
+        let unnamed = temp_dir.path().join("track");
+        fs::write(&unnamed, b"OggS\x00\x02\x00\x00\x00\x00\x00\x00").unwrap();
+
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        queue_manager.add_file(&renamed).unwrap();
+        queue_manager.add_file(&unnamed).unwrap();
+
+        let tracks = queue_manager.list();
+        assert_eq!(tracks[0].codec, Some(AudioCodec::Flac));
+        assert_eq!(tracks[0].metadata.title.as_deref(), Some("Renamed"));
+        assert_eq!(tracks[1].codec, Some(AudioCodec::OggVorbis));
+    }
+
+    /// Minimal FLAC stream: STREAMINFO, a Vorbis comment block and one silent 4096-sample frame
+    pub(crate) fn flac_with_comments(comments: &[&str]) -> Vec<u8> {
+        let mut streaminfo = Vec::new();
+        streaminfo.extend_from_slice(&4096u16.to_be_bytes());
+        streaminfo.extend_from_slice(&4096u16.to_be_bytes());
+        streaminfo.extend_from_slice(&[0; 6]);
+        // 44.1 kHz, 2 channels, 16 bits, 4096 samples
+        let packed = (44100u64 << 44) | (1 << 41) | (15 << 36) | 4096;
+        streaminfo.extend_from_slice(&packed.to_be_bytes());
+        streaminfo.extend_from_slice(&[0; 16]);
+
+        let mut vorbis = Vec::new();
+        let vendor = b"test";
+        vorbis.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        vorbis.extend_from_slice(vendor);
+        vorbis.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            vorbis.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            vorbis.extend_from_slice(comment.as_bytes());
+        }
+
+        let mut bytes = b"fLaC".to_vec();
+        for (block_type, block) in [(0u8, &streaminfo), (0x84, &vorbis)] {
+            bytes.push(block_type);
+            bytes.extend_from_slice(&(block.len() as u32).to_be_bytes()[1..]);
+            bytes.extend_from_slice(block);
+        }
+        // Frame header with CRC-8, two constant zero subframes, CRC-16
+        bytes.extend_from_slice(&[
+            0xFF, 0xF8, 0xC9, 0x18, 0x00, 0xC2, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xB8, 0xEE,
+        ]);
+        bytes
+    }
+
+    #[test]
+    fn test_flac_disc_and_track_totals() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("disc2.flac");
+        fs::write(&path, flac_with_comments(&[
+            "TITLE=Second Disc Opener",
+            "TRACKNUMBER=1",
+            "TOTALTRACKS=12",
+            "DISCNUMBER=2",
+            "TOTALDISCS=3",
+            "COMPOSER=Test Composer",
+        ])).unwrap();
+
+        let track = QueueManagerImpl::create_track_info(&path).unwrap();
+        assert_eq!(track.metadata.title.as_deref(), Some("Second Disc Opener"));
+        assert_eq!(track.metadata.track_number, Some(1));
+        assert_eq!(track.metadata.total_tracks, Some(12));
+        assert_eq!(track.metadata.disc_number, Some(2));
+        assert_eq!(track.metadata.total_discs, Some(3));
+        assert_eq!(track.metadata.composer.as_deref(), Some("Test Composer"));
+        assert_eq!(track.metadata.bpm, None);
+    }
+
+    #[test]
+    fn test_refresh_file_rereads_edited_tags() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("song.flac");
+        fs::write(&path, flac_with_comments(&["TITLE=Old Title", "ARTIST=Someone"])).unwrap();
+        let other = temp_dir.path().join("other.flac");
+        fs::write(&other, flac_with_comments(&["TITLE=Other"])).unwrap();
+
+        let mut queue_manager = QueueManagerImpl::new();
+        queue_manager.add_file(&path).unwrap();
+        queue_manager.add_file(&other).unwrap();
+        queue_manager.add_file(&path).unwrap();
+
+        super::tags::write_comments(&path, &[("TITLE", "New Title".to_string())]).unwrap();
+        assert_eq!(queue_manager.refresh_file(&path).unwrap(), 2);
+
+        let titles: Vec<_> = queue_manager.list().iter().map(|track| track.metadata.title.clone().unwrap()).collect();
+        assert_eq!(titles, vec!["New Title", "Other", "New Title"]);
+        assert_eq!(queue_manager.list()[0].metadata.artist.as_deref(), Some("Someone"));
+        assert!(queue_manager.refresh_file(&temp_dir.path().join("missing.flac")).is_err());
+    }
+
+    #[test]
+    fn test_remove_under_keeps_the_current_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        let album = temp_dir.path().join("album");
+        fs::create_dir(&album).unwrap();
+        let mut queue_manager = QueueManagerImpl::new();
+        for path in [album.join("01.flac"), temp_dir.path().join("single.flac"), album.join("02.flac")] {
+            fs::write(&path, flac_with_comments(&[])).unwrap();
+            queue_manager.add_file(&path).unwrap();
+        }
+        queue_manager.jump_to(2).unwrap();
+
+        let removed = queue_manager.remove_under(&album, true);
+        assert_eq!(removed.iter().map(|track| track.path.clone()).collect::<Vec<_>>(), vec![album.join("01.flac")]);
+        assert_eq!(queue_manager.current_track().unwrap().path, album.join("02.flac"));
+
+        assert_eq!(queue_manager.remove_under(&album.join("02.flac"), false).len(), 1);
+        assert_eq!(queue_manager.len(), 1);
+        // Only whole path components match
+        assert!(queue_manager.remove_under(&temp_dir.path().join("single"), false).is_empty());
+    }
+
+    #[test]
+    fn test_flac_bpm_tag() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("tempo.flac");
+        fs::write(&path, flac_with_comments(&["TITLE=Tempo", "BPM=128.5"])).unwrap();
+
+        let track = QueueManagerImpl::create_track_info(&path).unwrap();
+        assert_eq!(track.metadata.bpm, Some(128.5));
+    }
+
     #[test]
     fn test_add_directory_success() {
         let temp_dir = create_test_directory_structure();
         let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
         
-        let result = queue_manager.add_directory(temp_dir.path());
+        let result = queue_manager.add_directory(temp_dir.path(), &DirectoryAddOptions::default());
         assert!(result.is_ok());
         
         // Should have found 5 audio files (3 in root + 2 in subdir)
@@ -541,16 +1355,102 @@ mod tests {
         assert_eq!(file_names.len(), 5);
     }
 
+    /// Files named for what each filter should do with them
+    fn create_filter_test_tree() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("one/two")).unwrap();
+        write_wav_seconds(&root.join("a-long.wav"), 40);
+        write_wav_seconds(&root.join("b-short.wav"), 2);
+        create_test_audio_file(root, "c-unprobed", "flac");
+        write_wav_seconds(&root.join("one/d-long.wav"), 40);
+        write_wav_seconds(&root.join("one/two/e-long.wav"), 40);
+        create_test_audio_file(&root.join("one/two"), "f-unprobed", "mp3");
+        temp_dir
+    }
+
+    fn added_names(root: &Path, options: DirectoryAddOptions) -> Vec<String> {
+        let playlist_dir = TempDir::new().unwrap();
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(playlist_dir.path().to_path_buf()).unwrap();
+        queue_manager.add_directory(root, &options).unwrap();
+        queue_manager.list().iter()
+            .map(|track| track.path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_add_directory_filters() {
+        let temp_dir = create_filter_test_tree();
+        let root = temp_dir.path();
+
+        assert_eq!(added_names(root, DirectoryAddOptions::default()).len(), 6);
+        assert_eq!(
+            added_names(root, DirectoryAddOptions { max_depth: Some(0), ..Default::default() }),
+            vec!["a-long.wav", "b-short.wav", "c-unprobed.flac"]
+        );
+        assert_eq!(
+            added_names(root, DirectoryAddOptions { max_depth: Some(1), ..Default::default() }),
+            vec!["a-long.wav", "b-short.wav", "c-unprobed.flac", "d-long.wav"]
+        );
+        assert_eq!(
+            added_names(root, DirectoryAddOptions { extensions: vec!["MP3".to_string(), ".flac".to_string()], ..Default::default() }),
+            vec!["c-unprobed.flac", "f-unprobed.mp3"]
+        );
+        // Files whose duration cannot be probed are left out along with the short one
+        assert_eq!(
+            added_names(root, DirectoryAddOptions { min_duration: Some(Duration::from_secs(30)), ..Default::default() }),
+            vec!["a-long.wav", "d-long.wav", "e-long.wav"]
+        );
+    }
+
+    #[test]
+    fn test_add_directory_filters_combined() {
+        let temp_dir = create_filter_test_tree();
+        let options = DirectoryAddOptions {
+            max_depth: Some(1),
+            extensions: vec!["wav".to_string()],
+            min_duration: Some(Duration::from_secs(30)),
+            ..Default::default()
+        };
+
+        assert_eq!(added_names(temp_dir.path(), options), vec!["a-long.wav", "d-long.wav"]);
+    }
+
+    #[test]
+    fn test_add_directory_verified() {
+        let temp_dir = TempDir::new().unwrap();
+        let song = temp_dir.path().join("song.wav");
+        write_wav_seconds(&song, 1);
+        let hash = integrity::sha256_file(&song).unwrap();
+        let options = DirectoryAddOptions { verify: true, ..Default::default() };
+
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().join("playlists")).unwrap();
+        queue_manager.add_directory(temp_dir.path(), &options).unwrap();
+        assert_eq!(queue_manager.list()[0].content_hash, Some(hash));
+
+        // A sidecar from before the file was damaged
+        fs::write(temp_dir.path().join("song.wav.sha256"), format!("{}  song.wav\n", integrity::to_hex(&hash))).unwrap();
+        let mut bytes = fs::read(&song).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        fs::write(&song, bytes).unwrap();
+
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().join("playlists")).unwrap();
+        let result = queue_manager.add_directory(temp_dir.path(), &options);
+        assert!(matches!(result, Err(QueueError::CorruptedFile { .. })));
+        assert!(queue_manager.is_empty());
+    }
+
     #[test]
     fn test_add_directory_nonexistent() {
         let temp_dir = TempDir::new().unwrap();
         let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
-        let result = queue_manager.add_directory(Path::new("/nonexistent/directory"));
+        let result = queue_manager.add_directory(Path::new("/nonexistent/directory"), &DirectoryAddOptions::default());
         
         assert!(result.is_err());
         match result.unwrap_err() {
             QueueError::FileNotFound { path } => {
-                assert!(path.contains("nonexistent"));
+                assert!(path.starts_with("/nonexistent"));
             }
             _ => panic!("Expected FileNotFound error"),
         }
@@ -598,6 +1498,222 @@ mod tests {
         assert_eq!(queue_manager.current_index(), 1);
     }
 
+    #[test]
+    fn test_sort_by_disc_keeps_current_track() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        for (name, disc, track) in [("a", Some(2), Some(1)), ("b", Some(1), Some(2)), ("c", None, None), ("d", Some(1), Some(1))] {
+            let mut metadata = AudioMetadata::new();
+            metadata.disc_number = disc;
+            metadata.track_number = track;
+            queue_manager.current_queue.push_back(TrackInfo::new(PathBuf::from(name), metadata, Duration::ZERO, 0));
+        }
+        queue_manager.jump_to(1).unwrap();
+
+        queue_manager.sort_by(SortKey::Disc);
+
+        let order: Vec<_> = queue_manager.list().iter().map(|track| track.path.to_str().unwrap()).collect();
+        assert_eq!(order, vec!["d", "b", "c", "a"]);
+        assert_eq!(queue_manager.current_track().unwrap().path, PathBuf::from("b"));
+        assert_eq!("DISC".parse::<SortKey>(), Ok(SortKey::Disc));
+        assert!("colour".parse::<SortKey>().is_err());
+    }
+
+    fn sortable_track(file: &str, artist: Option<&str>, album: Option<&str>, disc: Option<u32>, track: Option<u32>) -> TrackInfo {
+        let mut metadata = AudioMetadata::new();
+        metadata.artist = artist.map(str::to_string);
+        metadata.album = album.map(str::to_string);
+        metadata.disc_number = disc;
+        metadata.track_number = track;
+        TrackInfo::new(PathBuf::from("/music").join(file), metadata, Duration::ZERO, 0)
+    }
+
+    fn queue_order(queue_manager: &QueueManagerImpl) -> Vec<String> {
+        queue_manager.list().iter().map(file_name_sort_key).collect()
+    }
+
+    #[test]
+    fn test_sort_by_album_is_hierarchical_with_file_name_fallback() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        let mut compilation_track = sortable_track("comp-2.flac", Some("Miles Davis"), Some("Jazz Hits"), None, Some(2));
+        compilation_track.metadata.album_artist = Some("Various Artists".to_string());
+        for track in [
+            sortable_track("b-untagged.flac", None, None, None, None),
+            sortable_track("wall-2-1.flac", Some("Pink Floyd"), Some("The Wall"), Some(2), Some(1)),
+            compilation_track,
+            sortable_track("a-untagged.flac", None, None, None, None),
+            sortable_track("wall-1-2.flac", Some("pink floyd"), Some("The Wall"), Some(1), Some(2)),
+            sortable_track("animals.flac", Some("Pink Floyd"), Some("Animals"), None, Some(1)),
+            sortable_track("wall-1-x.flac", Some("Pink Floyd"), Some("The Wall"), Some(1), None),
+            sortable_track("wall-1-1.flac", Some("Pink Floyd"), Some("The Wall"), Some(1), Some(1)),
+        ] {
+            queue_manager.current_queue.push_back(track);
+        }
+        queue_manager.jump_to(3).unwrap();
+
+        queue_manager.sort_by(SortKey::Album);
+
+        assert_eq!(queue_order(&queue_manager), vec![
+            "animals.flac", "wall-1-1.flac", "wall-1-2.flac", "wall-1-x.flac", "wall-2-1.flac",
+            "comp-2.flac", "a-untagged.flac", "b-untagged.flac",
+        ]);
+        assert_eq!(queue_manager.current_index(), 6);
+        assert_eq!(file_name_sort_key(queue_manager.current_track().unwrap()), "a-untagged.flac");
+    }
+
+    #[test]
+    fn test_sort_by_tag_keys_fall_back_for_untagged_tracks() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        let mut tracks = vec![
+            sortable_track("zebra.flac", None, None, None, None),
+            sortable_track("b.flac", Some("Coltrane"), None, None, Some(2)),
+            sortable_track("a.flac", Some("Armstrong"), None, None, None),
+            sortable_track("c.flac", Some("armstrong"), None, None, Some(1)),
+        ];
+        tracks[1].metadata.title = Some("Naima".to_string());
+        tracks[3].metadata.title = Some("Basin Street".to_string());
+        tracks[0].duration = Duration::from_secs(30);
+        tracks[2].duration = Duration::from_secs(10);
+        queue_manager.current_queue.extend(tracks);
+        queue_manager.jump_to(1).unwrap();
+
+        queue_manager.sort_by(SortKey::Artist);
+        assert_eq!(queue_order(&queue_manager), vec!["a.flac", "c.flac", "b.flac", "zebra.flac"]);
+        queue_manager.sort_by(SortKey::Title);
+        assert_eq!(queue_order(&queue_manager), vec!["a.flac", "c.flac", "b.flac", "zebra.flac"]);
+        queue_manager.sort_by(SortKey::Track);
+        assert_eq!(queue_order(&queue_manager), vec!["c.flac", "b.flac", "a.flac", "zebra.flac"]);
+        queue_manager.sort_by(SortKey::Path);
+        assert_eq!(queue_order(&queue_manager), vec!["a.flac", "b.flac", "c.flac", "zebra.flac"]);
+        // Stable: the equal zero-length tracks keep their path order
+        queue_manager.sort_by(SortKey::Duration);
+        assert_eq!(queue_order(&queue_manager), vec!["b.flac", "c.flac", "a.flac", "zebra.flac"]);
+
+        assert_eq!(file_name_sort_key(queue_manager.current_track().unwrap()), "b.flac");
+        assert_eq!(queue_manager.current_index(), 0);
+        assert_eq!("Album".parse::<SortKey>(), Ok(SortKey::Album));
+    }
+
+    #[test]
+    fn test_sort_random_keeps_tracks_and_current() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        for i in 0..50 {
+            queue_manager.current_queue.push_back(sortable_track(&format!("{:02}.flac", i), None, None, None, None));
+        }
+        queue_manager.jump_to(17).unwrap();
+
+        queue_manager.sort_by(SortKey::Random);
+
+        let mut order = queue_order(&queue_manager);
+        assert_eq!(file_name_sort_key(queue_manager.current_track().unwrap()), "17.flac");
+        order.sort();
+        assert_eq!(order, (0..50).map(|i| format!("{:02}.flac", i)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_shuffle_tracks_is_reproducible_from_the_seed() {
+        let temp_dir = TempDir::new().unwrap();
+        let shuffled = |seed: u64| {
+            let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+            for i in 0..5 {
+                queue_manager.current_queue.push_back(sortable_track(&format!("{}.flac", i), None, None, None, None));
+            }
+            queue_manager.jump_to(2).unwrap();
+            queue_manager.shuffle_tracks(seed);
+            assert_eq!(file_name_sort_key(queue_manager.current_track().unwrap()), "2.flac");
+            queue_order(&queue_manager)
+        };
+
+        assert_eq!(shuffled(42), shuffled(42));
+        assert_ne!(shuffled(42), shuffled(7));
+        let mut order = shuffled(42);
+        order.sort();
+        assert_eq!(order, (0..5).map(|i| format!("{}.flac", i)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_sort_by_bpm_puts_untagged_last() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        for (name, bpm) in [("fast", Some(174.0)), ("untagged", None), ("slow", Some(90.5)), ("mid", Some(128.0))] {
+            let mut metadata = AudioMetadata::new();
+            metadata.bpm = bpm;
+            queue_manager.current_queue.push_back(TrackInfo::new(PathBuf::from(name), metadata, Duration::ZERO, 0));
+        }
+
+        queue_manager.sort_by(SortKey::Bpm);
+
+        let order: Vec<_> = queue_manager.list().iter().map(|track| track.path.to_str().unwrap()).collect();
+        assert_eq!(order, vec!["slow", "mid", "fast", "untagged"]);
+        assert_eq!(queue_manager.current_track().unwrap().path, PathBuf::from("fast"));
+    }
+
+    fn write_cue_sheet(audio_file: &Path) {
+        let sheet = format!(
+            "PERFORMER \"Test Artist\"\nTITLE \"Test Album\"\nFILE \"{}\" WAVE\n\
+             TRACK 01 AUDIO\n  TITLE \"First\"\n  INDEX 01 00:00:00\n\
+             TRACK 02 AUDIO\n  TITLE \"Second\"\n  INDEX 01 03:30:00\n\
+             TRACK 03 AUDIO\n  TITLE \"Third\"\n  INDEX 01 07:15:40\n",
+            audio_file.file_name().unwrap().to_str().unwrap()
+        );
+        fs::write(audio_file.with_extension("cue"), sheet).unwrap();
+    }
+
+    #[test]
+    fn test_add_file_with_cue_sheet() {
+        let temp_dir = TempDir::new().unwrap();
+        let album = create_test_audio_file(temp_dir.path(), "album", "flac");
+        let other = create_test_audio_file(temp_dir.path(), "single", "wav");
+        write_cue_sheet(&album);
+
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        queue_manager.add_file(&album).unwrap();
+        queue_manager.add_file(&other).unwrap();
+        assert_eq!(queue_manager.len(), 4);
+
+        let titles: Vec<_> = queue_manager.list().iter().map(|track| track.display_name()).collect();
+        assert_eq!(titles, vec!["First", "Second", "Third", "single"]);
+
+        let starts: Vec<_> = queue_manager.list().iter().map(|track| track.start_offset).collect();
+        assert_eq!(starts, vec![
+            Duration::ZERO,
+            Duration::from_secs(210),
+            Duration::from_secs(435) + Duration::from_nanos(533_333_333),
+            Duration::ZERO,
+        ]);
+        assert_eq!(queue_manager.list()[1].end_offset, Some(starts[2]));
+
+        // Each virtual track is its own queue entry
+        assert_eq!(queue_manager.current_track().unwrap().display_name(), "First");
+        assert_eq!(queue_manager.next_track().unwrap().display_name(), "Second");
+        assert_eq!(queue_manager.next_track().unwrap().display_name(), "Third");
+        assert_eq!(queue_manager.next_track().unwrap().path, other);
+    }
+
+    #[test]
+    fn test_cue_tracks_follow_file_position() {
+        let temp_dir = TempDir::new().unwrap();
+        let album = create_test_audio_file(temp_dir.path(), "album", "flac");
+        let other = create_test_audio_file(temp_dir.path(), "single", "wav");
+        write_cue_sheet(&album);
+
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        queue_manager.add_file(&album).unwrap();
+        queue_manager.add_file(&other).unwrap();
+
+        // The file ends after the last CUE track, so the next file is the single
+        assert_eq!(queue_manager.next_file_index(), Some(3));
+
+        assert!(queue_manager.advance_within_file(Duration::from_secs(100)).is_none());
+        let track = queue_manager.advance_within_file(Duration::from_secs(500)).unwrap();
+        assert_eq!(track.display_name(), "Third");
+        assert_eq!(queue_manager.current_index(), 2);
+        assert_eq!(queue_manager.next_file_index(), Some(3));
+    }
+
     #[test]
     fn test_queue_navigation_empty() {
         let temp_dir = TempDir::new().unwrap();
@@ -646,6 +1762,252 @@ mod tests {
         assert!(queue_manager.current_track().is_none());
     }
 
+    #[test]
+    fn test_insert_file_keeps_current_track() {
+        let temp_dir = TempDir::new().unwrap();
+        let files: Vec<_> = ["song1", "song2", "song3", "extra1", "extra2"]
+            .iter()
+            .map(|name| create_test_audio_file(temp_dir.path(), name, "flac"))
+            .collect();
+
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        for file in &files[..3] {
+            queue_manager.add_file(file).unwrap();
+        }
+        queue_manager.jump_to(1).unwrap();
+
+        // Play next goes right after the current track
+        let next = queue_manager.play_next_index();
+        assert_eq!(queue_manager.insert_file(&files[3], next).unwrap(), 1);
+        assert_eq!(queue_manager.list()[2].path, files[3]);
+        assert_eq!(queue_manager.current_index(), 1);
+
+        // Inserting ahead of the current track shifts it back
+        queue_manager.insert_file(&files[4], 0).unwrap();
+        assert_eq!(queue_manager.list()[0].path, files[4]);
+        assert_eq!(queue_manager.current_index(), 2);
+        assert_eq!(queue_manager.current_track().unwrap().path, files[1]);
+
+        // Appending at len() is allowed, past it is not
+        let len = queue_manager.len();
+        assert!(queue_manager.insert_file(&files[0], len).is_ok());
+        assert!(matches!(queue_manager.insert_file(&files[0], len + 2), Err(QueueError::InvalidIndex { .. })));
+        assert_eq!(queue_manager.len(), 6);
+    }
+
+    #[test]
+    fn test_insert_directory() {
+        let temp_dir = create_test_directory_structure();
+        let other_dir = TempDir::new().unwrap();
+        let first = create_test_audio_file(other_dir.path(), "first", "flac");
+
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(other_dir.path().to_path_buf()).unwrap();
+        assert_eq!(queue_manager.play_next_index(), 0);
+        queue_manager.add_file(&first).unwrap();
+
+        assert_eq!(queue_manager.insert_directory(temp_dir.path(), 0).unwrap(), 5);
+        assert_eq!(queue_manager.len(), 6);
+        assert_eq!(queue_manager.current_index(), 5);
+        assert_eq!(queue_manager.current_track().unwrap().path, first);
+    }
+
+    fn paths(queue_manager: &QueueManagerImpl) -> Vec<PathBuf> {
+        queue_manager.list().iter().map(|track| track.path.clone()).collect()
+    }
+
+    #[test]
+    fn test_dedupe_before_current() {
+        let temp_dir = TempDir::new().unwrap();
+        let a = create_test_audio_file(temp_dir.path(), "a", "flac");
+        let b = create_test_audio_file(temp_dir.path(), "b", "flac");
+        let c = create_test_audio_file(temp_dir.path(), "c", "flac");
+
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        for file in [&a, &b, &a, &b, &c, &a] {
+            queue_manager.add_file(file).unwrap();
+        }
+        queue_manager.jump_to(4).unwrap();
+
+        assert_eq!(queue_manager.dedupe(false), 3);
+        assert_eq!(paths(&queue_manager), vec![a, b, c.clone()]);
+        assert_eq!(queue_manager.current_index(), 2);
+        assert_eq!(queue_manager.current_track().unwrap().path, c);
+        assert_eq!(queue_manager.dedupe(false), 0);
+    }
+
+    #[test]
+    fn test_dedupe_at_current_selects_first_occurrence() {
+        let temp_dir = TempDir::new().unwrap();
+        let a = create_test_audio_file(temp_dir.path(), "a", "flac");
+        let b = create_test_audio_file(temp_dir.path(), "b", "flac");
+
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        for file in [&a, &b, &a] {
+            queue_manager.add_file(file).unwrap();
+        }
+        queue_manager.jump_to(2).unwrap();
+
+        assert_eq!(queue_manager.dedupe(false), 1);
+        assert_eq!(paths(&queue_manager), vec![a.clone(), b]);
+        assert_eq!(queue_manager.current_index(), 0);
+        assert_eq!(queue_manager.current_track().unwrap().path, a);
+    }
+
+    #[test]
+    fn test_dedupe_keeps_cue_tracks_of_one_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        let mut first = TrackInfo::new(PathBuf::from("/music/live.flac"), AudioMetadata::new(), Duration::from_secs(60), 0);
+        first.end_offset = Some(Duration::from_secs(60));
+        let mut second = first.clone();
+        second.start_offset = Duration::from_secs(60);
+        second.end_offset = None;
+        queue_manager.current_queue.extend([first.clone(), second, first]);
+
+        assert_eq!(queue_manager.dedupe(false), 1);
+        assert_eq!(queue_manager.len(), 2);
+    }
+
+    #[test]
+    fn test_dedupe_same_file_added_three_times() {
+        let temp_dir = TempDir::new().unwrap();
+        let a = create_test_audio_file(temp_dir.path(), "a", "flac");
+        fs::create_dir(temp_dir.path().join("sub")).unwrap();
+        // The same file written three ways
+        let dotted = temp_dir.path().join("sub").join("..").join("a.flac");
+        let current_dir = temp_dir.path().join(".").join("a.flac");
+
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        for file in [&a, &dotted, &current_dir] {
+            queue_manager.add_file(file).unwrap();
+        }
+        queue_manager.jump_to(2).unwrap();
+
+        assert_eq!(queue_manager.dedupe(false), 2);
+        assert_eq!(queue_manager.len(), 1);
+        assert_eq!(queue_manager.current_index(), 0);
+        assert_eq!(queue_manager.current_track().unwrap().path, a);
+    }
+
+    #[test]
+    fn test_dedupe_by_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        let track = |path: &str, artist: Option<&str>, title: Option<&str>, millis: u64| {
+            let mut metadata = AudioMetadata::new();
+            metadata.artist = artist.map(str::to_string);
+            metadata.title = title.map(str::to_string);
+            TrackInfo::new(PathBuf::from(path), metadata, Duration::from_millis(millis), 0)
+        };
+        queue_manager.current_queue.extend([
+            track("/music/flac/song.flac", Some("Nina Simone"), Some("Sinnerman"), 622_400),
+            track("/music/other.flac", Some("Nina Simone"), Some("Feeling Good"), 177_000),
+            track("/music/mp3/song.mp3", Some("nina simone"), Some("Sinnerman "), 622_900),
+            // A different recording: same tags, other length
+            track("/music/live/song.flac", Some("Nina Simone"), Some("Sinnerman"), 650_000),
+            track("/music/untagged-1.wav", None, None, 60_000),
+            track("/music/untagged-2.wav", None, None, 60_000),
+        ]);
+        queue_manager.jump_to(3).unwrap();
+
+        assert_eq!(queue_manager.dedupe(false), 0);
+        assert_eq!(queue_manager.dedupe(true), 1);
+        assert_eq!(
+            paths(&queue_manager),
+            ["/music/flac/song.flac", "/music/other.flac", "/music/live/song.flac", "/music/untagged-1.wav", "/music/untagged-2.wav"]
+                .map(PathBuf::from)
+        );
+        // The current entry moves down with the removal before it
+        assert_eq!(queue_manager.current_index(), 2);
+        assert_eq!(queue_manager.current_track().unwrap().path, PathBuf::from("/music/live/song.flac"));
+    }
+
+    #[test]
+    fn test_unreadable_entries_and_prune() {
+        let temp_dir = TempDir::new().unwrap();
+        let a = create_test_audio_file(temp_dir.path(), "a", "flac");
+        let b = create_test_audio_file(temp_dir.path(), "b", "flac");
+        let c = create_test_audio_file(temp_dir.path(), "c", "flac");
+
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        for file in [&a, &b, &c] {
+            queue_manager.add_file(file).unwrap();
+        }
+        queue_manager.jump_to(1).unwrap();
+        fs::remove_file(&a).unwrap();
+        fs::remove_file(&b).unwrap();
+
+        let missing = queue_manager.unreadable_entries();
+        assert_eq!(missing, vec![0, 1]);
+
+        // The current track is among the removed ones; the one after it takes over
+        let removed = queue_manager.remove_indices(&missing);
+        assert_eq!(removed.len(), 2);
+        assert_eq!(paths(&queue_manager), vec![c]);
+        assert_eq!(queue_manager.current_index(), 0);
+        assert!(queue_manager.unreadable_entries().is_empty());
+    }
+
+    #[test]
+    fn test_skip_duplicates_when_adding_directory() {
+        let temp_dir = create_test_directory_structure();
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+
+        queue_manager.add_directory(&temp_dir.path().join("subdir"), &DirectoryAddOptions::default()).unwrap();
+        assert_eq!(queue_manager.len(), 2);
+
+        queue_manager.set_skip_duplicates(true);
+        queue_manager.add_directory(temp_dir.path(), &DirectoryAddOptions::default()).unwrap();
+        assert_eq!(queue_manager.len(), 5);
+
+        queue_manager.set_skip_duplicates(false);
+        queue_manager.add_directory(&temp_dir.path().join("subdir"), &DirectoryAddOptions::default()).unwrap();
+        assert_eq!(queue_manager.len(), 7);
+    }
+
+    /// A short silent PCM WAV file in the given format
+    fn write_wav_stub(path: &Path, sample_rate: u32, bits: u16) {
+        write_wav(path, sample_rate, bits, 16);
+    }
+
+    /// A silent 16-bit PCM WAV file lasting `seconds`
+    fn write_wav_seconds(path: &Path, seconds: u32) {
+        write_wav(path, 1000, 16, seconds as usize * 1000);
+    }
+
+    fn write_wav(path: &Path, sample_rate: u32, bits: u16, frames: usize) {
+        let data = vec![0u8; 2 * bits as usize / 8 * frames];
+        crate::audio::test_files::write_wav(path, sample_rate, 2, bits, &data);
+    }
+
+    #[test]
+    fn test_smart_playlist_loads_only_matching_tracks() {
+        let playlist_dir = TempDir::new().unwrap();
+        let library = TempDir::new().unwrap();
+        fs::create_dir(library.path().join("hires")).unwrap();
+        write_wav_stub(&library.path().join("cd.wav"), 44100, 16);
+        write_wav_stub(&library.path().join("dvd.wav"), 48000, 24);
+        write_wav_stub(&library.path().join("hires/96k.wav"), 96000, 24);
+        write_wav_stub(&library.path().join("hires/192k.wav"), 192000, 24);
+        fs::write(library.path().join("unreadable.wav"), b"not audio").unwrap();
+
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(playlist_dir.path().to_path_buf()).unwrap();
+        let criteria = SmartPlaylistCriteria::from_json(r#"{"min_sample_rate": 96000}"#).unwrap();
+        queue_manager.save_smart_playlist("hires", &criteria).unwrap();
+        assert!(playlist_dir.path().join("hires.smart.json").exists());
+
+        let criteria = queue_manager.smart_playlist("hires").unwrap();
+        assert_eq!(queue_manager.load_matching(library.path(), &criteria).unwrap(), 2);
+        let names: Vec<_> = queue_manager.list().iter()
+            .map(|track| track.path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, vec!["192k.wav", "96k.wav"]);
+        assert_eq!(queue_manager.list()[1].sample_rate, Some(96000));
+        assert_eq!(queue_manager.list()[1].bit_depth, Some(24));
+
+        assert!(matches!(queue_manager.smart_playlist("missing"), Err(PlaylistError::PlaylistNotFound { .. })));
+    }
+
     #[test]
     fn test_remove_track() {
         let temp_dir = TempDir::new().unwrap();
@@ -750,7 +2112,7 @@ mod tests {
     #[test]
     fn test_scan_directory_recursive() {
         let temp_dir = create_test_directory_structure();
-        let audio_files = QueueManagerImpl::scan_directory(temp_dir.path()).unwrap();
+        let audio_files = QueueManagerImpl::scan_directory(temp_dir.path(), &DirectoryAddOptions::default(), true, &[]).unwrap();
         
         // Should find 5 audio files total
         assert_eq!(audio_files.len(), 5);
@@ -764,6 +2126,53 @@ mod tests {
         assert!(has_subdir_files);
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_follows_symlinks_without_looping() {
+        use std::os::unix::fs::symlink;
+
+        let library = TempDir::new().unwrap();
+        let elsewhere = TempDir::new().unwrap();
+        let album = library.path().join("album");
+        fs::create_dir(&album).unwrap();
+        let own_track = create_test_audio_file(&album, "own", "flac");
+        create_test_audio_file(elsewhere.path(), "linked", "flac");
+
+        // A symlinked album folder, a link back up the tree and a dangling link
+        symlink(elsewhere.path(), library.path().join("linked_album")).unwrap();
+        symlink(library.path(), album.join("up")).unwrap();
+        symlink(library.path().join("missing"), library.path().join("broken")).unwrap();
+
+        let options = DirectoryAddOptions::default();
+        let audio_files = QueueManagerImpl::scan_directory(library.path(), &options, true, &[]).unwrap();
+        assert_eq!(audio_files, vec![own_track.clone(), library.path().join("linked_album").join("linked.flac")]);
+
+        let audio_files = QueueManagerImpl::scan_directory(library.path(), &options, false, &[]).unwrap();
+        assert_eq!(audio_files, vec![own_track]);
+    }
+
+    #[test]
+    fn test_scan_skips_directories_with_a_nomusic_file() {
+        let library = TempDir::new().unwrap();
+        let album = library.path().join("album");
+        let podcasts = library.path().join("podcasts");
+        fs::create_dir_all(podcasts.join("2024")).unwrap();
+        fs::create_dir(&album).unwrap();
+        let top = create_test_audio_file(library.path(), "top", "flac");
+        let track = create_test_audio_file(&album, "track", "flac");
+        create_test_audio_file(&podcasts, "episode", "mp3");
+        create_test_audio_file(&podcasts.join("2024"), "older", "mp3");
+        fs::write(podcasts.join(".nomedia"), b"").unwrap();
+
+        let options = DirectoryAddOptions::default();
+        let audio_files = QueueManagerImpl::scan_directory(library.path(), &options, true, &crate::config::default_nomusic_files()).unwrap();
+        assert_eq!(audio_files, vec![track, top]);
+
+        // Without marker names the podcasts are scanned like any other directory
+        let audio_files = QueueManagerImpl::scan_directory(library.path(), &options, true, &[]).unwrap();
+        assert_eq!(audio_files.len(), 4);
+    }
+
     #[test]
     fn test_default_implementation() {
         let queue_manager = QueueManagerImpl::default();
@@ -771,6 +2180,33 @@ mod tests {
         assert_eq!(queue_manager.len(), 0);
     }
     
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_path_survives_playlist_round_trip() {
+        use crate::audio::decoders::WavDecoder;
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        // Latin-1 "Café.wav", as older rips often name files
+        let song = temp_dir.path().join(OsStr::from_bytes(b"Caf\xe9.wav"));
+        write_wav_seconds(&song, 1);
+
+        for format in [PlaylistFormat::M3u, PlaylistFormat::Pls] {
+            let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().join("playlists")).unwrap();
+            queue_manager.add_file(&song).unwrap();
+            queue_manager.save_playlist("latin1", format, &PlaylistSaveOptions::default()).unwrap();
+
+            queue_manager.clear();
+            queue_manager.load_playlist("latin1").unwrap();
+            assert_eq!(queue_manager.list()[0].path, song);
+            queue_manager.delete_playlist("latin1").unwrap();
+
+            let mut decoder = WavDecoder::new(&queue_manager.list()[0].path).unwrap();
+            assert!(decoder.decode_next().unwrap().is_some());
+        }
+    }
+
     #[test]
     fn test_playlist_integration() {
         let temp_dir = TempDir::new().unwrap();
@@ -785,7 +2221,7 @@ mod tests {
         assert_eq!(queue_manager.len(), 2);
         
         // Save playlist
-        let result = queue_manager.save_playlist("test_playlist", crate::queue::playlist::PlaylistFormat::M3u);
+        let result = queue_manager.save_playlist("test_playlist", crate::queue::playlist::PlaylistFormat::M3u, &PlaylistSaveOptions::default());
         assert!(result.is_ok());
         
         // List playlists
@@ -809,6 +2245,67 @@ mod tests {
         assert!(playlists.is_empty());
     }
     
+    #[test]
+    fn test_playlist_rename() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = create_test_audio_file(temp_dir.path(), "song1", "flac");
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        queue_manager.add_file(&file).unwrap();
+        queue_manager.save_playlist("road trip", PlaylistFormat::M3u, &PlaylistSaveOptions::default()).unwrap();
+        queue_manager.save_playlist("chill", PlaylistFormat::Pls, &PlaylistSaveOptions::default()).unwrap();
+
+        queue_manager.rename_playlist("road trip", "summer").unwrap();
+        assert_eq!(queue_manager.list_playlists().unwrap(), vec!["chill", "summer"]);
+        queue_manager.clear();
+        queue_manager.load_playlist("summer").unwrap();
+        assert_eq!(queue_manager.len(), 1);
+
+        // Neither a missing playlist nor a taken name changes anything
+        assert!(matches!(
+            queue_manager.rename_playlist("road trip", "winter"),
+            Err(PlaylistError::PlaylistNotFound { name }) if name == "road trip"
+        ));
+        assert!(matches!(queue_manager.rename_playlist("summer", "chill"), Err(PlaylistError::IoError(_))));
+        assert_eq!(queue_manager.list_playlists().unwrap(), vec!["chill", "summer"]);
+    }
+
+    #[test]
+    fn test_playlist_merge() {
+        let temp_dir = TempDir::new().unwrap();
+        let files: Vec<PathBuf> = ["song1", "song2", "song3", "song4"].iter()
+            .map(|name| create_test_audio_file(temp_dir.path(), name, "flac"))
+            .collect();
+        let mut queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
+        for file in &files[..3] {
+            queue_manager.add_file(file).unwrap();
+        }
+        queue_manager.save_playlist("morning", PlaylistFormat::M3u, &PlaylistSaveOptions::default()).unwrap();
+        queue_manager.clear();
+        for file in &files[2..] {
+            queue_manager.add_file(file).unwrap();
+        }
+        queue_manager.save_playlist("evening", PlaylistFormat::Pls, &PlaylistSaveOptions::default()).unwrap();
+
+        // song3 is in both and is kept once, where it first appears
+        let names = vec!["morning".to_string(), "evening".to_string()];
+        assert_eq!(queue_manager.merge_playlists(&names, "all day").unwrap(), 3 + 2 - 1);
+        queue_manager.clear();
+        queue_manager.load_playlist("all day").unwrap();
+        let paths: Vec<PathBuf> = queue_manager.list().iter().map(|track| track.path.clone()).collect();
+        assert_eq!(paths, files);
+
+        // The sources are untouched, and neither a missing source nor a taken name writes anything
+        queue_manager.load_playlist("evening").unwrap();
+        assert_eq!(queue_manager.len(), 2);
+        let missing = vec!["morning".to_string(), "night".to_string()];
+        assert!(matches!(
+            queue_manager.merge_playlists(&missing, "mix"),
+            Err(PlaylistError::PlaylistNotFound { name }) if name == "night"
+        ));
+        assert!(matches!(queue_manager.merge_playlists(&names, "morning"), Err(PlaylistError::IoError(_))));
+        assert_eq!(queue_manager.list_playlists().unwrap(), vec!["all day", "evening", "morning"]);
+    }
+
     #[test]
     fn test_playlist_load_nonexistent() {
         let temp_dir = TempDir::new().unwrap();
@@ -830,7 +2327,7 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let queue_manager = QueueManagerImpl::with_playlist_directory(temp_dir.path().to_path_buf()).unwrap();
         
-        let result = queue_manager.save_playlist("empty", crate::queue::playlist::PlaylistFormat::M3u);
+        let result = queue_manager.save_playlist("empty", crate::queue::playlist::PlaylistFormat::M3u, &PlaylistSaveOptions::default());
         assert!(result.is_err());
         
         match result.unwrap_err() {
@@ -1,9 +1,15 @@
-use std::collections::VecDeque;
+use std::borrow::Cow;
+use std::collections::{HashSet, VecDeque};
 use std::fs;
 use std::io::{BufRead, BufReader, Write};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use crate::error::PlaylistError;
-use crate::models::TrackInfo;
+use crate::models::{AudioMetadata, TrackInfo};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// File name suffix of smart playlist criteria
+const SMART_SUFFIX: &str = ".smart.json";
 
 /// Supported playlist formats
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -39,6 +45,84 @@ impl PlaylistFormat {
     }
 }
 
+/// How track paths are written when saving a playlist
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaylistSaveOptions {
+    /// Write paths relative to `base_dir` so the playlist moves along with the music
+    pub use_relative_paths: bool,
+    /// Directory relative paths start from; defaults to the playlist file's directory
+    pub base_dir: Option<PathBuf>,
+}
+
+impl PlaylistSaveOptions {
+    /// Paths relative to the playlist file
+    pub fn relative() -> Self {
+        Self { use_relative_paths: true, base_dir: None }
+    }
+}
+
+/// Rules a smart playlist picks tracks by; fields left out match every track
+///
+/// Stored as JSON, e.g. `{"codec": "flac", "min_bit_depth": 24, "artist_contains": "Miles Davis"}`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmartPlaylistCriteria {
+    /// Codec name such as "flac" or "mp3", case-insensitive
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codec: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_bit_depth: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bit_depth: Option<u16>,
+    /// Lowest sample rate in Hz
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_sample_rate: Option<u32>,
+    /// Case-insensitive substring of the artist tag
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist_contains: Option<String>,
+    /// Case-insensitive substring of the genre tag
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genre_contains: Option<String>,
+    /// First and last year, inclusive: `[1955, 1965]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year_range: Option<(u32, u32)>,
+}
+
+impl SmartPlaylistCriteria {
+    /// Parse criteria as typed in `playlist create-smart`
+    pub fn from_json(json: &str) -> Result<Self, PlaylistError> {
+        serde_json::from_str(json).map_err(|e| PlaylistError::InvalidFormat(format!("Invalid smart playlist criteria: {}", e)))
+    }
+
+    /// Whether `track` meets every rule; a rule on an unknown value does not match
+    pub fn matches(&self, track: &TrackInfo) -> bool {
+        let contains = |value: &Option<String>, needle: &Option<String>| match needle {
+            Some(needle) => value.as_ref().is_some_and(|value| value.to_lowercase().contains(&needle.to_lowercase())),
+            None => true,
+        };
+
+        self.codec.as_ref().is_none_or(|codec| {
+            track.codec.as_ref().is_some_and(|actual| actual.name().eq_ignore_ascii_case(codec))
+        }) && self.min_bit_depth.is_none_or(|min| track.bit_depth.is_some_and(|bits| bits >= min))
+            && self.max_bit_depth.is_none_or(|max| track.bit_depth.is_some_and(|bits| bits <= max))
+            && self.min_sample_rate.is_none_or(|min| track.sample_rate.is_some_and(|rate| rate >= min))
+            && contains(&track.metadata.artist, &self.artist_contains)
+            && contains(&track.metadata.genre, &self.genre_contains)
+            && self.year_range.is_none_or(|(first, last)| {
+                track.metadata.year.is_some_and(|year| (first..=last).contains(&year))
+            })
+    }
+}
+
+/// Outcome of moving the playlists to another directory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaylistMove {
+    /// Number of playlist files moved
+    pub moved: usize,
+    /// Files left in place because the new directory already has one with that name
+    pub skipped: Vec<PathBuf>,
+}
+
 /// Playlist manager for saving and loading playlists
 pub struct PlaylistManager {
     playlist_directory: PathBuf,
@@ -57,12 +141,56 @@ impl PlaylistManager {
         })
     }
 
+    /// Directory playlists are saved to and loaded from
+    pub fn directory(&self) -> &Path {
+        &self.playlist_directory
+    }
+
+    /// Move every saved and smart playlist into `target`
+    ///
+    /// Relative entries are rewritten so they keep pointing at the same files.
+    pub fn move_playlists(&self, target: &Path) -> Result<PlaylistMove, PlaylistError> {
+        let mut outcome = PlaylistMove::default();
+        let source_dir = std::path::absolute(&self.playlist_directory)?;
+        let target_dir = std::path::absolute(target)?;
+        fs::create_dir_all(&target_dir)?;
+        if !source_dir.is_dir() || fs::canonicalize(&source_dir)? == fs::canonicalize(&target_dir)? {
+            return Ok(outcome);
+        }
+
+        for entry in fs::read_dir(&source_dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name() else { continue };
+            let is_smart = name.to_str().is_some_and(|name| name.ends_with(SMART_SUFFIX));
+            let format = path.extension().and_then(|ext| ext.to_str()).and_then(PlaylistFormat::from_extension);
+            if !path.is_file() || !(is_smart || format.is_some()) {
+                continue;
+            }
+
+            let destination = target_dir.join(name);
+            if destination.exists() {
+                outcome.skipped.push(path);
+                continue;
+            }
+            let contents = fs::read(&path)?;
+            let contents = match format {
+                Some(format) => rebase_entries(&contents, format, &source_dir, &target_dir),
+                None => contents,
+            };
+            fs::write(&destination, contents)?;
+            fs::remove_file(&path)?;
+            outcome.moved += 1;
+        }
+        Ok(outcome)
+    }
+
     /// Save a queue as a playlist
     pub fn save_playlist(
         &self,
         name: &str,
         queue: &VecDeque<TrackInfo>,
         format: PlaylistFormat,
+        options: &PlaylistSaveOptions,
     ) -> Result<(), PlaylistError> {
         if queue.is_empty() {
             return Err(PlaylistError::InvalidFormat("Cannot save empty playlist".to_string()));
@@ -71,9 +199,23 @@ impl PlaylistManager {
         let filename = format!("{}.{}", name, format.extension());
         let playlist_path = self.playlist_directory.join(filename);
 
+        let base_dir = match (&options.base_dir, options.use_relative_paths) {
+            (_, false) => None,
+            (Some(base_dir), true) => Some(std::path::absolute(base_dir)?),
+            (None, true) => Some(std::path::absolute(&self.playlist_directory)?),
+        };
+        let entry_path = |track: &TrackInfo| -> String {
+            let relative = base_dir.as_deref().and_then(|base| relative_path(&track.path, base));
+            match relative.as_deref().unwrap_or(&track.path).to_str() {
+                Some(path) => path.to_string(),
+                // Not valid UTF-8: a file URL keeps every byte of the name
+                None => file_url(&track.path),
+            }
+        };
+
         match format {
-            PlaylistFormat::M3u => self.save_m3u(&playlist_path, queue),
-            PlaylistFormat::Pls => self.save_pls(&playlist_path, queue),
+            PlaylistFormat::M3u => self.save_m3u(&playlist_path, queue, entry_path),
+            PlaylistFormat::Pls => self.save_pls(&playlist_path, queue, entry_path),
         }
     }
 
@@ -156,8 +298,91 @@ impl PlaylistManager {
         Ok(())
     }
 
+    /// Rename a playlist, keeping its format; an existing playlist called `new_name` is left alone
+    pub fn rename_playlist(&self, old_name: &str, new_name: &str) -> Result<(), PlaylistError> {
+        let extensions = ["m3u", "m3u8", "pls"];
+        let renames: Vec<(PathBuf, PathBuf)> = extensions
+            .iter()
+            .map(|ext| (
+                self.playlist_directory.join(format!("{}.{}", old_name, ext)),
+                self.playlist_directory.join(format!("{}.{}", new_name, ext)),
+            ))
+            .filter(|(from, _)| from.exists())
+            .collect();
+
+        if renames.is_empty() {
+            return Err(PlaylistError::PlaylistNotFound {
+                name: old_name.to_string(),
+            });
+        }
+        if old_name == new_name {
+            return Ok(());
+        }
+        if self.list_playlists()?.iter().any(|name| name == new_name) {
+            return Err(PlaylistError::IoError(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("a playlist named '{}' already exists", new_name),
+            )));
+        }
+
+        for (from, to) in renames {
+            fs::rename(from, to)?;
+        }
+        Ok(())
+    }
+
+    /// Save the tracks of `names`, in order and each path once, as a new playlist `output`
+    ///
+    /// The source playlists are left as they are. Returns the number of tracks saved.
+    pub fn merge_playlists(&self, names: &[String], output: &str) -> Result<usize, PlaylistError> {
+        let mut seen = HashSet::new();
+        let mut merged = VecDeque::new();
+        for name in names {
+            for track in self.load_playlist(name)? {
+                if seen.insert(track.path.clone()) {
+                    merged.push_back(track);
+                }
+            }
+        }
+        if self.list_playlists()?.iter().any(|name| name == output) {
+            return Err(PlaylistError::IoError(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("a playlist named '{}' already exists", output),
+            )));
+        }
+
+        self.save_playlist(output, &merged, PlaylistFormat::M3u, &PlaylistSaveOptions::default())?;
+        Ok(merged.len())
+    }
+
+    /// Save the criteria of a smart playlist as `<name>.smart.json`
+    pub fn save_smart_playlist(&self, name: &str, criteria: &SmartPlaylistCriteria) -> Result<(), PlaylistError> {
+        let json = serde_json::to_string_pretty(criteria)
+            .map_err(|e| PlaylistError::InvalidFormat(e.to_string()))?;
+        fs::write(self.smart_playlist_path(name), json)?;
+        Ok(())
+    }
+
+    /// Read back the criteria of a smart playlist
+    pub fn load_smart_playlist(&self, name: &str) -> Result<SmartPlaylistCriteria, PlaylistError> {
+        let path = self.smart_playlist_path(name);
+        if !path.exists() {
+            return Err(PlaylistError::PlaylistNotFound { name: name.to_string() });
+        }
+        SmartPlaylistCriteria::from_json(&fs::read_to_string(path)?)
+    }
+
+    fn smart_playlist_path(&self, name: &str) -> PathBuf {
+        self.playlist_directory.join(format!("{}{}", name, SMART_SUFFIX))
+    }
+
     /// Save playlist in M3U format
-    fn save_m3u(&self, path: &Path, queue: &VecDeque<TrackInfo>) -> Result<(), PlaylistError> {
+    fn save_m3u(
+        &self,
+        path: &Path,
+        queue: &VecDeque<TrackInfo>,
+        entry_path: impl Fn(&TrackInfo) -> String,
+    ) -> Result<(), PlaylistError> {
         let mut file = fs::File::create(path)?;
         
         // Write M3U header
@@ -176,16 +401,19 @@ impl PlaylistManager {
 
             writeln!(file, "#EXTINF:{},{} - {}", duration_seconds, artist, title)?;
             
-            // Write file path (convert to string, handling potential UTF-8 issues)
-            let path_str = track.path.to_string_lossy();
-            writeln!(file, "{}", path_str)?;
+            writeln!(file, "{}", entry_path(track))?;
         }
 
         Ok(())
     }
 
     /// Save playlist in PLS format
-    fn save_pls(&self, path: &Path, queue: &VecDeque<TrackInfo>) -> Result<(), PlaylistError> {
+    fn save_pls(
+        &self,
+        path: &Path,
+        queue: &VecDeque<TrackInfo>,
+        entry_path: impl Fn(&TrackInfo) -> String,
+    ) -> Result<(), PlaylistError> {
         let mut file = fs::File::create(path)?;
         
         // Write PLS header
@@ -197,8 +425,7 @@ impl PlaylistManager {
             let entry_num = index + 1;
             
             // File path
-            let path_str = track.path.to_string_lossy();
-            writeln!(file, "File{}={}", entry_num, path_str)?;
+            writeln!(file, "File{}={}", entry_num, entry_path(track))?;
             
             // Title
             let artist = track.metadata.artist.as_deref().unwrap_or("Unknown Artist");
@@ -227,23 +454,35 @@ impl PlaylistManager {
         let file = fs::File::open(path)?;
         let reader = BufReader::new(file);
         let mut queue = VecDeque::new();
+        // Info from the #EXTINF line preceding the next path
+        let mut ext_info: Option<ExtInf> = None;
 
-        for line in reader.lines() {
+        // Lines are read as bytes so paths that are not valid UTF-8 survive
+        for line in reader.split(b'\n') {
             let line = line?;
-            let line = line.trim();
+            let line = line.trim_ascii();
+
+            if let Some(info) = line.strip_prefix(b"#EXTINF:") {
+                ext_info = ExtInf::parse(&String::from_utf8_lossy(info));
+                continue;
+            }
 
-            // Skip empty lines and comments (except #EXTINF which we ignore for now)
-            if line.is_empty() || line.starts_with('#') {
+            // Skip empty lines and other comments
+            if line.is_empty() || line.starts_with(b"#") {
                 continue;
             }
 
             // This should be a file path
-            let track_path = self.resolve_path(path, line)?;
-            
+            let track_path = self.resolve_path(path, &path_from_entry(line))?;
+            let info = ext_info.take();
+
             if let Ok(track_info) = self.create_track_info_from_path(&track_path) {
                 queue.push_back(track_info);
+            } else if let Some(info) = info.filter(|_| !track_path.exists()) {
+                // Keep moved or deleted files under the name the playlist recorded
+                queue.push_back(info.into_track(track_path));
             }
-            // Silently skip files that can't be loaded (they might have been moved/deleted)
+            // Silently skip other files that can't be loaded
         }
 
         Ok(queue)
@@ -256,23 +495,23 @@ impl PlaylistManager {
         let mut queue = VecDeque::new();
         let mut file_entries = std::collections::HashMap::new();
 
-        for line in reader.lines() {
+        for line in reader.split(b'\n') {
             let line = line?;
-            let line = line.trim();
+            let line = line.trim_ascii();
 
             // Skip empty lines and section headers
-            if line.is_empty() || line.starts_with('[') || line.starts_with("NumberOfEntries") || line.starts_with("Version") {
+            if line.is_empty() || line.starts_with(b"[") || line.starts_with(b"NumberOfEntries") || line.starts_with(b"Version") {
                 continue;
             }
 
             // Parse File entries
-            if let Some(file_line) = line.strip_prefix("File") {
-                if let Some(equals_pos) = file_line.find('=') {
-                    let entry_part = &file_line[..equals_pos];
+            if let Some(file_line) = line.strip_prefix(b"File") {
+                if let Some(equals_pos) = file_line.iter().position(|&byte| byte == b'=') {
+                    let entry_part = String::from_utf8_lossy(&file_line[..equals_pos]);
                     let path_part = &file_line[equals_pos + 1..];
-                    
+
                     if let Ok(entry_num) = entry_part.parse::<usize>() {
-                        file_entries.insert(entry_num, path_part.to_string());
+                        file_entries.insert(entry_num, path_from_entry(path_part));
                     }
                 }
             }
@@ -296,15 +535,13 @@ impl PlaylistManager {
     }
 
     /// Resolve a file path relative to the playlist file
-    fn resolve_path(&self, playlist_path: &Path, file_path: &str) -> Result<PathBuf, PlaylistError> {
-        let path = Path::new(file_path);
-        
+    fn resolve_path(&self, playlist_path: &Path, path: &Path) -> Result<PathBuf, PlaylistError> {
         if path.is_absolute() {
             Ok(path.to_path_buf())
         } else {
             // Resolve relative to the playlist file's directory
             if let Some(playlist_dir) = playlist_path.parent() {
-                Ok(playlist_dir.join(path))
+                Ok(without_parent_dirs(&playlist_dir.join(path)))
             } else {
                 Ok(path.to_path_buf())
             }
@@ -322,11 +559,197 @@ impl PlaylistManager {
     }
 }
 
+/// `path` relative to the directory `base`, climbing with `..` as needed
+///
+/// Relative paths are returned unchanged; None when the two share no root (other drive).
+fn relative_path(path: &Path, base: &Path) -> Option<PathBuf> {
+    if path.is_relative() {
+        return Some(path.to_path_buf());
+    }
+    let path = std::path::absolute(path).ok()?;
+    let path_parts: Vec<_> = path.components().collect();
+    let base_parts: Vec<_> = base.components().collect();
+    let common = path_parts.iter().zip(&base_parts).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        return None;
+    }
+
+    let mut relative: PathBuf = base_parts[common..].iter().map(|_| "..").collect();
+    relative.extend(&path_parts[common..]);
+    Some(relative)
+}
+
+/// Bytes of a path; lossy outside Unix, where paths are not byte strings
+fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Cow::Borrowed(path.as_os_str().as_bytes())
+    }
+    #[cfg(not(unix))]
+    {
+        match path.to_string_lossy() {
+            Cow::Borrowed(text) => Cow::Borrowed(text.as_bytes()),
+            Cow::Owned(text) => Cow::Owned(text.into_bytes()),
+        }
+    }
+}
+
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+    }
+    #[cfg(not(unix))]
+    {
+        PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+/// `file://` URL for a path, percent-encoding every byte outside the unreserved set
+fn file_url(path: &Path) -> String {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut url = String::from("file://");
+    for &byte in path_bytes(&absolute).iter() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            url.push(byte as char);
+        } else {
+            url.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    url
+}
+
+/// Path named by a playlist line: a plain path, or a `file://` URL with percent-encoded bytes
+fn path_from_entry(entry: &[u8]) -> PathBuf {
+    let Some(url_path) = entry.strip_prefix(b"file://") else {
+        return path_from_bytes(entry);
+    };
+    let url_path = url_path.strip_prefix(b"localhost").unwrap_or(url_path);
+
+    let mut decoded = Vec::with_capacity(url_path.len());
+    let mut i = 0;
+    while i < url_path.len() {
+        let escaped = url_path.get(i + 1..i + 3)
+            .filter(|hex| url_path[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(url_path[i]);
+                i += 1;
+            }
+        }
+    }
+    path_from_bytes(&decoded)
+}
+
+/// Rewrite the relative entries of an M3U or PLS playlist moved from directory `from` to `to`
+fn rebase_entries(contents: &[u8], format: PlaylistFormat, from: &Path, to: &Path) -> Vec<u8> {
+    let mut rebased = Vec::with_capacity(contents.len());
+    for (index, line) in contents.split(|&byte| byte == b'\n').enumerate() {
+        if index > 0 {
+            rebased.push(b'\n');
+        }
+        let trimmed = line.trim_ascii();
+        let entry_start = match format {
+            PlaylistFormat::M3u if !trimmed.is_empty() && !trimmed.starts_with(b"#") => Some(0),
+            PlaylistFormat::Pls if trimmed.starts_with(b"File") => trimmed.iter().position(|&byte| byte == b'=').map(|equals| equals + 1),
+            _ => None,
+        };
+        match entry_start.and_then(|start| Some((start, rebased_entry(&trimmed[start..], from, to)?))) {
+            Some((start, entry)) => {
+                rebased.extend_from_slice(&trimmed[..start]);
+                rebased.extend_from_slice(&entry);
+                if line.ends_with(b"\r") {
+                    rebased.push(b'\r');
+                }
+            }
+            None => rebased.extend_from_slice(line),
+        }
+    }
+    rebased
+}
+
+/// A relative entry written relative to `to` instead of `from`; None for absolute paths and URLs
+fn rebased_entry(entry: &[u8], from: &Path, to: &Path) -> Option<Vec<u8>> {
+    if entry.windows(3).any(|window| window == b"://") {
+        return None;
+    }
+    let path = path_from_bytes(entry);
+    if !path.is_relative() {
+        return None;
+    }
+    let absolute = without_parent_dirs(&from.join(path));
+    let relative = relative_path(&absolute, to).unwrap_or_else(|| absolute.clone());
+    Some(match relative.to_str() {
+        Some(relative) => relative.as_bytes().to_vec(),
+        None => file_url(&absolute).into_bytes(),
+    })
+}
+
+/// Fold `dir/..` pairs so resolved relative entries read like ordinary paths
+fn without_parent_dirs(path: &Path) -> PathBuf {
+    let mut folded = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir if matches!(folded.components().next_back(), Some(Component::Normal(_))) => {
+                folded.pop();
+            }
+            other => folded.push(other),
+        }
+    }
+    folded
+}
+
+/// Duration and display name from an `#EXTINF:<seconds>,<artist> - <title>` line
+#[derive(Debug, Clone, PartialEq)]
+struct ExtInf {
+    duration: Duration,
+    artist: Option<String>,
+    title: String,
+}
+
+impl ExtInf {
+    /// Parse the part after `#EXTINF:`; a negative duration means unknown
+    fn parse(info: &str) -> Option<Self> {
+        let (seconds, display) = info.split_once(',')?;
+        // Attributes such as tvg-id="..." may follow the duration
+        let seconds = seconds.split_whitespace().next()?.parse::<i64>().ok()?;
+        let duration = Duration::from_secs(seconds.max(0) as u64);
+
+        let display = display.trim();
+        let (artist, title) = match display.split_once(" - ") {
+            Some((artist, title)) if !title.is_empty() => {
+                let artist = Some(artist.to_string()).filter(|artist| artist != "Unknown Artist");
+                (artist, title.to_string())
+            }
+            _ => (None, display.to_string()),
+        };
+        if title.is_empty() {
+            return None;
+        }
+        Some(Self { duration, artist, title })
+    }
+
+    /// Queue entry for a file that could not be read
+    fn into_track(self, path: PathBuf) -> TrackInfo {
+        let metadata = AudioMetadata {
+            title: Some(self.title),
+            artist: self.artist,
+            ..Default::default()
+        };
+        TrackInfo::new(path, metadata, self.duration, 0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::AudioMetadata;
-    use std::time::Duration;
     use tempfile::TempDir;
 
     fn create_test_track(name: &str, artist: &str, duration_secs: u64) -> TrackInfo {
@@ -337,6 +760,7 @@ mod tests {
             track_number: Some(1),
             year: Some(2023),
             genre: Some("Test".to_string()),
+            ..Default::default()
         };
 
         TrackInfo::new(
@@ -410,7 +834,7 @@ mod tests {
         let manager = PlaylistManager::new(temp_dir.path().to_path_buf()).unwrap();
         let queue = create_test_queue();
 
-        let result = manager.save_playlist("test_playlist", &queue, PlaylistFormat::M3u);
+        let result = manager.save_playlist("test_playlist", &queue, PlaylistFormat::M3u, &PlaylistSaveOptions::default());
         assert!(result.is_ok());
 
         // Check that file was created
@@ -434,7 +858,7 @@ mod tests {
         let manager = PlaylistManager::new(temp_dir.path().to_path_buf()).unwrap();
         let queue = create_test_queue();
 
-        let result = manager.save_playlist("test_playlist", &queue, PlaylistFormat::Pls);
+        let result = manager.save_playlist("test_playlist", &queue, PlaylistFormat::Pls, &PlaylistSaveOptions::default());
         assert!(result.is_ok());
 
         // Check that file was created
@@ -463,7 +887,7 @@ mod tests {
         let manager = PlaylistManager::new(temp_dir.path().to_path_buf()).unwrap();
         let empty_queue = VecDeque::new();
 
-        let result = manager.save_playlist("empty_playlist", &empty_queue, PlaylistFormat::M3u);
+        let result = manager.save_playlist("empty_playlist", &empty_queue, PlaylistFormat::M3u, &PlaylistSaveOptions::default());
         assert!(result.is_err());
         
         match result.unwrap_err() {
@@ -490,9 +914,9 @@ mod tests {
         let queue = create_test_queue();
 
         // Create some playlists
-        manager.save_playlist("playlist1", &queue, PlaylistFormat::M3u).unwrap();
-        manager.save_playlist("playlist2", &queue, PlaylistFormat::Pls).unwrap();
-        manager.save_playlist("playlist3", &queue, PlaylistFormat::M3u).unwrap();
+        manager.save_playlist("playlist1", &queue, PlaylistFormat::M3u, &PlaylistSaveOptions::default()).unwrap();
+        manager.save_playlist("playlist2", &queue, PlaylistFormat::Pls, &PlaylistSaveOptions::default()).unwrap();
+        manager.save_playlist("playlist3", &queue, PlaylistFormat::M3u, &PlaylistSaveOptions::default()).unwrap();
 
         // Create a non-playlist file (should be ignored)
         fs::write(temp_dir.path().join("not_a_playlist.txt"), "test").unwrap();
@@ -512,8 +936,8 @@ mod tests {
         let queue = create_test_queue();
 
         // Create same playlist in different formats
-        manager.save_playlist("same_name", &queue, PlaylistFormat::M3u).unwrap();
-        manager.save_playlist("same_name", &queue, PlaylistFormat::Pls).unwrap();
+        manager.save_playlist("same_name", &queue, PlaylistFormat::M3u, &PlaylistSaveOptions::default()).unwrap();
+        manager.save_playlist("same_name", &queue, PlaylistFormat::Pls, &PlaylistSaveOptions::default()).unwrap();
 
         let playlists = manager.list_playlists().unwrap();
         assert_eq!(playlists.len(), 1);
@@ -527,7 +951,7 @@ mod tests {
         let queue = create_test_queue();
 
         // Create playlist
-        manager.save_playlist("to_delete", &queue, PlaylistFormat::M3u).unwrap();
+        manager.save_playlist("to_delete", &queue, PlaylistFormat::M3u, &PlaylistSaveOptions::default()).unwrap();
         
         let playlist_path = temp_dir.path().join("to_delete.m3u");
         assert!(playlist_path.exists());
@@ -561,8 +985,8 @@ mod tests {
         let queue = create_test_queue();
 
         // Create same playlist in multiple formats
-        manager.save_playlist("multi_format", &queue, PlaylistFormat::M3u).unwrap();
-        manager.save_playlist("multi_format", &queue, PlaylistFormat::Pls).unwrap();
+        manager.save_playlist("multi_format", &queue, PlaylistFormat::M3u, &PlaylistSaveOptions::default()).unwrap();
+        manager.save_playlist("multi_format", &queue, PlaylistFormat::Pls, &PlaylistSaveOptions::default()).unwrap();
         
         let m3u_path = temp_dir.path().join("multi_format.m3u");
         let pls_path = temp_dir.path().join("multi_format.pls");
@@ -599,7 +1023,7 @@ mod tests {
         let playlist_path = temp_dir.path().join("test.m3u");
 
         let absolute_path = "/absolute/path/to/file.flac";
-        let resolved = manager.resolve_path(&playlist_path, absolute_path).unwrap();
+        let resolved = manager.resolve_path(&playlist_path, Path::new(absolute_path)).unwrap();
         
         assert_eq!(resolved, PathBuf::from(absolute_path));
     }
@@ -611,13 +1035,212 @@ mod tests {
         let playlist_path = temp_dir.path().join("test.m3u");
 
         let relative_path = "music/file.flac";
-        let resolved = manager.resolve_path(&playlist_path, relative_path).unwrap();
+        let resolved = manager.resolve_path(&playlist_path, Path::new(relative_path)).unwrap();
         
         let expected = temp_dir.path().join("music/file.flac");
         assert_eq!(resolved, expected);
     }
 
-    // Note: We can't easily test the actual loading of M3U/PLS files without creating real audio files,
-    // as the create_track_info_from_path method requires actual files to exist and be valid audio files.
-    // In a real implementation, you might want to add a mock or test mode for this.
+    #[test]
+    fn test_file_url_entries() {
+        assert_eq!(file_url(Path::new("/music/A & B/01 100%.flac")), "file:///music/A%20%26%20B/01%20100%25.flac");
+        assert_eq!(path_from_entry(b"file:///music/A%20%26%20B/01%20100%25.flac"), PathBuf::from("/music/A & B/01 100%.flac"));
+        assert_eq!(path_from_entry(b"file://localhost/music/a.flac"), PathBuf::from("/music/a.flac"));
+        // Plain entries are taken literally, stray escapes included
+        assert_eq!(path_from_entry(b"music/100%25.flac"), PathBuf::from("music/100%25.flac"));
+        assert_eq!(path_from_entry(b"file:///music/50%.flac"), PathBuf::from("/music/50%.flac"));
+    }
+
+    #[test]
+    fn test_move_playlists_rebases_relative_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = PlaylistManager::new(temp_dir.path().join("playlists")).unwrap();
+        let target = temp_dir.path().join("new").join("lists");
+        fs::create_dir_all(&target).unwrap();
+
+        fs::write(
+            manager.directory().join("mix.m3u"),
+            "#EXTM3U\r\n#EXTINF:180,A - B\r\n../music/a.flac\r\n/abs/b.flac\r\nhttp://radio/stream\r\n",
+        ).unwrap();
+        fs::write(manager.directory().join("old.pls"), "[playlist]\nFile1=local.flac\nTitle1=Local\n").unwrap();
+        fs::write(manager.directory().join("hires.smart.json"), "{}").unwrap();
+        fs::write(manager.directory().join("notes.txt"), "keep").unwrap();
+        fs::write(manager.directory().join("taken.m3u"), "a.flac\n").unwrap();
+        fs::write(target.join("taken.m3u"), "other.flac\n").unwrap();
+
+        let outcome = manager.move_playlists(&target).unwrap();
+        assert_eq!(outcome.moved, 3);
+        assert_eq!(outcome.skipped, vec![manager.directory().join("taken.m3u")]);
+
+        assert_eq!(
+            fs::read_to_string(target.join("mix.m3u")).unwrap(),
+            "#EXTM3U\r\n#EXTINF:180,A - B\r\n../../music/a.flac\r\n/abs/b.flac\r\nhttp://radio/stream\r\n"
+        );
+        assert_eq!(fs::read_to_string(target.join("old.pls")).unwrap(), "[playlist]\nFile1=../../playlists/local.flac\nTitle1=Local\n");
+        assert_eq!(fs::read_to_string(target.join("hires.smart.json")).unwrap(), "{}");
+        assert_eq!(fs::read_to_string(target.join("taken.m3u")).unwrap(), "other.flac\n");
+        assert!(manager.directory().join("taken.m3u").exists());
+        assert!(manager.directory().join("notes.txt").exists());
+        assert!(!manager.directory().join("mix.m3u").exists());
+
+        // Moving into the same directory is a no-op
+        let moved_manager = PlaylistManager::new(target.clone()).unwrap();
+        assert_eq!(moved_manager.move_playlists(&target).unwrap(), PlaylistMove::default());
+    }
+
+    #[test]
+    fn test_parse_extinf() {
+        assert_eq!(ExtInf::parse("180,Artist A - Song One"), Some(ExtInf {
+            duration: Duration::from_secs(180),
+            artist: Some("Artist A".to_string()),
+            title: "Song One".to_string(),
+        }));
+        assert_eq!(ExtInf::parse("-1,Unknown Artist - Intro - Live"), Some(ExtInf {
+            duration: Duration::ZERO,
+            artist: None,
+            title: "Intro - Live".to_string(),
+        }));
+        assert_eq!(ExtInf::parse("42 tvg-id=\"x\",Radio Stream").map(|info| info.title), Some("Radio Stream".to_string()));
+        assert_eq!(ExtInf::parse("abc,Title"), None);
+        assert_eq!(ExtInf::parse("180"), None);
+    }
+
+    #[test]
+    fn test_load_m3u_keeps_moved_files_by_extinf_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = PlaylistManager::new(temp_dir.path().join("playlists")).unwrap();
+        let music_dir = temp_dir.path().join("music");
+        fs::create_dir(&music_dir).unwrap();
+
+        let mut queue = create_test_queue();
+        for track in queue.iter_mut() {
+            track.path = music_dir.join(track.path.file_name().unwrap());
+            fs::write(&track.path, b"not audio").unwrap();
+        }
+        manager.save_playlist("moved", &queue, PlaylistFormat::M3u, &PlaylistSaveOptions::default()).unwrap();
+
+        // Move the referenced files away
+        fs::rename(&music_dir, temp_dir.path().join("elsewhere")).unwrap();
+
+        let loaded = manager.load_playlist("moved").unwrap();
+        assert_eq!(loaded.len(), 3);
+        for (loaded, saved) in loaded.iter().zip(&queue) {
+            assert_eq!(loaded.path, saved.path);
+            assert_eq!(loaded.display_name(), saved.display_name());
+            assert_eq!(loaded.artist_name(), saved.artist_name());
+            assert_eq!(loaded.duration, saved.duration);
+        }
+    }
+
+    #[test]
+    fn test_smart_criteria_matches() {
+        use crate::models::AudioCodec;
+
+        let mut track = create_test_track("So What", "Miles Davis", 545);
+        track.metadata.genre = Some("Modal Jazz".to_string());
+        track.metadata.year = Some(1959);
+        track.codec = Some(AudioCodec::Flac);
+        track.sample_rate = Some(96000);
+        track.bit_depth = Some(24);
+
+        let criteria = SmartPlaylistCriteria::from_json(
+            r#"{"codec": "flac", "min_bit_depth": 24, "artist_contains": "miles", "genre_contains": "jazz", "year_range": [1955, 1965]}"#,
+        ).unwrap();
+        assert!(criteria.matches(&track));
+        assert!(SmartPlaylistCriteria::default().matches(&track));
+
+        let reject = |json: &str, track: &TrackInfo| !SmartPlaylistCriteria::from_json(json).unwrap().matches(track);
+        assert!(reject(r#"{"codec": "mp3"}"#, &track));
+        assert!(reject(r#"{"max_bit_depth": 16}"#, &track));
+        assert!(reject(r#"{"min_sample_rate": 176400}"#, &track));
+        assert!(reject(r#"{"artist_contains": "Coltrane"}"#, &track));
+        assert!(reject(r#"{"year_range": [1960, 1969]}"#, &track));
+
+        // Rules on values the file does not have do not match
+        track.bit_depth = None;
+        assert!(reject(r#"{"min_bit_depth": 16}"#, &track));
+
+        assert!(matches!(
+            SmartPlaylistCriteria::from_json(r#"{"min_bitdepth": 24}"#),
+            Err(PlaylistError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let base = Path::new("/home/alice/.config/hires-player/playlists");
+        assert_eq!(
+            relative_path(Path::new("/home/alice/Music/album/01.flac"), base),
+            Some(PathBuf::from("../../../Music/album/01.flac"))
+        );
+        assert_eq!(
+            relative_path(Path::new("/home/alice/.config/hires-player/playlists/local.flac"), base),
+            Some(PathBuf::from("local.flac"))
+        );
+        assert_eq!(relative_path(Path::new("music/01.flac"), base), Some(PathBuf::from("music/01.flac")));
+    }
+
+    #[test]
+    fn test_relative_playlist_survives_move() {
+        let temp_dir = TempDir::new().unwrap();
+        let library = temp_dir.path().join("library");
+        let music_dir = library.join("music").join("album");
+        fs::create_dir_all(&music_dir).unwrap();
+
+        let mut queue = create_test_queue();
+        for track in queue.iter_mut() {
+            track.path = music_dir.join(track.path.file_name().unwrap());
+            fs::write(&track.path, b"not audio").unwrap();
+        }
+        let manager = PlaylistManager::new(library.join("playlists")).unwrap();
+        manager.save_playlist("portable", &queue, PlaylistFormat::M3u, &PlaylistSaveOptions::relative()).unwrap();
+        manager.save_playlist("portable", &queue, PlaylistFormat::Pls, &PlaylistSaveOptions::relative()).unwrap();
+
+        let content = fs::read_to_string(library.join("playlists/portable.m3u")).unwrap();
+        assert!(content.contains("\n../music/album/song_one.flac\n"));
+        assert!(!content.contains(&*temp_dir.path().to_string_lossy()));
+        let content = fs::read_to_string(library.join("playlists/portable.pls")).unwrap();
+        assert!(content.contains("File1=../music/album/song_one.flac\n"));
+
+        // Move the playlist together with the music, as on another machine
+        let moved = temp_dir.path().join("elsewhere");
+        fs::rename(&library, &moved).unwrap();
+        let manager = PlaylistManager::new(moved.join("playlists")).unwrap();
+
+        let loaded = manager.load_playlist("portable").unwrap();
+        assert_eq!(loaded.len(), 3);
+        let expected: Vec<_> = queue
+            .iter()
+            .map(|track| moved.join("music/album").join(track.path.file_name().unwrap()))
+            .collect();
+        let paths: Vec<_> = loaded.iter().map(|track| track.path.clone()).collect();
+        assert_eq!(paths, expected);
+        assert!(paths.iter().all(|path| path.exists()));
+    }
+
+    #[test]
+    fn test_save_relative_to_base_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = PlaylistManager::new(temp_dir.path().join("playlists")).unwrap();
+        let options = PlaylistSaveOptions {
+            use_relative_paths: true,
+            base_dir: Some(PathBuf::from("/test")),
+        };
+        manager.save_playlist("based", &create_test_queue(), PlaylistFormat::M3u, &options).unwrap();
+
+        let content = fs::read_to_string(temp_dir.path().join("playlists/based.m3u")).unwrap();
+        assert!(content.contains("\npath/song_one.flac\n"));
+    }
+
+    #[test]
+    fn test_load_m3u_skips_missing_files_without_extinf() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = PlaylistManager::new(temp_dir.path().to_path_buf()).unwrap();
+        fs::write(temp_dir.path().join("bare.m3u"), "/missing/one.flac\n#EXTINF:10,Two\n/missing/two.flac\n").unwrap();
+
+        let loaded = manager.load_playlist("bare").unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].display_name(), "Two");
+        assert_eq!(loaded[0].artist_name(), "Unknown Artist");
+    }
 }
\ No newline at end of file
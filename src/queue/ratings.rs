@@ -0,0 +1,470 @@
+/*!
+Track ratings and favorites
+
+`RatingStore` keeps a star rating and a favorite flag per track in
+`ratings.json` next to `config.toml`. Entries are keyed by path and, for CUE
+sheet tracks, by where the track starts in its file. Each carries a
+fingerprint of the file contents, so a track that was moved or renamed picks
+its rating back up. The file is read on first use and replaced atomically on
+save; fields written by newer versions are carried through untouched.
+*/
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::config::store;
+use crate::models::TrackInfo;
+
+/// Ratings and favorites, in the config directory
+pub const RATINGS_FILE: &str = "ratings.json";
+
+/// Format version written by this build
+const FORMAT_VERSION: u32 = 1;
+
+/// Bytes hashed from each end of a file for its fingerprint
+const FINGERPRINT_SPAN: u64 = 64 * 1024;
+
+/// Prefix of keys that spell out the raw path bytes in hex
+const RAW_KEY_PREFIX: &str = "hex:";
+
+/// Rating and favorite flag of one track
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrackRating {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub favorite: bool,
+    /// Content fingerprint used to follow moved files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    /// Fields this version does not know about
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl TrackRating {
+    fn is_empty(&self) -> bool {
+        self.rating.is_none() && !self.favorite && self.extra.is_empty()
+    }
+}
+
+/// On-disk layout of `ratings.json`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RatingsFile {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    tracks: BTreeMap<String, TrackRating>,
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+}
+
+/// Lazily loaded, atomically saved ratings store
+#[derive(Debug)]
+pub struct RatingStore {
+    path: PathBuf,
+    file: Option<RatingsFile>,
+    /// Paths already fingerprinted without matching a moved entry
+    unmatched: HashSet<String>,
+}
+
+impl RatingStore {
+    /// Store backed by `path`; nothing is read until first use
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, file: None, unmatched: HashSet::new() }
+    }
+
+    /// Rating entry for the track starting at `start` in a file, following it if it was moved
+    pub fn get(&mut self, path: &Path, start: Duration) -> io::Result<Option<TrackRating>> {
+        let key = self.find_key(path, start)?;
+        Ok(key.and_then(|key| self.loaded().tracks.get(&key).cloned()))
+    }
+
+    /// Fill in `track.rating` and `track.favorite` from the store
+    pub fn annotate(&mut self, track: &mut TrackInfo) -> io::Result<()> {
+        let entry = self.get(&track.path, track.start_offset)?.unwrap_or_default();
+        track.rating = entry.rating;
+        track.favorite = entry.favorite;
+        Ok(())
+    }
+
+    /// Set (or with `None` clear) the star rating of the track starting at `start`
+    pub fn set_rating(&mut self, path: &Path, start: Duration, rating: Option<u8>) -> io::Result<()> {
+        if rating.is_some_and(|stars| !(1..=5).contains(&stars)) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "rating must be between 1 and 5"));
+        }
+        self.update(path, start, |entry| entry.rating = rating)
+    }
+
+    /// Set the favorite flag of the track starting at `start`
+    pub fn set_favorite(&mut self, path: &Path, start: Duration, favorite: bool) -> io::Result<()> {
+        self.update(path, start, |entry| entry.favorite = favorite)
+    }
+
+    /// Files holding a favorited track that still exist, each once, in path order
+    pub fn favorites(&mut self) -> io::Result<Vec<PathBuf>> {
+        self.load()?;
+        let mut files: Vec<PathBuf> = self.loaded().tracks.iter()
+            .filter(|(_, entry)| entry.favorite)
+            .map(|(key, _)| parse_key(key).0)
+            .filter(|path| path.is_file())
+            .collect();
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    fn update(&mut self, path: &Path, start: Duration, change: impl FnOnce(&mut TrackRating)) -> io::Result<()> {
+        let key = match self.find_key(path, start)? {
+            Some(key) => key,
+            None => track_key(path, start),
+        };
+        let current = fingerprint(path).ok();
+        let tracks = &mut self.loaded_mut().tracks;
+        let entry = tracks.entry(key.clone()).or_default();
+        change(entry);
+        if current.is_some() {
+            entry.fingerprint = current;
+        }
+        if entry.is_empty() {
+            tracks.remove(&key);
+        }
+        self.unmatched.clear();
+        self.save()
+    }
+
+    /// Key of the entry for a track; a moved file is matched by fingerprint and start
+    /// against entries whose file no longer exists and re-keyed in place
+    fn find_key(&mut self, path: &Path, start: Duration) -> io::Result<Option<String>> {
+        self.load()?;
+        let key = track_key(path, start);
+        let tracks = &self.loaded().tracks;
+        if tracks.contains_key(&key) {
+            return Ok(Some(key));
+        }
+        if self.unmatched.contains(&key) {
+            return Ok(None);
+        }
+
+        let mut orphans = tracks.iter()
+            .filter(|(old, entry)| {
+                let (old_path, old_start) = parse_key(old);
+                entry.fingerprint.is_some() && old_start == start && !old_path.exists()
+            })
+            .peekable();
+        let old_key = match orphans.peek() {
+            Some(_) => fingerprint(path).ok().and_then(|wanted| orphans
+                .find(|(_, entry)| entry.fingerprint.as_deref() == Some(wanted.as_str()))
+                .map(|(old, _)| old.clone())),
+            None => None,
+        };
+        let Some(old_key) = old_key else {
+            self.unmatched.insert(key);
+            return Ok(None);
+        };
+
+        let tracks = &mut self.loaded_mut().tracks;
+        if let Some(entry) = tracks.remove(&old_key) {
+            tracks.insert(key.clone(), entry);
+        }
+        self.save()?;
+        Ok(Some(key))
+    }
+
+    fn load(&mut self) -> io::Result<()> {
+        if self.file.is_some() {
+            return Ok(());
+        }
+        self.file = Some(store::load_json(&self.path)?);
+        Ok(())
+    }
+
+    fn loaded(&self) -> &RatingsFile {
+        self.file.as_ref().expect("ratings store not loaded")
+    }
+
+    fn loaded_mut(&mut self) -> &mut RatingsFile {
+        self.file.as_mut().expect("ratings store not loaded")
+    }
+
+    fn save(&mut self) -> io::Result<()> {
+        let file = self.loaded_mut();
+        file.version = file.version.max(FORMAT_VERSION);
+        store::save_json(&self.path, self.loaded())
+    }
+}
+
+/// Store key of a whole file
+pub(super) fn path_key(path: &Path) -> String {
+    track_key(path, Duration::ZERO)
+}
+
+/// Store key of the track starting at `start` in a file. A UTF-8 path of a track starting the
+/// file is its own key, which keeps keys readable; any other path is spelled out byte for byte
+/// so distinct paths never share a key, followed by `@` and the start in nanoseconds.
+fn track_key(path: &Path, start: Duration) -> String {
+    let absolute = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    match absolute.to_str() {
+        Some(text) if start.is_zero() && !text.starts_with(RAW_KEY_PREFIX) => text.to_string(),
+        _ => {
+            let mut key = String::from(RAW_KEY_PREFIX);
+            for byte in raw_path_bytes(&absolute) {
+                key.push_str(&format!("{:02x}", byte));
+            }
+            if !start.is_zero() {
+                key.push_str(&format!("@{}", start.as_nanos()));
+            }
+            key
+        }
+    }
+}
+
+/// Path and track start a key was made from
+fn parse_key(key: &str) -> (PathBuf, Duration) {
+    let Some(raw) = key.strip_prefix(RAW_KEY_PREFIX) else {
+        return (PathBuf::from(key), Duration::ZERO);
+    };
+    let (hex, start) = raw.split_once('@').unwrap_or((raw, "0"));
+    let bytes: Vec<u8> = (0..hex.len() / 2)
+        .filter_map(|index| u8::from_str_radix(hex.get(index * 2..index * 2 + 2)?, 16).ok())
+        .collect();
+    let start = start.parse::<u64>().map(Duration::from_nanos).unwrap_or_default();
+    (path_from_raw_bytes(&bytes), start)
+}
+
+/// Bytes of a path as the OS stores it: bytes on Unix, big-endian UTF-16 units on Windows
+fn raw_path_bytes(path: &Path) -> Vec<u8> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes().to_vec()
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        path.as_os_str().encode_wide().flat_map(u16::to_be_bytes).collect()
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        path.to_string_lossy().into_owned().into_bytes()
+    }
+}
+
+fn path_from_raw_bytes(bytes: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStringExt;
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+        PathBuf::from(std::ffi::OsString::from_wide(&units))
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+/// FNV-1a over the file size and its first and last 64 KiB
+pub(super) fn fingerprint(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    };
+    feed(&size.to_le_bytes());
+
+    let mut chunk = Vec::with_capacity(FINGERPRINT_SPAN as usize);
+    (&mut file).take(FINGERPRINT_SPAN).read_to_end(&mut chunk)?;
+    feed(&chunk);
+    if size > FINGERPRINT_SPAN {
+        chunk.clear();
+        file.seek(SeekFrom::Start(size.saturating_sub(FINGERPRINT_SPAN).max(FINGERPRINT_SPAN)))?;
+        file.read_to_end(&mut chunk)?;
+        feed(&chunk);
+    }
+
+    Ok(format!("{:016x}", hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_track(dir: &TempDir, name: &str, content: &[u8]) -> PathBuf {
+        let path = dir.path().join(name);
+        fs::write(&path, content).unwrap();
+        fs::canonicalize(path).unwrap()
+    }
+
+    #[test]
+    fn test_rating_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let track = write_track(&dir, "song.flac", b"audio data");
+        let store_path = dir.path().join(RATINGS_FILE);
+
+        let mut store = RatingStore::new(store_path.clone());
+        assert_eq!(store.get(&track, Duration::ZERO).unwrap(), None);
+        // Nothing is written until something changes
+        assert!(!store_path.exists());
+
+        store.set_rating(&track, Duration::ZERO, Some(4)).unwrap();
+        store.set_favorite(&track, Duration::ZERO, true).unwrap();
+        assert!(store.set_rating(&track, Duration::ZERO, Some(6)).is_err());
+
+        let mut reopened = RatingStore::new(store_path.clone());
+        let entry = reopened.get(&track, Duration::ZERO).unwrap().unwrap();
+        assert_eq!(entry.rating, Some(4));
+        assert!(entry.favorite);
+        assert_eq!(reopened.favorites().unwrap(), vec![track.clone()]);
+
+        // Clearing everything removes the entry
+        reopened.set_rating(&track, Duration::ZERO, None).unwrap();
+        reopened.set_favorite(&track, Duration::ZERO, false).unwrap();
+        assert_eq!(RatingStore::new(store_path).get(&track, Duration::ZERO).unwrap(), None);
+    }
+
+    #[test]
+    fn test_unknown_fields_preserved() {
+        let dir = TempDir::new().unwrap();
+        let track = write_track(&dir, "song.flac", b"audio data");
+        let store_path = dir.path().join(RATINGS_FILE);
+        let key = track.to_string_lossy();
+        fs::write(&store_path, format!(
+            r#"{{"version": 7, "sync": {{"remote": "x"}}, "tracks": {{"{}": {{"rating": 2, "play_count": 12}}}}}}"#,
+            key
+        )).unwrap();
+
+        let mut store = RatingStore::new(store_path.clone());
+        store.set_favorite(&track, Duration::ZERO, true).unwrap();
+
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&store_path).unwrap()).unwrap();
+        assert_eq!(saved["version"], 7);
+        assert_eq!(saved["sync"]["remote"], "x");
+        assert_eq!(saved["tracks"][key.as_ref()]["play_count"], 12);
+        assert_eq!(saved["tracks"][key.as_ref()]["rating"], 2);
+        assert_eq!(saved["tracks"][key.as_ref()]["favorite"], true);
+        assert!(!dir.path().join("ratings.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_corrupt_store_is_not_overwritten() {
+        let dir = TempDir::new().unwrap();
+        let track = write_track(&dir, "song.flac", b"audio data");
+        let store_path = dir.path().join(RATINGS_FILE);
+        fs::write(&store_path, "{ not json").unwrap();
+
+        let mut store = RatingStore::new(store_path.clone());
+        let err = store.set_rating(&track, Duration::ZERO, Some(3)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(fs::read_to_string(&store_path).unwrap(), "{ not json");
+    }
+
+    #[test]
+    fn test_moved_file_keeps_rating() {
+        let dir = TempDir::new().unwrap();
+        let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let original = write_track(&dir, "old.flac", &content);
+        let other = write_track(&dir, "other.flac", b"different");
+        let store_path = dir.path().join(RATINGS_FILE);
+
+        let mut store = RatingStore::new(store_path.clone());
+        store.set_rating(&original, Duration::ZERO, Some(5)).unwrap();
+
+        let moved = dir.path().join("renamed.flac");
+        fs::rename(&original, &moved).unwrap();
+        let moved = fs::canonicalize(moved).unwrap();
+
+        let mut store = RatingStore::new(store_path.clone());
+        assert_eq!(store.get(&other, Duration::ZERO).unwrap(), None);
+        assert_eq!(store.get(&moved, Duration::ZERO).unwrap().unwrap().rating, Some(5));
+
+        // The entry now lives under the new path
+        let saved = fs::read_to_string(&store_path).unwrap();
+        assert!(saved.contains("renamed.flac"));
+        assert!(!saved.contains("old.flac"));
+    }
+
+    #[test]
+    fn test_cue_tracks_rated_separately() {
+        let dir = TempDir::new().unwrap();
+        let image = write_track(&dir, "album.flac", b"whole album image");
+        let store_path = dir.path().join(RATINGS_FILE);
+        let second = Duration::from_millis(183_400);
+
+        let mut store = RatingStore::new(store_path.clone());
+        store.set_rating(&image, Duration::ZERO, Some(2)).unwrap();
+        store.set_rating(&image, second, Some(5)).unwrap();
+        store.set_favorite(&image, second, true).unwrap();
+
+        let mut reopened = RatingStore::new(store_path);
+        assert_eq!(reopened.get(&image, Duration::ZERO).unwrap().unwrap().rating, Some(2));
+        assert_eq!(reopened.get(&image, second).unwrap().unwrap().rating, Some(5));
+        assert_eq!(reopened.get(&image, Duration::from_secs(400)).unwrap(), None);
+        assert_eq!(reopened.favorites().unwrap(), vec![image]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_paths_keep_separate_ratings() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = TempDir::new().unwrap();
+        // Both names turn into the same string under lossy UTF-8 conversion
+        let first = dir.path().join(OsStr::from_bytes(b"song-\xff.flac"));
+        let second = dir.path().join(OsStr::from_bytes(b"song-\xfe.flac"));
+        fs::write(&first, b"first").unwrap();
+        fs::write(&second, b"second").unwrap();
+        assert_eq!(first.to_string_lossy(), second.to_string_lossy());
+        let store_path = dir.path().join(RATINGS_FILE);
+
+        let mut store = RatingStore::new(store_path.clone());
+        store.set_rating(&first, Duration::ZERO, Some(1)).unwrap();
+        store.set_rating(&second, Duration::ZERO, Some(4)).unwrap();
+        store.set_favorite(&first, Duration::ZERO, true).unwrap();
+
+        let mut reopened = RatingStore::new(store_path);
+        assert_eq!(reopened.get(&first, Duration::ZERO).unwrap().unwrap().rating, Some(1));
+        assert_eq!(reopened.get(&second, Duration::ZERO).unwrap().unwrap().rating, Some(4));
+        assert_eq!(reopened.favorites().unwrap(), vec![fs::canonicalize(&first).unwrap()]);
+    }
+
+    #[test]
+    fn test_key_round_trip() {
+        let path = PathBuf::from("/music/hex:odd/track.flac");
+        assert_eq!(track_key(&path, Duration::ZERO), "/music/hex:odd/track.flac");
+        assert_eq!(parse_key(&track_key(&path, Duration::ZERO)), (path.clone(), Duration::ZERO));
+
+        let start = Duration::from_nanos(61_013_333_333);
+        let key = track_key(&path, start);
+        assert!(key.starts_with(RAW_KEY_PREFIX));
+        assert_eq!(parse_key(&key), (path, start));
+    }
+
+    #[test]
+    fn test_annotate_track() {
+        let dir = TempDir::new().unwrap();
+        let path = write_track(&dir, "song.flac", b"audio data");
+        let mut store = RatingStore::new(dir.path().join(RATINGS_FILE));
+        store.set_rating(&path, Duration::ZERO, Some(3)).unwrap();
+
+        let mut track = TrackInfo::new(path, crate::models::AudioMetadata::new(), std::time::Duration::ZERO, 10);
+        store.annotate(&mut track).unwrap();
+        assert_eq!(track.rating, Some(3));
+        assert!(!track.favorite);
+    }
+}
@@ -0,0 +1,600 @@
+/*!
+Vorbis comment editing for `tag` and `scan-loudness --write-tags`
+
+Only FLAC and Ogg Vorbis are handled, both of which keep their tags in a Vorbis
+comment block that can be rebuilt without touching the audio:
+
+- FLAC: the VORBIS_COMMENT metadata block is replaced (or added after
+  STREAMINFO) and the frames are copied through unchanged.
+- Ogg Vorbis: the comment header packet is replaced and the header pages are
+  laid out again; the audio pages keep their bytes apart from a new page
+  sequence number and checksum when the number of header pages changes.
+
+The new file is written next to the old one and renamed over it, so a failure
+part way leaves the original in place. Opus, chained or multiplexed Ogg streams
+and everything else are refused.
+*/
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// FLAC metadata block types
+const STREAMINFO: u8 = 0;
+const VORBIS_COMMENT: u8 = 4;
+
+/// Whether `path` holds a format whose comments can be read and written here
+pub fn can_write(path: &Path) -> bool {
+    let mut header = [0u8; 64];
+    let Ok(read) = File::open(path).and_then(|mut file| file.read(&mut header)) else {
+        return false;
+    };
+    let header = &header[..read];
+    header.starts_with(b"fLaC") || (header.starts_with(b"OggS") && header.get(28..35) == Some(b"\x01vorbis"))
+}
+
+/// Whether `key` can name a Vorbis comment: printable ASCII other than `=`
+pub fn valid_key(key: &str) -> bool {
+    !key.is_empty() && key.bytes().all(|byte| (0x20..=0x7d).contains(&byte) && byte != b'=')
+}
+
+/// The file's Vorbis comments as key and value, in file order
+pub fn read_comments(path: &Path) -> io::Result<Vec<(String, String)>> {
+    let comments = match &magic(path)? {
+        b"fLaC" => flac_comments(&read_flac_blocks(&mut BufReader::new(File::open(path)?))?)?,
+        b"OggS" => OggHeaders::read(&fs::read(path)?)?.comments()?,
+        _ => return Err(unsupported("only FLAC and Ogg Vorbis files can be tagged")),
+    };
+    Ok(comments.pairs())
+}
+
+/// Set `tags` in the file's Vorbis comments, replacing comments with the same keys
+pub fn write_comments(path: &Path, tags: &[(&str, String)]) -> io::Result<()> {
+    edit(path, |comments| comments.set(tags))
+}
+
+/// Remove every comment keyed like one of `keys`
+pub fn remove_comments(path: &Path, keys: &[&str]) -> io::Result<()> {
+    edit(path, |comments| comments.remove(keys))
+}
+
+fn magic(path: &Path) -> io::Result<[u8; 4]> {
+    let mut magic = [0u8; 4];
+    File::open(path)?.read_exact(&mut magic)?;
+    Ok(magic)
+}
+
+fn edit(path: &Path, change: impl FnOnce(&mut Comments)) -> io::Result<()> {
+    match &magic(path)? {
+        b"fLaC" => write_flac(path, change),
+        b"OggS" => write_ogg(path, change),
+        _ => Err(unsupported("only FLAC and Ogg Vorbis files can be tagged")),
+    }
+}
+
+fn unsupported(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, reason.to_string())
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.to_string())
+}
+
+/// A Vorbis comment block body: the vendor string and `KEY=value` comments
+#[derive(Debug, Default)]
+struct Comments {
+    vendor: Vec<u8>,
+    comments: Vec<Vec<u8>>,
+}
+
+impl Comments {
+    fn parse(data: &[u8]) -> io::Result<Self> {
+        let mut rest = data;
+        let mut take = |len: usize| -> io::Result<&[u8]> {
+            if rest.len() < len {
+                return Err(invalid("Vorbis comment block is truncated"));
+            }
+            let (head, tail) = rest.split_at(len);
+            rest = tail;
+            Ok(head)
+        };
+        let vendor_length = read_u32(take(4)?) as usize;
+        let vendor = take(vendor_length)?.to_vec();
+        let count = read_u32(take(4)?) as usize;
+        let mut comments = Vec::new();
+        for _ in 0..count {
+            let comment_length = read_u32(take(4)?) as usize;
+            comments.push(take(comment_length)?.to_vec());
+        }
+        Ok(Self { vendor, comments })
+    }
+
+    /// Drop every comment keyed like one of `keys`; keys compare case-insensitively
+    fn remove(&mut self, keys: &[&str]) {
+        self.comments.retain(|comment| {
+            let key = comment.split(|&byte| byte == b'=').next().unwrap_or_default();
+            !keys.iter().any(|wanted| key.eq_ignore_ascii_case(wanted.as_bytes()))
+        });
+    }
+
+    /// Replace every comment keyed like one of `tags` and append `tags`
+    fn set(&mut self, tags: &[(&str, String)]) {
+        self.remove(&tags.iter().map(|(key, _)| *key).collect::<Vec<_>>());
+        self.comments.extend(tags.iter().map(|(key, value)| format!("{}={}", key, value).into_bytes()));
+    }
+
+    /// Comments split at their first `=`; ones without it are left out
+    fn pairs(&self) -> Vec<(String, String)> {
+        self.comments.iter()
+            .filter_map(|comment| {
+                let comment = String::from_utf8_lossy(comment);
+                let (key, value) = comment.split_once('=')?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect()
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend((self.vendor.len() as u32).to_le_bytes());
+        bytes.extend(&self.vendor);
+        bytes.extend((self.comments.len() as u32).to_le_bytes());
+        for comment in &self.comments {
+            bytes.extend((comment.len() as u32).to_le_bytes());
+            bytes.extend(comment);
+        }
+        bytes
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Write through `write` to a temporary file beside `path`, then rename it over `path`
+fn replace_file(path: &Path, write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>) -> io::Result<()> {
+    let mut temp_name = path.as_os_str().to_os_string();
+    temp_name.push(".tags.tmp");
+    let temp_path = PathBuf::from(temp_name);
+
+    let result = (|| {
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        write(&mut writer)?;
+        let file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.set_permissions(fs::metadata(path)?.permissions())?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Every metadata block of a FLAC file, leaving the reader at the first frame
+fn read_flac_blocks(reader: &mut impl Read) -> io::Result<Vec<(u8, Vec<u8>)>> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    let mut blocks: Vec<(u8, Vec<u8>)> = Vec::new();
+    loop {
+        let mut header = [0u8; 4];
+        reader.read_exact(&mut header)?;
+        let length = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let mut data = vec![0u8; length];
+        reader.read_exact(&mut data)?;
+        blocks.push((header[0] & 0x7f, data));
+        if header[0] & 0x80 != 0 {
+            break;
+        }
+    }
+    if blocks.first().map(|(kind, _)| *kind) != Some(STREAMINFO) {
+        return Err(invalid("FLAC file does not start with STREAMINFO"));
+    }
+    Ok(blocks)
+}
+
+/// The VORBIS_COMMENT block, or an empty one when the file has none
+fn flac_comments(blocks: &[(u8, Vec<u8>)]) -> io::Result<Comments> {
+    match blocks.iter().find(|(kind, _)| *kind == VORBIS_COMMENT) {
+        Some((_, data)) => Comments::parse(data),
+        None => Ok(Comments { vendor: b"rmusic".to_vec(), comments: Vec::new() }),
+    }
+}
+
+fn write_flac(path: &Path, change: impl FnOnce(&mut Comments)) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut blocks = read_flac_blocks(&mut reader)?;
+    let mut comments = flac_comments(&blocks)?;
+    change(&mut comments);
+    let data = comments.to_bytes();
+    if data.len() >= 1 << 24 {
+        return Err(invalid("Vorbis comment block is too large for FLAC"));
+    }
+    match blocks.iter_mut().find(|(kind, _)| *kind == VORBIS_COMMENT) {
+        Some(block) => block.1 = data,
+        None => blocks.insert(1, (VORBIS_COMMENT, data)),
+    }
+
+    replace_file(path, |writer| {
+        writer.write_all(b"fLaC")?;
+        let last = blocks.len() - 1;
+        for (index, (kind, data)) in blocks.iter().enumerate() {
+            let flag = if index == last { 0x80 } else { 0 };
+            let length = (data.len() as u32).to_be_bytes();
+            writer.write_all(&[kind | flag, length[1], length[2], length[3]])?;
+            writer.write_all(data)?;
+        }
+        io::copy(&mut reader, writer)?;
+        Ok(())
+    })
+}
+
+/// One Ogg page: its header fields and the lacing values and bytes it carries
+#[derive(Debug, Clone)]
+struct Page {
+    header_type: u8,
+    granule: u64,
+    serial: u32,
+    sequence: u32,
+    lacing: Vec<u8>,
+    body: Vec<u8>,
+}
+
+impl Page {
+    /// The page at the start of `bytes` and its length
+    fn parse(bytes: &[u8]) -> io::Result<(Self, usize)> {
+        if bytes.len() < 27 || &bytes[..4] != b"OggS" {
+            return Err(invalid("expected an Ogg page"));
+        }
+        let segments = bytes[26] as usize;
+        let lacing = bytes.get(27..27 + segments).ok_or_else(|| invalid("Ogg page is truncated"))?.to_vec();
+        let body_length: usize = lacing.iter().map(|&value| value as usize).sum();
+        let start = 27 + segments;
+        let body = bytes.get(start..start + body_length).ok_or_else(|| invalid("Ogg page is truncated"))?.to_vec();
+        let page = Self {
+            header_type: bytes[5],
+            granule: u64::from_le_bytes(bytes[6..14].try_into().unwrap()),
+            serial: u32::from_le_bytes(bytes[14..18].try_into().unwrap()),
+            sequence: u32::from_le_bytes(bytes[18..22].try_into().unwrap()),
+            lacing,
+            body,
+        };
+        Ok((page, start + body_length))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = b"OggS\0".to_vec();
+        bytes.push(self.header_type);
+        bytes.extend(self.granule.to_le_bytes());
+        bytes.extend(self.serial.to_le_bytes());
+        bytes.extend(self.sequence.to_le_bytes());
+        bytes.extend([0; 4]);
+        bytes.push(self.lacing.len() as u8);
+        bytes.extend(&self.lacing);
+        bytes.extend(&self.body);
+        let crc = ogg_crc(&bytes);
+        bytes[22..26].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+}
+
+/// CRC-32 of an Ogg page: polynomial 0x04c11db7, no reflection, starting from zero
+fn ogg_crc(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0u32, |crc, &byte| {
+        (0..8).fold(crc ^ ((byte as u32) << 24), |crc, _| if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04c1_1db7 } else { crc << 1 })
+    })
+}
+
+/// Pages for the comment and setup headers, numbered from `sequence`
+fn header_pages(packets: &[Vec<u8>], serial: u32, sequence: u32) -> Vec<Page> {
+    // Lacing values with the bytes they cover, and whether each ends a packet
+    let mut segments: Vec<(u8, &[u8], bool)> = Vec::new();
+    for packet in packets {
+        let mut chunks = packet.chunks(255).peekable();
+        while let Some(chunk) = chunks.next() {
+            segments.push((chunk.len() as u8, chunk, chunk.len() < 255));
+            if chunks.peek().is_none() && chunk.len() == 255 {
+                segments.push((0, &[], true));
+            }
+        }
+        if packet.is_empty() {
+            segments.push((0, &[], true));
+        }
+    }
+
+    let mut pages = Vec::new();
+    let mut continued = false;
+    for (index, page_segments) in segments.chunks(255).enumerate() {
+        let ends_packet = page_segments.iter().any(|(_, _, ends)| *ends);
+        pages.push(Page {
+            header_type: if continued { 0x01 } else { 0 },
+            // Header pages are at granule 0; a page no packet ends on has none
+            granule: if ends_packet { 0 } else { u64::MAX },
+            serial,
+            sequence: sequence + index as u32,
+            lacing: page_segments.iter().map(|(value, _, _)| *value).collect(),
+            body: page_segments.iter().flat_map(|(_, bytes, _)| bytes.iter().copied()).collect(),
+        });
+        continued = !page_segments.last().is_some_and(|(_, _, ends)| *ends);
+    }
+    pages
+}
+
+/// The identification page and the comment and setup headers of an Ogg Vorbis stream
+struct OggHeaders {
+    serial: u32,
+    /// Length of the identification page
+    first_length: usize,
+    /// Comment and setup packets
+    packets: Vec<Vec<u8>>,
+    /// Pages the two packets took, and where the audio pages start
+    page_count: usize,
+    audio_start: usize,
+}
+
+impl OggHeaders {
+    fn read(bytes: &[u8]) -> io::Result<Self> {
+        let (first, first_length) = Page::parse(bytes)?;
+        if first.body.starts_with(b"OpusHead") {
+            return Err(unsupported("Opus files carry R128 gain tags, not ReplayGain"));
+        }
+        if !first.body.starts_with(b"\x01vorbis") || first.lacing.last() == Some(&255) {
+            return Err(unsupported("only Ogg Vorbis streams can be tagged"));
+        }
+        let serial = first.serial;
+
+        // Gather the comment and setup packets; the setup packet must finish its page
+        let mut packets: Vec<Vec<u8>> = vec![Vec::new()];
+        let mut offset = first_length;
+        let mut page_count = 0;
+        while packets.len() < 3 {
+            let (page, length) = Page::parse(&bytes[offset..])?;
+            if page.serial != serial {
+                return Err(unsupported("multiplexed Ogg streams cannot be tagged"));
+            }
+            offset += length;
+            page_count += 1;
+            let mut start = 0;
+            for &value in &page.lacing {
+                let end = start + value as usize;
+                if packets.len() > 2 {
+                    return Err(invalid("audio starts on the page that ends the Vorbis headers"));
+                }
+                packets.last_mut().unwrap().extend(&page.body[start..end]);
+                start = end;
+                if value < 255 {
+                    packets.push(Vec::new());
+                }
+            }
+        }
+        packets.pop();
+        if packets.len() != 2 || !packets[0].starts_with(b"\x03vorbis") {
+            return Err(invalid("Ogg Vorbis comment header is missing"));
+        }
+        Ok(Self { serial, first_length, packets, page_count, audio_start: offset })
+    }
+
+    /// The comment block, after the packet type and `vorbis` that start the comment packet
+    fn comments(&self) -> io::Result<Comments> {
+        Comments::parse(&self.packets[0][7..])
+    }
+}
+
+fn write_ogg(path: &Path, change: impl FnOnce(&mut Comments)) -> io::Result<()> {
+    let bytes = fs::read(path)?;
+    let headers = OggHeaders::read(&bytes)?;
+    let mut comments = headers.comments()?;
+    change(&mut comments);
+    let OggHeaders { serial, first_length, mut packets, page_count, audio_start } = headers;
+
+    // The comment packet ends with a framing bit after the comments
+    let mut comment_packet = b"\x03vorbis".to_vec();
+    comment_packet.extend(comments.to_bytes());
+    comment_packet.push(1);
+    packets[0] = comment_packet;
+
+    let pages = header_pages(&packets, serial, 1);
+    let shift = pages.len() as i64 - page_count as i64;
+    replace_file(path, |writer| {
+        writer.write_all(&bytes[..first_length])?;
+        for page in &pages {
+            writer.write_all(&page.to_bytes())?;
+        }
+        let mut rest = &bytes[audio_start..];
+        while !rest.is_empty() {
+            let (mut page, length) = Page::parse(rest)?;
+            if shift == 0 {
+                writer.write_all(&rest[..length])?;
+            } else if page.serial != serial {
+                return Err(unsupported("chained Ogg streams cannot be tagged"));
+            } else {
+                page.sequence = (page.sequence as i64 + shift) as u32;
+                writer.write_all(&page.to_bytes())?;
+            }
+            rest = &rest[length..];
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(dir: &TempDir, name: &str, bytes: &[u8]) -> PathBuf {
+        let path = dir.path().join(name);
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn comment_block(comments: &[&str]) -> Vec<u8> {
+        Comments {
+            vendor: b"test".to_vec(),
+            comments: comments.iter().map(|comment| comment.as_bytes().to_vec()).collect(),
+        }.to_bytes()
+    }
+
+    /// Comments of the VORBIS_COMMENT block and the bytes after the metadata
+    fn read_flac(path: &Path) -> (Vec<String>, Vec<u8>) {
+        let bytes = fs::read(path).unwrap();
+        let mut offset = 4;
+        let mut comments = Vec::new();
+        loop {
+            let header = &bytes[offset..offset + 4];
+            let length = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+            let data = &bytes[offset + 4..offset + 4 + length];
+            if header[0] & 0x7f == VORBIS_COMMENT {
+                comments = Comments::parse(data).unwrap().comments.into_iter()
+                    .map(|comment| String::from_utf8(comment).unwrap())
+                    .collect();
+            }
+            offset += 4 + length;
+            if header[0] & 0x80 != 0 {
+                return (comments, bytes[offset..].to_vec());
+            }
+        }
+    }
+
+    fn flac(blocks: &[(u8, Vec<u8>)], frames: &[u8]) -> Vec<u8> {
+        let mut bytes = b"fLaC".to_vec();
+        for (index, (kind, data)) in blocks.iter().enumerate() {
+            let flag = if index == blocks.len() - 1 { 0x80 } else { 0 };
+            bytes.push(kind | flag);
+            bytes.extend(&(data.len() as u32).to_be_bytes()[1..]);
+            bytes.extend(data);
+        }
+        bytes.extend(frames);
+        bytes
+    }
+
+    #[test]
+    fn test_flac_comments_are_replaced_and_frames_kept() {
+        let dir = TempDir::new().unwrap();
+        let frames: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        let tagged = write(&dir, "tagged.flac", &flac(&[
+            (STREAMINFO, vec![0; 34]),
+            (VORBIS_COMMENT, comment_block(&["TITLE=Song", "replaygain_track_gain=-1.00 dB"])),
+            (1, vec![0; 1024]),
+        ], &frames));
+        let untagged = write(&dir, "untagged.flac", &flac(&[(STREAMINFO, vec![0; 34])], &frames));
+        assert!(can_write(&tagged));
+
+        let tags = [("REPLAYGAIN_TRACK_GAIN", "-6.20 dB".to_string())];
+        write_comments(&tagged, &tags).unwrap();
+        write_comments(&untagged, &tags).unwrap();
+
+        assert_eq!(read_flac(&tagged), (vec!["TITLE=Song".to_string(), "REPLAYGAIN_TRACK_GAIN=-6.20 dB".to_string()], frames.clone()));
+        assert_eq!(read_flac(&untagged), (vec!["REPLAYGAIN_TRACK_GAIN=-6.20 dB".to_string()], frames));
+        assert!(!dir.path().join("tagged.flac.tags.tmp").exists());
+    }
+
+    /// Ogg stream of a Vorbis-like identification header, `comment`, a setup header and `audio_pages` one-packet pages
+    fn ogg(comment: &[u8], audio_pages: u32) -> Vec<u8> {
+        let mut ident = b"\x01vorbis".to_vec();
+        ident.extend([0; 23]);
+        let mut bytes = Page { header_type: 0x02, granule: 0, serial: 7, sequence: 0, lacing: vec![30], body: ident }.to_bytes();
+
+        let mut comment_packet = b"\x03vorbis".to_vec();
+        comment_packet.extend(comment);
+        comment_packet.push(1);
+        let setup = b"\x05vorbis setup".to_vec();
+        for page in header_pages(&[comment_packet, setup], 7, 1) {
+            bytes.extend(page.to_bytes());
+        }
+        for index in 0..audio_pages {
+            let header_type = if index == audio_pages - 1 { 0x04 } else { 0 };
+            let page = Page { header_type, granule: 1024 * (index as u64 + 1), serial: 7, sequence: 2 + index, lacing: vec![3], body: vec![index as u8; 3] };
+            bytes.extend(page.to_bytes());
+        }
+        bytes
+    }
+
+    fn pages(bytes: &[u8]) -> Vec<Page> {
+        let mut pages = Vec::new();
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let (page, length) = Page::parse(rest).unwrap();
+            // Every page must still carry a valid checksum
+            let mut unchecked = rest[..length].to_vec();
+            unchecked[22..26].copy_from_slice(&[0; 4]);
+            assert_eq!(ogg_crc(&unchecked).to_le_bytes(), rest[22..26]);
+            pages.push(page);
+            rest = &rest[length..];
+        }
+        pages
+    }
+
+    #[test]
+    fn test_ogg_vorbis_comments_grow_onto_new_pages() {
+        let dir = TempDir::new().unwrap();
+        let path = write(&dir, "song.ogg", &ogg(&comment_block(&["TITLE=Song"]), 3));
+        assert!(can_write(&path));
+        assert_eq!(pages(&fs::read(&path).unwrap()).len(), 5);
+
+        // A comment this long needs two more header pages
+        let long = "x".repeat(255 * 255 + 100);
+        write_comments(&path, &[("REPLAYGAIN_TRACK_GAIN", "-6.20 dB".to_string()), ("LYRICS", long.clone())]).unwrap();
+
+        let written = pages(&fs::read(&path).unwrap());
+        assert_eq!(written.len(), 6);
+        assert_eq!(written.iter().map(|page| page.sequence).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(written[2].header_type, 0x01);
+        assert_eq!(written[1].granule, u64::MAX);
+        assert_eq!(written[5].body, vec![2; 3]);
+        assert_eq!(written[5].granule, 3072);
+
+        // Reassemble the comment packet from the header pages
+        let header: Vec<u8> = written[1..3].iter().flat_map(|page| page.body.clone()).collect();
+        let comments = Comments::parse(&header[7..]).unwrap();
+        assert_eq!(comments.comments, vec![
+            b"TITLE=Song".to_vec(),
+            b"REPLAYGAIN_TRACK_GAIN=-6.20 dB".to_vec(),
+            format!("LYRICS={}", long).into_bytes(),
+        ]);
+        assert!(header.ends_with(b"\x01\x05vorbis setup"));
+    }
+
+    #[test]
+    fn test_comments_round_trip_in_flac_and_ogg() {
+        let dir = TempDir::new().unwrap();
+        let flac_path = write(&dir, "song.flac", &flac(&[
+            (STREAMINFO, vec![0; 34]),
+            (VORBIS_COMMENT, comment_block(&["TITLE=Song", "COMMENT=rip", "ARTIST=Band"])),
+        ], b"frames"));
+        let ogg_path = write(&dir, "song.ogg", &ogg(&comment_block(&["TITLE=Song", "COMMENT=rip", "ARTIST=Band"]), 2));
+
+        for path in [&flac_path, &ogg_path] {
+            write_comments(path, &[("TITLE", "Better Song".to_string()), ("TRACKNUMBER", "3".to_string())]).unwrap();
+            remove_comments(path, &["comment"]).unwrap();
+            assert_eq!(read_comments(path).unwrap(), vec![
+                ("ARTIST".to_string(), "Band".to_string()),
+                ("TITLE".to_string(), "Better Song".to_string()),
+                ("TRACKNUMBER".to_string(), "3".to_string()),
+            ]);
+        }
+        assert_eq!(read_flac(&flac_path).1, b"frames");
+        assert_eq!(pages(&fs::read(&ogg_path).unwrap()).len(), 4);
+
+        assert!(valid_key("REPLAYGAIN_TRACK_GAIN") && valid_key("Custom Field"));
+        assert!(!valid_key("") && !valid_key("A=B") && !valid_key("TITLE~"));
+    }
+
+    #[test]
+    fn test_other_formats_are_refused() {
+        let dir = TempDir::new().unwrap();
+        let wav = write(&dir, "song.wav", b"RIFF\0\0\0\0WAVE");
+        let mut opus_head = b"OpusHead".to_vec();
+        opus_head.extend([0; 11]);
+        let opus = write(&dir, "song.opus", &Page { header_type: 0x02, granule: 0, serial: 1, sequence: 0, lacing: vec![19], body: opus_head }.to_bytes());
+
+        for path in [&wav, &opus] {
+            assert!(!can_write(path));
+            let error = write_comments(path, &[("REPLAYGAIN_TRACK_GAIN", "0.00 dB".to_string())]).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+            assert_eq!(read_comments(path).unwrap_err().kind(), io::ErrorKind::Unsupported);
+        }
+        let error = write_comments(&opus, &[("TITLE", "Song".to_string())]).unwrap_err();
+        assert!(error.to_string().contains("R128"));
+    }
+}
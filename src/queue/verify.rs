@@ -0,0 +1,263 @@
+/*!
+Library verification for `verify`
+
+Each file is opened with the same decoder playback would use and decoded to the
+end into a null sink, without touching the output device. A file fails when it
+cannot be opened or a packet does not decode, when its decoded length differs
+from the length in its header by more than a second (usually a truncated
+download or copy), or, for FLAC, when the audio does not match the MD5
+signature in STREAMINFO.
+*/
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use crate::audio::AudioDecoder;
+use crate::audio::engine::AudioEngineImpl;
+use crate::error::DecodeError;
+use super::integrity;
+
+/// Largest accepted difference between the header and decoded lengths
+pub const DURATION_TOLERANCE: Duration = Duration::from_secs(1);
+
+/// Why a file failed verification
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// The file could not be opened or a packet did not decode
+    Corrupt(String),
+    /// Decoding ended well before the length in the header
+    Truncated { expected: Duration, decoded: Duration },
+    /// More audio decoded than the header announces
+    TooLong { expected: Duration, decoded: Duration },
+    /// FLAC audio that does not hash to its MD5 signature
+    Md5Mismatch,
+}
+
+impl Problem {
+    /// Short label for the summary table
+    pub fn label(&self) -> &'static str {
+        match self {
+            Problem::Corrupt(_) => "corrupt",
+            Problem::Truncated { .. } => "truncated",
+            Problem::TooLong { .. } => "length",
+            Problem::Md5Mismatch => "md5",
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Corrupt(reason) => write!(f, "{}", reason),
+            Problem::Truncated { expected, decoded } => {
+                write!(f, "decoded {:.1}s of {:.1}s", decoded.as_secs_f64(), expected.as_secs_f64())
+            }
+            Problem::TooLong { expected, decoded } => {
+                write!(f, "decoded {:.1}s, header says {:.1}s", decoded.as_secs_f64(), expected.as_secs_f64())
+            }
+            Problem::Md5Mismatch => write!(f, "decoded audio does not match the FLAC MD5 signature"),
+        }
+    }
+}
+
+/// Outcome of verifying one file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileCheck {
+    pub path: PathBuf,
+    /// Length of the audio that decoded
+    pub decoded: Duration,
+    pub problem: Option<Problem>,
+}
+
+/// Outcome of a `verify` run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VerifySummary {
+    /// Checked files, in the order they were checked
+    pub checks: Vec<FileCheck>,
+    /// Files left unchecked because the run was cancelled
+    pub skipped: usize,
+}
+
+impl VerifySummary {
+    pub fn failures(&self) -> impl Iterator<Item = &FileCheck> {
+        self.checks.iter().filter(|check| check.problem.is_some())
+    }
+
+    pub fn failed(&self) -> usize {
+        self.failures().count()
+    }
+}
+
+/// Files in `paths` with repeats dropped, such as CUE tracks sharing one image
+pub fn unique_files(paths: impl IntoIterator<Item = PathBuf>) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    paths.into_iter().filter(|path| seen.insert(path.clone())).collect()
+}
+
+/// Verify `files` one after another, calling `progress` after each
+///
+/// Setting `cancel` stops the run after the file being decoded; the rest count as skipped.
+pub fn run(files: &[PathBuf], cancel: &AtomicBool, mut progress: impl FnMut(usize, &FileCheck)) -> VerifySummary {
+    let mut summary = VerifySummary::default();
+    for (index, path) in files.iter().enumerate() {
+        let check = check_file(path, cancel);
+        if cancel.load(Ordering::Relaxed) {
+            summary.skipped = files.len() - index;
+            break;
+        }
+        progress(index + 1, &check);
+        summary.checks.push(check);
+    }
+    summary
+}
+
+/// Decode `path` to the end and compare what came out with its header
+pub fn check_file(path: &Path, cancel: &AtomicBool) -> FileCheck {
+    let mut check = FileCheck { path: path.to_path_buf(), decoded: Duration::ZERO, problem: None };
+    let mut decoder = match AudioEngineImpl::open_decoder(path) {
+        Ok(decoder) => decoder,
+        Err(e) => {
+            check.problem = Some(Problem::Corrupt(e.to_string()));
+            return check;
+        }
+    };
+
+    let frames = match decode_to_null(decoder.as_mut(), cancel) {
+        Ok(frames) => frames,
+        Err(e) => {
+            check.problem = Some(Problem::Corrupt(e.to_string()));
+            return check;
+        }
+    };
+    check.decoded = Duration::from_secs_f64(frames as f64 / decoder.sample_rate().max(1) as f64);
+
+    // Streams without a length in their header have nothing to compare against
+    if let Some(expected) = decoder.duration().filter(|expected| !expected.is_zero()) {
+        if check.decoded + DURATION_TOLERANCE < expected {
+            check.problem = Some(Problem::Truncated { expected, decoded: check.decoded });
+        } else if check.decoded > expected + DURATION_TOLERANCE {
+            check.problem = Some(Problem::TooLong { expected, decoded: check.decoded });
+        }
+    }
+
+    let is_flac = path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ext.eq_ignore_ascii_case("flac"));
+    if check.problem.is_none() && is_flac {
+        check.problem = match integrity::flac_md5_matches(path) {
+            Ok(true) => None,
+            Ok(false) => Some(Problem::Md5Mismatch),
+            Err(reason) => Some(Problem::Corrupt(reason)),
+        };
+    }
+    check
+}
+
+/// Decode everything `decoder` produces and throw it away; returns the frames decoded
+///
+/// Stops early, with the frames so far, once `cancel` is set.
+pub fn decode_to_null(decoder: &mut dyn AudioDecoder, cancel: &AtomicBool) -> Result<u64, DecodeError> {
+    let mut frames = 0u64;
+    while !cancel.load(Ordering::Relaxed) {
+        match decoder.decode_next()? {
+            Some(buffer) => frames += buffer.frames as u64,
+            None => break,
+        }
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::test_files;
+    use std::fs;
+    use symphonia::core::checksum::Md5;
+    use symphonia::core::io::Monitor;
+    use tempfile::TempDir;
+
+    /// 4096-sample frames in the test files
+    const BLOCK: usize = 4096;
+    const FRAMES: usize = 33;
+
+    /// MD5 of the silence in the test files: 16-bit little-endian zeros
+    fn silence_md5() -> [u8; 16] {
+        let mut md5 = Md5::default();
+        md5.process_buf_bytes(&vec![0; BLOCK * FRAMES * 2]);
+        md5.md5()
+    }
+
+    /// About three seconds of mono 16-bit 44.1 kHz silence, one CONSTANT subframe per frame
+    fn flac_bytes(md5: [u8; 16]) -> Vec<u8> {
+        let streaminfo = test_files::flac_streaminfo(BLOCK as u16, 44_100, 1, 16, (BLOCK * FRAMES) as u64, md5);
+        let mut bytes = b"fLaC".to_vec();
+        bytes.extend(test_files::flac_block(0, true, &streaminfo));
+        for index in 0..FRAMES {
+            // 4096-sample block, 44.1 kHz, mono, 16-bit, frame number
+            bytes.extend(test_files::flac_frame(&[0xFF, 0xF8, 0xC9, 0x08, index as u8], &[0x00, 0x00, 0x00]));
+        }
+        bytes
+    }
+
+    fn write(dir: &TempDir, name: &str, bytes: &[u8]) -> PathBuf {
+        let path = dir.path().join(name);
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_intact_flac_passes() {
+        let dir = TempDir::new().unwrap();
+        let path = write(&dir, "intact.flac", &flac_bytes(silence_md5()));
+
+        let check = check_file(&path, &AtomicBool::new(false));
+        assert_eq!(check.problem, None);
+        assert_eq!((check.decoded.as_secs_f64() * 44_100.0).round() as usize, BLOCK * FRAMES);
+    }
+
+    #[test]
+    fn test_truncated_flac_is_reported() {
+        let dir = TempDir::new().unwrap();
+        let bytes = flac_bytes(silence_md5());
+        // Cut partway into a frame, about a second in
+        let path = write(&dir, "truncated.flac", &bytes[..42 + 11 * 11 + 5]);
+
+        let check = check_file(&path, &AtomicBool::new(false));
+        match check.problem {
+            Some(Problem::Truncated { expected, decoded }) => {
+                assert_eq!((expected.as_secs_f64() * 44_100.0).round() as usize, BLOCK * FRAMES);
+                // The last whole frame may be dropped along with the cut one
+                assert!((decoded.as_secs_f64() * 44_100.0).round() as usize <= BLOCK * 11);
+            }
+            other => panic!("expected a truncated file, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_md5_mismatch_and_garbage_are_reported() {
+        let dir = TempDir::new().unwrap();
+        let mismatched = write(&dir, "mismatched.flac", &flac_bytes([0xAA; 16]));
+        let garbage = write(&dir, "garbage.flac", b"not audio at all");
+        let cancel = AtomicBool::new(false);
+
+        assert_eq!(check_file(&mismatched, &cancel).problem, Some(Problem::Md5Mismatch));
+        assert!(matches!(check_file(&garbage, &cancel).problem, Some(Problem::Corrupt(_))));
+    }
+
+    #[test]
+    fn test_run_reports_progress_and_stops_when_cancelled() {
+        let dir = TempDir::new().unwrap();
+        let good = write(&dir, "good.flac", &flac_bytes(silence_md5()));
+        let bad = write(&dir, "bad.flac", b"not audio at all");
+        let files = unique_files([good.clone(), bad.clone(), good.clone()]);
+        assert_eq!(files, vec![good.clone(), bad.clone()]);
+
+        let mut seen = Vec::new();
+        let summary = run(&files, &AtomicBool::new(false), |done, check| seen.push((done, check.path.clone())));
+        assert_eq!(seen, vec![(1, good.clone()), (2, bad.clone())]);
+        assert_eq!((summary.checks.len(), summary.failed(), summary.skipped), (2, 1, 0));
+
+        let summary = run(&files, &AtomicBool::new(true), |_, _| panic!("nothing is checked once cancelled"));
+        assert_eq!((summary.checks.len(), summary.skipped), (0, 2));
+    }
+}
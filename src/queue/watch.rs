@@ -0,0 +1,234 @@
+/*!
+Watching directories for audio files that appear or go away
+
+Downloads and copies write a file over several seconds, so a path is only
+reported once its size has stayed the same for the settle period. A rename
+is seen as its old path going away and its new path appearing.
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use super::QueueManagerImpl;
+
+/// A settled change under a watched directory
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DirectoryChange {
+    /// An audio file that is complete, new or rewritten
+    Added(PathBuf),
+    /// A file or directory that no longer exists
+    Removed(PathBuf),
+}
+
+/// A path with recent events, waiting to settle
+struct Pending {
+    last_event: Instant,
+    size: Option<u64>,
+}
+
+/// Reports audio files appearing under, and paths leaving, a set of directories
+pub struct DirectoryWatcher {
+    events: Receiver<PathBuf>,
+    pending: HashMap<PathBuf, Pending>,
+    directories: Vec<PathBuf>,
+    settle_period: Duration,
+    watcher: RecommendedWatcher,
+}
+
+impl DirectoryWatcher {
+    /// How long a file must keep its size before it is reported
+    pub const SETTLE_PERIOD: Duration = Duration::from_secs(2);
+
+    pub fn new(settle_period: Duration) -> notify::Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            let Ok(event) = result else { return };
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            for path in event.paths {
+                let _ = sender.send(path);
+            }
+        })?;
+        Ok(Self { events, pending: HashMap::new(), directories: Vec::new(), settle_period, watcher })
+    }
+
+    /// Watched directories, in the order they were added
+    pub fn directories(&self) -> &[PathBuf] {
+        &self.directories
+    }
+
+    /// Watch `directory` and everything below it; false if it was already watched
+    pub fn add(&mut self, directory: &Path) -> notify::Result<bool> {
+        if self.directories.iter().any(|watched| watched == directory) {
+            return Ok(false);
+        }
+        self.watcher.watch(directory, RecursiveMode::Recursive)?;
+        self.directories.push(directory.to_path_buf());
+        Ok(true)
+    }
+
+    /// Stop watching `directory`; false if it was not watched
+    pub fn remove(&mut self, directory: &Path) -> bool {
+        let Some(index) = self.directories.iter().position(|watched| watched == directory) else {
+            return false;
+        };
+        // The directory may already be gone, taking its watch with it
+        let _ = self.watcher.unwatch(directory);
+        self.directories.remove(index);
+        let directories = &self.directories;
+        self.pending.retain(|path, _| directories.iter().any(|watched| path.starts_with(watched)));
+        true
+    }
+
+    /// Changes whose paths have been quiet for the settle period
+    ///
+    /// A new directory is reported as the audio files inside it.
+    pub fn poll(&mut self) -> Vec<DirectoryChange> {
+        let now = Instant::now();
+        for path in std::iter::from_fn(|| self.events.try_recv().ok()) {
+            let size = file_size(&path);
+            self.pending.insert(path, Pending { last_event: now, size });
+        }
+
+        let mut settled = Vec::new();
+        for (path, pending) in self.pending.iter_mut() {
+            if now.duration_since(pending.last_event) < self.settle_period {
+                continue;
+            }
+            // Still being written: wait for another quiet period
+            let size = file_size(path);
+            if size != pending.size {
+                *pending = Pending { last_event: now, size };
+                continue;
+            }
+            settled.push(path.clone());
+        }
+        settled.sort();
+
+        let mut changes = Vec::new();
+        for path in settled {
+            self.pending.remove(&path);
+            match fs::metadata(&path) {
+                Ok(metadata) if metadata.is_dir() => {
+                    // The scan covers the files inside, whatever events they still have pending
+                    self.pending.retain(|pending, _| !pending.starts_with(&path));
+                    changes.extend(audio_files_under(&path).into_iter().map(DirectoryChange::Added));
+                }
+                Ok(_) if is_audio_file(&path) => changes.push(DirectoryChange::Added(path)),
+                Ok(_) => {}
+                Err(_) => changes.push(DirectoryChange::Removed(path)),
+            }
+        }
+        // Files of a new directory that settled along with it
+        let mut seen = HashSet::new();
+        changes.retain(|change| seen.insert(change.clone()));
+        changes
+    }
+}
+
+fn file_size(path: &Path) -> Option<u64> {
+    fs::metadata(path).ok().map(|metadata| metadata.len())
+}
+
+fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(QueueManagerImpl::is_supported_format)
+}
+
+/// Audio files in `directory` and its subdirectories, sorted by path
+fn audio_files_under(directory: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut directories = vec![directory.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        let Ok(entries) = fs::read_dir(&directory) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => directories.push(path),
+                Ok(file_type) if file_type.is_file() && is_audio_file(&path) => files.push(path),
+                _ => {}
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Poll until some changes are reported or `timeout` passes
+    fn wait_for_changes(watcher: &mut DirectoryWatcher, timeout: Duration) -> Vec<DirectoryChange> {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            let changes = watcher.poll();
+            if !changes.is_empty() {
+                return changes;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        Vec::new()
+    }
+
+    #[test]
+    fn test_reports_settled_audio_files_and_removals() {
+        let dir = TempDir::new().unwrap();
+        let directory = dir.path().canonicalize().unwrap();
+        let mut watcher = DirectoryWatcher::new(Duration::from_millis(300)).unwrap();
+        assert!(watcher.add(&directory).unwrap());
+        assert!(!watcher.add(&directory).unwrap());
+
+        // A download lands under a temporary name and is renamed when complete
+        let partial = directory.join("song.flac.part");
+        fs::write(&partial, b"partial").unwrap();
+        let song = directory.join("song.flac");
+        fs::rename(&partial, &song).unwrap();
+        fs::write(directory.join("cover.jpg"), b"jpeg").unwrap();
+
+        let changes = wait_for_changes(&mut watcher, Duration::from_secs(5));
+        assert!(changes.contains(&DirectoryChange::Added(song.clone())), "{:?}", changes);
+        assert!(changes.iter().all(|change| !matches!(change, DirectoryChange::Added(path) if path != &song)), "{:?}", changes);
+
+        // An album copied in as a directory
+        let album = directory.join("album");
+        fs::create_dir(&album).unwrap();
+        fs::write(album.join("01.flac"), b"one").unwrap();
+        fs::write(album.join("02.mp3"), b"two").unwrap();
+        let changes = wait_for_changes(&mut watcher, Duration::from_secs(5));
+        assert!(changes.contains(&DirectoryChange::Added(album.join("01.flac"))), "{:?}", changes);
+        assert!(changes.contains(&DirectoryChange::Added(album.join("02.mp3"))), "{:?}", changes);
+
+        fs::remove_file(&song).unwrap();
+        assert_eq!(wait_for_changes(&mut watcher, Duration::from_secs(5)), vec![DirectoryChange::Removed(song)]);
+
+        assert!(watcher.remove(&directory));
+        assert!(!watcher.remove(&directory));
+        assert!(watcher.directories().is_empty());
+    }
+
+    #[test]
+    fn test_growing_file_waits_until_its_size_settles() {
+        let dir = TempDir::new().unwrap();
+        let directory = dir.path().canonicalize().unwrap();
+        let mut watcher = DirectoryWatcher::new(Duration::from_millis(300)).unwrap();
+        watcher.add(&directory).unwrap();
+
+        let song = directory.join("song.wav");
+        fs::write(&song, vec![0; 100]).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(watcher.poll().is_empty());
+        // Writing more restarts the wait
+        fs::write(&song, vec![0; 200]).unwrap();
+        std::thread::sleep(Duration::from_millis(250));
+        assert!(watcher.poll().is_empty());
+
+        assert_eq!(wait_for_changes(&mut watcher, Duration::from_secs(5)), vec![DirectoryChange::Added(song)]);
+    }
+}